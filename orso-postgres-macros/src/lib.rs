@@ -16,8 +16,63 @@ pub fn orso_table(_args: TokenStream, input: TokenStream) -> TokenStream {
     input
 }
 
+// orso_hooks attribute (passthrough - makes the derive forward lifecycle hooks to OrsoHooks)
+#[proc_macro_attribute]
+pub fn orso_hooks(_args: TokenStream, input: TokenStream) -> TokenStream {
+    input
+}
+
+// orso_audited attribute (passthrough - makes the derive enable the audit log)
+#[proc_macro_attribute]
+pub fn orso_audited(_args: TokenStream, input: TokenStream) -> TokenStream {
+    input
+}
+
+// orso_default_scope attribute (passthrough - makes the derive use the model's DefaultScope impl)
+#[proc_macro_attribute]
+pub fn orso_default_scope(_args: TokenStream, input: TokenStream) -> TokenStream {
+    input
+}
+
+// orso_join_table attribute (passthrough - makes the derive add association methods)
+#[proc_macro_attribute]
+pub fn orso_join_table(_args: TokenStream, input: TokenStream) -> TokenStream {
+    input
+}
+
+// orso_shard_key attribute (passthrough - names the field a ShardRouter routes records by)
+#[proc_macro_attribute]
+pub fn orso_shard_key(_args: TokenStream, input: TokenStream) -> TokenStream {
+    input
+}
+
+// orso_index attribute (passthrough - declares an index the migrations create)
+#[proc_macro_attribute]
+pub fn orso_index(_args: TokenStream, input: TokenStream) -> TokenStream {
+    input
+}
+
+// non_transactional attribute (passthrough - makes migrations run the model outside a transaction)
+#[proc_macro_attribute]
+pub fn non_transactional(_args: TokenStream, input: TokenStream) -> TokenStream {
+    input
+}
+
 // Derive macro for Orso trait
-#[proc_macro_derive(Orso, attributes(orso_table, orso_column))]
+#[proc_macro_derive(
+    Orso,
+    attributes(
+        orso_table,
+        orso_column,
+        orso_hooks,
+        orso_audited,
+        orso_default_scope,
+        orso_join_table,
+        orso_shard_key,
+        orso_index,
+        non_transactional
+    )
+)]
 pub fn derive_orso(input: TokenStream) -> TokenStream {
     let input = parse_macro_input!(input as DeriveInput);
     let name = input.ident;
@@ -26,10 +81,187 @@ pub fn derive_orso(input: TokenStream) -> TokenStream {
     let table_name =
         extract_orso_table_name(&input.attrs).unwrap_or_else(|| name.to_string().to_lowercase());
 
-    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    // Generic models need the Orso supertraits to hold for every instantiation; bounding the
+    // struct type itself leaves the type parameters as free as the serde derives made them
+    let mut generics = input.generics.clone();
+    if !generics.params.is_empty() {
+        let (_, ty_generics, _) = input.generics.split_for_impl();
+        generics
+            .make_where_clause()
+            .predicates
+            .push(syn::parse_quote! {
+                #name #ty_generics: orso_postgres::Serialize
+                    + for<'de> orso_postgres::Deserialize<'de>
+                    + Send
+                    + Sync
+                    + Clone
+            });
+    }
+    let (impl_generics, ty_generics, where_clause) = generics.split_for_impl();
+
+    let connection_impl = match extract_orso_table_connection(&input.attrs) {
+        Some(connection) => quote! {
+            fn connection() -> Option<&'static str> {
+                Some(#connection)
+            }
+        },
+        None => quote! {},
+    };
+
+    let table_comment_impl = match extract_orso_table_option(&input.attrs, "comment") {
+        Some(comment) => quote! {
+            fn table_comment() -> Option<&'static str> {
+                Some(#comment)
+            }
+        },
+        None => quote! {},
+    };
+
+    let audited_impl = if input
+        .attrs
+        .iter()
+        .any(|attr| attr.path().is_ident("orso_audited"))
+    {
+        quote! {
+            fn audited() -> bool {
+                true
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    let default_scope_impl = if input
+        .attrs
+        .iter()
+        .any(|attr| attr.path().is_ident("orso_default_scope"))
+    {
+        quote! {
+            fn default_scope() -> Option<orso_postgres::FilterOperator> {
+                <Self as orso_postgres::DefaultScope>::default_scope()
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // Forward lifecycle hooks to the model's OrsoHooks impl when requested
+    let hooks_impl = if input
+        .attrs
+        .iter()
+        .any(|attr| attr.path().is_ident("orso_hooks"))
+    {
+        quote! {
+            async fn before_insert(&mut self, db: &impl orso_postgres::Executor) -> orso_postgres::Result<()> {
+                <Self as orso_postgres::OrsoHooks>::before_insert(self, db).await
+            }
+            async fn after_insert(&self, db: &impl orso_postgres::Executor) -> orso_postgres::Result<()> {
+                <Self as orso_postgres::OrsoHooks>::after_insert(self, db).await
+            }
+            async fn before_update(&mut self, db: &impl orso_postgres::Executor) -> orso_postgres::Result<()> {
+                <Self as orso_postgres::OrsoHooks>::before_update(self, db).await
+            }
+            async fn after_update(&self, db: &impl orso_postgres::Executor) -> orso_postgres::Result<()> {
+                <Self as orso_postgres::OrsoHooks>::after_update(self, db).await
+            }
+            async fn before_delete(&self, db: &impl orso_postgres::Executor) -> orso_postgres::Result<()> {
+                <Self as orso_postgres::OrsoHooks>::before_delete(self, db).await
+            }
+            async fn after_delete(&self, db: &impl orso_postgres::Executor) -> orso_postgres::Result<()> {
+                <Self as orso_postgres::OrsoHooks>::after_delete(self, db).await
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // Many-to-many methods on the models linked by a join table, which links each pair once
+    let (join_table_impl, join_index) = match input
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("orso_join_table"))
+    {
+        Some(attr) => match join_table_methods(&name, attr) {
+            Ok((tokens, index)) => (tokens, Some(index)),
+            Err(err) => return err.to_compile_error().into(),
+        },
+        None => (quote! {}, None),
+    };
+
+    let non_transactional = input
+        .attrs
+        .iter()
+        .any(|attr| attr.path().is_ident("non_transactional"));
+    let transactional_impl = if non_transactional {
+        quote! {
+            fn transactional() -> bool {
+                false
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // Indexes declared with #[orso_index(...)]
+    let index_attrs: Vec<&Attribute> = input
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("orso_index"))
+        .collect();
+    let indexes_impl = if index_attrs.is_empty() && join_index.is_none() {
+        quote! {}
+    } else {
+        let mut indexes = match index_attrs
+            .into_iter()
+            .map(|attr| index_def(attr, &input.data, non_transactional))
+            .collect::<syn::Result<Vec<_>>>()
+        {
+            Ok(indexes) => indexes,
+            Err(err) => return err.to_compile_error().into(),
+        };
+        indexes.extend(join_index);
+        quote! {
+            fn indexes() -> Vec<orso_postgres::IndexDef> {
+                vec![#(#indexes),*]
+            }
+        }
+    };
+
+    // Name of the field records are routed to shards by
+    let shard_key_impl = match input
+        .attrs
+        .iter()
+        .find(|attr| attr.path().is_ident("orso_shard_key"))
+    {
+        Some(attr) => {
+            let field = match attr.parse_args::<syn::Ident>() {
+                Ok(field) => field,
+                Err(err) => return err.to_compile_error().into(),
+            };
+            let exists = match &input.data {
+                Data::Struct(data) => data.fields.iter().any(|f| f.ident.as_ref() == Some(&field)),
+                _ => false,
+            };
+            if !exists {
+                return syn::Error::new_spanned(
+                    &field,
+                    format!("no field named `{field}` to shard by"),
+                )
+                .to_compile_error()
+                .into();
+            }
+            let field = field.to_string();
+            quote! {
+                fn shard_key_field() -> Option<&'static str> {
+                    Some(#field)
+                }
+            }
+        }
+        None => quote! {},
+    };
 
     // Extract field metadata
-    let (
+    let FieldMetadata {
         field_names,
         column_definitions,
         field_types,
@@ -37,38 +269,44 @@ pub fn derive_orso(input: TokenStream) -> TokenStream {
         primary_key_field,
         created_at_field,
         updated_at_field,
+        created_by_field,
+        updated_by_field,
         unique_fields,
-        compressed_fields, // New compression flags
-    ) = if let Data::Struct(data) = &input.data {
+        case_insensitive_fields,
+        deferrable_fields,
+        column_comments,
+        type_overrides,
+        compressed_fields,
+        compressed_elements,
+        compression_precisions,
+        lazy_fields,
+        encrypted_fields,
+        masked_fields,
+        updated_at_trigger,
+        created_at_seek,
+        unix_ms_fields,
+        validation_checks,
+        phantom_fields,
+        references,
+        flattened,
+        custom_fields,
+        errors,
+    } = if let Data::Struct(data) = &input.data {
         if let Fields::Named(fields) = &data.fields {
             extract_field_metadata_original(&fields.named)
         } else {
-            (
-                vec![],
-                vec![],
-                vec![],
-                vec![],
-                None,
-                None,
-                None,
-                vec![],
-                vec![],
-            )
+            FieldMetadata::default()
         }
     } else {
-        (
-            vec![],
-            vec![],
-            vec![],
-            vec![],
-            None,
-            None,
-            None,
-            vec![],
-            vec![],
-        )
+        FieldMetadata::default()
     };
 
+    // Report malformed attributes instead of silently ignoring them
+    if !errors.is_empty() {
+        let errors = errors.iter().map(syn::Error::to_compile_error);
+        return TokenStream::from(quote! { #(#errors)* });
+    }
+
     // Generate dynamic getters based on actual fields found
     let primary_key_getter = if let Some(ref pk_field) = primary_key_field {
         quote! {
@@ -92,19 +330,23 @@ pub fn derive_orso(input: TokenStream) -> TokenStream {
     };
 
     let created_at_getter = if let Some(ref ca_field) = created_at_field {
-        quote! { self.#ca_field }
+        quote! { orso_postgres::TimestampField::to_timestamp(&self.#ca_field) }
     } else {
         quote! { None }
     };
 
     let updated_at_getter = if let Some(ref ua_field) = updated_at_field {
-        quote! { self.#ua_field }
+        quote! { orso_postgres::TimestampField::to_timestamp(&self.#ua_field) }
     } else {
         quote! { None }
     };
 
     let updated_at_setter = if let Some(ref ua_field) = updated_at_field {
-        quote! { self.#ua_field = Some(updated_at); }
+        quote! {
+            if let Some(value) = orso_postgres::TimestampField::from_timestamp(Some(updated_at)) {
+                self.#ua_field = value;
+            }
+        }
     } else {
         quote! { /* No updated_at field found */ }
     };
@@ -128,18 +370,260 @@ pub fn derive_orso(input: TokenStream) -> TokenStream {
         quote! { None }
     };
 
+    let created_by_field_name = match created_by_field {
+        Some(ref field) => quote! { Some(stringify!(#field)) },
+        None => quote! { None },
+    };
+
+    let updated_by_field_name = match updated_by_field {
+        Some(ref field) => quote! { Some(stringify!(#field)) },
+        None => quote! { None },
+    };
+
+    // A reference to the model's own table makes it a tree
+    let parent_field_impl = match references.iter().find(|(_, table)| *table == table_name) {
+        Some((field, _)) => quote! {
+            fn parent_field() -> Option<&'static str> {
+                Some(stringify!(#field))
+            }
+        },
+        None => quote! {},
+    };
+
     // Generate unique fields list
     let unique_field_names: Vec<proc_macro2::TokenStream> = unique_fields
         .iter()
         .map(|field| quote! { stringify!(#field) })
         .collect();
 
+    let deferrable_impl = if deferrable_fields.is_empty() {
+        quote! {}
+    } else {
+        let (columns, initially_deferred): (Vec<_>, Vec<_>) = deferrable_fields.into_iter().unzip();
+        quote! {
+            fn deferrable_fields() -> Vec<(&'static str, bool)> {
+                vec![#((stringify!(#columns), #initially_deferred)),*]
+            }
+        }
+    };
+
+    let column_comment_impl = if column_comments.is_empty() {
+        quote! {}
+    } else {
+        let (columns, comments): (Vec<_>, Vec<_>) = column_comments.into_iter().unzip();
+        quote! {
+            fn column_comments() -> Vec<(&'static str, &'static str)> {
+                vec![#((stringify!(#columns), #comments)),*]
+            }
+        }
+    };
+
+    let type_override_impl = if type_overrides.is_empty() {
+        quote! {}
+    } else {
+        let (columns, (sql_types, rust_types)): (Vec<_>, (Vec<_>, Vec<_>)) = type_overrides
+            .into_iter()
+            .map(|(column, sql_type, rust_type)| (column, (sql_type, rust_type)))
+            .unzip();
+        quote! {
+            fn type_overrides() -> Vec<orso_postgres::TypeOverride> {
+                vec![#(orso_postgres::TypeOverride {
+                    column: stringify!(#columns),
+                    sql_type: #sql_types,
+                    rust_type: std::any::type_name::<#rust_types>(),
+                }),*]
+            }
+        }
+    };
+
+    let case_insensitive_field_names: Vec<proc_macro2::TokenStream> = case_insensitive_fields
+        .iter()
+        .map(|field| quote! { stringify!(#field) })
+        .collect();
+
+    // Generate validate() only when fields declare rules
+    let validate_impl = if validation_checks.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            fn validate(&self) -> orso_postgres::Result<()> {
+                let mut errors: Vec<orso_postgres::FieldError> = Vec::new();
+                #(#validation_checks)*
+                orso_postgres::validation::finish(errors)
+            }
+        }
+    };
+
+    // Generate lossy compression precision list
+    let compression_precision_tokens: Vec<proc_macro2::TokenStream> = compression_precisions
+        .iter()
+        .map(|precision| match precision {
+            Some(p) => quote! { Some(#p) },
+            None => quote! { None },
+        })
+        .collect();
+
+    // Generate lazy fields list
+    let lazy_field_names: Vec<proc_macro2::TokenStream> = lazy_fields
+        .iter()
+        .map(|field| quote! { stringify!(#field) })
+        .collect();
+
+    // Generate encrypted fields list, encrypted before the rest of the record is converted
+    let encrypted_field_names: Vec<proc_macro2::TokenStream> = encrypted_fields
+        .iter()
+        .map(|field| quote! { stringify!(#field) })
+        .collect();
+    let encrypt_fields = if encrypted_fields.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            let mut map = map;
+            #(
+                if let Some(value) = map.remove(#encrypted_field_names) {
+                    result.insert(
+                        #encrypted_field_names.to_string(),
+                        orso_postgres::encryption::encrypt_json(&value)?,
+                    );
+                }
+            )*
+        }
+    };
+
+    let updated_at_trigger_impl = if updated_at_trigger {
+        quote! {
+            fn updated_at_trigger() -> bool {
+                true
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    let unix_ms_impl = if unix_ms_fields.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            fn unix_ms_fields() -> Vec<&'static str> {
+                vec![#(stringify!(#unix_ms_fields)),*]
+            }
+        }
+    };
+
+    let seek_index_impl = if created_at_seek {
+        quote! {
+            fn seek_index() -> bool {
+                true
+            }
+        }
+    } else {
+        quote! {}
+    };
+
+    // Generate masked fields list
+    let masked_field_names: Vec<proc_macro2::TokenStream> = masked_fields
+        .iter()
+        .map(|field| quote! { stringify!(#field) })
+        .collect();
+
     // Generate compressed fields list
     let compressed_field_flags: Vec<proc_macro2::TokenStream> = compressed_fields
         .iter()
         .map(|&is_compressed| quote! { #is_compressed })
         .collect();
 
+    // PhantomData fields have no column
+    let remove_phantom_fields = if phantom_fields.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            let mut map = map;
+            #(map.remove(#phantom_fields);)*
+        }
+    };
+
+    // #[orso_column(custom)] fields are converted through their OrsoType
+    let custom_idents: Vec<&syn::Ident> = custom_fields.iter().map(|(field, _)| field).collect();
+    let custom_types: Vec<&syn::Type> = custom_fields.iter().map(|(_, ty)| ty).collect();
+
+    // Fields of embedded structs are spliced into the column lists in field order
+    let flatten_fields: Vec<&(syn::Ident, syn::Type)> = flattened.iter().flatten().collect();
+    let splice =
+        |items: &[proc_macro2::TokenStream],
+         embedded: &dyn Fn(&syn::Ident, &syn::Type) -> proc_macro2::TokenStream| {
+            if flatten_fields.is_empty() {
+                return quote! { vec![#(#items),*] };
+            }
+            let parts = items.iter().zip(&flattened).map(|(item, flat)| match flat {
+                Some((field, ty)) => {
+                    let embedded = embedded(field, ty);
+                    quote! { items.extend(#embedded); }
+                }
+                None => quote! { items.push(#item); },
+            });
+            quote! {
+                {
+                    let mut items = Vec::new();
+                    #(#parts)*
+                    items
+                }
+            }
+        };
+    let nullable_tokens: Vec<proc_macro2::TokenStream> =
+        nullable_flags.iter().map(|flag| quote! { #flag }).collect();
+
+    let field_names_body = splice(&field_names, &|field, ty| {
+        quote! {
+            orso_postgres::embed::prefixed_names(
+                stringify!(#field),
+                <#ty as orso_postgres::OrsoEmbed>::field_names(),
+            )
+        }
+    });
+    let field_types_body = splice(&field_types, &|_, ty| {
+        quote! { <#ty as orso_postgres::OrsoEmbed>::field_types() }
+    });
+    let field_nullable_body = splice(&nullable_tokens, &|_, ty| {
+        quote! { <#ty as orso_postgres::OrsoEmbed>::field_nullable() }
+    });
+    let field_compressed_body = splice(&compressed_field_flags, &|_, ty| {
+        quote! { vec![false; <#ty as orso_postgres::OrsoEmbed>::field_names().len()] }
+    });
+    let compression_precision_body = splice(&compression_precision_tokens, &|_, ty| {
+        quote! { vec![None; <#ty as orso_postgres::OrsoEmbed>::field_names().len()] }
+    });
+    let compressed_elements_body = splice(&compressed_elements, &|_, ty| {
+        quote! { vec![None; <#ty as orso_postgres::OrsoEmbed>::field_names().len()] }
+    });
+    let column_definitions_body = splice(&column_definitions, &|field, ty| {
+        quote! {
+            <#ty as orso_postgres::OrsoEmbed>::column_definitions()
+                .into_iter()
+                .map(|column| format!("{}_{}", stringify!(#field), column))
+        }
+    });
+
+    let flatten_idents: Vec<&syn::Ident> = flatten_fields.iter().map(|(field, _)| field).collect();
+    let flatten_types: Vec<&syn::Type> = flatten_fields.iter().map(|(_, ty)| ty).collect();
+    let flatten_embedded = quote! {
+        #(
+            let map = orso_postgres::embed::flatten(
+                map,
+                stringify!(#flatten_idents),
+                &<#flatten_types as orso_postgres::OrsoEmbed>::field_names(),
+            );
+        )*
+    };
+    let nest_embedded = quote! {
+        #(
+            orso_postgres::embed::nest(
+                &mut json_map,
+                stringify!(#flatten_idents),
+                &<#flatten_types as orso_postgres::OrsoEmbed>::field_names(),
+            );
+        )*
+    };
+
     // Generate only the trait implementation
     let expanded = quote! {
         impl #impl_generics orso_postgres::Orso for #name #ty_generics #where_clause {
@@ -159,10 +643,46 @@ pub fn derive_orso(input: TokenStream) -> TokenStream {
                 #updated_at_field_name
             }
 
+            fn created_by_field() -> Option<&'static str> {
+                #created_by_field_name
+            }
+
+            fn updated_by_field() -> Option<&'static str> {
+                #updated_by_field_name
+            }
+
             fn unique_fields() -> Vec<&'static str> {
                 vec![#(#unique_field_names),*]
             }
 
+            fn case_insensitive_unique_fields() -> Vec<&'static str> {
+                vec![#(#case_insensitive_field_names),*]
+            }
+
+            fn lazy_fields() -> Vec<&'static str> {
+                vec![#(#lazy_field_names),*]
+            }
+
+            fn encrypted_fields() -> Vec<&'static str> {
+                vec![#(#encrypted_field_names),*]
+            }
+
+            fn masked_fields() -> Vec<&'static str> {
+                vec![#(#masked_field_names),*]
+            }
+
+            #updated_at_trigger_impl
+            #unix_ms_impl
+
+            #seek_index_impl
+
+            #column_comment_impl
+            #type_override_impl
+
+            #deferrable_impl
+
+            #parent_field_impl
+
             fn get_primary_key(&self) -> Option<String> {
                 #primary_key_getter
             }
@@ -184,28 +704,49 @@ pub fn derive_orso(input: TokenStream) -> TokenStream {
             }
 
             fn field_names() -> Vec<&'static str> {
-                vec![#(#field_names),*]
+                #field_names_body
             }
 
             fn field_types() -> Vec<orso_postgres::FieldType> {
-                vec![#(#field_types),*]
+                #field_types_body
             }
 
             fn field_nullable() -> Vec<bool> {
-                vec![#(#nullable_flags),*]
+                #field_nullable_body
             }
 
             fn field_compressed() -> Vec<bool> {
-                vec![#(#compressed_field_flags),*]
+                #field_compressed_body
+            }
+
+            #connection_impl
+            #shard_key_impl
+            #audited_impl
+            #indexes_impl
+            #transactional_impl
+            #table_comment_impl
+
+            #default_scope_impl
+
+            #hooks_impl
+
+            #validate_impl
+
+            fn field_compression_precision() -> Vec<Option<f64>> {
+                #compression_precision_body
+            }
+
+            fn field_compressed_elements() -> Vec<Option<orso_postgres::FieldType>> {
+                #compressed_elements_body
             }
 
             fn columns() -> Vec<&'static str> {
-                vec![#(#field_names),*]
+                #field_names_body
             }
 
             fn migration_sql() -> String {
                 // Only generate columns for actual struct fields
-                let columns: Vec<String> = vec![#(#column_definitions),*];
+                let columns: Vec<String> = #column_definitions_body;
 
                 format!(
                     "CREATE TABLE IF NOT EXISTS {} (\n    {}\n)",
@@ -219,34 +760,33 @@ pub fn derive_orso(input: TokenStream) -> TokenStream {
                 let json = serde_json::to_value(self)?;
                 let map: std::collections::HashMap<String, serde_json::Value> =
                     serde_json::from_value(json)?;
+                #remove_phantom_fields
+                #flatten_embedded
 
                 let mut result = std::collections::HashMap::new();
+                #encrypt_fields
 
                 // Get field names for auto-generated fields
                 let pk_field = Self::primary_key_field();
                 let created_field = Self::created_at_field();
                 let updated_field = Self::updated_at_field();
+                let lazy_fields = Self::lazy_fields();
 
                 // Get compression information
                 let field_names = Self::field_names();
                 let field_types = Self::field_types();
                 let compressed_flags = Self::field_compressed();
+                let compression_precisions = Self::field_compression_precision();
 
-                // Group compressed fields by type for batch processing
-                let mut compressed_i64_fields: std::collections::HashMap<String, Vec<i64>> = std::collections::HashMap::new();
-                let mut compressed_u64_fields: std::collections::HashMap<String, Vec<u64>> = std::collections::HashMap::new();
-                let mut compressed_i32_fields: std::collections::HashMap<String, Vec<i32>> = std::collections::HashMap::new();
-                let mut compressed_u32_fields: std::collections::HashMap<String, Vec<u32>> = std::collections::HashMap::new();
-                let mut compressed_f64_fields: std::collections::HashMap<String, Vec<f64>> = std::collections::HashMap::new();
-                let mut compressed_f32_fields: std::collections::HashMap<String, Vec<f32>> = std::collections::HashMap::new();
-
-                // First pass: collect compressed fields by type
+                // First pass: compress arrays into counted frames, so range reads can skip them
                 for (k, v) in &map {
                     // Skip auto-generated fields when they are null - let PostgreSQL use DEFAULT values
+                    // Unloaded lazy fields are skipped too so the stored value is left untouched
                     let should_skip = matches!(v, serde_json::Value::Null) && (
                         *k == pk_field ||
                         (created_field.is_some() && *k == created_field.unwrap()) ||
-                        (updated_field.is_some() && *k == updated_field.unwrap())
+                        (updated_field.is_some() && *k == updated_field.unwrap()) ||
+                        lazy_fields.contains(&k.as_str())
                     );
 
                     if should_skip {
@@ -278,7 +818,10 @@ pub fn derive_orso(input: TokenStream) -> TokenStream {
                                                         val.as_f64().ok_or("Invalid f64")
                                                     }).collect();
                                                     if let Ok(vec) = f64_result {
-                                                        compressed_f64_fields.insert(k.clone(), vec);
+                                                        // Lossy fields record their precision in every frame header
+                                                        let precision = compression_precisions.get(pos).copied().flatten();
+                                                        let blob = orso_postgres::compression::encode_chunked(&vec, precision)?;
+                                                        result.insert(k.clone(), orso_postgres::Value::Blob(blob));
                                                         continue;
                                                     }
                                                 } else {
@@ -287,7 +830,8 @@ pub fn derive_orso(input: TokenStream) -> TokenStream {
                                                         val.as_i64().ok_or("Invalid i64")
                                                     }).collect();
                                                     if let Ok(vec) = i64_result {
-                                                        compressed_i64_fields.insert(k.clone(), vec);
+                                                        let blob = orso_postgres::compression::encode_chunked(&vec, None)?;
+                                                        result.insert(k.clone(), orso_postgres::Value::Blob(blob));
                                                         continue;
                                                     }
                                                 }
@@ -302,301 +846,6 @@ pub fn derive_orso(input: TokenStream) -> TokenStream {
                     }
                 }
 
-                // Batch process compressed fields by type
-                // Process i64 fields
-                if !compressed_i64_fields.is_empty() {
-                    let codec = orso_postgres::IntegerCodec::default();
-                    if compressed_i64_fields.len() == 1 {
-                        // Single field - process individually
-                        let (field_name, vec) = compressed_i64_fields.into_iter().next().unwrap();
-                        match codec.compress_i64(&vec) {
-                            Ok(compressed) => {
-                                result.insert(field_name, orso_postgres::Value::Blob(compressed));
-                            }
-                            Err(_) => {
-                                // Fallback to JSON string
-                                if let Some(original_value) = map.get(&field_name) {
-                                    result.insert(field_name, orso_postgres::Value::Text(serde_json::to_string(original_value)?));
-                                }
-                            }
-                        }
-                    } else {
-                        // Multiple fields - process in batch
-                        let field_names: Vec<String> = compressed_i64_fields.keys().cloned().collect();
-                        let arrays: Vec<Vec<i64>> = compressed_i64_fields.values().cloned().collect();
-
-                        match codec.compress_many_i64(&arrays) {
-                            Ok(compressed_blobs) => {
-                                for (field_name, blob) in field_names.into_iter().zip(compressed_blobs.into_iter()) {
-                                    result.insert(field_name, orso_postgres::Value::Blob(blob));
-                                }
-                            }
-                            Err(_) => {
-                                // Fallback to individual compression
-                                for (field_name, vec) in compressed_i64_fields {
-                                    match codec.compress_i64(&vec) {
-                                        Ok(compressed) => {
-                                            result.insert(field_name, orso_postgres::Value::Blob(compressed));
-                                        }
-                                        Err(_) => {
-                                            // Ultimate fallback to JSON string
-                                            if let Some(original_value) = map.get(&field_name) {
-                                                result.insert(field_name, orso_postgres::Value::Text(serde_json::to_string(original_value)?));
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-
-                // Process u64 fields
-                if !compressed_u64_fields.is_empty() {
-                    let codec = orso_postgres::IntegerCodec::default();
-                    if compressed_u64_fields.len() == 1 {
-                        // Single field - process individually
-                        let (field_name, vec) = compressed_u64_fields.into_iter().next().unwrap();
-                        match codec.compress_u64(&vec) {
-                            Ok(compressed) => {
-                                result.insert(field_name, orso_postgres::Value::Blob(compressed));
-                            }
-                            Err(_) => {
-                                // Fallback to JSON string
-                                if let Some(original_value) = map.get(&field_name) {
-                                    result.insert(field_name, orso_postgres::Value::Text(serde_json::to_string(original_value)?));
-                                }
-                            }
-                        }
-                    } else {
-                        // Multiple fields - process in batch
-                        let field_names: Vec<String> = compressed_u64_fields.keys().cloned().collect();
-                        let arrays: Vec<Vec<u64>> = compressed_u64_fields.values().cloned().collect();
-
-                        match codec.compress_many_u64(&arrays) {
-                            Ok(compressed_blobs) => {
-                                for (field_name, blob) in field_names.into_iter().zip(compressed_blobs.into_iter()) {
-                                    result.insert(field_name, orso_postgres::Value::Blob(blob));
-                                }
-                            }
-                            Err(_) => {
-                                // Fallback to individual compression
-                                for (field_name, vec) in compressed_u64_fields {
-                                    match codec.compress_u64(&vec) {
-                                        Ok(compressed) => {
-                                            result.insert(field_name, orso_postgres::Value::Blob(compressed));
-                                        }
-                                        Err(_) => {
-                                            // Ultimate fallback to JSON string
-                                            if let Some(original_value) = map.get(&field_name) {
-                                                result.insert(field_name, orso_postgres::Value::Text(serde_json::to_string(original_value)?));
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-
-                // Process i32 fields (compress as i64 for storage efficiency)
-                if !compressed_i32_fields.is_empty() {
-                    let codec = orso_postgres::IntegerCodec::default();
-                    if compressed_i32_fields.len() == 1 {
-                        // Single field - process individually
-                        let (field_name, vec) = compressed_i32_fields.into_iter().next().unwrap();
-                        let i64_vec: Vec<i64> = vec.into_iter().map(|x| x as i64).collect();
-                        match codec.compress_i64(&i64_vec) {
-                            Ok(compressed) => {
-                                result.insert(field_name, orso_postgres::Value::Blob(compressed));
-                            }
-                            Err(_) => {
-                                // Fallback to JSON string
-                                if let Some(original_value) = map.get(&field_name) {
-                                    result.insert(field_name, orso_postgres::Value::Text(serde_json::to_string(original_value)?));
-                                }
-                            }
-                        }
-                    } else {
-                        // Multiple fields - process in batch
-                        let field_names: Vec<String> = compressed_i32_fields.keys().cloned().collect();
-                        let arrays: Vec<Vec<i64>> = compressed_i32_fields.values().map(|vec| vec.iter().map(|&x| x as i64).collect()).collect();
-
-                        match codec.compress_many_i64(&arrays) {
-                            Ok(compressed_blobs) => {
-                                for (field_name, blob) in field_names.into_iter().zip(compressed_blobs.into_iter()) {
-                                    result.insert(field_name, orso_postgres::Value::Blob(blob));
-                                }
-                            }
-                            Err(_) => {
-                                // Fallback to individual compression
-                                for (field_name, vec) in compressed_i32_fields {
-                                    let i64_vec: Vec<i64> = vec.into_iter().map(|x| x as i64).collect();
-                                    match codec.compress_i64(&i64_vec) {
-                                        Ok(compressed) => {
-                                            result.insert(field_name, orso_postgres::Value::Blob(compressed));
-                                        }
-                                        Err(_) => {
-                                            // Ultimate fallback to JSON string
-                                            if let Some(original_value) = map.get(&field_name) {
-                                                result.insert(field_name, orso_postgres::Value::Text(serde_json::to_string(original_value)?));
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-
-                // Process u32 fields (compress as u64 for storage efficiency)
-                if !compressed_u32_fields.is_empty() {
-                    let codec = orso_postgres::IntegerCodec::default();
-                    if compressed_u32_fields.len() == 1 {
-                        // Single field - process individually
-                        let (field_name, vec) = compressed_u32_fields.into_iter().next().unwrap();
-                        let u64_vec: Vec<u64> = vec.into_iter().map(|x| x as u64).collect();
-                        match codec.compress_u64(&u64_vec) {
-                            Ok(compressed) => {
-                                result.insert(field_name, orso_postgres::Value::Blob(compressed));
-                            }
-                            Err(_) => {
-                                // Fallback to JSON string
-                                if let Some(original_value) = map.get(&field_name) {
-                                    result.insert(field_name, orso_postgres::Value::Text(serde_json::to_string(original_value)?));
-                                }
-                            }
-                        }
-                    } else {
-                        // Multiple fields - process in batch
-                        let field_names: Vec<String> = compressed_u32_fields.keys().cloned().collect();
-                        let arrays: Vec<Vec<u64>> = compressed_u32_fields.values().map(|vec| vec.iter().map(|&x| x as u64).collect()).collect();
-
-                        match codec.compress_many_u64(&arrays) {
-                            Ok(compressed_blobs) => {
-                                for (field_name, blob) in field_names.into_iter().zip(compressed_blobs.into_iter()) {
-                                    result.insert(field_name, orso_postgres::Value::Blob(blob));
-                                }
-                            }
-                            Err(_) => {
-                                // Fallback to individual compression
-                                for (field_name, vec) in compressed_u32_fields {
-                                    let u64_vec: Vec<u64> = vec.into_iter().map(|x| x as u64).collect();
-                                    match codec.compress_u64(&u64_vec) {
-                                        Ok(compressed) => {
-                                            result.insert(field_name, orso_postgres::Value::Blob(compressed));
-                                        }
-                                        Err(_) => {
-                                            // Ultimate fallback to JSON string
-                                            if let Some(original_value) = map.get(&field_name) {
-                                                result.insert(field_name, orso_postgres::Value::Text(serde_json::to_string(original_value)?));
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-
-                // Process f64 fields
-                if !compressed_f64_fields.is_empty() {
-                    let codec = orso_postgres::FloatingCodec::default();
-                    if compressed_f64_fields.len() == 1 {
-                        // Single field - process individually
-                        let (field_name, vec) = compressed_f64_fields.into_iter().next().unwrap();
-                        match codec.compress_f64(&vec, None) {
-                            Ok(compressed) => {
-                                result.insert(field_name, orso_postgres::Value::Blob(compressed));
-                            }
-                            Err(e) => {
-                                // DEBUG: Print compression error
-                                eprintln!("F64 compression failed for field {}: {:?}", field_name, e);
-                                // Fallback to JSON string
-                                if let Some(original_value) = map.get(&field_name) {
-                                    result.insert(field_name, orso_postgres::Value::Text(serde_json::to_string(original_value)?));
-                                }
-                            }
-                        }
-                    } else {
-                        // Multiple fields - process in batch
-                        let field_names: Vec<String> = compressed_f64_fields.keys().cloned().collect();
-                        let arrays: Vec<Vec<f64>> = compressed_f64_fields.values().cloned().collect();
-
-                        match codec.compress_many_f64(&arrays, None) {
-                            Ok(compressed_blobs) => {
-                                for (field_name, blob) in field_names.into_iter().zip(compressed_blobs.into_iter()) {
-                                    result.insert(field_name, orso_postgres::Value::Blob(blob));
-                                }
-                            }
-                            Err(_) => {
-                                // Fallback to individual compression
-                                for (field_name, vec) in compressed_f64_fields {
-                                    match codec.compress_f64(&vec, None) {
-                                        Ok(compressed) => {
-                                            result.insert(field_name, orso_postgres::Value::Blob(compressed));
-                                        }
-                                        Err(_) => {
-                                            // Ultimate fallback to JSON string
-                                            if let Some(original_value) = map.get(&field_name) {
-                                                result.insert(field_name, orso_postgres::Value::Text(serde_json::to_string(original_value)?));
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-
-                // Process f32 fields
-                if !compressed_f32_fields.is_empty() {
-                    let codec = orso_postgres::FloatingCodec::default();
-                    if compressed_f32_fields.len() == 1 {
-                        // Single field - process individually
-                        let (field_name, vec) = compressed_f32_fields.into_iter().next().unwrap();
-                        match codec.compress_f32(&vec, None) {
-                            Ok(compressed) => {
-                                result.insert(field_name, orso_postgres::Value::Blob(compressed));
-                            }
-                            Err(_) => {
-                                // Fallback to JSON string
-                                if let Some(original_value) = map.get(&field_name) {
-                                    result.insert(field_name, orso_postgres::Value::Text(serde_json::to_string(original_value)?));
-                                }
-                            }
-                        }
-                    } else {
-                        // Multiple fields - process in batch
-                        let field_names: Vec<String> = compressed_f32_fields.keys().cloned().collect();
-                        let arrays: Vec<Vec<f32>> = compressed_f32_fields.values().cloned().collect();
-
-                        match codec.compress_many_f32(&arrays, None) {
-                            Ok(compressed_blobs) => {
-                                for (field_name, blob) in field_names.into_iter().zip(compressed_blobs.into_iter()) {
-                                    result.insert(field_name, orso_postgres::Value::Blob(blob));
-                                }
-                            }
-                            Err(_) => {
-                                // Fallback to individual compression
-                                for (field_name, vec) in compressed_f32_fields {
-                                    match codec.compress_f32(&vec, None) {
-                                        Ok(compressed) => {
-                                            result.insert(field_name, orso_postgres::Value::Blob(compressed));
-                                        }
-                                        Err(_) => {
-                                            // Ultimate fallback to JSON string
-                                            if let Some(original_value) = map.get(&field_name) {
-                                                result.insert(field_name, orso_postgres::Value::Text(serde_json::to_string(original_value)?));
-                                            }
-                                        }
-                                    }
-                                }
-                            }
-                        }
-                    }
-                }
-
                 // Second pass: process non-compressed fields and any fields that fell through
                 for (k, v) in map {
                     // Skip fields that were already processed as compressed
@@ -608,18 +857,30 @@ pub fn derive_orso(input: TokenStream) -> TokenStream {
                     let should_skip = matches!(v, serde_json::Value::Null) && (
                         k == pk_field ||
                         (created_field.is_some() && k == created_field.unwrap()) ||
-                        (updated_field.is_some() && k == updated_field.unwrap())
+                        (updated_field.is_some() && k == updated_field.unwrap()) ||
+                        lazy_fields.contains(&k.as_str())
                     );
 
                     if should_skip {
                         continue;
                     }
 
+                    // JSONB columns take the serialized value as-is
+                    let is_json = field_names.iter().position(|&name| name == k)
+                        .is_some_and(|pos| matches!(field_types.get(pos), Some(orso_postgres::FieldType::JsonB)));
+
                     let value = match v {
                         serde_json::Value::Null => orso_postgres::Value::Null,
+                        v if is_json => orso_postgres::Value::Json(v),
                         serde_json::Value::Bool(b) => orso_postgres::Value::Boolean(b),
                         serde_json::Value::Number(n) => {
-                            if let Some(i) = n.as_i64() {
+                            let is_timestamp = field_names.iter().position(|&name| name == k)
+                                .is_some_and(|pos| matches!(field_types.get(pos), Some(orso_postgres::FieldType::Timestamp)));
+                            let millis = n.as_i64().filter(|_| is_timestamp);
+                            // Timestamps serialized as Unix milliseconds, see `timestamp_format`
+                            if let Some(dt) = millis.and_then(orso_postgres::chrono::DateTime::from_timestamp_millis) {
+                                orso_postgres::Value::DateTime(orso_postgres::OrsoDateTime::new(dt))
+                            } else if let Some(i) = n.as_i64() {
                                 orso_postgres::Value::Integer(i)
                             } else if let Some(f) = n.as_f64() {
                                 orso_postgres::Value::Real(f)
@@ -734,12 +995,32 @@ pub fn derive_orso(input: TokenStream) -> TokenStream {
                     result.insert(k, value);
                 }
 
+                #(
+                    result.insert(
+                        stringify!(#custom_idents).to_string(),
+                        orso_postgres::OrsoType::to_value(&self.#custom_idents),
+                    );
+                )*
+
                 Ok(result)
             }
 
+            fn decode_columns(map: &mut std::collections::HashMap<String, orso_postgres::Value>) -> orso_postgres::Result<()> {
+                #(orso_postgres::encryption::decrypt_field(map, #encrypted_field_names)?;)*
+                #(
+                    orso_postgres::custom::decode::<#custom_types>(
+                        map,
+                        stringify!(#custom_idents),
+                    )?;
+                )*
+                #(orso_postgres::context::mask_field(map, #masked_field_names);)*
+                Ok(())
+            }
+
             fn from_map(mut map: std::collections::HashMap<String, orso_postgres::Value>) -> orso_postgres::Result<Self> {
                 use serde_json;
                 let mut json_map = serde_json::Map::new();
+                Self::decode_columns(&mut map)?;
 
                 // Get field metadata for type-aware conversion
                 let field_names = Self::field_names();
@@ -777,6 +1058,20 @@ pub fn derive_orso(input: TokenStream) -> TokenStream {
                                         }
                                     }
                                 }
+                                // Framed blobs (appended columns) are decoded frame by frame
+                                else if orso_postgres::compression::is_framed(blob) {
+                                    match orso_postgres::compression::decode_framed_json(blob) {
+                                        Ok(json_array) => {
+                                            json_map.insert(k.clone(), json_array);
+                                        }
+                                        Err(e) => {
+                                            let error_msg = format!("Failed to decompress framed blob for field {}: {}", k, e);
+                                            let value = orso_postgres::decode::failed_field(Self::table_name(), k, error_msg)?;
+                                            json_map.insert(k.clone(), value);
+                                        }
+                                    }
+                                    continue;
+                                }
                                 // Check blob header to determine the correct type
                                 else if blob.len() >= 7 && &blob[0..4] == b"ORSO" {
                                     match blob[6] {
@@ -879,6 +1174,7 @@ pub fn derive_orso(input: TokenStream) -> TokenStream {
                                             Err(_) => serde_json::Value::Null
                                         }
                                     }
+                                    orso_postgres::Value::Json(v) => v.clone(),
                                 };
                                 json_map.insert(k.clone(), json_value);
                             }
@@ -903,8 +1199,9 @@ pub fn derive_orso(input: TokenStream) -> TokenStream {
                             }
                             Err(e) => {
                                 // If decompression fails, return the raw data as a string
-                                let error_msg = format!("Failed to decompress: {:?}", blob);
-                                json_map.insert(field_name, serde_json::Value::String(error_msg));
+                                let error_msg = format!("Failed to decompress: {}", e);
+                                let value = orso_postgres::decode::failed_field(Self::table_name(), &field_name, error_msg)?;
+                                json_map.insert(field_name, value);
                             }
                         }
                     } else {
@@ -936,7 +1233,8 @@ pub fn derive_orso(input: TokenStream) -> TokenStream {
                                         Err(_) => {
                                             // Ultimate fallback to raw blob data as string
                                             let error_msg = format!("Failed to decompress blob for field: {}", field_name);
-                                            json_map.insert(field_name, serde_json::Value::String(error_msg));
+                                            let value = orso_postgres::decode::failed_field(Self::table_name(), &field_name, error_msg)?;
+                                            json_map.insert(field_name, value);
                                         }
                                     }
                                 }
@@ -961,8 +1259,9 @@ pub fn derive_orso(input: TokenStream) -> TokenStream {
                             }
                             Err(e) => {
                                 // If decompression fails, return the raw data as a string
-                                let error_msg = format!("Failed to decompress: {:?}", blob);
-                                json_map.insert(field_name, serde_json::Value::String(error_msg));
+                                let error_msg = format!("Failed to decompress: {}", e);
+                                let value = orso_postgres::decode::failed_field(Self::table_name(), &field_name, error_msg)?;
+                                json_map.insert(field_name, value);
                             }
                         }
                     } else {
@@ -994,7 +1293,8 @@ pub fn derive_orso(input: TokenStream) -> TokenStream {
                                         Err(_) => {
                                             // Ultimate fallback to raw blob data as string
                                             let error_msg = format!("Failed to decompress blob for field: {}", field_name);
-                                            json_map.insert(field_name, serde_json::Value::String(error_msg));
+                                            let value = orso_postgres::decode::failed_field(Self::table_name(), &field_name, error_msg)?;
+                                            json_map.insert(field_name, value);
                                         }
                                     }
                                 }
@@ -1020,8 +1320,9 @@ pub fn derive_orso(input: TokenStream) -> TokenStream {
                             }
                             Err(e) => {
                                 // If decompression fails, return the raw data as a string
-                                let error_msg = format!("Failed to decompress: {:?}", blob);
-                                json_map.insert(field_name, serde_json::Value::String(error_msg));
+                                let error_msg = format!("Failed to decompress: {}", e);
+                                let value = orso_postgres::decode::failed_field(Self::table_name(), &field_name, error_msg)?;
+                                json_map.insert(field_name, value);
                             }
                         }
                     } else {
@@ -1055,7 +1356,8 @@ pub fn derive_orso(input: TokenStream) -> TokenStream {
                                         Err(_) => {
                                             // Ultimate fallback to raw blob data as string
                                             let error_msg = format!("Failed to decompress blob for field: {}", field_name);
-                                            json_map.insert(field_name, serde_json::Value::String(error_msg));
+                                            let value = orso_postgres::decode::failed_field(Self::table_name(), &field_name, error_msg)?;
+                                            json_map.insert(field_name, value);
                                         }
                                     }
                                 }
@@ -1081,8 +1383,9 @@ pub fn derive_orso(input: TokenStream) -> TokenStream {
                             }
                             Err(e) => {
                                 // If decompression fails, return the raw data as a string
-                                let error_msg = format!("Failed to decompress: {:?}", blob);
-                                json_map.insert(field_name, serde_json::Value::String(error_msg));
+                                let error_msg = format!("Failed to decompress: {}", e);
+                                let value = orso_postgres::decode::failed_field(Self::table_name(), &field_name, error_msg)?;
+                                json_map.insert(field_name, value);
                             }
                         }
                     } else {
@@ -1116,7 +1419,8 @@ pub fn derive_orso(input: TokenStream) -> TokenStream {
                                         Err(_) => {
                                             // Ultimate fallback to raw blob data as string
                                             let error_msg = format!("Failed to decompress blob for field: {}", field_name);
-                                            json_map.insert(field_name, serde_json::Value::String(error_msg));
+                                            let value = orso_postgres::decode::failed_field(Self::table_name(), &field_name, error_msg)?;
+                                            json_map.insert(field_name, value);
                                         }
                                     }
                                 }
@@ -1148,7 +1452,8 @@ pub fn derive_orso(input: TokenStream) -> TokenStream {
                             Err(_) => {
                                 // If decompression fails, return the raw data as a string
                                 let error_msg = format!("Failed to decompress f64 blob for field: {}", field_name);
-                                json_map.insert(field_name, serde_json::Value::String(error_msg));
+                                let value = orso_postgres::decode::failed_field(Self::table_name(), &field_name, error_msg)?;
+                                json_map.insert(field_name, value);
                             }
                         }
                     } else {
@@ -1192,7 +1497,8 @@ pub fn derive_orso(input: TokenStream) -> TokenStream {
                                         Err(_) => {
                                             // Ultimate fallback to raw blob data as string
                                             let error_msg = format!("Failed to decompress f64 blob for field: {}", field_name);
-                                            json_map.insert(field_name, serde_json::Value::String(error_msg));
+                                            let value = orso_postgres::decode::failed_field(Self::table_name(), &field_name, error_msg)?;
+                                            json_map.insert(field_name, value);
                                         }
                                     }
                                 }
@@ -1224,7 +1530,8 @@ pub fn derive_orso(input: TokenStream) -> TokenStream {
                             Err(_) => {
                                 // If decompression fails, return the raw data as a string
                                 let error_msg = format!("Failed to decompress f32 blob for field: {}", field_name);
-                                json_map.insert(field_name, serde_json::Value::String(error_msg));
+                                let value = orso_postgres::decode::failed_field(Self::table_name(), &field_name, error_msg)?;
+                                json_map.insert(field_name, value);
                             }
                         }
                     } else {
@@ -1268,7 +1575,8 @@ pub fn derive_orso(input: TokenStream) -> TokenStream {
                                         Err(_) => {
                                             // Ultimate fallback to raw blob data as string
                                             let error_msg = format!("Failed to decompress f32 blob for field: {}", field_name);
-                                            json_map.insert(field_name, serde_json::Value::String(error_msg));
+                                            let value = orso_postgres::decode::failed_field(Self::table_name(), &field_name, error_msg)?;
+                                            json_map.insert(field_name, value);
                                         }
                                     }
                                 }
@@ -1308,14 +1616,12 @@ pub fn derive_orso(input: TokenStream) -> TokenStream {
                             }
                         }
                         orso_postgres::Value::Text(s) => {
-                            // Check if this might be a database datetime that needs conversion
-                            if s.len() == 19 && s.chars().nth(4) == Some('-') && s.chars().nth(7) == Some('-') && s.chars().nth(10) == Some(' ') {
-                                // This looks like datetime format: "2025-09-13 10:50:43"
-                                // Convert to RFC3339 format: "2025-09-13T10:50:43Z"
-                                let rfc3339_format = s.replace(' ', "T") + "Z";
-                                serde_json::Value::String(rfc3339_format)
-                            } else {
-                                serde_json::Value::String(s.clone())
+                            // Timestamps selected as text, e.g. `created_at::text`, in RFC 3339
+                            let is_timestamp = field_names.iter().position(|&name| name == *k)
+                                .is_some_and(|pos| matches!(field_types.get(pos), Some(orso_postgres::FieldType::Timestamp)));
+                            match orso_postgres::Utils::parse_timestamp(s) {
+                                Ok(dt) if is_timestamp => serde_json::Value::String(orso_postgres::Utils::create_timestamp(dt)),
+                                _ => serde_json::Value::String(s.clone()),
                             }
                         },
                         orso_postgres::Value::Blob(b) => {
@@ -1371,44 +1677,273 @@ pub fn derive_orso(input: TokenStream) -> TokenStream {
                                 Err(_) => serde_json::Value::Null
                             }
                         }
+                        orso_postgres::Value::Json(v) => v.clone(),
                     };
                     json_map.insert(k.clone(), json_value);
                 }
 
-                let json_value = serde_json::Value::Object(json_map);
+                #nest_embedded
+                #(json_map.insert(#phantom_fields.to_string(), serde_json::Value::Null);)*
+                orso_postgres::decode::from_json(Self::table_name(), json_map)
+            }
+
+
+            // Utility methods
+            fn row_to_map(row: &orso_postgres::tokio_postgres::Row) -> orso_postgres::Result<std::collections::HashMap<String, orso_postgres::Value>> {
+                let mut map = std::collections::HashMap::new();
+                for (i, column) in row.columns().iter().enumerate() {
+                    let column_name = column.name();
+                    let value = orso_postgres::Value::from_postgres_row(row, i)?;
+                    map.insert(column_name.to_string(), value);
+                }
+                Ok(map)
+            }
+
+            fn value_to_postgres_param(value: &orso_postgres::Value) -> Box<dyn orso_postgres::tokio_postgres::types::ToSql + Send + Sync> {
+                match value {
+                    orso_postgres::Value::Null => Box::new(Option::<String>::None),
+                    orso_postgres::Value::Integer(i) => Box::new(*i),
+                    orso_postgres::Value::Real(f) => Box::new(*f),
+                    orso_postgres::Value::Text(s) => Box::new(s.clone()),
+                    orso_postgres::Value::Blob(b) => Box::new(b.clone()),
+                    orso_postgres::Value::Boolean(b) => Box::new(*b),
+                    orso_postgres::Value::DateTime(dt) => Box::new(*dt),
+                    orso_postgres::Value::IntegerArray(arr) => Box::new(arr.clone()),
+                    orso_postgres::Value::BigIntArray(arr) => Box::new(arr.clone()),
+                    orso_postgres::Value::NumericArray(arr) => Box::new(arr.clone()),
+                    orso_postgres::Value::Vector(v) => Box::new(v.clone()),
+                    orso_postgres::Value::Json(v) => Box::new(v.clone()),
+                }
+            }
+        }
+    };
+
+    // OpenAPI schema from the columns, embedded structs as nested objects
+    #[cfg(feature = "openapi")]
+    let openapi_impl = {
+        let embedded = flattened
+            .iter()
+            .flatten()
+            .map(|(field, _)| field.to_string());
+        quote! {
+            impl #impl_generics orso_postgres::utoipa::PartialSchema for #name #ty_generics
+            #where_clause
+            {
+                fn schema() -> orso_postgres::utoipa::openapi::RefOr<
+                    orso_postgres::utoipa::openapi::schema::Schema,
+                > {
+                    orso_postgres::openapi::model_schema::<Self>(&[#(#embedded),*])
+                }
+            }
+
+            impl #impl_generics orso_postgres::utoipa::ToSchema for #name #ty_generics
+            #where_clause
+            {
+                fn name() -> std::borrow::Cow<'static, str> {
+                    std::borrow::Cow::Borrowed(stringify!(#name))
+                }
+            }
+        }
+    };
+    #[cfg(not(feature = "openapi"))]
+    let openapi_impl = quote! {};
+
+    // `Model::col()`: a constant per column, so misspelt column names fail to compile;
+    // `Model::filter()`: a typed column per field, so mistyped values fail to compile too.
+    // The filter type is only named as `<Model as Filterable>::Filter`, so it can't clash
+    let vis = &input.vis;
+    let columns_name = syn::Ident::new(&format!("{name}Columns"), name.span());
+    let (column_idents, column_types): (Vec<&syn::Ident>, Vec<&syn::Type>) = match &input.data {
+        Data::Struct(data) => data
+            .fields
+            .iter()
+            .filter(|f| !is_phantom_data(&f.ty))
+            .filter(|f| !has_column_flag(f, "flatten") && !has_column_flag(f, "ref_poly"))
+            .filter_map(|f| Some((f.ident.as_ref()?, &f.ty)))
+            .unzip(),
+        _ => (Vec::new(), Vec::new()),
+    };
+    let (model_generics, model_ty_generics, model_where_clause) = input.generics.split_for_impl();
+    let columns_doc = format!("Column names of [`{name}`], returned by `{name}::col()`");
+    let filter_doc = format!("Typed filters on `{name}`, returned by `{name}::filter()`");
+    let columns_impl = quote! {
+        #[doc = #columns_doc]
+        #[allow(dead_code)]
+        #[derive(Debug, Clone, Copy)]
+        #vis struct #columns_name {
+            #(pub #column_idents: orso_postgres::Column,)*
+        }
+
+        impl #impl_generics #name #ty_generics #where_clause {
+            /// Column names of this model, for filters and sorts checked at compile time
+            #[allow(dead_code)]
+            pub const fn col() -> #columns_name {
+                #columns_name {
+                    #(#column_idents: orso_postgres::Column::new(stringify!(#column_idents)),)*
+                }
+            }
+        }
+
+        const _: () = {
+            #[doc = #filter_doc]
+            #[allow(dead_code)]
+            pub struct __OrsoFilter #model_generics #model_where_clause {
+                _model: std::marker::PhantomData<fn() -> #name #model_ty_generics>,
+            }
+
+            #[allow(dead_code)]
+            impl #model_generics __OrsoFilter #model_ty_generics #model_where_clause {
+                #(
+                    pub fn #column_idents(
+                        &self,
+                    ) -> orso_postgres::TypedColumn<#name #model_ty_generics, #column_types> {
+                        orso_postgres::TypedColumn::new(
+                            orso_postgres::Column::new(stringify!(#column_idents)),
+                        )
+                    }
+                )*
+            }
+
+            impl #impl_generics orso_postgres::Filterable for #name #ty_generics #where_clause {
+                type Filter = __OrsoFilter #ty_generics;
 
-                match serde_json::from_value(json_value) {
-                    Ok(result) => Ok(result),
-                    Err(e) => Err(orso_postgres::Error::serialization(e.to_string()))
+                fn filter() -> Self::Filter {
+                    __OrsoFilter {
+                        _model: std::marker::PhantomData,
+                    }
                 }
             }
+        };
+    };
 
+    TokenStream::from(quote! {
+        #expanded
+        #join_table_impl
+        #openapi_impl
+        #columns_impl
+    })
+}
 
-            // Utility methods
-            fn row_to_map(row: &orso_postgres::tokio_postgres::Row) -> orso_postgres::Result<std::collections::HashMap<String, orso_postgres::Value>> {
-                let mut map = std::collections::HashMap::new();
-                for (i, column) in row.columns().iter().enumerate() {
-                    let column_name = column.name();
-                    let value = orso_postgres::Value::from_postgres_row(row, i)?;
-                    map.insert(column_name.to_string(), value);
-                }
-                Ok(map)
+// Derive macro for structs stored flattened into a model's table
+#[proc_macro_derive(OrsoEmbed, attributes(orso_column))]
+pub fn derive_orso_embed(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return syn::Error::new_spanned(name, "OrsoEmbed requires named fields")
+                    .to_compile_error()
+                    .into()
             }
+        },
+        _ => {
+            return syn::Error::new_spanned(name, "OrsoEmbed can only be derived for structs")
+                .to_compile_error()
+                .into()
+        }
+    };
 
-            fn value_to_postgres_param(value: &orso_postgres::Value) -> Box<dyn orso_postgres::tokio_postgres::types::ToSql + Send + Sync> {
-                match value {
-                    orso_postgres::Value::Null => Box::new(Option::<String>::None),
-                    orso_postgres::Value::Integer(i) => Box::new(*i),
-                    orso_postgres::Value::Real(f) => Box::new(*f),
-                    orso_postgres::Value::Text(s) => Box::new(s.clone()),
-                    orso_postgres::Value::Blob(b) => Box::new(b.clone()),
-                    orso_postgres::Value::Boolean(b) => Box::new(*b),
-                    orso_postgres::Value::DateTime(dt) => Box::new(std::time::SystemTime::from(*dt)),
-                    orso_postgres::Value::IntegerArray(arr) => Box::new(arr.clone()),
-                    orso_postgres::Value::BigIntArray(arr) => Box::new(arr.clone()),
-                    orso_postgres::Value::NumericArray(arr) => Box::new(arr.clone()),
-                    orso_postgres::Value::Vector(v) => Box::new(v.clone()),
-                }
+    let field_names: Vec<&syn::Ident> = fields.iter().filter_map(|f| f.ident.as_ref()).collect();
+    let field_types: Vec<proc_macro2::TokenStream> = fields
+        .iter()
+        .map(|field| map_field_type(&field.ty, field, false))
+        .collect();
+    let nullable_flags: Vec<bool> = fields.iter().map(|f| is_option_type(&f.ty)).collect();
+    let column_definitions: Vec<proc_macro2::TokenStream> =
+        fields.iter().map(column_definition_tokens).collect();
+
+    let expanded = quote! {
+        impl #impl_generics orso_postgres::OrsoEmbed for #name #ty_generics #where_clause {
+            fn field_names() -> Vec<&'static str> {
+                vec![#(stringify!(#field_names)),*]
+            }
+
+            fn field_types() -> Vec<orso_postgres::FieldType> {
+                vec![#(#field_types),*]
+            }
+
+            fn field_nullable() -> Vec<bool> {
+                vec![#(#nullable_flags),*]
+            }
+
+            fn column_definitions() -> Vec<String> {
+                vec![#(#column_definitions),*]
+            }
+        }
+    };
+
+    TokenStream::from(expanded)
+}
+
+// Derive macro for OrsoRow: field metadata and from_map only, for query results
+#[proc_macro_derive(OrsoRow, attributes(orso_column))]
+pub fn derive_orso_row(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => &fields.named,
+            _ => {
+                return syn::Error::new_spanned(name, "OrsoRow requires named fields")
+                    .to_compile_error()
+                    .into()
+            }
+        },
+        _ => {
+            return syn::Error::new_spanned(name, "OrsoRow can only be derived for structs")
+                .to_compile_error()
+                .into()
+        }
+    };
+
+    let field_names: Vec<&syn::Ident> = fields.iter().filter_map(|f| f.ident.as_ref()).collect();
+    let field_types: Vec<proc_macro2::TokenStream> = fields
+        .iter()
+        .map(|field| map_field_type(&field.ty, field, false))
+        .collect();
+    let nullable_flags: Vec<bool> = fields.iter().map(|f| is_option_type(&f.ty)).collect();
+    let (custom_idents, custom_types): (Vec<&syn::Ident>, Vec<&syn::Type>) = fields
+        .iter()
+        .filter(|field| has_column_flag(field, "custom"))
+        .filter_map(|field| Some((field.ident.as_ref()?, &field.ty)))
+        .unzip();
+    let decode_custom = if custom_idents.is_empty() {
+        quote! {}
+    } else {
+        quote! {
+            let mut map = map;
+            #(
+                orso_postgres::custom::decode::<#custom_types>(
+                    &mut map,
+                    stringify!(#custom_idents),
+                )?;
+            )*
+        }
+    };
+
+    let expanded = quote! {
+        impl #impl_generics orso_postgres::OrsoRow for #name #ty_generics #where_clause {
+            fn field_names() -> Vec<&'static str> {
+                vec![#(stringify!(#field_names)),*]
+            }
+
+            fn field_types() -> Vec<orso_postgres::FieldType> {
+                vec![#(#field_types),*]
+            }
+
+            fn field_nullable() -> Vec<bool> {
+                vec![#(#nullable_flags),*]
+            }
+
+            fn from_map(map: orso_postgres::RowMap) -> orso_postgres::Result<Self> {
+                #decode_custom
+                orso_postgres::row::from_row_map(map, &Self::field_names(), &Self::field_types())
             }
         }
     };
@@ -1416,6 +1951,17 @@ pub fn derive_orso(input: TokenStream) -> TokenStream {
     TokenStream::from(expanded)
 }
 
+// Column definition expression; custom fields take their SQL type from OrsoType at runtime
+fn column_definition_tokens(field: &syn::Field) -> proc_macro2::TokenStream {
+    let column_def = parse_field_column_definition(field);
+    if has_column_flag(field, "custom") {
+        let ty = &field.ty;
+        quote! { #column_def.replace("{custom}", <#ty as orso_postgres::OrsoType>::sql_type()) }
+    } else {
+        quote! { #column_def.to_string() }
+    }
+}
+
 // Parse field-level column definition with inline REFERENCES for maximum Turso compatibility
 fn parse_field_column_definition(field: &syn::Field) -> String {
     let field_name = field.ident.as_ref().unwrap().to_string();
@@ -1440,13 +1986,21 @@ fn parse_orso_column_attr(
     let mut column_type = None;
     let mut is_foreign_key = false;
     let mut foreign_table = None;
+    let mut foreign_column = None;
     let mut unique = false;
     let mut primary_key = false;
     let mut is_compressed = false;
+    let mut is_lazy = false;
+    let mut is_jsonb = false;
+    let mut is_custom = false;
+    let mut is_encrypted = false;
     let mut vector_dimensions: Option<u32> = None;
 
     let mut is_created_at = false;
     let mut is_updated_at = false;
+    let mut unix_ms = false;
+    let mut deferrable = false;
+    let mut initially_deferred = false;
 
     let _ = attr.parse_nested_meta(|meta| {
         if meta.path.is_ident("ref") {
@@ -1454,9 +2008,14 @@ fn parse_orso_column_attr(
             if let Ok(value) = meta.value() {
                 let lit: Lit = value.parse()?;
                 if let Lit::Str(lit_str) = lit {
-                    foreign_table = Some(lit_str.value());
+                    let (table, column) = parse_reference(&lit_str.value());
+                    foreign_table = Some(table);
+                    foreign_column = foreign_column.take().or(column);
                 }
             }
+        } else if meta.path.is_ident("ref_column") {
+            let column: syn::LitStr = meta.value()?.parse()?;
+            foreign_column = Some(column.value());
         } else if meta.path.is_ident("type") {
             if let Ok(value) = meta.value() {
                 let lit: Lit = value.parse()?;
@@ -1465,15 +2024,36 @@ fn parse_orso_column_attr(
                 }
             }
         } else if meta.path.is_ident("unique") {
-            unique = true;
+            // Case-insensitive uniqueness is a `lower()` index created by the migration
+            unique = !parse_unique_case_insensitive(&meta)?;
         } else if meta.path.is_ident("primary_key") {
             primary_key = true;
         } else if meta.path.is_ident("created_at") {
             is_created_at = true;
         } else if meta.path.is_ident("updated_at") {
             is_updated_at = true;
+        } else if meta.path.is_ident("as") {
+            let format: syn::LitStr = meta.value()?.parse()?;
+            unix_ms = format.value() == "unix_ms";
         } else if meta.path.is_ident("compress") {
             is_compressed = true;
+            parse_compress_precision(&meta)?;
+        } else if meta.path.is_ident("lazy") {
+            is_lazy = true;
+        } else if meta.path.is_ident("jsonb") {
+            is_jsonb = true;
+        } else if meta.path.is_ident("custom") {
+            is_custom = true;
+        } else if meta.path.is_ident("encrypted") {
+            is_encrypted = true;
+        } else if meta.path.is_ident("validate") {
+            parse_validate_rules(&meta, None)?;
+        } else if meta.path.is_ident("comment") {
+            let _: syn::LitStr = meta.value()?.parse()?;
+        } else if meta.path.is_ident("deferrable") {
+            deferrable = true;
+        } else if meta.path.is_ident("initially_deferred") {
+            initially_deferred = true;
         } else if meta.path.is_ident("vector") {
             // Parse vector(N) attribute
             if meta.input.peek(syn::token::Paren) {
@@ -1490,13 +2070,17 @@ fn parse_orso_column_attr(
     });
 
     // Generate column definition
-    // For compressed fields, we always use BYTEA type (PostgreSQL binary data)
-    let base_type = if is_compressed {
+    // For compressed and encrypted fields, we always use BYTEA type (PostgreSQL binary data)
+    let base_type = if is_compressed || is_encrypted {
         "BYTEA".to_string()
     } else if let Some(dimensions) = vector_dimensions {
         format!("vector({})", dimensions) // PostgreSQL pgvector type
-    } else if is_foreign_key {
-        "TEXT".to_string() // Foreign keys are always TEXT (UUID)
+    } else if is_jsonb {
+        "JSONB".to_string()
+    } else if is_custom {
+        "{custom}".to_string() // Filled in from OrsoType::sql_type by column_definition_tokens
+    } else if is_foreign_key && column_type.is_none() && foreign_column.is_none() {
+        "TEXT".to_string() // Foreign keys to `id` are TEXT (UUID)
     } else {
         column_type.unwrap_or_else(|| map_rust_type_to_sql_type(field_type, is_compressed))
     };
@@ -1511,18 +2095,29 @@ fn parse_orso_column_attr(
         }
     }
     // Add NOT NULL for non-Option types (except primary keys which are already handled)
-    if !is_option_type(field_type) && !primary_key {
+    // Lazy columns stay nullable since an unloaded value is never written
+    if !is_option_type(field_type) && !primary_key && !is_lazy {
         column_def.push_str(" NOT NULL");
     }
+    let deferral = match (deferrable, initially_deferred) {
+        (_, true) => " DEFERRABLE INITIALLY DEFERRED",
+        (true, false) => " DEFERRABLE",
+        (false, false) => "",
+    };
     if unique {
         column_def.push_str(" UNIQUE");
+        column_def.push_str(deferral);
     }
     if let Some(ref_table) = foreign_table {
-        column_def.push_str(&format!(" REFERENCES {}(id)", ref_table));
+        let ref_column = foreign_column.as_deref().unwrap_or("id");
+        column_def.push_str(&format!(" REFERENCES {}({})", ref_table, ref_column));
+        column_def.push_str(deferral);
     }
 
     // Add defaults for timestamp columns
-    if is_created_at || is_updated_at {
+    if unix_ms && (is_created_at || is_updated_at) {
+        column_def.push_str(" DEFAULT (extract(epoch FROM NOW()) * 1000)::bigint");
+    } else if is_created_at || is_updated_at {
         column_def.push_str(" DEFAULT NOW()"); // PostgreSQL timestamp generation
     }
 
@@ -1572,7 +2167,7 @@ fn map_rust_type_to_sql_type(rust_type: &syn::Type, is_compressed: bool) -> Stri
                 "u32" | "u16" | "u8" => "INTEGER".to_string(),
                 "f64" | "f32" => "DOUBLE PRECISION".to_string(), // PostgreSQL DOUBLE PRECISION
                 "bool" => "BOOLEAN".to_string(),                 // PostgreSQL native BOOLEAN type
-                "DateTime" => "TIMESTAMP WITHOUT TIME ZONE".to_string(), // UTC timestamp without timezone
+                "DateTime" | "OrsoDateTime" => "TIMESTAMP WITH TIME ZONE".to_string(), // Any chrono time zone
                 "Option" => {
                     // Handle Option<T> types
                     if let syn::PathArguments::AngleBracketed(args) = &segment.arguments {
@@ -1590,8 +2185,8 @@ fn map_rust_type_to_sql_type(rust_type: &syn::Type, is_compressed: bool) -> Stri
     // Handle full path types like chrono::DateTime<chrono::Utc>
     if let syn::Type::Path(type_path) = rust_type {
         let path_str = quote::quote!(#type_path).to_string();
-        if path_str.contains("DateTime") && path_str.contains("Utc") {
-            return "TIMESTAMP WITHOUT TIME ZONE".to_string();
+        if path_str.contains("DateTime") {
+            return "TIMESTAMP WITH TIME ZONE".to_string();
         }
     }
 
@@ -1632,18 +2227,69 @@ fn map_vec_to_array_field_type(inner_type: &syn::Type) -> proc_macro2::TokenStre
     quote! { orso_postgres::FieldType::Text } // Fallback
 }
 
+// Element type of a compressed `Vec<T>`, also inside `Option` or `Lazy`, `None` for other fields
+fn compressed_element_type(rust_type: &syn::Type, is_compressed: bool) -> proc_macro2::TokenStream {
+    let syn::Type::Path(type_path) = rust_type else {
+        return quote! { None };
+    };
+    let Some(segment) = type_path.path.segments.last() else {
+        return quote! { None };
+    };
+    let inner = match &segment.arguments {
+        syn::PathArguments::AngleBracketed(args) => match args.args.first() {
+            Some(syn::GenericArgument::Type(inner)) => inner,
+            _ => return quote! { None },
+        },
+        _ => return quote! { None },
+    };
+    if segment.ident == "Option" || segment.ident == "Lazy" {
+        return compressed_element_type(inner, is_compressed);
+    }
+    if !is_compressed || segment.ident != "Vec" {
+        return quote! { None };
+    }
+    let syn::Type::Path(inner_path) = inner else {
+        return quote! { None };
+    };
+    let name = inner_path.path.segments.last().map(|s| s.ident.to_string());
+    let element = match name.as_deref() {
+        Some("i64" | "u64") => quote! { orso_postgres::FieldType::BigInt },
+        Some("i32" | "i16" | "i8" | "u32" | "u16" | "u8") => {
+            quote! { orso_postgres::FieldType::Integer }
+        }
+        Some("f64" | "f32") => quote! { orso_postgres::FieldType::Numeric },
+        _ => return quote! { None },
+    };
+    quote! { Some(#element) }
+}
+
 // Map field types to FieldType enum
 fn map_field_type(
     rust_type: &syn::Type,
     field: &syn::Field,
     is_compressed: bool,
 ) -> proc_macro2::TokenStream {
+    if has_column_flag(field, "jsonb") {
+        return quote! { orso_postgres::FieldType::JsonB };
+    }
+    if has_column_flag(field, "custom") {
+        return quote! {
+            orso_postgres::FieldType::Custom(<#rust_type as orso_postgres::OrsoType>::sql_type())
+        };
+    }
+
     // First check for vector attribute
     for attr in &field.attrs {
         if attr.path().is_ident("orso_column") {
             let mut vector_dimensions: Option<u32> = None;
             let _ = attr.parse_nested_meta(|meta| {
-                if meta.path.is_ident("vector") {
+                if meta.path.is_ident("compress") {
+                    parse_compress_precision(&meta)?;
+                } else if meta.path.is_ident("validate") {
+                    parse_validate_rules(&meta, None)?;
+                } else if meta.path.is_ident("comment") {
+                    let _: syn::LitStr = meta.value()?.parse()?;
+                } else if meta.path.is_ident("vector") {
                     if meta.input.peek(syn::token::Paren) {
                         let content;
                         syn::parenthesized!(content in meta.input);
@@ -1689,7 +2335,7 @@ fn map_field_type(
                 "u32" | "u16" | "u8" => quote! { orso_postgres::FieldType::Integer },
                 "f64" | "f32" => quote! { orso_postgres::FieldType::Numeric },
                 "bool" => quote! { orso_postgres::FieldType::Boolean },
-                "DateTime" => quote! { orso_postgres::FieldType::Timestamp },
+                "DateTime" | "OrsoDateTime" => quote! { orso_postgres::FieldType::Timestamp },
                 "Timestamp" => quote! { orso_postgres::FieldType::Timestamp },
                 "Option" => {
                     // Handle Option<T> types - get the inner type
@@ -1708,7 +2354,7 @@ fn map_field_type(
     // Handle full path types like chrono::DateTime<chrono::Utc>
     if let syn::Type::Path(type_path) = rust_type {
         let path_str = quote::quote!(#type_path).to_string();
-        if path_str.contains("DateTime") && path_str.contains("Utc") {
+        if path_str.contains("DateTime") {
             return quote! { orso_postgres::FieldType::Timestamp };
         }
     }
@@ -1716,6 +2362,150 @@ fn map_field_type(
     quote! { orso_postgres::FieldType::Text }
 }
 
+// Parse the optional arguments of `compress(precision = 1e-6)`
+fn parse_compress_precision(meta: &syn::meta::ParseNestedMeta) -> syn::Result<Option<f64>> {
+    let mut precision = None;
+    if meta.input.peek(syn::token::Paren) {
+        meta.parse_nested_meta(|inner| {
+            if inner.path.is_ident("precision") {
+                precision = match inner.value()?.parse::<Lit>()? {
+                    Lit::Float(lit) => Some(lit.base10_parse::<f64>()?),
+                    Lit::Int(lit) => Some(lit.base10_parse::<f64>()?),
+                    lit => return Err(syn::Error::new(lit.span(), "expected a numeric precision")),
+                };
+            }
+            Ok(())
+        })?;
+    }
+    Ok(precision)
+}
+
+// Split `ref = "users(email)"` into the table and the referenced column
+fn parse_reference(reference: &str) -> (String, Option<String>) {
+    match reference
+        .trim()
+        .strip_suffix(')')
+        .and_then(|r| r.split_once('('))
+    {
+        Some((table, column)) => (table.trim().to_string(), Some(column.trim().to_string())),
+        None => (reference.trim().to_string(), None),
+    }
+}
+
+// Parse the optional `(case_insensitive)` of `unique`
+fn parse_unique_case_insensitive(meta: &syn::meta::ParseNestedMeta) -> syn::Result<bool> {
+    let mut case_insensitive = false;
+    if meta.input.peek(syn::token::Paren) {
+        meta.parse_nested_meta(|inner| {
+            if inner.path.is_ident("case_insensitive") {
+                case_insensitive = true;
+                Ok(())
+            } else {
+                Err(inner.error("expected `case_insensitive`"))
+            }
+        })?;
+    }
+    Ok(case_insensitive)
+}
+
+// Parse `validate(length(min = 1, max = 255), range(min = 0), email, url)` into checks on
+// `self.<field>`, pushing failures into a local `errors` vector
+// (`field` is `None` when the caller only needs the attribute consumed)
+fn parse_validate_rules(
+    meta: &syn::meta::ParseNestedMeta,
+    field: Option<&syn::Ident>,
+) -> syn::Result<Vec<proc_macro2::TokenStream>> {
+    let name = field
+        .map(|f| f.to_string().trim_start_matches("r#").to_string())
+        .unwrap_or_default();
+    let mut checks = Vec::new();
+
+    meta.parse_nested_meta(|rule| {
+        if rule.path.is_ident("length") {
+            let (min, max) = parse_validate_bounds(&rule)?;
+            let min = option_tokens(min.map(|v| v as usize));
+            let max = option_tokens(max.map(|v| v as usize));
+            if let Some(field) = field {
+                checks.push(quote! {
+                    orso_postgres::validation::length(#name, &self.#field, #min, #max, &mut errors);
+                });
+            }
+        } else if rule.path.is_ident("range") {
+            let (min, max) = parse_validate_bounds(&rule)?;
+            let min = option_tokens(min);
+            let max = option_tokens(max);
+            if let Some(field) = field {
+                checks.push(quote! {
+                    orso_postgres::validation::range(#name, &self.#field, #min, #max, &mut errors);
+                });
+            }
+        } else if rule.path.is_ident("email") {
+            if let Some(field) = field {
+                checks.push(quote! {
+                    orso_postgres::validation::email(#name, &self.#field, &mut errors);
+                });
+            }
+        } else if rule.path.is_ident("url") {
+            if let Some(field) = field {
+                checks.push(quote! {
+                    orso_postgres::validation::url(#name, &self.#field, &mut errors);
+                });
+            }
+        } else {
+            return Err(rule.error("unknown validation rule, expected length, range, email or url"));
+        }
+        Ok(())
+    })?;
+
+    Ok(checks)
+}
+
+// Parse the `min = ..`/`max = ..` arguments of a validation rule
+fn parse_validate_bounds(
+    rule: &syn::meta::ParseNestedMeta,
+) -> syn::Result<(Option<f64>, Option<f64>)> {
+    let mut min = None;
+    let mut max = None;
+    rule.parse_nested_meta(|bound| {
+        let value = parse_number(&bound.value()?.parse::<syn::Expr>()?)?;
+        if bound.path.is_ident("min") {
+            min = Some(value);
+        } else if bound.path.is_ident("max") {
+            max = Some(value);
+        } else {
+            return Err(bound.error("expected `min` or `max`"));
+        }
+        Ok(())
+    })?;
+    Ok((min, max))
+}
+
+// Evaluate a numeric literal, optionally negated
+fn parse_number(expr: &syn::Expr) -> syn::Result<f64> {
+    match expr {
+        syn::Expr::Lit(syn::ExprLit {
+            lit: Lit::Int(lit), ..
+        }) => lit.base10_parse::<f64>(),
+        syn::Expr::Lit(syn::ExprLit {
+            lit: Lit::Float(lit),
+            ..
+        }) => lit.base10_parse::<f64>(),
+        syn::Expr::Unary(syn::ExprUnary {
+            op: syn::UnOp::Neg(_),
+            expr,
+            ..
+        }) => parse_number(expr).map(|v| -v),
+        _ => Err(syn::Error::new_spanned(expr, "expected a number")),
+    }
+}
+
+fn option_tokens<T: quote::ToTokens>(value: Option<T>) -> proc_macro2::TokenStream {
+    match value {
+        Some(v) => quote! { Some(#v) },
+        None => quote! { None },
+    }
+}
+
 // Check if a type is Option<T>
 fn is_option_type(rust_type: &syn::Type) -> bool {
     if let syn::Type::Path(type_path) = rust_type {
@@ -1726,55 +2516,167 @@ fn is_option_type(rust_type: &syn::Type) -> bool {
     false
 }
 
+// Metadata collected from all struct fields
+#[derive(Default)]
+struct FieldMetadata {
+    field_names: Vec<proc_macro2::TokenStream>,
+    column_definitions: Vec<proc_macro2::TokenStream>,
+    field_types: Vec<proc_macro2::TokenStream>,
+    nullable_flags: Vec<bool>,
+    primary_key_field: Option<proc_macro2::Ident>,
+    created_at_field: Option<proc_macro2::Ident>,
+    updated_at_field: Option<proc_macro2::Ident>,
+    created_by_field: Option<proc_macro2::Ident>,
+    updated_by_field: Option<proc_macro2::Ident>,
+    unique_fields: Vec<proc_macro2::Ident>,
+    /// `#[orso_column(unique(case_insensitive))]` fields, also in `unique_fields`
+    case_insensitive_fields: Vec<proc_macro2::Ident>,
+    /// `#[orso_column(unique, deferrable)]` fields, and whether they are `initially_deferred`
+    deferrable_fields: Vec<(proc_macro2::Ident, bool)>,
+    /// `#[orso_column(comment = "...")]` by field
+    column_comments: Vec<(proc_macro2::Ident, String)>,
+    /// `#[orso_column(type = "...")]` by field, with the field's type
+    type_overrides: Vec<(proc_macro2::Ident, String, syn::Type)>,
+    compressed_fields: Vec<bool>, // Compression flags
+    /// Element type of compressed `Vec` fields
+    compressed_elements: Vec<proc_macro2::TokenStream>,
+    compression_precisions: Vec<Option<f64>>,
+    lazy_fields: Vec<proc_macro2::Ident>,
+    encrypted_fields: Vec<proc_macro2::Ident>,
+    masked_fields: Vec<proc_macro2::Ident>,
+    /// `#[orso_column(updated_at, trigger)]`: a database trigger maintains `updated_at`
+    updated_at_trigger: bool,
+    /// `#[orso_column(created_at, seek)]`: migrations index `(created_at, primary key)`
+    created_at_seek: bool,
+    /// `#[orso_column(created_at, as = "unix_ms")]` timestamps stored as `i64` milliseconds
+    unix_ms_fields: Vec<proc_macro2::Ident>,
+    validation_checks: Vec<proc_macro2::TokenStream>,
+    phantom_fields: Vec<String>,
+    /// `ref = "table"` targets by field, for references to `id`
+    references: Vec<(proc_macro2::Ident, String)>,
+    /// `#[orso_column(flatten)]` fields, parallel to `field_names`
+    flattened: Vec<Option<(syn::Ident, syn::Type)>>,
+    /// `#[orso_column(custom)]` fields and their types
+    custom_fields: Vec<(syn::Ident, syn::Type)>,
+    errors: Vec<syn::Error>,
+}
+
 // Extract field metadata from all struct fields
-fn extract_field_metadata_original(
-    fields: &Punctuated<syn::Field, Comma>,
-) -> (
-    Vec<proc_macro2::TokenStream>,
-    Vec<proc_macro2::TokenStream>,
-    Vec<proc_macro2::TokenStream>,
-    Vec<bool>,
-    Option<proc_macro2::Ident>,
-    Option<proc_macro2::Ident>,
-    Option<proc_macro2::Ident>,
-    Vec<proc_macro2::Ident>,
-    Vec<bool>, // Compression flags
-) {
-    let mut field_names = Vec::new();
-    let mut column_defs = Vec::new();
-    let mut field_types = Vec::new();
-    let mut nullable_flags = Vec::new();
-    let mut primary_key_field: Option<proc_macro2::Ident> = None;
-    let mut created_at_field: Option<proc_macro2::Ident> = None;
-    let mut updated_at_field: Option<proc_macro2::Ident> = None;
-    let mut unique_fields = Vec::new();
-    let mut compressed_fields = Vec::new(); // New vector for compression flags
+fn extract_field_metadata_original(fields: &Punctuated<syn::Field, Comma>) -> FieldMetadata {
+    let mut metadata = FieldMetadata::default();
 
     for field in fields {
         if let Some(field_name) = &field.ident {
+            // PhantomData markers of generic models carry no data and get no column
+            if is_phantom_data(&field.ty) {
+                metadata.phantom_fields.push(field_name.to_string());
+                continue;
+            }
+
+            // Embedded structs contribute their own columns, filled in at expansion; a polymorphic
+            // reference is a PolyRef embedded as its `_type`/`_id` pair
+            if has_column_flag(field, "flatten") || has_column_flag(field, "ref_poly") {
+                metadata
+                    .field_names
+                    .push(quote! { stringify!(#field_name) });
+                metadata.column_definitions.push(quote! {});
+                metadata.field_types.push(quote! {});
+                metadata.nullable_flags.push(false);
+                metadata.compressed_fields.push(false);
+                metadata.compressed_elements.push(quote! { None });
+                metadata.compression_precisions.push(None);
+                metadata
+                    .flattened
+                    .push(Some((field_name.clone(), field.ty.clone())));
+                continue;
+            }
+            metadata.flattened.push(None);
+
             // Check for special attributes
-            let mut is_primary_key = false;
-            let mut is_created_at = false;
-            let mut is_updated_at = false;
             let mut is_unique = false;
+            let mut is_case_insensitive = false;
+            let mut reference_table: Option<String> = None;
+            let mut deferral: Option<(syn::Path, bool)> = None;
+            let mut referenced_column: Option<String> = None;
             let mut is_compressed = false; // Track compression
+            let mut compression_precision = None;
+            let mut is_lazy = false;
+            let mut is_encrypted = false;
+            let mut is_masked = false;
+            let mut is_updated_at = false;
+            let mut trigger: Option<syn::Path> = None;
+            let mut is_created_at = false;
+            let mut seek: Option<syn::Path> = None;
+            let mut format: Option<syn::LitStr> = None;
+            let mut column_type: Option<syn::LitStr> = None;
 
             for attr in &field.attrs {
                 if attr.path().is_ident("orso_column") {
                     let _ = attr.parse_nested_meta(|meta| {
                         if meta.path.is_ident("primary_key") {
-                            is_primary_key = true;
-                            primary_key_field = Some(field_name.clone());
+                            metadata.primary_key_field = Some(field_name.clone());
                         } else if meta.path.is_ident("created_at") {
+                            metadata.created_at_field = Some(field_name.clone());
                             is_created_at = true;
-                            created_at_field = Some(field_name.clone());
+                        } else if meta.path.is_ident("seek") {
+                            seek = Some(meta.path.clone());
+                        } else if meta.path.is_ident("deferrable") {
+                            let initially = deferral.as_ref().is_some_and(|(_, i)| *i);
+                            deferral = Some((meta.path.clone(), initially));
+                        } else if meta.path.is_ident("initially_deferred") {
+                            deferral = Some((meta.path.clone(), true));
+                        } else if meta.path.is_ident("comment") {
+                            let comment: syn::LitStr = meta.value()?.parse()?;
+                            metadata
+                                .column_comments
+                                .push((field_name.clone(), comment.value()));
+                        } else if meta.path.is_ident("type") {
+                            column_type = Some(meta.value()?.parse()?);
                         } else if meta.path.is_ident("updated_at") {
+                            metadata.updated_at_field = Some(field_name.clone());
                             is_updated_at = true;
-                            updated_at_field = Some(field_name.clone());
+                        } else if meta.path.is_ident("trigger") {
+                            trigger = Some(meta.path.clone());
+                        } else if meta.path.is_ident("as") {
+                            format = Some(meta.value()?.parse()?);
+                        } else if meta.path.is_ident("created_by") {
+                            metadata.created_by_field = Some(field_name.clone());
+                        } else if meta.path.is_ident("updated_by") {
+                            metadata.updated_by_field = Some(field_name.clone());
                         } else if meta.path.is_ident("unique") {
                             is_unique = true;
+                            match parse_unique_case_insensitive(&meta) {
+                                Ok(case_insensitive) => is_case_insensitive = case_insensitive,
+                                Err(err) => {
+                                    metadata.errors.push(err.clone());
+                                    return Err(err);
+                                }
+                            }
+                        } else if meta.path.is_ident("ref") {
+                            let reference: syn::LitStr = meta.value()?.parse()?;
+                            let (table, column) = parse_reference(&reference.value());
+                            referenced_column = referenced_column.take().or(column);
+                            reference_table = Some(table);
+                        } else if meta.path.is_ident("ref_column") {
+                            let column: syn::LitStr = meta.value()?.parse()?;
+                            referenced_column = Some(column.value());
                         } else if meta.path.is_ident("compress") {
                             is_compressed = true;
+                            compression_precision = parse_compress_precision(&meta)?;
+                        } else if meta.path.is_ident("lazy") {
+                            is_lazy = true;
+                        } else if meta.path.is_ident("encrypted") {
+                            is_encrypted = true;
+                        } else if meta.path.is_ident("masked") {
+                            is_masked = true;
+                        } else if meta.path.is_ident("validate") {
+                            match parse_validate_rules(&meta, Some(field_name)) {
+                                Ok(checks) => metadata.validation_checks.extend(checks),
+                                Err(err) => {
+                                    metadata.errors.push(err.clone());
+                                    return Err(err);
+                                }
+                            }
                         }
                         Ok(())
                     });
@@ -1782,50 +2684,484 @@ fn extract_field_metadata_original(
             }
 
             if is_unique {
-                unique_fields.push(field_name.clone());
+                metadata.unique_fields.push(field_name.clone());
+            }
+            // Compressed, encrypted, JSONB, custom and vector columns ignore `type`
+            let typed_elsewhere = ["jsonb", "custom", "vector"]
+                .iter()
+                .any(|flag| has_column_flag(field, flag));
+            if let Some(column_type) = column_type {
+                if !is_compressed && !is_encrypted && !typed_elsewhere {
+                    metadata.type_overrides.push((
+                        field_name.clone(),
+                        column_type.value(),
+                        field.ty.clone(),
+                    ));
+                }
+            }
+            if is_case_insensitive {
+                metadata.case_insensitive_fields.push(field_name.clone());
+            }
+            match deferral {
+                Some((_, initially))
+                    if (is_unique && !is_case_insensitive) || reference_table.is_some() =>
+                {
+                    metadata
+                        .deferrable_fields
+                        .push((field_name.clone(), initially));
+                }
+                Some((path, _)) => metadata.errors.push(syn::Error::new_spanned(
+                    path,
+                    "`deferrable` and `initially_deferred` apply to `unique` and `ref` columns",
+                )),
+                None => {}
+            }
+            // Only references to `id` link records, e.g. into a tree
+            if let Some(table) = reference_table {
+                if referenced_column.as_deref().unwrap_or("id") == "id" {
+                    metadata.references.push((field_name.clone(), table));
+                }
+            }
+            if is_lazy {
+                metadata.lazy_fields.push(field_name.clone());
+            }
+            if is_encrypted {
+                metadata.encrypted_fields.push(field_name.clone());
+            }
+            if is_masked {
+                metadata.masked_fields.push(field_name.clone());
+            }
+            match trigger {
+                Some(_) if is_updated_at => metadata.updated_at_trigger = true,
+                Some(path) => metadata.errors.push(syn::Error::new_spanned(
+                    path,
+                    "`trigger` only applies to the `updated_at` column",
+                )),
+                None => {}
+            }
+            match seek {
+                Some(_) if is_created_at => metadata.created_at_seek = true,
+                Some(path) => metadata.errors.push(syn::Error::new_spanned(
+                    path,
+                    "`seek` only applies to the `created_at` column",
+                )),
+                None => {}
+            }
+            match format {
+                Some(format) if format.value() != "unix_ms" => metadata.errors.push(
+                    syn::Error::new_spanned(format, "the only timestamp format is \"unix_ms\""),
+                ),
+                Some(format) if !is_created_at && !is_updated_at => {
+                    metadata.errors.push(syn::Error::new_spanned(
+                        format,
+                        "`as` only applies to the `created_at` and `updated_at` columns",
+                    ))
+                }
+                Some(format) if map_rust_type_to_sql_type(&field.ty, false) != "BIGINT" => {
+                    metadata.errors.push(syn::Error::new_spanned(
+                        format,
+                        "`as = \"unix_ms\"` columns must be `i64` or `Option<i64>`",
+                    ))
+                }
+                Some(_) => metadata.unix_ms_fields.push(field_name.clone()),
+                None => {}
             }
 
             // Process ALL fields - no skipping based on field names
 
             let field_name_token = quote! { stringify!(#field_name) };
-            field_names.push(field_name_token);
+            metadata.field_names.push(field_name_token);
 
             // Parse column attributes for foreign key references (inline REFERENCES)
-            let column_def = parse_field_column_definition(field);
-            column_defs.push(quote! { #column_def.to_string() });
+            metadata
+                .column_definitions
+                .push(column_definition_tokens(field));
+            if has_column_flag(field, "custom") {
+                metadata
+                    .custom_fields
+                    .push((field_name.clone(), field.ty.clone()));
+            }
 
             // Enhanced type mapping based on field type and attributes
             let field_type = map_field_type(&field.ty, field, is_compressed);
-            field_types.push(field_type);
+            metadata.field_types.push(field_type);
 
-            // Check if field is Option<T> (nullable)
-            let is_nullable = is_option_type(&field.ty);
-            nullable_flags.push(is_nullable);
+            // Check if field is Option<T> (nullable), lazy columns are nullable as well
+            let is_nullable = is_option_type(&field.ty) || is_lazy;
+            metadata.nullable_flags.push(is_nullable);
 
             // Store compression flag
-            compressed_fields.push(is_compressed);
+            metadata.compressed_fields.push(is_compressed);
+            metadata
+                .compressed_elements
+                .push(compressed_element_type(&field.ty, is_compressed));
+            metadata.compression_precisions.push(compression_precision);
         }
     }
 
-    (
-        field_names,
-        column_defs,
-        field_types,
-        nullable_flags,
-        primary_key_field,
-        created_at_field,
-        updated_at_field,
-        unique_fields,
-        compressed_fields, // Return compression flags
-    )
+    metadata
+}
+
+fn has_column_flag(field: &syn::Field, flag: &str) -> bool {
+    field.attrs.iter().any(|attr| {
+        let mut found = false;
+        if attr.path().is_ident("orso_column") {
+            let _ = attr.parse_nested_meta(|meta| {
+                if meta.path.is_ident(flag) {
+                    found = true;
+                } else if meta.input.peek(syn::Token![=]) {
+                    let _: syn::Expr = meta.value()?.parse()?;
+                } else if meta.input.peek(syn::token::Paren) {
+                    let _content;
+                    syn::parenthesized!(_content in meta.input);
+                }
+                Ok(())
+            });
+        }
+        found
+    })
+}
+
+fn is_phantom_data(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Path(type_path) => type_path
+            .path
+            .segments
+            .last()
+            .is_some_and(|segment| segment.ident == "PhantomData"),
+        _ => false,
+    }
+}
+
+// Parse `#[orso_index(columns("a", "b") | expr = "...", where = "...", unique, using = "...",
+// concurrently, name = "...")]`
+fn index_def(
+    attr: &Attribute,
+    data: &Data,
+    non_transactional: bool,
+) -> syn::Result<proc_macro2::TokenStream> {
+    const METHODS: [&str; 6] = ["btree", "hash", "gist", "spgist", "gin", "brin"];
+
+    let mut columns: Vec<syn::LitStr> = Vec::new();
+    let mut expr: Option<String> = None;
+    let mut predicate: Option<String> = None;
+    let mut name: Option<String> = None;
+    let mut method: Option<String> = None;
+    let mut unique: Option<syn::Path> = None;
+    let mut concurrently = false;
+
+    attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("columns") {
+            let content;
+            syn::parenthesized!(content in meta.input);
+            let list = Punctuated::<syn::LitStr, Comma>::parse_terminated(&content)?;
+            columns.extend(list);
+        } else if meta.path.is_ident("expr") {
+            expr = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+        } else if meta.path.is_ident("where") {
+            predicate = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+        } else if meta.path.is_ident("name") {
+            name = Some(meta.value()?.parse::<syn::LitStr>()?.value());
+        } else if meta.path.is_ident("unique") {
+            unique = Some(meta.path);
+        } else if meta.path.is_ident("concurrently") {
+            // CREATE INDEX CONCURRENTLY fails inside the migration's transaction
+            if !non_transactional {
+                return Err(meta.error(
+                    "`concurrently` indexes can't be built in a transaction, mark the model \
+                     `#[non_transactional]`",
+                ));
+            }
+            concurrently = true;
+        } else if meta.path.is_ident("using") {
+            let lit: syn::LitStr = meta.value()?.parse()?;
+            let value = lit.value().to_lowercase();
+            if !METHODS.contains(&value.as_str()) {
+                return Err(syn::Error::new_spanned(
+                    lit,
+                    format!(
+                        "unknown index method, expected one of {}",
+                        METHODS.join(", ")
+                    ),
+                ));
+            }
+            method = Some(value);
+        } else {
+            return Err(meta.error(
+                "expected `columns(..)`, `expr = \"..\"`, `where = \"..\"`, `unique`, `using`, \
+                 `concurrently` or `name`",
+            ));
+        }
+        Ok(())
+    })?;
+
+    if let Some(path) = &unique {
+        if method.as_deref().is_some_and(|method| method != "btree") {
+            return Err(syn::Error::new_spanned(
+                path,
+                "only B-tree indexes can be `unique`",
+            ));
+        }
+    }
+    let unique = unique.is_some();
+
+    if columns.is_empty() == expr.is_none() {
+        return Err(syn::Error::new_spanned(
+            attr,
+            "orso_index takes either `columns(..)` or `expr = \"..\"`",
+        ));
+    }
+
+    // Flattened fields contribute columns prefixed with their name
+    let fields: Vec<(String, bool)> = match data {
+        Data::Struct(data) => data
+            .fields
+            .iter()
+            .filter_map(|field| {
+                let flattened =
+                    has_column_flag(field, "flatten") || has_column_flag(field, "ref_poly");
+                Some((field.ident.as_ref()?.to_string(), flattened))
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    for column in &columns {
+        let value = column.value();
+        let known = fields.iter().any(|(field, flattened)| {
+            *field == value || (*flattened && value.starts_with(&format!("{field}_")))
+        });
+        if !known {
+            return Err(syn::Error::new_spanned(
+                column,
+                format!("no column `{value}` to index"),
+            ));
+        }
+    }
+
+    let option = |value: Option<String>| match value {
+        Some(value) => quote! { Some(#value) },
+        None => quote! { None },
+    };
+    let (name, expr, predicate) = (option(name), option(expr), option(predicate));
+    let method = option(method);
+    Ok(quote! {
+        orso_postgres::IndexDef {
+            name: #name,
+            columns: &[#(#columns),*],
+            expr: #expr,
+            predicate: #predicate,
+            unique: #unique,
+            method: #method,
+            concurrently: #concurrently,
+        }
+    })
+}
+
+// Parse `#[orso_join_table(UserRole, left = User, right = Role)]` and generate the association
+// methods of both sides, with the unique index on the pair that attaching relies on
+fn join_table_methods(
+    name: &syn::Ident,
+    attr: &Attribute,
+) -> syn::Result<(proc_macro2::TokenStream, proc_macro2::TokenStream)> {
+    let mut join: syn::Path = name.clone().into();
+    let mut left: Option<syn::Path> = None;
+    let mut right: Option<syn::Path> = None;
+
+    attr.parse_nested_meta(|meta| {
+        if meta.path.is_ident("left") {
+            left = Some(meta.value()?.parse()?);
+        } else if meta.path.is_ident("right") {
+            right = Some(meta.value()?.parse()?);
+        } else if meta.input.is_empty() || meta.input.peek(Comma) {
+            join = meta.path;
+        } else {
+            return Err(meta.error("expected the join model, `left = Model` or `right = Model`"));
+        }
+        Ok(())
+    })?;
+
+    let missing = |side: &str| {
+        syn::Error::new_spanned(attr, format!("orso_join_table requires `{side} = Model`"))
+    };
+    let left = left.ok_or_else(|| missing("left"))?;
+    let right = right.ok_or_else(|| missing("right"))?;
+
+    let left_name = snake_case(&left.segments.last().unwrap().ident.to_string());
+    let right_name = snake_case(&right.segments.last().unwrap().ident.to_string());
+    let left_column = format!("{left_name}_id");
+    let right_column = format!("{right_name}_id");
+
+    let left_methods = association_methods(
+        &join,
+        &left,
+        &right,
+        &left_column,
+        &right_column,
+        &right_name,
+    );
+    let right_methods = association_methods(
+        &join,
+        &right,
+        &left,
+        &right_column,
+        &left_column,
+        &left_name,
+    );
+    let methods = quote! {
+        #left_methods
+        #right_methods
+    };
+    let index = quote! {
+        orso_postgres::IndexDef {
+            name: None,
+            columns: &[#left_column, #right_column],
+            expr: None,
+            predicate: None,
+            unique: true,
+            method: None,
+            concurrently: false,
+        }
+    };
+    Ok((methods, index))
+}
+
+fn association_methods(
+    join: &syn::Path,
+    owner: &syn::Path,
+    other: &syn::Path,
+    owner_column: &str,
+    other_column: &str,
+    other_name: &str,
+) -> proc_macro2::TokenStream {
+    let plural = pluralize(other_name);
+    let ident = |name: String| syn::Ident::new(&name, proc_macro2::Span::call_site());
+    let add_one = ident(format!("add_{other_name}"));
+    let add_many = ident(format!("add_{plural}"));
+    let remove_one = ident(format!("remove_{other_name}"));
+    let remove_many = ident(format!("remove_{plural}"));
+    let list = ident(plural.clone());
+
+    let association = quote! {
+        orso_postgres::Association::new(
+            <#join as orso_postgres::Orso>::table_name(),
+            #owner_column,
+            #other_column,
+        )
+    };
+
+    quote! {
+        impl #owner {
+            pub async fn #add_one(
+                &self,
+                record: &#other,
+                db: &impl orso_postgres::Executor,
+            ) -> orso_postgres::Result<()> {
+                self.#add_many(std::slice::from_ref(record), db).await.map(|_| ())
+            }
+
+            pub async fn #add_many(
+                &self,
+                records: &[#other],
+                db: &impl orso_postgres::Executor,
+            ) -> orso_postgres::Result<u64> {
+                #association.attach(self, records, db).await
+            }
+
+            pub async fn #remove_one(
+                &self,
+                record: &#other,
+                db: &impl orso_postgres::Executor,
+            ) -> orso_postgres::Result<()> {
+                self.#remove_many(std::slice::from_ref(record), db).await.map(|_| ())
+            }
+
+            pub async fn #remove_many(
+                &self,
+                records: &[#other],
+                db: &impl orso_postgres::Executor,
+            ) -> orso_postgres::Result<u64> {
+                #association.detach(self, records, db).await
+            }
+
+            pub async fn #list(
+                &self,
+                db: &impl orso_postgres::Executor,
+            ) -> orso_postgres::Result<Vec<#other>> {
+                #association.related(self, db).await
+            }
+        }
+    }
+}
+
+// `UserRole` -> `user_role`
+fn snake_case(name: &str) -> String {
+    let mut out = String::new();
+    for (i, c) in name.chars().enumerate() {
+        if c.is_uppercase() {
+            if i > 0 {
+                out.push('_');
+            }
+            out.extend(c.to_lowercase());
+        } else {
+            out.push(c);
+        }
+    }
+    out
+}
+
+// `role` -> `roles`, `category` -> `categories`, `address` -> `addresses`
+fn pluralize(name: &str) -> String {
+    if let Some(stem) = name.strip_suffix('y') {
+        if !stem.ends_with(['a', 'e', 'i', 'o', 'u']) {
+            return format!("{stem}ies");
+        }
+    }
+    if name.ends_with('s') || name.ends_with('x') || name.ends_with("ch") || name.ends_with("sh") {
+        return format!("{name}es");
+    }
+    format!("{name}s")
 }
 
 // Extract table name from struct attributes
 fn extract_orso_table_name(attrs: &[Attribute]) -> Option<String> {
     for attr in attrs {
         if attr.path().is_ident("orso_table") {
-            if let Ok(Lit::Str(lit_str)) = attr.parse_args::<Lit>() {
-                return Some(lit_str.value());
+            let args = attr.parse_args_with(Punctuated::<syn::Expr, Comma>::parse_terminated);
+            if let Some(syn::Expr::Lit(expr)) = args.ok().and_then(|args| args.into_iter().next()) {
+                if let Lit::Str(lit_str) = expr.lit {
+                    return Some(lit_str.value());
+                }
+            }
+        }
+    }
+    None
+}
+
+// Extract the default connection from #[orso_table("events", connection = "analytics")]
+fn extract_orso_table_connection(attrs: &[Attribute]) -> Option<String> {
+    extract_orso_table_option(attrs, "connection")
+}
+
+// Extract a `key = "value"` option of #[orso_table(..)]
+fn extract_orso_table_option(attrs: &[Attribute], key: &str) -> Option<String> {
+    for attr in attrs {
+        if attr.path().is_ident("orso_table") {
+            let Ok(args) = attr.parse_args_with(Punctuated::<syn::Expr, Comma>::parse_terminated)
+            else {
+                continue;
+            };
+            for arg in args {
+                if let syn::Expr::Assign(assign) = arg {
+                    let is_key = matches!(
+                        &*assign.left,
+                        syn::Expr::Path(path) if path.path.is_ident(key)
+                    );
+                    if let (true, syn::Expr::Lit(expr)) = (is_key, &*assign.right) {
+                        if let Lit::Str(lit_str) = &expr.lit {
+                            return Some(lit_str.value());
+                        }
+                    }
+                }
             }
         }
     }
@@ -0,0 +1,626 @@
+//! Index suggestions from the queries an application runs
+//!
+//! Attach a [`QueryLog`] to a database with
+//! [`Database::with_query_log`](crate::Database::with_query_log) and the filters and sorts of
+//! the statements it runs are recorded by shape, whether they come from model operations, the
+//! query builder or raw SQL. [`Advisor::suggest_indexes`] turns the shapes seen on a model's
+//! table into `CREATE INDEX` statements for those no existing index serves:
+//!
+//! ```rust,ignore
+//! let log = Arc::new(QueryLog::new());
+//! let db = Database::init(config).await?.with_query_log(log.clone());
+//! // ... run the workload
+//! let suggestions = Advisor::suggest_indexes::<Trade>(&db, &log.observed()).await?;
+//! for suggestion in &suggestions {
+//!     println!("{} -- {} queries", suggestion.sql(), suggestion.queries);
+//! }
+//! Migrations::init(&db, &[Advisor::migration(suggestions)]).await?;
+//! ```
+//!
+//! Suggested keys are the equality columns followed by the sort columns, or by the first range
+//! column for unsorted queries. Only `SELECT`, `UPDATE` and `DELETE` statements on a single
+//! table are recorded; conditions under `OR` and `NOT` and conditions on expressions such as
+//! JSON paths are not looked at.
+
+use crate::migrations::{Drift, MigrationAction, MigrationConfig, MigrationResult, MigrationTrait};
+use crate::{Database, Error, FilterOperator, Operator, Orso, Result, Schema, Sort, SortOrder};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tracing::{debug, instrument};
+
+/// Columns a read filtered and sorted on
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ObservedQuery {
+    pub table: String,
+    /// Columns compared with `=`, `IN` or `IS NULL`
+    pub equality: Vec<String>,
+    /// Columns compared with `<`, `<=`, `>`, `>=`, `BETWEEN` or `LIKE`
+    pub range: Vec<String>,
+    pub sort: Vec<(String, SortOrder)>,
+}
+
+impl ObservedQuery {
+    pub fn new(table: impl Into<String>) -> Self {
+        Self {
+            table: table.into(),
+            ..Default::default()
+        }
+    }
+
+    pub fn with_equality(mut self, column: impl Into<String>) -> Self {
+        self.equality.push(column.into());
+        self
+    }
+
+    pub fn with_range(mut self, column: impl Into<String>) -> Self {
+        self.range.push(column.into());
+        self
+    }
+
+    pub fn with_sort(mut self, column: impl Into<String>, order: SortOrder) -> Self {
+        self.sort.push((column.into(), order));
+        self
+    }
+
+    /// The shape of a read of `table` with the `AND`ed `filters`, ordered by `sorts`
+    pub fn from_clauses(table: &str, filters: &[FilterOperator], sorts: &[Sort]) -> Self {
+        let mut query = Self::new(table);
+        for filter in filters {
+            query.collect(filter);
+        }
+        query.sort = sorts.iter().map(|s| (s.column.clone(), s.order)).collect();
+        query
+    }
+
+    /// The shape of a `SELECT`, `UPDATE` or `DELETE` statement on a single table, if `sql` is
+    /// one
+    pub fn from_sql(sql: &str) -> Option<Self> {
+        let tokens = tokenize(sql)?;
+        let word = |i: usize, keyword: &str| tokens.get(i).is_some_and(|t| is_keyword(t, keyword));
+        let table_at = if word(0, "select") {
+            top_level(&tokens).find(|&i| word(i, "from"))? + 1
+        } else if word(0, "update") {
+            1
+        } else if word(0, "delete") && word(1, "from") {
+            2
+        } else {
+            return None;
+        };
+        let Some(Token::Word(table)) = tokens.get(table_at) else {
+            return None;
+        };
+        // Catalog reads, e.g. of `Schema::describe`, are no model's
+        if table.starts_with("pg_") || table.starts_with("information_schema.") {
+            return None;
+        }
+        let mut query = Self::new(unquote(table));
+
+        // Conditions may name the table or its alias, but nothing else may be joined in
+        let mut names = vec![table.clone()];
+        let next = top_level(&tokens).find(|&i| i > table_at && !word(i, "as"));
+        if let Some(Token::Word(alias)) = next.map(|i| &tokens[i]) {
+            if !CLAUSES
+                .iter()
+                .chain(JOINS)
+                .any(|c| alias.eq_ignore_ascii_case(c))
+            {
+                names.push(alias.clone());
+            }
+        }
+        let after_table = || top_level(&tokens).filter(|&i| i > table_at);
+        let listed = after_table()
+            .take_while(|&i| !CLAUSES.iter().any(|c| word(i, c)))
+            .any(|i| tokens[i] == Token::Punct(','));
+        if listed || after_table().any(|i| JOINS.iter().any(|j| word(i, j))) {
+            return None;
+        }
+
+        let clause_end = |from: usize| {
+            top_level(&tokens)
+                .filter(|&i| i > from)
+                .find(|&i| CLAUSES.iter().any(|c| word(i, c)) && !word(i, "where"))
+                .unwrap_or(tokens.len())
+        };
+        if let Some(start) = top_level(&tokens).find(|&i| word(i, "where")) {
+            query.collect_sql(&tokens[start + 1..clause_end(start)], &names);
+        }
+        if let Some(start) = top_level(&tokens).find(|&i| word(i, "order") && word(i + 1, "by")) {
+            let end = clause_end(start + 1);
+            for key in split_top_level(&tokens[start + 2..end], |t| *t == Token::Punct(',')) {
+                let Some(column) = column_of(key, &names) else {
+                    continue;
+                };
+                let order = match key.get(1) {
+                    Some(t) if is_keyword(t, "desc") => SortOrder::Desc,
+                    _ => SortOrder::Asc,
+                };
+                query.sort.push((column, order));
+            }
+        }
+        Some(query)
+    }
+
+    /// Record the `AND`ed conditions of a `WHERE` clause
+    fn collect_sql(&mut self, tokens: &[Token], names: &[String]) {
+        let tokens = strip_parens(tokens);
+        // The AND of a BETWEEN belongs to it
+        let mut between = false;
+        let conditions = split_top_level(tokens, |t| {
+            if is_keyword(t, "between") {
+                between = true;
+            } else if is_keyword(t, "and") {
+                return !std::mem::take(&mut between);
+            }
+            false
+        });
+        if conditions.len() > 1 {
+            for condition in conditions {
+                self.collect_sql(condition, names);
+            }
+            return;
+        }
+        let negated =
+            split_top_level(tokens, |t| is_keyword(t, "or") || is_keyword(t, "not")).len() > 1;
+        let Some(column) = column_of(tokens, names).filter(|_| !negated) else {
+            return;
+        };
+        // Skip a cast of the column, e.g. `id::text`
+        let mut rest = &tokens[1..];
+        while let [Token::Punct(':'), Token::Punct(':'), Token::Word(_), tail @ ..] = rest {
+            rest = tail;
+        }
+        let columns = match rest {
+            // `<>` and `!=` compare for inequality, which no index serves
+            [Token::Punct('<'), Token::Punct('>'), ..] => return,
+            [Token::Punct('='), ..] => &mut self.equality,
+            [t, ..] if is_keyword(t, "in") || is_keyword(t, "is") => &mut self.equality,
+            [Token::Punct('<' | '>'), ..] => &mut self.range,
+            [t, ..]
+                if ["between", "like", "ilike"]
+                    .iter()
+                    .any(|k| is_keyword(t, k)) =>
+            {
+                &mut self.range
+            }
+            _ => return,
+        };
+        if !columns.contains(&column) {
+            columns.push(column);
+        }
+    }
+
+    fn collect(&mut self, filter: &FilterOperator) {
+        match filter {
+            FilterOperator::Single(filter) => {
+                let columns = match filter.operator {
+                    Operator::Eq
+                    | Operator::In
+                    | Operator::IsNull
+                    | Operator::IsTrue
+                    | Operator::IsFalse => &mut self.equality,
+                    Operator::Lt
+                    | Operator::Le
+                    | Operator::Gt
+                    | Operator::Ge
+                    | Operator::Between
+                    | Operator::Like => &mut self.range,
+                    _ => return,
+                };
+                if !columns.contains(&filter.column) {
+                    columns.push(filter.column.clone());
+                }
+            }
+            FilterOperator::And(filters) => filters.iter().for_each(|f| self.collect(f)),
+            _ => {}
+        }
+    }
+
+    /// Index keys serving the query, equality columns first
+    fn keys(&self) -> (Vec<(String, SortOrder)>, usize) {
+        let mut keys: Vec<(String, SortOrder)> = self
+            .equality
+            .iter()
+            .map(|column| (column.clone(), SortOrder::Asc))
+            .collect();
+        let equalities = keys.len();
+        let has =
+            |keys: &[(String, SortOrder)], column: &str| keys.iter().any(|(c, _)| c == column);
+        if self.sort.is_empty() {
+            if let Some(column) = self.range.iter().find(|c| !has(&keys, c)) {
+                keys.push((column.clone(), SortOrder::Asc));
+            }
+        } else {
+            for (column, order) in &self.sort {
+                if !has(&keys, column) {
+                    keys.push((column.clone(), *order));
+                }
+            }
+        }
+        (keys, equalities)
+    }
+}
+
+/// Keywords starting the clause after a table name or `WHERE` condition
+const CLAUSES: &[&str] = &[
+    "where",
+    "set",
+    "order",
+    "group",
+    "having",
+    "limit",
+    "offset",
+    "for",
+    "returning",
+    "union",
+];
+
+/// Keywords bringing another table into a statement
+const JOINS: &[&str] = &[
+    "join", "inner", "left", "right", "full", "cross", "natural", "using", "from",
+];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    /// A keyword or identifier, possibly qualified, with quoted parts kept in their quotes
+    Word(String),
+    /// A literal or `$n` parameter
+    Value,
+    Punct(char),
+}
+
+/// Split `sql` into tokens, or `None` for an unterminated quote
+fn tokenize(sql: &str) -> Option<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut chars = sql.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {}
+            '\'' => {
+                // A doubled quote stands for one inside the literal
+                loop {
+                    match chars.next()? {
+                        '\'' if chars.peek() == Some(&'\'') => {
+                            chars.next();
+                        }
+                        '\'' => break,
+                        _ => {}
+                    }
+                }
+                tokens.push(Token::Value);
+            }
+            '$' | '0'..='9' => {
+                while chars
+                    .next_if(|c| c.is_alphanumeric() || *c == '.')
+                    .is_some()
+                {}
+                tokens.push(Token::Value);
+            }
+            c if c == '"' || c == '_' || c.is_alphabetic() => {
+                let mut word = String::new();
+                let mut quoted = c == '"';
+                word.push(c);
+                while let Some(&next) = chars.peek() {
+                    if quoted {
+                        quoted = next != '"';
+                    } else if next == '"' {
+                        quoted = true;
+                    } else if !(next.is_alphanumeric() || next == '_' || next == '.') {
+                        break;
+                    }
+                    word.push(next);
+                    chars.next();
+                }
+                if quoted {
+                    return None;
+                }
+                tokens.push(Token::Word(word));
+            }
+            c => tokens.push(Token::Punct(c)),
+        }
+    }
+    Some(tokens)
+}
+
+/// Indexes of the tokens outside parentheses
+fn top_level(tokens: &[Token]) -> impl Iterator<Item = usize> + '_ {
+    let mut depth = 0i32;
+    tokens.iter().enumerate().filter_map(move |(i, token)| {
+        let outside = depth == 0;
+        match token {
+            Token::Punct('(') => depth += 1,
+            Token::Punct(')') => depth -= 1,
+            _ => {}
+        }
+        (outside && *token != Token::Punct('(')).then_some(i)
+    })
+}
+
+/// `tokens` split at the tokens outside parentheses `separator` matches
+fn split_top_level(tokens: &[Token], mut separator: impl FnMut(&Token) -> bool) -> Vec<&[Token]> {
+    let mut parts = Vec::new();
+    let mut start = 0;
+    for i in top_level(tokens) {
+        if separator(&tokens[i]) {
+            parts.push(&tokens[start..i]);
+            start = i + 1;
+        }
+    }
+    parts.push(&tokens[start..]);
+    parts
+}
+
+/// `tokens` without the parentheses around all of them
+fn strip_parens(mut tokens: &[Token]) -> &[Token] {
+    while let [Token::Punct('('), inner @ .., Token::Punct(')')] = tokens {
+        // `(a) AND (b)` starts and ends with parentheses that don't pair up
+        let mut depth = 0;
+        let paired = inner.iter().all(|token| {
+            match token {
+                Token::Punct('(') => depth += 1,
+                Token::Punct(')') => depth -= 1,
+                _ => {}
+            }
+            depth >= 0
+        });
+        if !paired {
+            break;
+        }
+        tokens = inner;
+    }
+    tokens
+}
+
+/// The column `tokens` start with, unqualified, unless it is a call or names another table
+fn column_of(tokens: &[Token], names: &[String]) -> Option<String> {
+    let Some(Token::Word(word)) = tokens.first() else {
+        return None;
+    };
+    if tokens.get(1) == Some(&Token::Punct('(')) {
+        return None;
+    }
+    let column = match word.rsplit_once('.') {
+        Some((qualifier, column)) if names.iter().any(|n| n == qualifier) => column,
+        Some(_) => return None,
+        None => word,
+    };
+    const NOT_COLUMNS: &[&str] = &["not", "exists", "true", "false", "null", "case"];
+    if NOT_COLUMNS.iter().any(|k| column.eq_ignore_ascii_case(k)) {
+        return None;
+    }
+    Some(unquote(column))
+}
+
+fn is_keyword(token: &Token, keyword: &str) -> bool {
+    matches!(token, Token::Word(word) if word.eq_ignore_ascii_case(keyword))
+}
+
+fn unquote(identifier: &str) -> String {
+    identifier.trim_matches('"').to_string()
+}
+
+/// Hook counting the shapes of the statements run on a [`Database`]
+#[derive(Debug, Default)]
+pub struct QueryLog {
+    shapes: Mutex<HashMap<ObservedQuery, u64>>,
+}
+
+impl QueryLog {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Record the shape of `sql`, if it has one, see [`ObservedQuery::from_sql`]
+    pub fn record_sql(&self, sql: &str) {
+        if let Some(query) = ObservedQuery::from_sql(sql) {
+            self.record(query);
+        }
+    }
+
+    pub fn record(&self, query: ObservedQuery) {
+        let mut shapes = self.shapes.lock().unwrap_or_else(|e| e.into_inner());
+        *shapes.entry(query).or_insert(0) += 1;
+    }
+
+    /// Shapes recorded so far and how often each was seen, most frequent first
+    pub fn observed(&self) -> Vec<(ObservedQuery, u64)> {
+        let shapes = self.shapes.lock().unwrap_or_else(|e| e.into_inner());
+        let mut observed: Vec<_> = shapes.iter().map(|(q, n)| (q.clone(), *n)).collect();
+        observed.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+        observed
+    }
+
+    pub fn clear(&self) {
+        self.shapes
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clear();
+    }
+}
+
+/// An index that would serve observed queries
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexSuggestion {
+    pub table: String,
+    pub name: String,
+    /// Keys in index order
+    pub columns: Vec<(String, SortOrder)>,
+    /// Number of observed queries it serves
+    pub queries: u64,
+    /// Leading keys that only take equality conditions, in any order
+    equalities: usize,
+}
+
+impl IndexSuggestion {
+    fn new(table: &str, columns: Vec<(String, SortOrder)>, equalities: usize) -> Self {
+        let names: Vec<&str> = columns.iter().map(|(c, _)| c.as_str()).collect();
+        Self {
+            table: table.to_string(),
+            name: format!("{table}_{}_idx", names.join("_")),
+            columns,
+            queries: 0,
+            equalities,
+        }
+    }
+
+    /// The `CREATE INDEX` statement
+    pub fn sql(&self) -> String {
+        let keys: Vec<String> = self
+            .columns
+            .iter()
+            .map(|(column, order)| match order {
+                SortOrder::Asc => column.clone(),
+                SortOrder::Desc => format!("{column} DESC"),
+            })
+            .collect();
+        format!(
+            "CREATE INDEX IF NOT EXISTS {} ON {} ({})",
+            self.name,
+            self.table,
+            keys.join(", ")
+        )
+    }
+
+    /// Whether an index on `columns` can stand in for this one
+    fn served_by(&self, columns: &[&str]) -> bool {
+        if columns.len() < self.columns.len() {
+            return false;
+        }
+        let (equal, rest) = self.columns.split_at(self.equalities);
+        let mut leading = columns[..self.equalities].to_vec();
+        let mut wanted: Vec<&str> = equal.iter().map(|(c, _)| c.as_str()).collect();
+        leading.sort_unstable();
+        wanted.sort_unstable();
+        leading == wanted
+            && rest
+                .iter()
+                .zip(&columns[self.equalities..])
+                .all(|((c, _), column)| c == column)
+    }
+}
+
+/// Index suggestions from a [`QueryLog`]
+pub struct Advisor;
+
+impl Advisor {
+    /// Indexes on the table of `T` that would serve the `observed` queries the existing
+    /// indexes don't, by how many queries they serve
+    pub async fn suggest_indexes<T: Orso>(
+        db: &Database,
+        observed: &[(ObservedQuery, u64)],
+    ) -> Result<Vec<IndexSuggestion>> {
+        Self::suggest_indexes_with_table::<T>(db, observed, T::table_name()).await
+    }
+
+    #[instrument(name = "orso.advisor.suggest", skip_all, fields(table = %table_name))]
+    pub async fn suggest_indexes_with_table<T: Orso>(
+        db: &Database,
+        observed: &[(ObservedQuery, u64)],
+        table_name: &str,
+    ) -> Result<Vec<IndexSuggestion>> {
+        let table = Schema::describe(db, table_name).await?;
+        let model_columns = T::columns();
+
+        let mut candidates: Vec<IndexSuggestion> = Vec::new();
+        for (query, count) in observed.iter().filter(|(q, _)| q.table == table_name) {
+            // Columns of joined or computed expressions can't be indexed here
+            let mut query = query.clone();
+            query
+                .equality
+                .retain(|c| model_columns.contains(&c.as_str()));
+            query.range.retain(|c| model_columns.contains(&c.as_str()));
+            query
+                .sort
+                .retain(|(c, _)| model_columns.contains(&c.as_str()));
+
+            let (keys, equalities) = query.keys();
+            if keys.is_empty() {
+                continue;
+            }
+            let suggestion = IndexSuggestion::new(table_name, keys, equalities);
+            let served = table.indexes.iter().any(|index| {
+                let columns: Vec<&str> = index.columns.iter().map(String::as_str).collect();
+                suggestion.served_by(&columns)
+            });
+            if served {
+                continue;
+            }
+            match candidates
+                .iter_mut()
+                .find(|c| c.columns == suggestion.columns)
+            {
+                Some(existing) => existing.queries += count,
+                None => candidates.push(IndexSuggestion {
+                    queries: *count,
+                    ..suggestion
+                }),
+            }
+        }
+
+        // Wider indexes first, so narrower ones they also serve fold into them
+        candidates.sort_by_key(|c| std::cmp::Reverse(c.columns.len()));
+        let mut suggestions: Vec<IndexSuggestion> = Vec::new();
+        for candidate in candidates {
+            let wider = suggestions.iter_mut().find(|s| {
+                let columns: Vec<&str> = s.columns.iter().map(|(c, _)| c.as_str()).collect();
+                candidate.served_by(&columns)
+            });
+            match wider {
+                Some(wider) => wider.queries += candidate.queries,
+                None => suggestions.push(candidate),
+            }
+        }
+        suggestions.sort_by_key(|s| std::cmp::Reverse(s.queries));
+
+        debug!(suggestions = suggestions.len(), "Suggested indexes");
+        Ok(suggestions)
+    }
+
+    /// A migration creating the suggested indexes, to run with
+    /// [`Migrations::init`](crate::Migrations::init)
+    pub fn migration(suggestions: Vec<IndexSuggestion>) -> Box<dyn MigrationTrait> {
+        Box::new(IndexMigration { suggestions })
+    }
+}
+
+struct IndexMigration {
+    suggestions: Vec<IndexSuggestion>,
+}
+
+#[async_trait::async_trait]
+impl MigrationTrait for IndexMigration {
+    async fn run_migration(
+        &self,
+        db: &Database,
+        _config: &MigrationConfig,
+    ) -> Result<MigrationResult> {
+        let mut changes = Vec::new();
+        for suggestion in &self.suggestions {
+            let sql = suggestion.sql();
+            db.execute(&sql, &[]).await.map_err(|e| {
+                Error::migration(
+                    format!("Failed to create suggested index: {}", e),
+                    Some(suggestion.table.clone()),
+                    Some("create_index".to_string()),
+                )
+            })?;
+            changes.push(sql);
+        }
+        let action = if self.suggestions.is_empty() {
+            MigrationAction::SchemaMatched
+        } else {
+            MigrationAction::IndexesCreated {
+                indexes: self.suggestions.iter().map(|s| s.name.clone()).collect(),
+            }
+        };
+        Ok(MigrationResult {
+            action,
+            backup_table: None,
+            rows_migrated: None,
+            schema_changes: changes,
+        })
+    }
+
+    async fn verify(&self, _db: &Database) -> Result<Vec<Drift>> {
+        Ok(Vec::new())
+    }
+}
@@ -0,0 +1,161 @@
+//! Moving old rows out of hot tables
+//!
+//! [`Archive::move_where`] moves the rows matching a filter into an archive table with the
+//! model's schema, creating it if needed. Every batch is a single `DELETE ... RETURNING` feeding
+//! an `INSERT`, so a row is always in exactly one of the two tables, even if the run is
+//! interrupted:
+//!
+//! ```rust,ignore
+//! let cutoff = FilterOperator::Single(Filter::lt("created_at", one_year_ago));
+//! let options = ArchiveOptions::new()
+//!     .with_batch_size(5_000)
+//!     .on_progress(|p| info!(moved = p.rows_moved, "Archiving trades"));
+//! Archive::move_where_with::<Trade>(cutoff, "trades_archive", options, &db).await?;
+//!
+//! // Archived rows are read through the same model
+//! let old = Trade::find_all_with_table(&db, "trades_archive").await?;
+//! ```
+//!
+//! The archive table is only created, not migrated; add `migration!(Trade, "trades_archive")`
+//! to keep it in step with the model.
+
+use crate::{Database, FilterOperator, Result};
+use std::sync::Arc;
+use tracing::{info, instrument};
+
+/// Progress of an [`Archive::move_where`] run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ArchiveProgress {
+    /// Rows moved so far
+    pub rows_moved: u64,
+    /// Batches committed so far
+    pub batches: u64,
+}
+
+type ProgressCallback = Arc<dyn Fn(&ArchiveProgress) + Send + Sync>;
+
+/// Options for [`Archive::move_where_with`]
+#[derive(Clone)]
+pub struct ArchiveOptions {
+    pub batch_size: usize,
+    on_progress: Option<ProgressCallback>,
+}
+
+impl Default for ArchiveOptions {
+    fn default() -> Self {
+        Self {
+            batch_size: 1000,
+            on_progress: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for ArchiveOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArchiveOptions")
+            .field("batch_size", &self.batch_size)
+            .field("on_progress", &self.on_progress.is_some())
+            .finish()
+    }
+}
+
+impl ArchiveOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    /// Called after every batch with the totals so far
+    pub fn on_progress(
+        mut self,
+        callback: impl Fn(&ArchiveProgress) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_progress = Some(Arc::new(callback));
+        self
+    }
+}
+
+/// Archiving of model tables
+pub struct Archive;
+
+impl Archive {
+    /// Move the rows of `T` matching `filter` into `archive_table`
+    pub async fn move_where<T>(
+        filter: FilterOperator,
+        archive_table: &str,
+        db: &Database,
+    ) -> Result<ArchiveProgress>
+    where
+        T: crate::Orso,
+    {
+        Self::move_where_with::<T>(filter, archive_table, ArchiveOptions::default(), db).await
+    }
+
+    pub async fn move_where_with<T>(
+        filter: FilterOperator,
+        archive_table: &str,
+        options: ArchiveOptions,
+        db: &Database,
+    ) -> Result<ArchiveProgress>
+    where
+        T: crate::Orso,
+    {
+        Self::move_where_with_table::<T>(filter, archive_table, options, db, T::table_name()).await
+    }
+
+    #[instrument(name = "orso.archive", skip_all, fields(table = %table_name, archive = %archive_table))]
+    pub async fn move_where_with_table<T>(
+        filter: FilterOperator,
+        archive_table: &str,
+        options: ArchiveOptions,
+        db: &Database,
+        table_name: &str,
+    ) -> Result<ArchiveProgress>
+    where
+        T: crate::Orso,
+    {
+        let create_sql =
+            crate::migrations::generate_migration_sql_with_custom_name::<T>(archive_table);
+        db.execute(&create_sql, &[]).await?;
+
+        let (where_sql, params) = crate::FilterOperations::build_filter_operator(&filter)?;
+        let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
+            params.iter().map(|p| p.as_ref()).collect();
+        let columns = T::columns().join(", ");
+        let batch_size = options.batch_size.max(1);
+        let sql = format!(
+            "WITH moved AS (\
+             DELETE FROM {table_name} WHERE ctid = ANY(ARRAY(\
+             SELECT ctid FROM {table_name} WHERE {where_sql} LIMIT {batch_size} FOR UPDATE\
+             )) RETURNING {columns}\
+             ) INSERT INTO {archive_table} ({columns}) SELECT {columns} FROM moved"
+        );
+
+        let mut progress = ArchiveProgress::default();
+        loop {
+            let moved = db.execute(&sql, &param_refs).await?;
+            progress.rows_moved += moved;
+            if moved > 0 {
+                progress.batches += 1;
+                if let Some(callback) = &options.on_progress {
+                    callback(&progress);
+                }
+            }
+            if moved < batch_size as u64 {
+                break;
+            }
+        }
+
+        info!(
+            table = table_name,
+            archive = archive_table,
+            rows_moved = progress.rows_moved,
+            "Archived rows"
+        );
+        Ok(progress)
+    }
+}
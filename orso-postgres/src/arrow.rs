@@ -0,0 +1,201 @@
+//! Arrow and Parquet export
+//!
+//! Enabled with the `arrow` feature. Records matching a filter are read in primary key order,
+//! one page per Arrow record batch, so whole tables can be exported without holding them in
+//! memory. Parquet files open directly in pandas, polars or DuckDB:
+//!
+//! ```rust,ignore
+//! let file = std::fs::File::create("trades.parquet")?;
+//! let rows = orso_postgres::arrow::write_parquet::<Trade>(filter, file, &db).await?;
+//!
+//! let batches = orso_postgres::arrow::record_batches::<Trade>(filter, 10_000, &db);
+//! pin_mut!(batches);
+//! while let Some(batch) = batches.try_next().await? {
+//!     println!("{} rows", batch.num_rows());
+//! }
+//! ```
+//!
+//! Columns keep their types: arrays, vectors and compressed `Vec` fields become list arrays,
+//! timestamps are UTC microsecond timestamps and JSONB columns are JSON text.
+
+use crate::{Error, Executor, FieldType, FilterOperator, Orso, Result};
+use ::arrow::datatypes::{DataType, Field, Schema, SchemaRef, TimeUnit};
+use ::arrow::json::ReaderBuilder;
+use ::arrow::record_batch::RecordBatch;
+use futures_util::{pin_mut, Stream, TryStreamExt};
+use parquet::arrow::ArrowWriter;
+use serde_json::{Map, Value};
+use std::io::Write;
+use std::sync::Arc;
+
+/// Rows per record batch written by [`write_parquet`]
+pub const PARQUET_BATCH_ROWS: u32 = 10_000;
+
+/// Convert records to a record batch with one column per model column
+pub fn record_batch<T: Orso>(records: &[T]) -> Result<RecordBatch> {
+    let rows = column_rows(records)?;
+    decode(&rows, Arc::new(model_schema::<T>()))
+}
+
+/// Stream the records matching `filter` as record batches of up to `batch_rows` rows
+pub fn record_batches<'a, T: Orso + 'a>(
+    filter: FilterOperator,
+    batch_rows: u32,
+    db: &'a impl Executor,
+) -> impl Stream<Item = Result<RecordBatch>> + 'a {
+    let batch_rows = batch_rows.max(1);
+    let schema = Arc::new(model_schema::<T>());
+    futures_util::stream::try_unfold(Some(None), move |after: Option<Option<String>>| {
+        let filter = filter.clone();
+        let schema = schema.clone();
+        async move {
+            let Some(after) = after else {
+                return Ok(None);
+            };
+            let records: Vec<T> =
+                crate::exchange::page_after(&filter, after.as_deref(), batch_rows, db).await?;
+            if records.is_empty() {
+                return Ok(None);
+            }
+
+            let batch = decode(&column_rows(&records)?, schema)?;
+            let last = records.last().and_then(Orso::get_primary_key);
+            let next = match last {
+                Some(last) if records.len() == batch_rows as usize => Some(Some(last)),
+                _ => None,
+            };
+            Ok(Some((batch, next)))
+        }
+    })
+}
+
+/// Write the records matching `filter` to `writer` as a Parquet file, returning the row count
+pub async fn write_parquet<T: Orso>(
+    filter: FilterOperator,
+    writer: impl Write + Send,
+    db: &impl Executor,
+) -> Result<u64> {
+    let batches = record_batches::<T>(filter, PARQUET_BATCH_ROWS, db);
+    pin_mut!(batches);
+
+    let mut next = batches.try_next().await?;
+    let schema = match &next {
+        Some(batch) => batch.schema(),
+        None => Arc::new(model_schema::<T>()),
+    };
+    let mut parquet = ArrowWriter::try_new(writer, schema, None).map_err(parquet_error)?;
+    let mut rows = 0;
+    while let Some(batch) = next {
+        rows += batch.num_rows() as u64;
+        parquet.write(&batch).map_err(parquet_error)?;
+        next = batches.try_next().await?;
+    }
+    parquet.close().map_err(parquet_error)?;
+    Ok(rows)
+}
+
+/// Records as JSON objects keyed by column, with embedded structs spread over their columns
+fn column_rows<T: Orso>(records: &[T]) -> Result<Vec<Map<String, Value>>> {
+    let names = T::field_names();
+    let types = T::field_types();
+
+    records
+        .iter()
+        .map(|record| {
+            let Value::Object(mut fields) = serde_json::to_value(record)? else {
+                return Err(Error::serialization(format!(
+                    "{} record is not a JSON object",
+                    T::table_name()
+                )));
+            };
+            let mut row = Map::new();
+            for (name, field_type) in names.iter().zip(&types) {
+                let value = fields
+                    .remove(*name)
+                    .or_else(|| embedded(&fields, name))
+                    .unwrap_or(Value::Null);
+                let value = match (field_type, value) {
+                    (FieldType::JsonB | FieldType::Custom(_), value) if !value.is_null() => {
+                        Value::String(value.to_string())
+                    }
+                    (_, value) => value,
+                };
+                row.insert(name.to_string(), value);
+            }
+            Ok(row)
+        })
+        .collect()
+}
+
+/// Value of the column `<field>_<name>` held by an embedded struct
+fn embedded(fields: &Map<String, Value>, column: &str) -> Option<Value> {
+    fields.iter().find_map(|(field, value)| {
+        let name = column.strip_prefix(field.as_str())?.strip_prefix('_')?;
+        value.get(name).cloned()
+    })
+}
+
+fn model_schema<T: Orso>() -> Schema {
+    let list = |item: DataType| DataType::List(Arc::new(Field::new("item", item, true)));
+    let elements = T::field_compressed_elements()
+        .into_iter()
+        .chain(std::iter::repeat(None));
+    let columns = T::field_names()
+        .into_iter()
+        .zip(T::field_types())
+        .zip(T::field_nullable())
+        .zip(T::field_compressed())
+        .zip(elements);
+
+    let fields: Vec<Field> = columns
+        .map(|((((name, field_type), nullable), compressed), element)| {
+            let data_type = match field_type {
+                _ if compressed => list(compressed_item_type(element.as_ref())),
+                FieldType::Text | FieldType::JsonB | FieldType::Custom(_) => DataType::Utf8,
+                FieldType::Integer => DataType::Int32,
+                FieldType::BigInt => DataType::Int64,
+                FieldType::Numeric => DataType::Float64,
+                FieldType::Boolean => DataType::Boolean,
+                FieldType::Timestamp => {
+                    DataType::Timestamp(TimeUnit::Microsecond, Some("UTC".into()))
+                }
+                FieldType::IntegerArray => list(DataType::Int32),
+                FieldType::BigIntArray => list(DataType::Int64),
+                FieldType::NumericArray => list(DataType::Float64),
+                FieldType::Vector(_) => list(DataType::Float32),
+            };
+            Field::new(name, data_type, nullable)
+        })
+        .collect();
+    Schema::new(fields)
+}
+
+/// Compressed columns hold the integers or floats of the field's `Vec`
+fn compressed_item_type(element: Option<&FieldType>) -> DataType {
+    match element {
+        Some(FieldType::Integer) => DataType::Int32,
+        Some(FieldType::Numeric) => DataType::Float64,
+        _ => DataType::Int64,
+    }
+}
+
+fn decode(rows: &[Map<String, Value>], schema: SchemaRef) -> Result<RecordBatch> {
+    let mut decoder = ReaderBuilder::new(schema.clone())
+        .with_batch_size(rows.len().max(1))
+        .with_coerce_primitive(true)
+        .build_decoder()
+        .map_err(arrow_error)?;
+    decoder.serialize(rows).map_err(arrow_error)?;
+    Ok(decoder
+        .flush()
+        .map_err(arrow_error)?
+        .unwrap_or_else(|| RecordBatch::new_empty(schema)))
+}
+
+fn arrow_error(err: ::arrow::error::ArrowError) -> Error {
+    Error::serialization(format!("Arrow error: {err}"))
+}
+
+fn parquet_error(err: parquet::errors::ParquetError) -> Error {
+    Error::serialization(format!("Parquet error: {err}"))
+}
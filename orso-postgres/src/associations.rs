@@ -0,0 +1,140 @@
+//! Many-to-many associations
+//!
+//! A join model marked `#[orso_join_table(UserRole, left = User, right = Role)]` links two models
+//! through its `user_id` and `role_id` columns. The derive adds methods on both sides:
+//!
+//! ```rust,ignore
+//! #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+//! #[orso_table("user_roles")]
+//! #[orso_join_table(UserRole, left = User, right = Role)]
+//! struct UserRole {
+//!     #[orso_column(primary_key)]
+//!     id: Option<String>,
+//!     #[orso_column(ref = "users")]
+//!     user_id: String,
+//!     #[orso_column(ref = "roles")]
+//!     role_id: String,
+//! }
+//!
+//! user.add_role(&admin, &db).await?;
+//! user.add_roles(&[editor, viewer], &db).await?;
+//! let roles = user.roles(&db).await?;
+//! let members = admin.users(&db).await?;
+//! user.remove_roles(&roles, &db).await?;
+//! ```
+//!
+//! Attaching a pair that is already linked is a no-op. The derive declares a unique index on the
+//! pair of columns, which the join table needs for that.
+
+use crate::operations::{key_array_param, key_param, key_sql_type};
+use crate::{Error, Executor, Filter, Orso, Result, Value};
+use tracing::instrument;
+
+/// One direction of a join table: the owner's column and the associated model's column
+#[derive(Debug, Clone, Copy)]
+pub struct Association {
+    pub join_table: &'static str,
+    pub owner_column: &'static str,
+    pub other_column: &'static str,
+}
+
+impl Association {
+    pub fn new(
+        join_table: &'static str,
+        owner_column: &'static str,
+        other_column: &'static str,
+    ) -> Self {
+        Self {
+            join_table,
+            owner_column,
+            other_column,
+        }
+    }
+
+    /// Link `owner` to each of `others`, skipping pairs that already exist
+    #[instrument(name = "orso.attach", skip_all, fields(table = %self.join_table))]
+    pub async fn attach<O: Orso, T: Orso>(
+        &self,
+        owner: &O,
+        others: &[T],
+        db: &impl Executor,
+    ) -> Result<u64> {
+        let owner_id = key(owner)?;
+        let other_ids = keys(others)?;
+        if other_ids.is_empty() {
+            return Ok(0);
+        }
+        let owner_id = key_param::<O>(&owner_id)?;
+        let other_ids =
+            key_array_param::<T>(&other_ids.iter().map(String::as_str).collect::<Vec<_>>())?;
+
+        let sql = format!(
+            "INSERT INTO {table} ({owner}, {other}) \
+             SELECT DISTINCT $1::{owner_type}, ids.id FROM unnest($2::{other_type}[]) AS ids(id) \
+             ON CONFLICT ({owner}, {other}) DO NOTHING",
+            table = self.join_table,
+            owner = self.owner_column,
+            other = self.other_column,
+            owner_type = key_sql_type::<O>(),
+            other_type = key_sql_type::<T>(),
+        );
+        db.execute(&sql, &[owner_id.as_ref(), other_ids.as_ref()])
+            .await
+    }
+
+    /// Remove the links between `owner` and each of `others`
+    #[instrument(name = "orso.detach", skip_all, fields(table = %self.join_table))]
+    pub async fn detach<O: Orso, T: Orso>(
+        &self,
+        owner: &O,
+        others: &[T],
+        db: &impl Executor,
+    ) -> Result<u64> {
+        let owner_id = key(owner)?;
+        let other_ids = keys(others)?;
+        if other_ids.is_empty() {
+            return Ok(0);
+        }
+        let owner_id = key_param::<O>(&owner_id)?;
+        let other_ids =
+            key_array_param::<T>(&other_ids.iter().map(String::as_str).collect::<Vec<_>>())?;
+
+        let sql = format!(
+            "DELETE FROM {} WHERE {} = $1 AND {} = ANY($2)",
+            self.join_table, self.owner_column, self.other_column
+        );
+        db.execute(&sql, &[owner_id.as_ref(), other_ids.as_ref()])
+            .await
+    }
+
+    /// Records linked to `owner`
+    #[instrument(name = "orso.related", skip_all, fields(table = %self.join_table))]
+    pub async fn related<O: Orso, T: Orso>(&self, owner: &O, db: &impl Executor) -> Result<Vec<T>> {
+        // The key is bound as text and cast, since filter values don't carry the column type
+        let filter = Filter::raw(
+            format!(
+                "{} IN (SELECT {} FROM {} WHERE {} = $?::text::{})",
+                T::primary_key_field(),
+                self.other_column,
+                self.join_table,
+                self.owner_column,
+                key_sql_type::<O>(),
+            ),
+            vec![Value::Text(key(owner)?)],
+        );
+        T::find_where(filter, db).await
+    }
+}
+
+fn key<T: Orso>(record: &T) -> Result<String> {
+    record.get_primary_key().ok_or_else(|| {
+        Error::validation(format!(
+            "Cannot associate {} record without primary key",
+            T::table_name()
+        ))
+    })
+}
+
+fn keys<T: Orso>(records: &[T]) -> Result<Vec<String>> {
+    records.iter().map(key).collect()
+}
@@ -0,0 +1,147 @@
+//! Audit log for `#[orso_audited]` models
+//!
+//! Audited tables get a `<table>_audit` shadow table and a row-level trigger that records
+//! every insert, update and delete with the old and new values as JSONB. The actor comes
+//! from the active [`OperationContext`](crate::OperationContext), which `Database::execute`
+//! exposes to the trigger through the `orso.actor` setting.
+//!
+//! The shadow table and trigger are installed by `Migrations::init`, or explicitly with
+//! [`Audit::install`].
+
+use crate::{Database, Error, OrsoDateTime, Result};
+
+/// A single recorded change
+#[derive(Debug, Clone, PartialEq)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub record_id: Option<String>,
+    /// `INSERT`, `UPDATE` or `DELETE`
+    pub operation: String,
+    pub old_values: Option<serde_json::Value>,
+    pub new_values: Option<serde_json::Value>,
+    pub actor: Option<String>,
+    pub changed_at: OrsoDateTime,
+}
+
+/// Audit log management
+pub struct Audit;
+
+impl Audit {
+    /// Name of the shadow table for `table_name`
+    pub fn audit_table_name(table_name: &str) -> String {
+        format!("{}_audit", table_name)
+    }
+
+    /// Statements creating the shadow table, trigger function and trigger
+    pub fn install_sql(table_name: &str, primary_key: &str) -> Vec<String> {
+        let audit = Self::audit_table_name(table_name);
+        let actor = "NULLIF(current_setting('orso.actor', true), '')";
+
+        vec![
+            format!(
+                "CREATE TABLE IF NOT EXISTS {audit} (\n    \
+                 id BIGSERIAL PRIMARY KEY,\n    \
+                 record_id TEXT,\n    \
+                 operation TEXT NOT NULL,\n    \
+                 old_values JSONB,\n    \
+                 new_values JSONB,\n    \
+                 actor TEXT,\n    \
+                 changed_at TIMESTAMPTZ NOT NULL DEFAULT NOW()\n)"
+            ),
+            format!("CREATE INDEX IF NOT EXISTS {audit}_record_idx ON {audit} (record_id)"),
+            format!(
+                "CREATE OR REPLACE FUNCTION {audit}_fn() RETURNS trigger AS $$\n\
+                 BEGIN\n    \
+                 IF TG_OP = 'DELETE' THEN\n        \
+                 INSERT INTO {audit} (record_id, operation, old_values, actor)\n        \
+                 VALUES (OLD.{primary_key}::text, TG_OP, to_jsonb(OLD), {actor});\n        \
+                 RETURN OLD;\n    \
+                 ELSIF TG_OP = 'UPDATE' THEN\n        \
+                 INSERT INTO {audit} (record_id, operation, old_values, new_values, actor)\n        \
+                 VALUES (NEW.{primary_key}::text, TG_OP, to_jsonb(OLD), to_jsonb(NEW), {actor});\n    \
+                 ELSE\n        \
+                 INSERT INTO {audit} (record_id, operation, new_values, actor)\n        \
+                 VALUES (NEW.{primary_key}::text, TG_OP, to_jsonb(NEW), {actor});\n    \
+                 END IF;\n    \
+                 RETURN NEW;\n\
+                 END;\n\
+                 $$ LANGUAGE plpgsql"
+            ),
+            format!("DROP TRIGGER IF EXISTS {audit}_trigger ON {table_name}"),
+            format!(
+                "CREATE TRIGGER {audit}_trigger AFTER INSERT OR UPDATE OR DELETE ON {table_name} \
+                 FOR EACH ROW EXECUTE FUNCTION {audit}_fn()"
+            ),
+        ]
+    }
+
+    /// Create the shadow table and trigger for `T`
+    pub async fn install<T: crate::Orso>(db: &Database) -> Result<()> {
+        Self::install_with_table::<T>(db, T::table_name()).await
+    }
+
+    pub async fn install_with_table<T: crate::Orso>(db: &Database, table_name: &str) -> Result<()> {
+        for sql in Self::install_sql(table_name, T::primary_key_field()) {
+            db.execute(&sql, &[]).await.map_err(|e| {
+                Error::migration(
+                    format!("Failed to install audit log: {}", e),
+                    Some(table_name.to_string()),
+                    Some("install_audit".to_string()),
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Stop recording changes made to a table, e.g. a migration backup
+    pub async fn uninstall_trigger(
+        db: &Database,
+        table_name: &str,
+        audited_table: &str,
+    ) -> Result<()> {
+        let sql = format!(
+            "DROP TRIGGER IF EXISTS {}_trigger ON {}",
+            Self::audit_table_name(audited_table),
+            table_name
+        );
+        db.execute(&sql, &[]).await?;
+        Ok(())
+    }
+
+    /// Recorded changes of a single record, oldest first
+    pub async fn history<T: crate::Orso>(id: &str, db: &Database) -> Result<Vec<AuditEntry>> {
+        Self::history_with_table::<T>(id, db, T::table_name()).await
+    }
+
+    pub async fn history_with_table<T: crate::Orso>(
+        id: &str,
+        db: &Database,
+        table_name: &str,
+    ) -> Result<Vec<AuditEntry>> {
+        let sql = format!(
+            "SELECT id, record_id, operation, old_values::text, new_values::text, actor, changed_at \
+             FROM {} WHERE record_id = $1 ORDER BY id",
+            Self::audit_table_name(table_name)
+        );
+
+        let rows = db.query(&sql, &[&id.to_string()]).await?;
+        rows.iter()
+            .map(|row| {
+                let parse = |idx: usize| -> Result<Option<serde_json::Value>> {
+                    let text: Option<String> = row.try_get(idx)?;
+                    Ok(text.map(|t| serde_json::from_str(&t)).transpose()?)
+                };
+
+                Ok(AuditEntry {
+                    id: row.try_get(0)?,
+                    record_id: row.try_get(1)?,
+                    operation: row.try_get(2)?,
+                    old_values: parse(3)?,
+                    new_values: parse(4)?,
+                    actor: row.try_get(5)?,
+                    changed_at: row.try_get(6)?,
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,323 @@
+//! Read-through query result cache
+//!
+//! A [`Cache`] keeps the rows of recent `SELECT`s in memory, keyed by the normalized SQL and its
+//! parameters, for read-heavy pages such as dashboards. Queries run through [`Cache::wrap`] are
+//! answered from it until the entry expires, is evicted by newer entries, or its table changes:
+//!
+//! ```rust,ignore
+//! let cache = Cache::new(1_000, Duration::from_secs(60));
+//! let cached = cache.wrap(&db);
+//! let totals = Order::find_where(filter, &cached).await?; // queries
+//! let totals = Order::find_where(filter, &cached).await?; // cached
+//!
+//! // Evict a table's entries when it changes, from any connection or process
+//! Cache::install::<Order>(&db).await?;
+//! cache.listen(&config).await?;
+//!
+//! // Or evict explicitly
+//! cache.invalidate::<Order>();
+//! ```
+//!
+//! Writes run through the wrapped executor evict their table at once. Entries are tracked by the
+//! table they read from (the first table of a join), so other tables of a join only expire
+//! with the TTL.
+
+use crate::{DatabaseConfig, Error, Executor, Result, RowMap};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::task::JoinHandle;
+use tokio_postgres::types::ToSql;
+use tracing::debug;
+
+/// Channel the change triggers notify on, with the table name as payload
+pub const NOTIFY_CHANNEL: &str = "orso_cache";
+
+struct Entry {
+    rows: Vec<RowMap>,
+    table: Option<String>,
+    expires: Instant,
+    last_used: u64,
+}
+
+#[derive(Default)]
+struct Entries {
+    entries: HashMap<String, Entry>,
+    clock: u64,
+    /// Invalidations of each table, so results read while one happened aren't cached
+    generations: HashMap<String, u64>,
+    clears: u64,
+}
+
+impl Entries {
+    /// Changes whenever the entries of `table` are invalidated or all entries cleared
+    fn generation(&self, table: Option<&str>) -> u64 {
+        let invalidations = table.and_then(|table| self.generations.get(table));
+        self.clears + invalidations.copied().unwrap_or(0)
+    }
+}
+
+/// In-memory LRU cache of query results with a TTL, shared by clones
+#[derive(Clone)]
+pub struct Cache {
+    entries: Arc<Mutex<Entries>>,
+    capacity: usize,
+    ttl: Duration,
+    /// Client of the `LISTEN` connection, which closes when it is dropped
+    listener: Arc<Mutex<Option<tokio_postgres::Client>>>,
+}
+
+impl std::fmt::Debug for Cache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Cache")
+            .field("capacity", &self.capacity)
+            .field("ttl", &self.ttl)
+            .field("len", &self.len())
+            .finish()
+    }
+}
+
+impl Cache {
+    /// Cache up to `capacity` results, each for at most `ttl`
+    pub fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            entries: Arc::default(),
+            capacity: capacity.max(1),
+            ttl,
+            listener: Arc::default(),
+        }
+    }
+
+    /// Executor reading through this cache
+    pub fn wrap<E: Executor>(&self, db: E) -> Cached<E> {
+        Cached {
+            db,
+            cache: self.clone(),
+        }
+    }
+
+    /// Drop the cached results of `T`'s table
+    pub fn invalidate<T: crate::Orso>(&self) {
+        self.invalidate_table(T::table_name());
+    }
+
+    pub fn invalidate_table(&self, table: &str) {
+        let mut entries = self.lock();
+        *entries.generations.entry(table.to_string()).or_default() += 1;
+        entries
+            .entries
+            .retain(|_, entry| entry.table.as_deref() != Some(table));
+    }
+
+    pub fn clear(&self) {
+        let mut entries = self.lock();
+        entries.clears += 1;
+        entries.entries.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.lock().entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Statements creating the trigger that notifies [`NOTIFY_CHANNEL`] when `table_name` changes
+    pub fn install_sql(table_name: &str) -> Vec<String> {
+        vec![
+            format!(
+                "CREATE OR REPLACE FUNCTION orso_cache_notify() RETURNS trigger AS $$\n\
+                 BEGIN\n    \
+                 PERFORM pg_notify('{NOTIFY_CHANNEL}', TG_TABLE_NAME);\n    \
+                 RETURN NULL;\n\
+                 END;\n\
+                 $$ LANGUAGE plpgsql"
+            ),
+            format!("DROP TRIGGER IF EXISTS orso_cache_notify ON {table_name}"),
+            format!(
+                "CREATE TRIGGER orso_cache_notify \
+                 AFTER INSERT OR UPDATE OR DELETE OR TRUNCATE ON {table_name} \
+                 FOR EACH STATEMENT EXECUTE FUNCTION orso_cache_notify()"
+            ),
+        ]
+    }
+
+    /// Install the change notification trigger on `T`'s table
+    pub async fn install<T: crate::Orso>(db: &impl Executor) -> Result<()> {
+        Self::install_with_table(db, T::table_name()).await
+    }
+
+    pub async fn install_with_table(db: &impl Executor, table_name: &str) -> Result<()> {
+        for sql in Self::install_sql(table_name) {
+            db.execute(&sql, &[]).await.map_err(|e| {
+                Error::migration(
+                    format!("Failed to install cache trigger: {}", e),
+                    Some(table_name.to_string()),
+                    Some("install_cache_trigger".to_string()),
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Evict tables as their triggers report changes, on a dedicated connection
+    ///
+    /// The connection is opened like those of the pool, with the hosts, TLS connector,
+    /// credentials, connect timeout and `on_connect_sql` of `config`. The returned task runs until the connection closes or [`Cache::listen`] is called again;
+    /// the cache is cleared then, since changes may have been missed.
+    pub async fn listen(&self, config: &DatabaseConfig) -> Result<JoinHandle<()>> {
+        let connector = crate::credentials::Connector::new(config);
+        let connection = connector.open(config.pg_config()?).await?;
+        let client = connection.client;
+        if let Some(init_sql) = config.init_sql() {
+            client.batch_execute(&init_sql).await?;
+        }
+        client
+            .batch_execute(&format!("LISTEN {NOTIFY_CHANNEL}"))
+            .await?;
+
+        // The channel closes with the connection
+        let mut notifications = connection.notifications;
+        let cache = self.clone();
+        let task = tokio::spawn(async move {
+            while let Some(notification) = notifications.recv().await {
+                debug!(
+                    table = notification.payload(),
+                    "Invalidating cached queries"
+                );
+                cache.invalidate_table(notification.payload());
+            }
+            debug!("Cache invalidation connection closed");
+            cache.clear();
+        });
+        *self.listener.lock().unwrap_or_else(|e| e.into_inner()) = Some(client);
+        Ok(task)
+    }
+
+    fn get(&self, key: &str) -> Option<Vec<RowMap>> {
+        let mut entries = self.lock();
+        entries.clock += 1;
+        let now = entries.clock;
+        match entries.entries.get_mut(key) {
+            Some(entry) if entry.expires > Instant::now() => {
+                entry.last_used = now;
+                Some(entry.rows.clone())
+            }
+            Some(_) => {
+                entries.entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    fn generation(&self, table: Option<&str>) -> u64 {
+        self.lock().generation(table)
+    }
+
+    /// Cache `rows`, unless their table was invalidated since `generation` was taken before
+    /// reading them, which may have been too late for the query to see the change
+    fn insert(&self, key: String, table: Option<&str>, generation: u64, rows: Vec<RowMap>) {
+        let mut entries = self.lock();
+        if entries.generation(table) != generation {
+            return;
+        }
+        entries.clock += 1;
+        if entries.entries.len() >= self.capacity && !entries.entries.contains_key(&key) {
+            let oldest = entries
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.entries.remove(&oldest);
+            }
+        }
+        let entry = Entry {
+            rows,
+            table: table.map(str::to_string),
+            expires: Instant::now() + self.ttl,
+            last_used: entries.clock,
+        };
+        entries.entries.insert(key, entry);
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, Entries> {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Executor answering `SELECT`s from a [`Cache`]
+pub struct Cached<E> {
+    db: E,
+    cache: Cache,
+}
+
+impl<E> Cached<E> {
+    pub fn cache(&self) -> &Cache {
+        &self.cache
+    }
+
+    pub fn into_inner(self) -> E {
+        self.db
+    }
+}
+
+/// Normalized SQL followed by the parameter values
+fn cache_key(sql: &str, params: &[&(dyn ToSql + Send + Sync)]) -> String {
+    let sql = sql.split_whitespace().collect::<Vec<_>>().join(" ");
+    format!("{sql}\u{0}{params:?}")
+}
+
+impl<E: Executor> Executor for Cached<E> {
+    async fn execute(&self, sql: &str, params: &[&(dyn ToSql + Send + Sync)]) -> Result<u64> {
+        let rows = self.db.execute(sql, params).await?;
+        match crate::database::statement_table(sql) {
+            Some(table) => self.cache.invalidate_table(table),
+            None => self.cache.clear(),
+        }
+        Ok(rows)
+    }
+
+    async fn query_rows(
+        &self,
+        sql: &str,
+        params: &[&(dyn ToSql + Send + Sync)],
+    ) -> Result<Vec<RowMap>> {
+        let table = crate::database::statement_table(sql);
+        if crate::database::statement_kind(sql) != "select" {
+            let rows = self.db.query_rows(sql, params).await?;
+            match table {
+                Some(table) => self.cache.invalidate_table(table),
+                None => self.cache.clear(),
+            }
+            return Ok(rows);
+        }
+
+        let key = cache_key(sql, params);
+        if let Some(rows) = self.cache.get(&key) {
+            return Ok(rows);
+        }
+        let generation = self.cache.generation(table);
+        let rows = self.db.query_rows(sql, params).await?;
+        self.cache.insert(key, table, generation, rows.clone());
+        Ok(rows)
+    }
+
+    fn identity_map(&self) -> Option<&crate::session::IdentityMap> {
+        self.db.identity_map()
+    }
+
+    fn query_log(&self) -> Option<&crate::advisor::QueryLog> {
+        self.db.query_log()
+    }
+
+    fn strict_decoding(&self) -> bool {
+        self.db.strict_decoding()
+    }
+
+    fn keyring(&self) -> Option<&std::sync::Arc<crate::encryption::Keyring>> {
+        self.db.keyring()
+    }
+}
@@ -0,0 +1,652 @@
+//! Change data capture over logical replication
+//!
+//! A [`ChangeFeed`] publishes a model's table, opens a logical replication slot on it with the
+//! built-in `pgoutput` plugin and turns the committed inserts, updates and deletes into typed
+//! [`Change`] events. Needs `wal_level = logical` on the server and a user with the
+//! `REPLICATION` attribute:
+//!
+//! ```rust,ignore
+//! let feed = ChangeFeed::<Order>::new("orders_search_sync");
+//! feed.create(&db).await?;
+//!
+//! let changes = feed.stream(&db, &config);
+//! pin_mut!(changes);
+//! while let Some(event) = changes.try_next().await? {
+//!     match event.change {
+//!         Change::Insert { after } | Change::Update { after, .. } => index.put(after).await?,
+//!         Change::Delete { before } => index.remove(before.id).await?,
+//!         Change::Truncate => index.clear().await?,
+//!     }
+//! }
+//! ```
+//!
+//! The feed reads over a replication connection of its own, opened with the pool's hosts, TLS
+//! connector and credentials: the server pushes each transaction as it commits and the feed
+//! answers with standby status updates, every [`ChangeFeed::with_status_interval`] and
+//! whenever the server asks.
+//!
+//! A transaction is confirmed once the stream is polled past it, which lets the slot release
+//! it. A consumer that stops halfway through a transaction sees it again from the start on its
+//! next run. The slot holds back WAL until then, so [`ChangeFeed::remove`] feeds that are no
+//! longer read.
+
+use crate::credentials::Connector;
+use crate::replication::{ReplicationMessage, ReplicationStream};
+use crate::{Database, DatabaseConfig, Error, Executor, Orso, OrsoDateTime, Result};
+use futures_util::Stream;
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::marker::PhantomData;
+use std::str::FromStr;
+use std::time::{Duration, Instant};
+use tracing::{debug, info, instrument};
+
+const JSON_OID: u32 = 114;
+const JSONB_OID: u32 = 3802;
+
+/// Microseconds from the Unix epoch to PostgreSQL's, 2000-01-01
+pub(crate) const POSTGRES_EPOCH_MICROS: i64 = 946_684_800_000_000;
+
+/// A position in the write-ahead log
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct Lsn(pub u64);
+
+impl fmt::Display for Lsn {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:X}/{:X}", self.0 >> 32, self.0 & 0xFFFF_FFFF)
+    }
+}
+
+impl FromStr for Lsn {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let invalid = || Error::validation_field("Invalid LSN", "lsn", Some(s.to_string()));
+        let (high, low) = s.split_once('/').ok_or_else(invalid)?;
+        let high = u64::from_str_radix(high, 16).map_err(|_| invalid())?;
+        let low = u64::from_str_radix(low, 16).map_err(|_| invalid())?;
+        Ok(Lsn((high << 32) | low))
+    }
+}
+
+/// A committed change to a row of `T`
+#[derive(Debug, Clone, PartialEq)]
+pub enum Change<T> {
+    Insert {
+        after: T,
+    },
+    /// `before` is missing when the row changed before [`ChangeFeed::create`] made the table
+    /// log full rows
+    Update {
+        before: Option<T>,
+        after: T,
+    },
+    Delete {
+        before: T,
+    },
+    Truncate,
+}
+
+/// A [`Change`] with the transaction that made it
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangeEvent<T> {
+    /// Position of the change in the log
+    pub lsn: Lsn,
+    pub xid: u32,
+    pub committed_at: OrsoDateTime,
+    pub change: Change<T>,
+}
+
+/// Typed changes to the table of `T`, streamed from a logical replication slot
+pub struct ChangeFeed<T> {
+    slot: String,
+    status_interval: Duration,
+    _model: PhantomData<fn() -> T>,
+}
+
+impl<T: Orso> ChangeFeed<T> {
+    /// A feed reading the slot `slot`, which is also the name of its publication
+    pub fn new(slot: impl Into<String>) -> Self {
+        Self {
+            slot: slot.into(),
+            status_interval: Duration::from_secs(10),
+            _model: PhantomData,
+        }
+    }
+
+    /// How often to report the stream's position when the server doesn't ask; keep it below
+    /// the server's `wal_sender_timeout`
+    pub fn with_status_interval(mut self, status_interval: Duration) -> Self {
+        self.status_interval = status_interval;
+        self
+    }
+
+    pub fn slot(&self) -> &str {
+        &self.slot
+    }
+
+    /// Create the publication and slot if they don't exist, and have the table log full rows
+    /// so updates and deletes carry the previous version
+    #[instrument(
+        name = "orso.cdc.create",
+        skip_all,
+        fields(table = T::table_name(), slot = %self.slot)
+    )]
+    pub async fn create(&self, db: &Database) -> Result<()> {
+        let table = T::table_name();
+        let wal_level: String = db
+            .query_one("SELECT current_setting('wal_level')", &[])
+            .await?
+            .get(0);
+        if wal_level != "logical" {
+            return Err(Error::operation(
+                format!("Change data capture needs wal_level = logical, not {wal_level}"),
+                "cdc",
+                Some(table.to_string()),
+            ));
+        }
+
+        let published = db
+            .query_opt(
+                "SELECT 1 FROM pg_publication WHERE pubname = $1",
+                &[&self.slot],
+            )
+            .await?
+            .is_some();
+        if !published {
+            db.execute(
+                &format!("CREATE PUBLICATION {} FOR TABLE {table}", self.slot),
+                &[],
+            )
+            .await?;
+        }
+        db.execute(&format!("ALTER TABLE {table} REPLICA IDENTITY FULL"), &[])
+            .await?;
+
+        let slot_exists = db
+            .query_opt(
+                "SELECT 1 FROM pg_replication_slots WHERE slot_name = $1",
+                &[&self.slot],
+            )
+            .await?
+            .is_some();
+        if !slot_exists {
+            db.execute(
+                "SELECT pg_create_logical_replication_slot($1, 'pgoutput')",
+                &[&self.slot],
+            )
+            .await?;
+            info!("Created replication slot");
+        }
+        Ok(())
+    }
+
+    /// Drop the slot and publication, releasing the WAL held for them
+    #[instrument(
+        name = "orso.cdc.remove",
+        skip_all,
+        fields(table = T::table_name(), slot = %self.slot)
+    )]
+    pub async fn remove(&self, db: &Database) -> Result<()> {
+        db.execute(
+            "SELECT pg_drop_replication_slot(slot_name)
+             FROM pg_replication_slots WHERE slot_name = $1",
+            &[&self.slot],
+        )
+        .await?;
+        db.execute(&format!("DROP PUBLICATION IF EXISTS {}", self.slot), &[])
+            .await?;
+        Ok(())
+    }
+
+    /// End of the last transaction the slot has handed out, if it exists
+    pub async fn position(&self, db: &Database) -> Result<Option<Lsn>> {
+        let row = db
+            .query_opt(
+                "SELECT confirmed_flush_lsn::text FROM pg_replication_slots WHERE slot_name = $1",
+                &[&self.slot],
+            )
+            .await?;
+        row.and_then(|row| row.get::<_, Option<String>>(0))
+            .map(|lsn| lsn.parse())
+            .transpose()
+    }
+
+    /// Move the slot to `lsn`, skipping the changes committed before it
+    ///
+    /// Fails while a stream of the feed is running, which holds the slot.
+    pub async fn checkpoint(&self, db: &Database, lsn: Lsn) -> Result<()> {
+        db.execute(
+            "SELECT pg_replication_slot_advance($1, $2::text::pg_lsn)",
+            &[&self.slot, &lsn.to_string()],
+        )
+        .await?;
+        debug!(slot = %self.slot, %lsn, "Advanced replication slot");
+        Ok(())
+    }
+
+    /// Changes from the slot as they are committed, oldest first
+    ///
+    /// Opens a replication connection with `config`, the config `db` was made with, when first
+    /// polled. Only one stream of a slot can run at a time. The stream ends after the first
+    /// error.
+    pub fn stream<'a>(
+        &'a self,
+        db: &'a Database,
+        config: &DatabaseConfig,
+    ) -> impl Stream<Item = Result<ChangeEvent<T>>> + 'a {
+        let state = Some(StreamState {
+            connect: Some((Connector::new(config), config.pg_config())),
+            replication: None,
+            decoder: Decoder::new(T::table_name()),
+            pending: VecDeque::new(),
+            pending_end: None,
+            received: Lsn::default(),
+            flushed: Lsn::default(),
+            last_status: Instant::now(),
+        });
+        futures_util::stream::unfold(state, move |state| async move {
+            let mut state = state?;
+            match self.next_event(db, &mut state).await {
+                Ok(event) => Some((Ok(event), Some(state))),
+                Err(e) => Some((Err(e), None)),
+            }
+        })
+    }
+
+    async fn next_event(
+        &self,
+        db: &Database,
+        state: &mut StreamState<'_, T>,
+    ) -> Result<ChangeEvent<T>> {
+        loop {
+            if let Some(event) = state.pending.pop_front() {
+                return Ok(event);
+            }
+            if let Some((connector, pg_config)) = state.connect.take() {
+                state.replication = Some(self.start(&connector, pg_config?).await?);
+            }
+            let replication = state.replication.as_mut().expect("started above");
+
+            // Everything of the last transaction has been handed out
+            if let Some(end) = state.pending_end.take() {
+                state.flushed = end;
+                replication
+                    .send_status(state.received, state.flushed)
+                    .await?;
+                state.last_status = Instant::now();
+            }
+
+            let wait = self
+                .status_interval
+                .saturating_sub(state.last_status.elapsed());
+            let mut reply = false;
+            if let Ok(message) = tokio::time::timeout(wait, replication.next()).await {
+                match message? {
+                    ReplicationMessage::XLogData { start, data } => {
+                        state.received = state.received.max(start);
+                        if let Some(end) = state.decoder.message(start, &data)? {
+                            let (tuples, changes) = state.decoder.take();
+                            let events = self.events(db, &tuples, changes).await?;
+                            debug!(events = events.len(), %end, "Received transaction");
+                            if events.is_empty() {
+                                state.flushed = end;
+                            } else {
+                                state.pending.extend(events);
+                                state.pending_end = Some(end);
+                            }
+                        }
+                    }
+                    ReplicationMessage::Keepalive { end, reply: asked } => {
+                        // Nothing is held back between transactions, so the slot can move on
+                        if state.pending.is_empty() && !state.decoder.in_transaction {
+                            state.flushed = state.flushed.max(end);
+                        }
+                        state.received = state.received.max(end);
+                        reply = asked;
+                    }
+                }
+            }
+            if reply || state.last_status.elapsed() >= self.status_interval {
+                replication
+                    .send_status(state.received, state.flushed)
+                    .await?;
+                state.last_status = Instant::now();
+            }
+        }
+    }
+
+    /// Open a replication connection and start streaming the slot, waiting a moment for a
+    /// stream that just ended to release it
+    async fn start(
+        &self,
+        connector: &Connector,
+        pg_config: tokio_postgres::Config,
+    ) -> Result<ReplicationStream> {
+        let mut attempts = 0;
+        loop {
+            let mut replication = ReplicationStream::connect(connector, pg_config.clone()).await?;
+            match replication.start(&self.slot).await {
+                Ok(()) => {
+                    debug!(slot = %self.slot, "Started replication");
+                    return Ok(replication);
+                }
+                // The slot is active for another process
+                Err(Error::PostgreSql {
+                    code: Some(code), ..
+                }) if code == "55006" && attempts < 20 => {
+                    attempts += 1;
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                }
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    /// Turn the changes of a transaction into events, parsing their rows
+    async fn events(
+        &self,
+        db: &Database,
+        tuples: &[serde_json::Value],
+        changes: Vec<DecodedChange>,
+    ) -> Result<Vec<ChangeEvent<T>>> {
+        let models = self.models(db, tuples).await?;
+        let mut models = models.into_iter().map(Some).collect::<Vec<_>>();
+        let mut take = |i: usize| models[i].take().expect("each tuple is used once");
+
+        Ok(changes
+            .into_iter()
+            .map(|change| ChangeEvent {
+                lsn: change.lsn,
+                xid: change.xid,
+                committed_at: change.committed_at,
+                change: match change.kind {
+                    Kind::Insert(after) => Change::Insert { after: take(after) },
+                    Kind::Update(before, after) => Change::Update {
+                        before: before.map(&mut take),
+                        after: take(after),
+                    },
+                    Kind::Delete(before) => Change::Delete {
+                        before: take(before),
+                    },
+                    Kind::Truncate => Change::Truncate,
+                },
+            })
+            .collect())
+    }
+
+    /// Have the server parse the text of decoded rows into the table's types
+    async fn models(&self, db: &Database, tuples: &[serde_json::Value]) -> Result<Vec<T>> {
+        if tuples.is_empty() {
+            return Ok(Vec::new());
+        }
+        let sql = format!(
+            "SELECT * FROM json_populate_recordset(NULL::{}, $1::text::json)",
+            T::table_name()
+        );
+        let json = serde_json::Value::Array(tuples.to_vec()).to_string();
+        let rows = db.query_rows(&sql, &[&json]).await?;
+        rows.into_iter()
+            .map(|map| crate::decode::decode_record(db, map))
+            .collect()
+    }
+}
+
+/// Where a running [`ChangeFeed::stream`] is
+struct StreamState<'a, T> {
+    /// How to open the replication connection, until it is
+    connect: Option<(Connector, Result<tokio_postgres::Config>)>,
+    replication: Option<ReplicationStream>,
+    decoder: Decoder<'a>,
+    /// Events of the last transaction received, not yet handed out
+    pending: VecDeque<ChangeEvent<T>>,
+    /// End of that transaction, confirmed once its events are handed out
+    pending_end: Option<Lsn>,
+    /// Position of the last message received
+    received: Lsn,
+    /// Position up to which everything has been handed out
+    flushed: Lsn,
+    last_status: Instant,
+}
+
+enum Kind {
+    Insert(usize),
+    Update(Option<usize>, usize),
+    Delete(usize),
+    Truncate,
+}
+
+struct DecodedChange {
+    lsn: Lsn,
+    xid: u32,
+    committed_at: OrsoDateTime,
+    kind: Kind,
+}
+
+/// `pgoutput` messages of a stream, turned into changes to one table
+struct Decoder<'a> {
+    table: &'a str,
+    /// Columns of the relations seen, with whether they hold JSON
+    relations: HashMap<u32, (String, Vec<(String, bool)>)>,
+    /// Between the begin and commit of a transaction
+    in_transaction: bool,
+    xid: u32,
+    committed_at: OrsoDateTime,
+    /// Rows as JSON objects of column text, referenced by `changes`
+    tuples: Vec<serde_json::Value>,
+    changes: Vec<DecodedChange>,
+}
+
+impl<'a> Decoder<'a> {
+    fn new(table: &'a str) -> Self {
+        Self {
+            table,
+            relations: HashMap::new(),
+            in_transaction: false,
+            xid: 0,
+            committed_at: OrsoDateTime::now(),
+            tuples: Vec::new(),
+            changes: Vec::new(),
+        }
+    }
+
+    /// The rows and changes of the transaction decoded so far
+    fn take(&mut self) -> (Vec<serde_json::Value>, Vec<DecodedChange>) {
+        (
+            std::mem::take(&mut self.tuples),
+            std::mem::take(&mut self.changes),
+        )
+    }
+
+    /// Decode the message at `lsn`, returning the end of the transaction it commits, if any
+    fn message(&mut self, lsn: Lsn, data: &[u8]) -> Result<Option<Lsn>> {
+        let mut msg = Message::new(data);
+        let kind = match msg.u8()? {
+            b'B' => {
+                msg.i64()?; // final LSN
+                let micros = msg.i64()? + POSTGRES_EPOCH_MICROS;
+                self.committed_at = OrsoDateTime::new(
+                    chrono::DateTime::from_timestamp_micros(micros).unwrap_or_default(),
+                );
+                self.xid = msg.i32()? as u32;
+                self.in_transaction = true;
+                return Ok(None);
+            }
+            b'C' => {
+                msg.u8()?; // flags
+                msg.i64()?; // commit LSN
+                let end = Lsn(msg.i64()? as u64);
+                self.in_transaction = false;
+                return Ok(Some(end));
+            }
+            b'R' => {
+                let id = msg.i32()? as u32;
+                msg.str()?; // namespace
+                let name = msg.str()?;
+                msg.u8()?; // replica identity
+                let mut columns = Vec::new();
+                for _ in 0..msg.i16()? {
+                    msg.u8()?; // flags
+                    let column = msg.str()?;
+                    let type_oid = msg.i32()? as u32;
+                    msg.i32()?; // type modifier
+                    columns.push((column, matches!(type_oid, JSON_OID | JSONB_OID)));
+                }
+                self.relations.insert(id, (name, columns));
+                return Ok(None);
+            }
+            b'I' => {
+                let Some(id) = self.relation(msg.i32()? as u32) else {
+                    return Ok(None);
+                };
+                msg.u8()?; // 'N'
+                Kind::Insert(self.tuple(id, &mut msg, None)?)
+            }
+            b'U' => {
+                let Some(id) = self.relation(msg.i32()? as u32) else {
+                    return Ok(None);
+                };
+                let mut before = None;
+                if let b'K' | b'O' = msg.u8()? {
+                    before = Some(self.tuple(id, &mut msg, None)?);
+                    msg.u8()?; // 'N'
+                }
+                // Values that didn't change and live out of line only come with `before`
+                Kind::Update(before, self.tuple(id, &mut msg, before)?)
+            }
+            b'D' => {
+                let Some(id) = self.relation(msg.i32()? as u32) else {
+                    return Ok(None);
+                };
+                msg.u8()?; // 'K' or 'O'
+                Kind::Delete(self.tuple(id, &mut msg, None)?)
+            }
+            b'T' => {
+                let count = msg.i32()?;
+                msg.u8()?; // options
+                let mut ours = false;
+                for _ in 0..count {
+                    ours |= self.relation(msg.i32()? as u32).is_some();
+                }
+                if !ours {
+                    return Ok(None);
+                }
+                Kind::Truncate
+            }
+            // Origins and types
+            _ => return Ok(None),
+        };
+        self.changes.push(DecodedChange {
+            lsn,
+            xid: self.xid,
+            committed_at: self.committed_at,
+            kind,
+        });
+        Ok(None)
+    }
+
+    fn relation(&self, id: u32) -> Option<u32> {
+        self.relations
+            .get(&id)
+            .filter(|(name, _)| name == self.table)
+            .map(|_| id)
+    }
+
+    fn tuple(
+        &mut self,
+        relation: u32,
+        msg: &mut Message,
+        unchanged_from: Option<usize>,
+    ) -> Result<usize> {
+        let (_, columns) = &self.relations[&relation];
+        let count = msg.i16()? as usize;
+        if count > columns.len() {
+            return Err(Error::serialization(format!(
+                "Row of {} has {count} columns but its relation has {}",
+                self.table,
+                columns.len()
+            )));
+        }
+        let mut row = serde_json::Map::new();
+        for (column, json) in &columns[..count] {
+            let value = match msg.u8()? {
+                b'n' => serde_json::Value::Null,
+                b'u' => match unchanged_from.map(|i| &self.tuples[i][column.as_str()]) {
+                    Some(value) => value.clone(),
+                    None => {
+                        return Err(Error::serialization_field(
+                            format!("Unchanged value missing from update of {}", self.table),
+                            column.clone(),
+                        ))
+                    }
+                },
+                _ => {
+                    let length = msg.i32()? as usize;
+                    let text = std::str::from_utf8(msg.take(length)?)
+                        .map_err(|e| Error::serialization_field(e.to_string(), column.clone()))?;
+                    if *json {
+                        serde_json::from_str(text)?
+                    } else {
+                        serde_json::Value::String(text.to_string())
+                    }
+                }
+            };
+            row.insert(column.clone(), value);
+        }
+        self.tuples.push(serde_json::Value::Object(row));
+        Ok(self.tuples.len() - 1)
+    }
+}
+
+/// Big-endian fields of a `pgoutput` or protocol message
+pub(crate) struct Message<'d> {
+    data: &'d [u8],
+}
+
+impl<'d> Message<'d> {
+    pub(crate) fn new(data: &'d [u8]) -> Self {
+        Self { data }
+    }
+
+    /// What hasn't been read yet
+    pub(crate) fn rest(&self) -> &'d [u8] {
+        self.data
+    }
+
+    pub(crate) fn take(&mut self, n: usize) -> Result<&'d [u8]> {
+        if self.data.len() < n {
+            return Err(Error::serialization(
+                "Truncated logical replication message",
+            ));
+        }
+        let (head, rest) = self.data.split_at(n);
+        self.data = rest;
+        Ok(head)
+    }
+
+    pub(crate) fn u8(&mut self) -> Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(crate) fn i16(&mut self) -> Result<i16> {
+        Ok(i16::from_be_bytes(self.take(2)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn i32(&mut self) -> Result<i32> {
+        Ok(i32::from_be_bytes(self.take(4)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn i64(&mut self) -> Result<i64> {
+        Ok(i64::from_be_bytes(self.take(8)?.try_into().unwrap()))
+    }
+
+    pub(crate) fn str(&mut self) -> Result<String> {
+        let end =
+            self.data.iter().position(|&b| b == 0).ok_or_else(|| {
+                Error::serialization("Unterminated string in replication message")
+            })?;
+        let text = String::from_utf8_lossy(self.take(end)?).into_owned();
+        self.take(1)?;
+        Ok(text)
+    }
+}
@@ -0,0 +1,170 @@
+//! Failing fast while the database is unreachable
+//!
+//! A [`CircuitBreaker`] attached with
+//! [`Database::with_circuit_breaker`](crate::Database::with_circuit_breaker) counts statements
+//! that fail because the server can't be reached: connection checkouts that fail or time out,
+//! dropped connections and cancelled statements. After `failure_threshold` of them in a row it
+//! opens, and statements fail at once with [`Error::CircuitOpen`] instead of queueing for
+//! connections that won't come. Once `cool_down` has passed, trial statements are let through
+//! one at a time; the breaker closes after enough of them succeed and opens again on the first
+//! failure:
+//!
+//! ```rust,ignore
+//! let breaker = Arc::new(CircuitBreaker::new(5, Duration::from_secs(30)));
+//! let db = Database::init(config).await?.with_circuit_breaker(breaker.clone());
+//!
+//! match User::find_by_id(&id, &db).await {
+//!     Err(Error::CircuitOpen { retry_after, .. }) => return unavailable(retry_after),
+//!     result => result?,
+//! }
+//! ```
+//!
+//! Errors PostgreSQL answers with, such as constraint violations, show the server is up and
+//! count as successes.
+
+use crate::{Error, Result};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tracing::{info, warn};
+
+/// Where a [`CircuitBreaker`] stands
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CircuitState {
+    /// Statements run; holds the current run of failures
+    Closed { failures: u32 },
+    /// Statements fail fast until the cool-down ends
+    Open,
+    /// Trial statements run one at a time; holds the successful ones so far
+    HalfOpen { successes: u32 },
+}
+
+#[derive(Debug)]
+enum State {
+    Closed {
+        failures: u32,
+    },
+    Open {
+        until: Instant,
+    },
+    HalfOpen {
+        successes: u32,
+        trial: Option<Instant>,
+    },
+}
+
+/// Opens after consecutive connection failures, see [`crate::circuit`]
+#[derive(Debug)]
+pub struct CircuitBreaker {
+    failure_threshold: u32,
+    cool_down: Duration,
+    trial_successes: u32,
+    state: Mutex<State>,
+}
+
+impl CircuitBreaker {
+    pub fn new(failure_threshold: u32, cool_down: Duration) -> Self {
+        Self {
+            failure_threshold: failure_threshold.max(1),
+            cool_down,
+            trial_successes: 1,
+            state: Mutex::new(State::Closed { failures: 0 }),
+        }
+    }
+
+    /// Successful trial statements needed to close the breaker again
+    pub fn with_trial_successes(mut self, trial_successes: u32) -> Self {
+        self.trial_successes = trial_successes.max(1);
+        self
+    }
+
+    pub fn state(&self) -> CircuitState {
+        match *self.lock() {
+            State::Closed { failures } => CircuitState::Closed { failures },
+            State::Open { .. } => CircuitState::Open,
+            State::HalfOpen { successes, .. } => CircuitState::HalfOpen { successes },
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Let a statement run, or fail it while the breaker is open or a trial is running
+    pub(crate) fn admit(&self) -> Result<()> {
+        let mut state = self.lock();
+        let now = Instant::now();
+        if let State::Open { until } = *state {
+            if now < until {
+                return Err(Error::circuit_open(
+                    "Database unreachable, failing fast",
+                    until - now,
+                ));
+            }
+            *state = State::HalfOpen {
+                successes: 0,
+                trial: None,
+            };
+        }
+        if let State::HalfOpen { trial, .. } = &mut *state {
+            // A trial that never reported back, e.g. because it was cancelled, is given up on
+            match trial {
+                Some(started) if now.duration_since(*started) < self.cool_down => {
+                    return Err(Error::circuit_open(
+                        "Waiting for a trial statement",
+                        self.cool_down - now.duration_since(*started),
+                    ));
+                }
+                _ => *trial = Some(now),
+            }
+        }
+        Ok(())
+    }
+
+    /// Record how an admitted statement went; `outage` when the server couldn't be reached
+    pub(crate) fn record(&self, outage: bool) {
+        let mut state = self.lock();
+        match &mut *state {
+            State::Closed { failures } if outage => {
+                *failures += 1;
+                if *failures >= self.failure_threshold {
+                    warn!(failures = *failures, "Circuit breaker opened");
+                    *state = State::Open {
+                        until: Instant::now() + self.cool_down,
+                    };
+                }
+            }
+            State::Closed { failures } => *failures = 0,
+            State::HalfOpen { .. } if outage => {
+                warn!("Trial statement failed, circuit breaker opened again");
+                *state = State::Open {
+                    until: Instant::now() + self.cool_down,
+                };
+            }
+            State::HalfOpen { successes, trial } => {
+                *successes += 1;
+                *trial = None;
+                if *successes >= self.trial_successes {
+                    info!("Circuit breaker closed");
+                    *state = State::Closed { failures: 0 };
+                }
+            }
+            // Statements admitted before the breaker opened
+            State::Open { .. } => {}
+        }
+    }
+}
+
+/// Whether a statement failed because the server couldn't be reached or cancelled it
+pub(crate) fn is_outage(error: &tokio_postgres::Error) -> bool {
+    if error.is_closed() {
+        return true;
+    }
+    match error.code() {
+        // Connection exceptions, shutdowns and cancelled statements
+        Some(code) => {
+            let code = code.code();
+            code.starts_with("08") || matches!(code, "57014" | "57P01" | "57P02" | "57P03")
+        }
+        None => std::error::Error::source(error).is_some_and(|s| s.is::<std::io::Error>()),
+    }
+}
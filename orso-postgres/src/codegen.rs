@@ -0,0 +1,150 @@
+//! Model generation from an existing database
+//!
+//! Enabled with the `codegen` feature. [`generate`] reads every table in the current schema with
+//! [`Schema::tables`] and writes a Rust model for each, with the `orso_table`/`orso_column`
+//! attributes matching its keys, so a legacy database can be adopted without typing out the
+//! structs by hand:
+//!
+//! ```rust,ignore
+//! let source = orso_postgres::codegen::generate(&db).await?;
+//! std::fs::write("src/models.rs", source)?;
+//! ```
+//!
+//! Columns whose type has no Orso mapping (`jsonb`, `uuid`, `bytea`, extension types, ...) become
+//! `String` fields marked with a `TODO` comment.
+
+use crate::{ColumnInfo, Database, Result, Schema, TableInfo};
+use std::fmt::Write;
+
+/// Models for every table in the current schema, with the imports they need
+pub async fn generate(db: &Database) -> Result<String> {
+    let mut source = String::from(
+        "use orso_postgres::{orso_column, orso_table, Orso, OrsoDateTime};\n\
+         use serde::{Deserialize, Serialize};\n",
+    );
+    for table in Schema::tables(db).await? {
+        source.push('\n');
+        source.push_str(&model_source(&table));
+    }
+    Ok(source)
+}
+
+/// Model for a single table
+pub fn model_source(table: &TableInfo) -> String {
+    let mut out = String::new();
+    out.push_str("#[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]\n");
+    let _ = writeln!(out, "#[orso_table(\"{}\")]", table.name);
+    let _ = writeln!(out, "pub struct {} {{", struct_name(&table.name));
+
+    for (i, column) in table.columns.iter().enumerate() {
+        if i > 0 {
+            out.push('\n');
+        }
+        let (rust_type, note) = rust_type(column);
+
+        let mut attributes = Vec::new();
+        if column.is_primary_key {
+            attributes.push("primary_key".to_string());
+        } else if column.is_unique {
+            attributes.push("unique".to_string());
+        }
+        if let Some(reference) = &column.foreign_key_reference {
+            attributes.push(match reference.split_once('.') {
+                Some((table, column)) if column != "id" => format!("ref = \"{table}({column})\""),
+                Some((table, _)) => format!("ref = \"{table}\""),
+                None => format!("ref = \"{reference}\""),
+            });
+        }
+        let timestamp = column.sql_type.starts_with("TIMESTAMP");
+        if timestamp && (column.name == "created_at" || column.name == "updated_at") {
+            attributes.push(column.name.clone());
+        }
+
+        if let Some(note) = note {
+            let _ = writeln!(out, "    // TODO: {note}");
+        }
+        if !attributes.is_empty() {
+            let _ = writeln!(out, "    #[orso_column({})]", attributes.join(", "));
+        }
+        let _ = writeln!(out, "    pub {}: {},", field_name(&column.name), rust_type);
+    }
+
+    out.push_str("}\n");
+    out
+}
+
+/// Rust type for a column, with a note when the SQL type has no exact mapping
+fn rust_type(column: &ColumnInfo) -> (String, Option<String>) {
+    let sql_type = column.sql_type.as_str();
+    let auto_timestamp = column.name == "created_at" || column.name == "updated_at";
+
+    let base = match sql_type {
+        "TEXT" | "CHARACTER VARYING" | "CHARACTER" => Some("String"),
+        "SMALLINT" => Some("i16"),
+        "INTEGER" => Some("i32"),
+        "BIGINT" => Some("i64"),
+        "REAL" => Some("f32"),
+        "DOUBLE PRECISION" => Some("f64"),
+        "BOOLEAN" => Some("bool"),
+        "INTEGER[]" => Some("Vec<i32>"),
+        "BIGINT[]" => Some("Vec<i64>"),
+        "DOUBLE PRECISION[]" => Some("Vec<f64>"),
+        t if t.starts_with("TIMESTAMP") && auto_timestamp => Some("OrsoDateTime"),
+        t if t.starts_with("TIMESTAMP") => Some("chrono::DateTime<chrono::Utc>"),
+        _ => None,
+    };
+    let (base, note) = match base {
+        Some(base) => (base, None),
+        None => (
+            "String",
+            Some(format!("no Orso mapping for {}", sql_type.to_lowercase())),
+        ),
+    };
+
+    // Primary keys and auto-managed timestamps are filled in by Orso
+    let optional =
+        column.nullable || column.is_primary_key || (auto_timestamp && column.has_default);
+    let rust_type = if optional {
+        format!("Option<{base}>")
+    } else {
+        base.to_string()
+    };
+    (rust_type, note)
+}
+
+/// `user_accounts` -> `UserAccount`
+fn struct_name(table: &str) -> String {
+    let singular = if let Some(stem) = table.strip_suffix("ies") {
+        format!("{stem}y")
+    } else if table.ends_with("ss") || table.ends_with("us") {
+        table.to_string()
+    } else {
+        table.strip_suffix('s').unwrap_or(table).to_string()
+    };
+
+    singular
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            let mut chars = part.chars();
+            match chars.next() {
+                Some(first) => first.to_ascii_uppercase().to_string() + chars.as_str(),
+                None => String::new(),
+            }
+        })
+        .collect()
+}
+
+fn field_name(column: &str) -> String {
+    const KEYWORDS: &[&str] = &[
+        "as", "async", "await", "box", "break", "const", "continue", "crate", "dyn", "else",
+        "enum", "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod",
+        "move", "mut", "pub", "ref", "return", "static", "struct", "trait", "true", "type",
+        "unsafe", "use", "where", "while", "yield",
+    ];
+    if KEYWORDS.contains(&column) {
+        format!("r#{column}")
+    } else {
+        column.to_string()
+    }
+}
@@ -0,0 +1,1003 @@
+//! Compressed column helpers
+//!
+//! Compressed `Vec<T>` fields are stored as `BYTEA` columns holding a sequence of frames, each
+//! one wrapping an independent cydec blob (`ORSO` header):
+//!
+//! ```text
+//! "ORSF" | version: u8 | flags: u8 | reserved: u16 | count: u32 BE | payload_len: u32 BE | payload
+//! ```
+//!
+//! `insert` and `update` split an array into frames of [`FRAME_CHUNK_LEN`] values, so range
+//! reads skip the frames they don't need. New values are compressed into a fresh frame and
+//! concatenated server-side, so appending never downloads the existing data. Plain cydec blobs
+//! written by earlier versions are still read.
+//!
+//! Frames holding lossy float data set [`FLAG_PRECISION`] and store the precision as an
+//! `f64 BE` right after the header, so the blob can be decoded without the model attributes.
+
+use crate::{Error, Executor, Result, RowMap, Value};
+use std::ops::Range;
+use std::sync::Arc;
+use tracing::{debug, info};
+
+/// Magic bytes at the start of every frame
+pub const FRAME_MAGIC: &[u8; 4] = b"ORSF";
+/// Current frame format version
+pub const FRAME_VERSION: u8 = 1;
+/// Size of the fixed frame header in bytes
+pub const FRAME_HEADER_LEN: usize = 16;
+/// Number of values per frame written by `insert`, `update` and recompression
+pub const FRAME_CHUNK_LEN: usize = 4096;
+/// Element count used when the number of values in a frame is not known up front
+pub const UNKNOWN_COUNT: u32 = u32::MAX;
+/// Frame flag: the payload was compressed with a lossy float precision
+pub const FLAG_PRECISION: u8 = 0b0000_0001;
+
+/// Values decoded from a single cydec blob
+#[derive(Debug, Clone, PartialEq)]
+pub enum CompressedValues {
+    I64(Vec<i64>),
+    U64(Vec<u64>),
+    F64(Vec<f64>),
+    F32(Vec<f32>),
+}
+
+impl CompressedValues {
+    pub fn len(&self) -> usize {
+        match self {
+            CompressedValues::I64(v) => v.len(),
+            CompressedValues::U64(v) => v.len(),
+            CompressedValues::F64(v) => v.len(),
+            CompressedValues::F32(v) => v.len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Convert the values to JSON, matching what `from_map` feeds to serde
+    pub fn to_json(&self) -> Vec<serde_json::Value> {
+        fn float(f: f64) -> serde_json::Value {
+            match serde_json::Number::from_f64(f) {
+                Some(n) => serde_json::Value::Number(n),
+                None => serde_json::Value::String(f.to_string()),
+            }
+        }
+
+        match self {
+            CompressedValues::I64(v) => v.iter().map(|i| serde_json::Value::from(*i)).collect(),
+            CompressedValues::U64(v) => v.iter().map(|i| serde_json::Value::from(*i)).collect(),
+            CompressedValues::F64(v) => v.iter().map(|f| float(*f)).collect(),
+            CompressedValues::F32(v) => v.iter().map(|f| float(*f as f64)).collect(),
+        }
+    }
+}
+
+/// Element types that can be written to and read from compressed columns
+pub trait CompressedElement: Copy + Send + Sync {
+    /// Compress a slice of values into a cydec blob
+    fn compress(values: &[Self]) -> Result<Vec<u8>>;
+
+    /// Compress with an optional lossy precision; only floating types make use of it
+    fn compress_with_precision(values: &[Self], _precision: Option<f64>) -> Result<Vec<u8>> {
+        Self::compress(values)
+    }
+
+    /// Convert decoded values into this element type
+    fn from_values(values: CompressedValues) -> Vec<Self>;
+}
+
+macro_rules! impl_from_values {
+    ($ty:ty) => {
+        fn from_values(values: CompressedValues) -> Vec<Self> {
+            match values {
+                CompressedValues::I64(v) => v.into_iter().map(|x| x as $ty).collect(),
+                CompressedValues::U64(v) => v.into_iter().map(|x| x as $ty).collect(),
+                CompressedValues::F64(v) => v.into_iter().map(|x| x as $ty).collect(),
+                CompressedValues::F32(v) => v.into_iter().map(|x| x as $ty).collect(),
+            }
+        }
+    };
+}
+
+impl CompressedElement for i64 {
+    impl_from_values!(i64);
+
+    fn compress(values: &[Self]) -> Result<Vec<u8>> {
+        crate::IntegerCodec::default()
+            .compress_i64(values)
+            .map_err(|e| codec_error("integer", e))
+    }
+}
+
+impl CompressedElement for i32 {
+    impl_from_values!(i32);
+
+    fn compress(values: &[Self]) -> Result<Vec<u8>> {
+        // Stored as i64 to match the blobs written by to_map
+        let values: Vec<i64> = values.iter().map(|&v| v as i64).collect();
+        i64::compress(&values)
+    }
+}
+
+impl CompressedElement for u32 {
+    impl_from_values!(u32);
+
+    fn compress(values: &[Self]) -> Result<Vec<u8>> {
+        let values: Vec<i64> = values.iter().map(|&v| v as i64).collect();
+        i64::compress(&values)
+    }
+}
+
+impl CompressedElement for u64 {
+    impl_from_values!(u64);
+
+    fn compress(values: &[Self]) -> Result<Vec<u8>> {
+        crate::IntegerCodec::default()
+            .compress_u64(values)
+            .map_err(|e| codec_error("integer", e))
+    }
+}
+
+impl CompressedElement for f64 {
+    impl_from_values!(f64);
+
+    fn compress(values: &[Self]) -> Result<Vec<u8>> {
+        Self::compress_with_precision(values, None)
+    }
+
+    fn compress_with_precision(values: &[Self], precision: Option<f64>) -> Result<Vec<u8>> {
+        crate::FloatingCodec::default()
+            .compress_f64(values, precision)
+            .map_err(|e| codec_error("floating", e))
+    }
+}
+
+impl CompressedElement for f32 {
+    impl_from_values!(f32);
+
+    fn compress(values: &[Self]) -> Result<Vec<u8>> {
+        Self::compress_with_precision(values, None)
+    }
+
+    fn compress_with_precision(values: &[Self], precision: Option<f64>) -> Result<Vec<u8>> {
+        // Stored as f64 to match the blobs written by to_map
+        let values: Vec<f64> = values.iter().map(|&v| v as f64).collect();
+        f64::compress_with_precision(&values, precision)
+    }
+}
+
+/// A single frame borrowed from a framed blob
+#[derive(Debug, Clone, Copy)]
+pub struct Frame<'a> {
+    pub flags: u8,
+    /// Number of values in the frame, `None` for wrapped legacy blobs
+    pub count: Option<u32>,
+    /// Lossy precision the payload was compressed with
+    pub precision: Option<f64>,
+    pub payload: &'a [u8],
+}
+
+impl Frame<'_> {
+    /// Decompress the frame payload
+    pub fn decode(&self) -> Result<CompressedValues> {
+        decode_payload_with_precision(self.payload, self.precision)
+    }
+}
+
+/// Build the frame header for a payload
+pub fn frame_header(count: u32, payload_len: u32) -> Vec<u8> {
+    frame_header_with_flags(0, count, payload_len)
+}
+
+fn frame_header_with_flags(flags: u8, count: u32, payload_len: u32) -> Vec<u8> {
+    let mut header = Vec::with_capacity(FRAME_HEADER_LEN);
+    header.extend_from_slice(FRAME_MAGIC);
+    header.push(FRAME_VERSION);
+    header.push(flags);
+    header.extend_from_slice(&[0, 0]); // reserved
+    header.extend_from_slice(&count.to_be_bytes());
+    header.extend_from_slice(&payload_len.to_be_bytes());
+    header
+}
+
+/// Wrap a cydec blob into a frame
+pub fn encode_frame(count: u32, payload: &[u8]) -> Vec<u8> {
+    let mut frame = frame_header(count, payload.len() as u32);
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// Wrap a cydec blob compressed with a lossy precision into a frame
+pub fn encode_frame_with_precision(count: u32, precision: Option<f64>, payload: &[u8]) -> Vec<u8> {
+    let Some(precision) = precision else {
+        return encode_frame(count, payload);
+    };
+
+    let mut frame = frame_header_with_flags(FLAG_PRECISION, count, payload.len() as u32);
+    frame.extend_from_slice(&precision.to_be_bytes());
+    frame.extend_from_slice(payload);
+    frame
+}
+
+/// Compress values into frames of at most [`FRAME_CHUNK_LEN`] values each
+pub fn encode_chunked<V: CompressedElement>(
+    values: &[V],
+    precision: Option<f64>,
+) -> Result<Vec<u8>> {
+    encode_chunks(values, precision, |chunk| {
+        V::compress_with_precision(chunk, precision)
+    })
+}
+
+fn encode_chunks<V>(
+    values: &[V],
+    precision: Option<f64>,
+    compress: impl Fn(&[V]) -> Result<Vec<u8>>,
+) -> Result<Vec<u8>> {
+    let mut blob = Vec::new();
+    for chunk in values.chunks(FRAME_CHUNK_LEN) {
+        let payload = compress(chunk)?;
+        blob.extend(encode_frame_with_precision(
+            chunk.len() as u32,
+            precision,
+            &payload,
+        ));
+    }
+    Ok(blob)
+}
+
+/// Check whether a blob uses the framed format
+pub fn is_framed(blob: &[u8]) -> bool {
+    blob.len() >= FRAME_HEADER_LEN && &blob[0..4] == FRAME_MAGIC
+}
+
+/// Split a framed blob into its frames
+pub fn split_frames(blob: &[u8]) -> Result<Vec<Frame<'_>>> {
+    let mut frames = Vec::new();
+    let mut offset = 0;
+
+    while offset < blob.len() {
+        let header = blob
+            .get(offset..offset + FRAME_HEADER_LEN)
+            .ok_or_else(|| frame_error("Truncated frame header", offset))?;
+        if &header[0..4] != FRAME_MAGIC {
+            return Err(frame_error("Invalid frame magic", offset));
+        }
+        if header[4] != FRAME_VERSION {
+            return Err(frame_error(
+                format!("Unsupported frame version {}", header[4]),
+                offset,
+            ));
+        }
+
+        let flags = header[5];
+        let count = u32::from_be_bytes([header[8], header[9], header[10], header[11]]);
+        let len = u32::from_be_bytes([header[12], header[13], header[14], header[15]]) as usize;
+        let mut start = offset + FRAME_HEADER_LEN;
+
+        let mut precision = None;
+        if flags & FLAG_PRECISION != 0 {
+            let bytes = blob
+                .get(start..start + 8)
+                .ok_or_else(|| frame_error("Truncated frame precision", offset))?;
+            precision = Some(f64::from_be_bytes(bytes.try_into().expect("8 bytes")));
+            start += 8;
+        }
+
+        let payload = blob
+            .get(start..start + len)
+            .ok_or_else(|| frame_error("Truncated frame payload", offset))?;
+
+        frames.push(Frame {
+            flags,
+            count: (count != UNKNOWN_COUNT).then_some(count),
+            precision,
+            payload,
+        });
+        offset = start + len;
+    }
+
+    Ok(frames)
+}
+
+/// Decompress a single cydec blob, using the type tag in its `ORSO` header
+pub fn decode_payload(payload: &[u8]) -> Result<CompressedValues> {
+    decode_payload_with_precision(payload, None)
+}
+
+/// Decompress a cydec blob that was compressed with a lossy float precision
+pub fn decode_payload_with_precision(
+    payload: &[u8],
+    precision: Option<f64>,
+) -> Result<CompressedValues> {
+    let tag = if payload.len() >= 7 && &payload[0..4] == b"ORSO" {
+        payload[6]
+    } else {
+        0
+    };
+
+    match tag {
+        1 | 3 => crate::IntegerCodec::default()
+            .decompress_u64(payload)
+            .map(CompressedValues::U64)
+            .map_err(|e| codec_error("integer", e)),
+        4 => crate::FloatingCodec::default()
+            .decompress_f64(payload, precision)
+            .map(CompressedValues::F64)
+            .map_err(|e| codec_error("floating", e)),
+        5 => crate::FloatingCodec::default()
+            .decompress_f32(payload, precision.map(|p| p as f32))
+            .map(CompressedValues::F32)
+            .map_err(|e| codec_error("floating", e)),
+        _ => crate::IntegerCodec::default()
+            .decompress_i64(payload)
+            .map(CompressedValues::I64)
+            .map_err(|e| codec_error("integer", e)),
+    }
+}
+
+/// Decode a framed blob into the JSON array `from_map` deserializes
+pub fn decode_framed_json(blob: &[u8]) -> Result<serde_json::Value> {
+    let mut values = Vec::new();
+    for frame in split_frames(blob)? {
+        values.extend(frame.decode()?.to_json());
+    }
+    Ok(serde_json::Value::Array(values))
+}
+
+/// Decode only the values in `range` from a stored blob
+///
+/// Frames that lie entirely outside the range are skipped without being decompressed.
+/// Plain cydec blobs (and wrapped legacy frames) carry no element count and are
+/// decoded in full before slicing.
+pub fn decode_range<V: CompressedElement>(blob: &[u8], range: Range<usize>) -> Result<Vec<V>> {
+    let mut result = Vec::new();
+    if range.start >= range.end || blob.is_empty() {
+        return Ok(result);
+    }
+
+    if !is_framed(blob) {
+        let values = V::from_values(decode_payload(blob)?);
+        let end = range.end.min(values.len());
+        if range.start < end {
+            result.extend_from_slice(&values[range.start..end]);
+        }
+        return Ok(result);
+    }
+
+    let mut offset = 0usize;
+    for frame in split_frames(blob)? {
+        if offset >= range.end {
+            break;
+        }
+
+        if let Some(count) = frame.count {
+            let count = count as usize;
+            if offset + count <= range.start {
+                offset += count;
+                continue;
+            }
+        }
+
+        let values = V::from_values(frame.decode()?);
+        let start = range.start.saturating_sub(offset).min(values.len());
+        let end = (range.end - offset).min(values.len());
+        result.extend_from_slice(&values[start..end]);
+        offset += values.len();
+    }
+
+    Ok(result)
+}
+
+/// Re-encode decoded values with the codecs of `options`, in frames of [`FRAME_CHUNK_LEN`]
+/// values like the ones `insert` writes
+fn reencode(
+    values: CompressedValues,
+    precision: Option<f64>,
+    options: &RecompressOptions,
+) -> Result<Vec<u8>> {
+    let integer = |v: &[i64]| codec_result("integer", options.integer_codec.compress_i64(v));
+    let floating = |v: &[f64]| {
+        codec_result(
+            "floating",
+            options.floating_codec.compress_f64(v, precision),
+        )
+    };
+    match values {
+        CompressedValues::I64(v) => encode_chunks(&v, None, integer),
+        CompressedValues::U64(v) => encode_chunks(&v, None, |v| {
+            codec_result("integer", options.integer_codec.compress_u64(v))
+        }),
+        CompressedValues::F64(v) => encode_chunks(&v, precision, floating),
+        // Stored as f64 to match the blobs written by to_map
+        CompressedValues::F32(v) => {
+            let v: Vec<f64> = v.into_iter().map(f64::from).collect();
+            encode_chunks(&v, precision, floating)
+        }
+    }
+}
+
+/// Decode a stored blob, framed or plain, into a single set of values
+fn decode_blob(blob: &[u8]) -> Result<CompressedValues> {
+    if !is_framed(blob) {
+        return decode_payload(blob);
+    }
+
+    let mut frames = split_frames(blob)?.into_iter();
+    let mut values = match frames.next() {
+        Some(frame) => frame.decode()?,
+        None => return Ok(CompressedValues::I64(Vec::new())),
+    };
+    for frame in frames {
+        match (&mut values, frame.decode()?) {
+            (CompressedValues::I64(a), CompressedValues::I64(b)) => a.extend(b),
+            (CompressedValues::U64(a), CompressedValues::U64(b)) => a.extend(b),
+            (CompressedValues::F64(a), CompressedValues::F64(b)) => a.extend(b),
+            (CompressedValues::F32(a), CompressedValues::F32(b)) => a.extend(b),
+            (CompressedValues::F64(a), CompressedValues::F32(b)) => {
+                a.extend(b.into_iter().map(|x| x as f64))
+            }
+            (CompressedValues::I64(a), CompressedValues::U64(b)) => {
+                a.extend(b.into_iter().map(|x| x as i64))
+            }
+            _ => {
+                return Err(Error::Compression {
+                    message: "Frames in the same blob hold incompatible value types".to_string(),
+                    algorithm: "frame".to_string(),
+                    source: None,
+                })
+            }
+        }
+    }
+    Ok(values)
+}
+
+/// Progress of a [`Compression::recompress_table`] run
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecompressProgress {
+    /// Rows read so far
+    pub rows_scanned: u64,
+    /// Rows whose blobs were rewritten
+    pub rows_rewritten: u64,
+    /// Size of the rewritten blobs before and after recompression
+    pub bytes_before: u64,
+    pub bytes_after: u64,
+}
+
+type ProgressCallback = Arc<dyn Fn(&RecompressProgress) + Send + Sync>;
+
+/// Options for [`Compression::recompress_table`]
+#[derive(Clone)]
+pub struct RecompressOptions {
+    pub batch_size: usize,
+    /// Columns to rewrite, all compressed fields when `None`
+    pub columns: Option<Vec<String>>,
+    /// Precision to use instead of the one declared on the model
+    pub precision: Option<f64>,
+    /// Codecs the blobs are re-encoded with, e.g. configured with another compression level
+    pub integer_codec: crate::IntegerCodec,
+    pub floating_codec: crate::FloatingCodec,
+    on_progress: Option<ProgressCallback>,
+}
+
+impl Default for RecompressOptions {
+    fn default() -> Self {
+        Self {
+            batch_size: 500,
+            columns: None,
+            precision: None,
+            integer_codec: crate::IntegerCodec::default(),
+            floating_codec: crate::FloatingCodec::default(),
+            on_progress: None,
+        }
+    }
+}
+
+impl std::fmt::Debug for RecompressOptions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RecompressOptions")
+            .field("batch_size", &self.batch_size)
+            .field("columns", &self.columns)
+            .field("precision", &self.precision)
+            .field("integer_codec", &self.integer_codec)
+            .field("floating_codec", &self.floating_codec)
+            .field("on_progress", &self.on_progress.is_some())
+            .finish()
+    }
+}
+
+impl RecompressOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn with_columns(mut self, columns: &[&str]) -> Self {
+        self.columns = Some(columns.iter().map(|c| c.to_string()).collect());
+        self
+    }
+
+    pub fn with_precision(mut self, precision: f64) -> Self {
+        self.precision = Some(precision);
+        self
+    }
+
+    pub fn with_integer_codec(mut self, codec: crate::IntegerCodec) -> Self {
+        self.integer_codec = codec;
+        self
+    }
+
+    pub fn with_floating_codec(mut self, codec: crate::FloatingCodec) -> Self {
+        self.floating_codec = codec;
+        self
+    }
+
+    /// Called after every batch with the totals so far
+    pub fn on_progress(
+        mut self,
+        callback: impl Fn(&RecompressProgress) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_progress = Some(Arc::new(callback));
+        self
+    }
+}
+
+/// Operations on compressed columns
+pub struct Compression;
+
+impl Compression {
+    /// Append values to a compressed column without reading the stored blob
+    pub async fn append_compressed<T, V>(
+        column: &str,
+        values: &[V],
+        id: &str,
+        db: &impl Executor,
+    ) -> Result<()>
+    where
+        T: crate::Orso,
+        V: CompressedElement,
+    {
+        Self::append_compressed_with_table::<T, V>(column, values, id, db, T::table_name()).await
+    }
+
+    pub async fn append_compressed_with_table<T, V>(
+        column: &str,
+        values: &[V],
+        id: &str,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<()>
+    where
+        T: crate::Orso,
+        V: CompressedElement,
+    {
+        Self::ensure_compressed_column::<T>(column)?;
+        if values.is_empty() {
+            return Ok(());
+        }
+
+        let precision = Self::column_precision::<T>(column);
+        let frame = encode_frame_with_precision(
+            values.len() as u32,
+            precision,
+            &V::compress_with_precision(values, precision)?,
+        );
+
+        // Existing blobs written by to_map are wrapped into a frame of unknown length on the fly
+        let mut legacy_prefix = frame_header(UNKNOWN_COUNT, 0);
+        legacy_prefix.truncate(FRAME_HEADER_LEN - 4);
+
+        let sql = format!(
+            "UPDATE {table} SET {col} = CASE \
+             WHEN {col} IS NULL OR octet_length({col}) = 0 THEN $1 \
+             WHEN substring({col} from 1 for 4) = $2 THEN {col} || $1 \
+             ELSE $3 || int4send(octet_length({col})) || {col} || $1 END \
+             WHERE {pk} = $4",
+            table = table_name,
+            col = column,
+            pk = T::primary_key_field()
+        );
+
+        let params: Vec<Box<dyn tokio_postgres::types::ToSql + Send + Sync>> = vec![
+            Box::new(frame),
+            Box::new(FRAME_MAGIC.to_vec()),
+            Box::new(legacy_prefix),
+            crate::operations::key_param::<T>(id)?,
+        ];
+        let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
+            params.iter().map(|p| p.as_ref()).collect();
+
+        let updated = db.execute(&sql, &param_refs).await?;
+        if updated == 0 {
+            return Err(Error::not_found_record(
+                "No record to append compressed values to",
+                table_name,
+                id,
+            ));
+        }
+
+        debug!(
+            table = table_name,
+            column = column,
+            appended = values.len(),
+            "Appended compressed values"
+        );
+        Ok(())
+    }
+
+    /// Read a slice of a compressed column, e.g. `1000..2000`
+    ///
+    /// Takes two round trips: the first walks the frame headers on the server, the second
+    /// fetches only the frames the range touches, which are the only ones decompressed. A
+    /// frame is decoded whole, so the cost is that of the frames overlapping the range. Plain
+    /// blobs written by earlier versions and frames wrapping them carry no element count: they,
+    /// and everything after them, are fetched and decoded like a full read.
+    pub async fn read_compressed_range<T, V>(
+        id: &str,
+        column: &str,
+        range: Range<usize>,
+        db: &impl Executor,
+    ) -> Result<Vec<V>>
+    where
+        T: crate::Orso,
+        V: CompressedElement,
+    {
+        Self::read_compressed_range_with_table::<T, V>(id, column, range, db, T::table_name()).await
+    }
+
+    pub async fn read_compressed_range_with_table<T, V>(
+        id: &str,
+        column: &str,
+        range: Range<usize>,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<Vec<V>>
+    where
+        T: crate::Orso,
+        V: CompressedElement,
+    {
+        Self::ensure_compressed_column::<T>(column)?;
+        let pk = T::primary_key_field();
+        let magic = FRAME_MAGIC.to_vec();
+
+        // Frame offsets, flags, counts and payload lengths, read by walking the headers
+        let header = FRAME_HEADER_LEN;
+        let sql = format!(
+            "WITH RECURSIVE stored AS (\
+             SELECT {column} AS blob FROM {table_name} WHERE {pk} = $1\
+             ), frames(off, flags, count, len) AS (\
+             SELECT 0::int8, get_byte(blob, 5), {}, {} FROM stored \
+             WHERE octet_length(blob) >= {header} AND substring(blob from 1 for 4) = $2 \
+             UNION ALL \
+             SELECT next.off, get_byte(blob, next.off::int4 + 5), {}, {} \
+             FROM frames, stored, LATERAL (SELECT frames.off + {header} \
+             + (frames.flags & {FLAG_PRECISION}) * 8 + frames.len AS off) next \
+             WHERE next.off + {header} <= octet_length(blob)\
+             ) SELECT blob IS NULL AS missing, substring(blob from 1 for 4) = $2 AS framed, \
+             off, count, len, flags \
+             FROM stored LEFT JOIN frames ON true ORDER BY off",
+            be_u32("0", 8),
+            be_u32("0", 12),
+            be_u32("next.off", 8),
+            be_u32("next.off", 12),
+        );
+        let key = crate::operations::key_param::<T>(id)?;
+        let rows = db.query_rows(&sql, &[key.as_ref(), &magic]).await?;
+        let Some(first) = rows.first() else {
+            return Err(Error::not_found_record(
+                "No record to read compressed values from",
+                table_name,
+                id,
+            ));
+        };
+        if bool_column(first, "missing")?.unwrap_or(true) || range.start >= range.end {
+            return Ok(Vec::new());
+        }
+        if !bool_column(first, "framed")?.unwrap_or(false) {
+            // A plain blob has no element count to skip by
+            let sql = format!("SELECT {column} AS blob FROM {table_name} WHERE {pk} = $1");
+            let rows = db.query_rows(&sql, &[key.as_ref()]).await?;
+            let blob = rows.first().map(|row| blob_column(row, "blob"));
+            return decode_range(&blob.transpose()?.flatten().unwrap_or_default(), range);
+        }
+
+        // Skip the frames before the range and stop after it, as far as counts are known
+        let mut skipped = 0usize;
+        let mut offset = Some(0usize);
+        let mut span: Option<(i64, i64)> = None;
+        for row in &rows {
+            // Too short to hold a frame header
+            let Some(off) = int_column(row, "off")? else {
+                break;
+            };
+            if offset.is_some_and(|start| start >= range.end) {
+                break;
+            }
+            let count = int_column(row, "count")?.unwrap_or_default() as u32;
+            let count = (count != UNKNOWN_COUNT).then_some(count as usize);
+            let flags = int_column(row, "flags")?.unwrap_or_default();
+            let precision_len = (flags & FLAG_PRECISION as i64) * 8;
+            let end = off
+                + FRAME_HEADER_LEN as i64
+                + precision_len
+                + int_column(row, "len")?.unwrap_or_default();
+
+            match (offset, count) {
+                (Some(start), Some(count)) if span.is_none() && start + count <= range.start => {
+                    skipped += count;
+                }
+                _ => span = Some((span.map_or(off, |(first, _)| first), end)),
+            }
+            offset = offset.zip(count).map(|(start, count)| start + count);
+        }
+        let Some((first, end)) = span else {
+            return Ok(Vec::new());
+        };
+
+        let sql = format!(
+            "SELECT substring({column} from $2 for $3) AS blob FROM {table_name} WHERE {pk} = $1"
+        );
+        let (from, length) = (first as i32 + 1, (end - first) as i32);
+        let rows = db.query_rows(&sql, &[key.as_ref(), &from, &length]).await?;
+        let blob = rows.first().map(|row| blob_column(row, "blob"));
+        decode_range(
+            &blob.transpose()?.flatten().unwrap_or_default(),
+            range.start.saturating_sub(skipped)..range.end - skipped,
+        )
+    }
+
+    /// Rewrite the compressed columns of every row with the current settings
+    ///
+    /// Rows are walked in primary key order, `batch_size` at a time. Each blob is decoded
+    /// and re-encoded into frames of [`FRAME_CHUNK_LEN`] values with the codecs of `options`
+    /// and the precision declared on the model (or the one set in `options`), which also merges
+    /// the short frames left behind by appends and splits plain blobs of earlier versions.
+    pub async fn recompress_table<T>(
+        db: &impl Executor,
+        options: RecompressOptions,
+    ) -> Result<RecompressProgress>
+    where
+        T: crate::Orso,
+    {
+        Self::recompress_table_with_table::<T>(db, options, T::table_name()).await
+    }
+
+    pub async fn recompress_table_with_table<T>(
+        db: &impl Executor,
+        options: RecompressOptions,
+        table_name: &str,
+    ) -> Result<RecompressProgress>
+    where
+        T: crate::Orso,
+    {
+        let columns: Vec<String> = match &options.columns {
+            Some(columns) => {
+                for column in columns {
+                    Self::ensure_compressed_column::<T>(column)?;
+                }
+                columns.clone()
+            }
+            None => T::field_names()
+                .into_iter()
+                .zip(T::field_compressed())
+                .filter(|(_, compressed)| *compressed)
+                .map(|(name, _)| name.to_string())
+                .collect(),
+        };
+
+        let mut progress = RecompressProgress::default();
+        if columns.is_empty() {
+            return Ok(progress);
+        }
+
+        let precisions: Vec<Option<f64>> = columns
+            .iter()
+            .map(|column| options.precision.or(Self::column_precision::<T>(column)))
+            .collect();
+        let pk = T::primary_key_field();
+        let batch_size = options.batch_size.max(1);
+        let mut last_key: Option<String> = None;
+
+        // Keys travel as text and are cast back, so integer and UUID keys keep their order
+        let key_type = db
+            .query_rows(
+                "SELECT format_type(atttypid, atttypmod) AS key_type FROM pg_attribute \
+                 WHERE attrelid = to_regclass($1) AND attname = $2",
+                &[&table_name, &pk],
+            )
+            .await?
+            .first()
+            .map(|row| text_column(row, "key_type"))
+            .transpose()?
+            .flatten()
+            .ok_or_else(|| Error::validation_field("Primary key column not found", pk, None))?;
+
+        loop {
+            let sql = format!(
+                "SELECT {pk}::text AS {pk}, {cols} FROM {table} {filter} \
+                 ORDER BY {table}.{pk} LIMIT {limit}",
+                pk = pk,
+                cols = columns.join(", "),
+                table = table_name,
+                filter = if last_key.is_some() {
+                    format!("WHERE {pk} > $1::text::{key_type}")
+                } else {
+                    String::new()
+                },
+                limit = batch_size
+            );
+
+            let rows = match &last_key {
+                Some(key) => db.query_rows(&sql, &[key]).await?,
+                None => db.query_rows(&sql, &[]).await?,
+            };
+
+            for row in &rows {
+                let key = text_column(row, pk)?.unwrap_or_default();
+                progress.rows_scanned += 1;
+
+                let mut assignments = Vec::new();
+                let mut params: Vec<Box<dyn tokio_postgres::types::ToSql + Send + Sync>> =
+                    Vec::new();
+
+                for (i, column) in columns.iter().enumerate() {
+                    let Some(blob) = blob_column(row, column)?.filter(|b| !b.is_empty()) else {
+                        continue;
+                    };
+
+                    let rewritten = reencode(decode_blob(&blob)?, precisions[i], &options)?;
+                    if rewritten == blob {
+                        continue;
+                    }
+
+                    progress.bytes_before += blob.len() as u64;
+                    progress.bytes_after += rewritten.len() as u64;
+                    params.push(Box::new(rewritten));
+                    assignments.push(format!("{} = ${}", column, params.len()));
+                }
+
+                if assignments.is_empty() {
+                    last_key = Some(key);
+                    continue;
+                }
+
+                let update_sql = format!(
+                    "UPDATE {} SET {} WHERE {} = ${}::text::{}",
+                    table_name,
+                    assignments.join(", "),
+                    pk,
+                    params.len() + 1,
+                    key_type
+                );
+                params.push(Box::new(key.clone()));
+                let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
+                    params.iter().map(|p| p.as_ref()).collect();
+
+                db.execute(&update_sql, &param_refs).await?;
+                progress.rows_rewritten += 1;
+                last_key = Some(key);
+            }
+
+            if let Some(callback) = &options.on_progress {
+                callback(&progress);
+            }
+
+            if rows.len() < batch_size {
+                break;
+            }
+        }
+
+        info!(
+            table = table_name,
+            rows_scanned = progress.rows_scanned,
+            rows_rewritten = progress.rows_rewritten,
+            bytes_before = progress.bytes_before,
+            bytes_after = progress.bytes_after,
+            "Recompressed table"
+        );
+        Ok(progress)
+    }
+
+    fn column_precision<T: crate::Orso>(column: &str) -> Option<f64> {
+        T::field_names()
+            .iter()
+            .position(|name| *name == column)
+            .and_then(|pos| T::field_compression_precision().get(pos).copied().flatten())
+    }
+
+    fn ensure_compressed_column<T: crate::Orso>(column: &str) -> Result<()> {
+        let compressed = T::field_names()
+            .iter()
+            .zip(T::field_compressed())
+            .any(|(name, compressed)| *name == column && compressed);
+
+        if compressed {
+            Ok(())
+        } else {
+            Err(Error::validation_field(
+                "Column is not a compressed field",
+                column,
+                None,
+            ))
+        }
+    }
+}
+
+fn int_column(row: &RowMap, column: &str) -> Result<Option<i64>> {
+    match row.get(column) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Integer(value)) => Ok(Some(*value)),
+        Some(other) => Err(column_error(column, "an integer", other)),
+    }
+}
+
+fn bool_column(row: &RowMap, column: &str) -> Result<Option<bool>> {
+    match row.get(column) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Boolean(value)) => Ok(Some(*value)),
+        Some(other) => Err(column_error(column, "a boolean", other)),
+    }
+}
+
+fn text_column(row: &RowMap, column: &str) -> Result<Option<String>> {
+    match row.get(column) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Text(value)) => Ok(Some(value.clone())),
+        Some(other) => Err(column_error(column, "text", other)),
+    }
+}
+
+fn blob_column(row: &RowMap, column: &str) -> Result<Option<Vec<u8>>> {
+    match row.get(column) {
+        None | Some(Value::Null) => Ok(None),
+        Some(Value::Blob(value)) => Ok(Some(value.clone())),
+        Some(other) => Err(column_error(column, "a blob", other)),
+    }
+}
+
+fn column_error(column: &str, expected: &str, found: &Value) -> Error {
+    Error::query(format!(
+        "Expected {expected} in column {column}, found {found:?}"
+    ))
+}
+
+/// SQL reading the big-endian `u32` at byte `at + offset` of `blob` as `int8`
+fn be_u32(at: &str, offset: usize) -> String {
+    let byte = |i: usize| format!("get_byte(blob, {at}::int4 + {})", offset + i);
+    format!(
+        "(({}::int8 << 24) | ({} << 16) | ({} << 8) | {})",
+        byte(0),
+        byte(1),
+        byte(2),
+        byte(3)
+    )
+}
+
+fn codec_result<T, E: std::fmt::Debug>(
+    algorithm: &str,
+    result: std::result::Result<T, E>,
+) -> Result<T> {
+    result.map_err(|e| codec_error(algorithm, e))
+}
+
+fn codec_error(algorithm: &str, err: impl std::fmt::Debug) -> Error {
+    Error::Compression {
+        message: format!("{:?}", err),
+        algorithm: algorithm.to_string(),
+        source: None,
+    }
+}
+
+fn frame_error(message: impl Into<String>, offset: usize) -> Error {
+    Error::Compression {
+        message: format!("{} at byte {}", message.into(), offset),
+        algorithm: "frame".to_string(),
+        source: None,
+    }
+}
@@ -0,0 +1,156 @@
+//! Per-operation context
+//!
+//! An [`OperationContext`] carries the actor performing a write. While a context is active,
+//! `CrudOperations` fills `#[orso_column(created_by)]` and `#[orso_column(updated_by)]`
+//! columns with the actor automatically.
+//!
+//! ```rust,ignore
+//! let ctx = OperationContext::new("user-42");
+//! ctx.scope(async {
+//!     invoice.insert(&db).await?; // created_by = updated_by = "user-42"
+//!     order.update(&db).await     // updated_by = "user-42"
+//! })
+//! .await?;
+//! ```
+//!
+//! A [`ReadContext`] decides whether `#[orso_column(masked)]` columns are read redacted, which
+//! they are by default, or in full:
+//!
+//! ```rust,ignore
+//! let user = User::find_by_id(&id, &db).await?; // email: "***@example.com"
+//! let user = ReadContext::revealed()
+//!     .scope(User::find_by_id(&id, &db))
+//!     .await?; // email: "ada@example.com"
+//! ```
+//!
+//! Updates leave masked columns alone unless they run in a revealed context, so a record read
+//! redacted can be saved without overwriting the real values.
+
+use crate::Value;
+use std::collections::HashMap;
+use std::future::Future;
+
+tokio::task_local! {
+    static CURRENT_CONTEXT: OperationContext;
+    static CURRENT_READ_CONTEXT: ReadContext;
+}
+
+/// Who is performing the current database operations
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OperationContext {
+    pub actor: String,
+}
+
+impl OperationContext {
+    pub fn new(actor: impl Into<String>) -> Self {
+        Self {
+            actor: actor.into(),
+        }
+    }
+
+    /// Run `future` with this context active for the current task
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        CURRENT_CONTEXT.scope(self, future).await
+    }
+
+    /// The context active for the current task, if any
+    pub fn current() -> Option<OperationContext> {
+        CURRENT_CONTEXT.try_with(|ctx| ctx.clone()).ok()
+    }
+}
+
+/// Fill the audit columns of `T` from the active context
+///
+/// Inserts set both `created_by` and `updated_by`; updates only touch `updated_by` and
+/// never clear an existing `created_by`.
+pub(crate) fn apply_actor<T: crate::Orso>(map: &mut HashMap<String, Value>, inserting: bool) {
+    if !inserting {
+        if let Some(field) = T::created_by_field() {
+            if matches!(map.get(field), Some(Value::Null)) {
+                map.remove(field);
+            }
+        }
+    }
+
+    let Some(ctx) = OperationContext::current() else {
+        return;
+    };
+
+    if inserting {
+        if let Some(field) = T::created_by_field() {
+            map.insert(field.to_string(), Value::Text(ctx.actor.clone()));
+        }
+    }
+    if let Some(field) = T::updated_by_field() {
+        map.insert(field.to_string(), Value::Text(ctx.actor));
+    }
+}
+
+/// How records are read in the current task
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ReadContext {
+    /// Return `#[orso_column(masked)]` columns in full instead of redacted
+    pub reveal_masked: bool,
+}
+
+impl ReadContext {
+    /// Context reading masked columns in full
+    pub fn revealed() -> Self {
+        Self {
+            reveal_masked: true,
+        }
+    }
+
+    /// Run `future` with this context active for the current task
+    pub async fn scope<F: Future>(self, future: F) -> F::Output {
+        CURRENT_READ_CONTEXT.scope(self, future).await
+    }
+
+    /// The context active for the current task, or the default one
+    pub fn current() -> ReadContext {
+        CURRENT_READ_CONTEXT
+            .try_with(|ctx| *ctx)
+            .unwrap_or_default()
+    }
+}
+
+/// Redact `value`, keeping the domain of an email or the last four characters of a long value
+pub fn mask(value: &str) -> String {
+    if let Some((_, domain)) = value.rsplit_once('@') {
+        return format!("***@{domain}");
+    }
+    let chars: Vec<char> = value.chars().collect();
+    if chars.len() > 8 {
+        let tail: String = chars[chars.len() - 4..].iter().collect();
+        format!("***{tail}")
+    } else {
+        "***".to_string()
+    }
+}
+
+/// Redact the text column of `field` in place unless the read context reveals it
+pub fn mask_field(map: &mut HashMap<String, Value>, field: &str) {
+    if ReadContext::current().reveal_masked {
+        return;
+    }
+    match map.get_mut(field) {
+        Some(Value::Text(text)) => *text = mask(text),
+        Some(Value::Json(serde_json::Value::String(text))) => *text = mask(text),
+        _ => {}
+    }
+}
+
+/// The masked columns of `T` updates must leave alone, none in a revealed read context
+pub(crate) fn kept_masked_fields<T: crate::Orso>() -> Vec<&'static str> {
+    if ReadContext::current().reveal_masked {
+        return Vec::new();
+    }
+    T::masked_fields()
+}
+
+/// Leave the masked columns of `T` out of an update unless the read context reveals them
+pub(crate) fn keep_masked<T: crate::Orso>(map: &mut HashMap<String, Value>) {
+    for field in kept_masked_fields::<T>() {
+        map.remove(field);
+    }
+}
@@ -0,0 +1,144 @@
+//! Per-connection credentials
+//!
+//! A [`CredentialsProvider`] supplies the password each time the pool opens a connection, for
+//! short-lived credentials such as AWS RDS IAM authentication tokens or Vault dynamic secrets.
+//! Combined with a maximum connection lifetime, connections are replaced, and fresh credentials
+//! fetched, before the old ones expire. RDS only accepts IAM tokens over TLS, so its
+//! connections need a connector, see [`crate::tls`]:
+//!
+//! ```rust,ignore
+//! let config = DatabaseConfig::new("host=db.example.com user=app dbname=app sslmode=require")
+//!     .with_tls(postgres_native_tls::MakeTlsConnector::new(TlsConnector::new()?))
+//!     .with_credentials(move || {
+//!         let signer = signer.clone();
+//!         async move { signer.rds_auth_token("db.example.com", 5432, "app").await }
+//!     })
+//!     .with_max_connection_lifetime(Duration::from_secs(10 * 60));
+//! ```
+//!
+//! When the provider fails, no connection is attempted: the statement that needed one fails with
+//! the provider's error.
+
+use crate::tls::{Connection, Tls};
+use crate::{Error, Result};
+use futures_util::future::BoxFuture;
+use std::future::Future;
+use std::sync::Arc;
+use tokio::task::JoinHandle;
+use tracing::error;
+
+/// Source of the password used for new connections
+#[async_trait::async_trait]
+pub trait CredentialsProvider: Send + Sync {
+    async fn password(&self) -> Result<String>;
+}
+
+#[async_trait::async_trait]
+impl<F, Fut> CredentialsProvider for F
+where
+    F: Fn() -> Fut + Send + Sync,
+    Fut: Future<Output = Result<String>> + Send,
+{
+    async fn password(&self) -> Result<String> {
+        self().await
+    }
+}
+
+/// Shared handle to a [`CredentialsProvider`], compared by identity
+#[derive(Clone)]
+pub struct Credentials(Arc<dyn CredentialsProvider>);
+
+impl Credentials {
+    pub fn new(provider: impl CredentialsProvider + 'static) -> Self {
+        Self(Arc::new(provider))
+    }
+
+    pub async fn password(&self) -> Result<String> {
+        self.0.password().await
+    }
+}
+
+impl std::fmt::Debug for Credentials {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("Credentials(..)")
+    }
+}
+
+impl PartialEq for Credentials {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Eq for Credentials {}
+
+/// Provider errors of pool connections, which deadpool can only report as a
+/// `tokio_postgres::Error`; `Database` returns them in place of that
+pub(crate) type CredentialFailures = Arc<std::sync::Mutex<Option<Error>>>;
+
+/// Opens connections with the configured TLS connector and, if a provider is set, a password
+/// fetched from it
+pub(crate) struct Connector {
+    pub(crate) credentials: Option<Credentials>,
+    pub(crate) tls: Tls,
+    pub(crate) failures: CredentialFailures,
+}
+
+impl Connector {
+    pub(crate) fn new(config: &crate::DatabaseConfig) -> Self {
+        Self {
+            credentials: config.credentials.clone(),
+            tls: config.tls.clone().unwrap_or_default(),
+            failures: Arc::default(),
+        }
+    }
+
+    /// `pg_config` with the password from the provider, if one is set
+    pub(crate) async fn authenticate(
+        &self,
+        mut pg_config: tokio_postgres::Config,
+    ) -> Result<tokio_postgres::Config> {
+        if let Some(credentials) = &self.credentials {
+            let password = credentials.password().await.inspect_err(|e| {
+                error!(error = %e, "Failed to fetch database credentials");
+            })?;
+            pg_config.password(password);
+        }
+        Ok(pg_config)
+    }
+
+    pub(crate) async fn open(&self, pg_config: tokio_postgres::Config) -> Result<Connection> {
+        let pg_config = self.authenticate(pg_config).await?;
+        Ok(self.tls.connect(pg_config).await?)
+    }
+}
+
+/// Stands in for a provider error where only a `tokio_postgres::Error` can be returned
+fn credentials_failed() -> tokio_postgres::Error {
+    "orso_credentials=failed"
+        .parse::<tokio_postgres::Config>()
+        .expect_err("unknown options don't parse")
+}
+
+impl deadpool_postgres::Connect for Connector {
+    fn connect(
+        &self,
+        pg_config: &tokio_postgres::Config,
+    ) -> BoxFuture<
+        '_,
+        std::result::Result<(tokio_postgres::Client, JoinHandle<()>), tokio_postgres::Error>,
+    > {
+        let pg_config = pg_config.clone();
+        Box::pin(async move {
+            let pg_config = match self.authenticate(pg_config).await {
+                Ok(pg_config) => pg_config,
+                Err(e) => {
+                    *self.failures.lock().unwrap_or_else(|e| e.into_inner()) = Some(e);
+                    return Err(credentials_failed());
+                }
+            };
+            let connection = self.tls.connect(pg_config).await?;
+            Ok((connection.client, connection.handle))
+        })
+    }
+}
@@ -0,0 +1,84 @@
+//! Custom column types
+//!
+//! Fields marked `#[orso_column(custom)]` are stored through their [`OrsoType`] implementation
+//! instead of the built-in type mapping, so downstream crates can give their own newtypes a
+//! column of their choice:
+//!
+//! ```rust,ignore
+//! #[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+//! struct SymbolId(u32);
+//!
+//! impl OrsoType for SymbolId {
+//!     fn sql_type() -> &'static str {
+//!         "INTEGER"
+//!     }
+//!
+//!     fn to_value(&self) -> Value {
+//!         Value::Integer(self.0 as i64)
+//!     }
+//!
+//!     fn from_value(value: Value) -> Result<Self> {
+//!         match value {
+//!             Value::Integer(id) => Ok(SymbolId(id as u32)),
+//!             other => Err(Error::serialization(format!("Expected an INTEGER, got {other:?}"))),
+//!         }
+//!     }
+//! }
+//!
+//! #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+//! #[orso_table("trades")]
+//! struct Trade {
+//!     #[orso_column(primary_key)]
+//!     id: Option<String>,
+//!     #[orso_column(custom)]
+//!     symbol: SymbolId,
+//!     #[orso_column(custom)]
+//!     hedge: Option<SymbolId>,
+//! }
+//! ```
+//!
+//! Spell `sql_type` the way `information_schema.columns` reports it (`NUMERIC`, `BIGINT`,
+//! `TIMESTAMP WITH TIME ZONE`, ...), or migrations will see a type change on every run.
+//!
+//! The functions below are used by the derive.
+
+use crate::{Result, RowMap, Value};
+use serde::Serialize;
+
+/// A Rust type stored in a column through its own conversion to and from [`Value`]
+pub trait OrsoType: Sized {
+    /// Column type used in `CREATE TABLE`, e.g. `BIGINT`
+    fn sql_type() -> &'static str;
+    fn to_value(&self) -> Value;
+    fn from_value(value: Value) -> Result<Self>;
+}
+
+/// `NULL` for `None`, otherwise the inner value
+impl<T: OrsoType> OrsoType for Option<T> {
+    fn sql_type() -> &'static str {
+        T::sql_type()
+    }
+
+    fn to_value(&self) -> Value {
+        match self {
+            Some(value) => value.to_value(),
+            None => Value::Null,
+        }
+    }
+
+    fn from_value(value: Value) -> Result<Self> {
+        match value {
+            Value::Null => Ok(None),
+            value => T::from_value(value).map(Some),
+        }
+    }
+}
+
+/// Decode the column of `field` in place into the JSON form of its Rust value
+pub fn decode<T: OrsoType + Serialize>(map: &mut RowMap, field: &str) -> Result<()> {
+    if let Some(value) = map.remove(field) {
+        let json = serde_json::to_value(T::from_value(value)?)?;
+        map.insert(field.to_string(), Value::Json(json));
+    }
+    Ok(())
+}
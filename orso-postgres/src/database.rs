@@ -1,13 +1,59 @@
 use crate::{Error, Result};
-use deadpool_postgres::{Manager, ManagerConfig, Pool, RecyclingMethod};
+use deadpool_postgres::{Hook, HookError, Manager, ManagerConfig, Pool, RecyclingMethod};
 use serde::{Deserialize, Serialize};
-use tokio_postgres::{NoTls, Row};
-use tracing::debug;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio_postgres::error::ErrorPosition;
+use tokio_postgres::Row;
+use tracing::{debug, debug_span, field, instrument, warn, Instrument, Span};
 
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DatabaseConfig {
     pub connection_string: String,
     pub max_pool_size: usize,
+    /// Statements run on every new pooled connection, e.g. `SET search_path TO app`
+    #[serde(default)]
+    pub on_connect_sql: Vec<String>,
+    /// Record parameter values on query spans instead of redacting them
+    #[serde(default)]
+    pub log_parameters: bool,
+    /// Statements taking longer than this are logged as slow queries
+    #[serde(default)]
+    pub slow_query_threshold: Option<Duration>,
+    /// Servers to connect to, tried in order; `host` or `host:port`
+    #[serde(default)]
+    pub hosts: Vec<String>,
+    /// Skip servers that don't accept writes, like libpq's `target_session_attrs=read-write`
+    #[serde(default)]
+    pub read_write: bool,
+    /// How long to wait for each server before trying the next one
+    #[serde(default)]
+    pub connect_timeout: Option<Duration>,
+    /// Replace pooled connections once they are this old
+    #[serde(default)]
+    pub max_connection_lifetime: Option<Duration>,
+    /// Statements allowed to run at once; the rest wait for a slot
+    #[serde(default)]
+    pub max_concurrent_queries: Option<usize>,
+    /// How long a statement waits for a slot before failing with [`Error::Overloaded`]
+    #[serde(default)]
+    pub queue_timeout: Option<Duration>,
+    /// Session time zone of every connection, e.g. `UTC`; the server's default when unset
+    #[serde(default)]
+    pub timezone: Option<String>,
+    /// Fail with [`Error::FieldDecode`] on columns that don't decode, see [`crate::decode`]
+    #[serde(default)]
+    pub strict_decoding: bool,
+    /// Supplies the password for every new connection, see [`crate::credentials`]
+    #[serde(skip)]
+    pub credentials: Option<crate::credentials::Credentials>,
+    /// Encrypts new connections, see [`crate::tls`]; they are unencrypted when unset
+    #[serde(skip)]
+    pub tls: Option<crate::tls::Tls>,
+    /// Keys for `#[orso_column(encrypted)]` columns, see [`crate::encryption`]
+    #[serde(skip)]
+    pub encryption_keys: Vec<crate::encryption::EncryptionKey>,
 }
 
 impl DatabaseConfig {
@@ -15,6 +61,20 @@ impl DatabaseConfig {
         Self {
             connection_string: connection_string.into(),
             max_pool_size: 16,
+            on_connect_sql: Vec::new(),
+            log_parameters: false,
+            slow_query_threshold: None,
+            hosts: Vec::new(),
+            read_write: false,
+            connect_timeout: None,
+            max_connection_lifetime: None,
+            max_concurrent_queries: None,
+            queue_timeout: None,
+            timezone: None,
+            strict_decoding: false,
+            credentials: None,
+            tls: None,
+            encryption_keys: Vec::new(),
         }
     }
 
@@ -26,43 +86,845 @@ impl DatabaseConfig {
         self.max_pool_size = size;
         self
     }
+
+    /// Run `statements` whenever the pool opens a new connection
+    pub fn with_on_connect_sql<S: Into<String>>(
+        mut self,
+        statements: impl IntoIterator<Item = S>,
+    ) -> Self {
+        self.on_connect_sql = statements.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Include parameter values in query spans; they are redacted by default
+    pub fn with_log_parameters(mut self, enabled: bool) -> Self {
+        self.log_parameters = enabled;
+        self
+    }
+
+    /// Log statements that take longer than `threshold`
+    pub fn with_slow_query_threshold(mut self, threshold: Duration) -> Self {
+        self.slow_query_threshold = Some(threshold);
+        self
+    }
+
+    /// Connect to the first of `hosts` that is reachable, for failover between servers
+    ///
+    /// The connection string then names no host. Ports come from `host:port` entries or, for
+    /// all hosts at once, from the connection string.
+    pub fn with_hosts<S: Into<String>>(mut self, hosts: impl IntoIterator<Item = S>) -> Self {
+        self.hosts = hosts.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Only connect to a server accepting writes, so a demoted primary is passed over
+    pub fn with_read_write(mut self, read_write: bool) -> Self {
+        self.read_write = read_write;
+        self
+    }
+
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Self {
+        self.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Fetch the password from `provider` whenever a connection is opened
+    pub fn with_credentials(
+        mut self,
+        provider: impl crate::credentials::CredentialsProvider + 'static,
+    ) -> Self {
+        self.credentials = Some(crate::credentials::Credentials::new(provider));
+        self
+    }
+
+    /// Open connections over TLS with `tls`, any [`crate::tls::MakeTls`] such as `postgres-native-tls`
+    pub fn with_tls(mut self, tls: impl crate::tls::MakeTls) -> Self {
+        self.tls = Some(crate::tls::Tls::new(tls));
+        self
+    }
+
+    /// Encrypt `#[orso_column(encrypted)]` columns with `key`, keeping earlier keys for reading
+    pub fn with_encryption_key(mut self, key: crate::encryption::EncryptionKey) -> Self {
+        self.encryption_keys.push(key);
+        self
+    }
+
+    /// Close pooled connections older than `lifetime` instead of reusing them
+    pub fn with_max_connection_lifetime(mut self, lifetime: Duration) -> Self {
+        self.max_connection_lifetime = Some(lifetime);
+        self
+    }
+
+    /// Run at most `limit` statements at once, queueing the others
+    ///
+    /// Combine with [`with_queue_timeout`](Self::with_queue_timeout) to shed load during bursts
+    /// instead of letting queued statements pile up.
+    pub fn with_max_concurrent_queries(mut self, limit: usize) -> Self {
+        self.max_concurrent_queries = Some(limit);
+        self
+    }
+
+    /// Fail statements with [`Error::Overloaded`] after waiting `timeout` for a slot
+    pub fn with_queue_timeout(mut self, timeout: Duration) -> Self {
+        self.queue_timeout = Some(timeout);
+        self
+    }
+
+    /// Set the session time zone of every connection to `timezone`
+    ///
+    /// Timestamp columns are `TIMESTAMPTZ`, so stored instants don't depend on it; it decides
+    /// how `NOW()::date`, `date_trunc` and timestamps in raw SQL text are interpreted.
+    pub fn with_timezone(mut self, timezone: impl Into<String>) -> Self {
+        self.timezone = Some(timezone.into());
+        self
+    }
+
+    /// Report the table and column of values that fail to decode instead of an opaque error
+    pub fn with_strict_decoding(mut self, strict: bool) -> Self {
+        self.strict_decoding = strict;
+        self
+    }
+
+    /// The connection string with the failover settings applied
+    pub(crate) fn pg_config(&self) -> Result<tokio_postgres::Config> {
+        let mut pg_config: tokio_postgres::Config =
+            self.connection_string.parse().map_err(|e| Error::Config {
+                message: format!("Invalid connection string: {}", e),
+                parameter: Some("connection_string".to_string()),
+                source: Some(Box::new(e)),
+            })?;
+
+        if !self.hosts.is_empty() {
+            if !pg_config.get_hosts().is_empty() {
+                return Err(Error::Config {
+                    message: "Hosts are set both in the connection string and with with_hosts"
+                        .to_string(),
+                    parameter: Some("hosts".to_string()),
+                    source: None,
+                });
+            }
+            let shared_port = pg_config.get_ports().first().copied();
+            let mut ports = Vec::new();
+            for entry in &self.hosts {
+                let (host, port) = split_host_port(entry)?;
+                pg_config.host(host);
+                ports.push(port.or(shared_port).unwrap_or(5432));
+            }
+            match shared_port {
+                None => {
+                    for port in ports {
+                        pg_config.port(port);
+                    }
+                }
+                Some(shared) if ports.iter().all(|port| *port == shared) => {}
+                Some(_) => {
+                    return Err(Error::Config {
+                        message: "Ports are set both in the connection string and in hosts"
+                            .to_string(),
+                        parameter: Some("hosts".to_string()),
+                        source: None,
+                    })
+                }
+            }
+        }
+        if self.read_write {
+            pg_config.target_session_attrs(tokio_postgres::config::TargetSessionAttrs::ReadWrite);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            pg_config.connect_timeout(timeout);
+        }
+        Ok(pg_config)
+    }
+
+    /// Statements every new connection runs first: the time zone, then `on_connect_sql`
+    pub(crate) fn init_sql(&self) -> Option<String> {
+        let mut statements = Vec::new();
+        if let Some(timezone) = &self.timezone {
+            statements.push(format!("SET TIME ZONE '{}'", timezone.replace('\'', "''")));
+        }
+        statements.extend(self.on_connect_sql.iter().cloned());
+        (!statements.is_empty()).then(|| statements.join(";\n"))
+    }
+}
+
+/// Split a `host:port`, `[v6 address]:port` or bare host entry of `with_hosts`
+///
+/// An entry with several colons and no brackets is an IPv6 address without a port.
+pub(crate) fn split_host_port(entry: &str) -> Result<(&str, Option<u16>)> {
+    let invalid = || Error::Config {
+        message: format!("Invalid host '{}'", entry),
+        parameter: Some("hosts".to_string()),
+        source: None,
+    };
+    let (host, port) = match entry.strip_prefix('[') {
+        Some(bracketed) => {
+            let (host, rest) = bracketed.split_once(']').ok_or_else(invalid)?;
+            match rest {
+                "" => (host, None),
+                _ => (host, Some(rest.strip_prefix(':').ok_or_else(invalid)?)),
+            }
+        }
+        None => match entry.split_once(':') {
+            Some((host, port)) if !port.contains(':') => (host, Some(port)),
+            _ => (entry, None),
+        },
+    };
+    let port = port
+        .map(|port| port.parse().map_err(|_| invalid()))
+        .transpose()?;
+    Ok((host, port))
+}
+
+/// A statement that exceeded the slow query threshold
+#[derive(Debug, Clone, PartialEq)]
+pub struct SlowQuery {
+    pub sql: String,
+    pub duration: Duration,
+    pub table: Option<String>,
+}
+
+pub type SlowQueryCallback = Arc<dyn Fn(&SlowQuery) + Send + Sync>;
+
+/// The latest failed statement or connection checkout of a [`Database`]
+#[derive(Debug, Clone, PartialEq)]
+pub struct LastError {
+    pub message: String,
+    /// SQLSTATE of errors reported by the server, e.g. `42P01`
+    pub code: Option<String>,
+    pub at: crate::OrsoDateTime,
+}
+
+/// State of a [`Database`] for readiness probes, from [`Database::health`]
+#[derive(Debug, Clone)]
+pub struct HealthReport {
+    /// Whether `SELECT 1` succeeded within [`Database::HEALTH_TIMEOUT`]
+    pub healthy: bool,
+    /// Time to check out a connection and run `SELECT 1`
+    pub latency: Duration,
+    /// Why the check failed
+    pub error: Option<String>,
+    pub pool: deadpool_postgres::Status,
+    pub last_error: Option<LastError>,
+    /// On a primary, the largest replay lag of its streaming replicas; on a standby, the time
+    /// since the last replayed transaction. `None` without replication.
+    pub replication_lag: Option<Duration>,
+}
+
+impl HealthReport {
+    /// Share of the pool's connections checked out, between 0 and 1
+    pub fn pool_utilization(&self) -> f64 {
+        if self.pool.max_size == 0 {
+            return 0.0;
+        }
+        self.pool.size.saturating_sub(self.pool.available) as f64 / self.pool.max_size as f64
+    }
+}
+
+/// Statement kind used to label queries: `select`, `insert`, `update`, `delete` or `other`
+///
+/// A `WITH` query counts as the first insert, update or delete in it, `other` without one.
+pub fn statement_kind(sql: &str) -> &'static str {
+    let keyword = sql
+        .trim_start()
+        .split(|c: char| c.is_whitespace() || c == '(')
+        .next()
+        .unwrap_or("");
+    match keyword.to_ascii_lowercase().as_str() {
+        "select" => "select",
+        "insert" => "insert",
+        "update" => "update",
+        "delete" => "delete",
+        "with" => cte_write_kind(sql).unwrap_or("other"),
+        _ => "other",
+    }
+}
+
+/// The first insert, update or delete keyword of `sql`, outside quoted literals and names
+fn cte_write_kind(sql: &str) -> Option<&'static str> {
+    let mut quote = None;
+    let mut word = String::new();
+    for c in sql.chars().chain([' ']) {
+        match quote {
+            Some(open) if c == open => quote = None,
+            Some(_) => {}
+            None if c == '\'' || c == '"' => {
+                quote = Some(c);
+                word.clear();
+            }
+            None if c.is_ascii_alphanumeric() || c == '_' => word.push(c),
+            None => {
+                match word.to_ascii_lowercase().as_str() {
+                    "insert" => return Some("insert"),
+                    "update" => return Some("update"),
+                    "delete" => return Some("delete"),
+                    _ => {}
+                }
+                word.clear();
+            }
+        }
+    }
+    None
+}
+
+/// Whether `sql` is an insert, update or delete, the statements audit triggers fire for
+///
+/// Only these name the actor: wrapping anything else in a transaction would break
+/// statements such as `VACUUM` or `CREATE INDEX CONCURRENTLY` that can't run in one.
+pub(crate) fn writes_rows(sql: &str) -> bool {
+    matches!(statement_kind(sql), "insert" | "update" | "delete")
+}
+
+/// Table a statement reads from or writes to, if it can be told from the SQL
+pub fn statement_table(sql: &str) -> Option<&str> {
+    let mut words = sql.split_whitespace();
+    let table = match words.next()?.to_ascii_lowercase().as_str() {
+        "update" => words.next(),
+        _ => words
+            .by_ref()
+            .find(|w| w.eq_ignore_ascii_case("from") || w.eq_ignore_ascii_case("into"))
+            .and_then(|_| words.next()),
+    }?;
+    let table = table.trim_end_matches([',', ';', ')']);
+    (!table.is_empty() && !table.starts_with('(')).then_some(table)
+}
+
+/// Convert the error of a failed statement, attaching the statement and the position in it
+/// PostgreSQL reported
+pub(crate) fn with_statement_context<T>(
+    result: std::result::Result<T, tokio_postgres::Error>,
+    sql: &str,
+    param_count: usize,
+) -> Result<T> {
+    result.map_err(|err| {
+        let mut context = crate::ErrorContext::new(sql, param_count);
+        if let Some(ErrorPosition::Original(position)) =
+            err.as_db_error().and_then(|e| e.position())
+        {
+            context.position = Some(*position as usize);
+        }
+        Error::from(err).with_context(context)
+    })
+}
+
+/// Whether a statement failed because the server only allows reads
+fn is_read_only(err: &tokio_postgres::Error) -> bool {
+    err.code() == Some(&tokio_postgres::error::SqlState::READ_ONLY_SQL_TRANSACTION)
+}
+
+/// The context whose actor audit triggers see during `sql`, if it writes rows
+pub(crate) fn actor_for(sql: &str) -> Option<crate::OperationContext> {
+    crate::OperationContext::current().filter(|_| writes_rows(sql))
+}
+
+/// A connection checked out to run one statement on
+pub(crate) enum Conn {
+    Pooled(Box<deadpool_postgres::Object>),
+    /// The connection of a [`Database::pin`]ned handle
+    Pinned(tokio::sync::OwnedMutexGuard<Option<deadpool_postgres::Object>>),
+}
+
+impl std::ops::Deref for Conn {
+    type Target = deadpool_postgres::Object;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            Conn::Pooled(client) => client,
+            // `checkout` never hands out a detached connection
+            Conn::Pinned(client) => client.as_ref().expect("pinned connection"),
+        }
+    }
+}
+
+impl std::ops::DerefMut for Conn {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        match self {
+            Conn::Pooled(client) => client,
+            Conn::Pinned(client) => client.as_mut().expect("pinned connection"),
+        }
+    }
 }
 
-#[derive(Debug)]
 pub struct Database {
     pub pool: Pool,
+    /// Connection every statement runs on, for handles made by `Database::pin`
+    pinned: Option<Arc<tokio::sync::Mutex<Option<deadpool_postgres::Object>>>>,
+    query_slots: Option<Arc<Semaphore>>,
+    /// When a server stopped accepting writes; connections opened before are not reused
+    failed_over_at: Arc<std::sync::Mutex<Option<Instant>>>,
+    queue_timeout: Option<Duration>,
+    log_parameters: bool,
+    slow_query_threshold: Option<Duration>,
+    slow_query_callback: Option<SlowQueryCallback>,
+    query_log: Option<Arc<crate::advisor::QueryLog>>,
+    hooks: Option<Arc<dyn crate::hooks::Hooks>>,
+    circuit_breaker: Option<Arc<crate::circuit::CircuitBreaker>>,
+    named_queries: Option<Arc<crate::named::NamedQueries>>,
+    last_error: std::sync::Mutex<Option<LastError>>,
+    strict_decoding: bool,
+    keyring: Option<Arc<crate::encryption::Keyring>>,
+    credential_failures: crate::credentials::CredentialFailures,
+    #[cfg(feature = "metrics")]
+    metrics: Option<std::sync::Arc<dyn crate::metrics::MetricsHook>>,
+}
+
+impl std::fmt::Debug for Database {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Database")
+            .field("pool", &self.pool)
+            .field("log_parameters", &self.log_parameters)
+            .field("slow_query_threshold", &self.slow_query_threshold)
+            .finish_non_exhaustive()
+    }
 }
 
 impl Database {
     pub async fn init(config: DatabaseConfig) -> Result<Self> {
-        let pg_config: tokio_postgres::Config = config
-            .connection_string
-            .parse()
-            .map_err(|e| Error::Config {
-                message: format!("Invalid connection string: {}", e),
-                parameter: Some("connection_string".to_string()),
-                source: Some(Box::new(e)),
-            })?;
+        let pg_config = config.pg_config()?;
 
         let mgr_config = ManagerConfig {
             recycling_method: RecyclingMethod::Fast,
         };
 
-        let mgr = Manager::from_config(pg_config, NoTls, mgr_config);
-        let pool = Pool::builder(mgr)
+        let connect = crate::credentials::Connector::new(&config);
+        let credential_failures = connect.failures.clone();
+        let mgr = Manager::from_connect(pg_config, connect, mgr_config);
+        let failed_over_at = Arc::new(std::sync::Mutex::new(None::<Instant>));
+        let failover = failed_over_at.clone();
+        let mut builder = Pool::builder(mgr)
             .max_size(config.max_pool_size)
-            .build()
-            .map_err(|e| Error::Connection {
-                message: format!("Failed to create connection pool: {}", e),
-                source: Some(Box::new(e)),
-            })?;
+            .pre_recycle(Hook::sync_fn(move |_, metrics| {
+                let failed_over_at = *failover.lock().unwrap_or_else(|e| e.into_inner());
+                if failed_over_at.is_some_and(|at| metrics.created < at) {
+                    return Err(HookError::message("Connection predates a failover"));
+                }
+                Ok(())
+            }));
+        if let Some(lifetime) = config.max_connection_lifetime {
+            builder = builder.pre_recycle(Hook::sync_fn(move |_, metrics| {
+                if metrics.age() > lifetime {
+                    return Err(HookError::message(
+                        "Connection reached its maximum lifetime",
+                    ));
+                }
+                Ok(())
+            }));
+        }
+        if let Some(init_sql) = config.init_sql() {
+            builder = builder.post_create(Hook::async_fn(move |client, _| {
+                let init_sql = init_sql.clone();
+                Box::pin(async move {
+                    debug!(sql = %init_sql, "Initializing connection");
+                    client
+                        .batch_execute(&init_sql)
+                        .await
+                        .map_err(HookError::Backend)
+                })
+            }));
+        }
+
+        let pool = builder.build().map_err(|e| Error::Connection {
+            message: format!("Failed to create connection pool: {}", e),
+            source: Some(Box::new(e)),
+        })?;
 
         debug!(
             "PostgreSQL connection pool established with max_size: {}",
             config.max_pool_size
         );
 
-        Ok(Self { pool })
+        Ok(Self {
+            pool,
+            pinned: None,
+            failed_over_at,
+            query_slots: config
+                .max_concurrent_queries
+                .map(|limit| Arc::new(Semaphore::new(limit.max(1)))),
+            queue_timeout: config.queue_timeout,
+            log_parameters: config.log_parameters,
+            slow_query_threshold: config.slow_query_threshold,
+            slow_query_callback: None,
+            query_log: None,
+            hooks: None,
+            circuit_breaker: None,
+            named_queries: None,
+            last_error: std::sync::Mutex::new(None),
+            strict_decoding: config.strict_decoding,
+            keyring: (!config.encryption_keys.is_empty())
+                .then(|| Arc::new(crate::encryption::Keyring::new(&config.encryption_keys))),
+            credential_failures,
+            #[cfg(feature = "metrics")]
+            metrics: None,
+        })
+    }
+
+    /// A handle running every statement on one connection checked out of the pool, so that
+    /// session state such as an open transaction carries over between them
+    ///
+    /// Transactions started through `transaction_with`, pipelines and health checks still
+    /// use connections of their own.
+    pub(crate) async fn pin(&self) -> Result<Database> {
+        let client = self.client().await?;
+        Ok(Self {
+            pool: self.pool.clone(),
+            pinned: Some(Arc::new(tokio::sync::Mutex::new(Some(client)))),
+            query_slots: self.query_slots.clone(),
+            failed_over_at: self.failed_over_at.clone(),
+            queue_timeout: self.queue_timeout,
+            log_parameters: self.log_parameters,
+            slow_query_threshold: self.slow_query_threshold,
+            slow_query_callback: self.slow_query_callback.clone(),
+            query_log: self.query_log.clone(),
+            hooks: self.hooks.clone(),
+            circuit_breaker: self.circuit_breaker.clone(),
+            named_queries: self.named_queries.clone(),
+            last_error: std::sync::Mutex::new(None),
+            strict_decoding: self.strict_decoding,
+            keyring: self.keyring.clone(),
+            credential_failures: self.credential_failures.clone(),
+            #[cfg(feature = "metrics")]
+            metrics: self.metrics.clone(),
+        })
+    }
+
+    /// Whether statements run on one connection, see `Database::pin`
+    pub(crate) fn is_pinned(&self) -> bool {
+        self.pinned.is_some()
+    }
+
+    /// Close the connection of a pinned handle instead of returning it to the pool, where
+    /// it would hand its open transaction to the next statement
+    pub(crate) fn detach(&self) {
+        let Some(pinned) = &self.pinned else {
+            return;
+        };
+        if let Some(client) = pinned.try_lock().ok().and_then(|mut client| client.take()) {
+            warn!("Closing connection left in a transaction");
+            drop(deadpool_postgres::Object::take(client));
+        }
+    }
+
+    /// How long [`Database::health`] waits for a connection and `SELECT 1`
+    pub const HEALTH_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Check that the server answers and report the pool, the latest error and replication lag
+    ///
+    /// Bypasses the `max_concurrent_queries` limit, so a saturated but working database still
+    /// reports healthy.
+    #[instrument(name = "orso.health", skip_all)]
+    pub async fn health(&self) -> HealthReport {
+        let started = Instant::now();
+        let checked = tokio::time::timeout(Self::HEALTH_TIMEOUT, async {
+            let client = self.client().await?;
+            client.simple_query("SELECT 1").await?;
+            Ok::<_, Error>(client)
+        })
+        .await;
+        let latency = started.elapsed();
+
+        let (client, error) = match checked {
+            Ok(Ok(client)) => (Some(client), None),
+            Ok(Err(e)) => (None, Some(e.to_string())),
+            Err(_) => (
+                None,
+                Some(format!("No answer within {:?}", Self::HEALTH_TIMEOUT)),
+            ),
+        };
+        let replication_lag = match &client {
+            Some(client) => Self::replication_lag(client).await.unwrap_or_else(|e| {
+                debug!(error = %e, "Could not read replication lag");
+                None
+            }),
+            None => None,
+        };
+        if let Some(error) = &error {
+            warn!(error = %error, "Health check failed");
+        }
+
+        HealthReport {
+            healthy: error.is_none(),
+            latency,
+            error,
+            pool: self.pool.status(),
+            last_error: self.last_error(),
+            replication_lag,
+        }
+    }
+
+    async fn replication_lag(client: &tokio_postgres::Client) -> Result<Option<Duration>> {
+        let row = client
+            .query_one(
+                "SELECT CASE WHEN pg_is_in_recovery()
+                     THEN extract(epoch FROM now() - pg_last_xact_replay_timestamp())
+                     ELSE (SELECT extract(epoch FROM max(replay_lag)) FROM pg_stat_replication)
+                 END::float8",
+                &[],
+            )
+            .await?;
+        let seconds: Option<f64> = row.get(0);
+        Ok(seconds.map(|s| Duration::from_secs_f64(s.max(0.0))))
+    }
+
+    /// The latest statement or connection checkout that failed
+    pub fn last_error(&self) -> Option<LastError> {
+        self.last_error
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+
+    fn record_error(&self, message: String, code: Option<String>) {
+        *self.last_error.lock().unwrap_or_else(|e| e.into_inner()) = Some(LastError {
+            message,
+            code,
+            at: crate::OrsoDateTime::now(),
+        });
+    }
+
+    fn record_postgres_error(&self, error: &tokio_postgres::Error) {
+        let code = error.code().map(|c| c.code().to_string());
+        self.record_error(crate::error::postgres_message(error), code);
+    }
+
+    /// Call `callback` for every statement over the slow query threshold instead of logging it
+    pub fn on_slow_query(mut self, callback: impl Fn(&SlowQuery) + Send + Sync + 'static) -> Self {
+        self.slow_query_callback = Some(Arc::new(callback));
+        self
+    }
+
+    /// Record the filters and sorts of the statements run on it in `log`, for [`crate::Advisor`]
+    pub fn with_query_log(mut self, log: Arc<crate::advisor::QueryLog>) -> Self {
+        self.query_log = Some(log);
+        self
+    }
+
+    pub(crate) fn query_log(&self) -> Option<&crate::advisor::QueryLog> {
+        self.query_log.as_deref()
+    }
+
+    /// Whether it was initialized with [`DatabaseConfig::with_strict_decoding`]
+    pub fn strict_decoding(&self) -> bool {
+        self.strict_decoding
+    }
+
+    /// Keys of `#[orso_column(encrypted)]` columns, from [`DatabaseConfig::with_encryption_key`]
+    pub fn keyring(&self) -> Option<&Arc<crate::encryption::Keyring>> {
+        self.keyring.as_ref()
+    }
+
+    /// Call `hooks` around every statement, see [`crate::hooks`]
+    pub fn with_hooks(mut self, hooks: Arc<dyn crate::hooks::Hooks>) -> Self {
+        self.hooks = Some(hooks);
+        self
+    }
+
+    /// Fail statements fast while `breaker` is open, see [`crate::circuit`]
+    pub fn with_circuit_breaker(mut self, breaker: Arc<crate::circuit::CircuitBreaker>) -> Self {
+        self.circuit_breaker = Some(breaker);
+        self
+    }
+
+    /// Make the statements of `queries` available to [`Database::named`]
+    pub fn with_named_queries(mut self, queries: Arc<crate::named::NamedQueries>) -> Self {
+        self.named_queries = Some(queries);
+        self
+    }
+
+    /// Let the circuit breaker and the hooks veto `sql` before it runs
+    pub(crate) fn before_query(&self, sql: &str) -> Result<()> {
+        if let Some(breaker) = &self.circuit_breaker {
+            breaker.admit()?;
+        }
+        match &self.hooks {
+            Some(hooks) => hooks.before_query(sql),
+            None => Ok(()),
+        }
+    }
+
+    /// Report query and pool measurements to `hook`
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(mut self, hook: std::sync::Arc<dyn crate::metrics::MetricsHook>) -> Self {
+        self.metrics = Some(hook);
+        self
+    }
+
+    /// Check out a pooled connection
+    async fn client(&self) -> Result<deadpool_postgres::Object> {
+        #[cfg(feature = "metrics")]
+        let started = Instant::now();
+
+        let client = match self.pool.get().await {
+            Ok(client) => client,
+            Err(e) => {
+                // A failing credentials provider is reported by its own error
+                let failure = self
+                    .credential_failures
+                    .lock()
+                    .unwrap_or_else(|e| e.into_inner())
+                    .take();
+                if let Some(failure) = failure {
+                    self.record_error(failure.to_string(), None);
+                    return Err(failure);
+                }
+                match &e {
+                    deadpool_postgres::PoolError::Backend(e) => self.record_postgres_error(e),
+                    e => self.record_error(e.to_string(), None),
+                }
+                return Err(e.into());
+            }
+        };
+
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            metrics.record_pool_wait(started.elapsed());
+            let status = self.pool.status();
+            metrics.record_connections(
+                status.size.saturating_sub(status.available),
+                status.available,
+            );
+        }
+
+        Ok(client)
+    }
+
+    /// Wait for a query slot and a connection to run `sql` on, once the hooks let it run
+    async fn checkout(&self, sql: &str) -> Result<(Option<OwnedSemaphorePermit>, Conn)> {
+        self.checkout_from(sql, self.pinned.as_ref()).await
+    }
+
+    /// Like `checkout`, with the connection of `pinned` if given and one from the pool otherwise
+    async fn checkout_from(
+        &self,
+        sql: &str,
+        pinned: Option<&Arc<tokio::sync::Mutex<Option<deadpool_postgres::Object>>>>,
+    ) -> Result<(Option<OwnedSemaphorePermit>, Conn)> {
+        self.before_query(sql)?;
+        let started = Instant::now();
+        let checked_out = async {
+            let slot = self.acquire_slot().await?;
+            let client = match pinned {
+                Some(pinned) => {
+                    let client = pinned.clone().lock_owned().await;
+                    if client.is_none() {
+                        return Err(Error::Connection {
+                            message: "Pinned connection was closed".to_string(),
+                            source: None,
+                        });
+                    }
+                    Conn::Pinned(client)
+                }
+                None => Conn::Pooled(Box::new(self.client().await?)),
+            };
+            Ok((slot, client))
+        }
+        .await;
+        if let Err(e) = &checked_out {
+            if let (Error::Pool { .. }, Some(breaker)) = (e, &self.circuit_breaker) {
+                breaker.record(true);
+            }
+            if let Some(hooks) = &self.hooks {
+                hooks.on_error(sql, started.elapsed(), e);
+            }
+        }
+        checked_out
+    }
+
+    /// Wait for a slot when the number of concurrent statements is limited
+    pub(crate) async fn acquire_slot(&self) -> Result<Option<OwnedSemaphorePermit>> {
+        let Some(slots) = &self.query_slots else {
+            return Ok(None);
+        };
+        let started = Instant::now();
+        let permit = match self.queue_timeout {
+            Some(timeout) => tokio::time::timeout(timeout, slots.clone().acquire_owned())
+                .await
+                .map_err(|_| {
+                    warn!(waited_ms = timeout.as_millis() as u64, "Shedding statement");
+                    Error::overloaded("No query slot became free in time", started.elapsed())
+                })?,
+            None => slots.clone().acquire_owned().await,
+        };
+        // The semaphore is never closed
+        Ok(permit.ok())
+    }
+
+    /// Span covering a single statement
+    pub(crate) fn statement_span(
+        &self,
+        sql: &str,
+        params: &[&(dyn tokio_postgres::types::ToSql + Send + Sync)],
+    ) -> Span {
+        let span = debug_span!(
+            "orso.query",
+            db.statement = %sql,
+            db.operation = statement_kind(sql),
+            db.params = field::Empty,
+            rows = field::Empty,
+            duration_ms = field::Empty,
+            error = field::Empty,
+        );
+        if self.log_parameters {
+            span.record("db.params", field::debug(params));
+        } else if !params.is_empty() {
+            span.record("db.params", format!("[{} redacted]", params.len()));
+        }
+        span
+    }
+
+    /// Report a finished statement with its row count or error
+    pub(crate) fn observe(
+        &self,
+        span: &Span,
+        sql: &str,
+        started: Instant,
+        outcome: std::result::Result<u64, &tokio_postgres::Error>,
+    ) {
+        let duration = started.elapsed();
+        if let Err(e) = outcome {
+            self.record_postgres_error(e);
+        }
+        if let Some(breaker) = &self.circuit_breaker {
+            breaker.record(outcome.is_err_and(crate::circuit::is_outage));
+        }
+        if let Some(hooks) = &self.hooks {
+            match outcome {
+                Ok(rows) => hooks.after_query(sql, duration, rows),
+                Err(e) => hooks.on_error(sql, duration, &Error::postgres_ref(e)),
+            }
+        }
+        let rows = outcome.ok();
+        span.record("duration_ms", duration.as_secs_f64() * 1000.0);
+        match rows {
+            Some(rows) => span.record("rows", rows),
+            None => span.record("error", true),
+        };
+
+        if self
+            .slow_query_threshold
+            .is_some_and(|threshold| duration > threshold)
+        {
+            let slow = SlowQuery {
+                sql: sql.to_string(),
+                duration,
+                table: statement_table(sql).map(str::to_string),
+            };
+            match &self.slow_query_callback {
+                Some(callback) => callback(&slow),
+                None => warn!(
+                    sql = %slow.sql,
+                    duration_ms = duration.as_secs_f64() * 1000.0,
+                    table = slow.table.as_deref().unwrap_or(""),
+                    "Slow query"
+                ),
+            }
+        }
+
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = &self.metrics {
+            match rows {
+                Some(rows) => metrics.record_query(sql, duration, rows),
+                None => metrics.record_query_error(sql, duration),
+            }
+        }
     }
 
     pub async fn execute(
@@ -70,7 +932,10 @@ impl Database {
         sql: &str,
         params: &[&(dyn tokio_postgres::types::ToSql + Send + Sync)],
     ) -> Result<u64> {
-        let client = self.pool.get().await?;
+        let (_slot, mut client) = self.checkout(sql).await?;
+        if let Some(log) = &self.query_log {
+            log.record_sql(sql);
+        }
 
         // Convert Send + Sync to Sync at the boundary (secure coercion)
         let sync_params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> = params
@@ -78,8 +943,111 @@ impl Database {
             .map(|p| *p as &(dyn tokio_postgres::types::ToSql + Sync))
             .collect();
 
-        let rows = client.execute(sql, &sync_params).await?;
-        Ok(rows)
+        let span = self.statement_span(sql, params);
+        let started = Instant::now();
+        let mut result = Self::execute_on(&mut client, sql, &sync_params)
+            .instrument(span.clone())
+            .await;
+        if result.as_ref().is_err_and(is_read_only) && self.pinned.is_none() {
+            client = self.reconnect(client).await?;
+            result = Self::execute_on(&mut client, sql, &sync_params)
+                .instrument(span.clone())
+                .await;
+        }
+        self.observe(&span, sql, started, result.as_ref().copied());
+
+        with_statement_context(result, sql, params.len())
+    }
+
+    async fn execute_on(
+        client: &mut Conn,
+        sql: &str,
+        params: &[&(dyn tokio_postgres::types::ToSql + Sync)],
+    ) -> std::result::Result<u64, tokio_postgres::Error> {
+        // Expose the current actor to audit triggers for the duration of a write
+        if let Some(ctx) = actor_for(sql) {
+            if let Conn::Pinned(_) = client {
+                Self::set_session_actor(client, &ctx).await?;
+                let result = client.execute(sql, params).await;
+                return Self::reset_session_actor(client, result).await;
+            }
+            let tx = client.transaction().await?;
+            tx.execute("SELECT set_config('orso.actor', $1, true)", &[&ctx.actor])
+                .await?;
+            let rows = tx.execute(sql, params).await?;
+            tx.commit().await?;
+            return Ok(rows);
+        }
+
+        client.execute(sql, params).await
+    }
+
+    /// Like `execute_on`, for statements returning rows such as `INSERT ... RETURNING`
+    async fn query_on(
+        client: &mut Conn,
+        sql: &str,
+        params: &[&(dyn tokio_postgres::types::ToSql + Sync)],
+    ) -> std::result::Result<Vec<Row>, tokio_postgres::Error> {
+        if let Some(ctx) = actor_for(sql) {
+            if let Conn::Pinned(_) = client {
+                Self::set_session_actor(client, &ctx).await?;
+                let result = client.query(sql, params).await;
+                return Self::reset_session_actor(client, result).await;
+            }
+            let tx = client.transaction().await?;
+            tx.execute("SELECT set_config('orso.actor', $1, true)", &[&ctx.actor])
+                .await?;
+            let rows = tx.query(sql, params).await?;
+            tx.commit().await?;
+            return Ok(rows);
+        }
+
+        client.query(sql, params).await
+    }
+
+    /// Name the actor on a pinned connection, which may or may not be in a transaction, for
+    /// the session rather than a transaction that could end with this statement
+    pub(crate) async fn set_session_actor(
+        client: &tokio_postgres::Client,
+        ctx: &crate::OperationContext,
+    ) -> std::result::Result<(), tokio_postgres::Error> {
+        client
+            .execute("SELECT set_config('orso.actor', $1, false)", &[&ctx.actor])
+            .await?;
+        Ok(())
+    }
+
+    /// Clear the actor named by `set_session_actor` once the write returned `result`
+    ///
+    /// A write that failed in a transaction aborts it, so the reset fails too and the
+    /// rollback clears the actor instead.
+    pub(crate) async fn reset_session_actor<T>(
+        client: &tokio_postgres::Client,
+        result: std::result::Result<T, tokio_postgres::Error>,
+    ) -> std::result::Result<T, tokio_postgres::Error> {
+        let reset = client.batch_execute("RESET orso.actor").await;
+        let value = result?;
+        reset?;
+        Ok(value)
+    }
+
+    /// Replace a connection to a server that stopped accepting writes, such as a primary
+    /// demoted by a switchover, so the pool connects to whichever server accepts them now
+    ///
+    /// The rest of the pool went to the same server, so idle connections are closed and
+    /// checked out ones are closed instead of reused when they come back.
+    async fn reconnect(&self, client: Conn) -> Result<Conn> {
+        warn!("Server no longer accepts writes, reconnecting");
+        if let Conn::Pooled(client) = client {
+            drop(deadpool_postgres::Object::take(*client));
+        }
+        let now = Instant::now();
+        *self
+            .failed_over_at
+            .lock()
+            .unwrap_or_else(|e| e.into_inner()) = Some(now);
+        self.pool.retain(|_, metrics| metrics.created >= now);
+        Ok(Conn::Pooled(Box::new(self.client().await?)))
     }
 
     pub async fn query(
@@ -87,7 +1055,10 @@ impl Database {
         sql: &str,
         params: &[&(dyn tokio_postgres::types::ToSql + Send + Sync)],
     ) -> Result<Vec<Row>> {
-        let client = self.pool.get().await?;
+        let (_slot, mut client) = self.checkout(sql).await?;
+        if let Some(log) = &self.query_log {
+            log.record_sql(sql);
+        }
 
         // Convert Send + Sync to Sync at the boundary (secure coercion)
         let sync_params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> = params
@@ -95,41 +1066,204 @@ impl Database {
             .map(|p| *p as &(dyn tokio_postgres::types::ToSql + Sync))
             .collect();
 
-        let rows = client.query(sql, &sync_params).await?;
-        Ok(rows)
+        let span = self.statement_span(sql, params);
+        let started = Instant::now();
+        let mut result = Self::query_on(&mut client, sql, &sync_params)
+            .instrument(span.clone())
+            .await;
+        if result.as_ref().is_err_and(is_read_only) && self.pinned.is_none() {
+            client = self.reconnect(client).await?;
+            result = Self::query_on(&mut client, sql, &sync_params)
+                .instrument(span.clone())
+                .await;
+        }
+        self.observe(
+            &span,
+            sql,
+            started,
+            result.as_ref().map(|rows| rows.len() as u64),
+        );
+
+        with_statement_context(result, sql, params.len())
     }
 
+    /// Run a statement returning exactly one row
+    ///
+    /// Like [`Database::query`], the statement is retried on a new connection after a failover
+    /// and writes see the current actor.
     pub async fn query_one(
         &self,
         sql: &str,
         params: &[&(dyn tokio_postgres::types::ToSql + Send + Sync)],
     ) -> Result<Row> {
-        let client = self.pool.get().await?;
+        let rows = self.query(sql, params).await?;
+        match <[Row; 1]>::try_from(rows) {
+            Ok([row]) => Ok(row),
+            Err(rows) => Err(Error::query_with_sql(
+                format!("query_one returned {} rows", rows.len()),
+                sql,
+                None,
+            )),
+        }
+    }
 
-        // Convert Send + Sync to Sync at the boundary (secure coercion)
-        let sync_params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> = params
+    /// Run the statement registered as `name` with [`Database::with_named_queries`]
+    #[instrument(name = "orso.named", skip_all, fields(query = %name))]
+    pub async fn named<T: crate::Orso>(
+        &self,
+        name: &str,
+        params: &[&(dyn tokio_postgres::types::ToSql + Send + Sync)],
+    ) -> Result<Vec<T>> {
+        let queries = self
+            .named_queries
+            .as_deref()
+            .ok_or_else(|| Error::validation("No named queries are attached to the database"))?;
+        let sql = queries.sql_for::<T>(name)?;
+        self.query(sql, params)
+            .await?
             .iter()
-            .map(|p| *p as &(dyn tokio_postgres::types::ToSql + Sync))
-            .collect();
-
-        let row = client.query_one(sql, &sync_params).await?;
-        Ok(row)
+            .map(|row| {
+                let map = crate::operations::CrudOperations::row_to_map(row)?;
+                crate::decode::decode_record(self, map)
+            })
+            .collect()
     }
 
+    /// Run a statement returning at most one row, see [`Database::query_one`]
     pub async fn query_opt(
         &self,
         sql: &str,
         params: &[&(dyn tokio_postgres::types::ToSql + Send + Sync)],
     ) -> Result<Option<Row>> {
-        let client = self.pool.get().await?;
+        let mut rows = self.query(sql, params).await?;
+        match rows.len() {
+            0 => Ok(None),
+            1 => Ok(rows.pop()),
+            n => Err(Error::query_with_sql(
+                format!("query_opt returned {n} rows"),
+                sql,
+                None,
+            )),
+        }
+    }
 
-        // Convert Send + Sync to Sync at the boundary (secure coercion)
-        let sync_params: Vec<&(dyn tokio_postgres::types::ToSql + Sync)> = params
-            .iter()
-            .map(|p| *p as &(dyn tokio_postgres::types::ToSql + Sync))
-            .collect();
+    /// Check out one connection to pipeline concurrent queries on, see [`crate::Pipeline`]
+    ///
+    /// The pipeline takes up one of the `max_concurrent_queries` slots until it is dropped.
+    pub async fn pipeline(&self) -> Result<crate::Pipeline<'_>> {
+        let slot = self.acquire_slot().await?;
+        Ok(crate::Pipeline::new(self, self.client().await?, slot))
+    }
+
+    /// Run `work` on a connection checked out like the one of a single statement, for COPY
+    /// and transactions spanning several statements
+    ///
+    /// The hooks, circuit breaker and metrics see the work as one statement, `sql`. A pinned
+    /// handle hands over its connection as it is, and gets it back when `work` drops it.
+    pub(crate) async fn with_connection<R, F>(
+        &self,
+        sql: &str,
+        work: impl FnOnce(Conn) -> F,
+    ) -> Result<R>
+    where
+        F: std::future::Future<Output = Result<R>>,
+    {
+        let (_slot, client) = self.checkout(sql).await?;
+        let span = self.statement_span(sql, &[]);
+        let started = Instant::now();
+        let result = work(client).instrument(span.clone()).await;
+        match &result {
+            Ok(_) => self.observe(&span, sql, started, Ok(0)),
+            Err(e) => match e.postgres_source() {
+                Some(source) => self.observe(&span, sql, started, Err(source)),
+                None => {
+                    span.record("error", true);
+                    if let Some(hooks) = &self.hooks {
+                        hooks.on_error(sql, started.elapsed(), e);
+                    }
+                }
+            },
+        }
+        result
+    }
+
+    /// Run `f` in a transaction started with `options`, see [`crate::transaction`]
+    ///
+    /// Commits when `f` returns `Ok`, rolls back when it returns an error. `BEGIN`, the
+    /// statements of `f` and the closing `COMMIT` or `ROLLBACK` are each reported to the hooks,
+    /// circuit breaker and metrics.
+    pub async fn transaction_with<R, F>(&self, options: crate::TxOptions, f: F) -> Result<R>
+    where
+        F: for<'t> FnOnce(
+            &'t crate::Transaction<'t>,
+        ) -> futures_util::future::BoxFuture<'t, Result<R>>,
+    {
+        // Even a pinned handle starts the transaction on a connection of its own
+        let (_slot, mut client) = self.checkout_from("BEGIN", None).await?;
+        let span = self.statement_span("BEGIN", &[]);
+        let started = Instant::now();
+        let tx = client
+            .build_transaction()
+            .isolation_level(options.isolation.into())
+            .read_only(options.read_only)
+            .deferrable(options.deferrable)
+            .start()
+            .instrument(span.clone())
+            .await;
+        self.observe(&span, "BEGIN", started, tx.as_ref().map(|_| 0));
+        let tx = crate::Transaction::new(self, with_statement_context(tx, "BEGIN", 0)?);
 
-        let row = client.query_opt(sql, &sync_params).await?;
-        Ok(row)
+        match f(&tx).await {
+            Ok(result) => {
+                tx.end(true).await?;
+                Ok(result)
+            }
+            Err(e) => {
+                // The original error says more than a failed rollback would
+                if let Err(rollback) = tx.end(false).await {
+                    warn!(error = %rollback, "Failed to roll back transaction");
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Run `f` in a serializable transaction, retrying on serialization failures with the
+    /// default [`RetryPolicy`](crate::RetryPolicy), see [`crate::transaction`]
+    pub async fn serializable<R, F>(&self, f: F) -> Result<R>
+    where
+        F: for<'t> FnMut(
+            &'t crate::Transaction<'t>,
+        ) -> futures_util::future::BoxFuture<'t, Result<R>>,
+    {
+        self.serializable_with(crate::RetryPolicy::default(), f)
+            .await
+    }
+
+    /// Run `f` in a serializable transaction, retrying on serialization failures up to
+    /// `policy.max_attempts` times in total
+    pub async fn serializable_with<R, F>(&self, policy: crate::RetryPolicy, mut f: F) -> Result<R>
+    where
+        F: for<'t> FnMut(
+            &'t crate::Transaction<'t>,
+        ) -> futures_util::future::BoxFuture<'t, Result<R>>,
+    {
+        let options = crate::TxOptions {
+            isolation: crate::IsolationLevel::Serializable,
+            ..Default::default()
+        };
+        let mut attempt = 1;
+        loop {
+            match self.transaction_with(options, &mut f).await {
+                Err(e) if e.is_serialization_failure() && attempt < policy.max_attempts => {
+                    let backoff = policy.backoff(attempt);
+                    let backoff_ms = backoff.as_millis() as u64;
+                    debug!(attempt, backoff_ms, "Retrying serializable transaction");
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
     }
 }
@@ -0,0 +1,161 @@
+//! Strict decoding
+//!
+//! By default a compressed column that fails to decompress is handed to serde as an error
+//! string, so the record fails to load with a message that doesn't say which column was at
+//! fault. In strict mode such failures, and serde rejecting a column's value, surface as
+//! [`Error::FieldDecode`] naming the table and column:
+//!
+//! ```rust,ignore
+//! let db = Database::init(DatabaseConfig::new(url).with_strict_decoding(true)).await?;
+//!
+//! match Trade::find_all(&db).await {
+//!     Err(Error::FieldDecode { table, column, source }) => {
+//!         warn!("{table}.{column} is corrupt: {source}");
+//!     }
+//!     other => { /* ... */ }
+//! }
+//! ```
+//!
+//! The mode belongs to the database: records read through one configured with it decode
+//! strictly, records read through any other, or decoded with `from_map` directly, don't.
+//!
+//! The functions below are used by the derive.
+
+use crate::{Error, Executor, Result, RowMap};
+use serde::de::{DeserializeOwned, DeserializeSeed, IntoDeserializer, MapAccess, Visitor};
+use serde::Deserializer;
+use std::cell::{Cell, RefCell};
+
+thread_local! {
+    /// Whether the record being decoded on this thread was read with strict decoding
+    static STRICT: Cell<bool> = const { Cell::new(false) };
+}
+
+pub fn is_strict() -> bool {
+    STRICT.with(Cell::get)
+}
+
+/// Decode a record read through `db`, strictly when `db` was configured with it
+pub fn decode_record<T: crate::Orso>(db: &impl Executor, map: RowMap) -> Result<T> {
+    decode_with(db, || T::from_map(map))
+}
+
+/// Run `decode` with the mode and keys of `db`, as [`decode_record`] does for a record
+pub(crate) fn decode_with<R>(db: &impl Executor, decode: impl FnOnce() -> Result<R>) -> Result<R> {
+    /// Puts back the previous mode, also when decoding panics
+    struct Restore(bool);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            STRICT.with(|strict| strict.set(self.0));
+        }
+    }
+
+    let _restore = Restore(STRICT.with(|strict| strict.replace(db.strict_decoding())));
+    crate::encryption::with_keys(db, decode)
+}
+
+pub(crate) fn field_decode(
+    table: &str,
+    column: &str,
+    source: impl Into<Box<dyn std::error::Error + Send + Sync>>,
+) -> Error {
+    Error::FieldDecode {
+        table: table.to_string(),
+        column: column.to_string(),
+        source: source.into(),
+    }
+}
+
+/// The JSON put in place of a column that failed to decode, or the error in strict mode
+pub fn failed_field(table: &str, column: &str, message: String) -> Result<serde_json::Value> {
+    if is_strict() {
+        return Err(field_decode(table, column, message));
+    }
+    Ok(serde_json::Value::String(message))
+}
+
+/// Deserialize a record from its columns, naming the column serde rejected in strict mode
+pub fn from_json<T: DeserializeOwned>(
+    table: &str,
+    json: serde_json::Map<String, serde_json::Value>,
+) -> Result<T> {
+    if !is_strict() {
+        return serde_json::from_value(serde_json::Value::Object(json))
+            .map_err(|e| Error::serialization(e.to_string()));
+    }
+
+    let current = RefCell::new(None);
+    let result = T::deserialize(TrackedMap {
+        entries: json.into_iter(),
+        current: &current,
+    });
+    result.map_err(|e| match current.into_inner() {
+        Some(column) => field_decode(table, &column, e),
+        // Missing fields and the like, which serde already names
+        None => Error::serialization(e.to_string()),
+    })
+}
+
+/// A JSON object deserializer remembering which column is being decoded
+struct TrackedMap<'a> {
+    entries: serde_json::map::IntoIter,
+    current: &'a RefCell<Option<String>>,
+}
+
+impl<'de> Deserializer<'de> for TrackedMap<'_> {
+    type Error = serde_json::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(
+        self,
+        visitor: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        visitor.visit_map(TrackedEntries {
+            entries: self.entries,
+            value: None,
+            current: self.current,
+        })
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string bytes byte_buf
+        option unit unit_struct newtype_struct seq tuple tuple_struct map struct enum identifier
+        ignored_any
+    }
+}
+
+struct TrackedEntries<'a> {
+    entries: serde_json::map::IntoIter,
+    value: Option<(String, serde_json::Value)>,
+    current: &'a RefCell<Option<String>>,
+}
+
+impl<'de> MapAccess<'de> for TrackedEntries<'_> {
+    type Error = serde_json::Error;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> std::result::Result<Option<K::Value>, Self::Error> {
+        let Some((key, value)) = self.entries.next() else {
+            return Ok(None);
+        };
+        let parsed = seed.deserialize(key.as_str().into_deserializer())?;
+        self.value = Some((key, value));
+        Ok(Some(parsed))
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(
+        &mut self,
+        seed: V,
+    ) -> std::result::Result<V::Value, Self::Error> {
+        let (key, value) = self
+            .value
+            .take()
+            .ok_or_else(|| serde::de::Error::custom("value requested before key"))?;
+        *self.current.borrow_mut() = Some(key);
+        let parsed = seed.deserialize(value)?;
+        *self.current.borrow_mut() = None;
+        Ok(parsed)
+    }
+}
@@ -0,0 +1,92 @@
+//! Embedded structs stored in prefixed columns
+//!
+//! A struct deriving [`OrsoEmbed`] can be a field of a model marked `#[orso_column(flatten)]`.
+//! Its fields become columns of the model's table named after the field, so `address: Address`
+//! is stored in `address_street`, `address_city`, ... instead of one serialized column:
+//!
+//! ```rust,ignore
+//! #[derive(OrsoEmbed, Serialize, Deserialize, Clone, Debug, Default)]
+//! struct Address {
+//!     street: String,
+//!     city: String,
+//!     zip: Option<String>,
+//! }
+//!
+//! #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+//! #[orso_table("customers")]
+//! struct Customer {
+//!     #[orso_column(primary_key)]
+//!     id: Option<String>,
+//!     #[orso_column(flatten)]
+//!     address: Address,
+//! }
+//!
+//! Customer::find_where(FilterOperator::Single(Filter::eq("address_city", "Berlin")), &db).await?;
+//! ```
+//!
+//! The functions below are used by the derive.
+
+use crate::FieldType;
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::{HashMap, HashSet};
+use std::sync::{Mutex, OnceLock};
+
+/// A struct whose fields are stored as columns of the model embedding it
+pub trait OrsoEmbed: Serialize + DeserializeOwned {
+    fn field_names() -> Vec<&'static str>;
+    fn field_types() -> Vec<FieldType>;
+    fn field_nullable() -> Vec<bool>;
+    /// Column definitions named after the fields, e.g. `street TEXT NOT NULL`
+    fn column_definitions() -> Vec<String>;
+}
+
+/// Column names `<prefix>_<field>` for the fields of an embedded struct
+pub fn prefixed_names(prefix: &str, names: Vec<&'static str>) -> Vec<&'static str> {
+    // Column names are `&'static str` throughout; each prefixed name is allocated once
+    static INTERNED: OnceLock<Mutex<HashSet<&'static str>>> = OnceLock::new();
+    let mut interned = INTERNED
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap_or_else(|e| e.into_inner());
+
+    names
+        .into_iter()
+        .map(|name| {
+            let column = format!("{prefix}_{name}");
+            match interned.get(column.as_str()) {
+                Some(existing) => *existing,
+                None => {
+                    let leaked: &'static str = Box::leak(column.into_boxed_str());
+                    interned.insert(leaked);
+                    leaked
+                }
+            }
+        })
+        .collect()
+}
+
+/// Replace the nested object under `field` with its prefixed columns
+pub fn flatten(
+    mut map: HashMap<String, serde_json::Value>,
+    field: &str,
+    names: &[&str],
+) -> HashMap<String, serde_json::Value> {
+    if let Some(serde_json::Value::Object(mut nested)) = map.remove(field) {
+        for name in names {
+            let value = nested.remove(*name).unwrap_or(serde_json::Value::Null);
+            map.insert(format!("{field}_{name}"), value);
+        }
+    }
+    map
+}
+
+/// Gather the prefixed columns of `field` back into a nested object
+pub fn nest(map: &mut serde_json::Map<String, serde_json::Value>, field: &str, names: &[&str]) {
+    let mut nested = serde_json::Map::new();
+    for name in names {
+        if let Some(value) = map.remove(&format!("{field}_{name}")) {
+            nested.insert(name.to_string(), value);
+        }
+    }
+    map.insert(field.to_string(), serde_json::Value::Object(nested));
+}
@@ -0,0 +1,194 @@
+//! Encrypted columns
+//!
+//! Fields marked `#[orso_column(encrypted)]` are encrypted with AES-256-GCM before they are
+//! written and decrypted when they are read, for PII such as emails or API tokens. The column is
+//! `BYTEA`; each value is stored as a header with the key id and a random nonce, followed by the
+//! ciphertext of the field's JSON:
+//!
+//! ```rust,ignore
+//! #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+//! #[orso_table("customers")]
+//! struct Customer {
+//!     #[orso_column(primary_key)]
+//!     id: Option<String>,
+//!     #[orso_column(encrypted)]
+//!     email: String,
+//!     #[orso_column(encrypted)]
+//!     api_token: Option<String>,
+//! }
+//!
+//! let config = DatabaseConfig::new(url)
+//!     .with_encryption_key(EncryptionKey::new(1, old_key))
+//!     .with_encryption_key(EncryptionKey::new(2, current_key)); // encrypts new writes
+//! let db = Database::init(config).await?;
+//! ```
+//!
+//! Each database encrypts and decrypts with its own keys, which operations install for the
+//! records they convert. Values are read with whichever key their header names, so old keys stay
+//! configured until every row has been rewritten with the new one.
+//!
+//! Encryption uses a random nonce, so equal values have different ciphertexts: encrypted columns
+//! can't be filtered, sorted or made unique on.
+
+use crate::{Error, Executor, Result, RowMap, Value};
+use aes_gcm::aead::{Aead, AeadCore, KeyInit, OsRng};
+use aes_gcm::{Aes256Gcm, Nonce};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+const MAGIC: &[u8; 4] = b"ORSE";
+const VERSION: u8 = 1;
+const NONCE_LEN: usize = 12;
+/// Magic, version, key id and nonce
+const HEADER_LEN: usize = MAGIC.len() + 1 + 4 + NONCE_LEN;
+
+/// 256-bit AES-GCM key, identified by `id` in the values it encrypts
+#[derive(Clone, PartialEq, Eq)]
+pub struct EncryptionKey {
+    id: u32,
+    key: [u8; 32],
+}
+
+impl EncryptionKey {
+    pub fn new(id: u32, key: [u8; 32]) -> Self {
+        Self { id, key }
+    }
+
+    pub fn id(&self) -> u32 {
+        self.id
+    }
+}
+
+impl std::fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EncryptionKey")
+            .field("id", &self.id)
+            .finish_non_exhaustive()
+    }
+}
+
+/// The keys of a database, from [`DatabaseConfig::with_encryption_key`](crate::DatabaseConfig)
+#[derive(Default)]
+pub struct Keyring {
+    ciphers: HashMap<u32, Aes256Gcm>,
+    active: Option<u32>,
+}
+
+impl Keyring {
+    /// A keyring encrypting with the last of `keys`
+    pub fn new(keys: &[EncryptionKey]) -> Self {
+        let mut keyring = Self::default();
+        for key in keys {
+            let cipher = Aes256Gcm::new(&key.key.into());
+            keyring.ciphers.insert(key.id, cipher);
+            keyring.active = Some(key.id);
+        }
+        keyring
+    }
+}
+
+impl std::fmt::Debug for Keyring {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Keyring")
+            .field("active", &self.active)
+            .finish_non_exhaustive()
+    }
+}
+
+thread_local! {
+    /// Keys of the database whose records are being converted on this thread
+    static CURRENT: RefCell<Option<Arc<Keyring>>> = const { RefCell::new(None) };
+}
+
+/// Run `convert` with the keys of `db`, which the derive's `to_map` and `from_map` use
+pub(crate) fn with_keys<R>(db: &impl Executor, convert: impl FnOnce() -> R) -> R {
+    /// Puts back the previous keys, also when converting panics
+    struct Restore(Option<Arc<Keyring>>);
+
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            CURRENT.with(|current| *current.borrow_mut() = self.0.take());
+        }
+    }
+
+    let keys = db.keyring().cloned();
+    let _restore = Restore(CURRENT.with(|current| current.replace(keys)));
+    convert()
+}
+
+/// Convert `model` into the row written through `db`, encrypting with its keys
+pub fn encode_record<T: crate::Orso>(db: &impl Executor, model: &T) -> Result<RowMap> {
+    with_keys(db, || model.to_map())
+}
+
+fn current_keys() -> Option<Arc<Keyring>> {
+    CURRENT.with(|current| current.borrow().clone())
+}
+
+/// Encrypt `plaintext` with the active key of the current database
+pub fn encrypt(plaintext: &[u8]) -> Result<Vec<u8>> {
+    let keyring = current_keys().unwrap_or_default();
+    let Some((id, cipher)) = keyring
+        .active
+        .and_then(|id| Some((id, keyring.ciphers.get(&id)?)))
+    else {
+        return Err(Error::Config {
+            message: "No encryption key configured for encrypted columns".to_string(),
+            parameter: Some("encryption_key".to_string()),
+            source: None,
+        });
+    };
+
+    let nonce = Aes256Gcm::generate_nonce(&mut OsRng);
+    let ciphertext = cipher
+        .encrypt(&nonce, plaintext)
+        .map_err(|_| Error::serialization("Failed to encrypt value"))?;
+
+    let mut blob = Vec::with_capacity(HEADER_LEN + ciphertext.len());
+    blob.extend_from_slice(MAGIC);
+    blob.push(VERSION);
+    blob.extend_from_slice(&id.to_be_bytes());
+    blob.extend_from_slice(&nonce);
+    blob.extend_from_slice(&ciphertext);
+    Ok(blob)
+}
+
+/// Decrypt a value written by [`encrypt`] with the key named in its header
+pub fn decrypt(blob: &[u8]) -> Result<Vec<u8>> {
+    if blob.len() < HEADER_LEN || &blob[..MAGIC.len()] != MAGIC || blob[MAGIC.len()] != VERSION {
+        return Err(Error::serialization("Not an encrypted column value"));
+    }
+    let id_start = MAGIC.len() + 1;
+    let id = u32::from_be_bytes(blob[id_start..id_start + 4].try_into().unwrap());
+    let nonce = Nonce::from_slice(&blob[id_start + 4..HEADER_LEN]);
+
+    let keyring = current_keys().unwrap_or_default();
+    let cipher = keyring.ciphers.get(&id).ok_or_else(|| Error::Config {
+        message: format!("Encryption key {id} is not configured"),
+        parameter: Some("encryption_key".to_string()),
+        source: None,
+    })?;
+    cipher
+        .decrypt(nonce, &blob[HEADER_LEN..])
+        .map_err(|_| Error::serialization(format!("Failed to decrypt value with key {id}")))
+}
+
+/// Encrypt the JSON of a field; `null` stays `NULL`
+pub fn encrypt_json(value: &serde_json::Value) -> Result<Value> {
+    if value.is_null() {
+        return Ok(Value::Null);
+    }
+    encrypt(&serde_json::to_vec(value)?).map(Value::Blob)
+}
+
+/// Decrypt the column of `field` in place into the JSON it was encrypted from
+pub fn decrypt_field(map: &mut RowMap, field: &str) -> Result<()> {
+    if let Some(Value::Blob(blob)) = map.get(field) {
+        let plaintext =
+            decrypt(blob).map_err(|e| Error::serialization_field(e.to_string(), field))?;
+        let json = serde_json::from_slice(&plaintext)?;
+        map.insert(field.to_string(), Value::Json(json));
+    }
+    Ok(())
+}
@@ -15,12 +15,14 @@ pub enum Error {
     },
 
     /// PostgreSQL query execution errors
-    #[error("PostgreSQL error: {message}")]
+    #[error("PostgreSQL error: {message}{}", context_suffix(context))]
     PostgreSql {
         message: String,
         code: Option<String>,
         #[source]
         source: Option<Box<dyn std::error::Error + Send + Sync>>,
+        /// The statement that failed, when it is known
+        context: Option<Box<ErrorContext>>,
     },
 
     /// Connection pool errors (timeout, exhausted, etc.)
@@ -31,6 +33,22 @@ pub enum Error {
         source: Option<Box<dyn std::error::Error + Send + Sync>>,
     },
 
+    /// More statements were waiting than `max_concurrent_queries` allows within the queue timeout
+    #[error("Database overloaded: {message}")]
+    Overloaded {
+        message: String,
+        /// How long the statement waited for a slot before it was rejected
+        waited: std::time::Duration,
+    },
+
+    /// The circuit breaker is failing statements fast after repeated connection failures
+    #[error("Circuit open: {message}")]
+    CircuitOpen {
+        message: String,
+        /// Time until the breaker lets a trial statement through
+        retry_after: std::time::Duration,
+    },
+
     // === Query Building Errors ===
     /// SQL query building and parsing errors
     #[error("Query error: {message}")]
@@ -65,12 +83,23 @@ pub enum Error {
         source: Option<Box<dyn std::error::Error + Send + Sync>>,
     },
 
+    /// A column that couldn't be decoded into its field, see [`crate::decode`]
+    #[error("Failed to decode {table}.{column}: {source}")]
+    FieldDecode {
+        table: String,
+        column: String,
+        #[source]
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
     /// Data validation errors (constraints, formats, etc.)
     #[error("Validation error: {message}")]
     Validation {
         message: String,
         field: Option<String>,
         value: Option<String>,
+        /// Every failing field when several checks ran, e.g. from `validate()`
+        errors: Vec<FieldError>,
     },
 
     /// Type conversion errors
@@ -175,6 +204,22 @@ pub enum Error {
     },
 }
 
+/// A single failed field check reported by [`Error::Validation`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            field: field.into(),
+            message: message.into(),
+        }
+    }
+}
+
 // === Error Construction Helper Methods ===
 impl Error {
     /// Create a connection error with context
@@ -199,6 +244,20 @@ impl Error {
             message: message.into(),
             code,
             source: None,
+            context: None,
+        }
+    }
+
+    /// Create a PostgreSQL error from one the caller keeps, with a copy of the server's
+    /// report as source for its detail and constraint
+    pub(crate) fn postgres_ref(err: &tokio_postgres::Error) -> Self {
+        Self::PostgreSql {
+            message: postgres_message(err),
+            code: err.code().map(|c| c.code().to_string()),
+            source: err
+                .as_db_error()
+                .map(|db| Box::new(db.clone()) as Box<dyn std::error::Error + Send + Sync>),
+            context: None,
         }
     }
 
@@ -226,6 +285,7 @@ impl Error {
             message: message.into(),
             field: None,
             value: None,
+            errors: Vec::new(),
         }
     }
 
@@ -235,6 +295,43 @@ impl Error {
             message: message.into(),
             field: Some(field.into()),
             value,
+            errors: Vec::new(),
+        }
+    }
+
+    /// Create a validation error listing every failing field
+    pub fn validation_errors(errors: Vec<FieldError>) -> Self {
+        let message = errors
+            .iter()
+            .map(|e| format!("{}: {}", e.field, e.message))
+            .collect::<Vec<_>>()
+            .join("; ");
+        let field = match errors.as_slice() {
+            [single] => Some(single.field.clone()),
+            _ => None,
+        };
+
+        Self::Validation {
+            message,
+            field,
+            value: None,
+            errors,
+        }
+    }
+
+    /// Create an overloaded error for a statement rejected after waiting `waited`
+    pub fn overloaded(message: impl Into<String>, waited: std::time::Duration) -> Self {
+        Self::Overloaded {
+            message: message.into(),
+            waited,
+        }
+    }
+
+    /// Create a circuit open error for a statement rejected by the circuit breaker
+    pub fn circuit_open(message: impl Into<String>, retry_after: std::time::Duration) -> Self {
+        Self::CircuitOpen {
+            message: message.into(),
+            retry_after,
         }
     }
 
@@ -348,9 +445,10 @@ impl From<tokio_postgres::Error> for Error {
         let code = err.code().map(|c| c.code().to_string());
 
         Self::PostgreSql {
-            message: err.to_string(),
+            message: postgres_message(&err),
             code,
             source: Some(Box::new(err)),
+            context: None,
         }
     }
 }
@@ -384,6 +482,16 @@ impl From<std::io::Error> for Error {
     }
 }
 
+impl From<csv::Error> for Error {
+    fn from(err: csv::Error) -> Self {
+        Self::Serialization {
+            message: format!("CSV error: {}", err),
+            field: None,
+            source: Some(Box::new(err)),
+        }
+    }
+}
+
 impl From<chrono::ParseError> for Error {
     fn from(err: chrono::ParseError) -> Self {
         Self::DateTime {
@@ -403,8 +511,103 @@ impl From<anyhow::Error> for Error {
     }
 }
 
-// For backward compatibility during transition
+/// The server's message of an error PostgreSQL reported, with its detail, and the client's
+/// description of any other error
+pub(crate) fn postgres_message(err: &tokio_postgres::Error) -> String {
+    match err.as_db_error() {
+        Some(db) => match db.detail() {
+            Some(detail) => format!("{}: {}", db.message(), detail),
+            None => db.message().to_string(),
+        },
+        None => err.to_string(),
+    }
+}
+
+/// The statement a database error came from; parameter values are never included
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorContext {
+    /// `select`, `insert`, `update`, `delete` or `other`
+    pub operation: &'static str,
+    pub table: Option<String>,
+    pub sql: String,
+    pub param_count: usize,
+    /// 1-based character position in `sql` PostgreSQL reported the error at
+    pub position: Option<usize>,
+}
+
+impl ErrorContext {
+    pub fn new(sql: &str, param_count: usize) -> Self {
+        Self {
+            operation: crate::database::statement_kind(sql),
+            table: crate::database::statement_table(sql).map(str::to_string),
+            sql: sql.to_string(),
+            param_count,
+            position: None,
+        }
+    }
+
+    /// The statement from the reported position on, for pointing at the failing clause
+    pub fn near(&self) -> Option<&str> {
+        let position = self.position?.checked_sub(1)?;
+        let start = self.sql.char_indices().nth(position)?.0;
+        Some(&self.sql[start..])
+    }
+}
+
+fn context_suffix(context: &Option<Box<ErrorContext>>) -> String {
+    context
+        .as_ref()
+        .map(|c| format!(" ({c})"))
+        .unwrap_or_default()
+}
+
+impl std::fmt::Display for ErrorContext {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.operation)?;
+        if let Some(table) = &self.table {
+            write!(f, " on {}", table)?;
+        }
+        write!(f, ", {} params", self.param_count)?;
+        if let Some(position) = self.position {
+            write!(f, " at character {position}")?;
+        }
+        write!(f, ": {}", self.sql)
+    }
+}
+
 impl Error {
+    /// Attach the statement that failed to a PostgreSQL error; other errors are returned as is
+    pub fn with_context(mut self, ctx: ErrorContext) -> Self {
+        if let Self::PostgreSql { context, .. } = &mut self {
+            *context = Some(Box::new(ctx));
+        }
+        self
+    }
+
+    /// Whether PostgreSQL aborted a serializable transaction that can be retried (`40001`)
+    pub fn is_serialization_failure(&self) -> bool {
+        matches!(self, Self::PostgreSql { code: Some(code), .. } if code == "40001")
+    }
+
+    /// The statement a PostgreSQL error came from
+    pub fn context(&self) -> Option<&ErrorContext> {
+        match self {
+            Self::PostgreSql { context, .. } => context.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// The driver error a PostgreSQL error was converted from
+    pub(crate) fn postgres_source(&self) -> Option<&tokio_postgres::Error> {
+        match self {
+            Self::PostgreSql {
+                source: Some(source),
+                ..
+            } => source.downcast_ref(),
+            _ => None,
+        }
+    }
+
     /// Legacy method for serde deserialization errors
     pub fn custom(message: impl Into<String>) -> Self {
         Self::Internal {
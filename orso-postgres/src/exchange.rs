@@ -0,0 +1,240 @@
+//! Bulk export and import
+//!
+//! CSV is streamed through `COPY` rather than fetched or inserted one statement at a time, and
+//! JSON Lines exports page through the table by primary key, for backups, data dumps and
+//! exchanging data with other tools:
+//!
+//! ```rust,ignore
+//! let file = std::fs::File::create("users.csv")?;
+//! User::export_csv(FilterOperator::Single(Filter::eq("active", true)), file, &db).await?;
+//!
+//! let imported = User::import_csv(std::fs::File::open("users.csv")?, &db).await?;
+//!
+//! // One JSON document per line, resuming after the last key a client received
+//! let lines = User::export_ndjson_after(FilterOperator::Custom("TRUE".into()), last_id, &db);
+//! ```
+//!
+//! CSV files have a header row naming the model's columns. Imports check every value against
+//! the column's type before it is sent and abort on the first invalid one, leaving the table
+//! untouched. Empty values are `NULL` in nullable columns and empty strings otherwise; leave the
+//! primary key column out to have keys generated.
+
+use crate::{
+    Database, Error, Executor, FieldType, Filter, FilterOperations, FilterOperator, Orso,
+    QueryBuilder, Result, Sort, Utils,
+};
+use bytes::Bytes;
+use futures_util::{pin_mut, SinkExt, Stream, TryStreamExt};
+use std::io::{Read, Write};
+use tracing::instrument;
+
+/// Rows buffered before they are sent to the server during an import
+const IMPORT_CHUNK_BYTES: usize = 64 * 1024;
+
+/// Records fetched per query during a JSON Lines export
+pub const NDJSON_PAGE_ROWS: u32 = 1000;
+
+/// Up to `limit` records matching `filter` with a primary key above `after`, in key order
+///
+/// Walking a table by key instead of by offset keeps each page cheap and stable while rows are
+/// added or removed.
+pub(crate) async fn page_after<T: Orso>(
+    filter: &FilterOperator,
+    after: Option<&str>,
+    limit: u32,
+    db: &impl Executor,
+) -> Result<Vec<T>> {
+    let pk = T::primary_key_field();
+    let mut query = QueryBuilder::new(T::table_name())
+        ._where(filter.clone())
+        .order_by(Sort::asc(pk))
+        .limit(limit);
+    if let Some(after) = after {
+        query = query._where(FilterOperator::Single(Filter::gt(pk, after)));
+    }
+    query.execute::<T>(db).await
+}
+
+/// Stream the records matching `filter` with a primary key above `after` as JSON lines
+///
+/// Each item is one record serialized as JSON and terminated by a newline. Records come in
+/// primary key order, so an interrupted export resumes from the key of the last line received.
+pub fn export_ndjson<'a, T: Orso + 'a>(
+    filter: FilterOperator,
+    after: Option<String>,
+    db: &'a impl Executor,
+) -> impl Stream<Item = Result<String>> + 'a {
+    let pages = futures_util::stream::try_unfold(Some(after), move |after| {
+        let filter = filter.clone();
+        async move {
+            let Some(after) = after else {
+                return Ok::<_, Error>(None);
+            };
+            let records: Vec<T> =
+                page_after(&filter, after.as_deref(), NDJSON_PAGE_ROWS, db).await?;
+            if records.is_empty() {
+                return Ok(None);
+            }
+
+            let next = match records.last().and_then(Orso::get_primary_key) {
+                Some(last) if records.len() == NDJSON_PAGE_ROWS as usize => Some(Some(last)),
+                _ => None,
+            };
+            let lines = records
+                .iter()
+                .map(|record| Ok(serde_json::to_string(record)? + "\n"))
+                .collect::<Vec<Result<String>>>();
+            Ok(Some((futures_util::stream::iter(lines), next)))
+        }
+    });
+    pages.try_flatten()
+}
+
+/// Write the records matching `filter` to `writer` as CSV with a header row
+#[instrument(name = "orso.export_csv", skip_all, fields(table = T::table_name()))]
+pub async fn export_csv<T: Orso>(
+    filter: FilterOperator,
+    mut writer: impl Write,
+    db: &Database,
+) -> Result<()> {
+    let filter = match crate::scope::active_scope::<T>() {
+        Some(scope) => FilterOperator::and(vec![filter, scope]),
+        None => filter,
+    };
+    let sql = format!(
+        "COPY (SELECT {} FROM {} WHERE {}) TO STDOUT WITH (FORMAT csv, HEADER)",
+        T::field_names().join(", "),
+        T::table_name(),
+        FilterOperations::build_filter_operator_inline(&filter)?,
+    );
+
+    let statement = sql.as_str();
+    db.with_connection(&sql, |client| async move {
+        let stream = client.copy_out(statement).await?;
+        pin_mut!(stream);
+        while let Some(chunk) = stream.try_next().await? {
+            writer.write_all(&chunk)?;
+        }
+        writer.flush()?;
+        Ok(())
+    })
+    .await
+}
+
+/// Insert the records of a CSV file with a header row, returning how many were imported
+#[instrument(name = "orso.import_csv", skip_all, fields(table = T::table_name()))]
+pub async fn import_csv<T: Orso>(reader: impl Read, db: &Database) -> Result<u64> {
+    let mut reader = csv::Reader::from_reader(reader);
+    let names = T::field_names();
+    let types = T::field_types();
+    let nullable = T::field_nullable();
+    let compressed = T::field_compressed();
+
+    let header = reader.headers()?.clone();
+    let columns = header
+        .iter()
+        .map(|column| {
+            names
+                .iter()
+                .position(|name| *name == column)
+                .ok_or_else(|| Error::validation_field("Unknown column", column, None))
+        })
+        .collect::<Result<Vec<_>>>()?;
+
+    // Unquoted empty values are NULL in COPY; keep them as empty strings where NULL isn't allowed
+    let not_null: Vec<&str> = columns
+        .iter()
+        .filter(|index| !nullable[**index])
+        .map(|index| names[*index])
+        .collect();
+    let force_not_null = if not_null.is_empty() {
+        String::new()
+    } else {
+        format!(", FORCE_NOT_NULL ({})", not_null.join(", "))
+    };
+    let sql = format!(
+        "COPY {} ({}) FROM STDIN WITH (FORMAT csv{force_not_null})",
+        T::table_name(),
+        header.iter().collect::<Vec<_>>().join(", "),
+    );
+
+    let statement = sql.as_str();
+    db.with_connection(&sql, |client| async move {
+        let sink = client.copy_in::<_, Bytes>(statement).await?;
+        pin_mut!(sink);
+
+        // Dropping the sink before `finish` aborts the COPY, so an invalid row imports nothing
+        let mut rows = csv::Writer::from_writer(Vec::new());
+        for record in reader.records() {
+            let record = record?;
+            let line = record.position().map_or(0, |position| position.line());
+            for (value, index) in record.iter().zip(&columns) {
+                if !compressed[*index] {
+                    check_value(value, &types[*index], nullable[*index], names[*index], line)?;
+                }
+            }
+            rows.write_record(&record)?;
+
+            if rows.get_ref().len() >= IMPORT_CHUNK_BYTES {
+                sink.send(take_rows(&mut rows)?).await?;
+            }
+        }
+        let remaining = take_rows(&mut rows)?;
+        if !remaining.is_empty() {
+            sink.send(remaining).await?;
+        }
+        Ok(sink.finish().await?)
+    })
+    .await
+}
+
+/// The CSV written so far, leaving an empty writer behind
+fn take_rows(rows: &mut csv::Writer<Vec<u8>>) -> Result<Bytes> {
+    let rows = std::mem::replace(rows, csv::Writer::from_writer(Vec::new()));
+    let buffer = rows
+        .into_inner()
+        .map_err(|e| Error::serialization(format!("CSV error: {}", e.error())))?;
+    Ok(Bytes::from(buffer))
+}
+
+fn check_value(
+    value: &str,
+    field_type: &FieldType,
+    nullable: bool,
+    column: &str,
+    line: u64,
+) -> Result<()> {
+    let invalid = |message: &str| {
+        Error::validation_field(
+            format!("{message} on line {line}"),
+            column,
+            Some(value.to_string()),
+        )
+    };
+    if value.is_empty() {
+        return match field_type {
+            FieldType::Text => Ok(()),
+            _ if nullable => Ok(()),
+            _ => Err(invalid("Missing value")),
+        };
+    }
+
+    let value = value.trim();
+    let valid = match field_type {
+        FieldType::Integer => value.parse::<i32>().is_ok(),
+        FieldType::BigInt => value.parse::<i64>().is_ok(),
+        FieldType::Numeric => value.parse::<f64>().is_ok(),
+        FieldType::Boolean => matches!(
+            value.to_ascii_lowercase().as_str(),
+            "t" | "true" | "f" | "false" | "y" | "yes" | "n" | "no" | "on" | "off" | "1" | "0"
+        ),
+        FieldType::Timestamp => Utils::parse_timestamp(value).is_ok(),
+        FieldType::JsonB => serde_json::from_str::<serde_json::Value>(value).is_ok(),
+        _ => true,
+    };
+    if valid {
+        Ok(())
+    } else {
+        Err(invalid(&format!("Invalid {field_type:?} value")))
+    }
+}
@@ -0,0 +1,193 @@
+//! Statement executors
+//!
+//! [`Executor`] is what CRUD operations, [`Orso`](crate::Orso) methods and
+//! [`QueryBuilder`](crate::QueryBuilder) run their statements on. It is implemented by
+//! [`Database`], pooled connections, transactions and, with the `testing` feature, the mock and
+//! test databases, so the same call works everywhere:
+//!
+//! ```rust,ignore
+//! user.insert(&db).await?;
+//!
+//! let mut client = db.pool.get().await?;
+//! let tx = client.transaction().await?;
+//! user.insert(&tx).await?;
+//! order.insert(&tx).await?;
+//! tx.commit().await?;
+//! ```
+//!
+//! Connections and transactions taken straight from the pool don't know the [`Database`] they
+//! came from, so they have no keys for encrypted columns and always decode leniently. The
+//! [`Transaction`](crate::Transaction) of [`Database::transaction_with`] uses its settings.
+//!
+//! Rows come back as column maps, the shape `Orso::from_map` reads. A [`Session`](crate::Session)
+//! wraps any of them to cache records found by primary key.
+
+use crate::database::{with_statement_context, writes_rows};
+use crate::{Database, Error, Result, Value};
+use std::collections::HashMap;
+use tokio_postgres::types::ToSql;
+
+/// A row read through an [`Executor`], keyed by column name
+pub type RowMap = HashMap<String, Value>;
+
+/// Something statements can be run on
+#[allow(async_fn_in_trait)]
+pub trait Executor: Send + Sync {
+    /// Run a statement and return the number of rows affected
+    async fn execute(&self, sql: &str, params: &[&(dyn ToSql + Send + Sync)]) -> Result<u64>;
+
+    /// Run a query and return its rows
+    async fn query_rows(
+        &self,
+        sql: &str,
+        params: &[&(dyn ToSql + Send + Sync)],
+    ) -> Result<Vec<RowMap>>;
+
+    /// Rows cached by primary key for the current unit of work, see [`crate::Session`]
+    fn identity_map(&self) -> Option<&crate::session::IdentityMap> {
+        None
+    }
+
+    /// Where statements are recorded, see [`Database::with_query_log`]
+    fn query_log(&self) -> Option<&crate::advisor::QueryLog> {
+        None
+    }
+
+    /// Whether records read through it fail on columns that don't decode, see [`crate::decode`]
+    fn strict_decoding(&self) -> bool {
+        false
+    }
+
+    /// Keys for encrypted columns, see [`crate::encryption`]
+    fn keyring(&self) -> Option<&std::sync::Arc<crate::encryption::Keyring>> {
+        None
+    }
+}
+
+impl Executor for Database {
+    async fn execute(&self, sql: &str, params: &[&(dyn ToSql + Send + Sync)]) -> Result<u64> {
+        Database::execute(self, sql, params).await
+    }
+
+    async fn query_rows(
+        &self,
+        sql: &str,
+        params: &[&(dyn ToSql + Send + Sync)],
+    ) -> Result<Vec<RowMap>> {
+        to_maps(Database::query(self, sql, params).await?)
+    }
+
+    fn query_log(&self) -> Option<&crate::advisor::QueryLog> {
+        Database::query_log(self)
+    }
+
+    fn strict_decoding(&self) -> bool {
+        Database::strict_decoding(self)
+    }
+
+    fn keyring(&self) -> Option<&std::sync::Arc<crate::encryption::Keyring>> {
+        Database::keyring(self)
+    }
+}
+
+impl<E: Executor> Executor for &E {
+    async fn execute(&self, sql: &str, params: &[&(dyn ToSql + Send + Sync)]) -> Result<u64> {
+        (**self).execute(sql, params).await
+    }
+
+    async fn query_rows(
+        &self,
+        sql: &str,
+        params: &[&(dyn ToSql + Send + Sync)],
+    ) -> Result<Vec<RowMap>> {
+        (**self).query_rows(sql, params).await
+    }
+
+    fn identity_map(&self) -> Option<&crate::session::IdentityMap> {
+        (**self).identity_map()
+    }
+
+    fn query_log(&self) -> Option<&crate::advisor::QueryLog> {
+        (**self).query_log()
+    }
+
+    fn strict_decoding(&self) -> bool {
+        (**self).strict_decoding()
+    }
+
+    fn keyring(&self) -> Option<&std::sync::Arc<crate::encryption::Keyring>> {
+        (**self).keyring()
+    }
+}
+
+/// Implement [`Executor`] for a type that derefs to a tokio-postgres client or transaction
+macro_rules! client_executor {
+    ($ty:ty => $target:ty $(, $prepare:ident)?) => {
+        impl Executor for $ty {
+            async fn execute(
+                &self,
+                sql: &str,
+                params: &[&(dyn ToSql + Send + Sync)],
+            ) -> Result<u64> {
+                $($prepare(self, sql).await?;)?
+                let result = <$target>::execute(self, sql, &sync_params(params)).await;
+                with_statement_context(result, sql, params.len())
+            }
+
+            async fn query_rows(
+                &self,
+                sql: &str,
+                params: &[&(dyn ToSql + Send + Sync)],
+            ) -> Result<Vec<RowMap>> {
+                $($prepare(self, sql).await?;)?
+                let result = <$target>::query(self, sql, &sync_params(params)).await;
+                to_maps(with_statement_context(result, sql, params.len())?)
+            }
+        }
+    };
+}
+
+client_executor!(tokio_postgres::Client => tokio_postgres::Client);
+client_executor!(deadpool_postgres::Object => tokio_postgres::Client);
+client_executor!(tokio_postgres::Transaction<'_> => tokio_postgres::Transaction, set_actor_for);
+client_executor!(deadpool_postgres::Transaction<'_> => tokio_postgres::Transaction, set_actor_for);
+
+/// Expose the current actor to audit triggers before `sql` if it writes rows
+///
+/// These transactions keep no state of their own, so each write sets it again. The
+/// [`Transaction`](crate::Transaction) of [`Database::transaction_with`] sets it once.
+async fn set_actor_for(tx: &tokio_postgres::Transaction<'_>, sql: &str) -> Result<()> {
+    if !writes_rows(sql) {
+        return Ok(());
+    }
+    set_actor(tx).await
+}
+
+/// Expose the current actor to audit triggers for the rest of the transaction
+pub(crate) async fn set_actor(tx: &tokio_postgres::Transaction<'_>) -> Result<()> {
+    if let Some(ctx) = crate::OperationContext::current() {
+        tx.execute("SELECT set_config('orso.actor', $1, true)", &[&ctx.actor])
+            .await?;
+    }
+    Ok(())
+}
+
+pub(crate) fn sync_params<'a>(
+    params: &[&'a (dyn ToSql + Send + Sync)],
+) -> Vec<&'a (dyn ToSql + Sync)> {
+    params.iter().map(|p| *p as &(dyn ToSql + Sync)).collect()
+}
+
+fn to_maps(rows: Vec<tokio_postgres::Row>) -> Result<Vec<RowMap>> {
+    rows.iter()
+        .map(crate::operations::CrudOperations::row_to_map)
+        .collect()
+}
+
+/// Read the result of a `SELECT COUNT(*)` query
+pub(crate) fn count(rows: &[RowMap]) -> Result<u64> {
+    match rows.first().and_then(|row| row.get("count")) {
+        Some(Value::Integer(count)) => Ok(*count as u64),
+        _ => Err(Error::query("No count result")),
+    }
+}
@@ -0,0 +1,163 @@
+//! Query plan assertions
+//!
+//! [`ExplainAssert`] runs `EXPLAIN` for a [`QueryBuilder`] and checks properties of the plan,
+//! so tests can catch generated SQL that stops using an index:
+//!
+//! ```rust,ignore
+//! ExplainAssert::new(&QueryBuilder::new("users")._where(filter), &db)
+//!     .await?
+//!     .uses_index("idx_users_email")
+//!     .no_seq_scan("users");
+//! ```
+//!
+//! Tables with only a handful of rows are usually scanned sequentially whatever indexes exist;
+//! tests can run against a config with `with_on_connect_sql(["SET enable_seqscan = off"])`.
+
+use crate::{Executor, QueryBuilder, Result, Value};
+use tracing::instrument;
+
+/// A node of a query plan, e.g. `Index Scan using users_pkey on users`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlanNode {
+    pub node_type: String,
+    pub relation: Option<String>,
+    pub index: Option<String>,
+}
+
+impl PlanNode {
+    /// Parse a plan line; returns `None` for detail lines such as `Filter: ...`
+    fn parse(line: &str, first: bool) -> Option<Self> {
+        let trimmed = line.trim_start();
+        let label = match trimmed.strip_prefix("->") {
+            Some(rest) => rest.trim_start(),
+            None if first => trimmed,
+            None => return None,
+        };
+        let label = label.split("  (").next().unwrap_or(label).trim();
+
+        if let Some((node_type, rest)) = label.split_once(" using ") {
+            let mut words = rest.split_whitespace();
+            let index = words.next().map(str::to_string);
+            let relation = words.skip_while(|w| *w != "on").nth(1).map(str::to_string);
+            return Some(Self {
+                node_type: node_type.to_string(),
+                relation,
+                index,
+            });
+        }
+
+        if let Some((node_type, rest)) = label.split_once(" on ") {
+            let target = rest.split_whitespace().next().map(str::to_string);
+            let is_index = node_type.ends_with("Index Scan");
+            return Some(Self {
+                node_type: node_type.to_string(),
+                relation: if is_index { None } else { target.clone() },
+                index: if is_index { target } else { None },
+            });
+        }
+
+        Some(Self {
+            node_type: label.to_string(),
+            relation: None,
+            index: None,
+        })
+    }
+}
+
+/// The plan PostgreSQL chose for a query
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct QueryPlan {
+    pub sql: String,
+    pub text: String,
+    pub nodes: Vec<PlanNode>,
+}
+
+impl QueryPlan {
+    /// Run `EXPLAIN` for the query `builder` would execute
+    #[instrument(name = "orso.explain", skip_all)]
+    pub async fn of(builder: &QueryBuilder, db: &impl Executor) -> Result<Self> {
+        let (sql, params) = builder.build()?;
+        let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
+            params.iter().map(|p| p.as_ref()).collect();
+
+        let rows = db
+            .query_rows(&format!("EXPLAIN {sql}"), &param_refs)
+            .await?;
+        let lines: Vec<String> = rows
+            .into_iter()
+            .filter_map(|mut row| match row.remove("QUERY PLAN") {
+                Some(Value::Text(line)) => Some(line),
+                _ => None,
+            })
+            .collect();
+        let nodes = lines
+            .iter()
+            .enumerate()
+            .filter_map(|(i, line)| PlanNode::parse(line, i == 0))
+            .collect();
+
+        Ok(Self {
+            sql,
+            text: lines.join("\n"),
+            nodes,
+        })
+    }
+
+    /// Whether any node scans `index`
+    pub fn uses_index(&self, index: &str) -> bool {
+        self.nodes
+            .iter()
+            .any(|node| node.index.as_deref() == Some(index))
+    }
+
+    /// Whether `table` is read with a sequential scan
+    pub fn has_seq_scan(&self, table: &str) -> bool {
+        self.nodes.iter().any(|node| {
+            node.node_type.ends_with("Seq Scan") && node.relation.as_deref() == Some(table)
+        })
+    }
+}
+
+/// Panicking assertions over a [`QueryPlan`], for use in tests
+#[derive(Debug, Clone)]
+pub struct ExplainAssert {
+    plan: QueryPlan,
+}
+
+impl ExplainAssert {
+    pub async fn new(builder: &QueryBuilder, db: &impl Executor) -> Result<Self> {
+        Ok(Self {
+            plan: QueryPlan::of(builder, db).await?,
+        })
+    }
+
+    pub fn plan(&self) -> &QueryPlan {
+        &self.plan
+    }
+
+    /// Assert that the plan scans `index`
+    #[track_caller]
+    pub fn uses_index(&self, index: &str) -> &Self {
+        assert!(
+            self.plan.uses_index(index),
+            "expected plan to use index `{}`\nquery: {}\nplan:\n{}",
+            index,
+            self.plan.sql,
+            self.plan.text
+        );
+        self
+    }
+
+    /// Assert that `table` is not read with a sequential scan
+    #[track_caller]
+    pub fn no_seq_scan(&self, table: &str) -> &Self {
+        assert!(
+            !self.plan.has_seq_scan(table),
+            "expected no sequential scan on `{}`\nquery: {}\nplan:\n{}",
+            table,
+            self.plan.sql,
+            self.plan.text
+        );
+        self
+    }
+}
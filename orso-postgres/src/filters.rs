@@ -1,6 +1,9 @@
-use crate::{Operator, Result, Value};
+use crate::{Error, Operator, Result, Value};
 use serde::{Deserialize, Serialize};
 
+/// Most parameters PostgreSQL accepts in one statement
+pub const MAX_BIND_PARAMS: usize = 65535;
+
 // Filter operator for building complex queries
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum FilterOperator {
@@ -14,6 +17,44 @@ pub enum FilterOperator {
     Not(Box<FilterOperator>),
     /// Custom SQL condition
     Custom(String),
+    /// Condition on a JSON path expression such as `address->>'city'`
+    JsonPath(String, Operator, FilterValue),
+    /// SQL fragment whose `$?` placeholders are bound to the values, see [`Filter::raw`]
+    Raw(String, Vec<Value>),
+}
+
+/// A column name generated by `#[derive(Orso)]`, see the model's `col()`
+///
+/// Accepted wherever a filter or sort takes a column name: `Filter::gt(User::col().age, 25)`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Column(&'static str);
+
+impl Column {
+    pub const fn new(name: &'static str) -> Self {
+        Self(name)
+    }
+
+    pub const fn name(self) -> &'static str {
+        self.0
+    }
+}
+
+impl From<Column> for String {
+    fn from(column: Column) -> Self {
+        column.0.to_string()
+    }
+}
+
+impl AsRef<str> for Column {
+    fn as_ref(&self) -> &str {
+        self.0
+    }
+}
+
+impl std::fmt::Display for Column {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.0)
+    }
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -59,12 +100,12 @@ impl Filter {
         }
     }
 
-    /// Create an equality filter
+    /// Create an equality filter; `Value::Null` matches rows where the column is null
     pub fn eq(column: impl Into<String>, value: impl Into<Value>) -> Self {
         Self::new(column, Operator::Eq, FilterValue::Single(value.into()))
     }
 
-    /// Create a not-equal filter
+    /// Create a not-equal filter; `Value::Null` matches rows where the column is not null
     pub fn ne(column: impl Into<String>, value: impl Into<Value>) -> Self {
         Self::new(column, Operator::Ne, FilterValue::Single(value.into()))
     }
@@ -107,13 +148,13 @@ impl Filter {
         )
     }
 
-    /// Create an IN filter
+    /// Create an IN filter; a `Value::Null` in `values` also matches null columns
     pub fn in_values(column: impl Into<String>, values: Vec<impl Into<Value>>) -> Self {
         let values = values.into_iter().map(|v| v.into()).collect();
         Self::new(column, Operator::In, FilterValue::Multiple(values))
     }
 
-    /// Create a NOT IN filter
+    /// Create a NOT IN filter; a `Value::Null` in `values` also leaves out null columns
     pub fn not_in_values(column: impl Into<String>, values: Vec<impl Into<Value>>) -> Self {
         let values = values.into_iter().map(|v| v.into()).collect();
         Self::new(column, Operator::NotIn, FilterValue::Multiple(values))
@@ -133,6 +174,25 @@ impl Filter {
         )
     }
 
+    /// Create an IS TRUE filter; null matches neither this nor [`Filter::is_false`]
+    pub fn is_true(column: impl Into<String>) -> Self {
+        Self::new(column, Operator::IsTrue, FilterValue::Single(Value::Null))
+    }
+
+    /// Create an IS FALSE filter
+    pub fn is_false(column: impl Into<String>) -> Self {
+        Self::new(column, Operator::IsFalse, FilterValue::Single(Value::Null))
+    }
+
+    /// Create an IS UNKNOWN filter, matching a null boolean
+    pub fn is_unknown(column: impl Into<String>) -> Self {
+        Self::new(
+            column,
+            Operator::IsUnknown,
+            FilterValue::Single(Value::Null),
+        )
+    }
+
     /// Create a BETWEEN filter
     pub fn between(
         column: impl Into<String>,
@@ -158,6 +218,15 @@ impl Filter {
             FilterValue::Range(min.into(), max.into()),
         )
     }
+
+    /// Create a condition from an SQL fragment, e.g.
+    /// `Filter::raw("(metadata->>'score')::int > $?", vec![Value::Integer(10)])`
+    ///
+    /// Each `$?` outside a quoted literal is numbered in line with the rest of the query and
+    /// bound to the next value, so the values are never spliced into the SQL.
+    pub fn raw(sql: impl Into<String>, params: Vec<Value>) -> FilterOperator {
+        FilterOperator::Raw(sql.into(), params)
+    }
 }
 
 impl FilterOperator {
@@ -171,6 +240,11 @@ impl FilterOperator {
         FilterOperator::Or(filters)
     }
 
+    /// Create a condition on a JSON path expression, e.g. `settings->>'theme'`
+    pub fn json_path(path: impl Into<String>, operator: Operator, value: impl Into<Value>) -> Self {
+        FilterOperator::JsonPath(path.into(), operator, FilterValue::Single(value.into()))
+    }
+
     /// Create a NOT filter
     pub fn negate(filter: FilterOperator) -> Self {
         FilterOperator::Not(Box::new(filter))
@@ -364,10 +438,12 @@ impl FilterOperations {
         Vec<Box<dyn tokio_postgres::types::ToSql + Send + Sync>>,
     )> {
         let mut param_counter = 1;
-        Self::build_filter_operator_with_counter(filter, &mut param_counter)
+        let built = Self::build_filter_operator_with_counter(filter, &mut param_counter)?;
+        check_param_count(built.1.len())?;
+        Ok(built)
     }
 
-    fn build_filter_operator_with_counter(
+    pub(crate) fn build_filter_operator_with_counter(
         filter: &FilterOperator,
         param_counter: &mut usize,
     ) -> Result<(
@@ -376,7 +452,7 @@ impl FilterOperations {
     )> {
         match filter {
             FilterOperator::Single(filter) => {
-                Self::build_filter_with_counter(filter, param_counter)
+                Self::build_filter_with_counter(filter, false, param_counter)
             }
             FilterOperator::And(filters) => {
                 let mut sql = String::new();
@@ -416,9 +492,55 @@ impl FilterOperations {
                 Ok((format!("NOT ({filter_sql})"), filter_params))
             }
             FilterOperator::Custom(condition) => Ok((condition.clone(), vec![])),
+            FilterOperator::Raw(fragment, values) => {
+                let sql = number_placeholders(fragment, values.len(), param_counter)?;
+                let params = values
+                    .iter()
+                    .map(|value| value.to_postgres_param())
+                    .collect();
+                Ok((format!("({sql})"), params))
+            }
+            FilterOperator::JsonPath(path, operator, value) => {
+                let (expression, numeric) = json_path_expression(path, value);
+                let filter = Filter::new(expression, *operator, value.clone());
+                Self::build_filter_with_counter(&filter, numeric, param_counter)
+            }
         }
     }
 
+    /// Build SQL for a filter operator with its values written inline as literals, for
+    /// statements such as `COPY` that take no parameters
+    pub(crate) fn build_filter_operator_inline(filter: &FilterOperator) -> Result<String> {
+        let (sql, _) = Self::build_filter_operator(filter)?;
+        let mut values = Vec::new();
+        collect_literals(filter, &mut values);
+
+        // Placeholders are replaced in one pass, so `$1` inside an inlined value stays as is
+        let mut inlined = String::with_capacity(sql.len());
+        let mut chars = sql.chars().peekable();
+        let mut quoted = false;
+        while let Some(c) = chars.next() {
+            if c == '\'' {
+                quoted = !quoted;
+            }
+            if c != '$' || quoted || !chars.peek().is_some_and(char::is_ascii_digit) {
+                inlined.push(c);
+                continue;
+            }
+            let mut index = String::new();
+            while let Some(digit) = chars.next_if(char::is_ascii_digit) {
+                index.push(digit);
+            }
+            let value = index
+                .parse::<usize>()
+                .ok()
+                .and_then(|index| values.get(index.checked_sub(1)?))
+                .ok_or_else(|| Error::query(format!("No value for placeholder ${index}")))?;
+            inlined.push_str(value);
+        }
+        Ok(inlined)
+    }
+
     /// Build SQL for an individual filter
     pub fn build_filter(
         filter: &Filter,
@@ -427,11 +549,14 @@ impl FilterOperations {
         Vec<Box<dyn tokio_postgres::types::ToSql + Send + Sync>>,
     )> {
         let mut param_counter = 1;
-        Self::build_filter_with_counter(filter, &mut param_counter)
+        Self::build_filter_with_counter(filter, false, &mut param_counter)
     }
 
+    /// With `typed`, each placeholder is cast to the type its value is bound as, so the
+    /// parameter isn't inferred from a column of another numeric type
     fn build_filter_with_counter(
         filter: &Filter,
+        typed: bool,
         param_counter: &mut usize,
     ) -> Result<(
         String,
@@ -440,19 +565,90 @@ impl FilterOperations {
         let mut sql = String::new();
         let mut params = Vec::new();
 
-        match &filter.operator {
-            Operator::IsNull => {
-                sql.push_str(&format!("{} IS NULL", filter.column));
+        // `= NULL` and `IN (NULL)` match no rows, so NULL is compared with IS [NOT] NULL
+        match (&filter.operator, &filter.value) {
+            (Operator::Eq, FilterValue::Single(Value::Null)) => {
+                return Ok((format!("{} IS NULL", filter.column), params));
+            }
+            (Operator::Ne, FilterValue::Single(Value::Null)) => {
+                return Ok((format!("{} IS NOT NULL", filter.column), params));
+            }
+            (operator, _) if operator.is_unary() => {}
+            (
+                operator,
+                FilterValue::Single(Value::Null)
+                | FilterValue::Range(Value::Null, _)
+                | FilterValue::Range(_, Value::Null),
+            ) => {
+                return Err(Error::query(format!(
+                    "{} {operator} NULL matches no rows; use IS NULL or IS NOT NULL",
+                    filter.column
+                )));
             }
-            Operator::IsNotNull => {
-                sql.push_str(&format!("{} IS NOT NULL", filter.column));
+            (operator @ (Operator::In | Operator::NotIn), FilterValue::Multiple(values)) => {
+                if let Some(values) = without_nulls(values) {
+                    let (null_check, joiner) = match operator {
+                        Operator::In => ("IS NULL", "OR"),
+                        _ => ("IS NOT NULL", "AND"),
+                    };
+                    if values.is_empty() {
+                        return Ok((format!("{} {null_check}", filter.column), params));
+                    }
+                    let rest = Filter::new(
+                        filter.column.clone(),
+                        *operator,
+                        FilterValue::Multiple(values),
+                    );
+                    let (rest_sql, rest_params) =
+                        Self::build_filter_with_counter(&rest, typed, param_counter)?;
+                    return Ok((
+                        format!("({rest_sql} {joiner} {} {null_check})", filter.column),
+                        rest_params,
+                    ));
+                }
+            }
+            _ => {}
+        }
+
+        if let (Operator::In | Operator::NotIn, FilterValue::Multiple(values)) =
+            (&filter.operator, &filter.value)
+        {
+            let (comparison, empty) = match filter.operator {
+                Operator::In => ("= ANY", "FALSE"),
+                _ => ("<> ALL", "TRUE"),
+            };
+            if values.is_empty() {
+                return Ok((empty.to_string(), params));
+            }
+            let cast = array_cast(values).ok_or_else(|| {
+                Error::query(format!(
+                    "{} {} list mixes value types that can't be bound as one array",
+                    filter.column, filter.operator
+                ))
+            })?;
+            sql.push_str(&format!(
+                "{} {comparison}(${}{cast})",
+                filter.column, param_counter
+            ));
+            *param_counter += 1;
+            params.push(array_param(values, cast));
+            return Ok((sql, params));
+        }
+
+        let placeholder = |value: &Value, param_counter: &mut usize| {
+            let cast = if typed { param_cast(value) } else { "" };
+            *param_counter += 1;
+            format!("${}{cast}", *param_counter - 1)
+        };
+        match &filter.operator {
+            operator if operator.is_unary() => {
+                sql.push_str(&format!("{} {operator}", filter.column));
             }
             _ => {
                 sql.push_str(&format!("{} {} ", filter.column, filter.operator));
                 match &filter.value {
                     FilterValue::Single(value) => {
-                        sql.push_str(&format!("${}", param_counter));
-                        *param_counter += 1;
+                        sql.push_str(&placeholder(value, param_counter));
                         params.push(value.to_postgres_param());
                     }
                     FilterValue::Multiple(values) => {
@@ -461,15 +657,15 @@ impl FilterOperations {
                             if i > 0 {
                                 sql.push_str(", ");
                             }
-                            sql.push_str(&format!("${}", param_counter));
-                            *param_counter += 1;
+                            sql.push_str(&placeholder(value, param_counter));
                             params.push(value.to_postgres_param());
                         }
                         sql.push(')');
                     }
                     FilterValue::Range(min, max) => {
-                        sql.push_str(&format!("${} AND ${}", param_counter, *param_counter + 1));
-                        *param_counter += 2;
+                        let min_sql = placeholder(min, param_counter);
+                        let max_sql = placeholder(max, param_counter);
+                        sql.push_str(&format!("{min_sql} AND {max_sql}"));
                         params.push(min.to_postgres_param());
                         params.push(max.to_postgres_param());
                     }
@@ -480,3 +676,204 @@ impl FilterOperations {
         Ok((sql, params))
     }
 }
+
+/// Fail before PostgreSQL does when a statement has too many parameters
+pub(crate) fn check_param_count(count: usize) -> Result<()> {
+    if count > MAX_BIND_PARAMS {
+        return Err(Error::query(format!(
+            "Statement needs {count} parameters, more than the {MAX_BIND_PARAMS} PostgreSQL allows"
+        )));
+    }
+    Ok(())
+}
+
+/// Cast to bind an `IN` list as a single array with, if its values share a type
+///
+/// One array parameter keeps long lists cheap to parse and plan and within
+/// [`MAX_BIND_PARAMS`]. Text is left uncast so the array takes the column's type, and
+/// integers mixed with reals are bound as reals.
+fn array_cast(values: &[Value]) -> Option<&'static str> {
+    let element_type = |value: &Value| match value {
+        Value::Integer(_) => Some("::bigint[]"),
+        Value::Real(_) => Some("::double precision[]"),
+        Value::Text(_) => Some(""),
+        Value::Blob(_) => Some("::bytea[]"),
+        Value::Boolean(_) => Some("::boolean[]"),
+        Value::DateTime(_) => Some("::timestamptz[]"),
+        Value::Json(_) => Some("::jsonb[]"),
+        _ => None,
+    };
+    let first = element_type(values.first()?)?;
+    if values
+        .iter()
+        .all(|value| element_type(value) == Some(first))
+    {
+        return Some(first);
+    }
+    values
+        .iter()
+        .all(|value| matches!(value, Value::Integer(_) | Value::Real(_)))
+        .then_some("::double precision[]")
+}
+
+/// The values of an `IN` list as one array parameter of the type [`array_cast`] chose
+fn array_param(
+    values: &[Value],
+    cast: &str,
+) -> Box<dyn tokio_postgres::types::ToSql + Send + Sync> {
+    fn collect<T>(values: &[Value], item: impl Fn(&Value) -> Option<T>) -> Vec<T> {
+        values.iter().filter_map(item).collect()
+    }
+    match cast {
+        "::bigint[]" => Box::new(collect(values, |v| match v {
+            Value::Integer(i) => Some(*i),
+            _ => None,
+        })),
+        "::double precision[]" => Box::new(collect(values, |v| match v {
+            Value::Real(f) => Some(*f),
+            Value::Integer(i) => Some(*i as f64),
+            _ => None,
+        })),
+        "::bytea[]" => Box::new(collect(values, |v| match v {
+            Value::Blob(b) => Some(b.clone()),
+            _ => None,
+        })),
+        "::boolean[]" => Box::new(collect(values, |v| match v {
+            Value::Boolean(b) => Some(*b),
+            _ => None,
+        })),
+        "::timestamptz[]" => Box::new(collect(values, |v| match v {
+            Value::DateTime(dt) => Some(*dt),
+            _ => None,
+        })),
+        "::jsonb[]" => Box::new(collect(values, |v| match v {
+            Value::Json(json) => Some(json.clone()),
+            _ => None,
+        })),
+        _ => Box::new(collect(values, |v| match v {
+            Value::Text(s) => Some(s.clone()),
+            _ => None,
+        })),
+    }
+}
+
+/// The non-null values of an `IN` list, if it has nulls
+fn without_nulls(values: &[Value]) -> Option<Vec<Value>> {
+    values
+        .iter()
+        .any(|value| matches!(value, Value::Null))
+        .then(|| {
+            values
+                .iter()
+                .filter(|value| !matches!(value, Value::Null))
+                .cloned()
+                .collect()
+        })
+}
+
+/// Literals of the values of `filter`, in the order the builder numbers their placeholders
+fn collect_literals(filter: &FilterOperator, literals: &mut Vec<String>) {
+    let mut push = |operator: &Operator, value: &FilterValue| {
+        if operator.is_unary() {
+            return;
+        }
+        match value {
+            // Compared with IS [NOT] NULL, without a placeholder
+            FilterValue::Single(Value::Null) => {}
+            FilterValue::Single(value) => literals.push(value.to_sql_literal()),
+            FilterValue::Multiple(items) => {
+                let in_list = matches!(operator, Operator::In | Operator::NotIn);
+                let non_null = without_nulls(items).filter(|_| in_list);
+                let items = non_null.as_deref().unwrap_or(items);
+                match array_cast(items).filter(|_| in_list) {
+                    Some(cast) => {
+                        let items: Vec<String> = items.iter().map(Value::to_sql_literal).collect();
+                        literals.push(format!("ARRAY[{}]{cast}", items.join(", ")));
+                    }
+                    None => literals.extend(items.iter().map(Value::to_sql_literal)),
+                }
+            }
+            FilterValue::Range(min, max) => {
+                literals.extend([min.to_sql_literal(), max.to_sql_literal()])
+            }
+        }
+    };
+    match filter {
+        FilterOperator::Single(filter) => push(&filter.operator, &filter.value),
+        FilterOperator::JsonPath(_, operator, value) => push(operator, value),
+        FilterOperator::And(filters) | FilterOperator::Or(filters) => {
+            for filter in filters {
+                collect_literals(filter, literals);
+            }
+        }
+        FilterOperator::Not(filter) => collect_literals(filter, literals),
+        FilterOperator::Raw(_, params) => literals.extend(params.iter().map(Value::to_sql_literal)),
+        FilterOperator::Custom(_) => {}
+    }
+}
+
+/// Replace the `$?` placeholders of a raw fragment with `$n`, `$n+1`, ...
+fn number_placeholders(
+    fragment: &str,
+    expected: usize,
+    param_counter: &mut usize,
+) -> Result<String> {
+    let mut sql = String::with_capacity(fragment.len());
+    let mut chars = fragment.chars().peekable();
+    let mut quoted = false;
+    let mut found = 0;
+    while let Some(c) = chars.next() {
+        if c == '\'' {
+            quoted = !quoted;
+        }
+        if c == '$' && !quoted && chars.next_if_eq(&'?').is_some() {
+            sql.push_str(&format!("${}", param_counter));
+            *param_counter += 1;
+            found += 1;
+        } else {
+            sql.push(c);
+        }
+    }
+    if found != expected {
+        return Err(Error::query(format!(
+            "Raw filter has {found} placeholders but {expected} values: {fragment}"
+        )));
+    }
+    Ok(sql)
+}
+
+/// `->>` yields text, so paths compared against typed values are cast to the values' type
+///
+/// Numbers are cast to `bigint`, or to `numeric` when a value is fractional, whatever the
+/// magnitude of the values, since the stored numbers may be wider than the ones filtered by.
+/// Returns whether the comparison is numeric, in which case its placeholders need casts.
+fn json_path_expression(path: &str, value: &FilterValue) -> (String, bool) {
+    let values: Vec<&Value> = match value {
+        FilterValue::Single(value) => vec![value],
+        FilterValue::Range(min, max) => vec![min, max],
+        FilterValue::Multiple(values) => values.iter().collect(),
+    };
+    let numbers = values
+        .iter()
+        .filter(|value| !matches!(value, Value::Null))
+        .all(|value| matches!(value, Value::Integer(_) | Value::Real(_)));
+    let cast = match values.iter().find(|value| !matches!(value, Value::Null)) {
+        Some(_) if numbers && values.iter().any(|value| matches!(value, Value::Real(_))) => {
+            "numeric"
+        }
+        Some(_) if numbers => "bigint",
+        Some(Value::Boolean(_)) => "boolean",
+        _ => return (path.to_string(), false),
+    };
+    (format!("({path})::{cast}"), numbers)
+}
+
+/// Cast naming the type [`Value::to_postgres_param`] binds a number as
+fn param_cast(value: &Value) -> &'static str {
+    match value {
+        Value::Integer(i) if i32::try_from(*i).is_ok() => "::integer",
+        Value::Integer(_) => "::bigint",
+        Value::Real(_) => "::double precision",
+        _ => "",
+    }
+}
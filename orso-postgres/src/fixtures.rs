@@ -0,0 +1,115 @@
+//! Fixture and seed data
+//!
+//! [`load`] reads records of one model from a JSON file, or a YAML file with the `yaml`
+//! feature, and saves them with `insert_or_update`, so loading the same fixtures twice is
+//! harmless. A fixture file is either a list of records or a map from fixture names to
+//! records:
+//!
+//! ```yaml
+//! alice:
+//!   name: Alice
+//!   email: alice@example.com
+//! bob:
+//!   name: Bob
+//!   email: bob@example.com
+//! ```
+//!
+//! Records without a primary key get a deterministic one derived from the table and fixture
+//! name (or list position), so other fixtures can refer to them with [`id`]. The [`seed!`]
+//! macro does the same for records written in Rust.
+//!
+//! [`seed!`]: crate::seed
+
+use crate::{Database, Error, Orso, Result};
+use std::path::Path;
+use tracing::instrument;
+
+/// File formats fixtures can be read from
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FixtureFormat {
+    Json,
+    #[cfg(feature = "yaml")]
+    Yaml,
+}
+
+impl FixtureFormat {
+    /// Pick the format from a file extension
+    pub fn from_path(path: &Path) -> Result<Self> {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("json") => Ok(Self::Json),
+            #[cfg(feature = "yaml")]
+            Some("yaml") | Some("yml") => Ok(Self::Yaml),
+            other => Err(Error::Config {
+                message: format!("Unsupported fixture file extension: {:?}", other),
+                parameter: Some(path.display().to_string()),
+                source: None,
+            }),
+        }
+    }
+}
+
+/// Deterministic primary key for the fixture `key` of `table`
+pub fn id(table: &str, key: &str) -> String {
+    let name = format!("orso://{table}/{key}");
+    uuid::Uuid::new_v5(&uuid::Uuid::NAMESPACE_URL, name.as_bytes()).to_string()
+}
+
+/// Parse fixture records, keyed by fixture name or list position
+pub fn parse<T: Orso>(content: &str, format: FixtureFormat) -> Result<Vec<(String, T)>> {
+    let document: serde_json::Value = match format {
+        FixtureFormat::Json => serde_json::from_str(content)?,
+        #[cfg(feature = "yaml")]
+        FixtureFormat::Yaml => serde_yaml::from_str(content)
+            .map_err(|e| Error::serialization(format!("Invalid YAML fixture: {e}")))?,
+    };
+
+    let entries: Vec<(String, serde_json::Value)> = match document {
+        serde_json::Value::Object(map) => map.into_iter().collect(),
+        serde_json::Value::Array(items) => items
+            .into_iter()
+            .enumerate()
+            .map(|(i, item)| (i.to_string(), item))
+            .collect(),
+        _ => {
+            return Err(Error::serialization(
+                "Fixture file must contain a list or a map of records",
+            ))
+        }
+    };
+
+    entries
+        .into_iter()
+        .map(|(key, value)| {
+            let record = serde_json::from_value(value).map_err(|e| {
+                Error::serialization_field(e.to_string(), format!("{}.{}", T::table_name(), key))
+            })?;
+            Ok((key, record))
+        })
+        .collect()
+}
+
+/// Load fixtures for `T` from `path` and save them
+#[instrument(name = "orso.fixtures", skip_all, fields(table = T::table_name()))]
+pub async fn load<T: Orso>(db: &Database, path: impl AsRef<Path>) -> Result<Vec<T>> {
+    let path = path.as_ref();
+    let content = std::fs::read_to_string(path)?;
+    let records = parse::<T>(&content, FixtureFormat::from_path(path)?)?;
+    seed(db, records).await
+}
+
+/// Save named records, giving those without a primary key a deterministic one
+pub async fn seed<T, K>(db: &Database, records: impl IntoIterator<Item = (K, T)>) -> Result<Vec<T>>
+where
+    T: Orso,
+    K: AsRef<str>,
+{
+    let mut saved = Vec::new();
+    for (key, mut record) in records {
+        if record.get_primary_key().is_none() {
+            record.set_primary_key(id(T::table_name(), key.as_ref()));
+        }
+        record.insert_or_update(db).await?;
+        saved.push(record);
+    }
+    Ok(saved)
+}
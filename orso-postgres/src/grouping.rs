@@ -0,0 +1,187 @@
+//! `GROUP BY` queries with typed access to keys and aggregates
+//!
+//! [`QueryBuilder::aggregates`](crate::QueryBuilder::aggregates) selects the `group_by`
+//! columns followed by any number of aggregates, and
+//! [`QueryBuilder::execute_grouped`](crate::QueryBuilder::execute_grouped) returns one
+//! [`GroupedRow`] per group. Comparisons on an [`AggregateExpr`] are `HAVING` conditions:
+//!
+//! ```rust,ignore
+//! use orso_postgres::grouping::{count, sum};
+//!
+//! let rows = QueryBuilder::new("trades")
+//!     .group_by(vec!["category"])
+//!     .aggregates([count(), sum("volume").alias("volume")])
+//!     .having(count().gt(10))
+//!     .order_by(Sort::desc("volume"))
+//!     .execute_grouped(&db)
+//!     .await?;
+//!
+//! for row in &rows {
+//!     let category: String = row.get("category")?;
+//!     let trades: i64 = row.get("count")?;
+//!     let volume: f64 = row.get("volume")?;
+//! }
+//! ```
+//!
+//! `COUNT` comes back as `i64`, `SUM` and `AVG` as `f64`, `MIN` and `MAX` as the column's type.
+
+use crate::{Aggregate, Error, Filter, FilterOperator, Result, RowMap, Value};
+use serde::de::DeserializeOwned;
+
+/// An aggregate of a grouped query, named by its alias in each [`GroupedRow`]
+#[derive(Debug, Clone)]
+pub struct AggregateExpr {
+    function: Aggregate,
+    column: String,
+    alias: String,
+}
+
+/// `COUNT(*)`, aliased `count`
+pub fn count() -> AggregateExpr {
+    AggregateExpr::new(Aggregate::Count, "*")
+}
+
+/// `COUNT(column)`, the rows where `column` is not null, aliased `count_<column>`
+pub fn count_of(column: impl Into<String>) -> AggregateExpr {
+    AggregateExpr::new(Aggregate::Count, column)
+}
+
+/// `SUM(column)`, aliased `sum_<column>`
+pub fn sum(column: impl Into<String>) -> AggregateExpr {
+    AggregateExpr::new(Aggregate::Sum, column)
+}
+
+/// `AVG(column)`, aliased `avg_<column>`
+pub fn avg(column: impl Into<String>) -> AggregateExpr {
+    AggregateExpr::new(Aggregate::Avg, column)
+}
+
+/// `MIN(column)`, aliased `min_<column>`
+pub fn min(column: impl Into<String>) -> AggregateExpr {
+    AggregateExpr::new(Aggregate::Min, column)
+}
+
+/// `MAX(column)`, aliased `max_<column>`
+pub fn max(column: impl Into<String>) -> AggregateExpr {
+    AggregateExpr::new(Aggregate::Max, column)
+}
+
+impl AggregateExpr {
+    pub fn new(function: Aggregate, column: impl Into<String>) -> Self {
+        let column = column.into();
+        let function_name = function.to_string().to_lowercase();
+        let alias = if column == "*" {
+            function_name
+        } else {
+            let column: String = column
+                .chars()
+                .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+                .collect();
+            format!("{function_name}_{column}")
+        };
+        Self {
+            function,
+            column,
+            alias,
+        }
+    }
+
+    /// Name the aggregate in the result instead of the default alias
+    pub fn alias(mut self, alias: impl Into<String>) -> Self {
+        self.alias = alias.into();
+        self
+    }
+
+    pub fn alias_name(&self) -> &str {
+        &self.alias
+    }
+
+    /// The aggregate as an SQL expression, without its alias
+    pub fn expression(&self) -> String {
+        let expression = format!("{}({})", self.function, self.column);
+        match self.function {
+            // NUMERIC results have no Value to decode into
+            Aggregate::Sum | Aggregate::Avg => format!("{expression}::double precision"),
+            _ => expression,
+        }
+    }
+
+    pub(crate) fn select_sql(&self) -> String {
+        format!("{} AS {}", self.expression(), self.alias)
+    }
+
+    fn compare(&self, operator: &str, value: impl Into<f64>) -> FilterOperator {
+        Filter::raw(
+            format!("({})::double precision {operator} $?", self.expression()),
+            vec![Value::Real(value.into())],
+        )
+    }
+
+    /// `HAVING aggregate = value`
+    pub fn eq(&self, value: impl Into<f64>) -> FilterOperator {
+        self.compare("=", value)
+    }
+
+    /// `HAVING aggregate != value`
+    pub fn ne(&self, value: impl Into<f64>) -> FilterOperator {
+        self.compare("!=", value)
+    }
+
+    /// `HAVING aggregate < value`
+    pub fn lt(&self, value: impl Into<f64>) -> FilterOperator {
+        self.compare("<", value)
+    }
+
+    /// `HAVING aggregate <= value`
+    pub fn le(&self, value: impl Into<f64>) -> FilterOperator {
+        self.compare("<=", value)
+    }
+
+    /// `HAVING aggregate > value`
+    pub fn gt(&self, value: impl Into<f64>) -> FilterOperator {
+        self.compare(">", value)
+    }
+
+    /// `HAVING aggregate >= value`
+    pub fn ge(&self, value: impl Into<f64>) -> FilterOperator {
+        self.compare(">=", value)
+    }
+}
+
+/// One group of [`QueryBuilder::execute_grouped`](crate::QueryBuilder::execute_grouped)
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroupedRow {
+    keys: Vec<String>,
+    values: RowMap,
+}
+
+impl GroupedRow {
+    pub(crate) fn new(keys: &[String], values: RowMap) -> Self {
+        Self {
+            keys: keys.to_vec(),
+            values,
+        }
+    }
+
+    /// A group key by column, or an aggregate by alias, decoded as `T`
+    pub fn get<T: DeserializeOwned>(&self, name: &str) -> Result<T> {
+        let value = self
+            .values
+            .get(name)
+            .ok_or_else(|| Error::query(format!("Grouped row has no column {name}")))?;
+        serde_json::from_value(crate::row::value_to_json(value.clone(), None))
+            .map_err(|e| Error::serialization(format!("Column {name}: {e}")))
+    }
+
+    /// A group key or aggregate as returned
+    pub fn value(&self, name: &str) -> Option<&Value> {
+        self.values.get(name)
+    }
+
+    /// The group keys in `group_by` order
+    pub fn keys(&self) -> impl Iterator<Item = (&str, &Value)> {
+        self.keys
+            .iter()
+            .filter_map(|key| Some((key.as_str(), self.values.get(key)?)))
+    }
+}
@@ -0,0 +1,49 @@
+//! Callbacks around every statement of a database
+//!
+//! Attach [`Hooks`] to a database with [`Database::with_hooks`](crate::Database::with_hooks)
+//! to log, trace or meter statements in one place instead of around every ORM call. Returning
+//! an error from [`Hooks::before_query`] stops the statement before it takes a connection,
+//! which is enough to shed load or build a circuit breaker:
+//!
+//! ```rust,ignore
+//! struct Apm;
+//!
+//! impl Hooks for Apm {
+//!     fn after_query(&self, sql: &str, duration: Duration, rows: u64) {
+//!         apm::record("db", statement_kind(sql), duration, rows);
+//!     }
+//!
+//!     fn on_error(&self, sql: &str, _duration: Duration, error: &Error) {
+//!         apm::error("db", sql, error.to_string());
+//!     }
+//! }
+//!
+//! let db = Database::init(config).await?.with_hooks(Arc::new(Apm));
+//! ```
+//!
+//! Hooks see the statements run through [`Database`](crate::Database) and its pipelines,
+//! including the ones models issue; statements inside transactions are not reported.
+
+use crate::{Error, Result};
+use std::fmt;
+use std::time::Duration;
+
+/// Receives every statement a [`Database`](crate::Database) runs
+pub trait Hooks: Send + Sync {
+    /// A statement is about to run; an error fails it without running it
+    fn before_query(&self, _sql: &str) -> Result<()> {
+        Ok(())
+    }
+
+    /// A statement finished; `rows` is the number of rows returned or affected
+    fn after_query(&self, _sql: &str, _duration: Duration, _rows: u64) {}
+
+    /// A statement failed, or no query slot or connection was free for it
+    fn on_error(&self, _sql: &str, _duration: Duration, _error: &Error) {}
+}
+
+impl fmt::Debug for dyn Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Hooks")
+    }
+}
@@ -0,0 +1,117 @@
+//! Indexes declared on models
+//!
+//! `#[orso_index(...)]` on a model adds an index `Migrations::init` creates along with the
+//! table. An index has either `columns` or a key expression `expr`, and can be `unique` and
+//! partial with a `where` condition. `using` picks an index method other than B-tree:
+//!
+//! ```rust,ignore
+//! #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+//! #[orso_table("users")]
+//! #[orso_index(columns("last_name", "first_name"))]
+//! #[orso_index(expr = "lower(email)", where = "deleted_at IS NULL", unique)]
+//! #[orso_index(columns("tags"), using = "gin")]
+//! #[orso_index(columns("created_at"), using = "brin")]
+//! struct User {
+//!     #[orso_column(primary_key)]
+//!     id: Option<String>,
+//!     first_name: String,
+//!     last_name: String,
+//!     email: String,
+//!     tags: Vec<String>,
+//!     deleted_at: Option<OrsoDateTime>,
+//!     #[orso_column(created_at)]
+//!     created_at: Option<OrsoDateTime>,
+//! }
+//! ```
+//!
+//! Indexes are named `<table>_<keys>_idx`, or `<table>_<keys>_key` when unique, unless they set
+//! `name = "..."`. [`Migrations::verify`](crate::Migrations::verify) reports declared indexes
+//! that are missing from the table or built with another method, keys, uniqueness or `where`
+//! condition than declared, and `Migrations::init` rebuilds the latter.
+//!
+//! Building an index blocks writes to the table until it's done. On large tables, mark it
+//! `concurrently` to build it with `CREATE INDEX CONCURRENTLY` instead, which takes longer but
+//! lets writes through:
+//!
+//! ```rust,ignore
+//! #[orso_index(columns("account_id", "created_at"), concurrently)]
+//! #[non_transactional]
+//! ```
+//!
+//! Concurrent builds can't run inside a transaction, so the model has to be marked
+//! `#[non_transactional]`. `Migrations::init` retries them on deadlocks and drops the invalid
+//! index a failed build leaves.
+
+/// An index declared with `#[orso_index(...)]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IndexDef {
+    /// Name set with `name = "..."`
+    pub name: Option<&'static str>,
+    /// Key columns in index order, empty for expression indexes
+    pub columns: &'static [&'static str],
+    /// Key expression, e.g. `lower(email)`
+    pub expr: Option<&'static str>,
+    /// Condition of a partial index, e.g. `deleted_at IS NULL`
+    pub predicate: Option<&'static str>,
+    pub unique: bool,
+    /// Index method set with `using = "..."`, B-tree by default
+    pub method: Option<&'static str>,
+    /// Built with `CREATE INDEX CONCURRENTLY`, which doesn't block writes to the table
+    pub concurrently: bool,
+}
+
+impl IndexDef {
+    /// Name of the index on `table_name`
+    pub fn name(&self, table_name: &str) -> String {
+        if let Some(name) = self.name {
+            return name.to_string();
+        }
+        let keys = match self.expr {
+            Some(expr) => expr
+                .split(|c: char| !c.is_ascii_alphanumeric())
+                .filter(|part| !part.is_empty())
+                .collect::<Vec<_>>()
+                .join("_")
+                .to_lowercase(),
+            None => self.columns.join("_"),
+        };
+        let suffix = if self.unique { "key" } else { "idx" };
+        let mut name = format!("{table_name}_{keys}_{suffix}");
+        // PostgreSQL cuts identifiers to 63 bytes
+        while name.len() > 63 {
+            name.pop();
+        }
+        name
+    }
+
+    /// The index method, e.g. `gin`
+    pub fn method(&self) -> &'static str {
+        self.method.unwrap_or("btree")
+    }
+
+    /// The `CREATE INDEX` statement for the index on `table_name`
+    pub fn sql(&self, table_name: &str) -> String {
+        let unique = if self.unique { "UNIQUE " } else { "" };
+        let keys = match self.expr {
+            Some(expr) => format!("({expr})"),
+            None => self.columns.join(", "),
+        };
+        let using = match self.method {
+            Some(method) => format!(" USING {method}"),
+            None => String::new(),
+        };
+        let concurrently = if self.concurrently {
+            "CONCURRENTLY "
+        } else {
+            ""
+        };
+        let mut sql = format!(
+            "CREATE {unique}INDEX {concurrently}IF NOT EXISTS {} ON {table_name}{using} ({keys})",
+            self.name(table_name)
+        );
+        if let Some(predicate) = self.predicate {
+            sql.push_str(&format!(" WHERE {predicate}"));
+        }
+        sql
+    }
+}
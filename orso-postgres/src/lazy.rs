@@ -0,0 +1,260 @@
+//! Lazily loaded columns
+//!
+//! Fields marked `#[orso_column(compress, lazy)]` are declared as `Lazy<T>`. Finders leave the
+//! column out of the SELECT list and record where the value lives instead, so the blob is only
+//! fetched and decompressed when [`Lazy::load`] is called.
+
+use crate::{Error, Executor, Result, RowMap, Value};
+use serde::{de::DeserializeOwned, Deserialize, Deserializer, Serialize, Serializer};
+use std::collections::HashMap;
+
+/// Key of the marker object `from_map` receives for columns that were not selected
+const LAZY_MARKER: &str = "__orso_lazy";
+
+/// Location of a deferred column value
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LazySource {
+    pub table: String,
+    pub column: String,
+    pub primary_key: String,
+    pub key: String,
+    /// Type of the key column, so integer keys are bound as integers
+    #[serde(default)]
+    pub key_type: LazyKeyType,
+    /// Whether the column is encrypted with the keys of the database
+    #[serde(default)]
+    pub encrypted: bool,
+}
+
+/// Type of the key column a [`LazySource`] is looked up by
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum LazyKeyType {
+    #[default]
+    Text,
+    Integer,
+    BigInt,
+}
+
+impl LazySource {
+    fn key_param(&self) -> Result<Box<dyn tokio_postgres::types::ToSql + Send + Sync>> {
+        let key_type = match self.key_type {
+            LazyKeyType::Text => crate::FieldType::Text,
+            LazyKeyType::Integer => crate::FieldType::Integer,
+            LazyKeyType::BigInt => crate::FieldType::BigInt,
+        };
+        crate::operations::typed_key_param(Some(&key_type), &self.primary_key, &self.key)
+    }
+
+    /// Decode the column from the row it was read in, like `from_map` decodes its columns
+    fn decode<T: DeserializeOwned>(&self, mut map: RowMap) -> Result<T> {
+        if self.encrypted {
+            crate::encryption::decrypt_field(&mut map, &self.column)?;
+        }
+        let value = map.remove(&self.column).unwrap_or(Value::Null);
+        let json = match value_to_json(value) {
+            Err(e) if crate::decode::is_strict() => {
+                return Err(crate::decode::field_decode(&self.table, &self.column, e));
+            }
+            json => json?,
+        };
+        serde_json::from_value(json).map_err(|e| {
+            if crate::decode::is_strict() {
+                crate::decode::field_decode(&self.table, &self.column, e)
+            } else {
+                Error::serialization_field(e.to_string(), self.column.clone())
+            }
+        })
+    }
+}
+
+/// A column value that is loaded on first access
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lazy<T> {
+    value: Option<T>,
+    source: Option<LazySource>,
+}
+
+impl<T> Default for Lazy<T> {
+    fn default() -> Self {
+        Self {
+            value: None,
+            source: None,
+        }
+    }
+}
+
+impl<T> From<T> for Lazy<T> {
+    fn from(value: T) -> Self {
+        Self::loaded(value)
+    }
+}
+
+impl<T> Lazy<T> {
+    /// Create an already loaded value
+    pub fn loaded(value: T) -> Self {
+        Self {
+            value: Some(value),
+            source: None,
+        }
+    }
+
+    /// Create a deferred value that will be fetched from `source`
+    pub fn deferred(source: LazySource) -> Self {
+        Self {
+            value: None,
+            source: Some(source),
+        }
+    }
+
+    pub fn is_loaded(&self) -> bool {
+        self.value.is_some()
+    }
+
+    /// Get the value if it has been loaded
+    pub fn get(&self) -> Option<&T> {
+        self.value.as_ref()
+    }
+
+    pub fn source(&self) -> Option<&LazySource> {
+        self.source.as_ref()
+    }
+
+    /// Replace the value, e.g. before an update
+    pub fn set(&mut self, value: T) {
+        self.value = Some(value);
+    }
+
+    pub fn into_inner(self) -> Option<T> {
+        self.value
+    }
+}
+
+impl<T: DeserializeOwned> Lazy<T> {
+    /// Fetch and decompress the value through `db` if it hasn't been loaded yet
+    pub async fn load(&mut self, db: &impl Executor) -> Result<&T> {
+        if self.value.is_none() {
+            let source = self
+                .source
+                .as_ref()
+                .ok_or_else(|| Error::validation("Lazy value has no source to load from"))?;
+
+            let sql = format!(
+                "SELECT {} FROM {} WHERE {} = $1",
+                source.column, source.table, source.primary_key
+            );
+
+            let key = source.key_param()?;
+            let map = db
+                .query_rows(&sql, &[key.as_ref()])
+                .await?
+                .into_iter()
+                .next()
+                .ok_or_else(|| {
+                    Error::not_found_record(
+                        "Record for lazy column no longer exists",
+                        source.table.clone(),
+                        source.key.clone(),
+                    )
+                })?;
+
+            let value = crate::decode::decode_with(db, || source.decode(map))?;
+            self.value = Some(value);
+        }
+
+        Ok(self.value.as_ref().expect("lazy value loaded above"))
+    }
+}
+
+impl<T: Serialize> Serialize for Lazy<T> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        // Unloaded values serialize as null; to_map skips them so updates keep the stored data
+        self.value.serialize(serializer)
+    }
+}
+
+impl<'de, T: Deserialize<'de>> Deserialize<'de> for Lazy<T> {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> std::result::Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Repr<T> {
+            Deferred {
+                #[serde(rename = "__orso_lazy")]
+                source: LazySource,
+            },
+            Loaded(T),
+        }
+
+        Ok(match Option::<Repr<T>>::deserialize(deserializer)? {
+            Some(Repr::Deferred { source }) => Lazy::deferred(source),
+            Some(Repr::Loaded(value)) => Lazy::loaded(value),
+            None => Lazy::default(),
+        })
+    }
+}
+
+/// Columns to select for `T`, leaving out lazy fields
+pub fn select_columns<T: crate::Orso>() -> Vec<&'static str> {
+    let lazy = T::lazy_fields();
+    if lazy.is_empty() {
+        return vec!["*"];
+    }
+    T::columns()
+        .into_iter()
+        .filter(|column| !lazy.contains(column))
+        .collect()
+}
+
+/// Record where the lazy fields missing from a row can be loaded from
+pub fn attach_sources<T: crate::Orso>(map: &mut HashMap<String, Value>, table: &str) {
+    let lazy = T::lazy_fields();
+    if lazy.is_empty() {
+        return;
+    }
+
+    let key = match map.get(T::primary_key_field()) {
+        Some(Value::Text(s)) => s.clone(),
+        Some(Value::Integer(i)) => i.to_string(),
+        _ => return,
+    };
+    let key_type = match crate::operations::key_type::<T>() {
+        Some(crate::FieldType::Integer) => LazyKeyType::Integer,
+        Some(crate::FieldType::BigInt) => LazyKeyType::BigInt,
+        _ => LazyKeyType::Text,
+    };
+    let encrypted = T::encrypted_fields();
+
+    for column in lazy {
+        if map.contains_key(column) {
+            continue;
+        }
+        let source = LazySource {
+            table: table.to_string(),
+            column: column.to_string(),
+            primary_key: T::primary_key_field().to_string(),
+            key: key.clone(),
+            key_type,
+            encrypted: encrypted.contains(column),
+        };
+        let marker = serde_json::json!({ LAZY_MARKER: source });
+        map.insert(column.to_string(), Value::Text(marker.to_string()));
+    }
+}
+
+pub(crate) fn value_to_json(value: Value) -> Result<serde_json::Value> {
+    match value {
+        Value::Null => Ok(serde_json::Value::Null),
+        Value::Json(json) => Ok(json),
+        Value::Blob(blob) if crate::compression::is_framed(&blob) => {
+            crate::compression::decode_framed_json(&blob)
+        }
+        Value::Blob(blob) => Ok(serde_json::Value::Array(
+            crate::compression::decode_payload(&blob)?.to_json(),
+        )),
+        Value::Text(s) => Ok(serde_json::from_str(&s).unwrap_or(serde_json::Value::String(s))),
+        other => Err(Error::type_conversion(
+            format!("Unsupported lazy column value: {:?}", other),
+            "Value",
+            "JSON",
+        )),
+    }
+}
@@ -1,14 +1,75 @@
+pub mod advisor;
+pub mod archive;
+#[cfg(feature = "arrow")]
+pub mod arrow;
+pub mod associations;
+pub mod audit;
+pub mod cache;
+#[cfg(feature = "cdc")]
+pub mod cdc;
+pub mod circuit;
+#[cfg(feature = "codegen")]
+pub mod codegen;
+pub mod compression;
+pub mod context;
+pub mod credentials;
+pub mod custom;
 pub mod database;
+pub mod decode;
+pub mod embed;
+pub mod encryption;
 pub mod error;
+pub mod exchange;
+pub mod executor;
+pub mod explain;
 pub mod filters;
+pub mod fixtures;
+pub mod grouping;
+pub mod hooks;
+pub mod index;
+pub mod lazy;
+pub mod loader;
+pub mod lock;
 pub mod macros;
+pub mod maintenance;
+#[cfg(feature = "metrics")]
+pub mod metrics;
 pub mod migrations;
+#[cfg(any(test, feature = "testing"))]
+pub mod mock;
+pub mod named;
+#[cfg(feature = "openapi")]
+pub mod openapi;
 pub mod operations;
+pub mod outbox;
 pub mod pagination;
+pub mod pipeline;
+pub mod poly;
 pub mod query;
+pub mod queue;
+pub mod registry;
+#[cfg(feature = "cdc")]
+pub(crate) mod replication;
+pub mod rollups;
+pub mod row;
+pub mod schema;
+pub mod scope;
+pub mod session;
+pub mod shard;
+pub mod stats;
+pub mod strategy;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod tls;
 pub mod traits;
+pub mod transaction;
+pub mod tree;
+pub mod typed_filter;
 pub mod types;
 pub mod utils;
+pub mod validation;
+#[cfg(feature = "web")]
+pub mod web;
 
 #[cfg(test)]
 mod test;
@@ -16,24 +77,85 @@ mod test;
 // Re-export PostgreSQL dependencies for macro use
 pub use tokio_postgres;
 pub use postgres_types;
+#[cfg(feature = "openapi")]
+pub use utoipa;
 
 // Create orso module alias for macro compatibility
 pub mod orso {
     pub use crate::*;
 }
 
+pub use advisor::{Advisor, IndexSuggestion, ObservedQuery, QueryLog};
+pub use archive::{Archive, ArchiveOptions, ArchiveProgress};
+pub use associations::Association;
+pub use audit::{Audit, AuditEntry};
+pub use cache::{Cache, Cached};
+#[cfg(feature = "cdc")]
+pub use cdc::{Change, ChangeEvent, ChangeFeed, Lsn};
 pub use chrono;
+pub use circuit::{CircuitBreaker, CircuitState};
+pub use compression::{CompressedElement, Compression, RecompressOptions, RecompressProgress};
+pub use context::{OperationContext, ReadContext};
+pub use credentials::CredentialsProvider;
+pub use custom::OrsoType;
 pub use cydec::{FloatingCodec, IntegerCodec};
 pub use database::*;
-pub use error::{Error, Result};
-pub use filters::{Filter, FilterOperations, FilterOperator, FilterValue, SearchFilter, Sort};
-pub use migrations::{MigrationEntry, MigrationResult, MigrationTrait, Migrations};
-pub use orso_postgres_macros::{orso_column, orso_table, Orso};
-pub use pagination::{CursorPaginatedResult, CursorPagination, PaginatedResult, Pagination};
-pub use query::{QueryBuilder, QueryResult};
+pub use embed::OrsoEmbed;
+pub use encryption::EncryptionKey;
+pub use error::{Error, ErrorContext, FieldError, Result};
+pub use executor::{Executor, RowMap};
+pub use explain::{ExplainAssert, PlanNode, QueryPlan};
+pub use filters::{
+    Column, Filter, FilterOperations, FilterOperator, FilterValue, SearchFilter, Sort,
+    MAX_BIND_PARAMS,
+};
+pub use grouping::{AggregateExpr, GroupedRow};
+pub use hooks::Hooks;
+pub use index::IndexDef;
+pub use lazy::Lazy;
+pub use loader::{LoadFailure, LoadMethod, Loader, LoaderMetrics, LoaderOptions};
+pub use lock::Lock;
+pub use maintenance::{Maintenance, MaintenanceReport};
+#[cfg(feature = "metrics")]
+pub use metrics::{MetricsHook, PrometheusRecorder};
+pub use migrations::{
+    ColumnInfo, Drift, MigrationConfig, MigrationEntry, MigrationResult, MigrationTrait, Migrations,
+};
+#[cfg(any(test, feature = "testing"))]
+pub use mock::{MockDatabase, MockRow, RecordedStatement};
+pub use named::{NamedQueries, NamedQuery};
+pub use operations::{ConfirmDeleteAll, ConfirmTruncate};
+pub use orso_postgres_macros::{
+    non_transactional, orso_audited, orso_column, orso_default_scope, orso_hooks, orso_index,
+    orso_join_table, orso_shard_key, orso_table, Orso, OrsoEmbed, OrsoRow,
+};
+pub use outbox::{Outbox, OutboxEntry, OutboxEvent, OutboxExt};
+pub use pagination::{
+    CountMode, CursorPaginatedResult, CursorPagination, PaginatedResult, Pagination,
+};
+pub use pipeline::Pipeline;
+pub use poly::{PolyRef, PolyTarget};
+pub use query::{GroupByKey, QueryBuilder, QueryResult};
+pub use queue::Queue;
+pub use registry::DatabaseRegistry;
+pub use rollups::Rollup;
+pub use row::OrsoRow;
+pub use schema::{ConstraintInfo, ConstraintKind, IndexInfo, Schema, TableInfo};
+pub use scope::{unscoped, DefaultScope};
 pub use serde::{Deserialize, Serialize};
-pub use traits::{FieldType, Orso};
-pub use types::*;
+pub use session::Session;
+pub use shard::ShardRouter;
+pub use stats::{Stats, TableBloat, TableSize};
+pub use strategy::{MigrationStrategy, PlannedChange};
+#[cfg(any(test, feature = "testing"))]
+pub use testing::TestDb;
+pub use traits::{FieldType, Orso, OrsoHooks, TypeOverride};
+pub use transaction::{IsolationLevel, RetryPolicy, Transaction, TxOptions};
+pub use tree::TreeQuery;
+pub use typed_filter::{FieldValue, Filterable, TextField, TypedColumn, TypedFilter};
 pub use types::OrsoDateTime;
+pub use types::*;
 pub use utils::Utils;
 pub use uuid::Uuid;
+#[cfg(feature = "web")]
+pub use web::{FilterParams, SortParams};
@@ -0,0 +1,352 @@
+//! Bulk loading from a stream of models
+//!
+//! A [`Loader`] reads models from a stream and groups them into batches of `batch_size` models,
+//! or of whatever arrived within `flush_interval` of the first one. Each batch is written in a
+//! transaction of its own, with up to `concurrency` batches in flight on separate connections.
+//! While every writer is busy the stream isn't polled, so a fast producer is held to the pace
+//! of the database:
+//!
+//! ```rust,ignore
+//! let options = LoaderOptions::new().with_batch_size(5_000).with_method(LoadMethod::Copy);
+//! let (loader, mut failures) = Loader::<Tick>::new(options);
+//! let metrics = loader.metrics();
+//!
+//! tokio::spawn(async move {
+//!     while let Some(failure) = failures.recv().await {
+//!         warn!(rows = failure.models.len(), error = %failure.error, "Tick batch failed");
+//!     }
+//! });
+//! let written = loader.run(ticks, &db).await?;
+//! info!(written, failed = metrics.rows_failed(), "Loaded ticks");
+//! ```
+//!
+//! A batch that fails is rolled back and handed to the failure channel with its models, and
+//! loading carries on; once the channel's receiver is dropped, the first failure ends the run.
+//! Models are validated, but their hooks don't run.
+
+use crate::{Database, Error, Orso, Result, Value, MAX_BIND_PARAMS};
+use bytes::Bytes;
+use futures_util::{pin_mut, SinkExt, Stream, StreamExt, TryStreamExt};
+use std::collections::{BTreeMap, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::{debug, instrument, warn};
+
+/// How a [`Loader`] writes its batches
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LoadMethod {
+    /// Multi-row `INSERT` statements
+    #[default]
+    Insert,
+    /// `COPY ... FROM STDIN`, faster for large batches
+    Copy,
+}
+
+/// Options for a [`Loader`]
+#[derive(Debug, Clone)]
+pub struct LoaderOptions {
+    pub batch_size: usize,
+    pub flush_interval: Duration,
+    /// Batches written at the same time, each on its own connection
+    pub concurrency: usize,
+    pub method: LoadMethod,
+}
+
+impl Default for LoaderOptions {
+    fn default() -> Self {
+        Self {
+            batch_size: 1000,
+            flush_interval: Duration::from_secs(1),
+            concurrency: 4,
+            method: LoadMethod::Insert,
+        }
+    }
+}
+
+impl LoaderOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_batch_size(mut self, batch_size: usize) -> Self {
+        self.batch_size = batch_size.max(1);
+        self
+    }
+
+    pub fn with_flush_interval(mut self, flush_interval: Duration) -> Self {
+        self.flush_interval = flush_interval;
+        self
+    }
+
+    pub fn with_concurrency(mut self, concurrency: usize) -> Self {
+        self.concurrency = concurrency.max(1);
+        self
+    }
+
+    pub fn with_method(mut self, method: LoadMethod) -> Self {
+        self.method = method;
+        self
+    }
+}
+
+/// Counters of a [`Loader`], updated as batches complete
+#[derive(Debug, Default)]
+pub struct LoaderMetrics {
+    rows_received: AtomicU64,
+    rows_written: AtomicU64,
+    rows_failed: AtomicU64,
+    batches_written: AtomicU64,
+    batches_failed: AtomicU64,
+}
+
+impl LoaderMetrics {
+    /// Models read from the stream
+    pub fn rows_received(&self) -> u64 {
+        self.rows_received.load(Ordering::Relaxed)
+    }
+
+    pub fn rows_written(&self) -> u64 {
+        self.rows_written.load(Ordering::Relaxed)
+    }
+
+    pub fn rows_failed(&self) -> u64 {
+        self.rows_failed.load(Ordering::Relaxed)
+    }
+
+    pub fn batches_written(&self) -> u64 {
+        self.batches_written.load(Ordering::Relaxed)
+    }
+
+    pub fn batches_failed(&self) -> u64 {
+        self.batches_failed.load(Ordering::Relaxed)
+    }
+}
+
+/// A batch a [`Loader`] could not write
+#[derive(Debug)]
+pub struct LoadFailure<T> {
+    pub models: Vec<T>,
+    pub error: Error,
+}
+
+/// Writes a stream of models in concurrent batches
+pub struct Loader<T> {
+    options: LoaderOptions,
+    metrics: Arc<LoaderMetrics>,
+    failures: mpsc::UnboundedSender<LoadFailure<T>>,
+}
+
+impl<T: Orso> Loader<T> {
+    /// A loader and the receiving end of its failure channel
+    pub fn new(options: LoaderOptions) -> (Self, mpsc::UnboundedReceiver<LoadFailure<T>>) {
+        let (failures, receiver) = mpsc::unbounded_channel();
+        let loader = Self {
+            options,
+            metrics: Arc::new(LoaderMetrics::default()),
+            failures,
+        };
+        (loader, receiver)
+    }
+
+    pub fn metrics(&self) -> Arc<LoaderMetrics> {
+        self.metrics.clone()
+    }
+
+    /// Write every model of `models`, returning how many were written
+    #[instrument(
+        name = "orso.loader.run",
+        skip_all,
+        fields(table = T::table_name(), method = ?self.options.method)
+    )]
+    pub async fn run(&self, models: impl Stream<Item = T>, db: &Database) -> Result<u64> {
+        let (batches, receiver) = mpsc::channel::<Vec<T>>(self.options.concurrency);
+        let written = AtomicU64::new(0);
+
+        let produce = self.batch(models, batches);
+        let consume = futures_util::stream::unfold(receiver, |mut receiver| async move {
+            receiver.recv().await.map(|batch| (Ok(batch), receiver))
+        })
+        .try_for_each_concurrent(self.options.concurrency, |batch| {
+            self.write(batch, db, &written)
+        });
+
+        let ((), result) = tokio::join!(produce, consume);
+        result?;
+        Ok(written.into_inner())
+    }
+
+    /// Group `models` into batches, stopping early once the writers are gone
+    async fn batch(&self, models: impl Stream<Item = T>, batches: mpsc::Sender<Vec<T>>) {
+        pin_mut!(models);
+        let mut batch = Vec::with_capacity(self.options.batch_size);
+        let mut deadline = None;
+        loop {
+            // `None` once the flush interval of a pending batch passes
+            let next = match deadline {
+                Some(deadline) => tokio::time::timeout_at(deadline, models.next()).await.ok(),
+                None => Some(models.next().await),
+            };
+            let full = match next {
+                Some(Some(model)) => {
+                    self.metrics.rows_received.fetch_add(1, Ordering::Relaxed);
+                    if batch.is_empty() {
+                        deadline = Some(tokio::time::Instant::now() + self.options.flush_interval);
+                    }
+                    batch.push(model);
+                    batch.len() >= self.options.batch_size
+                }
+                Some(None) => break,
+                None => true,
+            };
+            if full {
+                deadline = None;
+                let batch =
+                    std::mem::replace(&mut batch, Vec::with_capacity(self.options.batch_size));
+                if batches.send(batch).await.is_err() {
+                    return;
+                }
+            }
+        }
+        if !batch.is_empty() {
+            let _ = batches.send(batch).await;
+        }
+    }
+
+    async fn write(&self, batch: Vec<T>, db: &Database, written: &AtomicU64) -> Result<()> {
+        let rows = batch.len() as u64;
+        match self.write_batch(&batch, db).await {
+            Ok(()) => {
+                self.metrics.rows_written.fetch_add(rows, Ordering::Relaxed);
+                self.metrics.batches_written.fetch_add(1, Ordering::Relaxed);
+                written.fetch_add(rows, Ordering::Relaxed);
+                debug!(rows, "Wrote batch");
+                Ok(())
+            }
+            Err(error) => {
+                self.metrics.rows_failed.fetch_add(rows, Ordering::Relaxed);
+                self.metrics.batches_failed.fetch_add(1, Ordering::Relaxed);
+                warn!(rows, error = %error, "Failed to write batch");
+                let failure = LoadFailure {
+                    models: batch,
+                    error,
+                };
+                self.failures.send(failure).map_err(|e| e.0.error)
+            }
+        }
+    }
+
+    async fn write_batch(&self, batch: &[T], db: &Database) -> Result<()> {
+        // Models leaving out generated columns are written separately from those setting them
+        let mut groups: BTreeMap<Vec<String>, Vec<HashMap<String, Value>>> = BTreeMap::new();
+        for model in batch {
+            model.validate()?;
+            let mut map = crate::encryption::encode_record(db, model)?;
+            crate::context::apply_actor::<T>(&mut map, true);
+            let mut columns: Vec<String> = map.keys().cloned().collect();
+            columns.sort();
+            groups.entry(columns).or_default().push(map);
+        }
+
+        let sql = match self.options.method {
+            LoadMethod::Insert => format!("INSERT INTO {}", T::table_name()),
+            LoadMethod::Copy => format!("COPY {} FROM STDIN", T::table_name()),
+        };
+        db.with_connection(&sql, |mut client| async move {
+            let tx = client.transaction().await?;
+            for (columns, rows) in &groups {
+                match self.options.method {
+                    LoadMethod::Insert => insert_rows::<T>(&tx, columns, rows).await?,
+                    LoadMethod::Copy => copy_rows::<T>(&tx, columns, rows).await?,
+                }
+            }
+            tx.commit().await?;
+            Ok(())
+        })
+        .await
+    }
+}
+
+async fn insert_rows<T: Orso>(
+    tx: &deadpool_postgres::Transaction<'_>,
+    columns: &[String],
+    rows: &[HashMap<String, Value>],
+) -> Result<()> {
+    let rows_per_statement = (MAX_BIND_PARAMS / columns.len().max(1)).max(1);
+    for chunk in rows.chunks(rows_per_statement) {
+        let mut params: Vec<Box<dyn tokio_postgres::types::ToSql + Send + Sync>> = Vec::new();
+        let mut tuples = Vec::with_capacity(chunk.len());
+        for row in chunk {
+            let placeholders: Vec<String> = columns
+                .iter()
+                .map(|column| {
+                    params.push(row[column].to_postgres_param());
+                    format!("${}", params.len())
+                })
+                .collect();
+            tuples.push(format!("({})", placeholders.join(", ")));
+        }
+        let sql = format!(
+            "INSERT INTO {} ({}) VALUES {}",
+            T::table_name(),
+            columns.join(", "),
+            tuples.join(", ")
+        );
+        let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
+            params.iter().map(|p| p.as_ref()).collect();
+        crate::Executor::execute(tx, &sql, &param_refs).await?;
+    }
+    Ok(())
+}
+
+async fn copy_rows<T: Orso>(
+    tx: &deadpool_postgres::Transaction<'_>,
+    columns: &[String],
+    rows: &[HashMap<String, Value>],
+) -> Result<()> {
+    crate::executor::set_actor(tx).await?;
+    let sql = format!(
+        "COPY {} ({}) FROM STDIN WITH (FORMAT csv)",
+        T::table_name(),
+        columns.join(", ")
+    );
+    let mut csv = String::new();
+    for row in rows {
+        let fields: Vec<String> = columns.iter().map(|c| copy_field(&row[c])).collect();
+        csv.push_str(&fields.join(","));
+        csv.push('\n');
+    }
+
+    let sink = tx.copy_in::<_, Bytes>(sql.as_str()).await?;
+    pin_mut!(sink);
+    sink.send(Bytes::from(csv)).await?;
+    sink.finish().await?;
+    Ok(())
+}
+
+/// A value as a CSV field of `COPY`, where only an unquoted empty field is `NULL`
+fn copy_field(value: &Value) -> String {
+    fn list<T: ToString>(items: &[T]) -> String {
+        items.iter().map(T::to_string).collect::<Vec<_>>().join(",")
+    }
+
+    let text = match value {
+        Value::Null => return String::new(),
+        Value::Integer(i) => i.to_string(),
+        Value::Real(f) => f.to_string(),
+        Value::Text(s) => s.clone(),
+        Value::Blob(b) => {
+            let hex: String = b.iter().map(|byte| format!("{byte:02x}")).collect();
+            format!("\\x{hex}")
+        }
+        Value::Boolean(b) => b.to_string(),
+        Value::DateTime(dt) => dt.inner().to_rfc3339(),
+        Value::IntegerArray(arr) => format!("{{{}}}", list(arr)),
+        Value::BigIntArray(arr) => format!("{{{}}}", list(arr)),
+        Value::NumericArray(arr) => format!("{{{}}}", list(arr)),
+        Value::Vector(v) => format!("[{}]", list(v)),
+        Value::Json(v) => v.to_string(),
+    };
+    format!("\"{}\"", text.replace('"', "\"\""))
+}
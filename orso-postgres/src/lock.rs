@@ -0,0 +1,123 @@
+//! Distributed locks in a table
+//!
+//! Advisory locks belong to a session, which transaction-mode poolers like PgBouncer don't
+//! keep. A [`Lock`] is a row in the `orso_locks` table instead, held until it is released or its
+//! time to live runs out, so a crashed holder can't keep it forever:
+//!
+//! ```rust,ignore
+//! Lock::install(&db).await?;
+//!
+//! if let Some(lock) = Lock::acquire(&db, "daily-rollup", Duration::from_secs(600)).await? {
+//!     run_rollup(lock.token).await?;
+//!     lock.release(&db).await?;
+//! }
+//! ```
+//!
+//! A holder that stalls past the time to live can find the lock taken over while it keeps
+//! working. Every acquisition gets a higher fencing token, so storage that remembers the last
+//! token it saw can reject writes from the stale holder. Long jobs should [`Lock::extend`] the
+//! lock, which fails once it is lost.
+
+use crate::queue::interval;
+use crate::{Database, Error, OrsoDateTime, Result};
+use std::time::Duration;
+use tracing::{debug, instrument};
+
+/// Table the locks are stored in
+pub const LOCKS_TABLE: &str = "orso_locks";
+
+/// A held lock
+#[derive(Debug, Clone, PartialEq)]
+pub struct Lock {
+    pub name: String,
+    /// Identifies this holder; a new one for every acquisition
+    pub owner: String,
+    /// Fencing token, higher than that of any earlier holder
+    pub token: i64,
+    pub expires_at: OrsoDateTime,
+}
+
+impl Lock {
+    /// Statements creating the locks table
+    pub fn install_sql() -> Vec<String> {
+        vec![format!(
+            "CREATE TABLE IF NOT EXISTS {LOCKS_TABLE} (\n    \
+             name TEXT PRIMARY KEY,\n    \
+             owner TEXT NOT NULL,\n    \
+             token BIGINT NOT NULL,\n    \
+             expires_at TIMESTAMPTZ NOT NULL\n)"
+        )]
+    }
+
+    /// Create the locks table
+    pub async fn install(db: &Database) -> Result<()> {
+        for sql in Self::install_sql() {
+            db.execute(&sql, &[]).await.map_err(|e| {
+                Error::migration(
+                    format!("Failed to install locks: {}", e),
+                    Some(LOCKS_TABLE.to_string()),
+                    Some("install_locks".to_string()),
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Take the lock `name` for `ttl`, or `None` while someone else holds it
+    #[instrument(name = "orso.lock.acquire", skip(db, ttl))]
+    pub async fn acquire(db: &Database, name: &str, ttl: Duration) -> Result<Option<Self>> {
+        let owner = uuid::Uuid::new_v4().to_string();
+        // Rows are kept on release so tokens keep increasing
+        let sql = format!(
+            "INSERT INTO {LOCKS_TABLE} AS l (name, owner, token, expires_at) \
+             VALUES ($1, $2, 1, NOW() + {ttl}) \
+             ON CONFLICT (name) DO UPDATE \
+             SET owner = EXCLUDED.owner, token = l.token + 1, expires_at = EXCLUDED.expires_at \
+             WHERE l.expires_at <= NOW() \
+             RETURNING token, expires_at",
+            ttl = interval(ttl)
+        );
+
+        let Some(row) = db.query_opt(&sql, &[&name, &owner]).await? else {
+            debug!(name, "Lock is held elsewhere");
+            return Ok(None);
+        };
+        Ok(Some(Self {
+            name: name.to_string(),
+            owner,
+            token: row.try_get(0)?,
+            expires_at: row.try_get(1)?,
+        }))
+    }
+
+    /// Hold the lock for `ttl` from now on; fails if it expired and was taken over meanwhile
+    #[instrument(name = "orso.lock.extend", skip_all, fields(name = %self.name))]
+    pub async fn extend(&mut self, db: &Database, ttl: Duration) -> Result<()> {
+        let sql = format!(
+            "UPDATE {LOCKS_TABLE} SET expires_at = NOW() + {} \
+             WHERE name = $1 AND owner = $2 AND expires_at > NOW() RETURNING expires_at",
+            interval(ttl)
+        );
+        match db.query_opt(&sql, &[&self.name, &self.owner]).await? {
+            Some(row) => {
+                self.expires_at = row.try_get(0)?;
+                Ok(())
+            }
+            None => Err(Error::operation(
+                format!("Lock {} was lost", self.name),
+                "extend_lock",
+                Some(LOCKS_TABLE.to_string()),
+            )),
+        }
+    }
+
+    /// Give the lock up; does nothing if it was already lost
+    #[instrument(name = "orso.lock.release", skip_all, fields(name = %self.name))]
+    pub async fn release(self, db: &Database) -> Result<()> {
+        let sql = format!(
+            "UPDATE {LOCKS_TABLE} SET expires_at = '-infinity' WHERE name = $1 AND owner = $2"
+        );
+        db.execute(&sql, &[&self.name, &self.owner]).await?;
+        Ok(())
+    }
+}
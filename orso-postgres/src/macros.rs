@@ -32,6 +32,18 @@ macro_rules! filter {
     ($column:expr, is_not_null) => {
         $crate::Filter::is_not_null($column)
     };
+
+    ($column:expr, is_true) => {
+        $crate::Filter::is_true($column)
+    };
+
+    ($column:expr, is_false) => {
+        $crate::Filter::is_false($column)
+    };
+
+    ($column:expr, is_unknown) => {
+        $crate::Filter::is_unknown($column)
+    };
 }
 
 #[macro_export]
@@ -92,3 +104,10 @@ macro_rules! filter_op {
         $crate::FilterOperator::Single($filter)
     };
 }
+
+#[macro_export]
+macro_rules! seed {
+    ($db:expr, $($key:expr => $record:expr),* $(,)?) => {
+        $crate::fixtures::seed($db, vec![$(($key, $record)),*])
+    };
+}
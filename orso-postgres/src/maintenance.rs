@@ -0,0 +1,160 @@
+//! `ANALYZE`, `VACUUM` and `REINDEX` of model tables
+//!
+//! [`Maintenance`] runs the routine upkeep a DBA would otherwise schedule with psql, and reports
+//! how long it took and how the table's size changed:
+//!
+//! ```rust,ignore
+//! let report = Maintenance::vacuum::<Trade>(&db, false).await?;
+//! info!(?report.duration, reclaimed = report.reclaimed(), "Vacuumed trades");
+//!
+//! // Nightly, outside of trading hours
+//! Maintenance::reindex::<Trade>(&db).await?;
+//! ```
+//!
+//! Statements run on a connection of their own outside any transaction, as `VACUUM` requires,
+//! and only on tables that exist. `VACUUM FULL` and `REINDEX` lock the table against reads and
+//! writes, so they give up after [`Maintenance::LOCK_TIMEOUT`] instead of queueing the workload
+//! behind them.
+
+use crate::{Database, Error, Orso, Result};
+use std::time::{Duration, Instant};
+use tracing::{info, instrument};
+
+/// Outcome of a maintenance statement
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MaintenanceReport {
+    pub table: String,
+    /// The statement that ran, e.g. `VACUUM FULL trades`
+    pub statement: String,
+    pub duration: Duration,
+    /// Size of the table with its indexes and TOAST data before and after, in bytes
+    pub size_before: u64,
+    pub size_after: u64,
+}
+
+impl MaintenanceReport {
+    /// Bytes given back by the statement
+    pub fn reclaimed(&self) -> u64 {
+        self.size_before.saturating_sub(self.size_after)
+    }
+}
+
+/// Table maintenance
+pub struct Maintenance;
+
+impl Maintenance {
+    /// How long `VACUUM FULL` and `REINDEX` wait for their exclusive lock
+    pub const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+    /// Refresh the planner statistics of `T`'s table
+    pub async fn analyze<T: Orso>(db: &Database) -> Result<MaintenanceReport> {
+        Self::analyze_with_table(db, T::table_name()).await
+    }
+
+    #[instrument(name = "orso.maintenance.analyze", skip_all, fields(table = %table_name))]
+    pub async fn analyze_with_table(db: &Database, table_name: &str) -> Result<MaintenanceReport> {
+        Self::run(db, table_name, format!("ANALYZE {table_name}"), false).await
+    }
+
+    /// Reclaim dead rows of `T`'s table; `full` rewrites the table, returning the space to
+    /// the operating system but locking the table while it runs
+    pub async fn vacuum<T: Orso>(db: &Database, full: bool) -> Result<MaintenanceReport> {
+        Self::vacuum_with_table(db, T::table_name(), full).await
+    }
+
+    #[instrument(
+        name = "orso.maintenance.vacuum",
+        skip_all,
+        fields(table = %table_name, full)
+    )]
+    pub async fn vacuum_with_table(
+        db: &Database,
+        table_name: &str,
+        full: bool,
+    ) -> Result<MaintenanceReport> {
+        let sql = if full {
+            format!("VACUUM (FULL, ANALYZE) {table_name}")
+        } else {
+            format!("VACUUM (ANALYZE) {table_name}")
+        };
+        Self::run(db, table_name, sql, full).await
+    }
+
+    /// Rebuild the indexes of `T`'s table, e.g. after heavy churn bloated them
+    pub async fn reindex<T: Orso>(db: &Database) -> Result<MaintenanceReport> {
+        Self::reindex_with_table(db, T::table_name()).await
+    }
+
+    #[instrument(name = "orso.maintenance.reindex", skip_all, fields(table = %table_name))]
+    pub async fn reindex_with_table(db: &Database, table_name: &str) -> Result<MaintenanceReport> {
+        Self::run(db, table_name, format!("REINDEX TABLE {table_name}"), true).await
+    }
+
+    async fn run(
+        db: &Database,
+        table_name: &str,
+        sql: String,
+        exclusive: bool,
+    ) -> Result<MaintenanceReport> {
+        if !crate::migrations::check_table_exists(db, table_name).await? {
+            return Err(Error::operation(
+                format!("Table {table_name} does not exist"),
+                "maintenance",
+                Some(table_name.to_string()),
+            ));
+        }
+
+        let statement = sql.as_str();
+        db.with_connection(&sql, |client| async move {
+            let size_before = Self::size(&client, table_name).await?;
+
+            if exclusive {
+                let timeout = Self::LOCK_TIMEOUT.as_millis();
+                client
+                    .batch_execute(&format!("SET lock_timeout = {timeout}"))
+                    .await?;
+            }
+            let started = Instant::now();
+            let result = client.batch_execute(statement).await;
+            let duration = started.elapsed();
+            if exclusive {
+                client.batch_execute("RESET lock_timeout").await?;
+            }
+            result.map_err(|e| {
+                Error::operation(
+                    format!("{statement} failed: {e}"),
+                    "maintenance",
+                    Some(table_name.to_string()),
+                )
+            })?;
+
+            let size_after = Self::size(&client, table_name).await?;
+            info!(
+                statement = %statement,
+                duration_ms = duration.as_secs_f64() * 1000.0,
+                size_before,
+                size_after,
+                "Maintenance finished"
+            );
+            Ok(MaintenanceReport {
+                table: table_name.to_string(),
+                statement: statement.to_string(),
+                duration,
+                size_before,
+                size_after,
+            })
+        })
+        .await
+    }
+
+    async fn size(client: &tokio_postgres::Client, table_name: &str) -> Result<u64> {
+        let row = client
+            .query_one(
+                "SELECT pg_total_relation_size(to_regclass($1))::bigint",
+                &[&table_name],
+            )
+            .await?;
+        let size: Option<i64> = row.get(0);
+        Ok(size.unwrap_or(0) as u64)
+    }
+}
@@ -0,0 +1,221 @@
+//! Pool and query metrics
+//!
+//! Enabled with the `metrics` feature. Attach a [`MetricsHook`] to a database with
+//! [`Database::with_metrics`](crate::Database::with_metrics) and every query reports its
+//! duration and row count, and every connection checkout its pool wait time. The built-in
+//! [`PrometheusRecorder`] keeps the numbers in memory and renders them in the Prometheus text
+//! exposition format:
+//!
+//! ```rust,ignore
+//! let recorder = Arc::new(PrometheusRecorder::new());
+//! let db = Database::init(config).await?.with_metrics(recorder.clone());
+//! // serve `recorder.render()` from a /metrics endpoint
+//! ```
+
+use crate::database::statement_kind;
+use std::collections::BTreeMap;
+use std::fmt::{self, Write};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Receives measurements from [`Database`](crate::Database)
+pub trait MetricsHook: Send + Sync {
+    /// A statement finished; `rows` is the number of rows returned or affected
+    fn record_query(&self, _sql: &str, _duration: Duration, _rows: u64) {}
+
+    /// A statement failed
+    fn record_query_error(&self, _sql: &str, _duration: Duration) {}
+
+    /// Time spent waiting for a pooled connection
+    fn record_pool_wait(&self, _duration: Duration) {}
+
+    /// Pool state after a connection was checked out
+    fn record_connections(&self, _active: usize, _idle: usize) {}
+}
+
+impl fmt::Debug for dyn MetricsHook {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("MetricsHook")
+    }
+}
+
+const DURATION_BUCKETS: &[f64] = &[
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0,
+];
+const ROW_BUCKETS: &[f64] = &[0.0, 1.0, 10.0, 100.0, 1_000.0, 10_000.0, 100_000.0];
+
+#[derive(Debug, Clone)]
+struct Histogram {
+    bounds: &'static [f64],
+    /// Cumulative count per bound
+    buckets: Vec<u64>,
+    sum: f64,
+    count: u64,
+}
+
+impl Histogram {
+    fn new(bounds: &'static [f64]) -> Self {
+        Self {
+            bounds,
+            buckets: vec![0; bounds.len()],
+            sum: 0.0,
+            count: 0,
+        }
+    }
+
+    fn observe(&mut self, value: f64) {
+        for (bound, bucket) in self.bounds.iter().zip(self.buckets.iter_mut()) {
+            if value <= *bound {
+                *bucket += 1;
+            }
+        }
+        self.sum += value;
+        self.count += 1;
+    }
+
+    fn render(&self, out: &mut String, name: &str, labels: &str) {
+        let sep = if labels.is_empty() { "" } else { "," };
+        for (bound, bucket) in self.bounds.iter().zip(&self.buckets) {
+            let _ = writeln!(out, "{name}_bucket{{{labels}{sep}le=\"{bound}\"}} {bucket}");
+        }
+        let _ = writeln!(
+            out,
+            "{name}_bucket{{{labels}{sep}le=\"+Inf\"}} {}",
+            self.count
+        );
+        let labels = if labels.is_empty() {
+            String::new()
+        } else {
+            format!("{{{labels}}}")
+        };
+        let _ = writeln!(out, "{name}_sum{labels} {}", self.sum);
+        let _ = writeln!(out, "{name}_count{labels} {}", self.count);
+    }
+}
+
+#[derive(Debug)]
+struct RecorderState {
+    durations: BTreeMap<&'static str, Histogram>,
+    rows: BTreeMap<&'static str, Histogram>,
+    errors: BTreeMap<&'static str, u64>,
+    pool_wait: Histogram,
+    active: usize,
+    idle: usize,
+}
+
+/// In-memory [`MetricsHook`] that renders Prometheus text output
+#[derive(Debug)]
+pub struct PrometheusRecorder {
+    state: Mutex<RecorderState>,
+}
+
+impl Default for PrometheusRecorder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PrometheusRecorder {
+    pub fn new() -> Self {
+        Self {
+            state: Mutex::new(RecorderState {
+                durations: BTreeMap::new(),
+                rows: BTreeMap::new(),
+                errors: BTreeMap::new(),
+                pool_wait: Histogram::new(DURATION_BUCKETS),
+                active: 0,
+                idle: 0,
+            }),
+        }
+    }
+
+    /// Number of statements recorded, including failed ones
+    pub fn query_count(&self) -> u64 {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let ok: u64 = state.durations.values().map(|h| h.count).sum();
+        ok + state.errors.values().sum::<u64>()
+    }
+
+    /// Render all metrics in the Prometheus text exposition format
+    pub fn render(&self) -> String {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        let mut out = String::new();
+
+        out.push_str("# HELP orso_query_duration_seconds Time spent executing statements\n");
+        out.push_str("# TYPE orso_query_duration_seconds histogram\n");
+        for (kind, histogram) in &state.durations {
+            let labels = format!("statement=\"{kind}\"");
+            histogram.render(&mut out, "orso_query_duration_seconds", &labels);
+        }
+
+        out.push_str("# HELP orso_query_rows Rows returned or affected per statement\n");
+        out.push_str("# TYPE orso_query_rows histogram\n");
+        for (kind, histogram) in &state.rows {
+            let labels = format!("statement=\"{kind}\"");
+            histogram.render(&mut out, "orso_query_rows", &labels);
+        }
+
+        out.push_str("# HELP orso_query_errors_total Statements that returned an error\n");
+        out.push_str("# TYPE orso_query_errors_total counter\n");
+        for (kind, count) in &state.errors {
+            let _ = writeln!(
+                out,
+                "orso_query_errors_total{{statement=\"{kind}\"}} {count}"
+            );
+        }
+
+        out.push_str("# HELP orso_pool_wait_seconds Time spent waiting for a pooled connection\n");
+        out.push_str("# TYPE orso_pool_wait_seconds histogram\n");
+        state
+            .pool_wait
+            .render(&mut out, "orso_pool_wait_seconds", "");
+
+        out.push_str("# HELP orso_pool_connections Pooled connections by state\n");
+        out.push_str("# TYPE orso_pool_connections gauge\n");
+        let _ = writeln!(
+            out,
+            "orso_pool_connections{{state=\"active\"}} {}",
+            state.active
+        );
+        let _ = writeln!(
+            out,
+            "orso_pool_connections{{state=\"idle\"}} {}",
+            state.idle
+        );
+
+        out
+    }
+}
+
+impl MetricsHook for PrometheusRecorder {
+    fn record_query(&self, sql: &str, duration: Duration, rows: u64) {
+        let kind = statement_kind(sql);
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state
+            .durations
+            .entry(kind)
+            .or_insert_with(|| Histogram::new(DURATION_BUCKETS))
+            .observe(duration.as_secs_f64());
+        state
+            .rows
+            .entry(kind)
+            .or_insert_with(|| Histogram::new(ROW_BUCKETS))
+            .observe(rows as f64);
+    }
+
+    fn record_query_error(&self, sql: &str, _duration: Duration) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        *state.errors.entry(statement_kind(sql)).or_insert(0) += 1;
+    }
+
+    fn record_pool_wait(&self, duration: Duration) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.pool_wait.observe(duration.as_secs_f64());
+    }
+
+    fn record_connections(&self, active: usize, idle: usize) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.active = active;
+        state.idle = idle;
+    }
+}
@@ -1,7 +1,8 @@
-use tracing::{debug, trace};
+use tracing::{debug, instrument, trace, warn};
 
 // Migration system with zero-loss schema changes
-use crate::{database::Database, error::Error, traits::FieldType, Orso};
+use crate::strategy::{apply_changes, plan_changes, MigrationStrategy, PlannedChange};
+use crate::{database::Database, error::Error, traits::FieldType, Orso, TypeOverride};
 // use chrono::{DateTime, Utc}; // Reserved for future migration timestamp features
 // use serde::{Deserialize, Serialize}; // Reserved for future migration serialization
 use std::collections::HashMap;
@@ -11,6 +12,9 @@ pub struct MigrationConfig {
     max_backups_per_table: Option<u8>,
     backup_retention_days: Option<u8>,
     backup_suffix: Option<String>,
+    strategy: MigrationStrategy,
+    /// Running `Migrations::contract`
+    contracting: bool,
 }
 
 impl Default for MigrationConfig {
@@ -19,6 +23,8 @@ impl Default for MigrationConfig {
             max_backups_per_table: Some(5),
             backup_retention_days: Some(30),
             backup_suffix: Some("migration".to_string()),
+            strategy: MigrationStrategy::default(),
+            contracting: false,
         }
     }
 }
@@ -36,6 +42,21 @@ impl MigrationConfig {
     pub fn suffix(&self) -> &str {
         self.backup_suffix.as_deref().unwrap_or("migration")
     }
+
+    /// How tables that no longer match their model are changed, copying them by default
+    pub fn with_strategy(mut self, strategy: MigrationStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    pub fn strategy(&self) -> MigrationStrategy {
+        self.strategy
+    }
+
+    pub(crate) fn contracting(mut self) -> Self {
+        self.contracting = true;
+        self
+    }
 }
 
 pub struct Migrations;
@@ -52,6 +73,7 @@ impl Migrations {
 
     /// Initialize database with migrations and custom config
     /// Usage: Migrations::init_with_config(&db, &[migration!(User)], &config).await?
+    #[instrument(name = "orso.migrate", skip_all, fields(migrations = migrations.len()))]
     pub async fn init_with_config(
         db: &Database,
         migrations: &[Box<dyn MigrationTrait>],
@@ -60,12 +82,326 @@ impl Migrations {
         let mut results = Vec::new();
 
         for migration in migrations {
-            let result = migration.run_migration(db, config).await?;
+            let result = if migration.transactional() {
+                Self::run_in_transaction(db, migration.as_ref(), config).await?
+            } else {
+                migration.run_migration(db, config).await?
+            };
             results.push(result);
         }
 
         Ok(results)
     }
+
+    /// Run `migration` in a transaction on a connection of its own, so a failure leaves the
+    /// table as it was
+    async fn run_in_transaction(
+        db: &Database,
+        migration: &dyn MigrationTrait,
+        config: &MigrationConfig,
+    ) -> Result<MigrationResult, Error> {
+        let tx = db.pin().await?;
+        tx.execute("BEGIN", &[]).await?;
+        let mut open = OpenTransaction {
+            db: &tx,
+            open: true,
+        };
+
+        match migration.run_migration(&tx, config).await {
+            Ok(result) => {
+                tx.execute("COMMIT", &[]).await?;
+                open.open = false;
+                Ok(result)
+            }
+            Err(e) => {
+                // The original error says more than a failed rollback would
+                match tx.execute("ROLLBACK", &[]).await {
+                    Ok(_) => open.open = false,
+                    Err(rollback) => warn!(error = %rollback, "Failed to roll back migration"),
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Compare live tables to their models without changing anything
+    /// Usage: let drift = Migrations::verify(&db, &[migration!(User)]).await?
+    #[instrument(name = "orso.verify", skip_all, fields(migrations = migrations.len()))]
+    pub async fn verify(
+        db: &Database,
+        migrations: &[Box<dyn MigrationTrait>],
+    ) -> Result<Vec<Drift>, Error> {
+        let mut drift = Vec::new();
+
+        for migration in migrations {
+            drift.extend(migration.verify(db).await?);
+        }
+
+        Ok(drift)
+    }
+
+    /// Statements creating a `BEFORE UPDATE` trigger that sets `column` of `table_name` to `NOW()`
+    pub fn updated_at_trigger_sql(table_name: &str, column: &str) -> Vec<String> {
+        Self::updated_at_trigger_sql_with(table_name, column, "NOW()")
+    }
+
+    /// Like `updated_at_trigger_sql`, setting the column to the SQL expression `now`
+    pub(crate) fn updated_at_trigger_sql_with(
+        table_name: &str,
+        column: &str,
+        now: &str,
+    ) -> Vec<String> {
+        vec![
+            format!(
+                "CREATE OR REPLACE FUNCTION {table_name}_updated_at_fn() RETURNS trigger AS $$\n\
+                 BEGIN\n    \
+                 NEW.{column} := {now};\n    \
+                 RETURN NEW;\n\
+                 END;\n\
+                 $$ LANGUAGE plpgsql"
+            ),
+            format!("DROP TRIGGER IF EXISTS {table_name}_updated_at_trigger ON {table_name}"),
+            format!(
+                "CREATE TRIGGER {table_name}_updated_at_trigger BEFORE UPDATE ON {table_name} \
+                 FOR EACH ROW EXECUTE FUNCTION {table_name}_updated_at_fn()"
+            ),
+        ]
+    }
+
+    /// `COMMENT ON` statements for the table and column comments of `T`
+    pub fn comment_sql<T: Orso>(table_name: &str) -> Vec<String> {
+        let literal = |text: &str| crate::Value::Text(text.to_string()).to_sql_literal();
+        let table = T::table_comment()
+            .map(|comment| format!("COMMENT ON TABLE {table_name} IS {}", literal(comment)));
+        let columns = T::column_comments().into_iter().map(|(column, comment)| {
+            format!(
+                "COMMENT ON COLUMN {table_name}.{column} IS {}",
+                literal(comment)
+            )
+        });
+        table.into_iter().chain(columns).collect()
+    }
+
+    /// Name of the `(created_at, primary key)` index of `table_name`
+    pub fn seek_index_name(table_name: &str) -> String {
+        format!("{table_name}_seek_idx")
+    }
+
+    /// Create the `(created_at, primary key)` index behind `page_after`, which
+    /// `Migrations::init` does for models marked `#[orso_column(created_at, seek)]`
+    pub async fn install_seek_index(
+        db: &Database,
+        table_name: &str,
+        created_at: &str,
+        primary_key: &str,
+    ) -> Result<(), Error> {
+        let sql = format!(
+            "CREATE INDEX IF NOT EXISTS {} ON {table_name} ({created_at}, {primary_key})",
+            Self::seek_index_name(table_name)
+        );
+        db.execute(&sql, &[]).await.map_err(|e| {
+            Error::migration(
+                format!("Failed to create seek index: {}", e),
+                Some(table_name.to_string()),
+                Some("install_seek_index".to_string()),
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Create an index declared with `#[orso_index(...)]`, which `Migrations::init` does for
+    /// every index of a model
+    ///
+    /// An existing index built with another method or definition, e.g. another `where`
+    /// condition, is rebuilt, and so is one left invalid by an interrupted concurrent build.
+    /// `concurrently` indexes are built without blocking writes, retrying with the default
+    /// [`RetryPolicy`](crate::RetryPolicy) on deadlocks and lock timeouts; a failed build is
+    /// dropped rather than left behind invalid.
+    pub async fn install_index(
+        db: &Database,
+        table_name: &str,
+        index: &crate::IndexDef,
+    ) -> Result<(), Error> {
+        let name = index.name(table_name);
+        let failed = |e: Error| {
+            Error::migration(
+                format!("Failed to create index {}: {}", name, e),
+                Some(table_name.to_string()),
+                Some("install_index".to_string()),
+            )
+        };
+
+        if let Some((method, valid, definition)) = Self::index_state(db, &name).await? {
+            if valid
+                && method == index.method()
+                && index_shape(&definition)
+                    == index_shape(&Self::declared_index_definition(db, table_name, index).await?)
+            {
+                return Ok(());
+            }
+            debug!(index = %name, from = %method, to = index.method(), valid, "Rebuilding index");
+            Self::drop_index(db, &name, index.concurrently)
+                .await
+                .map_err(failed)?;
+        }
+
+        let policy = crate::RetryPolicy::default();
+        let mut attempt = 1;
+        loop {
+            let Err(e) = db.execute(&index.sql(table_name), &[]).await else {
+                return Ok(());
+            };
+            if !index.concurrently {
+                return Err(failed(e));
+            }
+            // A failed concurrent build leaves an invalid index that still slows down writes
+            if let Some((_, false, _)) = Self::index_state(db, &name).await? {
+                Self::drop_index(db, &name, true).await.map_err(failed)?;
+            }
+            let retryable = matches!(
+                &e,
+                Error::PostgreSql { code: Some(code), .. } if code == "40P01" || code == "55P03"
+            );
+            if !retryable || attempt >= policy.max_attempts {
+                return Err(failed(e));
+            }
+            warn!(index = %name, attempt, error = %e, "Retrying concurrent index build");
+            tokio::time::sleep(policy.backoff(attempt)).await;
+            attempt += 1;
+        }
+    }
+
+    /// Method of the index `name` in the current schema, whether it is valid and its
+    /// `pg_get_indexdef`, if it exists
+    async fn index_state(
+        db: &Database,
+        name: &str,
+    ) -> Result<Option<(String, bool, String)>, Error> {
+        let query = "
+            SELECT am.amname::text, ix.indisvalid, pg_get_indexdef(ix.indexrelid)
+            FROM pg_class i
+            JOIN pg_index ix ON ix.indexrelid = i.oid
+            JOIN pg_am am ON am.oid = i.relam
+            JOIN pg_namespace n ON n.oid = i.relnamespace
+            WHERE n.nspname = current_schema() AND i.relname = $1
+        ";
+        let rows = db.query(query, &[&name]).await?;
+        Ok(rows.first().map(|row| (row.get(0), row.get(1), row.get(2))))
+    }
+
+    /// `pg_get_indexdef` of `index` built on an empty copy of `table_name`, which compares to
+    /// the live index since PostgreSQL rewrites key expressions and conditions
+    pub(crate) async fn declared_index_definition(
+        db: &Database,
+        table_name: &str,
+        index: &crate::IndexDef,
+    ) -> Result<String, Error> {
+        let probe = crate::IndexDef {
+            name: Some("orso_index_probe_idx"),
+            concurrently: false,
+            ..*index
+        };
+        let sql = probe.sql("orso_index_probe");
+        let statement = sql.as_str();
+        db.with_connection(&sql, |client| async move {
+            client
+                .batch_execute(&format!(
+                    "DROP TABLE IF EXISTS pg_temp.orso_index_probe;\n\
+                     CREATE TEMP TABLE orso_index_probe (LIKE {table_name});\n\
+                     {statement}"
+                ))
+                .await?;
+            let row = client
+                .query_one(
+                    "SELECT pg_get_indexdef('pg_temp.orso_index_probe_idx'::regclass)",
+                    &[],
+                )
+                .await?;
+            client
+                .batch_execute("DROP TABLE pg_temp.orso_index_probe")
+                .await?;
+            Ok(row.get(0))
+        })
+        .await
+    }
+
+    async fn drop_index(db: &Database, name: &str, concurrently: bool) -> Result<u64, Error> {
+        let concurrently = if concurrently { " CONCURRENTLY" } else { "" };
+        db.execute(&format!("DROP INDEX{concurrently} IF EXISTS {name}"), &[])
+            .await
+    }
+
+    /// Name of the unique `lower(column)` index of `table_name`
+    pub fn case_insensitive_index_name(table_name: &str, column: &str) -> String {
+        format!("{table_name}_{column}_lower_key")
+    }
+
+    /// Create the unique `lower(column)` index, which `Migrations::init` does for columns
+    /// marked `#[orso_column(unique(case_insensitive))]`
+    pub async fn install_case_insensitive_unique(
+        db: &Database,
+        table_name: &str,
+        column: &str,
+    ) -> Result<(), Error> {
+        let sql = format!(
+            "CREATE UNIQUE INDEX IF NOT EXISTS {} ON {table_name} (lower({column}))",
+            Self::case_insensitive_index_name(table_name, column)
+        );
+        db.execute(&sql, &[]).await.map_err(|e| {
+            Error::migration(
+                format!(
+                    "Failed to create case-insensitive unique index on {column}: {}",
+                    e
+                ),
+                Some(table_name.to_string()),
+                Some("install_case_insensitive_unique".to_string()),
+            )
+        })?;
+        Ok(())
+    }
+
+    /// Install the trigger maintaining `column` of `table_name`, which `Migrations::init` does
+    /// for models marked `#[orso_column(updated_at, trigger)]`
+    pub async fn install_updated_at_trigger(
+        db: &Database,
+        table_name: &str,
+        column: &str,
+    ) -> Result<(), Error> {
+        Self::install_updated_at_trigger_with(db, table_name, column, "NOW()").await
+    }
+
+    pub(crate) async fn install_updated_at_trigger_with(
+        db: &Database,
+        table_name: &str,
+        column: &str,
+        now: &str,
+    ) -> Result<(), Error> {
+        for sql in Self::updated_at_trigger_sql_with(table_name, column, now) {
+            db.execute(&sql, &[]).await.map_err(|e| {
+                Error::migration(
+                    format!("Failed to install updated_at trigger: {}", e),
+                    Some(table_name.to_string()),
+                    Some("install_updated_at_trigger".to_string()),
+                )
+            })?;
+        }
+        Ok(())
+    }
+}
+
+/// Closes the connection of a migration transaction that didn't end, e.g. because the
+/// migration was cancelled, rather than let the pool hand it out mid-transaction
+struct OpenTransaction<'a> {
+    db: &'a Database,
+    open: bool,
+}
+
+impl Drop for OpenTransaction<'_> {
+    fn drop(&mut self) {
+        if self.open {
+            self.db.detach();
+        }
+    }
 }
 
 // Trait for migrations to avoid generic constraints
@@ -76,6 +412,22 @@ pub trait MigrationTrait: Send + Sync {
         db: &Database,
         config: &MigrationConfig,
     ) -> Result<MigrationResult, Error>;
+
+    async fn verify(&self, db: &Database) -> Result<Vec<Drift>, Error>;
+
+    /// Whether `Migrations::init` runs the migration in a transaction
+    fn transactional(&self) -> bool {
+        true
+    }
+
+    /// Changes `run_migration` would make with `config`, without making them
+    async fn plan(
+        &self,
+        _db: &Database,
+        _config: &MigrationConfig,
+    ) -> Result<Vec<PlannedChange>, Error> {
+        Ok(Vec::new())
+    }
 }
 
 // Migration entry for the init system
@@ -107,11 +459,105 @@ impl<T: Orso + Default + Send + Sync> MigrationTrait for MigrationEntry<T> {
         db: &Database,
         config: &MigrationConfig,
     ) -> Result<MigrationResult, Error> {
-        if let Some(custom_name) = &self.custom_table_name {
-            ensure_table_with_name::<T>(db, custom_name, config).await
-        } else {
-            ensure_table::<T>(db, config).await
+        let table_name = self
+            .custom_table_name
+            .as_deref()
+            .unwrap_or_else(|| T::table_name());
+        let result = ensure_table_with_name::<T>(db, table_name, config).await?;
+
+        if T::audited() {
+            crate::audit::Audit::install_with_table::<T>(db, table_name).await?;
+            // The backup keeps the old trigger after a zero-loss migration
+            if let Some(backup) = &result.backup_table {
+                crate::audit::Audit::uninstall_trigger(db, backup, table_name).await?;
+            }
+        }
+
+        if let Some(column) = T::updated_at_field().filter(|_| T::updated_at_trigger()) {
+            let now = crate::types::now_sql::<T>(column);
+            Migrations::install_updated_at_trigger_with(db, table_name, column, now).await?;
+            // Likewise, the backup would keep bumping its own updated_at
+            if let Some(backup) = &result.backup_table {
+                let sql =
+                    format!("DROP TRIGGER IF EXISTS {table_name}_updated_at_trigger ON {backup}");
+                db.execute(&sql, &[]).await?;
+            }
+        }
+
+        if let Some(column) = T::created_at_field().filter(|_| T::seek_index()) {
+            // Index names are per schema, so the backup's copy has to make way
+            if let Some(backup) = &result.backup_table {
+                let sql = format!(
+                    "ALTER INDEX IF EXISTS {} RENAME TO {}",
+                    Migrations::seek_index_name(table_name),
+                    Migrations::seek_index_name(backup)
+                );
+                db.execute(&sql, &[]).await?;
+            }
+            Migrations::install_seek_index(db, table_name, column, T::primary_key_field()).await?;
+        }
+
+        for sql in Migrations::comment_sql::<T>(table_name) {
+            db.execute(&sql, &[]).await.map_err(|e| {
+                Error::migration(
+                    format!("Failed to set comment: {}", e),
+                    Some(table_name.to_string()),
+                    Some("comment".to_string()),
+                )
+            })?;
+        }
+
+        for column in T::case_insensitive_unique_fields() {
+            if let Some(backup) = &result.backup_table {
+                let sql = format!(
+                    "ALTER INDEX IF EXISTS {} RENAME TO {}",
+                    Migrations::case_insensitive_index_name(table_name, column),
+                    Migrations::case_insensitive_index_name(backup, column)
+                );
+                db.execute(&sql, &[]).await?;
+            }
+            Migrations::install_case_insensitive_unique(db, table_name, column).await?;
+        }
+
+        for index in T::indexes() {
+            let name = index.name(table_name);
+            if let Some(backup) = &result.backup_table {
+                let backup_name = crate::IndexDef {
+                    name: None,
+                    ..index
+                }
+                .name(backup);
+                let sql = format!("ALTER INDEX IF EXISTS {name} RENAME TO {backup_name}");
+                db.execute(&sql, &[]).await?;
+            }
+            Migrations::install_index(db, table_name, &index).await?;
         }
+
+        Ok(result)
+    }
+
+    async fn verify(&self, db: &Database) -> Result<Vec<Drift>, Error> {
+        let table_name = self
+            .custom_table_name
+            .as_deref()
+            .unwrap_or_else(|| T::table_name());
+        verify_table::<T>(db, table_name).await
+    }
+
+    async fn plan(
+        &self,
+        db: &Database,
+        config: &MigrationConfig,
+    ) -> Result<Vec<PlannedChange>, Error> {
+        let table_name = self
+            .custom_table_name
+            .as_deref()
+            .unwrap_or_else(|| T::table_name());
+        plan_table::<T>(db, table_name, config).await
+    }
+
+    fn transactional(&self) -> bool {
+        T::transactional()
     }
 }
 
@@ -142,6 +588,9 @@ pub struct ColumnInfo {
     pub foreign_key_reference: Option<String>,
     pub has_default: bool,
     pub is_compressed: bool, // Track if this column should be compressed
+    /// Whether the column's unique and foreign key constraints are deferrable, and if so
+    /// whether they start out deferred
+    pub deferral: Option<bool>,
 }
 
 #[derive(Debug, Clone)]
@@ -156,7 +605,21 @@ pub struct SchemaComparison {
 pub enum MigrationAction {
     TableCreated,
     SchemaMatched,
-    DataMigrated { from: String, to: String },
+    DataMigrated {
+        from: String,
+        to: String,
+    },
+    /// Changed with `ALTER TABLE`, under [`MigrationStrategy::InPlace`] or when contracting
+    AlteredInPlace,
+    /// Expanded under [`MigrationStrategy::ExpandContract`]; `pending` describes the changes
+    /// left for `Migrations::contract`
+    Expanded {
+        pending: Vec<String>,
+    },
+    /// Created the indexes named in `indexes`, from [`crate::Advisor::migration`]
+    IndexesCreated {
+        indexes: Vec<String>,
+    },
 }
 
 #[derive(Debug, Clone)]
@@ -167,6 +630,98 @@ pub struct MigrationResult {
     pub schema_changes: Vec<String>,
 }
 
+/// A difference between a model and its live table, reported by [`Migrations::verify`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Drift {
+    MissingTable {
+        table: String,
+    },
+    MissingColumn {
+        table: String,
+        column: String,
+    },
+    ExtraColumn {
+        table: String,
+        column: String,
+    },
+    TypeMismatch {
+        table: String,
+        column: String,
+        expected: String,
+        actual: String,
+    },
+    NullabilityMismatch {
+        table: String,
+        column: String,
+        expected: bool,
+        actual: bool,
+    },
+    PrimaryKeyMismatch {
+        table: String,
+        column: String,
+        expected: bool,
+        actual: bool,
+    },
+    UniqueMismatch {
+        table: String,
+        column: String,
+        expected: bool,
+        actual: bool,
+    },
+    /// Unique or foreign key constraints checked at another time than declared; `None`
+    /// stands for not deferrable, `Some(initially_deferred)` for deferrable
+    DeferralMismatch {
+        table: String,
+        column: String,
+        expected: Option<bool>,
+        actual: Option<bool>,
+    },
+    /// An index the model doesn't declare
+    ExtraIndex {
+        table: String,
+        index: String,
+    },
+    /// An index declared with `#[orso_index(...)]` the table doesn't have
+    MissingIndex {
+        table: String,
+        index: String,
+    },
+    /// A declared index built with another method, e.g. `btree` instead of `gin`
+    IndexMethodMismatch {
+        table: String,
+        index: String,
+        expected: String,
+        actual: String,
+    },
+    /// A declared index with other keys, uniqueness or `where` condition; `expected` is the
+    /// declared `CREATE INDEX` statement and `actual` the live one from `pg_get_indexdef`
+    IndexDefinitionMismatch {
+        table: String,
+        index: String,
+        expected: String,
+        actual: String,
+    },
+}
+
+impl Drift {
+    pub fn table(&self) -> &str {
+        match self {
+            Drift::MissingTable { table }
+            | Drift::MissingColumn { table, .. }
+            | Drift::ExtraColumn { table, .. }
+            | Drift::TypeMismatch { table, .. }
+            | Drift::NullabilityMismatch { table, .. }
+            | Drift::PrimaryKeyMismatch { table, .. }
+            | Drift::UniqueMismatch { table, .. }
+            | Drift::DeferralMismatch { table, .. }
+            | Drift::ExtraIndex { table, .. }
+            | Drift::MissingIndex { table, .. }
+            | Drift::IndexMethodMismatch { table, .. }
+            | Drift::IndexDefinitionMismatch { table, .. } => table,
+        }
+    }
+}
+
 pub async fn ensure_table<T>(
     db: &Database,
     config: &MigrationConfig,
@@ -178,6 +733,7 @@ where
     ensure_table_with_name::<T>(db, table_name, config).await
 }
 
+#[instrument(name = "orso.ensure_table", skip_all, fields(table = %table_name))]
 pub async fn ensure_table_with_name<T>(
     db: &Database,
     table_name: &str,
@@ -187,6 +743,7 @@ where
     T: Orso + Default,
 {
     // Step 1: Infer expected schema from Orso trait
+    validate_type_overrides::<T>(table_name)?;
     let expected_schema = infer_schema_from_orso::<T>()?;
 
     // Step 2: Check if table exists
@@ -215,7 +772,9 @@ where
     }
 
     // Step 3: Compare current vs expected schema
-    let current_schema = get_current_table_schema(db, table_name).await?;
+    let overrides = T::type_overrides();
+    let mut current_schema = get_current_table_schema(db, table_name).await?;
+    apply_live_override_types(db, table_name, &overrides, &mut current_schema).await?;
     let comparison = compare_schemas(&current_schema, &expected_schema);
 
     if !comparison.needs_migration {
@@ -227,11 +786,267 @@ where
         });
     }
 
-    // Step 4: Perform zero-loss migration using proven algorithm
-    perform_zero_loss_migration(db, table_name, &comparison, config).await
+    // Step 4: Alter the table in place if the strategy allows, or perform zero-loss migration
+    check_override_conversions(db, table_name, &overrides, &comparison).await?;
+    let deferrable = T::deferrable_fields();
+    let strategy = match config.strategy() {
+        _ if config.contracting => MigrationStrategy::InPlace,
+        MigrationStrategy::CopyTable => {
+            return perform_zero_loss_migration(db, table_name, &comparison, &deferrable, config)
+                .await
+        }
+        strategy => strategy,
+    };
+
+    let constraints = crate::Schema::constraints(db, table_name).await?;
+    let changes = plan_changes(
+        table_name,
+        &comparison.current_columns,
+        &comparison.expected_columns,
+        &constraints,
+        &deferrable,
+        strategy,
+    );
+    let result = |action, schema_changes| MigrationResult {
+        action,
+        backup_table: None,
+        rows_migrated: None,
+        schema_changes,
+    };
+    // Columns out of order are all that's left
+    if changes.is_empty() {
+        return Ok(result(MigrationAction::SchemaMatched, vec![]));
+    }
+
+    if strategy == MigrationStrategy::ExpandContract {
+        let (pending, expand): (Vec<_>, Vec<_>) = changes.into_iter().partition(|c| c.contract);
+        let applied = apply_changes(db, table_name, &expand).await?;
+        let pending = pending.iter().map(|c| c.description.clone()).collect();
+        return Ok(result(MigrationAction::Expanded { pending }, applied));
+    }
+
+    let in_place = changes
+        .iter()
+        .all(|c| !c.statements.is_empty() && (config.contracting || c.is_safe()));
+    if !in_place {
+        debug!(table = %table_name, "Changes aren't safe in place, copying the table");
+        return perform_zero_loss_migration(db, table_name, &comparison, &deferrable, config).await;
+    }
+    let applied = apply_changes(db, table_name, &changes).await?;
+    Ok(result(MigrationAction::AlteredInPlace, applied))
 }
 
-fn generate_migration_sql_with_custom_name<T>(table_name: &str) -> String
+/// Changes `ensure_table_with_name` would make to `table_name` under `config`
+async fn plan_table<T>(
+    db: &Database,
+    table_name: &str,
+    config: &MigrationConfig,
+) -> Result<Vec<PlannedChange>, Error>
+where
+    T: Orso,
+{
+    validate_type_overrides::<T>(table_name)?;
+    let expected_schema = infer_schema_from_orso::<T>()?;
+
+    if !check_table_exists(db, table_name).await? {
+        return Ok(vec![PlannedChange {
+            table: table_name.to_string(),
+            description: "Create table".to_string(),
+            statements: vec![generate_migration_sql_with_custom_name::<T>(table_name)],
+            rewrites_table: false,
+            blocks_writes: false,
+            contract: false,
+        }]);
+    }
+
+    let mut current_schema = get_current_table_schema(db, table_name).await?;
+    apply_live_override_types(db, table_name, &T::type_overrides(), &mut current_schema).await?;
+    let comparison = compare_schemas(&current_schema, &expected_schema);
+    if !comparison.needs_migration {
+        return Ok(Vec::new());
+    }
+
+    let deferrable = T::deferrable_fields();
+    if config.strategy() == MigrationStrategy::CopyTable {
+        // Every change rides on the copy, which misses writes made meanwhile
+        return Ok(comparison
+            .changes
+            .into_iter()
+            .map(|description| PlannedChange {
+                table: table_name.to_string(),
+                description,
+                statements: vec![],
+                rewrites_table: true,
+                blocks_writes: true,
+                contract: false,
+            })
+            .collect());
+    }
+
+    let constraints = crate::Schema::constraints(db, table_name).await?;
+    let mut changes = plan_changes(
+        table_name,
+        &current_schema,
+        &expected_schema,
+        &constraints,
+        &deferrable,
+        config.strategy(),
+    );
+    // Under `InPlace`, one change that isn't safe has the whole table copied
+    if config.strategy() == MigrationStrategy::InPlace
+        && changes
+            .iter()
+            .any(|c| c.statements.is_empty() || !c.is_safe())
+    {
+        for change in &mut changes {
+            change.statements.clear();
+            change.rewrites_table = true;
+            change.blocks_writes = true;
+        }
+    }
+    Ok(changes)
+}
+
+/// Uniqueness and everything from `USING` on of a `pg_get_indexdef` definition, which leaves
+/// out the index and table names
+fn index_shape(definition: &str) -> (bool, &str) {
+    let (head, tail) = definition.split_once(" USING ").unwrap_or((definition, ""));
+    (head.starts_with("CREATE UNIQUE "), tail)
+}
+
+async fn verify_table<T>(db: &Database, table_name: &str) -> Result<Vec<Drift>, Error>
+where
+    T: Orso,
+{
+    if !check_table_exists(db, table_name).await? {
+        return Ok(vec![Drift::MissingTable {
+            table: table_name.to_string(),
+        }]);
+    }
+
+    let expected = infer_schema_from_orso::<T>()?;
+    let mut live = crate::Schema::describe(db, table_name).await?;
+    apply_live_override_types(db, table_name, &T::type_overrides(), &mut live.columns).await?;
+    let table = || table_name.to_string();
+    let mut drift = Vec::new();
+
+    for expected_col in &expected {
+        let column = || expected_col.name.clone();
+        let Some(current_col) = live.column(&expected_col.name) else {
+            drift.push(Drift::MissingColumn {
+                table: table(),
+                column: column(),
+            });
+            continue;
+        };
+
+        if current_col.sql_type != expected_col.sql_type {
+            drift.push(Drift::TypeMismatch {
+                table: table(),
+                column: column(),
+                expected: expected_col.sql_type.clone(),
+                actual: current_col.sql_type.clone(),
+            });
+        }
+        if current_col.nullable != expected_col.nullable {
+            drift.push(Drift::NullabilityMismatch {
+                table: table(),
+                column: column(),
+                expected: expected_col.nullable,
+                actual: current_col.nullable,
+            });
+        }
+        if current_col.is_primary_key != expected_col.is_primary_key {
+            drift.push(Drift::PrimaryKeyMismatch {
+                table: table(),
+                column: column(),
+                expected: expected_col.is_primary_key,
+                actual: current_col.is_primary_key,
+            });
+        } else if current_col.is_unique != expected_col.is_unique {
+            drift.push(Drift::UniqueMismatch {
+                table: table(),
+                column: column(),
+                expected: expected_col.is_unique,
+                actual: current_col.is_unique,
+            });
+        }
+        if deferral_differs(current_col, expected_col) {
+            drift.push(Drift::DeferralMismatch {
+                table: table(),
+                column: column(),
+                expected: expected_col.deferral,
+                actual: current_col.deferral,
+            });
+        }
+    }
+
+    for current_col in &live.columns {
+        if !expected.iter().any(|c| c.name == current_col.name) {
+            drift.push(Drift::ExtraColumn {
+                table: table(),
+                column: current_col.name.clone(),
+            });
+        }
+    }
+
+    let mut declared_indexes = Vec::new();
+    for index in T::indexes() {
+        let name = index.name(table_name);
+        match live.index(&name) {
+            None => drift.push(Drift::MissingIndex {
+                table: table(),
+                index: name.clone(),
+            }),
+            Some(live_index) if live_index.method != index.method() => {
+                drift.push(Drift::IndexMethodMismatch {
+                    table: table(),
+                    index: name.clone(),
+                    expected: index.method().to_string(),
+                    actual: live_index.method.clone(),
+                })
+            }
+            Some(live_index) => {
+                let declared =
+                    Migrations::declared_index_definition(db, table_name, &index).await?;
+                if index_shape(&live_index.definition) != index_shape(&declared) {
+                    drift.push(Drift::IndexDefinitionMismatch {
+                        table: table(),
+                        index: name.clone(),
+                        expected: index.sql(table_name),
+                        actual: live_index.definition.clone(),
+                    })
+                }
+            }
+        }
+        declared_indexes.push(name);
+    }
+
+    // Primary key and unique constraints come with an index of the same name
+    for index in &live.indexes {
+        let backs_constraint = live.constraint(&index.name).is_some_and(|c| {
+            matches!(
+                c.kind,
+                crate::ConstraintKind::PrimaryKey | crate::ConstraintKind::Unique
+            )
+        });
+        let declared = declared_indexes.contains(&index.name)
+            || (T::seek_index() && index.name == Migrations::seek_index_name(table_name))
+            || T::case_insensitive_unique_fields().iter().any(|column| {
+                index.name == Migrations::case_insensitive_index_name(table_name, column)
+            });
+        if !backs_constraint && !declared {
+            drift.push(Drift::ExtraIndex {
+                table: table(),
+                index: index.name.clone(),
+            });
+        }
+    }
+
+    Ok(drift)
+}
+
+pub(crate) fn generate_migration_sql_with_custom_name<T>(table_name: &str) -> String
 where
     T: Orso,
 {
@@ -280,7 +1095,13 @@ where
     let field_nullable = T::field_nullable();
     let field_compressed = T::field_compressed();
     let unique_fields = T::unique_fields();
+    // Case-insensitive uniqueness is a `lower()` index rather than a constraint
+    let case_insensitive = T::case_insensitive_unique_fields();
     let primary_key_field = T::primary_key_field();
+    let encrypted_fields = T::encrypted_fields();
+    let deferrable = T::deferrable_fields();
+    let timestamps = [T::created_at_field(), T::updated_at_field()];
+    let overrides = T::type_overrides();
 
     if field_names.len() != field_types.len() || field_names.len() != field_nullable.len() {
         return Err(Error::internal(
@@ -297,14 +1118,17 @@ where
         .enumerate()
     {
         // Determine if this field should be unique
-        let is_unique = unique_fields.contains(name);
+        let is_unique = unique_fields.contains(name) && !case_insensitive.contains(name);
 
         // Determine if this is the primary key
         let is_primary_key = *name == primary_key_field;
 
-        // For compressed fields, we use BYTEA type (PostgreSQL binary data)
-        let sql_type = if *compressed {
+        // For compressed and encrypted fields, we use BYTEA type (PostgreSQL binary data)
+        let is_encrypted = encrypted_fields.contains(name);
+        let sql_type = if *compressed || is_encrypted {
             "BYTEA".to_string()
+        } else if let Some(column_type) = overrides.iter().find(|o| o.column == *name) {
+            canonical_sql_type(column_type.sql_type)
         } else {
             field_type_to_sqlite_type(field_type)
         };
@@ -314,8 +1138,11 @@ where
         // created_at and updated_at fields have NOW() default
         let has_default = if is_primary_key && sql_type == "TEXT" {
             true // PRIMARY KEY TEXT fields have DEFAULT gen_random_uuid()
-        } else if *name == "created_at" || *name == "updated_at" {
-            true // Timestamp fields have DEFAULT NOW()
+        } else if *name == "created_at"
+            || *name == "updated_at"
+            || timestamps.contains(&Some(*name))
+        {
+            true // Timestamp fields have DEFAULT NOW(), or the time in millis for unix_ms ones
         } else {
             false
         };
@@ -329,7 +1156,12 @@ where
             is_primary_key,
             foreign_key_reference: None, // Would need to add this to Orso trait
             has_default,
-            is_compressed: *compressed, // Track compression status
+            // Track compression status; BYTEA columns read back as compressed
+            is_compressed: *compressed || is_encrypted,
+            deferral: deferrable
+                .iter()
+                .find(|(field, _)| field == name)
+                .map(|(_, initially_deferred)| *initially_deferred),
         });
     }
 
@@ -344,18 +1176,218 @@ fn field_type_to_sqlite_type(field_type: &FieldType) -> String {
         FieldType::Numeric => "DOUBLE PRECISION".to_string(), // PostgreSQL DOUBLE PRECISION
         FieldType::Boolean => "BOOLEAN".to_string(), // PostgreSQL native BOOLEAN
         FieldType::JsonB => "JSONB".to_string(),     // PostgreSQL native JSONB
-        FieldType::Timestamp => "TIMESTAMP WITHOUT TIME ZONE".to_string(), // PostgreSQL UTC timestamp without timezone
+        FieldType::Timestamp => "TIMESTAMP WITH TIME ZONE".to_string(), // PostgreSQL TIMESTAMPTZ
         // Array types for PostgreSQL native arrays
         FieldType::IntegerArray => "INTEGER[]".to_string(), // PostgreSQL INTEGER array
         FieldType::BigIntArray => "BIGINT[]".to_string(),   // PostgreSQL BIGINT array
         FieldType::NumericArray => "DOUBLE PRECISION[]".to_string(), // PostgreSQL DOUBLE PRECISION array
         // Vector types for pgvector extension
         FieldType::Vector(dimensions) => format!("vector({})", dimensions), // PostgreSQL pgvector type
+        FieldType::Custom(sql_type) => sql_type.to_uppercase(),
+    }
+}
+
+/// Spell a column type the way `format_type` reports it, uppercased, so that an override
+/// written `decimal(20, 8)` or `timestamptz` matches the live column
+fn canonical_sql_type(sql_type: &str) -> String {
+    let mut base = sql_type
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_uppercase();
+    let mut arrays = 0;
+    while let Some(element) = base.strip_suffix("[]") {
+        base = element.trim_end().to_string();
+        arrays += 1;
+    }
+
+    // `NAME(ARGS) REST`, e.g. `TIMESTAMP(3) WITH TIME ZONE`
+    let (name, modifier, rest) = match (base.find('('), base.find(')')) {
+        (Some(open), Some(close)) if open < close => (
+            base[..open].trim(),
+            base[open..=close].replace(' ', ""),
+            base[close + 1..].trim(),
+        ),
+        _ => (base.as_str(), String::new(), ""),
+    };
+    let canonical = match (name, rest) {
+        ("TIMESTAMPTZ", "") => format!("TIMESTAMP{modifier} WITH TIME ZONE"),
+        ("TIMESTAMP", "") => format!("TIMESTAMP{modifier} WITHOUT TIME ZONE"),
+        ("TIMETZ", "") => format!("TIME{modifier} WITH TIME ZONE"),
+        ("TIME", "") => format!("TIME{modifier} WITHOUT TIME ZONE"),
+        ("CHAR" | "CHARACTER" | "BPCHAR", "") if modifier.is_empty() => "CHARACTER(1)".into(),
+        _ => {
+            let name = match name {
+                "INT" | "INT4" => "INTEGER",
+                "INT2" => "SMALLINT",
+                "INT8" => "BIGINT",
+                "FLOAT" | "FLOAT8" => "DOUBLE PRECISION",
+                "FLOAT4" => "REAL",
+                "DECIMAL" => "NUMERIC",
+                "VARCHAR" => "CHARACTER VARYING",
+                "CHAR" | "BPCHAR" => "CHARACTER",
+                "BOOL" => "BOOLEAN",
+                other => other,
+            };
+            match rest {
+                "" => format!("{name}{modifier}"),
+                rest => format!("{name}{modifier} {rest}"),
+            }
+        }
+    };
+    canonical + &"[]".repeat(arrays)
+}
+
+/// The type family of a canonical column type, without modifiers or array brackets
+fn sql_type_family(canonical: &str) -> (String, bool) {
+    let is_array = canonical.ends_with("[]");
+    let base = canonical.trim_end_matches("[]");
+    let family = match (base.find('('), base.find(')')) {
+        (Some(open), Some(close)) if open < close => {
+            format!("{}{}", &base[..open], &base[close + 1..])
+        }
+        _ => base.to_string(),
+    };
+    (family, is_array)
+}
+
+/// Whether a column of the canonical type `sql_type` can hold the values of a field
+fn override_fits(field_type: &FieldType, rust_type: &str, sql_type: &str) -> bool {
+    let (family, is_array) = sql_type_family(sql_type);
+    let family = family.as_str();
+    let scalar = |families: &[&str]| !is_array && families.contains(&family);
+    let array = |families: &[&str]| is_array && families.contains(&family);
+    match field_type {
+        FieldType::Integer => scalar(&["SMALLINT", "INTEGER", "BIGINT", "NUMERIC"]),
+        FieldType::BigInt => scalar(&["BIGINT", "NUMERIC"]),
+        FieldType::Numeric => scalar(&["REAL", "DOUBLE PRECISION", "NUMERIC"]),
+        FieldType::Boolean => scalar(&["BOOLEAN"]),
+        FieldType::JsonB => scalar(&["JSONB", "JSON"]),
+        FieldType::Timestamp => {
+            scalar(&["TIMESTAMP WITH TIME ZONE", "TIMESTAMP WITHOUT TIME ZONE"])
+        }
+        FieldType::IntegerArray => array(&["SMALLINT", "INTEGER", "BIGINT", "NUMERIC"]),
+        FieldType::BigIntArray => array(&["BIGINT", "NUMERIC"]),
+        FieldType::NumericArray => array(&["REAL", "DOUBLE PRECISION", "NUMERIC"]),
+        FieldType::Vector(_) => scalar(&["VECTOR"]),
+        // Generic parameters, enums and other types the derive doesn't know are TEXT fields
+        FieldType::Text
+            if rust_type == std::any::type_name::<String>()
+                || rust_type == std::any::type_name::<Option<String>>() =>
+        {
+            scalar(&["TEXT", "CHARACTER VARYING", "CHARACTER", "CITEXT"])
+        }
+        FieldType::Text | FieldType::Custom(_) => true,
+    }
+}
+
+/// Check every `#[orso_column(type = "...")]` of `T` can hold the values of its field
+fn validate_type_overrides<T: Orso>(table_name: &str) -> Result<(), Error> {
+    let field_names = T::field_names();
+    let field_types = T::field_types();
+    for column_type in T::type_overrides() {
+        let Some(field_type) = field_names
+            .iter()
+            .position(|name| *name == column_type.column)
+            .and_then(|i| field_types.get(i))
+        else {
+            continue;
+        };
+        let sql_type = canonical_sql_type(column_type.sql_type);
+        if !override_fits(field_type, column_type.rust_type, &sql_type) {
+            return Err(Error::Schema {
+                message: format!(
+                    "Column type {} can't hold values of {}",
+                    column_type.sql_type, column_type.rust_type
+                ),
+                table: Some(table_name.to_string()),
+                column: Some(column_type.column.to_string()),
+            });
+        }
+    }
+    Ok(())
+}
+
+/// Report overridden columns with their full type, e.g. `NUMERIC(20,8)` rather than the
+/// `NUMERIC` of `information_schema`, so they compare equal to their override
+async fn apply_live_override_types(
+    db: &Database,
+    table_name: &str,
+    overrides: &[TypeOverride],
+    columns: &mut [ColumnInfo],
+) -> Result<(), Error> {
+    if overrides.is_empty() {
+        return Ok(());
+    }
+
+    let query = "
+        SELECT a.attname::text, format_type(a.atttypid, a.atttypmod)
+        FROM pg_attribute a
+        JOIN pg_class c ON c.oid = a.attrelid
+        JOIN pg_namespace n ON n.oid = c.relnamespace
+        WHERE n.nspname = current_schema() AND c.relname = $1
+        AND a.attnum > 0 AND NOT a.attisdropped
+    ";
+    let rows = db.query(query, &[&table_name]).await.map_err(|e| {
+        Error::migration(
+            format!("Failed to get column types: {}", e),
+            Some(table_name.to_string()),
+            Some("column_types".to_string()),
+        )
+    })?;
+
+    for row in rows {
+        let name: String = row.get(0);
+        if !overrides.iter().any(|o| o.column == name) {
+            continue;
+        }
+        if let Some(column) = columns.iter_mut().find(|c| c.name == name) {
+            column.sql_type = row.get::<_, String>(1).to_uppercase();
+        }
     }
+    Ok(())
 }
 
-async fn check_table_exists(db: &Database, table_name: &str) -> Result<bool, Error> {
-    let query = "SELECT table_name FROM information_schema.tables WHERE table_schema = 'public' AND table_name = $1";
+/// Check the values of overridden columns convert to their new type before migrating them
+async fn check_override_conversions(
+    db: &Database,
+    table_name: &str,
+    overrides: &[TypeOverride],
+    comparison: &SchemaComparison,
+) -> Result<(), Error> {
+    for column_type in overrides {
+        let find = |columns: &[ColumnInfo]| {
+            columns
+                .iter()
+                .find(|c| c.name == column_type.column)
+                .map(|c| c.sql_type.clone())
+        };
+        let (Some(current), Some(expected)) = (
+            find(&comparison.current_columns),
+            find(&comparison.expected_columns),
+        ) else {
+            continue;
+        };
+        if current == expected {
+            continue;
+        }
+
+        let conversion = generate_type_conversion(&current, &expected, column_type.column);
+        let sql = format!("SELECT count({conversion}) FROM \"{table_name}\"");
+        db.query(&sql, &[]).await.map_err(|e| Error::Schema {
+            message: format!(
+                "Existing values don't convert from {current} to {}: {e}",
+                column_type.sql_type
+            ),
+            table: Some(table_name.to_string()),
+            column: Some(column_type.column.to_string()),
+        })?;
+    }
+    Ok(())
+}
+
+pub(crate) async fn check_table_exists(db: &Database, table_name: &str) -> Result<bool, Error> {
+    let query = "SELECT table_name FROM information_schema.tables WHERE table_schema = current_schema() AND table_name = $1";
 
     let params: Vec<Box<dyn tokio_postgres::types::ToSql + Send + Sync>> =
         vec![Box::new(table_name.to_string())];
@@ -373,7 +1405,7 @@ async fn check_table_exists(db: &Database, table_name: &str) -> Result<bool, Err
     Ok(!rows.is_empty())
 }
 
-async fn get_current_table_schema(
+pub(crate) async fn get_current_table_schema(
     db: &Database,
     table_name: &str,
 ) -> Result<Vec<ColumnInfo>, Error> {
@@ -393,7 +1425,7 @@ async fn get_current_table_schema(
             ordinal_position,
             column_default
         FROM information_schema.columns
-        WHERE table_schema = 'public' AND table_name = $1
+        WHERE table_schema = current_schema() AND table_name = $1
         ORDER BY ordinal_position
     ";
 
@@ -430,6 +1462,7 @@ async fn get_current_table_schema(
             foreign_key_reference: None,    // Will be updated later from constraints
             has_default: column_default.is_some(),
             is_compressed: data_type.to_uppercase() == "BYTEA", // PostgreSQL: BYTEA columns are probably compressed
+            deferral: None, // Will be updated later from constraints
         };
 
         column_info_map.insert(name.clone(), column_info.clone());
@@ -439,16 +1472,18 @@ async fn get_current_table_schema(
     // Sort by position to maintain order
     columns.sort_by_key(|c| c.position);
 
-    // Get PostgreSQL constraint information (primary keys, unique constraints)
+    // Get PostgreSQL constraint information (primary keys, unique and foreign key constraints)
     let constraint_query = "
         SELECT
             kcu.column_name,
-            tc.constraint_type
+            tc.constraint_type,
+            tc.is_deferrable = 'YES',
+            tc.initially_deferred = 'YES'
         FROM information_schema.table_constraints tc
         JOIN information_schema.key_column_usage kcu
         ON tc.constraint_name = kcu.constraint_name
-        WHERE tc.table_schema = 'public' AND tc.table_name = $1
-        AND tc.constraint_type IN ('PRIMARY KEY', 'UNIQUE')
+        WHERE tc.table_schema = current_schema() AND tc.table_name = $1
+        AND tc.constraint_type IN ('PRIMARY KEY', 'UNIQUE', 'FOREIGN KEY')
     ";
 
     let constraint_params: Vec<Box<dyn tokio_postgres::types::ToSql + Send + Sync>> =
@@ -471,6 +1506,8 @@ async fn get_current_table_schema(
     for row in constraint_rows {
         let column_name: String = row.get(0);
         let constraint_type: String = row.get(1);
+        let is_deferrable: bool = row.get(2);
+        let initially_deferred: bool = row.get(3);
 
         if let Some(column_info) = column_info_map.get_mut(&column_name) {
             match constraint_type.as_str() {
@@ -481,6 +1518,9 @@ async fn get_current_table_schema(
                 "UNIQUE" => column_info.is_unique = true,
                 _ => {}
             }
+            if constraint_type != "PRIMARY KEY" && is_deferrable {
+                column_info.deferral = Some(initially_deferred);
+            }
         }
     }
 
@@ -495,7 +1535,7 @@ async fn get_current_table_schema(
         ON rc.constraint_name = kcu.constraint_name
         JOIN information_schema.constraint_column_usage ccu
         ON rc.unique_constraint_name = ccu.constraint_name
-        WHERE kcu.table_schema = 'public' AND kcu.table_name = $1
+        WHERE kcu.table_schema = current_schema() AND kcu.table_name = $1
     ";
 
     let fk_params: Vec<Box<dyn tokio_postgres::types::ToSql + Send + Sync>> =
@@ -528,12 +1568,24 @@ async fn get_current_table_schema(
             column.is_primary_key = updated_info.is_primary_key;
             column.is_unique = updated_info.is_unique;
             column.foreign_key_reference = updated_info.foreign_key_reference.clone();
+            column.deferral = updated_info.deferral;
         }
     }
 
     Ok(columns)
 }
 
+/// Whether the live unique or foreign key constraints of a column are deferred otherwise
+/// than the model declares
+///
+/// Columns without such constraints are left to the other checks: table copies don't
+/// carry foreign keys over, and the model doesn't say which columns have them.
+pub(crate) fn deferral_differs(current: &ColumnInfo, expected: &ColumnInfo) -> bool {
+    let constrained =
+        (current.is_unique && !current.is_primary_key) || current.foreign_key_reference.is_some();
+    constrained && current.deferral != expected.deferral
+}
+
 fn compare_schemas(current: &[ColumnInfo], expected: &[ColumnInfo]) -> SchemaComparison {
     let mut changes = Vec::new();
     let mut needs_migration = false;
@@ -600,6 +1652,13 @@ fn compare_schemas(current: &[ColumnInfo], expected: &[ColumnInfo]) -> SchemaCom
                     ));
                     needs_migration = true;
                 }
+                if deferral_differs(current_col, expected_col) {
+                    changes.push(format!(
+                        "Deferral mismatch for {}: {:?} vs {:?}",
+                        expected_col.name, current_col.deferral, expected_col.deferral
+                    ));
+                    needs_migration = true;
+                }
                 // Note: We're not checking foreign key references here as they require
                 // additional Orso trait methods that we haven't added yet
             }
@@ -630,6 +1689,7 @@ async fn perform_zero_loss_migration(
     db: &Database,
     table_name: &str,
     comparison: &SchemaComparison,
+    deferrable: &[(&str, bool)],
     config: &MigrationConfig,
 ) -> Result<MigrationResult, Error> {
     // Generate unique backup table name with timestamp hash
@@ -641,7 +1701,8 @@ async fn perform_zero_loss_migration(
 
     // Step 1: Create new table with correct schema
     let temp_table_name = format!("{}_temp_{}", table_name, timestamp);
-    let create_sql = generate_create_table_sql(&temp_table_name, &comparison.expected_columns);
+    let create_sql =
+        generate_create_table_sql(&temp_table_name, &comparison.expected_columns, deferrable);
 
     db.execute(&create_sql, &[]).await.map_err(|e| {
         Error::migration(
@@ -659,33 +1720,40 @@ async fn perform_zero_loss_migration(
         &comparison.expected_columns,
     );
 
-    let _rows_affected = db.execute(&copy_sql, &[]).await.map_err(|e| {
-        Error::migration(
+    // Outside a transaction, a failed step is undone by hand
+    let drop_temp = format!("DROP TABLE IF EXISTS {}", temp_table_name);
+    let restore = format!("ALTER TABLE {} RENAME TO {}", backup_name, table_name);
+
+    if let Err(e) = db.execute(&copy_sql, &[]).await {
+        undo_migration_steps(db, &[&drop_temp]).await;
+        return Err(Error::migration(
             format!("Failed to migrate data: {}", e),
             None,
             Some("migrate_data".to_string()),
-        )
-    })?;
+        ));
+    }
 
     // Step 3: Rename original table to backup
     let rename_to_backup = format!("ALTER TABLE {} RENAME TO {}", table_name, backup_name);
-    db.execute(&rename_to_backup, &[]).await.map_err(|e| {
-        Error::migration(
+    if let Err(e) = db.execute(&rename_to_backup, &[]).await {
+        undo_migration_steps(db, &[&drop_temp]).await;
+        return Err(Error::migration(
             format!("Failed to create backup: {}", e),
             None,
             Some("create_backup".to_string()),
-        )
-    })?;
+        ));
+    }
 
     // Step 4: Rename new table to original name
     let rename_to_original = format!("ALTER TABLE {} RENAME TO {}", temp_table_name, table_name);
-    db.execute(&rename_to_original, &[]).await.map_err(|e| {
-        Error::migration(
+    if let Err(e) = db.execute(&rename_to_original, &[]).await {
+        undo_migration_steps(db, &[&restore, &drop_temp]).await;
+        return Err(Error::migration(
             format!("Failed to rename new table: {}", e),
             None,
             Some("rename_table".to_string()),
-        )
-    })?;
+        ));
+    }
 
     // Step 5: Verify migration success
     let verification_sql = format!("SELECT COUNT(*) FROM {}", table_name);
@@ -716,7 +1784,25 @@ async fn perform_zero_loss_migration(
     })
 }
 
-fn generate_create_table_sql(table_name: &str, columns: &[ColumnInfo]) -> String {
+/// Undo the steps of a table copy that failed outside a transaction, which would otherwise
+/// leave a half-filled temp table or the original renamed away behind
+async fn undo_migration_steps(db: &Database, statements: &[&str]) {
+    // A transaction rolls the steps back by itself
+    if db.is_pinned() {
+        return;
+    }
+    for sql in statements {
+        if let Err(e) = db.execute(sql, &[]).await {
+            warn!(sql = %sql, error = %e, "Failed to undo migration step");
+        }
+    }
+}
+
+fn generate_create_table_sql(
+    table_name: &str,
+    columns: &[ColumnInfo],
+    deferrable: &[(&str, bool)],
+) -> String {
     let mut column_defs = Vec::new();
     let mut table_constraints = Vec::new();
 
@@ -731,7 +1817,12 @@ fn generate_create_table_sql(table_name: &str, columns: &[ColumnInfo]) -> String
         if column.is_unique {
             // For unique constraints, we add them as table-level constraints
             // to avoid issues with column-level unique constraints in some cases
-            table_constraints.push(format!("UNIQUE (\"{}\")", column.name));
+            let deferral = match deferrable.iter().find(|(name, _)| *name == column.name) {
+                Some((_, true)) => " DEFERRABLE INITIALLY DEFERRED",
+                Some((_, false)) => " DEFERRABLE",
+                None => "",
+            };
+            table_constraints.push(format!("UNIQUE (\"{}\"){}", column.name, deferral));
         }
 
         // Add primary key constraints
@@ -740,12 +1831,8 @@ fn generate_create_table_sql(table_name: &str, columns: &[ColumnInfo]) -> String
         }
 
         // Add default values for columns that need them
-        if column.has_default {
-            if column.is_primary_key && column.sql_type == "TEXT" {
-                def.push_str(" DEFAULT gen_random_uuid()");
-            } else if column.name == "created_at" || column.name == "updated_at" {
-                def.push_str(" DEFAULT NOW()");
-            }
+        if let Some(default) = default_sql(column) {
+            def.push_str(&format!(" DEFAULT {default}"));
         }
 
         column_defs.push(def);
@@ -761,8 +1848,25 @@ fn generate_create_table_sql(table_name: &str, columns: &[ColumnInfo]) -> String
     )
 }
 
+/// The default of a column created by migrations, if it has one
+pub(crate) fn default_sql(column: &ColumnInfo) -> Option<&'static str> {
+    if !column.has_default {
+        None
+    } else if column.is_primary_key && column.sql_type == "TEXT" {
+        Some("gen_random_uuid()")
+    } else if column.sql_type == "BIGINT" {
+        Some(crate::UNIX_MS_NOW)
+    } else {
+        Some("NOW()")
+    }
+}
+
 // Generate PostgreSQL conversion SQL for type changes
-fn generate_type_conversion(source_type: &str, target_type: &str, column_name: &str) -> String {
+pub(crate) fn generate_type_conversion(
+    source_type: &str,
+    target_type: &str,
+    column_name: &str,
+) -> String {
     match (source_type, target_type) {
         ("TEXT", "BIGINT[]") => {
             // Convert JSON array "[1,2,3,4]" to PostgreSQL BIGINT array
@@ -797,6 +1901,14 @@ fn generate_type_conversion(source_type: &str, target_type: &str, column_name: &
                 column_name, column_name
             )
         }
+        ("TEXT", "TIMESTAMP WITH TIME ZONE") => {
+            // `OrsoDateTime` columns used to hold RFC 3339 text
+            format!("NULLIF(\"{}\", '')::timestamptz", column_name)
+        }
+        ("TIMESTAMP WITHOUT TIME ZONE", "TIMESTAMP WITH TIME ZONE") => {
+            // Naive timestamps were written as UTC, whatever the session time zone is
+            format!("\"{}\" AT TIME ZONE 'UTC'", column_name)
+        }
         ("TEXT", "BYTEA") => {
             // Convert TEXT to BYTEA for compression migration
             format!("convert_to(\"{}\", 'UTF8')", column_name)
@@ -979,7 +2091,7 @@ async fn get_all_migration_tables(
     suffix: &str,
 ) -> Result<Vec<MigrationTableInfo>, Error> {
     let pattern = format!("{}_{}_", base_table, suffix);
-    let query = "SELECT table_name FROM information_schema.tables WHERE table_schema = 'public' AND table_name LIKE $1";
+    let query = "SELECT table_name FROM information_schema.tables WHERE table_schema = current_schema() AND table_name LIKE $1";
 
     let params: Vec<Box<dyn tokio_postgres::types::ToSql + Send + Sync>> =
         vec![Box::new(format!("{}%", pattern))];
@@ -1022,6 +2134,107 @@ impl std::fmt::Display for MigrationAction {
             MigrationAction::DataMigrated { from, to } => {
                 write!(f, "DataMigrated from {} to {}", from, to)
             }
+            MigrationAction::AlteredInPlace => write!(f, "AlteredInPlace"),
+            MigrationAction::Expanded { pending } => {
+                write!(f, "Expanded with {} changes pending", pending.len())
+            }
+            MigrationAction::IndexesCreated { indexes } => {
+                write!(f, "IndexesCreated {}", indexes.join(", "))
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Drift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Drift::MissingTable { table } => write!(f, "{}: table is missing", table),
+            Drift::MissingColumn { table, column } => {
+                write!(f, "{}.{}: column is missing", table, column)
+            }
+            Drift::ExtraColumn { table, column } => {
+                write!(f, "{}.{}: column is not in the model", table, column)
+            }
+            Drift::TypeMismatch {
+                table,
+                column,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{}.{}: expected {}, found {}",
+                table, column, expected, actual
+            ),
+            Drift::NullabilityMismatch {
+                table,
+                column,
+                expected,
+                ..
+            } => {
+                let expected = if *expected { "nullable" } else { "NOT NULL" };
+                write!(f, "{}.{}: expected {}", table, column, expected)
+            }
+            Drift::PrimaryKeyMismatch {
+                table,
+                column,
+                expected,
+                ..
+            } => {
+                let expected = if *expected { "" } else { "not " };
+                write!(
+                    f,
+                    "{}.{}: expected {}to be the primary key",
+                    table, column, expected
+                )
+            }
+            Drift::UniqueMismatch {
+                table,
+                column,
+                expected,
+                ..
+            } => {
+                let expected = if *expected { "" } else { "not " };
+                write!(f, "{}.{}: expected {}to be unique", table, column, expected)
+            }
+            Drift::DeferralMismatch {
+                table,
+                column,
+                expected,
+                ..
+            } => {
+                let expected = match expected {
+                    Some(true) => "deferrable, initially deferred",
+                    Some(false) => "deferrable",
+                    None => "not deferrable",
+                };
+                write!(f, "{}.{}: expected constraints {}", table, column, expected)
+            }
+            Drift::ExtraIndex { table, index } => {
+                write!(f, "{}: index {} is not in the model", table, index)
+            }
+            Drift::MissingIndex { table, index } => {
+                write!(f, "{}: index {} is missing", table, index)
+            }
+            Drift::IndexMethodMismatch {
+                table,
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{}: index {} expected {}, found {}",
+                table, index, expected, actual
+            ),
+            Drift::IndexDefinitionMismatch {
+                table,
+                index,
+                expected,
+                actual,
+            } => write!(
+                f,
+                "{}: index {} expected `{}`, found `{}`",
+                table, index, expected, actual
+            ),
         }
     }
 }
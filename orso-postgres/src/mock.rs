@@ -0,0 +1,191 @@
+//! In-memory database double
+//!
+//! Enabled with the `testing` feature. [`MockDatabase`] has the same `execute`/`query` surface
+//! as [`Database`](crate::Database) but talks to no server: it records every statement with
+//! its parameters and answers with canned results registered for a piece of SQL. Rows are
+//! column maps, the shape `Orso::from_map` reads:
+//!
+//! ```rust,ignore
+//! let mock = MockDatabase::new();
+//! mock.return_rows("FROM users", vec![MockDatabase::row(&user)?]);
+//! mock.return_affected("DELETE FROM users", 1);
+//!
+//! let rows = mock.query("SELECT * FROM users WHERE id = $1", &[&"u1"]).await?;
+//! assert_eq!(mock.statements()[0].params, vec!["\"u1\""]);
+//! ```
+//!
+//! It implements [`Executor`], so model methods can run against it too:
+//! `User::find_all(&mock).await?`.
+
+use crate::{Error, Executor, Orso, Result, Value};
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// A row returned by [`MockDatabase`]
+pub type MockRow = HashMap<String, Value>;
+
+/// A statement received by [`MockDatabase`], with parameters rendered with `Debug`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecordedStatement {
+    pub sql: String,
+    pub params: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+enum MockResponse {
+    Rows(Vec<MockRow>),
+    Affected(u64),
+    Error(String),
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    statements: Vec<RecordedStatement>,
+    /// Canned responses keyed by a SQL fragment; later entries win
+    responses: Vec<(String, MockResponse)>,
+}
+
+/// Records statements and returns canned results instead of querying PostgreSQL
+#[derive(Debug, Default)]
+pub struct MockDatabase {
+    state: Mutex<MockState>,
+}
+
+impl MockDatabase {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Turn a model into a row as it would be read back from the table
+    pub fn row<T: Orso>(model: &T) -> Result<MockRow> {
+        model.to_map()
+    }
+
+    /// Return `rows` for statements containing `sql`
+    pub fn return_rows(&self, sql: impl Into<String>, rows: Vec<MockRow>) -> &Self {
+        self.respond(sql.into(), MockResponse::Rows(rows))
+    }
+
+    /// Report `affected` rows for statements containing `sql`
+    pub fn return_affected(&self, sql: impl Into<String>, affected: u64) -> &Self {
+        self.respond(sql.into(), MockResponse::Affected(affected))
+    }
+
+    /// Fail statements containing `sql`
+    pub fn return_error(&self, sql: impl Into<String>, message: impl Into<String>) -> &Self {
+        self.respond(sql.into(), MockResponse::Error(message.into()))
+    }
+
+    /// Statements received so far, oldest first
+    pub fn statements(&self) -> Vec<RecordedStatement> {
+        self.lock().statements.clone()
+    }
+
+    /// Forget recorded statements, keeping canned responses
+    pub fn clear_statements(&self) {
+        self.lock().statements.clear();
+    }
+
+    pub async fn execute(
+        &self,
+        sql: &str,
+        params: &[&(dyn tokio_postgres::types::ToSql + Send + Sync)],
+    ) -> Result<u64> {
+        match self.record(sql, params) {
+            Some(MockResponse::Affected(affected)) => Ok(affected),
+            Some(MockResponse::Rows(rows)) => Ok(rows.len() as u64),
+            Some(MockResponse::Error(message)) => Err(Error::query_with_sql(message, sql, None)),
+            None => Ok(0),
+        }
+    }
+
+    pub async fn query(
+        &self,
+        sql: &str,
+        params: &[&(dyn tokio_postgres::types::ToSql + Send + Sync)],
+    ) -> Result<Vec<MockRow>> {
+        match self.record(sql, params) {
+            Some(MockResponse::Rows(rows)) => Ok(rows),
+            Some(MockResponse::Error(message)) => Err(Error::query_with_sql(message, sql, None)),
+            Some(MockResponse::Affected(_)) | None => Ok(Vec::new()),
+        }
+    }
+
+    pub async fn query_one(
+        &self,
+        sql: &str,
+        params: &[&(dyn tokio_postgres::types::ToSql + Send + Sync)],
+    ) -> Result<MockRow> {
+        let rows = self.query(sql, params).await?;
+        match <[MockRow; 1]>::try_from(rows) {
+            Ok([row]) => Ok(row),
+            Err(rows) => Err(Error::query_with_sql(
+                format!("query_one returned {} rows", rows.len()),
+                sql,
+                None,
+            )),
+        }
+    }
+
+    pub async fn query_opt(
+        &self,
+        sql: &str,
+        params: &[&(dyn tokio_postgres::types::ToSql + Send + Sync)],
+    ) -> Result<Option<MockRow>> {
+        let mut rows = self.query(sql, params).await?;
+        match rows.len() {
+            0 => Ok(None),
+            1 => Ok(rows.pop()),
+            n => Err(Error::query_with_sql(
+                format!("query_opt returned {n} rows"),
+                sql,
+                None,
+            )),
+        }
+    }
+
+    fn respond(&self, sql: String, response: MockResponse) -> &Self {
+        self.lock().responses.push((sql, response));
+        self
+    }
+
+    fn record(
+        &self,
+        sql: &str,
+        params: &[&(dyn tokio_postgres::types::ToSql + Send + Sync)],
+    ) -> Option<MockResponse> {
+        let mut state = self.lock();
+        state.statements.push(RecordedStatement {
+            sql: sql.to_string(),
+            params: params.iter().map(|p| format!("{p:?}")).collect(),
+        });
+        state
+            .responses
+            .iter()
+            .rev()
+            .find(|(fragment, _)| sql.contains(fragment.as_str()))
+            .map(|(_, response)| response.clone())
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Executor for MockDatabase {
+    async fn execute(
+        &self,
+        sql: &str,
+        params: &[&(dyn tokio_postgres::types::ToSql + Send + Sync)],
+    ) -> Result<u64> {
+        MockDatabase::execute(self, sql, params).await
+    }
+
+    async fn query_rows(
+        &self,
+        sql: &str,
+        params: &[&(dyn tokio_postgres::types::ToSql + Send + Sync)],
+    ) -> Result<Vec<MockRow>> {
+        MockDatabase::query(self, sql, params).await
+    }
+}
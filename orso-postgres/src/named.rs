@@ -0,0 +1,134 @@
+//! Named, pre-checked SQL statements
+//!
+//! [`NamedQueries`] holds the hand-written SQL of an application under names, each with the
+//! model its rows decode into. [`NamedQueries::validate`] prepares every statement on the
+//! server at startup, so a typo, a dropped column or a result missing a model field fails
+//! the deploy instead of the first request that runs it:
+//!
+//! ```rust,ignore
+//! let queries = NamedQueries::new().register::<Trader>(
+//!     "top_traders",
+//!     "SELECT * FROM traders WHERE volume > $1 ORDER BY volume DESC LIMIT $2",
+//! );
+//! queries.validate(&db).await?;
+//! let db = db.with_named_queries(Arc::new(queries));
+//!
+//! let top: Vec<Trader> = db.named::<Trader>("top_traders", &[&1_000_000.0, &10i64]).await?;
+//! ```
+
+use crate::{Database, Error, FieldError, Orso, Result};
+use std::collections::BTreeMap;
+use tracing::{debug, instrument};
+
+/// A statement registered with [`NamedQueries::register`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct NamedQuery {
+    pub name: String,
+    pub sql: String,
+    /// Type name of the model rows decode into
+    pub model: &'static str,
+    pub table: &'static str,
+    /// Columns the result must have for the model to decode
+    columns: Vec<&'static str>,
+}
+
+/// Registry of [`NamedQuery`]s, attached with
+/// [`Database::with_named_queries`](crate::Database::with_named_queries)
+#[derive(Debug, Clone, Default)]
+pub struct NamedQueries {
+    queries: BTreeMap<String, NamedQuery>,
+}
+
+impl NamedQueries {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register `sql` under `name`, returning rows of `T`; a later registration replaces it
+    pub fn register<T: Orso>(mut self, name: impl Into<String>, sql: impl Into<String>) -> Self {
+        let name = name.into();
+        let lazy = T::lazy_fields();
+        let query = NamedQuery {
+            name: name.clone(),
+            sql: sql.into(),
+            model: std::any::type_name::<T>(),
+            table: T::table_name(),
+            columns: T::columns()
+                .into_iter()
+                .filter(|column| !lazy.contains(column))
+                .collect(),
+        };
+        self.queries.insert(name, query);
+        self
+    }
+
+    pub fn get(&self, name: &str) -> Option<&NamedQuery> {
+        self.queries.get(name)
+    }
+
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.queries.keys().map(String::as_str)
+    }
+
+    /// The SQL of `name`, checking it was registered for `T`
+    pub(crate) fn sql_for<T: Orso>(&self, name: &str) -> Result<&str> {
+        let query = self.get(name).ok_or_else(|| {
+            Error::validation_field("No query registered under this name", name, None)
+        })?;
+        if query.model != std::any::type_name::<T>() {
+            return Err(Error::validation_field(
+                format!(
+                    "Query returns {}, not {}",
+                    query.model,
+                    std::any::type_name::<T>()
+                ),
+                name,
+                None,
+            ));
+        }
+        Ok(&query.sql)
+    }
+
+    /// Prepare every statement on `db` and check its result has the columns of its model
+    ///
+    /// Reports all invalid statements at once, by name.
+    #[instrument(name = "orso.named.validate", skip_all, fields(queries = self.queries.len()))]
+    pub async fn validate(&self, db: &Database) -> Result<()> {
+        let client = db.pool.get().await?;
+        let mut errors = Vec::new();
+        for query in self.queries.values() {
+            let statement = match client.prepare(&query.sql).await {
+                Ok(statement) => statement,
+                Err(e) => {
+                    let message = e
+                        .as_db_error()
+                        .map_or(e.to_string(), |e| e.message().into());
+                    errors.push(FieldError::new(&query.name, message));
+                    continue;
+                }
+            };
+            let returned: Vec<&str> = statement.columns().iter().map(|c| c.name()).collect();
+            let missing: Vec<&str> = query
+                .columns
+                .iter()
+                .copied()
+                .filter(|column| !returned.contains(column))
+                .collect();
+            if !missing.is_empty() {
+                errors.push(FieldError::new(
+                    &query.name,
+                    format!(
+                        "Result lacks columns of {}: {}",
+                        query.table,
+                        missing.join(", ")
+                    ),
+                ));
+            }
+        }
+        if !errors.is_empty() {
+            return Err(Error::validation_errors(errors));
+        }
+        debug!("Named queries are valid");
+        Ok(())
+    }
+}
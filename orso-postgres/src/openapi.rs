@@ -0,0 +1,123 @@
+//! OpenAPI schemas for models
+//!
+//! Enabled with the `openapi` feature. `#[derive(Orso)]` then also implements utoipa's
+//! `ToSchema` from the model's columns, and [`PaginatedResult`](crate::PaginatedResult) and
+//! [`Pagination`](crate::Pagination) get schemas of their own, so handlers can be documented
+//! without a second set of annotations:
+//!
+//! ```rust,ignore
+//! #[utoipa::path(get, path = "/users", responses((status = 200, body = PaginatedResult<User>)))]
+//! async fn list_users() { /* ... */ }
+//!
+//! #[derive(OpenApi)]
+//! #[openapi(paths(list_users), components(schemas(User, PaginatedResult<User>)))]
+//! struct ApiDoc;
+//! ```
+//!
+//! Nullable columns are optional properties, and the primary key and timestamp columns are
+//! read-only. Embedded structs are nested objects, as they are serialized.
+
+use crate::{FieldType, Orso};
+use utoipa::openapi::schema::{
+    ArrayBuilder, KnownFormat, ObjectBuilder, Schema, SchemaFormat, SchemaType, Type,
+};
+use utoipa::openapi::RefOr;
+
+/// Object schema of `T` built from its columns
+///
+/// Columns of the `embedded` fields are gathered into nested objects under the field's name.
+pub fn model_schema<T: Orso>(embedded: &[&str]) -> RefOr<Schema> {
+    let timestamps = [T::created_at_field(), T::updated_at_field()];
+    let read_only = [Some(T::primary_key_field()), timestamps[0], timestamps[1]];
+
+    let mut object = ObjectBuilder::new().schema_type(Type::Object);
+    let mut nested: Vec<(&str, ObjectBuilder)> = embedded
+        .iter()
+        .map(|field| (*field, ObjectBuilder::new().schema_type(Type::Object)))
+        .collect();
+
+    let columns = T::field_names()
+        .into_iter()
+        .zip(T::field_types())
+        .zip(T::field_nullable());
+    for ((column, field_type), nullable) in columns {
+        let embedded = nested.iter_mut().find_map(|(field, builder)| {
+            let name = column.strip_prefix(*field)?.strip_prefix('_')?;
+            Some((name, builder))
+        });
+        match embedded {
+            Some((name, builder)) => {
+                let schema = column_schema(&field_type, nullable, false);
+                let mut inner = std::mem::take(builder).property(name, schema);
+                if !nullable {
+                    inner = inner.required(name);
+                }
+                *builder = inner;
+            }
+            None => {
+                // Auto timestamp columns are timestamps whatever their Rust type
+                let field_type = if timestamps.contains(&Some(column)) {
+                    FieldType::Timestamp
+                } else {
+                    field_type
+                };
+                let schema =
+                    column_schema(&field_type, nullable, read_only.contains(&Some(column)));
+                object = object.property(column, schema);
+                if !nullable {
+                    object = object.required(column);
+                }
+            }
+        }
+    }
+
+    for (field, builder) in nested {
+        object = object.property(field, builder).required(field);
+    }
+    object.into()
+}
+
+fn column_schema(field_type: &FieldType, nullable: bool, read_only: bool) -> Schema {
+    let typed = |kind: Type| -> SchemaType {
+        if nullable {
+            SchemaType::from_iter([kind, Type::Null])
+        } else {
+            kind.into()
+        }
+    };
+    let scalar = |kind: Type, format: Option<KnownFormat>| {
+        ObjectBuilder::new()
+            .schema_type(typed(kind))
+            .format(format.map(SchemaFormat::KnownFormat))
+            .read_only(read_only.then_some(true))
+            .into()
+    };
+    let array = |kind: Type, format: KnownFormat, len: Option<usize>| {
+        let items = ObjectBuilder::new()
+            .schema_type(kind)
+            .format(Some(SchemaFormat::KnownFormat(format)));
+        ArrayBuilder::new()
+            .schema_type(typed(Type::Array))
+            .items(items)
+            .min_items(len)
+            .max_items(len)
+            .into()
+    };
+
+    match field_type {
+        FieldType::Text => scalar(Type::String, None),
+        FieldType::Integer => scalar(Type::Integer, Some(KnownFormat::Int32)),
+        FieldType::BigInt => scalar(Type::Integer, Some(KnownFormat::Int64)),
+        FieldType::Numeric => scalar(Type::Number, Some(KnownFormat::Double)),
+        FieldType::Boolean => scalar(Type::Boolean, None),
+        FieldType::Timestamp => scalar(Type::String, Some(KnownFormat::DateTime)),
+        // Custom types are described by their serde form, which is unknown here
+        FieldType::JsonB | FieldType::Custom(_) => ObjectBuilder::new()
+            .schema_type(SchemaType::AnyValue)
+            .into(),
+        FieldType::IntegerArray => array(Type::Integer, KnownFormat::Int32, None),
+        FieldType::BigIntArray => array(Type::Integer, KnownFormat::Int64, None),
+        FieldType::NumericArray => array(Type::Number, KnownFormat::Double, None),
+        FieldType::Vector(dim) => array(Type::Number, KnownFormat::Float, Some(*dim as usize)),
+    }
+}
@@ -1,39 +1,210 @@
 use crate::{
-    Aggregate, Database, Error, FilterOperator, PaginatedResult, Pagination, QueryBuilder, Result,
-    SearchFilter, Sort, SortOrder,
+    Aggregate, CursorPaginatedResult, CursorPagination, Error, Executor, FilterOperator,
+    PaginatedResult, Pagination, QueryBuilder, Result, SearchFilter, Sort, SortOrder,
+    UpsertStrategy, WriteResult, MAX_BIND_PARAMS,
 };
-use std::collections::HashMap;
-use tracing::{debug, info, trace, warn};
+use std::collections::{HashMap, HashSet};
+use tracing::{debug, info, instrument, trace, warn};
+
+/// Acknowledges that [`CrudOperations::delete_all`] removes every row of the table
+#[derive(Debug, Clone, Copy)]
+pub struct ConfirmDeleteAll;
+
+/// Acknowledges that [`CrudOperations::truncate`] empties the table, with its options
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConfirmTruncate {
+    /// Also truncate tables with foreign keys referencing this one
+    pub cascade: bool,
+    /// Reset sequences owned by the table's columns
+    pub restart_identity: bool,
+}
+
+/// `RETURNING` list reading back the primary key as text, whatever its type
+fn returning_key(pk_field: &str) -> String {
+    format!("{pk_field}::text AS {pk_field}")
+}
+
+/// Column `find_by_ids` reads the primary key back as text in, to match rows to the IDs
+const FOUND_KEY: &str = "orso_found_key";
+
+/// The type of the primary key column, as declared on the model
+pub(crate) fn key_type<T: crate::Orso>() -> Option<crate::FieldType> {
+    let pk_field = T::primary_key_field();
+    T::field_names()
+        .iter()
+        .position(|name| *name == pk_field)
+        .and_then(|pos| T::field_types().get(pos).cloned())
+}
+
+fn parse_key<T: crate::Orso, K: std::str::FromStr>(id: &str) -> Result<K> {
+    parse_key_of(T::primary_key_field(), id)
+}
+
+fn parse_key_of<K: std::str::FromStr>(pk_field: &str, id: &str) -> Result<K> {
+    id.parse().map_err(|_| {
+        Error::validation_field(
+            "Primary key doesn't fit the key column",
+            pk_field,
+            Some(id.to_string()),
+        )
+    })
+}
+
+/// A primary key given as text, bound with the type of the key column so integer keys
+/// compare against their `INTEGER` or `BIGINT` column
+pub(crate) fn key_param<T: crate::Orso>(
+    id: &str,
+) -> Result<Box<dyn tokio_postgres::types::ToSql + Send + Sync>> {
+    typed_key_param(key_type::<T>().as_ref(), T::primary_key_field(), id)
+}
+
+/// Like [`key_param`], for a key column whose type is known without its model
+pub(crate) fn typed_key_param(
+    key_type: Option<&crate::FieldType>,
+    pk_field: &str,
+    id: &str,
+) -> Result<Box<dyn tokio_postgres::types::ToSql + Send + Sync>> {
+    Ok(match key_type {
+        Some(crate::FieldType::Integer) => Box::new(parse_key_of::<i32>(pk_field, id)?),
+        Some(crate::FieldType::BigInt) => Box::new(parse_key_of::<i64>(pk_field, id)?),
+        _ => Box::new(id.to_string()),
+    })
+}
+
+/// SQL type of the primary key column of `T`, for casting keys where none is inferred
+pub(crate) fn key_sql_type<T: crate::Orso>() -> &'static str {
+    match key_type::<T>() {
+        Some(crate::FieldType::Integer) => "INTEGER",
+        Some(crate::FieldType::BigInt) => "BIGINT",
+        _ => "TEXT",
+    }
+}
+
+/// Like [`key_param`], for an array of keys compared with `= ANY($n)`
+pub(crate) fn key_array_param<T: crate::Orso>(
+    ids: &[&str],
+) -> Result<Box<dyn tokio_postgres::types::ToSql + Send + Sync>> {
+    Ok(match key_type::<T>() {
+        Some(crate::FieldType::Integer) => Box::new(
+            ids.iter()
+                .map(|id| parse_key::<T, i32>(id))
+                .collect::<Result<Vec<_>>>()?,
+        ),
+        Some(crate::FieldType::BigInt) => Box::new(
+            ids.iter()
+                .map(|id| parse_key::<T, i64>(id))
+                .collect::<Result<Vec<_>>>()?,
+        ),
+        _ => Box::new(ids.iter().map(|id| id.to_string()).collect::<Vec<_>>()),
+    })
+}
+
+/// The primary keys in rows read back with [`returning_key`]
+fn returned_ids(rows: Vec<crate::RowMap>, pk_field: &str) -> Result<Vec<String>> {
+    rows.into_iter()
+        .map(|mut row| match row.remove(pk_field) {
+            Some(crate::Value::Text(id)) => Ok(id),
+            other => Err(Error::query(format!(
+                "Expected the primary key {pk_field} as text in RETURNING, got {other:?}"
+            ))),
+        })
+        .collect()
+}
+
+/// Consecutive records with the same columns, grouped into statements within [`MAX_BIND_PARAMS`]
+///
+/// Records sharing a `key` go in different statements, and those without one go alone.
+fn chunk_rows<K: Eq + std::hash::Hash>(
+    maps: &[&crate::RowMap],
+    key: impl Fn(usize) -> Option<K>,
+) -> Vec<(Vec<String>, Vec<usize>)> {
+    let mut chunks: Vec<(Vec<String>, Vec<usize>)> = Vec::new();
+    let mut keys = std::collections::HashSet::new();
+    let mut open = false;
+    for (index, map) in maps.iter().enumerate() {
+        let mut columns: Vec<String> = map.keys().cloned().collect();
+        columns.sort();
+        let key = key(index);
+        let fits =
+            open && chunks.last().is_some_and(|(current, rows)| {
+                *current == columns && (rows.len() + 1) * columns.len() <= MAX_BIND_PARAMS
+            }) && key.as_ref().is_some_and(|key| !keys.contains(key));
+        if !fits {
+            chunks.push((columns, Vec::new()));
+            keys.clear();
+        }
+        if let Some((_, rows)) = chunks.last_mut() {
+            rows.push(index);
+        }
+        open = key.is_some();
+        keys.extend(key);
+    }
+    chunks
+}
+
+/// `($1, $2), ($3, $4)` for `rows`, with their values in `columns` order
+fn multi_row_values(
+    columns: &[String],
+    rows: &[&crate::RowMap],
+) -> (
+    String,
+    Vec<Box<dyn tokio_postgres::types::ToSql + Send + Sync>>,
+) {
+    let mut params = Vec::with_capacity(columns.len() * rows.len());
+    let tuples: Vec<String> = rows
+        .iter()
+        .map(|row| {
+            let placeholders: Vec<String> = columns
+                .iter()
+                .map(|column| {
+                    params.push(row[column].to_postgres_param());
+                    format!("${}", params.len())
+                })
+                .collect();
+            format!("({})", placeholders.join(", "))
+        })
+        .collect();
+    (tuples.join(", "), params)
+}
 
 /// CRUD operations for database models
 pub struct CrudOperations;
 
 impl CrudOperations {
     /// Insert a new record in the database
-    pub async fn insert<T>(model: &T, db: &Database) -> Result<()>
+    pub async fn insert<T>(model: &T, db: &impl Executor) -> Result<WriteResult>
     where
         T: crate::Orso,
     {
         Self::insert_with_table(model, db, T::table_name()).await
     }
     /// Insert a new record in the database
-    pub async fn insert_with_table<T>(model: &T, db: &Database, table_name: &str) -> Result<()>
+    #[instrument(name = "orso.insert", skip_all, fields(operation = "insert", table = %table_name))]
+    pub async fn insert_with_table<T>(
+        model: &T,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<WriteResult>
     where
         T: crate::Orso,
     {
-        let map = model.to_map()?;
+        let mut model = model.clone();
+        model.before_insert(db).await?;
+        model.validate()?;
+
+        let mut map = crate::encryption::encode_record(db, &model)?;
+        crate::context::apply_actor::<T>(&mut map, true);
         let columns: Vec<String> = map.keys().cloned().collect();
         let placeholders: Vec<String> = (1..=columns.len()).map(|i| format!("${}", i)).collect();
 
         let sql = format!(
-            "INSERT INTO {} ({}) VALUES ({})",
+            "INSERT INTO {} ({}) VALUES ({}) RETURNING {}",
             table_name,
             columns.join(", "),
-            placeholders.join(", ")
+            placeholders.join(", "),
+            returning_key(T::primary_key_field())
         );
 
-        debug!(sql = %sql, "Executing SQL");
-
         let params: Vec<Box<dyn tokio_postgres::types::ToSql + Send + Sync>> = map
             .values()
             .map(|v| v.to_postgres_param())
@@ -42,31 +213,49 @@ impl CrudOperations {
         let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
             params.iter().map(|p| p.as_ref()).collect();
 
-        db.execute(&sql, &param_refs).await?;
+        let rows = db.query_rows(&sql, &param_refs).await?;
+        let rows_affected = rows.len() as u64;
+        let returned_ids = returned_ids(rows, T::primary_key_field())?;
+
+        // The hook sees the key the database generated
+        if let Some(id) = returned_ids.first() {
+            model.set_primary_key(id.clone());
+        }
+        model.after_insert(db).await?;
 
         debug!(table = table_name, "Successfully created record");
-        Ok(())
+        Ok(WriteResult {
+            rows_affected,
+            returned_ids,
+        })
     }
 
     /// Insert or update a record based on whether it has a primary key
-    pub async fn insert_or_update<T>(model: &T, db: &Database) -> Result<()>
+    pub async fn insert_or_update<T>(model: &T, db: &impl Executor) -> Result<WriteResult>
     where
         T: crate::Orso,
     {
         Self::insert_or_update_with_table(model, db, T::table_name()).await
     }
 
+    #[instrument(
+        name = "orso.insert_or_update",
+        skip_all,
+        fields(operation = "insert_or_update", table = %table_name)
+    )]
     pub async fn insert_or_update_with_table<T>(
         model: &T,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
-    ) -> Result<()>
+    ) -> Result<WriteResult>
     where
         T: crate::Orso,
     {
         if let Some(id) = model.get_primary_key() {
-            // Check if record exists
-            match Self::find_by_id_with_table::<T>(&id, db, table_name).await? {
+            // Check if record exists, including rows hidden by the default scope
+            match crate::scope::unscoped(Self::find_by_id_with_table::<T>(&id, db, table_name))
+                .await?
+            {
                 Some(_) => {
                     // Record exists, update it
                     Self::update_with_table(model, db, table_name).await
@@ -88,14 +277,23 @@ impl CrudOperations {
     }
 
     /// Insert or update a record based on unique constraints
-    pub async fn upsert<T>(model: &T, db: &Database) -> Result<()>
+    ///
+    /// A model without a primary key updates the row its unique columns match under that row's
+    /// key. A model carrying a key fails with [`Error::Validation`] when the matched row has
+    /// another one, rather than updating a record other than the one it names.
+    pub async fn upsert<T>(model: &T, db: &impl Executor) -> Result<WriteResult>
     where
         T: crate::Orso,
     {
         Self::upsert_with_table(model, db, T::table_name()).await
     }
 
-    pub async fn upsert_with_table<T>(model: &T, db: &Database, table_name: &str) -> Result<()>
+    #[instrument(name = "orso.upsert", skip_all, fields(operation = "upsert", table = %table_name))]
+    pub async fn upsert_with_table<T>(
+        model: &T,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<WriteResult>
     where
         T: crate::Orso,
     {
@@ -104,15 +302,21 @@ impl CrudOperations {
             return Err(Error::validation("No unique columns defined with orso_column(unique) for upsert"));
         }
 
-        let map = model.to_map()?;
+        let map = crate::encryption::encode_record(db, model)?;
 
         // Build WHERE clause for unique columns
         let mut where_conditions = Vec::new();
         let mut where_params: Vec<Box<dyn tokio_postgres::types::ToSql + Send + Sync>> = Vec::new();
 
-        for (param_index, column) in unique_columns.iter().enumerate() {
+        let case_insensitive = T::case_insensitive_unique_fields();
+        for column in &unique_columns {
             if let Some(value) = map.get(*column) {
-                where_conditions.push(format!("{column} = ${}", param_index + 1));
+                let param = where_params.len() + 1;
+                if case_insensitive.contains(column) {
+                    where_conditions.push(format!("lower({column}) = lower(${param})"));
+                } else {
+                    where_conditions.push(format!("{column} = ${param}"));
+                }
                 where_params.push(value.to_postgres_param());
             }
         }
@@ -122,24 +326,49 @@ impl CrudOperations {
         }
 
         let where_clause = where_conditions.join(" AND ");
+        let pk = T::primary_key_field();
+        // The key is read as text, the form `set_primary_key` parses whatever its column type
         let sql = format!(
-            "SELECT * FROM {} WHERE {} LIMIT 1",
+            "SELECT {pk}::text AS {pk} FROM {} WHERE {} LIMIT 1",
             table_name, where_clause
         );
 
         info!(table = table_name, "Checking for existing record");
-        debug!(sql = %sql, "Executing upsert query");
 
         let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
             where_params.iter().map(|p| p.as_ref()).collect();
 
-        let rows = db.query(&sql, &param_refs).await?;
+        let rows = db.query_rows(&sql, &param_refs).await?;
 
-        if !rows.is_empty() {
+        if let Some(row) = rows.first() {
             // Record exists, update it
-            let _row_map = T::row_to_map(&rows[0])?;
             info!(table = table_name, "Found existing record, updating");
-            Self::update_with_table(model, db, table_name).await
+            let Some(crate::Value::Text(id)) = row.get(pk) else {
+                return Err(Error::validation(
+                    "Existing record has no primary key to update by",
+                ));
+            };
+            if let Some(key) = model.get_primary_key() {
+                if key != *id {
+                    return Err(Error::validation_field(
+                        "Upsert matched a record with another primary key",
+                        pk,
+                        Some(key),
+                    ));
+                }
+                return Self::update_with_table(model, db, table_name).await;
+            }
+            // A model matched by its unique columns alone takes the key of the stored row
+            let mut model = model.clone();
+            model.set_primary_key(id.clone());
+            if model.get_primary_key().as_deref() != Some(id.as_str()) {
+                return Err(Error::validation_field(
+                    "Existing record's primary key doesn't fit the model",
+                    pk,
+                    Some(id.clone()),
+                ));
+            }
+            Self::update_with_table(&model, db, table_name).await
         } else {
             // Record doesn't exist, insert it
             info!(
@@ -151,89 +380,192 @@ impl CrudOperations {
     }
 
     /// Insert multiple records using Turso batch operations for optimal performance
-    pub async fn batch_create<T>(models: &[T], db: &Database) -> Result<()>
+    pub async fn batch_create<T>(models: &[T], db: &impl Executor) -> Result<WriteResult>
     where
         T: crate::Orso,
     {
         Self::batch_insert_with_table(models, db, T::table_name()).await
     }
 
+    #[instrument(
+        name = "orso.batch_insert",
+        skip_all,
+        fields(operation = "batch_insert", table = %table_name)
+    )]
     pub async fn batch_insert_with_table<T>(
         models: &[T],
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
-    ) -> Result<()>
+    ) -> Result<WriteResult>
     where
         T: crate::Orso,
     {
-        if models.is_empty() {
-            return Ok(());
-        }
+        Self::insert_each(models, db, table_name, false).await
+    }
 
-        // Use proper parameterized queries instead of building SQL strings
+    /// Insert multiple records, skipping those conflicting with existing rows
+    ///
+    /// The result counts and names the records inserted; hooks after insert only run for those.
+    pub async fn batch_create_ignore_conflicts<T>(
+        models: &[T],
+        db: &impl Executor,
+    ) -> Result<WriteResult>
+    where
+        T: crate::Orso,
+    {
+        Self::batch_insert_ignore_conflicts_with_table(models, db, T::table_name()).await
+    }
+
+    #[instrument(
+        name = "orso.batch_insert",
+        skip_all,
+        fields(operation = "batch_insert_ignore_conflicts", table = %table_name)
+    )]
+    pub async fn batch_insert_ignore_conflicts_with_table<T>(
+        models: &[T],
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<WriteResult>
+    where
+        T: crate::Orso,
+    {
+        let inserted = Self::insert_each(models, db, table_name, true).await?;
+        debug!(
+            table = table_name,
+            inserted = inserted.rows_affected,
+            skipped = models.len() as u64 - inserted.rows_affected,
+            "Inserted records ignoring conflicts"
+        );
+        Ok(inserted)
+    }
+
+    async fn insert_each<T>(
+        models: &[T],
+        db: &impl Executor,
+        table_name: &str,
+        ignore_conflicts: bool,
+    ) -> Result<WriteResult>
+    where
+        T: crate::Orso,
+    {
+        let mut prepared = Vec::with_capacity(models.len());
         for model in models {
-            let map = model.to_map()?;
-            let columns: Vec<String> = map.keys().cloned().collect();
-            let placeholders: Vec<String> =
-                (1..=columns.len()).map(|i| format!("${}", i)).collect();
-
-            let params: Vec<Box<dyn tokio_postgres::types::ToSql + Send + Sync>> = map
-                .values()
-                .map(|v| v.to_postgres_param())
-                .collect();
+            let mut model = model.clone();
+            model.before_insert(db).await?;
+            model.validate()?;
 
+            let mut map = crate::encryption::encode_record(db, &model)?;
+            crate::context::apply_actor::<T>(&mut map, true);
+            prepared.push((model, map));
+        }
+
+        // Skipped records are told apart by primary key, so keyless ones go alone
+        let maps: Vec<&crate::RowMap> = prepared.iter().map(|(_, map)| map).collect();
+        let chunks = chunk_rows(&maps, |index| match ignore_conflicts {
+            true => prepared[index].0.get_primary_key(),
+            false => Some(index.to_string()),
+        });
+
+        let mut result = WriteResult::default();
+        for (columns, chunk) in chunks {
+            let rows: Vec<&crate::RowMap> = chunk.iter().map(|&i| maps[i]).collect();
+            let (values, params) = multi_row_values(&columns, &rows);
             let sql = format!(
-                "INSERT INTO {} ({}) VALUES ({})",
+                "INSERT INTO {} ({}) VALUES {}{} RETURNING {}",
                 table_name,
                 columns.join(", "),
-                placeholders.join(", ")
+                values,
+                if ignore_conflicts {
+                    " ON CONFLICT DO NOTHING"
+                } else {
+                    ""
+                },
+                returning_key(T::primary_key_field())
             );
 
             let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
                 params.iter().map(|p| p.as_ref()).collect();
 
-            db.execute(&sql, &param_refs).await?;
+            let returned = db.query_rows(&sql, &param_refs).await?;
+            let inserted_all = returned.len() == chunk.len();
+            result.rows_affected += returned.len() as u64;
+            let ids = returned_ids(returned, T::primary_key_field())?;
+            for &i in &chunk {
+                let model = &prepared[i].0;
+                let inserted =
+                    inserted_all || model.get_primary_key().is_some_and(|id| ids.contains(&id));
+                if inserted {
+                    model.after_insert(db).await?;
+                }
+            }
+            result.returned_ids.extend(ids);
         }
-        Ok(())
+        Ok(result)
     }
 
     /// Find a record by its primary key
-    pub async fn find_by_id<T>(id: &str, db: &Database) -> Result<Option<T>>
+    pub async fn find_by_id<T>(id: &str, db: &impl Executor) -> Result<Option<T>>
     where
         T: crate::Orso,
     {
         Self::find_by_id_with_table(id, db, T::table_name()).await
     }
 
+    #[instrument(
+        name = "orso.find_by_id",
+        skip_all,
+        fields(operation = "find_by_id", table = %table_name)
+    )]
     pub async fn find_by_id_with_table<T>(
         id: &str,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<Option<T>>
     where
         T: crate::Orso,
     {
-        let sql = format!(
-            "SELECT * FROM {} WHERE {} = $1 LIMIT 1",
+        // Records already in the session are not queried again, unless a scope applies
+        let scope = crate::scope::active_scope::<T>();
+        let identity_map = db.identity_map().filter(|_| scope.is_none());
+        if let Some(map) = identity_map.and_then(|cache| cache.get(table_name, id)) {
+            debug!(table =table_name, id = %id, "Found record in session");
+            return Ok(Some(crate::decode::decode_record(db, map)?));
+        }
+
+        let mut sql = format!(
+            "SELECT {} FROM {} WHERE {} = $1",
+            crate::lazy::select_columns::<T>().join(", "),
             table_name,
             T::primary_key_field() // Use dynamic primary key field name
         );
+        let mut params: Vec<Box<dyn tokio_postgres::types::ToSql + Send + Sync>> =
+            vec![key_param::<T>(id)?];
+        if let Some(scope) = &scope {
+            let mut param_counter = 2;
+            let (scope_sql, scope_params) =
+                crate::filters::FilterOperations::build_filter_operator_with_counter(
+                    scope,
+                    &mut param_counter,
+                )?;
+            sql.push_str(&format!(" AND ({scope_sql})"));
+            params.extend(scope_params);
+        }
+        sql.push_str(" LIMIT 1");
 
         debug!(table =table_name, id = %id, "Finding record by ID");
-        debug!(sql = %sql, "Executing find query");
-
-        let params: Vec<Box<dyn tokio_postgres::types::ToSql + Send + Sync>> =
-            vec![Box::new(id.to_string())];
 
         let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
             params.iter().map(|p| p.as_ref()).collect();
 
-        let rows = db.query(&sql, &param_refs).await?;
+        let rows = db.query_rows(&sql, &param_refs).await?;
 
-        if let Some(row) = rows.get(0) {
-            let map = T::row_to_map(&row)?;
+        if let Some(mut map) = rows.into_iter().next() {
+            crate::lazy::attach_sources::<T>(&mut map, table_name);
             debug!(table =table_name, id = %id, "Found record");
-            Ok(Some(T::from_map(map)?))
+            if let Some(cache) = identity_map {
+                cache.insert(table_name, id, map.clone());
+            }
+            Ok(Some(crate::decode::decode_record(db, map)?))
         } else {
             debug!(table =table_name, id = %id, "No record found");
             Ok(None)
@@ -241,16 +573,21 @@ impl CrudOperations {
     }
 
     /// Find a single record by a specific condition
-    pub async fn find_one<T>(filter: FilterOperator, db: &Database) -> Result<Option<T>>
+    pub async fn find_one<T>(filter: FilterOperator, db: &impl Executor) -> Result<Option<T>>
     where
         T: crate::Orso,
     {
         Self::find_one_with_table(filter, db, T::table_name()).await
     }
 
+    #[instrument(
+        name = "orso.find_one",
+        skip_all,
+        fields(operation = "find_one", table = %table_name)
+    )]
     pub async fn find_one_with_table<T>(
         filter: FilterOperator,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<Option<T>>
     where
@@ -263,14 +600,19 @@ impl CrudOperations {
     }
 
     /// Find all records
-    pub async fn find_all<T>(db: &Database) -> Result<Vec<T>>
+    pub async fn find_all<T>(db: &impl Executor) -> Result<Vec<T>>
     where
         T: crate::Orso,
     {
         Self::find_all_with_table(db, T::table_name()).await
     }
 
-    pub async fn find_all_with_table<T>(db: &Database, table_name: &str) -> Result<Vec<T>>
+    #[instrument(
+        name = "orso.find_all",
+        skip_all,
+        fields(operation = "find_all", table = %table_name)
+    )]
+    pub async fn find_all_with_table<T>(db: &impl Executor, table_name: &str) -> Result<Vec<T>>
     where
         T: crate::Orso,
     {
@@ -279,16 +621,21 @@ impl CrudOperations {
     }
 
     /// Find records with a filter
-    pub async fn find_where<T>(filter: FilterOperator, db: &Database) -> Result<Vec<T>>
+    pub async fn find_where<T>(filter: FilterOperator, db: &impl Executor) -> Result<Vec<T>>
     where
         T: crate::Orso,
     {
         Self::find_where_with_table(filter, db, T::table_name()).await
     }
 
+    #[instrument(
+        name = "orso.find_where",
+        skip_all,
+        fields(operation = "find_where", table = %table_name)
+    )]
     pub async fn find_where_with_table<T>(
         filter: FilterOperator,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<Vec<T>>
     where
@@ -298,14 +645,22 @@ impl CrudOperations {
         builder.execute::<T>(db).await
     }
 
-    pub async fn find_latest<T>(db: &Database) -> Result<Option<T>>
+    pub async fn find_latest<T>(db: &impl Executor) -> Result<Option<T>>
     where
         T: crate::Orso,
     {
         Self::find_latest_with_table(db, T::table_name()).await
     }
 
-    pub async fn find_latest_with_table<T>(db: &Database, table_name: &str) -> Result<Option<T>>
+    #[instrument(
+        name = "orso.find_latest",
+        skip_all,
+        fields(operation = "find_latest", table = %table_name)
+    )]
+    pub async fn find_latest_with_table<T>(
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<Option<T>>
     where
         T: crate::Orso,
     {
@@ -318,16 +673,24 @@ impl CrudOperations {
     }
 
     /// Find latest record matching filter
-    pub async fn find_latest_filter<T>(filter: FilterOperator, db: &Database) -> Result<Option<T>>
+    pub async fn find_latest_filter<T>(
+        filter: FilterOperator,
+        db: &impl Executor,
+    ) -> Result<Option<T>>
     where
         T: crate::Orso,
     {
         Self::find_latest_filter_with_table(filter, db, T::table_name()).await
     }
 
+    #[instrument(
+        name = "orso.find_latest_filter",
+        skip_all,
+        fields(operation = "find_latest_filter", table = %table_name)
+    )]
     pub async fn find_latest_filter_with_table<T>(
         filter: FilterOperator,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<Option<T>>
     where
@@ -344,16 +707,24 @@ impl CrudOperations {
     }
 
     /// Find first record matching filter (oldest)
-    pub async fn find_first_filter<T>(filter: FilterOperator, db: &Database) -> Result<Option<T>>
+    pub async fn find_first_filter<T>(
+        filter: FilterOperator,
+        db: &impl Executor,
+    ) -> Result<Option<T>>
     where
         T: crate::Orso,
     {
         Self::find_first_filter_with_table(filter, db, T::table_name()).await
     }
 
+    #[instrument(
+        name = "orso.find_first_filter",
+        skip_all,
+        fields(operation = "find_first_filter", table = %table_name)
+    )]
     pub async fn find_first_filter_with_table<T>(
         filter: FilterOperator,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<Option<T>>
     where
@@ -369,56 +740,308 @@ impl CrudOperations {
         Ok(results.into_iter().next())
     }
 
+    /// Find the first record by `sort`, or by `created_at` (the primary key without one)
+    pub async fn first<T>(sort: Option<Sort>, db: &impl Executor) -> Result<Option<T>>
+    where
+        T: crate::Orso,
+    {
+        Self::first_with_table(sort, db, T::table_name()).await
+    }
+
+    #[instrument(
+        name = "orso.first",
+        skip_all,
+        fields(operation = "first", table = %table_name)
+    )]
+    pub async fn first_with_table<T>(
+        sort: Option<Sort>,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<Option<T>>
+    where
+        T: crate::Orso,
+    {
+        let builder = QueryBuilder::new(table_name)
+            .order_by_multiple(Self::ordering::<T>(sort, false))
+            .limit(1);
+        let results = builder.execute::<T>(db).await?;
+        Ok(results.into_iter().next())
+    }
+
+    /// Find the last record by `sort`, or by `created_at` (the primary key without one)
+    pub async fn last<T>(sort: Option<Sort>, db: &impl Executor) -> Result<Option<T>>
+    where
+        T: crate::Orso,
+    {
+        Self::last_with_table(sort, db, T::table_name()).await
+    }
+
+    #[instrument(
+        name = "orso.last",
+        skip_all,
+        fields(operation = "last", table = %table_name)
+    )]
+    pub async fn last_with_table<T>(
+        sort: Option<Sort>,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<Option<T>>
+    where
+        T: crate::Orso,
+    {
+        let builder = QueryBuilder::new(table_name)
+            .order_by_multiple(Self::ordering::<T>(sort, true))
+            .limit(1);
+        let results = builder.execute::<T>(db).await?;
+        Ok(results.into_iter().next())
+    }
+
+    /// `sort`, or the default ordering, with the primary key breaking ties
+    fn ordering<T: crate::Orso>(sort: Option<Sort>, reverse: bool) -> Vec<Sort> {
+        let sort = sort.unwrap_or_else(|| {
+            Sort::asc(T::created_at_field().unwrap_or_else(|| T::primary_key_field()))
+        });
+        let order = match (&sort.order, reverse) {
+            (SortOrder::Asc, false) | (SortOrder::Desc, true) => SortOrder::Asc,
+            (SortOrder::Desc, false) | (SortOrder::Asc, true) => SortOrder::Desc,
+        };
+        let mut sorts = vec![Sort::new(&sort.column, order)];
+        if sort.column != T::primary_key_field() {
+            sorts.push(Sort::new(T::primary_key_field(), order));
+        }
+        sorts
+    }
+
+    /// Find the record matching `filter`, failing if more than one does
+    pub async fn find_one_where<T>(filter: FilterOperator, db: &impl Executor) -> Result<Option<T>>
+    where
+        T: crate::Orso,
+    {
+        Self::find_one_where_with_table(filter, db, T::table_name()).await
+    }
+
+    #[instrument(
+        name = "orso.find_one_where",
+        skip_all,
+        fields(operation = "find_one_where", table = %table_name)
+    )]
+    pub async fn find_one_where_with_table<T>(
+        filter: FilterOperator,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<Option<T>>
+    where
+        T: crate::Orso,
+    {
+        let builder = QueryBuilder::new(table_name)._where(filter).limit(2);
+        let mut results = builder.execute::<T>(db).await?;
+        if results.len() > 1 {
+            return Err(Error::operation(
+                "Expected at most one record, but several match the filter",
+                "find_one_where",
+                Some(table_name.to_string()),
+            ));
+        }
+        Ok(results.pop())
+    }
+
+    /// Read one column of the matching records, without loading the rest of each row
+    pub async fn pluck<T, V>(
+        column: &str,
+        filter: Option<FilterOperator>,
+        db: &impl Executor,
+    ) -> Result<Vec<V>>
+    where
+        T: crate::Orso,
+        V: serde::de::DeserializeOwned,
+    {
+        Self::pluck_with_table::<T, V>(column, filter, db, T::table_name()).await
+    }
+
+    #[instrument(
+        name = "orso.pluck",
+        skip_all,
+        fields(operation = "pluck", table = %table_name, column = %column)
+    )]
+    pub async fn pluck_with_table<T, V>(
+        column: &str,
+        filter: Option<FilterOperator>,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<Vec<V>>
+    where
+        T: crate::Orso,
+        V: serde::de::DeserializeOwned,
+    {
+        let position = T::field_names()
+            .iter()
+            .position(|name| *name == column)
+            .ok_or_else(|| Error::validation_field("Unknown column", column, None))?;
+        let compressed = T::field_compressed()
+            .get(position)
+            .copied()
+            .unwrap_or(false);
+        let field_type = T::field_types().get(position).cloned();
+
+        let mut builder = QueryBuilder::new(table_name).select(vec![column]);
+        if let Some(filter) = filter {
+            builder = builder._where(filter);
+        }
+        let (sql, params) = crate::scope::apply::<T>(builder).build()?;
+        let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
+            params.iter().map(|p| p.as_ref()).collect();
+
+        let rows = db.query_rows(&sql, &param_refs).await?;
+        rows.into_iter()
+            .map(|mut row| {
+                // Decrypted, decoded and masked like the column of a full record
+                crate::encryption::with_keys(db, || T::decode_columns(&mut row))?;
+                let value = row.remove(column).unwrap_or(crate::Value::Null);
+                let json = if compressed {
+                    crate::lazy::value_to_json(value)?
+                } else {
+                    crate::row::value_to_json(value, field_type.as_ref())
+                };
+                serde_json::from_value(json)
+                    .map_err(|e| Error::serialization_field(e.to_string(), column))
+            })
+            .collect()
+    }
+
+    /// Count the matching records per value of `column`; rows where it is NULL are left out
+    pub async fn group_count<T>(
+        column: &str,
+        filter: Option<FilterOperator>,
+        db: &impl Executor,
+    ) -> Result<HashMap<String, i64>>
+    where
+        T: crate::Orso,
+    {
+        Self::group_count_with_table::<T>(column, filter, db, T::table_name()).await
+    }
+
+    #[instrument(
+        name = "orso.group_count",
+        skip_all,
+        fields(operation = "group_count", table = %table_name, column = %column)
+    )]
+    pub async fn group_count_with_table<T>(
+        column: &str,
+        filter: Option<FilterOperator>,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<HashMap<String, i64>>
+    where
+        T: crate::Orso,
+    {
+        let position = T::field_names()
+            .iter()
+            .position(|name| *name == column)
+            .ok_or_else(|| Error::validation_field("Unknown column", column, None))?;
+        let stored_as_bytes = T::field_compressed()
+            .get(position)
+            .copied()
+            .unwrap_or(false)
+            || T::encrypted_fields().contains(&column);
+        if stored_as_bytes {
+            return Err(Error::validation_field(
+                "Compressed and encrypted columns can't be grouped",
+                column,
+                None,
+            ));
+        }
+
+        let mut builder = QueryBuilder::new(table_name)
+            .select(vec![
+                format!("{column}::text AS key"),
+                "COUNT(*) AS count".to_string(),
+            ])
+            ._where(FilterOperator::Single(crate::Filter::is_not_null(column)))
+            .group_by(vec![column]);
+        if let Some(filter) = filter {
+            builder = builder._where(filter);
+        }
+        let (sql, params) = crate::scope::apply::<T>(builder).build()?;
+        let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
+            params.iter().map(|p| p.as_ref()).collect();
+
+        let rows = db.query_rows(&sql, &param_refs).await?;
+        rows.into_iter()
+            .map(|row| match (row.get("key"), row.get("count")) {
+                (Some(crate::Value::Text(key)), Some(crate::Value::Integer(count))) => {
+                    Ok((key.clone(), *count))
+                }
+                _ => Err(Error::query("Unexpected group_count row")),
+            })
+            .collect()
+    }
+
     /// Check if any record exists
-    pub async fn exists<T>(db: &Database) -> Result<bool>
+    pub async fn exists<T>(db: &impl Executor) -> Result<bool>
     where
         T: crate::Orso,
     {
         Self::exists_with_table::<T>(db, T::table_name()).await
     }
 
-    pub async fn exists_with_table<T>(db: &Database, table_name: &str) -> Result<bool>
+    #[instrument(name = "orso.exists", skip_all, fields(operation = "exists", table = %table_name))]
+    pub async fn exists_with_table<T>(db: &impl Executor, table_name: &str) -> Result<bool>
     where
         T: crate::Orso,
     {
-        let builder = QueryBuilder::new(table_name).limit(1);
+        let builder = crate::scope::apply::<T>(QueryBuilder::new(table_name)).limit(1);
         let count = builder.execute_count(db).await?;
         Ok(count > 0)
     }
 
     /// Check if any record exists matching filter
-    pub async fn exists_filter<T>(filter: FilterOperator, db: &Database) -> Result<bool>
+    pub async fn exists_filter<T>(filter: FilterOperator, db: &impl Executor) -> Result<bool>
     where
         T: crate::Orso,
     {
         Self::exists_filter_with_table::<T>(filter, db, T::table_name()).await
     }
 
+    #[instrument(
+        name = "orso.exists_filter",
+        skip_all,
+        fields(operation = "exists_filter", table = %table_name)
+    )]
     pub async fn exists_filter_with_table<T>(
         filter: FilterOperator,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<bool>
     where
         T: crate::Orso,
     {
-        let builder = QueryBuilder::new(table_name)._where(filter).limit(1);
+        let builder =
+            crate::scope::apply::<T>(QueryBuilder::new(table_name)._where(filter)).limit(1);
         let count = builder.execute_count(db).await?;
         Ok(count > 0)
     }
 
     /// Find by any field value
-    pub async fn find_by_field<T>(field: &str, value: crate::Value, db: &Database) -> Result<Vec<T>>
+    pub async fn find_by_field<T>(
+        field: &str,
+        value: crate::Value,
+        db: &impl Executor,
+    ) -> Result<Vec<T>>
     where
         T: crate::Orso,
     {
         Self::find_by_field_with_table(field, value, db, T::table_name()).await
     }
 
+    #[instrument(
+        name = "orso.find_by_field",
+        skip_all,
+        fields(operation = "find_by_field", table = %table_name)
+    )]
     pub async fn find_by_field_with_table<T>(
         field: &str,
         value: crate::Value,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<Vec<T>>
     where
@@ -434,7 +1057,7 @@ impl CrudOperations {
     pub async fn find_latest_by_field<T>(
         field: &str,
         value: crate::Value,
-        db: &Database,
+        db: &impl Executor,
     ) -> Result<Option<T>>
     where
         T: crate::Orso,
@@ -442,10 +1065,15 @@ impl CrudOperations {
         Self::find_latest_by_field_with_table(field, value, db, T::table_name()).await
     }
 
+    #[instrument(
+        name = "orso.find_latest_by_field",
+        skip_all,
+        fields(operation = "find_latest_by_field", table = %table_name)
+    )]
     pub async fn find_latest_by_field_with_table<T>(
         field: &str,
         value: crate::Value,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<Option<T>>
     where
@@ -467,7 +1095,7 @@ impl CrudOperations {
     pub async fn find_first_by_field<T>(
         field: &str,
         value: crate::Value,
-        db: &Database,
+        db: &impl Executor,
     ) -> Result<Option<T>>
     where
         T: crate::Orso,
@@ -475,10 +1103,15 @@ impl CrudOperations {
         Self::find_first_by_field_with_table(field, value, db, T::table_name()).await
     }
 
+    #[instrument(
+        name = "orso.find_first_by_field",
+        skip_all,
+        fields(operation = "find_first_by_field", table = %table_name)
+    )]
     pub async fn find_first_by_field_with_table<T>(
         field: &str,
         value: crate::Value,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<Option<T>>
     where
@@ -497,40 +1130,108 @@ impl CrudOperations {
     }
 
     /// Find multiple records by IDs (batch operation)
-    pub async fn find_by_ids<T>(ids: &[&str], db: &Database) -> Result<Vec<T>>
+    ///
+    /// Returns one entry per ID, in the order given, with `None` where no record matched.
+    /// IDs take the type of the primary key column, e.g. `&str` or `i64`; records are matched
+    /// to them by the text of their key.
+    pub async fn find_by_ids<T, K>(ids: &[K], db: &impl Executor) -> Result<Vec<Option<T>>>
     where
         T: crate::Orso,
+        K: tokio_postgres::types::ToSql + ToString + Sync,
     {
         Self::find_by_ids_with_table(ids, db, T::table_name()).await
     }
 
-    pub async fn find_by_ids_with_table<T>(
-        ids: &[&str],
-        db: &Database,
+    #[instrument(
+        name = "orso.find_by_ids",
+        skip_all,
+        fields(operation = "find_by_ids", table = %table_name, ids = ids.len())
+    )]
+    pub async fn find_by_ids_with_table<T, K>(
+        ids: &[K],
+        db: &impl Executor,
         table_name: &str,
-    ) -> Result<Vec<T>>
+    ) -> Result<Vec<Option<T>>>
     where
         T: crate::Orso,
+        K: tokio_postgres::types::ToSql + ToString + Sync,
     {
         if ids.is_empty() {
             return Ok(Vec::new());
         }
 
-        let id_values: Vec<crate::Value> = ids
-            .iter()
-            .map(|id| crate::Value::Text(id.to_string()))
-            .collect();
         let pk_field = T::primary_key_field();
-        let filter = FilterOperator::Single(crate::Filter::in_values(pk_field, id_values));
-        let builder = QueryBuilder::new(table_name)._where(filter);
-        builder.execute::<T>(db).await
+        let keys: Vec<String> = ids.iter().map(ToString::to_string).collect();
+        let mut found: HashMap<String, T> = HashMap::new();
+
+        // Records already in the session are not queried again, unless a scope applies
+        let scope = crate::scope::active_scope::<T>();
+        let identity_map = db.identity_map().filter(|_| scope.is_none());
+        let mut missing: Vec<&K> = Vec::new();
+        let mut queued: HashSet<&str> = HashSet::new();
+        for (id, key) in ids.iter().zip(&keys) {
+            match identity_map.and_then(|cache| cache.get(table_name, key)) {
+                Some(map) => {
+                    let record = crate::decode::decode_record(db, map)?;
+                    found.insert(key.clone(), record);
+                }
+                None if queued.insert(key) => missing.push(id),
+                None => {}
+            }
+        }
+
+        if !missing.is_empty() {
+            let mut sql = format!(
+                "SELECT {}, {pk_field}::text AS {FOUND_KEY} FROM {} WHERE {} = ANY($1)",
+                crate::lazy::select_columns::<T>().join(", "),
+                table_name,
+                pk_field
+            );
+            let mut params: Vec<Box<dyn tokio_postgres::types::ToSql + Send + Sync>> = Vec::new();
+            if let Some(scope) = &scope {
+                let mut param_counter = 2;
+                let (scope_sql, scope_params) =
+                    crate::filters::FilterOperations::build_filter_operator_with_counter(
+                        scope,
+                        &mut param_counter,
+                    )?;
+                sql.push_str(&format!(" AND ({scope_sql})"));
+                params = scope_params;
+            }
+            debug!(
+                table = table_name,
+                count = missing.len(),
+                "Finding records by IDs"
+            );
+
+            let mut param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
+                vec![&missing];
+            param_refs.extend(params.iter().map(|p| p.as_ref()));
+            for mut map in db.query_rows(&sql, &param_refs).await? {
+                let id = match map.remove(FOUND_KEY) {
+                    Some(crate::Value::Text(id)) => id,
+                    other => {
+                        return Err(Error::query(format!(
+                            "Expected the primary key {pk_field} as text, got {other:?}"
+                        )))
+                    }
+                };
+                crate::lazy::attach_sources::<T>(&mut map, table_name);
+                if let Some(cache) = identity_map {
+                    cache.insert(table_name, &id, map.clone());
+                }
+                found.insert(id, crate::decode::decode_record(db, map)?);
+            }
+        }
+
+        Ok(keys.iter().map(|key| found.get(key).cloned()).collect())
     }
 
     /// Find records by multiple values for same field (IN clause)
     pub async fn find_by_field_in<T>(
         field: &str,
         values: &[crate::Value],
-        db: &Database,
+        db: &impl Executor,
     ) -> Result<Vec<T>>
     where
         T: crate::Orso,
@@ -538,10 +1239,15 @@ impl CrudOperations {
         Self::find_by_field_in_with_table(field, values, db, T::table_name()).await
     }
 
+    #[instrument(
+        name = "orso.find_by_field_in",
+        skip_all,
+        fields(operation = "find_by_field_in", table = %table_name)
+    )]
     pub async fn find_by_field_in_with_table<T>(
         field: &str,
         values: &[crate::Value],
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<Vec<T>>
     where
@@ -559,7 +1265,7 @@ impl CrudOperations {
     /// Find records with pagination
     pub async fn find_paginated<T>(
         pagination: &Pagination,
-        db: &Database,
+        db: &impl Executor,
     ) -> Result<PaginatedResult<T>>
     where
         T: crate::Orso,
@@ -567,9 +1273,14 @@ impl CrudOperations {
         Self::find_paginated_with_table(pagination, db, T::table_name()).await
     }
 
+    #[instrument(
+        name = "orso.find_paginated",
+        skip_all,
+        fields(operation = "find_paginated", table = %table_name)
+    )]
     pub async fn find_paginated_with_table<T>(
         pagination: &Pagination,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<PaginatedResult<T>>
     where
@@ -583,7 +1294,7 @@ impl CrudOperations {
     pub async fn find_where_paginated<T>(
         filter: FilterOperator,
         pagination: &Pagination,
-        db: &Database,
+        db: &impl Executor,
     ) -> Result<PaginatedResult<T>>
     where
         T: crate::Orso,
@@ -591,10 +1302,15 @@ impl CrudOperations {
         Self::find_where_paginated_with_table(filter, pagination, db, T::table_name()).await
     }
 
+    #[instrument(
+        name = "orso.find_where_paginated",
+        skip_all,
+        fields(operation = "find_where_paginated", table = %table_name)
+    )]
     pub async fn find_where_paginated_with_table<T>(
         filter: FilterOperator,
         pagination: &Pagination,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<PaginatedResult<T>>
     where
@@ -604,11 +1320,116 @@ impl CrudOperations {
         builder.execute_paginated::<T>(db, pagination).await
     }
 
+    /// Find the records created after `(timestamp, id)`, oldest first
+    ///
+    /// Pass the `created_at` and primary key of the last row of the previous page, or
+    /// `Value::Null` for both to start at the beginning. With `#[orso_column(created_at, seek)]`
+    /// migrations add the `(created_at, id)` index that makes each page cost O(limit).
+    pub async fn page_after<T>(
+        timestamp: impl Into<crate::Value>,
+        id: impl Into<crate::Value>,
+        limit: u32,
+        db: &impl Executor,
+    ) -> Result<Vec<T>>
+    where
+        T: crate::Orso,
+    {
+        Self::page_after_with_table(timestamp, id, limit, db, T::table_name()).await
+    }
+
+    #[instrument(
+        name = "orso.page_after",
+        skip_all,
+        fields(operation = "page_after", table = %table_name)
+    )]
+    pub async fn page_after_with_table<T>(
+        timestamp: impl Into<crate::Value>,
+        id: impl Into<crate::Value>,
+        limit: u32,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<Vec<T>>
+    where
+        T: crate::Orso,
+    {
+        let created_at = T::created_at_field().ok_or_else(|| {
+            Error::validation(format!(
+                "page_after requires a created_at column on {table_name}"
+            ))
+        })?;
+        let key = T::primary_key_field();
+        let mut builder = QueryBuilder::new(table_name)
+            .order_by(Sort::asc(created_at))
+            .order_by(Sort::asc(key))
+            .limit(limit);
+
+        // `created_at >= $1` bounds the index scan; the rest only skips ties already seen
+        let timestamp = timestamp.into();
+        if timestamp != crate::Value::Null {
+            let after =
+                crate::Filter::new_simple(created_at, crate::Operator::Gt, timestamp.clone());
+            let filter = match id.into() {
+                crate::Value::Null => FilterOperator::Single(after),
+                id => FilterOperator::And(vec![
+                    FilterOperator::Single(crate::Filter::new_simple(
+                        created_at,
+                        crate::Operator::Ge,
+                        timestamp,
+                    )),
+                    FilterOperator::Or(vec![
+                        FilterOperator::Single(after),
+                        FilterOperator::Single(crate::Filter::new_simple(
+                            key,
+                            crate::Operator::Gt,
+                            id,
+                        )),
+                    ]),
+                ]),
+            };
+            builder = builder._where(filter);
+        }
+        builder.execute::<T>(db).await
+    }
+
+    /// Find records with filter and sort, a page at a time after a cursor
+    pub async fn find_where_cursor<T>(
+        filter: FilterOperator,
+        sorts: Vec<Sort>,
+        pagination: &CursorPagination,
+        db: &impl Executor,
+    ) -> Result<CursorPaginatedResult<T>>
+    where
+        T: crate::Orso,
+    {
+        Self::find_where_cursor_with_table(filter, sorts, pagination, db, T::table_name()).await
+    }
+
+    #[instrument(
+        name = "orso.find_where_cursor",
+        skip_all,
+        fields(operation = "find_where_cursor", table = %table_name)
+    )]
+    pub async fn find_where_cursor_with_table<T>(
+        filter: FilterOperator,
+        sorts: Vec<Sort>,
+        pagination: &CursorPagination,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<CursorPaginatedResult<T>>
+    where
+        T: crate::Orso,
+    {
+        let builder = QueryBuilder::new(table_name)
+            ._where(filter)
+            .order_by_multiple(sorts);
+        builder.execute_cursor::<T>(db, pagination).await
+    }
+
     /// Search records with text search
     pub async fn search<T>(
         search_filter: &SearchFilter,
         pagination: Option<&Pagination>,
-        db: &Database,
+        db: &impl Executor,
     ) -> Result<PaginatedResult<T>>
     where
         T: crate::Orso,
@@ -616,10 +1437,11 @@ impl CrudOperations {
         Self::search_with_table(search_filter, pagination, db, T::table_name()).await
     }
 
+    #[instrument(name = "orso.search", skip_all, fields(operation = "search", table = %table_name))]
     pub async fn search_with_table<T>(
         search_filter: &SearchFilter,
         pagination: Option<&Pagination>,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<PaginatedResult<T>>
     where
@@ -632,69 +1454,115 @@ impl CrudOperations {
     }
 
     /// Count all records
-    pub async fn count<T>(db: &Database) -> Result<u64>
+    pub async fn count<T>(db: &impl Executor) -> Result<u64>
     where
         T: crate::Orso,
     {
         Self::count_with_table::<T>(db, T::table_name()).await
     }
 
-    pub async fn count_with_table<T>(db: &Database, table_name: &str) -> Result<u64>
+    #[instrument(name = "orso.count", skip_all, fields(operation = "count", table = %table_name))]
+    pub async fn count_with_table<T>(db: &impl Executor, table_name: &str) -> Result<u64>
     where
         T: crate::Orso,
     {
+        if crate::scope::active_scope::<T>().is_some() {
+            return crate::scope::apply::<T>(QueryBuilder::new(table_name))
+                .execute_count(db)
+                .await;
+        }
+
         let sql = format!("SELECT COUNT(*) FROM {}", table_name);
-        let rows = db.query(&sql, &[]).await?;
+        let rows = db.query_rows(&sql, &[]).await?;
 
-        if let Some(row) = rows.get(0) {
-            let count: i64 = row.get(0);
-            Ok(count as u64)
-        } else {
-            Err(Error::query("No count result"))
-        }
+        crate::executor::count(&rows)
     }
 
     /// Count records with a filter
-    pub async fn count_where<T>(filter: FilterOperator, db: &Database) -> Result<u64>
+    pub async fn count_where<T>(filter: FilterOperator, db: &impl Executor) -> Result<u64>
     where
         T: crate::Orso,
     {
         Self::count_where_with_table::<T>(filter, db, T::table_name()).await
     }
 
+    #[instrument(
+        name = "orso.count_where",
+        skip_all,
+        fields(operation = "count_where", table = %table_name)
+    )]
     pub async fn count_where_with_table<T>(
         filter: FilterOperator,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<u64>
     where
         T: crate::Orso,
     {
-        let builder = QueryBuilder::new(table_name)._where(filter);
+        let builder = crate::scope::apply::<T>(QueryBuilder::new(table_name)._where(filter));
 
         let (sql, params) = builder.build_count()?;
         let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
             params.iter().map(|p| p.as_ref()).collect();
 
-        let rows = db.query(&sql, &param_refs).await?;
+        let rows = db.query_rows(&sql, &param_refs).await?;
 
-        if let Some(row) = rows.get(0) {
-            let count: i64 = row.get(0);
-            Ok(count as u64)
-        } else {
-            Err(Error::query("No count result"))
+        crate::executor::count(&rows)
+    }
+
+    /// Re-read a record from the database, replacing every field of `model`
+    pub async fn reload<T>(model: &mut T, db: &impl Executor) -> Result<()>
+    where
+        T: crate::Orso,
+    {
+        Self::reload_with_table(model, db, T::table_name()).await
+    }
+
+    #[instrument(name = "orso.reload", skip_all, fields(operation = "reload", table = %table_name))]
+    pub async fn reload_with_table<T>(
+        model: &mut T,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<()>
+    where
+        T: crate::Orso,
+    {
+        let id = model
+            .get_primary_key()
+            .ok_or_else(|| Error::validation("Cannot reload record without primary key"))?;
+
+        // A cached copy is what a reload is meant to replace
+        if let Some(cache) = db.identity_map() {
+            cache.evict(table_name, &id);
+        }
+
+        match Self::find_by_id_with_table::<T>(&id, db, table_name).await? {
+            Some(fresh) => {
+                *model = fresh;
+                Ok(())
+            }
+            None => Err(Error::not_found_record(
+                "Record no longer exists",
+                table_name,
+                id,
+            )),
         }
     }
 
     /// Update a record
-    pub async fn update<T>(model: &T, db: &Database) -> Result<()>
+    pub async fn update<T>(model: &T, db: &impl Executor) -> Result<WriteResult>
     where
         T: crate::Orso,
     {
         Self::update_with_table(model, db, T::table_name()).await
     }
 
-    pub async fn update_with_table<T>(model: &T, db: &Database, table_name: &str) -> Result<()>
+    #[instrument(name = "orso.update", skip_all, fields(operation = "update", table = %table_name))]
+    pub async fn update_with_table<T>(
+        model: &T,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<WriteResult>
     where
         T: crate::Orso,
     {
@@ -702,9 +1570,19 @@ impl CrudOperations {
             Error::validation("Cannot update record without primary key")
         })?;
 
-        let map = model.to_map()?;
+        let mut model = model.clone();
+        model.before_update(db).await?;
+        model.validate()?;
+
+        let mut map = crate::encryption::encode_record(db, &model)?;
+        crate::context::apply_actor::<T>(&mut map, false);
+        crate::context::keep_masked::<T>(&mut map);
         let pk_field = T::primary_key_field();
         let updated_at_field = T::updated_at_field();
+        // A trigger sets updated_at on its own
+        if let Some(field) = updated_at_field.filter(|_| T::updated_at_trigger()) {
+            map.remove(field);
+        }
 
         let mut set_clauses = Vec::new();
         let mut param_index = 1;
@@ -712,7 +1590,7 @@ impl CrudOperations {
             if k != pk_field {
                 // For updated_at fields, use database function instead of model value
                 if updated_at_field.is_some() && k == updated_at_field.unwrap() {
-                    set_clauses.push(format!("{k} = NOW()"));
+                    set_clauses.push(format!("{k} = {}", crate::types::now_sql::<T>(k)));
                 } else {
                     set_clauses.push(format!("{k} = ${}", param_index));
                     param_index += 1;
@@ -729,7 +1607,6 @@ impl CrudOperations {
         );
 
         info!(table = table_name, id = %id, "Updating record");
-        debug!(sql = %sql, "Executing update query");
 
         let mut params: Vec<Box<dyn tokio_postgres::types::ToSql + Send + Sync>> = map
             .iter()
@@ -738,45 +1615,152 @@ impl CrudOperations {
             })
             .map(|(_, v)| v.to_postgres_param())
             .collect();
-        params.push(Box::new(id.clone()));
+        params.push(key_param::<T>(&id)?);
 
         let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
             params.iter().map(|p| p.as_ref()).collect();
 
-        db.execute(&sql, &param_refs).await?;
+        let rows_affected = db.execute(&sql, &param_refs).await?;
+        model.after_update(db).await?;
 
-        info!(table = table_name, id = %id, "Successfully updated record");
-        Ok(())
+        info!(table = table_name, id = %id, rows = rows_affected, "Updated record");
+        Ok(WriteResult::for_id(rows_affected, id))
+    }
+
+    /// Set a record's `updated_at` column to the current time, leaving the other columns alone
+    pub async fn touch<T>(model: &mut T, db: &impl Executor) -> Result<()>
+    where
+        T: crate::Orso,
+    {
+        Self::touch_with_table(model, db, T::table_name()).await
+    }
+
+    #[instrument(name = "orso.touch", skip_all, fields(operation = "touch", table = %table_name))]
+    pub async fn touch_with_table<T>(
+        model: &mut T,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<()>
+    where
+        T: crate::Orso,
+    {
+        let id = model
+            .get_primary_key()
+            .ok_or_else(|| Error::validation("Cannot touch record without primary key"))?;
+        let updated_at = Self::updated_at_column::<T>(table_name)?;
+
+        // The stored row is read back so `model` gets the timestamp the database assigned
+        let sql = format!(
+            "UPDATE {} SET {} = {} WHERE {} = $1 RETURNING {}",
+            table_name,
+            updated_at,
+            crate::types::now_sql::<T>(updated_at),
+            T::primary_key_field(),
+            crate::lazy::select_columns::<T>().join(", ")
+        );
+        debug!(table = table_name, id = %id, "Touching record");
+
+        let key = key_param::<T>(&id)?;
+        let rows = db.query_rows(&sql, &[key.as_ref()]).await?;
+        match rows.into_iter().next() {
+            Some(mut map) => {
+                crate::lazy::attach_sources::<T>(&mut map, table_name);
+                *model = crate::decode::decode_record(db, map)?;
+                Ok(())
+            }
+            None => Err(Error::not_found_record(
+                "Record no longer exists",
+                table_name,
+                id,
+            )),
+        }
+    }
+
+    /// Set the `updated_at` column of every record matching `filter` to the current time
+    pub async fn touch_where<T>(filter: FilterOperator, db: &impl Executor) -> Result<u64>
+    where
+        T: crate::Orso,
+    {
+        Self::touch_where_with_table::<T>(filter, db, T::table_name()).await
+    }
+
+    #[instrument(
+        name = "orso.touch_where",
+        skip_all,
+        fields(operation = "touch_where", table = %table_name)
+    )]
+    pub async fn touch_where_with_table<T>(
+        filter: FilterOperator,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<u64>
+    where
+        T: crate::Orso,
+    {
+        let updated_at = Self::updated_at_column::<T>(table_name)?;
+        let (where_sql, params) = crate::FilterOperations::build_filter_operator(&filter)?;
+        let now = crate::types::now_sql::<T>(updated_at);
+        let sql = format!("UPDATE {table_name} SET {updated_at} = {now} WHERE {where_sql}");
+
+        let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
+            params.iter().map(|p| p.as_ref()).collect();
+
+        let affected_rows = db.execute(&sql, &param_refs).await?;
+        debug!(table = table_name, rows = affected_rows, "Touched records");
+        Ok(affected_rows)
+    }
+
+    fn updated_at_column<T>(table_name: &str) -> Result<&'static str>
+    where
+        T: crate::Orso,
+    {
+        T::updated_at_field().ok_or_else(|| {
+            Error::validation(format!(
+                "Table {table_name} has no updated_at column to touch"
+            ))
+        })
     }
 
     /// Update multiple records using Turso batch operations
-    pub async fn batch_update<T>(models: &[T], db: &Database) -> Result<()>
+    pub async fn batch_update<T>(models: &[T], db: &impl Executor) -> Result<WriteResult>
     where
         T: crate::Orso,
     {
         Self::batch_update_with_table(models, db, T::table_name()).await
     }
 
+    #[instrument(
+        name = "orso.batch_update",
+        skip_all,
+        fields(operation = "batch_update", table = %table_name)
+    )]
     pub async fn batch_update_with_table<T>(
         models: &[T],
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
-    ) -> Result<()>
+    ) -> Result<WriteResult>
     where
         T: crate::Orso,
     {
-        if models.is_empty() {
-            return Ok(());
-        }
-
+        let mut result = WriteResult::default();
         for model in models {
             let id = model.get_primary_key().ok_or_else(|| {
                 Error::validation("Cannot batch update record without primary key")
             })?;
 
-            let map = model.to_map()?;
+            let mut model = model.clone();
+            model.before_update(db).await?;
+            model.validate()?;
+
+            let mut map = crate::encryption::encode_record(db, &model)?;
+            crate::context::apply_actor::<T>(&mut map, false);
+            crate::context::keep_masked::<T>(&mut map);
             let pk_field = T::primary_key_field();
             let updated_at_field = T::updated_at_field();
+            // A trigger sets updated_at on its own
+            if let Some(field) = updated_at_field.filter(|_| T::updated_at_trigger()) {
+                map.remove(field);
+            }
 
             let mut set_clauses = Vec::new();
             let mut params: Vec<Box<dyn tokio_postgres::types::ToSql + Send + Sync>> = Vec::new();
@@ -786,7 +1770,7 @@ impl CrudOperations {
                 if k != pk_field {
                     // For updated_at fields, use database function instead of model value
                     if updated_at_field.is_some() && k == updated_at_field.unwrap() {
-                        set_clauses.push(format!("{} = NOW()", k));
+                        set_clauses.push(format!("{} = {}", k, crate::types::now_sql::<T>(k)));
                     } else {
                         set_clauses.push(format!("{} = ${}", k, param_index));
                         params.push(v.to_postgres_param());
@@ -796,7 +1780,7 @@ impl CrudOperations {
             }
 
             // Add the ID parameter for the WHERE clause
-            params.push(Box::new(id.clone()));
+            params.push(key_param::<T>(&id)?);
 
             let sql = format!(
                 "UPDATE {} SET {} WHERE {} = ${}",
@@ -809,20 +1793,27 @@ impl CrudOperations {
             let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
                 params.iter().map(|p| p.as_ref()).collect();
 
-            db.execute(&sql, &param_refs).await?;
+            let rows_affected = db.execute(&sql, &param_refs).await?;
+            model.after_update(db).await?;
+            result.add(WriteResult::for_id(rows_affected, id));
         }
-        Ok(())
+        Ok(result)
     }
 
     /// Delete a record
-    pub async fn delete<T>(model: &T, db: &Database) -> Result<bool>
+    pub async fn delete<T>(model: &T, db: &impl Executor) -> Result<WriteResult>
     where
         T: crate::Orso,
     {
         Self::delete_with_table(model, db, T::table_name()).await
     }
 
-    pub async fn delete_with_table<T>(model: &T, db: &Database, table_name: &str) -> Result<bool>
+    #[instrument(name = "orso.delete", skip_all, fields(operation = "delete", table = %table_name))]
+    pub async fn delete_with_table<T>(
+        model: &T,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<WriteResult>
     where
         T: crate::Orso,
     {
@@ -830,6 +1821,8 @@ impl CrudOperations {
             Error::validation("Cannot delete record without primary key")
         })?;
 
+        model.before_delete(db).await?;
+
         let sql = format!(
             "DELETE FROM {} WHERE {} = $1",
             table_name,
@@ -837,20 +1830,21 @@ impl CrudOperations {
         );
 
         info!(table = table_name, id = %id, "Deleting record");
-        debug!(sql = %sql, "Executing delete query");
 
-        let params: Vec<Box<dyn tokio_postgres::types::ToSql + Send + Sync>> = vec![Box::new(id)];
+        let params: Vec<Box<dyn tokio_postgres::types::ToSql + Send + Sync>> =
+            vec![key_param::<T>(&id)?];
 
         let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
             params.iter().map(|p| p.as_ref()).collect();
 
-        db.execute(&sql, &param_refs).await?;
-        info!(table = table_name, "Successfully deleted record");
-        Ok(true)
+        let rows_affected = db.execute(&sql, &param_refs).await?;
+        model.after_delete(db).await?;
+        info!(table = table_name, rows = rows_affected, "Deleted record");
+        Ok(WriteResult::for_id(rows_affected, id))
     }
 
     /// Delete a record with CASCADE to remove all dependent data
-    pub async fn delete_cascade<T>(model: &T, db: &Database) -> Result<bool>
+    pub async fn delete_cascade<T>(model: &T, db: &impl Executor) -> Result<WriteResult>
     where
         T: crate::Orso,
     {
@@ -858,7 +1852,16 @@ impl CrudOperations {
     }
 
     /// Delete a record with CASCADE from a specific table
-    pub async fn delete_cascade_with_table<T>(model: &T, db: &Database, table_name: &str) -> Result<bool>
+    #[instrument(
+        name = "orso.delete_cascade",
+        skip_all,
+        fields(operation = "delete_cascade", table = %table_name)
+    )]
+    pub async fn delete_cascade_with_table<T>(
+        model: &T,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<WriteResult>
     where
         T: crate::Orso,
     {
@@ -866,6 +1869,8 @@ impl CrudOperations {
             Error::validation("Cannot delete record without primary key")
         })?;
 
+        model.before_delete(db).await?;
+
         // PostgreSQL doesn't have CASCADE on DELETE statements, so we need to handle
         // foreign key constraints by allowing the database to cascade naturally
         // or explicitly delete dependent records first
@@ -876,66 +1881,69 @@ impl CrudOperations {
         );
 
         info!(table = table_name, id = %id, "Deleting record with cascade");
-        debug!(sql = %sql, "Executing cascade delete query");
 
-        let params: Vec<Box<dyn tokio_postgres::types::ToSql + Send + Sync>> = vec![Box::new(id)];
+        let params: Vec<Box<dyn tokio_postgres::types::ToSql + Send + Sync>> =
+            vec![key_param::<T>(&id)?];
 
         let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
             params.iter().map(|p| p.as_ref()).collect();
 
         // Execute the delete - PostgreSQL will handle cascading via foreign key constraints
-        db.execute(&sql, &param_refs).await?;
-        info!(table = table_name, "Successfully deleted record with cascade");
-        Ok(true)
+        let rows_affected = db.execute(&sql, &param_refs).await?;
+        model.after_delete(db).await?;
+        info!(
+            table = table_name,
+            rows = rows_affected,
+            "Deleted record with cascade"
+        );
+        Ok(WriteResult::for_id(rows_affected, id))
     }
 
-    /// Delete multiple records using Turso batch operations
-    pub async fn batch_delete<T>(ids: &[&str], db: &Database) -> Result<u64>
+    /// Delete multiple records using Turso batch operations, without running delete hooks
+    pub async fn batch_delete<T>(ids: &[&str], db: &impl Executor) -> Result<WriteResult>
     where
         T: crate::Orso,
     {
         Self::batch_delete_with_table::<T>(ids, db, T::table_name()).await
     }
 
+    #[instrument(
+        name = "orso.batch_delete",
+        skip_all,
+        fields(operation = "batch_delete", table = %table_name)
+    )]
     pub async fn batch_delete_with_table<T>(
         ids: &[&str],
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
-    ) -> Result<u64>
+    ) -> Result<WriteResult>
     where
         T: crate::Orso,
     {
         if ids.is_empty() {
-            return Ok(0);
+            return Ok(WriteResult::default());
         }
 
         let pk_field = T::primary_key_field();
 
-        // Use IN clause for efficient bulk delete
-        let placeholders: Vec<String> = (1..=ids.len()).map(|i| format!("${}", i)).collect();
+        // One array parameter, so any number of ids fits in a single statement
         let sql = format!(
-            "DELETE FROM {} WHERE {} IN ({})",
+            "DELETE FROM {} WHERE {} = ANY($1) RETURNING {}",
             table_name,
             pk_field,
-            placeholders.join(", ")
+            returning_key(pk_field)
         );
+        let ids = key_array_param::<T>(ids)?;
 
-        let params: Vec<Box<dyn tokio_postgres::types::ToSql + Send + Sync>> = ids
-            .iter()
-            .map(|id| {
-                Box::new(id.to_string()) as Box<dyn tokio_postgres::types::ToSql + Send + Sync>
-            })
-            .collect();
-
-        let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
-            params.iter().map(|p| p.as_ref()).collect();
-
-        let affected_rows = db.execute(&sql, &param_refs).await?;
-        Ok(affected_rows)
+        let rows = db.query_rows(&sql, &[ids.as_ref()]).await?;
+        Ok(WriteResult {
+            rows_affected: rows.len() as u64,
+            returned_ids: returned_ids(rows, pk_field)?,
+        })
     }
 
-    /// Delete multiple records with CASCADE to remove all dependent data
-    pub async fn batch_delete_cascade<T>(ids: &[&str], db: &Database) -> Result<u64>
+    /// Delete multiple records with CASCADE to remove all dependent data, without running hooks
+    pub async fn batch_delete_cascade<T>(ids: &[&str], db: &impl Executor) -> Result<WriteResult>
     where
         T: crate::Orso,
     {
@@ -943,50 +1951,52 @@ impl CrudOperations {
     }
 
     /// Delete multiple records with CASCADE from a specific table
+    #[instrument(
+        name = "orso.batch_delete_cascade",
+        skip_all,
+        fields(operation = "batch_delete_cascade", table = %table_name)
+    )]
     pub async fn batch_delete_cascade_with_table<T>(
         ids: &[&str],
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
-    ) -> Result<u64>
+    ) -> Result<WriteResult>
     where
         T: crate::Orso,
     {
         if ids.is_empty() {
-            return Ok(0);
+            return Ok(WriteResult::default());
         }
 
         let pk_field = T::primary_key_field();
 
-        // Use IN clause for efficient bulk cascade delete
-        let placeholders: Vec<String> = (1..=ids.len()).map(|i| format!("${}", i)).collect();
+        // One array parameter, so any number of ids fits in a single statement
         let sql = format!(
-            "DELETE FROM {} WHERE {} IN ({})",
+            "DELETE FROM {} WHERE {} = ANY($1) RETURNING {}",
             table_name,
             pk_field,
-            placeholders.join(", ")
+            returning_key(pk_field)
         );
 
         info!(table = table_name, count = ids.len(), "Batch deleting records with cascade");
-        debug!(sql = %sql, "Executing batch cascade delete query");
-
-        let params: Vec<Box<dyn tokio_postgres::types::ToSql + Send + Sync>> = ids
-            .iter()
-            .map(|id| {
-                Box::new(id.to_string()) as Box<dyn tokio_postgres::types::ToSql + Send + Sync>
-            })
-            .collect();
 
-        let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
-            params.iter().map(|p| p.as_ref()).collect();
+        let ids = key_array_param::<T>(ids)?;
 
         // Execute the delete - PostgreSQL will handle cascading via foreign key constraints
-        let affected_rows = db.execute(&sql, &param_refs).await?;
+        let rows = db.query_rows(&sql, &[ids.as_ref()]).await?;
+        let affected_rows = rows.len() as u64;
         info!(table = table_name, affected = affected_rows, "Successfully batch deleted records with cascade");
-        Ok(affected_rows)
+        Ok(WriteResult {
+            rows_affected: affected_rows,
+            returned_ids: returned_ids(rows, pk_field)?,
+        })
     }
 
     /// Upsert multiple records using Turso batch operations with automatically detected unique columns
-    pub async fn batch_upsert<T>(models: &[T], db: &Database) -> Result<()>
+    ///
+    /// Lifecycle hooks don't run: whether a record is inserted or updated is only known once
+    /// the statement has run.
+    pub async fn batch_upsert<T>(models: &[T], db: &impl Executor) -> Result<WriteResult>
     where
         T: crate::Orso,
     {
@@ -995,47 +2005,128 @@ impl CrudOperations {
 
     pub async fn batch_upsert_with_table<T>(
         models: &[T],
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
-    ) -> Result<()>
+    ) -> Result<WriteResult>
+    where
+        T: crate::Orso,
+    {
+        let strategy = UpsertStrategy::default();
+        Self::batch_upsert_with_strategy_with_table(models, &strategy, db, table_name).await
+    }
+
+    /// Upsert multiple records, merging the columns of existing rows as `strategy` says
+    pub async fn batch_upsert_with<T>(
+        models: &[T],
+        strategy: &UpsertStrategy,
+        db: &impl Executor,
+    ) -> Result<WriteResult>
+    where
+        T: crate::Orso,
+    {
+        Self::batch_upsert_with_strategy_with_table(models, strategy, db, T::table_name()).await
+    }
+
+    #[instrument(
+        name = "orso.batch_upsert",
+        skip_all,
+        fields(operation = "batch_upsert", table = %table_name)
+    )]
+    pub async fn batch_upsert_with_strategy_with_table<T>(
+        models: &[T],
+        strategy: &UpsertStrategy,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<WriteResult>
     where
         T: crate::Orso,
     {
         if models.is_empty() {
-            return Ok(());
+            return Ok(WriteResult::default());
         }
 
         let unique_columns: Vec<&str> = T::unique_fields();
         if unique_columns.is_empty() {
             return Err(Error::validation("No unique columns defined with orso_column(unique) for batch upsert"));
         }
+        // PostgreSQL can't resolve ON CONFLICT against a constraint checked at commit
+        let deferrable = T::deferrable_fields();
+        if let Some(column) = unique_columns
+            .iter()
+            .find(|column| deferrable.iter().any(|(name, _)| name == *column))
+        {
+            return Err(Error::validation(format!(
+                "Batch upsert can't use the deferrable unique column {column} as conflict target"
+            )));
+        }
 
+        // Validate everything up front so an invalid record doesn't leave a partial batch
         for model in models {
-            let map = model.to_map()?;
+            model.validate()?;
+        }
 
-            // Build conflict columns for ON CONFLICT clause
-            let conflict_columns = unique_columns.join(", ");
+        // New rows get the masked values, existing ones keep theirs like on update
+        let masked_columns = crate::context::kept_masked_fields::<T>();
+        let case_insensitive = T::case_insensitive_unique_fields();
+        let mut maps = Vec::with_capacity(models.len());
+        for model in models {
+            let mut map = crate::encryption::encode_record(db, model)?;
+            crate::context::apply_actor::<T>(&mut map, true);
+            maps.push(map);
+        }
 
-            let columns: Vec<String> = map.keys().cloned().collect();
-            let placeholders: Vec<String> =
-                (1..=columns.len()).map(|i| format!("${}", i)).collect();
+        // Build conflict columns for ON CONFLICT clause, naming `lower()` index expressions
+        let conflict_columns = unique_columns
+            .iter()
+            .map(|column| {
+                if case_insensitive.contains(column) {
+                    format!("(lower({column}))")
+                } else {
+                    column.to_string()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ");
 
-            let params: Vec<Box<dyn tokio_postgres::types::ToSql + Send + Sync>> = map
-                .values()
-                .map(|v| v.to_postgres_param())
-                .collect();
+        // A statement can't update a row twice, so a repeated key starts the next one
+        let conflict_key = |map: &crate::RowMap| -> Vec<String> {
+            unique_columns
+                .iter()
+                .map(|column| {
+                    let literal = map
+                        .get(*column)
+                        .map(|v| v.to_sql_literal())
+                        .unwrap_or_default();
+                    if case_insensitive.contains(column) {
+                        literal.to_lowercase()
+                    } else {
+                        literal
+                    }
+                })
+                .collect()
+        };
+        let rows: Vec<&crate::RowMap> = maps.iter().collect();
+        let chunks = chunk_rows(&rows, |index| Some(conflict_key(rows[index])));
+
+        let mut result = WriteResult::default();
+        for (columns, chunk) in chunks {
+            let chunk_rows: Vec<&crate::RowMap> = chunk.iter().map(|&i| rows[i]).collect();
+            let (values, params) = multi_row_values(&columns, &chunk_rows);
 
             // Build UPDATE SET clause for conflict resolution
             let updated_at_field = T::updated_at_field();
+            let created_by_field = T::created_by_field();
             let update_sets: Vec<String> = columns
                 .iter()
                 .filter(|col| !unique_columns.contains(&col.as_str())) // Don't update unique columns
-                .map(|col| {
+                .filter(|col| created_by_field != Some(col.as_str())) // Keep the original creator
+                .filter(|col| !masked_columns.contains(&col.as_str())) // Keep real masked values
+                .filter_map(|col| {
                     // For updated_at fields, use database function instead of excluded value
                     if updated_at_field.is_some() && col == updated_at_field.unwrap() {
-                        format!("{} = NOW()", col)
+                        Some(format!("{} = {}", col, crate::types::now_sql::<T>(col)))
                     } else {
-                        format!("{} = EXCLUDED.{}", col, col)
+                        strategy.merge_for(col).assignment(table_name, col)
                     }
                 })
                 .collect();
@@ -1043,65 +2134,185 @@ impl CrudOperations {
             let sql = if update_sets.is_empty() {
                 // If no columns to update, just ignore conflicts
                 format!(
-                    "INSERT INTO {} ({}) VALUES ({}) ON CONFLICT ({}) DO NOTHING",
+                    "INSERT INTO {} ({}) VALUES {} ON CONFLICT ({}) DO NOTHING RETURNING {}",
                     table_name,
                     columns.join(", "),
-                    placeholders.join(", "),
-                    conflict_columns
+                    values,
+                    conflict_columns,
+                    returning_key(T::primary_key_field())
                 )
             } else {
                 // Use INSERT ... ON CONFLICT DO UPDATE for proper upsert
                 format!(
-                    "INSERT INTO {} ({}) VALUES ({}) ON CONFLICT ({}) DO UPDATE SET {}",
+                    "INSERT INTO {} ({}) VALUES {} \
+                     ON CONFLICT ({}) DO UPDATE SET {} RETURNING {}",
                     table_name,
                     columns.join(", "),
-                    placeholders.join(", "),
+                    values,
                     conflict_columns,
-                    update_sets.join(", ")
+                    update_sets.join(", "),
+                    returning_key(T::primary_key_field())
                 )
             };
 
             let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
                 params.iter().map(|p| p.as_ref()).collect();
 
-            db.execute(&sql, &param_refs).await?;
+            let rows = db.query_rows(&sql, &param_refs).await?;
+            result.rows_affected += rows.len() as u64;
+            result
+                .returned_ids
+                .extend(returned_ids(rows, T::primary_key_field())?);
         }
-        Ok(())
+        Ok(result)
     }
 
     /// Delete records with a filter
-    pub async fn delete_where<T>(filter: FilterOperator, db: &Database) -> Result<u64>
+    pub async fn delete_where<T>(filter: FilterOperator, db: &impl Executor) -> Result<u64>
     where
         T: crate::Orso,
     {
         Self::delete_where_with_table::<T>(filter, db, T::table_name()).await
     }
 
+    #[instrument(
+        name = "orso.delete_where",
+        skip_all,
+        fields(operation = "delete_where", table = %table_name)
+    )]
     pub async fn delete_where_with_table<T>(
         filter: FilterOperator,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<u64>
     where
         T: crate::Orso,
     {
-        let builder = QueryBuilder::new(table_name)._where(filter);
+        QueryBuilder::delete(table_name)
+            ._where(filter)
+            .execute_write(db)
+            .await
+    }
 
-        let (sql, params) = builder.build()?;
-        let delete_sql = sql.replace("SELECT *", "DELETE");
+    /// Delete records with a filter, returning the primary keys of the deleted rows
+    pub async fn delete_where_returning<T>(
+        filter: FilterOperator,
+        db: &impl Executor,
+    ) -> Result<Vec<String>>
+    where
+        T: crate::Orso,
+    {
+        Self::delete_where_returning_with_table::<T>(filter, db, T::table_name()).await
+    }
+
+    #[instrument(
+        name = "orso.delete_where_returning",
+        skip_all,
+        fields(operation = "delete_where_returning", table = %table_name)
+    )]
+    pub async fn delete_where_returning_with_table<T>(
+        filter: FilterOperator,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<Vec<String>>
+    where
+        T: crate::Orso,
+    {
+        let pk_field = T::primary_key_field();
+        let (where_sql, params) = crate::FilterOperations::build_filter_operator(&filter)?;
+        let returning = returning_key(pk_field);
+        let sql = format!("DELETE FROM {table_name} WHERE {where_sql} RETURNING {returning}");
 
         let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
             params.iter().map(|p| p.as_ref()).collect();
 
-        let affected_rows = db.execute(&delete_sql, &param_refs).await?;
+        let rows = db.query_rows(&sql, &param_refs).await?;
+        let ids = returned_ids(rows, pk_field)?;
+        info!(table = table_name, rows = ids.len(), "Deleted records");
+        Ok(ids)
+    }
+
+    /// Delete every record of the table, returning how many were deleted
+    ///
+    /// Hooks are not run and default scopes do not apply.
+    pub async fn delete_all<T>(confirm: ConfirmDeleteAll, db: &impl Executor) -> Result<u64>
+    where
+        T: crate::Orso,
+    {
+        Self::delete_all_with_table::<T>(confirm, db, T::table_name()).await
+    }
+
+    #[instrument(
+        name = "orso.delete_all",
+        skip_all,
+        fields(operation = "delete_all", table = %table_name)
+    )]
+    pub async fn delete_all_with_table<T>(
+        _confirm: ConfirmDeleteAll,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<u64>
+    where
+        T: crate::Orso,
+    {
+        warn!(table = table_name, "Deleting all records");
+        let affected_rows = db
+            .execute(&format!("DELETE FROM {table_name}"), &[])
+            .await?;
+        info!(
+            table = table_name,
+            rows = affected_rows,
+            "Deleted all records"
+        );
         Ok(affected_rows)
     }
 
+    /// Empty the table with `TRUNCATE`, which is faster than deleting for large tables
+    ///
+    /// Hooks and row-level triggers are not run.
+    pub async fn truncate<T>(confirm: ConfirmTruncate, db: &impl Executor) -> Result<()>
+    where
+        T: crate::Orso,
+    {
+        Self::truncate_with_table::<T>(confirm, db, T::table_name()).await
+    }
+
+    #[instrument(
+        name = "orso.truncate",
+        skip_all,
+        fields(operation = "truncate", table = %table_name)
+    )]
+    pub async fn truncate_with_table<T>(
+        confirm: ConfirmTruncate,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<()>
+    where
+        T: crate::Orso,
+    {
+        let mut sql = format!("TRUNCATE TABLE {table_name}");
+        if confirm.restart_identity {
+            sql.push_str(" RESTART IDENTITY");
+        }
+        if confirm.cascade {
+            sql.push_str(" CASCADE");
+        }
+
+        warn!(
+            table = table_name,
+            cascade = confirm.cascade,
+            restart_identity = confirm.restart_identity,
+            "Truncating table"
+        );
+        db.execute(&sql, &[]).await?;
+        Ok(())
+    }
+
     /// List records with optional sorting and pagination
     pub async fn list<T>(
         sort: Option<Vec<Sort>>,
         pagination: Option<&Pagination>,
-        db: &Database,
+        db: &impl Executor,
     ) -> Result<PaginatedResult<T>>
     where
         T: crate::Orso,
@@ -1109,10 +2320,11 @@ impl CrudOperations {
         Self::list_with_table(sort, pagination, db, T::table_name()).await
     }
 
+    #[instrument(name = "orso.list", skip_all, fields(operation = "list", table = %table_name))]
     pub async fn list_with_table<T>(
         sort: Option<Vec<Sort>>,
         pagination: Option<&Pagination>,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<PaginatedResult<T>>
     where
@@ -1133,7 +2345,7 @@ impl CrudOperations {
         filter: FilterOperator,
         sort: Option<Vec<Sort>>,
         pagination: Option<&Pagination>,
-        db: &Database,
+        db: &impl Executor,
     ) -> Result<PaginatedResult<T>>
     where
         T: crate::Orso,
@@ -1141,11 +2353,16 @@ impl CrudOperations {
         Self::list_where_with_table(filter, sort, pagination, db, T::table_name()).await
     }
 
+    #[instrument(
+        name = "orso.list_where",
+        skip_all,
+        fields(operation = "list_where", table = %table_name)
+    )]
     pub async fn list_where_with_table<T>(
         filter: FilterOperator,
         sort: Option<Vec<Sort>>,
         pagination: Option<&Pagination>,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<PaginatedResult<T>>
     where
@@ -1162,7 +2379,7 @@ impl CrudOperations {
     }
 
     /// Execute a custom query
-    pub async fn query<T>(builder: QueryBuilder, db: &Database) -> Result<Vec<T>>
+    pub async fn query<T>(builder: QueryBuilder, db: &impl Executor) -> Result<Vec<T>>
     where
         T: crate::Orso,
     {
@@ -1170,7 +2387,7 @@ impl CrudOperations {
     }
 
     /// Execute a custom query with table override
-    pub async fn query_with_table<T>(builder: QueryBuilder, db: &Database) -> Result<Vec<T>>
+    pub async fn query_with_table<T>(builder: QueryBuilder, db: &impl Executor) -> Result<Vec<T>>
     where
         T: crate::Orso,
     {
@@ -1183,7 +2400,7 @@ impl CrudOperations {
     pub async fn query_paginated<T>(
         builder: QueryBuilder,
         pagination: &Pagination,
-        db: &Database,
+        db: &impl Executor,
     ) -> Result<PaginatedResult<T>>
     where
         T: crate::Orso,
@@ -1195,7 +2412,7 @@ impl CrudOperations {
     pub async fn query_paginated_with_table<T>(
         builder: QueryBuilder,
         pagination: &Pagination,
-        db: &Database,
+        db: &impl Executor,
     ) -> Result<PaginatedResult<T>>
     where
         T: crate::Orso,
@@ -1210,7 +2427,7 @@ impl CrudOperations {
         function: Aggregate,
         column: &str,
         filter: Option<FilterOperator>,
-        db: &Database,
+        db: &impl Executor,
     ) -> Result<Option<f64>>
     where
         T: crate::Orso,
@@ -1218,17 +2435,26 @@ impl CrudOperations {
         Self::aggregate_with_table::<T>(function, column, filter, db, T::table_name()).await
     }
 
+    #[instrument(
+        name = "orso.aggregate",
+        skip_all,
+        fields(operation = "aggregate", table = %table_name)
+    )]
     pub async fn aggregate_with_table<T>(
         function: Aggregate,
         column: &str,
         filter: Option<FilterOperator>,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<Option<f64>>
     where
         T: crate::Orso,
     {
-        let mut builder = QueryBuilder::new(table_name).aggregate(function, column, None::<String>);
+        let mut builder = crate::scope::apply::<T>(QueryBuilder::new(table_name).aggregate(
+            function,
+            column,
+            Some("value"),
+        ));
 
         if let Some(filter) = filter {
             builder = builder._where(filter);
@@ -1239,16 +2465,13 @@ impl CrudOperations {
         let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
             params.iter().map(|p| p.as_ref()).collect();
 
-        let rows = db.query(&sql, &param_refs).await?;
+        let rows = db.query_rows(&sql, &param_refs).await?;
 
-        if let Some(row) = rows.get(0) {
-            // Try to get as f64 first, then as i64 and convert
-            if let Ok(value) = row.try_get::<_, f64>(0) {
-                Ok(Some(value))
-            } else if let Ok(value) = row.try_get::<_, i64>(0) {
-                Ok(Some(value as f64))
-            } else {
-                Err(Error::query("Failed to get aggregate value"))
+        if let Some(row) = rows.first() {
+            match row.get("value") {
+                Some(crate::Value::Real(value)) => Ok(Some(*value)),
+                Some(crate::Value::Integer(value)) => Ok(Some(*value as f64)),
+                _ => Err(Error::query("Failed to get aggregate value")),
             }
         } else {
             Ok(None)
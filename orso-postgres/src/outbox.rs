@@ -0,0 +1,172 @@
+//! Transactional outbox
+//!
+//! Publishing an event right after committing a change loses the event when the process dies in
+//! between. Instead, [`OutboxExt::outbox`] writes it to the `orso_outbox` table in the same
+//! transaction as the change, and a poller hands committed events to the message broker with
+//! [`Outbox::drain`], removing them once published:
+//!
+//! ```rust,ignore
+//! Outbox::install(&db).await?;
+//!
+//! let tx = client.transaction().await?;
+//! order.insert(&tx).await?;
+//! tx.outbox(OutboxEvent::new("order.created", &order)?.with_key(&order.id)).await?;
+//! tx.commit().await?;
+//!
+//! // In the publisher
+//! loop {
+//!     let published = Outbox::drain(&db, 100, |events| async move {
+//!         for event in events {
+//!             broker.send(&event.topic, event.key.as_deref(), &event.payload).await?;
+//!         }
+//!         Ok(())
+//!     })
+//!     .await?;
+//!     if published == 0 {
+//!         tokio::time::sleep(Duration::from_secs(1)).await;
+//!     }
+//! }
+//! ```
+//!
+//! Events are handed over in the order they were written and removed only when the handler
+//! succeeds, so delivery is at least once: a failed or interrupted batch is drained again.
+//! Concurrent drainers skip each other's batches, at the cost of that ordering.
+
+use crate::{Database, Error, Executor, OrsoDateTime, Result};
+use serde::Serialize;
+use std::future::Future;
+use tracing::{debug, instrument, warn};
+
+/// Table the events are stored in
+pub const OUTBOX_TABLE: &str = "orso_outbox";
+
+/// An event to publish
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutboxEvent {
+    pub topic: String,
+    /// Partitioning or deduplication key for the broker
+    pub key: Option<String>,
+    pub payload: serde_json::Value,
+}
+
+impl OutboxEvent {
+    pub fn new(topic: impl Into<String>, payload: &impl Serialize) -> Result<Self> {
+        Ok(Self {
+            topic: topic.into(),
+            key: None,
+            payload: serde_json::to_value(payload)
+                .map_err(|e| Error::serialization(e.to_string()))?,
+        })
+    }
+
+    pub fn with_key(mut self, key: impl Into<String>) -> Self {
+        self.key = Some(key.into());
+        self
+    }
+}
+
+/// An event read back from the outbox
+#[derive(Debug, Clone, PartialEq)]
+pub struct OutboxEntry {
+    pub id: i64,
+    pub topic: String,
+    pub key: Option<String>,
+    pub payload: serde_json::Value,
+    pub created_at: OrsoDateTime,
+}
+
+/// Outbox table management and publication
+pub struct Outbox;
+
+impl Outbox {
+    /// Statements creating the outbox table
+    pub fn install_sql() -> Vec<String> {
+        vec![format!(
+            "CREATE TABLE IF NOT EXISTS {OUTBOX_TABLE} (\n    \
+             id BIGSERIAL PRIMARY KEY,\n    \
+             topic TEXT NOT NULL,\n    \
+             key TEXT,\n    \
+             payload JSONB NOT NULL,\n    \
+             created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()\n)"
+        )]
+    }
+
+    /// Create the outbox table
+    pub async fn install(db: &Database) -> Result<()> {
+        for sql in Self::install_sql() {
+            db.execute(&sql, &[]).await.map_err(|e| {
+                Error::migration(
+                    format!("Failed to install outbox: {}", e),
+                    Some(OUTBOX_TABLE.to_string()),
+                    Some("install_outbox".to_string()),
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Hand up to `batch` of the oldest events to `handler`, removing them if it succeeds
+    ///
+    /// Returns how many events were published, `0` once the outbox is empty.
+    #[instrument(name = "orso.outbox.drain", skip_all, fields(batch))]
+    pub async fn drain<F, Fut>(db: &Database, batch: u32, handler: F) -> Result<usize>
+    where
+        F: FnOnce(Vec<OutboxEntry>) -> Fut,
+        Fut: Future<Output = Result<()>>,
+    {
+        let sql = format!(
+            "SELECT id, topic, key, payload, created_at FROM {OUTBOX_TABLE} \
+             ORDER BY id LIMIT {batch} FOR UPDATE SKIP LOCKED"
+        );
+        let statement = sql.as_str();
+        db.with_connection(&sql, |mut client| async move {
+            let tx = client.transaction().await?;
+            let entries = tx
+                .query(statement, &[])
+                .await?
+                .iter()
+                .map(|row| {
+                    Ok(OutboxEntry {
+                        id: row.try_get(0)?,
+                        topic: row.try_get(1)?,
+                        key: row.try_get(2)?,
+                        payload: row.try_get(3)?,
+                        created_at: row.try_get(4)?,
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            if entries.is_empty() {
+                tx.commit().await?;
+                return Ok(0);
+            }
+
+            let ids: Vec<i64> = entries.iter().map(|entry| entry.id).collect();
+            if let Err(e) = handler(entries).await {
+                warn!(error = %e, events = ids.len(), "Outbox handler failed, events are kept");
+                tx.rollback().await?;
+                return Err(e);
+            }
+
+            let sql = format!("DELETE FROM {OUTBOX_TABLE} WHERE id = ANY($1)");
+            tx.execute(&sql, &[&ids]).await?;
+            tx.commit().await?;
+            debug!(events = ids.len(), "Published outbox events");
+            Ok(ids.len())
+        })
+        .await
+    }
+}
+
+/// Writing events to the outbox through any executor, see [`crate::outbox`]
+#[allow(async_fn_in_trait)]
+pub trait OutboxExt: Executor {
+    /// Store `event` for publication once the surrounding transaction commits
+    async fn outbox(&self, event: OutboxEvent) -> Result<()> {
+        let sql = format!("INSERT INTO {OUTBOX_TABLE} (topic, key, payload) VALUES ($1, $2, $3)");
+        self.execute(&sql, &[&event.topic, &event.key, &event.payload])
+            .await?;
+        Ok(())
+    }
+}
+
+impl<E: Executor> OutboxExt for E {}
@@ -1,8 +1,12 @@
 // Pagination support
+use crate::{Error, Result, Value};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
 use serde::{Deserialize, Serialize};
 
 // Pagination parameters for queries
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct Pagination {
     /// Page number (1-based)
     pub page: u32,
@@ -12,6 +16,26 @@ pub struct Pagination {
     pub total: Option<u64>,
     /// Total number of pages (calculated)
     pub total_pages: Option<u32>,
+    /// How `total` is worked out
+    #[serde(default)]
+    pub count: CountMode,
+    /// Whether another page follows, known without an exact count
+    #[serde(default)]
+    pub has_more: Option<bool>,
+}
+
+/// How a paginated query works out the total
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum CountMode {
+    /// `COUNT(*)` on every page
+    #[default]
+    Exact,
+    /// The planner's row estimate, from `pg_class.reltuples` or `EXPLAIN`
+    Estimated,
+    /// No total at all
+    Skip,
 }
 
 impl Pagination {
@@ -22,9 +46,26 @@ impl Pagination {
             per_page,
             total: None,
             total_pages: None,
+            count: CountMode::Exact,
+            has_more: None,
         }
     }
 
+    /// Don't count the matching rows; `has_next` is still known
+    pub fn without_count(mut self) -> Self {
+        self.count = CountMode::Skip;
+        self
+    }
+
+    /// Use the planner's row estimate as the total instead of counting
+    ///
+    /// The estimate is only as fresh as the table's statistics, so `has_next` is worked out
+    /// from the page itself rather than from `total_pages`.
+    pub fn with_estimated_count(mut self) -> Self {
+        self.count = CountMode::Estimated;
+        self
+    }
+
     /// Get the offset for SQL LIMIT/OFFSET
     pub fn offset(&self) -> u32 {
         (self.page - 1) * self.per_page
@@ -43,6 +84,9 @@ impl Pagination {
 
     /// Check if there's a next page
     pub fn has_next(&self) -> bool {
+        if let Some(has_more) = self.has_more {
+            return has_more;
+        }
         if let (Some(total_pages), Some(current_page)) = (self.total_pages, Some(self.page)) {
             current_page < total_pages
         } else {
@@ -91,6 +135,7 @@ impl Default for Pagination {
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct PaginatedResult<T> {
     /// The data items for the current page
     pub data: Vec<T>,
@@ -223,6 +268,27 @@ impl Default for CursorPagination {
     }
 }
 
+/// Encode the sort key of the last row of a page as an opaque cursor
+pub(crate) fn encode_cursor(keys: &[(String, Value)]) -> Result<String> {
+    let json = serde_json::to_vec(keys).map_err(|e| Error::serialization(e.to_string()))?;
+    Ok(URL_SAFE_NO_PAD.encode(json))
+}
+
+/// Decode a cursor, checking it was produced for the given sort columns
+pub(crate) fn decode_cursor(cursor: &str, columns: &[&str]) -> Result<Vec<Value>> {
+    let invalid = || Error::validation_field("Invalid cursor", "cursor", Some(cursor.to_string()));
+    let json = URL_SAFE_NO_PAD.decode(cursor).map_err(|_| invalid())?;
+    let keys: Vec<(String, Value)> = serde_json::from_slice(&json).map_err(|_| invalid())?;
+    if keys.len() != columns.len() || keys.iter().zip(columns).any(|((c, _), col)| c != col) {
+        return Err(Error::validation_field(
+            "Cursor was created for a different sort order",
+            "cursor",
+            Some(cursor.to_string()),
+        ));
+    }
+    Ok(keys.into_iter().map(|(_, value)| value).collect())
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct CursorPaginatedResult<T> {
     /// The data items
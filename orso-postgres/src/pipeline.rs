@@ -0,0 +1,145 @@
+//! Pipelined queries on one connection
+//!
+//! A [`Pipeline`] holds a single pooled connection. Queries run on it concurrently are sent
+//! back to back without waiting for each other's results, so a page that fans out into several
+//! independent reads pays for about one round trip instead of one per query:
+//!
+//! ```rust,ignore
+//! let pipeline = db.pipeline().await?;
+//! let (user, orders, unread) = futures_util::try_join!(
+//!     User::find_by_id(&user_id, &pipeline),
+//!     Order::find_where(FilterOperator::Single(Filter::eq("user_id", &user_id)), &pipeline),
+//!     Message::count_where(FilterOperator::Single(Filter::eq("read", false)), &pipeline),
+//! )?;
+//!
+//! // Queries of the same kind can be queued from an iterator
+//! let users = pipeline.join_all(ids.iter().map(|id| User::find_by_id(id, &pipeline))).await?;
+//! ```
+//!
+//! Statements are not wrapped in a transaction, so each sees the data as of its own start. Use a
+//! transaction for writes that belong together. Writes name the
+//! [`OperationContext`](crate::OperationContext) actor for the connection while they run, so they
+//! run one at a time rather than see each other's actor.
+
+use crate::database::with_statement_context;
+use crate::executor::sync_params;
+use crate::{Database, Executor, Result, RowMap};
+use std::future::Future;
+use std::time::Instant;
+use tokio::sync::OwnedSemaphorePermit;
+use tokio_postgres::types::ToSql;
+use tracing::Instrument;
+
+/// Executor pipelining concurrent statements on one pooled connection
+pub struct Pipeline<'db> {
+    db: &'db Database,
+    client: deadpool_postgres::Object,
+    /// Held by writes, which name their actor for the whole connection
+    writes: tokio::sync::Mutex<()>,
+    _slot: Option<OwnedSemaphorePermit>,
+}
+
+impl<'db> Pipeline<'db> {
+    pub(crate) fn new(
+        db: &'db Database,
+        client: deadpool_postgres::Object,
+        slot: Option<OwnedSemaphorePermit>,
+    ) -> Self {
+        Self {
+            db,
+            client,
+            writes: tokio::sync::Mutex::new(()),
+            _slot: slot,
+        }
+    }
+
+    /// Run queued queries together, returning their results in order
+    ///
+    /// Fails with the first error; the other queries still run to completion on the server.
+    pub async fn join_all<T>(
+        &self,
+        queries: impl IntoIterator<Item = impl Future<Output = Result<T>>>,
+    ) -> Result<Vec<T>> {
+        futures_util::future::try_join_all(queries).await
+    }
+
+    /// Return the connection to the pool
+    pub fn into_inner(self) -> deadpool_postgres::Object {
+        self.client
+    }
+
+    /// Run `statement`, naming the current actor around it if it is a write
+    async fn run<T>(
+        &self,
+        sql: &str,
+        statement: impl Future<Output = std::result::Result<T, tokio_postgres::Error>>,
+    ) -> std::result::Result<T, tokio_postgres::Error> {
+        if !crate::database::writes_rows(sql) {
+            return statement.await;
+        }
+        let _write = self.writes.lock().await;
+        let Some(ctx) = crate::database::actor_for(sql) else {
+            return statement.await;
+        };
+        Database::set_session_actor(&self.client, &ctx).await?;
+        let result = statement.await;
+        Database::reset_session_actor(&self.client, result).await
+    }
+}
+
+impl std::fmt::Debug for Pipeline<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Pipeline").finish_non_exhaustive()
+    }
+}
+
+impl Executor for Pipeline<'_> {
+    async fn execute(&self, sql: &str, params: &[&(dyn ToSql + Send + Sync)]) -> Result<u64> {
+        self.db.before_query(sql)?;
+        if let Some(log) = self.db.query_log() {
+            log.record_sql(sql);
+        }
+        let span = self.db.statement_span(sql, params);
+        let started = Instant::now();
+        let sync_params = sync_params(params);
+        let statement = tokio_postgres::Client::execute(&self.client, sql, &sync_params);
+        let result = self.run(sql, statement).instrument(span.clone()).await;
+        self.db
+            .observe(&span, sql, started, result.as_ref().copied());
+        with_statement_context(result, sql, params.len())
+    }
+
+    async fn query_rows(
+        &self,
+        sql: &str,
+        params: &[&(dyn ToSql + Send + Sync)],
+    ) -> Result<Vec<RowMap>> {
+        self.db.before_query(sql)?;
+        if let Some(log) = self.db.query_log() {
+            log.record_sql(sql);
+        }
+        let span = self.db.statement_span(sql, params);
+        let started = Instant::now();
+        let sync_params = sync_params(params);
+        let statement = tokio_postgres::Client::query(&self.client, sql, &sync_params);
+        let result = self.run(sql, statement).instrument(span.clone()).await;
+        let rows = result.as_ref().map(|rows| rows.len() as u64);
+        self.db.observe(&span, sql, started, rows);
+        with_statement_context(result, sql, params.len())?
+            .iter()
+            .map(crate::operations::CrudOperations::row_to_map)
+            .collect()
+    }
+
+    fn query_log(&self) -> Option<&crate::advisor::QueryLog> {
+        self.db.query_log()
+    }
+
+    fn strict_decoding(&self) -> bool {
+        self.db.strict_decoding()
+    }
+
+    fn keyring(&self) -> Option<&std::sync::Arc<crate::encryption::Keyring>> {
+        self.db.keyring()
+    }
+}
@@ -0,0 +1,162 @@
+//! Polymorphic references
+//!
+//! A `PolyRef` field marked `#[orso_column(ref_poly)]` points at a record of any model. It is
+//! stored in a `<field>_type`/`<field>_id` column pair holding the target's table name and
+//! primary key, so comments or attachments can belong to several kinds of record:
+//!
+//! ```rust,ignore
+//! #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+//! #[orso_table("comments")]
+//! struct Comment {
+//!     #[orso_column(primary_key)]
+//!     id: Option<String>,
+//!     #[orso_column(ref_poly)]
+//!     subject: PolyRef, // subject_type, subject_id
+//!     body: String,
+//! }
+//!
+//! orso_poly! {
+//!     enum Commentable {
+//!         Post(Post),
+//!         Photo(Photo),
+//!     }
+//! }
+//!
+//! let comment = Comment { subject: PolyRef::to(&post)?, ..Default::default() };
+//! match comment.subject.load_as::<Commentable>(&db).await? {
+//!     Some(Commentable::Post(post)) => println!("on post {}", post.title),
+//!     Some(Commentable::Photo(photo)) => println!("on photo {}", photo.url),
+//!     None => println!("subject was deleted"),
+//! }
+//!
+//! let on_post = Comment::find_where(PolyRef::filter("subject", &post)?, &db).await?;
+//! ```
+
+use crate::{Error, Executor, FieldType, Filter, FilterOperator, Orso, OrsoEmbed, Result};
+use serde::{Deserialize, Serialize};
+
+/// Reference to a record of any model: its table name and primary key
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct PolyRef {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub id: String,
+}
+
+impl PolyRef {
+    pub fn new(kind: impl Into<String>, id: impl Into<String>) -> Self {
+        Self {
+            kind: kind.into(),
+            id: id.into(),
+        }
+    }
+
+    /// Reference `record`, which must have been saved
+    pub fn to<T: Orso>(record: &T) -> Result<Self> {
+        let id = record.get_primary_key().ok_or_else(|| {
+            Error::validation(format!(
+                "Cannot reference {} record without primary key",
+                T::table_name()
+            ))
+        })?;
+        Ok(Self::new(T::table_name(), id))
+    }
+
+    /// Whether this points at a record of `T`
+    pub fn is<T: Orso>(&self) -> bool {
+        self.kind == T::table_name()
+    }
+
+    /// Load the target as `T`, `None` if it points at another model or the record is gone
+    pub async fn load<T: Orso>(&self, db: &impl Executor) -> Result<Option<T>> {
+        if !self.is::<T>() {
+            return Ok(None);
+        }
+        T::find_by_id(&self.id, db).await
+    }
+
+    /// Load the target through a type map declared with [`orso_poly!`](crate::orso_poly)
+    pub async fn load_as<T: PolyTarget>(&self, db: &impl Executor) -> Result<Option<T>> {
+        T::load(self, db).await
+    }
+
+    /// Filter matching records whose `field` references `record`
+    pub fn filter<T: Orso>(field: &str, record: &T) -> Result<FilterOperator> {
+        let target = Self::to(record)?;
+        Ok(FilterOperator::and(vec![
+            FilterOperator::Single(Filter::eq(format!("{field}_type"), target.kind)),
+            FilterOperator::Single(Filter::eq(format!("{field}_id"), target.id)),
+        ]))
+    }
+}
+
+impl OrsoEmbed for PolyRef {
+    fn field_names() -> Vec<&'static str> {
+        vec!["type", "id"]
+    }
+
+    fn field_types() -> Vec<FieldType> {
+        vec![FieldType::Text, FieldType::Text]
+    }
+
+    fn field_nullable() -> Vec<bool> {
+        vec![false, false]
+    }
+
+    fn column_definitions() -> Vec<String> {
+        vec![
+            "type TEXT NOT NULL".to_string(),
+            "id TEXT NOT NULL".to_string(),
+        ]
+    }
+}
+
+/// A set of models a [`PolyRef`] can point at, declared with [`orso_poly!`](crate::orso_poly)
+#[allow(async_fn_in_trait)]
+pub trait PolyTarget: Sized {
+    /// Table names of the registered models
+    fn kinds() -> Vec<&'static str>;
+
+    /// Load the target of `reference`, `None` if the record is gone
+    async fn load(reference: &PolyRef, db: &impl Executor) -> Result<Option<Self>>;
+}
+
+/// Unregistered kinds are reported instead of read as missing records
+pub fn unregistered<T: PolyTarget>(reference: &PolyRef) -> Error {
+    Error::validation(format!(
+        "Polymorphic type {} is not one of {}",
+        reference.kind,
+        T::kinds().join(", ")
+    ))
+}
+
+/// Declare the models a polymorphic reference can point at
+///
+/// Generates an enum with one variant per model and its [`PolyTarget`] impl.
+#[macro_export]
+macro_rules! orso_poly {
+    ($(#[$meta:meta])* $vis:vis enum $name:ident { $($variant:ident($model:ty)),* $(,)? }) => {
+        $(#[$meta])*
+        $vis enum $name {
+            $($variant($model)),*
+        }
+
+        impl $crate::PolyTarget for $name {
+            fn kinds() -> Vec<&'static str> {
+                vec![$(<$model as $crate::Orso>::table_name()),*]
+            }
+
+            async fn load(
+                reference: &$crate::PolyRef,
+                db: &impl $crate::Executor,
+            ) -> $crate::Result<Option<Self>> {
+                $(
+                    if reference.is::<$model>() {
+                        return Ok(reference.load::<$model>(db).await?.map($name::$variant));
+                    }
+                )*
+                Err($crate::poly::unregistered::<Self>(reference))
+            }
+        }
+    };
+}
@@ -1,4 +1,8 @@
-use crate::{Aggregate, Database, FilterOperator, PaginatedResult, Pagination, Result, Sort};
+use crate::{
+    Aggregate, AggregateExpr, CountMode, CursorPaginatedResult, CursorPagination, Error, Executor,
+    FilterOperator, GroupedRow, PaginatedResult, Pagination, Result, Sort, Value,
+};
+use tracing::instrument;
 
 pub struct QueryResult<T> {
     pub data: Vec<T>,
@@ -18,6 +22,31 @@ impl<T> QueryResult<T> {
     }
 }
 
+/// Group fetched records in memory, e.g. `page.data().group_by_key(|order| order.status)`
+pub trait GroupByKey<T> {
+    fn group_by_key<K, F>(&self, key: F) -> std::collections::HashMap<K, Vec<T>>
+    where
+        K: std::hash::Hash + Eq,
+        F: FnMut(&T) -> K;
+}
+
+impl<T: Clone> GroupByKey<T> for [T] {
+    fn group_by_key<K, F>(&self, mut key: F) -> std::collections::HashMap<K, Vec<T>>
+    where
+        K: std::hash::Hash + Eq,
+        F: FnMut(&T) -> K,
+    {
+        let mut groups = std::collections::HashMap::new();
+        for item in self {
+            groups
+                .entry(key(item))
+                .or_insert_with(Vec::new)
+                .push(item.clone());
+        }
+        groups
+    }
+}
+
 pub struct QueryBuilder {
     table: String,
     select_columns: Vec<String>,
@@ -30,6 +59,22 @@ pub struct QueryBuilder {
     offset: Option<u32>,
     distinct: bool,
     aggregate: Option<AggregateClause>,
+    aggregates: Vec<AggregateExpr>,
+    write: Option<WriteClause>,
+    returning: Vec<String>,
+}
+
+/// What an `UPDATE` or `DELETE` builder writes
+#[derive(Clone)]
+enum WriteClause {
+    Update(Vec<(String, Assignment)>),
+    Delete,
+}
+
+#[derive(Clone)]
+enum Assignment {
+    Value(Value),
+    Expression(String),
 }
 
 struct JoinClause {
@@ -60,9 +105,58 @@ impl QueryBuilder {
             offset: None,
             distinct: false,
             aggregate: None,
+            aggregates: Vec::new(),
+            write: None,
+            returning: Vec::new(),
         }
     }
 
+    /// Start an `UPDATE` of the rows matching the where clauses, see [`QueryBuilder::set`]
+    ///
+    /// `order_by`, `limit` and `offset` pick the rows to update.
+    pub fn update(table: impl Into<String>) -> Self {
+        Self {
+            write: Some(WriteClause::Update(Vec::new())),
+            ..Self::new(table)
+        }
+    }
+
+    /// Start a `DELETE` of the rows matching the where clauses
+    ///
+    /// `order_by`, `limit` and `offset` pick the rows to delete.
+    pub fn delete(table: impl Into<String>) -> Self {
+        Self {
+            write: Some(WriteClause::Delete),
+            ..Self::new(table)
+        }
+    }
+
+    /// Set a column of an `UPDATE` to a value
+    pub fn set(mut self, column: impl Into<String>, value: impl Into<Value>) -> Self {
+        if let Some(WriteClause::Update(assignments)) = &mut self.write {
+            assignments.push((column.into(), Assignment::Value(value.into())));
+        }
+        self
+    }
+
+    /// Set a column of an `UPDATE` to a SQL expression, e.g. `set_expression("views", "views + 1")`
+    pub fn set_expression(
+        mut self,
+        column: impl Into<String>,
+        expression: impl Into<String>,
+    ) -> Self {
+        if let Some(WriteClause::Update(assignments)) = &mut self.write {
+            assignments.push((column.into(), Assignment::Expression(expression.into())));
+        }
+        self
+    }
+
+    /// Columns an `UPDATE` or `DELETE` returns from the written rows
+    pub fn returning(mut self, columns: Vec<impl Into<String>>) -> Self {
+        self.returning = columns.into_iter().map(|c| c.into()).collect();
+        self
+    }
+
     /// Select specific columns
     pub fn select(mut self, columns: Vec<impl Into<String>>) -> Self {
         self.select_columns = columns.into_iter().map(|c| c.into()).collect();
@@ -165,6 +259,12 @@ impl QueryBuilder {
         self
     }
 
+    /// Select the `group_by` columns and these aggregates, see [`QueryBuilder::execute_grouped`]
+    pub fn aggregates(mut self, aggregates: impl IntoIterator<Item = AggregateExpr>) -> Self {
+        self.aggregates.extend(aggregates);
+        self
+    }
+
     /// Select all columns
     pub fn select_all(mut self) -> Self {
         self.select_columns = vec!["*".to_string()];
@@ -265,29 +365,65 @@ impl QueryBuilder {
     }
 
     /// Execute count query
-    pub async fn execute_count(&self, db: &Database) -> Result<u64> {
+    #[instrument(
+        name = "orso.execute_count",
+        skip_all,
+        fields(operation = "execute_count", table = %self.table)
+    )]
+    pub async fn execute_count(&self, db: &impl Executor) -> Result<u64> {
         let (sql, params) = self.build_count()?;
         let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
             params.iter().map(|p| p.as_ref()).collect();
 
-        let rows = db.query(&sql, &param_refs).await?;
-
-        if let Some(row) = rows.get(0) {
-            let count: i64 = row.get(0);
-            Ok(count as u64)
-        } else {
-            Err(crate::Error::query("No count result"))
-        }
+        let rows = db.query_rows(&sql, &param_refs).await?;
+        crate::executor::count(&rows)
     }
 
     /// Execute aggregate query
-    pub async fn execute_aggregate(&self, db: &Database) -> Result<Vec<tokio_postgres::Row>> {
+    #[instrument(
+        name = "orso.execute_aggregate",
+        skip_all,
+        fields(operation = "execute_aggregate", table = %self.table)
+    )]
+    pub async fn execute_aggregate(&self, db: &impl Executor) -> Result<Vec<crate::RowMap>> {
         let (sql, params) = self.build()?;
         let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
             params.iter().map(|p| p.as_ref()).collect();
 
-        let rows = db.query(&sql, &param_refs).await?;
-        Ok(rows)
+        db.query_rows(&sql, &param_refs).await
+    }
+
+    /// Execute a query of [`QueryBuilder::aggregates`], one [`GroupedRow`] per group
+    ///
+    /// Group keys are named by their column, without a table qualifier.
+    #[instrument(
+        name = "orso.execute_grouped",
+        skip_all,
+        fields(operation = "execute_grouped", table = %self.table)
+    )]
+    pub async fn execute_grouped(&self, db: &impl Executor) -> Result<Vec<GroupedRow>> {
+        if self.aggregates.is_empty() {
+            return Err(Error::query("execute_grouped needs at least one aggregate"));
+        }
+        let (sql, params) = self.build()?;
+        let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
+            params.iter().map(|p| p.as_ref()).collect();
+
+        let keys: Vec<String> = self
+            .group_by
+            .iter()
+            .map(|column| column.rsplit('.').next().unwrap_or(column).to_string())
+            .collect();
+        let rows = db.query_rows(&sql, &param_refs).await?;
+        Ok(rows
+            .into_iter()
+            .map(|row| GroupedRow::new(&keys, row))
+            .collect())
+    }
+
+    /// Run `EXPLAIN` for this query
+    pub async fn explain(&self, db: &impl Executor) -> Result<crate::explain::QueryPlan> {
+        crate::explain::QueryPlan::of(self, db).await
     }
 
     /// Build the SQL query
@@ -297,8 +433,13 @@ impl QueryBuilder {
         String,
         Vec<Box<dyn tokio_postgres::types::ToSql + Send + Sync>>,
     )> {
+        if let Some(write) = &self.write {
+            return self.build_write(write);
+        }
+
         let mut sql = String::new();
         let mut params = Vec::new();
+        let mut param_counter = 1;
 
         // SELECT clause
         sql.push_str("SELECT ");
@@ -311,6 +452,14 @@ impl QueryBuilder {
             if let Some(alias) = &agg.alias {
                 sql.push_str(&format!(" AS {alias}"));
             }
+        } else if !self.aggregates.is_empty() {
+            let columns: Vec<String> = self
+                .group_by
+                .iter()
+                .cloned()
+                .chain(self.aggregates.iter().map(AggregateExpr::select_sql))
+                .collect();
+            sql.push_str(&columns.join(", "));
         } else {
             sql.push_str(&self.select_columns.join(", "));
         }
@@ -330,7 +479,8 @@ impl QueryBuilder {
         // WHERE clause
         if !self.where_clauses.is_empty() {
             sql.push_str(" WHERE ");
-            let (where_sql, where_params) = self.build_where_clause(&self.where_clauses)?;
+            let (where_sql, where_params) =
+                self.build_where_clause(&self.where_clauses, &mut param_counter)?;
             sql.push_str(&where_sql);
             params.extend(where_params);
         }
@@ -343,7 +493,8 @@ impl QueryBuilder {
         // HAVING clause
         if !self.having.is_empty() {
             sql.push_str(" HAVING ");
-            let (having_sql, having_params) = self.build_where_clause(&self.having)?;
+            let (having_sql, having_params) =
+                self.build_where_clause(&self.having, &mut param_counter)?;
             sql.push_str(&having_sql);
             params.extend(having_params);
         }
@@ -370,6 +521,140 @@ impl QueryBuilder {
         Ok((sql, params))
     }
 
+    fn build_write(
+        &self,
+        write: &WriteClause,
+    ) -> Result<(
+        String,
+        Vec<Box<dyn tokio_postgres::types::ToSql + Send + Sync>>,
+    )> {
+        if !self.joins.is_empty()
+            || !self.group_by.is_empty()
+            || !self.having.is_empty()
+            || self.aggregate.is_some()
+            || !self.aggregates.is_empty()
+        {
+            return Err(Error::query(
+                "Joins, grouping and aggregates can't be used in UPDATE or DELETE",
+            ));
+        }
+
+        let mut params: Vec<Box<dyn tokio_postgres::types::ToSql + Send + Sync>> = Vec::new();
+        let mut param_counter = 1;
+        let mut sql = match write {
+            WriteClause::Update(assignments) if assignments.is_empty() => {
+                return Err(Error::query("UPDATE needs at least one column to set"));
+            }
+            WriteClause::Update(assignments) => {
+                let mut sets = Vec::new();
+                for (column, assignment) in assignments {
+                    match assignment {
+                        Assignment::Value(value) => {
+                            sets.push(format!("{column} = ${param_counter}"));
+                            param_counter += 1;
+                            params.push(value.to_postgres_param());
+                        }
+                        Assignment::Expression(expression) => {
+                            sets.push(format!("{column} = {expression}"));
+                        }
+                    }
+                }
+                format!("UPDATE {} SET {}", self.table, sets.join(", "))
+            }
+            WriteClause::Delete => format!("DELETE FROM {}", self.table),
+        };
+
+        let mut condition = String::new();
+        if !self.where_clauses.is_empty() {
+            let (where_sql, where_params) =
+                self.build_where_clause(&self.where_clauses, &mut param_counter)?;
+            condition = format!(" WHERE {where_sql}");
+            params.extend(where_params);
+        }
+
+        // UPDATE and DELETE have no ORDER BY or LIMIT, so the rows are picked by a subquery
+        if self.order_by.is_empty() && self.limit.is_none() && self.offset.is_none() {
+            sql.push_str(&condition);
+        } else {
+            let mut picked = format!("SELECT ctid FROM {}{condition}", self.table);
+            if !self.order_by.is_empty() {
+                let order: Vec<String> = self
+                    .order_by
+                    .iter()
+                    .map(|sort| format!("{} {}", sort.column, sort.order))
+                    .collect();
+                picked.push_str(&format!(" ORDER BY {}", order.join(", ")));
+            }
+            if let Some(limit) = self.limit {
+                picked.push_str(&format!(" LIMIT {limit}"));
+            }
+            if let Some(offset) = self.offset {
+                picked.push_str(&format!(" OFFSET {offset}"));
+            }
+            sql.push_str(&format!(" WHERE ctid = ANY(ARRAY({picked} FOR UPDATE))"));
+        }
+
+        if !self.returning.is_empty() {
+            sql.push_str(&format!(" RETURNING {}", self.returning.join(", ")));
+        }
+
+        Ok((sql, params))
+    }
+
+    /// Run an `UPDATE` or `DELETE` and return the number of rows written
+    #[instrument(
+        name = "orso.execute_write",
+        skip_all,
+        fields(operation = "execute_write", table = %self.table)
+    )]
+    pub async fn execute_write(&self, db: &impl Executor) -> Result<u64> {
+        if self.write.is_none() {
+            return Err(Error::query(
+                "execute_write needs an UPDATE or DELETE query",
+            ));
+        }
+        let (sql, params) = self.build()?;
+        let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
+            params.iter().map(|p| p.as_ref()).collect();
+
+        db.execute(&sql, &param_refs).await
+    }
+
+    /// Run an `UPDATE` or `DELETE` and return the written rows as records
+    #[instrument(
+        name = "orso.execute_returning",
+        skip_all,
+        fields(operation = "execute_returning", table = %self.table)
+    )]
+    pub async fn execute_returning<T>(&self, db: &impl Executor) -> Result<Vec<T>>
+    where
+        T: crate::Orso,
+    {
+        if self.write.is_none() {
+            return Err(Error::query(
+                "execute_returning needs an UPDATE or DELETE query",
+            ));
+        }
+        let mut builder = self.clone();
+        if builder.returning.is_empty() {
+            builder.returning = crate::lazy::select_columns::<T>()
+                .into_iter()
+                .map(str::to_string)
+                .collect();
+        }
+        let (sql, params) = builder.build()?;
+        let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
+            params.iter().map(|p| p.as_ref()).collect();
+
+        let rows = db.query_rows(&sql, &param_refs).await?;
+        rows.into_iter()
+            .map(|mut map| {
+                crate::lazy::attach_sources::<T>(&mut map, &self.table);
+                crate::decode::decode_record(db, map)
+            })
+            .collect()
+    }
+
     /// Build a count query
     pub fn build_count(
         &self,
@@ -379,6 +664,7 @@ impl QueryBuilder {
     )> {
         let mut sql = String::new();
         let mut params = Vec::new();
+        let mut param_counter = 1;
 
         sql.push_str("SELECT COUNT(*)");
 
@@ -397,7 +683,8 @@ impl QueryBuilder {
         // WHERE clause
         if !self.where_clauses.is_empty() {
             sql.push_str(" WHERE ");
-            let (where_sql, where_params) = self.build_where_clause(&self.where_clauses)?;
+            let (where_sql, where_params) =
+                self.build_where_clause(&self.where_clauses, &mut param_counter)?;
             sql.push_str(&where_sql);
             params.extend(where_params);
         }
@@ -410,7 +697,8 @@ impl QueryBuilder {
         // HAVING clause
         if !self.having.is_empty() {
             sql.push_str(" HAVING ");
-            let (having_sql, having_params) = self.build_where_clause(&self.having)?;
+            let (having_sql, having_params) =
+                self.build_where_clause(&self.having, &mut param_counter)?;
             sql.push_str(&having_sql);
             params.extend(having_params);
         }
@@ -422,6 +710,7 @@ impl QueryBuilder {
     fn build_where_clause(
         &self,
         filters: &[FilterOperator],
+        param_counter: &mut usize,
     ) -> Result<(
         String,
         Vec<Box<dyn tokio_postgres::types::ToSql + Send + Sync>>,
@@ -433,69 +722,253 @@ impl QueryBuilder {
             if i > 0 {
                 sql.push_str(" AND ");
             }
+            // Placeholders are numbered across all clauses of the query
             let (filter_sql, filter_params) =
-                crate::filters::FilterOperations::build_filter_operator(filter)?;
+                crate::filters::FilterOperations::build_filter_operator_with_counter(
+                    filter,
+                    param_counter,
+                )?;
             sql.push_str(&filter_sql);
             params.extend(filter_params);
         }
+        crate::filters::check_param_count(*param_counter - 1)?;
 
         Ok((sql, params))
     }
 
     /// Execute the query
-    pub async fn execute<T>(&self, db: &Database) -> Result<Vec<T>>
+    #[instrument(
+        name = "orso.execute",
+        skip_all,
+        fields(operation = "execute", table = %self.table)
+    )]
+    pub async fn execute<T>(&self, db: &impl Executor) -> Result<Vec<T>>
     where
         T: crate::Orso,
     {
-        let (sql, params) = self.build()?;
+        crate::scope::apply::<T>(self.clone()).fetch::<T>(db).await
+    }
+
+    pub(crate) async fn fetch<T>(&self, db: &impl Executor) -> Result<Vec<T>>
+    where
+        T: crate::Orso,
+    {
+        // Lazy columns are left out of SELECT * and loaded on demand
+        let (sql, params) = if self.selects_all_model_columns() && !T::lazy_fields().is_empty() {
+            self.clone()
+                .select(crate::lazy::select_columns::<T>())
+                .build()?
+        } else {
+            self.build()?
+        };
         let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
             params.iter().map(|p| p.as_ref()).collect();
 
-        let rows = db.query(&sql, &param_refs).await?;
+        let rows = db.query_rows(&sql, &param_refs).await?;
 
         let mut results = Vec::new();
-        for row in rows {
-            let map = T::row_to_map(&row)?;
-            let result: T = T::from_map(map)?;
+        for mut map in rows {
+            crate::lazy::attach_sources::<T>(&mut map, &self.table);
+            let result: T = crate::decode::decode_record(db, map)?;
             results.push(result);
         }
 
         Ok(results)
     }
 
+    /// Execute the query and map its rows into `R`, e.g. the result of a join or aggregate
+    #[instrument(
+        name = "orso.execute_as",
+        skip_all,
+        fields(operation = "execute_as", table = %self.table)
+    )]
+    pub async fn execute_as<R>(&self, db: &impl Executor) -> Result<Vec<R>>
+    where
+        R: crate::OrsoRow,
+    {
+        let (sql, params) = self.build()?;
+        let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
+            params.iter().map(|p| p.as_ref()).collect();
+
+        R::fetch_all(&sql, &param_refs, db).await
+    }
+
+    fn selects_all_model_columns(&self) -> bool {
+        self.select_columns == ["*"]
+            && self.joins.is_empty()
+            && self.aggregate.is_none()
+            && self.aggregates.is_empty()
+    }
+
     /// Execute the query with pagination
+    #[instrument(
+        name = "orso.execute_paginated",
+        skip_all,
+        fields(operation = "execute_paginated", table = %self.table)
+    )]
     pub async fn execute_paginated<T>(
         &self,
-        db: &Database,
+        db: &impl Executor,
         pagination: &Pagination,
     ) -> Result<PaginatedResult<T>>
     where
         T: crate::Orso,
     {
-        // Get total count
-        let count_builder = QueryBuilder::new(&self.table).select(vec!["COUNT(*) as count"]);
+        let scoped = crate::scope::apply::<T>(self.clone());
+
+        let total = match pagination.count {
+            CountMode::Exact => {
+                let mut count_builder =
+                    QueryBuilder::new(&self.table).select(vec!["COUNT(*) as count"]);
+                count_builder.where_clauses = scoped.where_clauses.clone();
+
+                let (count_sql, count_params) = count_builder.build_count()?;
+                let count_param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
+                    count_params.iter().map(|p| p.as_ref()).collect();
+
+                let count_rows = db.query_rows(&count_sql, &count_param_refs).await?;
+                Some(if count_rows.is_empty() {
+                    0
+                } else {
+                    crate::executor::count(&count_rows)?
+                })
+            }
+            CountMode::Estimated => {
+                let mut estimate_builder = QueryBuilder::new(&self.table);
+                estimate_builder.where_clauses = scoped.where_clauses.clone();
+                Some(estimate_builder.estimate_count(db).await?)
+            }
+            CountMode::Skip => None,
+        };
 
-        let (count_sql, count_params) = count_builder.build_count()?;
-        let count_param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
-            count_params.iter().map(|p| p.as_ref()).collect();
+        // Without an exact count, one extra row tells whether another page follows
+        let probe = pagination.count != CountMode::Exact;
+        let limit = pagination.limit() + probe as u32;
+        let data_builder = scoped.limit(limit).offset(pagination.offset());
+        let mut data = data_builder.fetch::<T>(db).await?;
 
-        let count_rows = db.query(&count_sql, &count_param_refs).await?;
-        let total: u64 = if let Some(row) = count_rows.get(0) {
-            let count: i64 = row.get(0);
-            count as u64
-        } else {
-            0
-        };
+        let mut pagination = pagination.clone();
+        if probe {
+            pagination.has_more = Some(data.len() > pagination.limit() as usize);
+            data.truncate(pagination.limit() as usize);
+        }
+        if let Some(total) = total {
+            pagination.set_total(total);
+        }
+        Ok(PaginatedResult::new(data, pagination))
+    }
+
+    /// The planner's estimate of how many rows the query matches, without counting them
+    ///
+    /// Unfiltered queries read `pg_class.reltuples`, others the row estimate of `EXPLAIN`.
+    /// Tables that were never analyzed fall back to `EXPLAIN` too.
+    pub async fn estimate_count(&self, db: &impl Executor) -> Result<u64> {
+        if self.where_clauses.is_empty() && self.joins.is_empty() {
+            let sql = "SELECT reltuples::bigint AS count FROM pg_class WHERE oid = to_regclass($1)";
+            let rows = db.query_rows(sql, &[&self.table]).await?;
+            match rows.first().and_then(|row| row.get("count")) {
+                Some(Value::Integer(count)) if *count >= 0 => return Ok(*count as u64),
+                _ => {}
+            }
+        }
+
+        let mut builder = self.clone();
+        builder.limit = None;
+        builder.offset = None;
+        let (sql, params) = builder.build()?;
+        let param_refs: Vec<&(dyn tokio_postgres::types::ToSql + Send + Sync)> =
+            params.iter().map(|p| p.as_ref()).collect();
+        let rows = db
+            .query_rows(&format!("EXPLAIN (FORMAT JSON) {sql}"), &param_refs)
+            .await?;
+        let plan_rows = rows
+            .first()
+            .and_then(|row| row.values().next())
+            .and_then(|plan| match plan {
+                Value::Json(plan) => plan[0]["Plan"]["Plan Rows"].as_f64(),
+                Value::Text(plan) => serde_json::from_str::<serde_json::Value>(plan).ok()?[0]
+                    ["Plan"]["Plan Rows"]
+                    .as_f64(),
+                _ => None,
+            })
+            .ok_or_else(|| Error::query("EXPLAIN returned no row estimate"))?;
+        Ok(plan_rows as u64)
+    }
+
+    /// Execute the query one page at a time, continuing after `pagination.cursor`
+    ///
+    /// The ORDER BY columns, plus the primary key as a tiebreaker, are encoded into
+    /// `next_cursor`, so rows inserted between pages neither repeat nor shift later pages.
+    /// Sort columns are compared with `=`, `<` and `>`, so they should be NOT NULL, and can't be
+    /// compressed, encrypted or masked.
+    #[instrument(
+        name = "orso.execute_cursor",
+        skip_all,
+        fields(operation = "execute_cursor", table = %self.table)
+    )]
+    pub async fn execute_cursor<T>(
+        &self,
+        db: &impl Executor,
+        pagination: &CursorPagination,
+    ) -> Result<CursorPaginatedResult<T>>
+    where
+        T: crate::Orso,
+    {
+        let mut sorts = self.order_by.clone();
+        if !sorts
+            .iter()
+            .any(|sort| sort.column == T::primary_key_field())
+        {
+            sorts.push(Sort::asc(T::primary_key_field()));
+        }
+        let columns: Vec<&str> = sorts.iter().map(|sort| sort.column.as_str()).collect();
+
+        let opaque = opaque_columns::<T>();
+        if let Some(column) = columns.iter().find(|column| opaque.contains(*column)) {
+            return Err(Error::validation_field(
+                "Cursor pagination can't sort by a compressed, encrypted or masked column",
+                *column,
+                None,
+            ));
+        }
 
-        // Get paginated data
-        let data_builder = self
-            .clone()
-            .limit(pagination.limit())
-            .offset(pagination.offset());
+        let mut builder = crate::scope::apply::<T>(self.clone());
+        builder.order_by = sorts.clone();
+        if let Some(cursor) = &pagination.cursor {
+            let values = crate::pagination::decode_cursor(cursor, &columns)?;
+            builder = builder._where(keyset_filter(&sorts, values, pagination.include_cursor));
+        }
 
-        let data = data_builder.execute::<T>(db).await?;
+        let limit = pagination.limit() as usize;
+        let mut data = builder.limit(pagination.limit() + 1).fetch::<T>(db).await?;
+        let has_next = data.len() > limit;
+        data.truncate(limit);
+
+        let next_cursor = match data.last() {
+            Some(last) if has_next => {
+                let map = crate::encryption::encode_record(db, last)?;
+                let keys = columns
+                    .iter()
+                    .map(|column| match map.get(*column) {
+                        Some(value) => Ok((column.to_string(), value.clone())),
+                        None => Err(Error::validation_field(
+                            "Cursor sort column is not a field of the model",
+                            *column,
+                            None,
+                        )),
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                Some(crate::pagination::encode_cursor(&keys)?)
+            }
+            _ => None,
+        };
 
-        Ok(PaginatedResult::with_total(data, pagination.clone(), total))
+        let mut pagination = pagination.clone();
+        pagination.has_prev = pagination.cursor.is_some();
+        pagination.prev_cursor = pagination.cursor.clone();
+        pagination.has_next = has_next;
+        pagination.next_cursor = next_cursor;
+        Ok(CursorPaginatedResult::new(data, pagination))
     }
 
     /// Add vector similarity search with cosine distance
@@ -540,6 +1013,46 @@ impl QueryBuilder {
     }
 }
 
+/// Rows sorting after the cursor: `(a > $1) OR (a = $1 AND b < $2) OR ...`
+/// Compressed, encrypted and masked columns of `T`, which store something other than the
+/// field's value and so can't be sorted or compared by it
+pub(crate) fn opaque_columns<T: crate::Orso>() -> Vec<&'static str> {
+    T::field_names()
+        .into_iter()
+        .zip(T::field_compressed())
+        .filter_map(|(name, compressed)| compressed.then_some(name))
+        .chain(T::encrypted_fields())
+        .chain(crate::context::kept_masked_fields::<T>())
+        .collect()
+}
+
+fn keyset_filter(sorts: &[Sort], values: Vec<Value>, inclusive: bool) -> FilterOperator {
+    let mut branches = Vec::with_capacity(sorts.len());
+    for (i, sort) in sorts.iter().enumerate() {
+        let last = i + 1 == sorts.len();
+        let operator = match (&sort.order, inclusive && last) {
+            (crate::SortOrder::Asc, false) => crate::Operator::Gt,
+            (crate::SortOrder::Asc, true) => crate::Operator::Ge,
+            (crate::SortOrder::Desc, false) => crate::Operator::Lt,
+            (crate::SortOrder::Desc, true) => crate::Operator::Le,
+        };
+        let mut conditions: Vec<FilterOperator> = sorts[..i]
+            .iter()
+            .zip(&values)
+            .map(|(prev, value)| {
+                FilterOperator::Single(crate::Filter::eq(&prev.column, value.clone()))
+            })
+            .collect();
+        conditions.push(FilterOperator::Single(crate::Filter::new_simple(
+            &sort.column,
+            operator,
+            values[i].clone(),
+        )));
+        branches.push(FilterOperator::And(conditions));
+    }
+    FilterOperator::Or(branches)
+}
+
 impl Clone for QueryBuilder {
     fn clone(&self) -> Self {
         Self {
@@ -554,6 +1067,9 @@ impl Clone for QueryBuilder {
             offset: self.offset,
             distinct: self.distinct,
             aggregate: self.aggregate.clone(),
+            aggregates: self.aggregates.clone(),
+            write: self.write.clone(),
+            returning: self.returning.clone(),
         }
     }
 }
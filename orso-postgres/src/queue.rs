@@ -0,0 +1,144 @@
+//! Job queues on `FOR UPDATE SKIP LOCKED`
+//!
+//! Any model with a `run_at` timestamp column can be used as a queue of jobs. A job is due once
+//! `run_at` has passed; [`Queue::claim`] takes due jobs no other worker is claiming and pushes
+//! their `run_at` back by a visibility timeout, so a job whose worker died becomes due again
+//! instead of being lost. A worker finishing in time removes it or schedules a retry:
+//!
+//! ```rust,ignore
+//! #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+//! #[orso_table("emails")]
+//! struct EmailJob {
+//!     #[orso_column(primary_key)]
+//!     id: Option<String>,
+//!     to: String,
+//!     run_at: OrsoDateTime,
+//!     attempts: i32,
+//! }
+//!
+//! for job in Queue::<EmailJob>::claim(10, &db).await? {
+//!     let id = job.get_primary_key().unwrap();
+//!     match send(&job).await {
+//!         Ok(()) => Queue::<EmailJob>::complete(&id, &db).await?,
+//!         Err(_) => Queue::<EmailJob>::fail(&id, Duration::from_secs(60), &db).await?,
+//!     }
+//! }
+//! ```
+//!
+//! An `attempts` integer column, when the model has one, counts how often a job was claimed.
+//! Workers should finish well within the visibility timeout, or a job runs twice. Index
+//! `run_at` on busy queues.
+
+use crate::{Error, Executor, Orso, Result};
+use std::marker::PhantomData;
+use std::time::Duration;
+use tracing::{debug, instrument};
+
+/// Time a claimed job stays hidden from other workers by default
+pub const DEFAULT_VISIBILITY_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Column holding when a job is due
+pub const RUN_AT: &str = "run_at";
+/// Optional column counting claims
+pub const ATTEMPTS: &str = "attempts";
+
+/// Queue operations on the jobs stored by `T`
+pub struct Queue<T>(PhantomData<T>);
+
+impl<T: Orso> Queue<T> {
+    /// Claim up to `limit` due jobs for [`DEFAULT_VISIBILITY_TIMEOUT`]
+    pub async fn claim(limit: u32, db: &impl Executor) -> Result<Vec<T>> {
+        Self::claim_for(limit, DEFAULT_VISIBILITY_TIMEOUT, db).await
+    }
+
+    /// Claim up to `limit` due jobs, earliest first, hiding them for `visibility_timeout`
+    #[instrument(name = "orso.queue.claim", skip_all, fields(table = T::table_name(), limit))]
+    pub async fn claim_for(
+        limit: u32,
+        visibility_timeout: Duration,
+        db: &impl Executor,
+    ) -> Result<Vec<T>> {
+        let table = queue_table::<T>("claim")?;
+        let pk = T::primary_key_field();
+        let attempts = if T::field_names().contains(&ATTEMPTS) {
+            format!(", {ATTEMPTS} = {ATTEMPTS} + 1")
+        } else {
+            String::new()
+        };
+
+        let columns = T::columns();
+        let selected = crate::lazy::select_columns::<T>();
+        let selected = if selected == ["*"] {
+            &columns
+        } else {
+            &selected
+        };
+
+        // RETURNING has no order, so the claimed rows are sorted by when they were due
+        let sql = format!(
+            "WITH due AS (\
+             SELECT {pk}, {RUN_AT} AS __due FROM {table} WHERE {RUN_AT} <= NOW() \
+             ORDER BY {RUN_AT}, {pk} LIMIT {limit} FOR UPDATE SKIP LOCKED\
+             ), claimed AS (\
+             UPDATE {table} SET {RUN_AT} = NOW() + {}{attempts} FROM due \
+             WHERE {table}.{pk} = due.{pk} RETURNING {table}.*, due.__due\
+             ) SELECT {} FROM claimed ORDER BY __due, {pk}",
+            interval(visibility_timeout),
+            selected.join(", "),
+        );
+
+        let rows = db.query_rows(&sql, &[]).await?;
+        debug!(table, claimed = rows.len(), "Claimed jobs");
+        rows.into_iter()
+            .map(|mut map| {
+                crate::lazy::attach_sources::<T>(&mut map, table);
+                crate::decode::decode_record(db, map)
+            })
+            .collect()
+    }
+
+    /// Remove a finished job
+    #[instrument(name = "orso.queue.complete", skip_all, fields(table = T::table_name()))]
+    pub async fn complete(id: &str, db: &impl Executor) -> Result<()> {
+        let table = queue_table::<T>("complete")?;
+        let sql = format!("DELETE FROM {table} WHERE {} = $1", T::primary_key_field());
+        let key = crate::operations::key_param::<T>(id)?;
+        match db.execute(&sql, &[key.as_ref()]).await? {
+            0 => Err(Error::not_found_record("Job no longer exists", table, id)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Make a failed job due again after `retry_in`
+    #[instrument(name = "orso.queue.fail", skip_all, fields(table = T::table_name()))]
+    pub async fn fail(id: &str, retry_in: Duration, db: &impl Executor) -> Result<()> {
+        let table = queue_table::<T>("fail")?;
+        let sql = format!(
+            "UPDATE {table} SET {RUN_AT} = NOW() + {} WHERE {} = $1",
+            interval(retry_in),
+            T::primary_key_field()
+        );
+        let key = crate::operations::key_param::<T>(id)?;
+        match db.execute(&sql, &[key.as_ref()]).await? {
+            0 => Err(Error::not_found_record("Job no longer exists", table, id)),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// The model's table, if it can be used as a queue
+fn queue_table<T: Orso>(operation: &str) -> Result<&'static str> {
+    let table = T::table_name();
+    if !T::field_names().contains(&RUN_AT) {
+        return Err(Error::operation(
+            format!("{table} has no `{RUN_AT}` column to queue jobs by"),
+            operation,
+            Some(table.to_string()),
+        ));
+    }
+    Ok(table)
+}
+
+pub(crate) fn interval(duration: Duration) -> String {
+    format!("interval '{} milliseconds'", duration.as_millis())
+}
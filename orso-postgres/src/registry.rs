@@ -0,0 +1,87 @@
+//! Named databases
+//!
+//! A [`DatabaseRegistry`] holds the databases an application talks to under names such as
+//! `primary`, `analytics` or `legacy`. Models declare where they live with
+//! `#[orso_table("events", connection = "analytics")]`, and [`DatabaseRegistry::for_model`]
+//! resolves that name; models without one use the default database:
+//!
+//! ```rust,ignore
+//! let registry = DatabaseRegistry::new()
+//!     .with_database("primary", Database::init(primary_config).await?)
+//!     .with_database("analytics", Database::init(analytics_config).await?);
+//!
+//! event.insert(registry.for_model::<Event>()?).await?; // analytics
+//! user.insert(registry.for_model::<User>()?).await?;   // primary
+//!
+//! // Any call can still name its database explicitly
+//! let old = Event::find_all(registry.get("legacy")?).await?;
+//! ```
+
+use crate::{Database, Error, Orso, Result};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Name of the database models without a `connection` use, unless changed with
+/// [`DatabaseRegistry::with_default`]
+pub const DEFAULT_CONNECTION: &str = "primary";
+
+/// Databases by name, cheap to clone and share between tasks
+#[derive(Debug, Clone)]
+pub struct DatabaseRegistry {
+    databases: HashMap<String, Arc<Database>>,
+    default: String,
+}
+
+impl Default for DatabaseRegistry {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl DatabaseRegistry {
+    pub fn new() -> Self {
+        Self {
+            databases: HashMap::new(),
+            default: DEFAULT_CONNECTION.to_string(),
+        }
+    }
+
+    /// Register `db` under `name`, replacing any database registered under it before
+    pub fn with_database(mut self, name: impl Into<String>, db: Database) -> Self {
+        self.insert(name, db);
+        self
+    }
+
+    /// Use the database registered as `name` for models without a `connection`
+    pub fn with_default(mut self, name: impl Into<String>) -> Self {
+        self.default = name.into();
+        self
+    }
+
+    pub fn insert(&mut self, name: impl Into<String>, db: Database) {
+        self.databases.insert(name.into(), Arc::new(db));
+    }
+
+    /// The database registered as `name`
+    pub fn get(&self, name: &str) -> Result<&Database> {
+        self.databases
+            .get(name)
+            .map(|db| db.as_ref())
+            .ok_or_else(|| Error::connection(format!("No database registered as '{name}'")))
+    }
+
+    /// The database models without a `connection` use
+    pub fn default_database(&self) -> Result<&Database> {
+        self.get(&self.default)
+    }
+
+    /// The database `T` is bound to, or the default one
+    pub fn for_model<T: Orso>(&self) -> Result<&Database> {
+        self.get(T::connection().unwrap_or(&self.default))
+    }
+
+    /// Registered names, in no particular order
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.databases.keys().map(String::as_str)
+    }
+}
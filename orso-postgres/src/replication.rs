@@ -0,0 +1,324 @@
+//! Logical replication connections
+//!
+//! tokio-postgres can't open a connection in replication mode nor run the copy-both exchange
+//! `START_REPLICATION` begins, so this speaks just enough of the protocol itself: log in with
+//! `replication=database`, start streaming a slot and answer with standby status updates.
+//! Hosts, TLS, credentials and the connect timeout come from the same config as the pool.
+
+use crate::cdc::{Lsn, Message, POSTGRES_EPOCH_MICROS};
+use crate::credentials::Connector;
+use crate::tls::{Io, Tls};
+use crate::{Error, Result};
+use bytes::{BufMut, Bytes, BytesMut};
+use postgres_protocol::authentication::md5_hash;
+use postgres_protocol::authentication::sasl::{ChannelBinding, ScramSha256, SCRAM_SHA_256};
+use postgres_protocol::message::frontend;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio_postgres::config::{Host, TargetSessionAttrs};
+use tracing::debug;
+
+/// A message of the replication stream
+pub(crate) enum ReplicationMessage {
+    /// Output of the slot's plugin for the change at `start`
+    XLogData { start: Lsn, data: Bytes },
+    /// The server has sent everything up to `end`; `reply` asks for a status update now
+    Keepalive { end: Lsn, reply: bool },
+}
+
+/// A connection in replication mode
+pub(crate) struct ReplicationStream {
+    io: Box<dyn Io>,
+    /// Bytes read past the last whole message
+    read: BytesMut,
+}
+
+impl ReplicationStream {
+    /// Connect to the first host of `pg_config` that accepts the session, as the pool would
+    pub(crate) async fn connect(
+        connector: &Connector,
+        pg_config: tokio_postgres::Config,
+    ) -> Result<Self> {
+        let pg_config = connector.authenticate(pg_config).await;
+        let ports = pg_config.get_ports();
+        let mut last_error = None;
+        for (i, host) in pg_config.get_hosts().iter().enumerate() {
+            let port = ports.get(i).or(ports.first()).copied().unwrap_or(5432);
+            let attempt = Self::connect_host(&connector.tls, &pg_config, host, port);
+            let result = match pg_config.get_connect_timeout() {
+                Some(timeout) => tokio::time::timeout(*timeout, attempt)
+                    .await
+                    .unwrap_or_else(|_| Err(Error::connection("Timed out connecting"))),
+                None => attempt.await,
+            };
+            match result {
+                Ok(stream) => return Ok(stream),
+                Err(e) => {
+                    debug!(host = ?host, port, error = %e, "Replication connection failed");
+                    last_error = Some(e);
+                }
+            }
+        }
+        Err(last_error.unwrap_or_else(|| Error::connection("No host to connect to")))
+    }
+
+    async fn connect_host(
+        tls: &Tls,
+        pg_config: &tokio_postgres::Config,
+        host: &Host,
+        port: u16,
+    ) -> Result<Self> {
+        let io: Box<dyn Io> = match host {
+            Host::Tcp(name) => {
+                let tcp = TcpStream::connect((name.as_str(), port)).await?;
+                tcp.set_nodelay(true)?;
+                tls.negotiate(name, tcp, pg_config.get_ssl_mode()).await?
+            }
+            #[cfg(unix)]
+            Host::Unix(dir) => {
+                let socket = dir.join(format!(".s.PGSQL.{port}"));
+                Box::new(tokio::net::UnixStream::connect(socket).await?)
+            }
+        };
+        let mut stream = Self {
+            io,
+            read: BytesMut::new(),
+        };
+        stream.startup(pg_config).await?;
+        if pg_config.get_target_session_attrs() == TargetSessionAttrs::ReadWrite
+            && stream.show("transaction_read_only").await? == "on"
+        {
+            return Err(Error::connection("Server only accepts reads"));
+        }
+        Ok(stream)
+    }
+
+    /// Log in, answering whichever password exchange the server asks for
+    async fn startup(&mut self, pg_config: &tokio_postgres::Config) -> Result<()> {
+        let user = pg_config
+            .get_user()
+            .ok_or_else(|| Error::connection("Replication connection needs a user"))?;
+        let mut parameters = vec![
+            ("client_encoding", "UTF8"),
+            ("user", user),
+            ("database", pg_config.get_dbname().unwrap_or(user)),
+            ("replication", "database"),
+        ];
+        if let Some(options) = pg_config.get_options() {
+            parameters.push(("options", options));
+        }
+        if let Some(name) = pg_config.get_application_name() {
+            parameters.push(("application_name", name));
+        }
+        let mut buf = BytesMut::new();
+        frontend::startup_message(parameters, &mut buf)?;
+        self.send(&buf).await?;
+
+        let password = || {
+            pg_config
+                .get_password()
+                .ok_or_else(|| Error::connection("Server asked for a password but none is set"))
+        };
+        let mut scram = None;
+        loop {
+            let (tag, body) = self.receive().await?;
+            let mut msg = Message::new(&body);
+            let mut buf = BytesMut::new();
+            match tag {
+                b'R' => match msg.i32()? {
+                    0 => continue,
+                    3 => frontend::password_message(password()?, &mut buf)?,
+                    5 => {
+                        let salt = msg.take(4)?.try_into().unwrap();
+                        let hash = md5_hash(user.as_bytes(), password()?, salt);
+                        frontend::password_message(hash.as_bytes(), &mut buf)?;
+                    }
+                    10 => {
+                        let mut mechanisms = Vec::new();
+                        loop {
+                            match msg.str()? {
+                                name if name.is_empty() => break,
+                                name => mechanisms.push(name),
+                            }
+                        }
+                        if !mechanisms.iter().any(|name| name == SCRAM_SHA_256) {
+                            return Err(Error::connection(format!(
+                                "Unsupported authentication mechanisms {mechanisms:?}"
+                            )));
+                        }
+                        let state = ScramSha256::new(password()?, ChannelBinding::unsupported());
+                        frontend::sasl_initial_response(SCRAM_SHA_256, state.message(), &mut buf)?;
+                        scram = Some(state);
+                    }
+                    11 => {
+                        let state = scram.as_mut().ok_or_else(unexpected)?;
+                        state.update(msg.rest())?;
+                        frontend::sasl_response(state.message(), &mut buf)?;
+                    }
+                    12 => {
+                        let state = scram.as_mut().ok_or_else(unexpected)?;
+                        state.finish(msg.rest())?;
+                        continue;
+                    }
+                    method => {
+                        return Err(Error::connection(format!(
+                            "Unsupported authentication method {method}"
+                        )))
+                    }
+                },
+                b'E' => return Err(server_error(&body)),
+                b'Z' => return Ok(()),
+                // Parameter status, backend key data and notices
+                _ => continue,
+            }
+            self.send(&buf).await?;
+        }
+    }
+
+    /// The value of the setting `name`
+    async fn show(&mut self, name: &str) -> Result<String> {
+        let mut buf = BytesMut::new();
+        frontend::query(&format!("SHOW {name}"), &mut buf)?;
+        self.send(&buf).await?;
+        let mut value = None;
+        loop {
+            let (tag, body) = self.receive().await?;
+            match tag {
+                b'D' => {
+                    let mut msg = Message::new(&body);
+                    msg.i16()?;
+                    let length = msg.i32()?;
+                    if length >= 0 {
+                        let text = msg.take(length as usize)?;
+                        value = Some(String::from_utf8_lossy(text).into_owned());
+                    }
+                }
+                b'E' => return Err(server_error(&body)),
+                b'Z' => return value.ok_or_else(unexpected),
+                _ => {}
+            }
+        }
+    }
+
+    /// Stream the changes of `slot` decoded by `pgoutput` for the publication of the same name,
+    /// from its confirmed position on
+    pub(crate) async fn start(&mut self, slot: &str) -> Result<()> {
+        let mut buf = BytesMut::new();
+        frontend::query(
+            &format!(
+                "START_REPLICATION SLOT {slot} LOGICAL 0/0 \
+                 (proto_version '1', publication_names '{slot}')"
+            ),
+            &mut buf,
+        )?;
+        self.send(&buf).await?;
+        loop {
+            let (tag, body) = self.receive().await?;
+            match tag {
+                // CopyBothResponse
+                b'W' => return Ok(()),
+                b'E' => return Err(server_error(&body)),
+                _ => {}
+            }
+        }
+    }
+
+    /// The next message of a started stream
+    ///
+    /// Cancel safe: a message is either returned whole or stays buffered.
+    pub(crate) async fn next(&mut self) -> Result<ReplicationMessage> {
+        loop {
+            let (tag, body) = self.receive().await?;
+            match tag {
+                b'd' => {
+                    let mut msg = Message::new(&body);
+                    match msg.u8()? {
+                        b'w' => {
+                            let start = Lsn(msg.i64()? as u64);
+                            msg.i64()?; // end of the server's WAL
+                            msg.i64()?; // send time
+                            let offset = body.len() - msg.rest().len();
+                            let data = body.slice(offset..);
+                            return Ok(ReplicationMessage::XLogData { start, data });
+                        }
+                        b'k' => {
+                            let end = Lsn(msg.i64()? as u64);
+                            msg.i64()?; // send time
+                            let reply = msg.u8()? == 1;
+                            return Ok(ReplicationMessage::Keepalive { end, reply });
+                        }
+                        _ => continue,
+                    }
+                }
+                b'E' => return Err(server_error(&body)),
+                b'c' => return Err(Error::connection("Server ended replication")),
+                // Notices
+                _ => continue,
+            }
+        }
+    }
+
+    /// Tell the server how far changes have been received and handled; the slot keeps what
+    /// comes after `flushed`
+    pub(crate) async fn send_status(&mut self, received: Lsn, flushed: Lsn) -> Result<()> {
+        let now = chrono::Utc::now().timestamp_micros() - POSTGRES_EPOCH_MICROS;
+        let mut status = BytesMut::with_capacity(34);
+        status.put_u8(b'r');
+        status.put_u64(received.max(flushed).0);
+        status.put_u64(flushed.0);
+        status.put_u64(flushed.0);
+        status.put_i64(now);
+        status.put_u8(0);
+        let mut buf = BytesMut::new();
+        frontend::CopyData::new(&status[..])?.write(&mut buf);
+        self.send(&buf).await
+    }
+
+    async fn send(&mut self, buf: &[u8]) -> Result<()> {
+        self.io.write_all(buf).await?;
+        self.io.flush().await?;
+        Ok(())
+    }
+
+    /// Read one message, its tag and body
+    async fn receive(&mut self) -> Result<(u8, Bytes)> {
+        loop {
+            if self.read.len() >= 5 {
+                let length = i32::from_be_bytes(self.read[1..5].try_into().unwrap());
+                if length < 4 {
+                    return Err(Error::serialization("Invalid message length from server"));
+                }
+                let total = 1 + length as usize;
+                if self.read.len() >= total {
+                    let mut message = self.read.split_to(total).freeze();
+                    let tag = message[0];
+                    return Ok((tag, message.split_off(5)));
+                }
+                self.read.reserve(total - self.read.len());
+            }
+            if self.io.read_buf(&mut self.read).await? == 0 {
+                return Err(Error::connection("Replication connection closed"));
+            }
+        }
+    }
+}
+
+fn unexpected() -> Error {
+    Error::connection("Unexpected message from server")
+}
+
+/// The error of an ErrorResponse body, with its SQLSTATE
+fn server_error(body: &[u8]) -> Error {
+    let mut code = None;
+    let mut message = String::new();
+    let mut msg = Message::new(body);
+    while let Ok(field) = msg.u8() {
+        let Ok(value) = msg.str() else { break };
+        match field {
+            b'C' => code = Some(value),
+            b'M' => message = value,
+            _ => {}
+        }
+    }
+    Error::postgres(message, code)
+}
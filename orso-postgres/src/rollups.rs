@@ -0,0 +1,278 @@
+//! Rollup tables maintained from a source model
+//!
+//! A [`Rollup`] declares how rows of a source model aggregate into rows of a target model:
+//! the target columns keying each group, and the aggregate filling every other column. Refreshing
+//! recomputes the groups that received rows since a point in time and upserts them, e.g. one
+//! minute candles from a tick table:
+//!
+//! ```rust,ignore
+//! let candles = Rollup::<Tick, Candle>::new("traded_at")
+//!     .group_by("symbol", "symbol")
+//!     .bucket("bucket", Duration::from_secs(60))
+//!     .first("open", "price")
+//!     .aggregate("high", Aggregate::Max, "price")
+//!     .aggregate("low", Aggregate::Min, "price")
+//!     .last("close", "price")
+//!     .aggregate("volume", Aggregate::Sum, "size");
+//!
+//! candles.install(&db).await?;
+//! candles.refresh_incremental(&db, last_refresh).await?;
+//! ```
+//!
+//! Groups are always recomputed from all of their source rows, so a refresh can be repeated or
+//! overlap the previous one. With a [`bucket`](Rollup::bucket), `since` is rounded down to its
+//! bucket and only rows from there on are read; without one, every source row of the touched
+//! groups is.
+
+use crate::{Aggregate, Error, Executor, Orso, OrsoDateTime, Result};
+use std::marker::PhantomData;
+use std::time::Duration;
+use tracing::{debug, instrument};
+
+/// Aggregation of the rows of `S` into rows of `T`
+pub struct Rollup<S, T> {
+    time_column: String,
+    groups: Vec<(String, String)>,
+    bucket: Option<(String, Duration)>,
+    aggregates: Vec<(String, String)>,
+    _models: PhantomData<fn() -> (S, T)>,
+}
+
+impl<S, T> Clone for Rollup<S, T> {
+    fn clone(&self) -> Self {
+        Self {
+            time_column: self.time_column.clone(),
+            groups: self.groups.clone(),
+            bucket: self.bucket.clone(),
+            aggregates: self.aggregates.clone(),
+            _models: PhantomData,
+        }
+    }
+}
+
+impl<S, T> std::fmt::Debug for Rollup<S, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Rollup")
+            .field("time_column", &self.time_column)
+            .field("groups", &self.groups)
+            .field("bucket", &self.bucket)
+            .field("aggregates", &self.aggregates)
+            .finish()
+    }
+}
+
+impl<S: Orso, T: Orso> Rollup<S, T> {
+    /// A rollup whose incremental refreshes look at the source timestamp `time_column`
+    pub fn new(time_column: impl Into<String>) -> Self {
+        Self {
+            time_column: time_column.into(),
+            groups: Vec::new(),
+            bucket: None,
+            aggregates: Vec::new(),
+            _models: PhantomData,
+        }
+    }
+
+    /// Key target `column` by the source expression `expr`
+    pub fn group_by(mut self, column: impl Into<String>, expr: impl Into<String>) -> Self {
+        self.groups.push((column.into(), expr.into()));
+        self
+    }
+
+    /// Key target `column` by the start of the `width` long interval the source time falls in
+    pub fn bucket(mut self, column: impl Into<String>, width: Duration) -> Self {
+        self.bucket = Some((column.into(), width));
+        self
+    }
+
+    /// Fill target `column` with `aggregate` over the source column `source`
+    pub fn aggregate(
+        self,
+        column: impl Into<String>,
+        aggregate: Aggregate,
+        source: impl Into<String>,
+    ) -> Self {
+        let expr = format!("{}({})", aggregate, source.into());
+        self.aggregate_expr(column, expr)
+    }
+
+    /// Fill target `column` with an aggregate SQL expression over the source columns
+    pub fn aggregate_expr(mut self, column: impl Into<String>, expr: impl Into<String>) -> Self {
+        self.aggregates.push((column.into(), expr.into()));
+        self
+    }
+
+    /// Fill target `column` with the earliest value of `source` in the group
+    pub fn first(self, column: impl Into<String>, source: impl Into<String>) -> Self {
+        let expr = format!(
+            "(array_agg({} ORDER BY {}))[1]",
+            source.into(),
+            self.time_column
+        );
+        self.aggregate_expr(column, expr)
+    }
+
+    /// Fill target `column` with the latest value of `source` in the group
+    pub fn last(self, column: impl Into<String>, source: impl Into<String>) -> Self {
+        let expr = format!(
+            "(array_agg({} ORDER BY {} DESC))[1]",
+            source.into(),
+            self.time_column
+        );
+        self.aggregate_expr(column, expr)
+    }
+
+    /// Target columns keying a group, in order
+    fn key_columns(&self) -> Vec<&str> {
+        let mut columns: Vec<&str> = self.groups.iter().map(|(c, _)| c.as_str()).collect();
+        if let Some((column, _)) = &self.bucket {
+            columns.push(column);
+        }
+        columns
+    }
+
+    /// Source expressions of the group keys, matching `key_columns`
+    fn key_exprs(&self) -> Vec<String> {
+        let mut exprs: Vec<String> = self.groups.iter().map(|(_, e)| e.clone()).collect();
+        if let Some((_, width)) = &self.bucket {
+            exprs.push(bucket_sql(&self.time_column, *width));
+        }
+        exprs
+    }
+
+    fn validate(&self) -> Result<()> {
+        let keys = self.key_columns();
+        if keys.is_empty() {
+            return Err(Error::validation(
+                "A rollup needs at least one group column",
+            ));
+        }
+        if self.aggregates.is_empty() {
+            return Err(Error::validation("A rollup needs at least one aggregate"));
+        }
+        if let Some((_, width)) = &self.bucket {
+            if width.as_millis() == 0 {
+                return Err(Error::validation(
+                    "Rollup buckets must be at least a millisecond",
+                ));
+            }
+        }
+        let target = T::columns();
+        let columns = keys
+            .into_iter()
+            .chain(self.aggregates.iter().map(|(c, _)| c.as_str()));
+        for column in columns {
+            if !target.contains(&column) {
+                return Err(Error::validation_field(
+                    format!("{} has no column {column}", T::table_name()),
+                    column,
+                    None,
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// Name of the unique index on the group columns
+    pub fn key_index_name() -> String {
+        format!("{}_rollup_key", T::table_name())
+    }
+
+    /// The unique index on the target's group columns that refreshes upsert on
+    pub fn install_sql(&self) -> Result<String> {
+        self.validate()?;
+        Ok(format!(
+            "CREATE UNIQUE INDEX IF NOT EXISTS {} ON {} ({})",
+            Self::key_index_name(),
+            T::table_name(),
+            self.key_columns().join(", ")
+        ))
+    }
+
+    /// Create the unique index on the target's group columns
+    pub async fn install(&self, db: &impl Executor) -> Result<()> {
+        db.execute(&self.install_sql()?, &[]).await?;
+        Ok(())
+    }
+
+    /// The upsert recomputing groups, with `$1` as the point in time when `incremental`
+    pub fn refresh_sql(&self, incremental: bool) -> Result<String> {
+        self.validate()?;
+        let key_columns = self.key_columns();
+        let key_exprs = self.key_exprs().join(", ");
+        let target_columns: Vec<&str> = key_columns
+            .iter()
+            .copied()
+            .chain(self.aggregates.iter().map(|(c, _)| c.as_str()))
+            .collect();
+        let aggregates: Vec<&str> = self.aggregates.iter().map(|(_, e)| e.as_str()).collect();
+
+        let source = S::table_name();
+        let time = &self.time_column;
+        let filter = match (&self.bucket, incremental) {
+            (_, false) => String::new(),
+            (Some((_, width)), true) => {
+                format!(" WHERE {time} >= {}", bucket_sql("$1::timestamptz", *width))
+            }
+            (None, true) => format!(
+                " WHERE ({key_exprs}) IN (SELECT {key_exprs} FROM {source} WHERE {time} >= $1)"
+            ),
+        };
+
+        let mut updates: Vec<String> = self
+            .aggregates
+            .iter()
+            .map(|(c, _)| format!("{c} = EXCLUDED.{c}"))
+            .collect();
+        if let Some(updated_at) = T::updated_at_field().filter(|_| !T::updated_at_trigger()) {
+            let now = crate::types::now_sql::<T>(updated_at);
+            updates.push(format!("{updated_at} = {now}"));
+        }
+
+        Ok(format!(
+            "INSERT INTO {} ({}) SELECT {key_exprs}, {} FROM {source}{filter} GROUP BY {key_exprs} \
+             ON CONFLICT ({}) DO UPDATE SET {}",
+            T::table_name(),
+            target_columns.join(", "),
+            aggregates.join(", "),
+            key_columns.join(", "),
+            updates.join(", ")
+        ))
+    }
+
+    /// Recompute the groups with source rows at or after `since`, returning the rows upserted
+    #[instrument(
+        name = "orso.rollup.refresh",
+        skip_all,
+        fields(source = S::table_name(), target = T::table_name())
+    )]
+    pub async fn refresh_incremental(
+        &self,
+        db: &impl Executor,
+        since: OrsoDateTime,
+    ) -> Result<u64> {
+        let sql = self.refresh_sql(true)?;
+        let rows = db.execute(&sql, &[&since]).await?;
+        debug!(rows, "Refreshed rollup");
+        Ok(rows)
+    }
+
+    /// Recompute every group, returning the rows upserted
+    #[instrument(
+        name = "orso.rollup.refresh",
+        skip_all,
+        fields(source = S::table_name(), target = T::table_name())
+    )]
+    pub async fn refresh_all(&self, db: &impl Executor) -> Result<u64> {
+        let sql = self.refresh_sql(false)?;
+        let rows = db.execute(&sql, &[]).await?;
+        debug!(rows, "Refreshed rollup");
+        Ok(rows)
+    }
+}
+
+/// Start of the `width` long interval since the epoch that the timestamp `expr` falls in
+fn bucket_sql(expr: &str, width: Duration) -> String {
+    let seconds = width.as_millis() as f64 / 1000.0;
+    format!("to_timestamp(floor(extract(epoch FROM {expr}) / {seconds}) * {seconds})")
+}
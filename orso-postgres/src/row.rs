@@ -0,0 +1,121 @@
+//! Typed rows of arbitrary queries
+//!
+//! `#[derive(OrsoRow)]` maps the result of any `SELECT` — joins, aggregates, views — into a
+//! struct, without the table, migration and write methods of a full [`Orso`](crate::Orso)
+//! model. Fields are matched to result columns by name; extra columns are ignored:
+//!
+//! ```rust,ignore
+//! #[derive(OrsoRow, Serialize, Deserialize, Debug)]
+//! struct AuthorStats {
+//!     name: String,
+//!     posts: i64,
+//!     last_post_at: Option<OrsoDateTime>,
+//! }
+//!
+//! let stats = AuthorStats::fetch_all(
+//!     "SELECT u.name, COUNT(p.id) AS posts, MAX(p.created_at) AS last_post_at \
+//!      FROM users u JOIN posts p ON p.author_id = u.id GROUP BY u.name",
+//!     &[],
+//!     &db,
+//! )
+//! .await?;
+//!
+//! // Or from a query builder
+//! let stats: Vec<AuthorStats> = query.execute_as(&db).await?;
+//! ```
+
+use crate::{Error, Executor, FieldType, Result, RowMap, Value};
+use serde::de::DeserializeOwned;
+use tokio_postgres::types::ToSql;
+
+/// A struct built from the columns of a query result
+#[allow(async_fn_in_trait)]
+pub trait OrsoRow: DeserializeOwned {
+    fn field_names() -> Vec<&'static str>;
+    fn field_types() -> Vec<FieldType>;
+    fn field_nullable() -> Vec<bool>;
+
+    fn from_map(map: RowMap) -> Result<Self> {
+        from_row_map(map, &Self::field_names(), &Self::field_types())
+    }
+
+    /// Run `sql` and map every row
+    async fn fetch_all(
+        sql: &str,
+        params: &[&(dyn ToSql + Send + Sync)],
+        db: &impl Executor,
+    ) -> Result<Vec<Self>> {
+        db.query_rows(sql, params)
+            .await?
+            .into_iter()
+            .map(Self::from_map)
+            .collect()
+    }
+
+    /// Run `sql` and map the first row, if any
+    async fn fetch_optional(
+        sql: &str,
+        params: &[&(dyn ToSql + Send + Sync)],
+        db: &impl Executor,
+    ) -> Result<Option<Self>> {
+        db.query_rows(sql, params)
+            .await?
+            .into_iter()
+            .next()
+            .map(Self::from_map)
+            .transpose()
+    }
+
+    /// Run `sql` and map the first row, failing if there is none
+    async fn fetch_one(
+        sql: &str,
+        params: &[&(dyn ToSql + Send + Sync)],
+        db: &impl Executor,
+    ) -> Result<Self> {
+        Self::fetch_optional(sql, params, db)
+            .await?
+            .ok_or_else(|| Error::query("Query returned no rows"))
+    }
+}
+
+/// Deserialize a row into `T`, converting values by the declared field types
+pub fn from_row_map<T: DeserializeOwned>(
+    map: RowMap,
+    field_names: &[&str],
+    field_types: &[FieldType],
+) -> Result<T> {
+    let json: serde_json::Map<String, serde_json::Value> = map
+        .into_iter()
+        .map(|(column, value)| {
+            let field_type = field_names
+                .iter()
+                .position(|name| *name == column)
+                .and_then(|pos| field_types.get(pos));
+            let json = value_to_json(value, field_type);
+            (column, json)
+        })
+        .collect();
+    serde_json::from_value(serde_json::Value::Object(json))
+        .map_err(|e| Error::serialization(e.to_string()))
+}
+
+pub(crate) fn value_to_json(value: Value, field_type: Option<&FieldType>) -> serde_json::Value {
+    use serde_json::Value as Json;
+
+    let float = |f: f64| serde_json::Number::from_f64(f).map_or(Json::Null, Json::Number);
+    match value {
+        Value::Null => Json::Null,
+        Value::Integer(i) if matches!(field_type, Some(FieldType::Boolean)) => Json::Bool(i != 0),
+        Value::Integer(i) => Json::from(i),
+        Value::Real(f) => float(f),
+        Value::Text(s) => Json::String(s),
+        Value::Blob(b) => Json::from(b),
+        Value::Boolean(b) => Json::Bool(b),
+        Value::DateTime(dt) => serde_json::to_value(dt).unwrap_or(Json::Null),
+        Value::IntegerArray(arr) => Json::from(arr),
+        Value::BigIntArray(arr) => Json::from(arr),
+        Value::NumericArray(arr) => Json::Array(arr.into_iter().map(float).collect()),
+        Value::Vector(v) => Json::Array(v.into_iter().map(|f| float(f as f64)).collect()),
+        Value::Json(v) => v,
+    }
+}
@@ -0,0 +1,206 @@
+//! Schema introspection
+//!
+//! [`Schema`] reads the structure of existing tables in the current schema: the same column
+//! information the migrator compares models against, plus indexes and constraints.
+//!
+//! ```rust,ignore
+//! let users = Schema::describe(&db, "users").await?;
+//! for column in &users.columns {
+//!     println!("{} {} nullable={}", column.name, column.sql_type, column.nullable);
+//! }
+//! assert!(users.index("idx_users_email").is_some_and(|i| i.unique));
+//! ```
+
+use crate::migrations::{check_table_exists, get_current_table_schema, ColumnInfo};
+use crate::{Database, Error, Result};
+use tracing::instrument;
+
+/// Structure of a table
+#[derive(Debug, Clone)]
+pub struct TableInfo {
+    pub name: String,
+    /// Columns in table order
+    pub columns: Vec<ColumnInfo>,
+    pub indexes: Vec<IndexInfo>,
+    pub constraints: Vec<ConstraintInfo>,
+}
+
+impl TableInfo {
+    pub fn column(&self, name: &str) -> Option<&ColumnInfo> {
+        self.columns.iter().find(|c| c.name == name)
+    }
+
+    pub fn index(&self, name: &str) -> Option<&IndexInfo> {
+        self.indexes.iter().find(|i| i.name == name)
+    }
+
+    pub fn constraint(&self, name: &str) -> Option<&ConstraintInfo> {
+        self.constraints.iter().find(|c| c.name == name)
+    }
+}
+
+/// An index on a table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IndexInfo {
+    pub name: String,
+    /// Indexed columns in key order; expressions are left out
+    pub columns: Vec<String>,
+    pub unique: bool,
+    pub primary: bool,
+    /// Index method, e.g. `btree` or `gin`
+    pub method: String,
+    /// `CREATE INDEX` statement as reported by `pg_get_indexdef`
+    pub definition: String,
+}
+
+/// Kind of a table constraint
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ConstraintKind {
+    PrimaryKey,
+    Unique,
+    ForeignKey,
+    Check,
+    Exclusion,
+    NotNull,
+    /// A kind this version doesn't know, with its `pg_constraint.contype` code
+    Other(String),
+}
+
+impl ConstraintKind {
+    fn from_code(code: &str) -> Self {
+        match code {
+            "p" => Self::PrimaryKey,
+            "u" => Self::Unique,
+            "f" => Self::ForeignKey,
+            "c" => Self::Check,
+            "x" => Self::Exclusion,
+            "n" => Self::NotNull,
+            other => Self::Other(other.to_string()),
+        }
+    }
+}
+
+/// A constraint on a table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstraintInfo {
+    pub name: String,
+    pub kind: ConstraintKind,
+    pub columns: Vec<String>,
+    /// Constraint clause as reported by `pg_get_constraintdef`, e.g. `CHECK ((age >= 0))`
+    pub definition: String,
+}
+
+/// Schema introspection
+pub struct Schema;
+
+impl Schema {
+    /// Describe the table `table_name` in the current schema
+    #[instrument(name = "orso.schema.describe", skip_all, fields(table = %table_name))]
+    pub async fn describe(db: &Database, table_name: &str) -> Result<TableInfo> {
+        if !check_table_exists(db, table_name).await? {
+            return Err(Error::Schema {
+                message: format!("Table {} does not exist", table_name),
+                table: Some(table_name.to_string()),
+                column: None,
+            });
+        }
+
+        Ok(TableInfo {
+            name: table_name.to_string(),
+            columns: get_current_table_schema(db, table_name).await?,
+            indexes: Self::indexes(db, table_name).await?,
+            constraints: Self::constraints(db, table_name).await?,
+        })
+    }
+
+    /// Describe every table in the current schema, ordered by name
+    #[instrument(name = "orso.schema.tables", skip_all)]
+    pub async fn tables(db: &Database) -> Result<Vec<TableInfo>> {
+        let rows = db
+            .query(
+                "SELECT table_name::text FROM information_schema.tables \
+                 WHERE table_schema = current_schema() AND table_type = 'BASE TABLE' \
+                 ORDER BY table_name",
+                &[],
+            )
+            .await?;
+
+        let mut tables = Vec::with_capacity(rows.len());
+        for row in rows {
+            let name: String = row.get(0);
+            tables.push(Self::describe(db, &name).await?);
+        }
+        Ok(tables)
+    }
+
+    async fn indexes(db: &Database, table_name: &str) -> Result<Vec<IndexInfo>> {
+        let query = "
+            SELECT
+                i.relname::text,
+                ix.indisunique,
+                ix.indisprimary,
+                am.amname::text,
+                pg_get_indexdef(ix.indexrelid),
+                ARRAY(
+                    SELECT a.attname::text
+                    FROM unnest(ix.indkey) WITH ORDINALITY AS k(attnum, ord)
+                    JOIN pg_attribute a ON a.attrelid = t.oid AND a.attnum = k.attnum
+                    ORDER BY k.ord
+                )
+            FROM pg_index ix
+            JOIN pg_class t ON t.oid = ix.indrelid
+            JOIN pg_class i ON i.oid = ix.indexrelid
+            JOIN pg_am am ON am.oid = i.relam
+            JOIN pg_namespace n ON n.oid = t.relnamespace
+            WHERE n.nspname = current_schema() AND t.relname = $1
+            ORDER BY i.relname
+        ";
+
+        let rows = db.query(query, &[&table_name]).await?;
+        Ok(rows
+            .iter()
+            .map(|row| IndexInfo {
+                name: row.get(0),
+                unique: row.get(1),
+                primary: row.get(2),
+                method: row.get(3),
+                definition: row.get(4),
+                columns: row.get(5),
+            })
+            .collect())
+    }
+
+    pub(crate) async fn constraints(
+        db: &Database,
+        table_name: &str,
+    ) -> Result<Vec<ConstraintInfo>> {
+        let query = "
+            SELECT
+                c.conname::text,
+                c.contype::text,
+                pg_get_constraintdef(c.oid),
+                ARRAY(
+                    SELECT a.attname::text
+                    FROM unnest(c.conkey) WITH ORDINALITY AS k(attnum, ord)
+                    JOIN pg_attribute a ON a.attrelid = c.conrelid AND a.attnum = k.attnum
+                    ORDER BY k.ord
+                )
+            FROM pg_constraint c
+            JOIN pg_class t ON t.oid = c.conrelid
+            JOIN pg_namespace n ON n.oid = t.relnamespace
+            WHERE n.nspname = current_schema() AND t.relname = $1
+            ORDER BY c.conname
+        ";
+
+        let rows = db.query(query, &[&table_name]).await?;
+        Ok(rows
+            .iter()
+            .map(|row| ConstraintInfo {
+                name: row.get(0),
+                kind: ConstraintKind::from_code(row.get(1)),
+                definition: row.get(2),
+                columns: row.get(3),
+            })
+            .collect())
+    }
+}
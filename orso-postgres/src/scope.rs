@@ -0,0 +1,50 @@
+//! Default scopes
+//!
+//! A model deriving `Orso` with `#[orso_default_scope]` implements [`DefaultScope`]; the
+//! returned filter is added to every finder (`find_*`, `count*`, `exists*`, `list*`, `query*`,
+//! `aggregate`). Writes and deletes by id or filter are not scoped.
+//!
+//! Code running inside [`unscoped`] sees all rows:
+//!
+//! ```rust,ignore
+//! let everything = orso_postgres::unscoped(Order::find_all(&db)).await?;
+//! ```
+
+use crate::{FilterOperator, QueryBuilder};
+use std::future::Future;
+
+tokio::task_local! {
+    static UNSCOPED: bool;
+}
+
+/// Default filter for a model, used with `#[orso_default_scope]`
+pub trait DefaultScope {
+    fn default_scope() -> Option<FilterOperator>;
+}
+
+/// Run `future` with default scopes disabled
+pub async fn unscoped<F: Future>(future: F) -> F::Output {
+    UNSCOPED.scope(true, future).await
+}
+
+/// Whether the current task is running inside [`unscoped`]
+pub fn is_unscoped() -> bool {
+    UNSCOPED.try_with(|unscoped| *unscoped).unwrap_or(false)
+}
+
+/// The default scope of `T` unless disabled for the current task
+pub fn active_scope<T: crate::Orso>() -> Option<FilterOperator> {
+    if is_unscoped() {
+        None
+    } else {
+        T::default_scope()
+    }
+}
+
+/// Add the active default scope of `T` to a query
+pub(crate) fn apply<T: crate::Orso>(builder: QueryBuilder) -> QueryBuilder {
+    match active_scope::<T>() {
+        Some(scope) => builder._where(scope),
+        None => builder,
+    }
+}
@@ -0,0 +1,139 @@
+//! Identity map for a unit of work
+//!
+//! A [`Session`] wraps an executor and remembers the rows `find_by_id` fetched through it, so
+//! looking the same record up again while handling a request (e.g. when resolving relations)
+//! is answered from memory:
+//!
+//! ```rust,ignore
+//! let session = Session::new(&db);
+//! let author = User::find_by_id(&post.author_id, &session).await?; // queries
+//! let again = User::find_by_id(&post.author_id, &session).await?;  // cached
+//!
+//! author.unwrap().update(&session).await?; // drops the cached `users` rows
+//! ```
+//!
+//! Statements other than `SELECT` run through the session evict the rows of the table they
+//! write. Writes made elsewhere are not seen, so keep a session to one request or job.
+
+use crate::{Executor, Result, RowMap};
+use std::collections::HashMap;
+use std::sync::Mutex;
+use tokio_postgres::types::ToSql;
+
+/// Rows cached by table and primary key
+#[derive(Debug, Default)]
+pub struct IdentityMap {
+    rows: Mutex<HashMap<(String, String), RowMap>>,
+}
+
+impl IdentityMap {
+    pub fn get(&self, table: &str, id: &str) -> Option<RowMap> {
+        let rows = self.rows.lock().unwrap_or_else(|e| e.into_inner());
+        rows.get(&(table.to_string(), id.to_string())).cloned()
+    }
+
+    pub fn insert(&self, table: &str, id: &str, row: RowMap) {
+        let mut rows = self.rows.lock().unwrap_or_else(|e| e.into_inner());
+        rows.insert((table.to_string(), id.to_string()), row);
+    }
+
+    pub fn evict(&self, table: &str, id: &str) {
+        let mut rows = self.rows.lock().unwrap_or_else(|e| e.into_inner());
+        rows.remove(&(table.to_string(), id.to_string()));
+    }
+
+    /// Drop the cached rows of `table`
+    pub fn evict_table(&self, table: &str) {
+        let mut rows = self.rows.lock().unwrap_or_else(|e| e.into_inner());
+        rows.retain(|(cached, _), _| cached != table);
+    }
+
+    pub fn clear(&self) {
+        self.rows.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.rows.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+/// Executor caching the records found by primary key through it
+pub struct Session<E> {
+    db: E,
+    identity_map: IdentityMap,
+}
+
+impl<E: Executor> Session<E> {
+    pub fn new(db: E) -> Self {
+        Self {
+            db,
+            identity_map: IdentityMap::default(),
+        }
+    }
+
+    /// Forget every cached row
+    pub fn clear(&self) {
+        self.identity_map.clear();
+    }
+
+    /// Forget the cached row of one record
+    pub fn evict<T: crate::Orso>(&self, id: &str) {
+        self.identity_map.evict(T::table_name(), id);
+    }
+
+    /// Number of cached rows
+    pub fn cached(&self) -> usize {
+        self.identity_map.len()
+    }
+
+    pub fn into_inner(self) -> E {
+        self.db
+    }
+
+    /// Evict whatever a write statement may have changed
+    fn invalidate(&self, sql: &str) {
+        if crate::database::statement_kind(sql) == "select" {
+            return;
+        }
+        match crate::database::statement_table(sql) {
+            Some(table) => self.identity_map.evict_table(table),
+            None => self.identity_map.clear(),
+        }
+    }
+}
+
+impl<E: Executor> Executor for Session<E> {
+    async fn execute(&self, sql: &str, params: &[&(dyn ToSql + Send + Sync)]) -> Result<u64> {
+        self.invalidate(sql);
+        self.db.execute(sql, params).await
+    }
+
+    async fn query_rows(
+        &self,
+        sql: &str,
+        params: &[&(dyn ToSql + Send + Sync)],
+    ) -> Result<Vec<RowMap>> {
+        self.invalidate(sql);
+        self.db.query_rows(sql, params).await
+    }
+
+    fn identity_map(&self) -> Option<&IdentityMap> {
+        Some(&self.identity_map)
+    }
+
+    fn query_log(&self) -> Option<&crate::advisor::QueryLog> {
+        self.db.query_log()
+    }
+
+    fn strict_decoding(&self) -> bool {
+        self.db.strict_decoding()
+    }
+
+    fn keyring(&self) -> Option<&std::sync::Arc<crate::encryption::Keyring>> {
+        self.db.keyring()
+    }
+}
@@ -0,0 +1,131 @@
+//! Sharding by a model field
+//!
+//! A [`ShardRouter`] spreads the records of sharded models over several databases. Models name
+//! the field they are routed by with `#[orso_shard_key(field)]`; records with the same key
+//! always land on the same shard:
+//!
+//! ```rust,ignore
+//! #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+//! #[orso_table("messages")]
+//! #[orso_shard_key(user_id)]
+//! struct Message { /* ... */ }
+//!
+//! let router = ShardRouter::new(vec![shard_a, shard_b, shard_c]);
+//! message.insert(router.shard_for(&message)?).await?;
+//! let inbox = Message::find_where(by_user, router.shard_for_key(&user_id)).await?;
+//!
+//! // Scatter-gather over every shard
+//! let unread = Message::find_where_all_shards(unread_filter, &router).await?;
+//! ```
+//!
+//! Keys are hashed with FNV-1a by default, which is stable across processes and releases. Use
+//! [`ShardRouter::with_router`] to route through a lookup table or a different hash instead.
+//! Changing the number of shards moves keys between them, so plan for resharding up front.
+
+use crate::{Database, Error, FilterOperator, Orso, Result, Value};
+use std::sync::Arc;
+
+type RouteFn = dyn Fn(&str, usize) -> usize + Send + Sync;
+
+/// Databases holding the shards of sharded models
+#[derive(Clone)]
+pub struct ShardRouter {
+    shards: Vec<Arc<Database>>,
+    route: Arc<RouteFn>,
+}
+
+impl std::fmt::Debug for ShardRouter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ShardRouter")
+            .field("shards", &self.shards.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl ShardRouter {
+    /// Route keys over `shards` by hash
+    ///
+    /// # Panics
+    ///
+    /// Panics if `shards` is empty.
+    pub fn new(shards: Vec<Database>) -> Self {
+        assert!(!shards.is_empty(), "a ShardRouter needs at least one shard");
+        Self {
+            shards: shards.into_iter().map(Arc::new).collect(),
+            route: Arc::new(|key, shards| (fnv1a(key) % shards as u64) as usize),
+        }
+    }
+
+    /// Pick shards with `route`, which gets the key and the shard count and returns an index
+    ///
+    /// Indexes past the last shard wrap around.
+    pub fn with_router(
+        mut self,
+        route: impl Fn(&str, usize) -> usize + Send + Sync + 'static,
+    ) -> Self {
+        self.route = Arc::new(route);
+        self
+    }
+
+    pub fn shards(&self) -> impl Iterator<Item = &Database> {
+        self.shards.iter().map(|db| db.as_ref())
+    }
+
+    pub fn len(&self) -> usize {
+        self.shards.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shards.is_empty()
+    }
+
+    /// Index of the shard holding records with shard key `key`
+    pub fn shard_index(&self, key: &str) -> usize {
+        (self.route)(key, self.shards.len()) % self.shards.len()
+    }
+
+    /// The shard holding records with shard key `key`
+    pub fn shard_for_key(&self, key: &str) -> &Database {
+        &self.shards[self.shard_index(key)]
+    }
+
+    /// The shard `record` belongs on, from its `#[orso_shard_key]` field
+    pub fn shard_for<T: Orso>(&self, record: &T) -> Result<&Database> {
+        let field = T::shard_key_field().ok_or_else(|| {
+            Error::validation(format!(
+                "{} has no #[orso_shard_key] to route by",
+                T::table_name()
+            ))
+        })?;
+        // Shards share their configuration, so any of them converts the record
+        let mut map = crate::encryption::encode_record(self.shards[0].as_ref(), record)?;
+        let key = match map.remove(field) {
+            Some(Value::Text(key)) => key,
+            Some(Value::Integer(key)) => key.to_string(),
+            _ => {
+                return Err(Error::validation_field(
+                    "Shard key must be a non-null string or integer",
+                    field,
+                    None,
+                ))
+            }
+        };
+        Ok(self.shard_for_key(&key))
+    }
+
+    /// Run `find_where` on every shard at once and combine the results, shard by shard
+    pub async fn find_where_all_shards<T: Orso>(&self, filter: FilterOperator) -> Result<Vec<T>> {
+        let queries = self
+            .shards()
+            .map(|shard| T::find_where(filter.clone(), shard));
+        let results = futures_util::future::try_join_all(queries).await?;
+        Ok(results.into_iter().flatten().collect())
+    }
+}
+
+/// 64-bit FNV-1a, unlike `DefaultHasher` the same in every process
+fn fnv1a(key: &str) -> u64 {
+    key.bytes().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
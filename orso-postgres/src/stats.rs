@@ -0,0 +1,138 @@
+//! Table sizes and bloat
+//!
+//! [`Stats`] reports how much disk the tables of the current schema take, split into the heap,
+//! indexes and TOAST, where the compressed columns of wide rows end up. [`Stats::bloat`] adds
+//! the dead rows left by updates and deletes that `VACUUM` has not reclaimed yet:
+//!
+//! ```rust,ignore
+//! for size in Stats::table_sizes(&db).await? {
+//!     println!("{}: {} heap, {} toast", size.table, size.heap_bytes, size.toast_bytes);
+//! }
+//!
+//! let bloat = Stats::bloat::<Trade>(&db).await?;
+//! if bloat.dead_ratio() > 0.2 {
+//!     Maintenance::vacuum::<Trade>(&db, false).await?;
+//! }
+//! ```
+//!
+//! Row counts come from PostgreSQL's statistics, which lag behind recent writes until the next
+//! `ANALYZE` or autovacuum.
+
+use crate::{Database, Error, Orso, OrsoDateTime, Result};
+use tracing::instrument;
+
+/// Disk usage of a table, in bytes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableSize {
+    pub table: String,
+    /// Table data, with its free space and visibility maps
+    pub heap_bytes: u64,
+    pub index_bytes: u64,
+    /// Out-of-line storage of large values, with its index
+    pub toast_bytes: u64,
+    pub total_bytes: u64,
+    /// The planner's estimate of the number of rows
+    pub estimated_rows: u64,
+}
+
+/// Dead rows of a table
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableBloat {
+    pub size: TableSize,
+    pub live_rows: u64,
+    pub dead_rows: u64,
+    /// Latest manual or automatic `VACUUM`
+    pub last_vacuum: Option<OrsoDateTime>,
+    /// Latest manual or automatic `ANALYZE`
+    pub last_analyze: Option<OrsoDateTime>,
+}
+
+impl TableBloat {
+    /// Share of the rows in the table that are dead, between 0 and 1
+    pub fn dead_ratio(&self) -> f64 {
+        let rows = self.live_rows + self.dead_rows;
+        if rows == 0 {
+            return 0.0;
+        }
+        self.dead_rows as f64 / rows as f64
+    }
+}
+
+const SIZE_COLUMNS: &str = "
+    c.relname::text,
+    pg_table_size(c.oid) - COALESCE(pg_total_relation_size(NULLIF(c.reltoastrelid, 0)), 0),
+    pg_indexes_size(c.oid),
+    COALESCE(pg_total_relation_size(NULLIF(c.reltoastrelid, 0)), 0),
+    pg_total_relation_size(c.oid),
+    GREATEST(c.reltuples, 0)::bigint";
+
+fn table_size(row: &tokio_postgres::Row) -> TableSize {
+    let bytes = |i: usize| row.get::<_, i64>(i).max(0) as u64;
+    TableSize {
+        table: row.get(0),
+        heap_bytes: bytes(1),
+        index_bytes: bytes(2),
+        toast_bytes: bytes(3),
+        total_bytes: bytes(4),
+        estimated_rows: bytes(5),
+    }
+}
+
+/// Size and bloat reports
+pub struct Stats;
+
+impl Stats {
+    /// Sizes of the tables in the current schema, largest first
+    #[instrument(name = "orso.stats.table_sizes", skip_all)]
+    pub async fn table_sizes(db: &Database) -> Result<Vec<TableSize>> {
+        let sql = format!(
+            "SELECT {SIZE_COLUMNS}
+             FROM pg_class c
+             JOIN pg_namespace n ON n.oid = c.relnamespace
+             WHERE n.nspname = current_schema() AND c.relkind IN ('r', 'p')
+             ORDER BY pg_total_relation_size(c.oid) DESC, c.relname"
+        );
+        let rows = db.query(&sql, &[]).await?;
+        Ok(rows.iter().map(table_size).collect())
+    }
+
+    /// Size and dead rows of `T`'s table
+    pub async fn bloat<T: Orso>(db: &Database) -> Result<TableBloat> {
+        Self::bloat_with_table(db, T::table_name()).await
+    }
+
+    #[instrument(name = "orso.stats.bloat", skip_all, fields(table = %table_name))]
+    pub async fn bloat_with_table(db: &Database, table_name: &str) -> Result<TableBloat> {
+        let sql = format!(
+            "SELECT {SIZE_COLUMNS},
+                 COALESCE(s.n_live_tup, 0),
+                 COALESCE(s.n_dead_tup, 0),
+                 GREATEST(s.last_vacuum, s.last_autovacuum),
+                 GREATEST(s.last_analyze, s.last_autoanalyze)
+             FROM pg_class c
+             JOIN pg_namespace n ON n.oid = c.relnamespace
+             LEFT JOIN pg_stat_user_tables s ON s.relid = c.oid
+             WHERE n.nspname = current_schema() AND c.relkind IN ('r', 'p') AND c.relname = $1"
+        );
+        let row = db
+            .query_opt(&sql, &[&table_name])
+            .await?
+            .ok_or_else(|| Error::Schema {
+                message: format!("Table {} does not exist", table_name),
+                table: Some(table_name.to_string()),
+                column: None,
+            })?;
+
+        let timestamp = |i: usize| {
+            row.get::<_, Option<chrono::DateTime<chrono::Utc>>>(i)
+                .map(OrsoDateTime::new)
+        };
+        Ok(TableBloat {
+            size: table_size(&row),
+            live_rows: row.get::<_, i64>(6).max(0) as u64,
+            dead_rows: row.get::<_, i64>(7).max(0) as u64,
+            last_vacuum: timestamp(8),
+            last_analyze: timestamp(9),
+        })
+    }
+}
@@ -0,0 +1,555 @@
+//! How migrations change existing tables, and what those changes cost
+//!
+//! By default a table whose model changed is copied into a new table, which rewrites every
+//! row and loses writes made during the copy. [`MigrationStrategy`] picks another way:
+//!
+//! - [`MigrationStrategy::InPlace`] alters the table with `ALTER TABLE` when every change is
+//!   safe, i.e. neither rewrites the table nor blocks writes while scanning it, and copies
+//!   the table otherwise.
+//! - [`MigrationStrategy::ExpandContract`] only makes the changes old and new application
+//!   versions can both run against: new columns are added nullable and removed columns
+//!   stop being required. Dropping columns, enforcing `NOT NULL` and unique constraints and
+//!   rewriting types wait for [`Migrations::contract`], run once every instance is upgraded.
+//!
+//! [`Migrations::plan`] reports the changes a migration would make under a config, flagging
+//! those that rewrite the table or block writes, without changing anything:
+//!
+//! ```rust,ignore
+//! let config = MigrationConfig::default().with_strategy(MigrationStrategy::ExpandContract);
+//! for change in Migrations::plan(&db, &[migration!(User)], &config).await? {
+//!     println!("{change}"); // e.g. "users: Make email NOT NULL (blocks writes, contract)"
+//! }
+//!
+//! Migrations::init_with_config(&db, &[migration!(User)], &config).await?;
+//! // ...deploy the new version everywhere...
+//! Migrations::contract(&db, &[migration!(User)]).await?;
+//! ```
+//!
+//! Renames aren't detected: a renamed field plans as a new column and a dropped one. To rename
+//! without downtime, expand to the new column and keep it in step with the old one:
+//! [`Migrations::install_dual_write`] copies writes to either column into the other, and
+//! [`Migrations::backfill`] fills the new column for existing rows in batches.
+
+use crate::migrations::{
+    default_sql, deferral_differs, generate_type_conversion, ColumnInfo, MigrationConfig,
+    MigrationTrait, Migrations,
+};
+use crate::{ConstraintInfo, ConstraintKind, Database, Error, Result};
+use std::fmt;
+use tracing::{debug, instrument};
+
+/// How `Migrations::init` changes a table that no longer matches its model
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MigrationStrategy {
+    /// Copy the rows into a new table with the model's schema, keeping the old one as backup
+    #[default]
+    CopyTable,
+    /// `ALTER TABLE` when every change is safe, copy the table otherwise
+    InPlace,
+    /// Make the backward-compatible changes now and the rest in `Migrations::contract`
+    ExpandContract,
+}
+
+/// A change a migration makes to a table, reported by [`Migrations::plan`]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlannedChange {
+    pub table: String,
+    pub description: String,
+    /// Statements making the change in place, empty when only copying the table can
+    pub statements: Vec<String>,
+    /// Every row is written again, which takes time and space proportional to the table
+    pub rewrites_table: bool,
+    /// Writes have to stop while it runs, because it blocks them for longer than a catalog
+    /// update or because a copy of the table would miss them
+    pub blocks_writes: bool,
+    /// Left for [`Migrations::contract`] under [`MigrationStrategy::ExpandContract`]
+    pub contract: bool,
+}
+
+impl PlannedChange {
+    fn new(table: &str, description: String, statements: Vec<String>) -> Self {
+        Self {
+            table: table.to_string(),
+            description,
+            statements,
+            rewrites_table: false,
+            blocks_writes: false,
+            contract: false,
+        }
+    }
+
+    /// Neither rewrites the table nor blocks writes
+    pub fn is_safe(&self) -> bool {
+        !self.rewrites_table && !self.blocks_writes
+    }
+
+    fn rewriting(mut self) -> Self {
+        self.rewrites_table = true;
+        self.blocks_writes = true;
+        self
+    }
+
+    fn blocking(mut self) -> Self {
+        self.blocks_writes = true;
+        self
+    }
+
+    fn contract(mut self) -> Self {
+        self.contract = true;
+        self
+    }
+}
+
+impl fmt::Display for PlannedChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.table, self.description)?;
+        let flags: Vec<&str> = [
+            (self.rewrites_table, "rewrites table"),
+            (self.blocks_writes, "blocks writes"),
+            (self.contract, "contract"),
+        ]
+        .into_iter()
+        .filter_map(|(set, flag)| set.then_some(flag))
+        .collect();
+        if !flags.is_empty() {
+            write!(f, " ({})", flags.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// The changes turning `current` into `expected` under `strategy`, in column order
+///
+/// Column order is left alone: only copying the table can change it.
+pub(crate) fn plan_changes(
+    table_name: &str,
+    current: &[ColumnInfo],
+    expected: &[ColumnInfo],
+    constraints: &[ConstraintInfo],
+    deferrable: &[(&str, bool)],
+    strategy: MigrationStrategy,
+) -> Vec<PlannedChange> {
+    let expand = strategy == MigrationStrategy::ExpandContract;
+    let alter = |clause: String| format!("ALTER TABLE \"{table_name}\" {clause}");
+    let change = |description: String, statements: Vec<String>| {
+        PlannedChange::new(table_name, description, statements)
+    };
+    let mut changes = Vec::new();
+
+    for column in expected {
+        let name = &column.name;
+        let Some(live) = current.iter().find(|c| &c.name == name) else {
+            if column.is_primary_key {
+                let description = format!("Add primary key column {name}");
+                changes.push(change(description, vec![]).rewriting().contract());
+                continue;
+            }
+            let add = format!("ADD COLUMN \"{name}\" {}", column.sql_type);
+            if column.nullable {
+                changes.push(change(format!("Add column {name}"), vec![alter(add)]));
+            } else if let Some(default) = default_sql(column) {
+                let add = format!("{add} NOT NULL DEFAULT {default}");
+                changes.push(change(format!("Add column {name}"), vec![alter(add)]));
+            } else if expand {
+                // Old versions don't write the column, so it can't be required yet
+                changes.push(change(
+                    format!("Add column {name} as nullable"),
+                    vec![alter(add)],
+                ));
+                let set = alter(format!("ALTER COLUMN \"{name}\" SET NOT NULL"));
+                let description = format!("Make {name} NOT NULL");
+                changes.push(change(description, vec![set]).blocking().contract());
+            } else {
+                let statements = match zero_value(&column.sql_type) {
+                    Some(zero) => vec![
+                        alter(format!("{add} NOT NULL DEFAULT {zero}")),
+                        alter(format!("ALTER COLUMN \"{name}\" DROP DEFAULT")),
+                    ],
+                    None => vec![],
+                };
+                let added = change(format!("Add column {name}"), statements);
+                changes.push(if added.statements.is_empty() {
+                    added.rewriting()
+                } else {
+                    added
+                });
+            }
+            if column.is_unique {
+                changes.push(add_unique(table_name, name, deferrable).contract());
+            }
+            continue;
+        };
+
+        if live.sql_type != column.sql_type {
+            let description = format!(
+                "Change type of {name} from {} to {}",
+                live.sql_type, column.sql_type
+            );
+            let mut clause = format!("ALTER COLUMN \"{name}\" TYPE {}", column.sql_type);
+            if binary_coercible(&live.sql_type, &column.sql_type) {
+                changes.push(change(description, vec![alter(clause)]));
+            } else {
+                let conversion = generate_type_conversion(&live.sql_type, &column.sql_type, name);
+                clause.push_str(&format!(" USING {conversion}"));
+                changes.push(
+                    change(description, vec![alter(clause)])
+                        .rewriting()
+                        .contract(),
+                );
+            }
+        }
+
+        if live.nullable && !column.nullable {
+            // Checking for NULLs scans the table under an exclusive lock
+            let set = alter(format!("ALTER COLUMN \"{name}\" SET NOT NULL"));
+            let description = format!("Make {name} NOT NULL");
+            changes.push(change(description, vec![set]).blocking().contract());
+        } else if !live.nullable && column.nullable {
+            let drop = alter(format!("ALTER COLUMN \"{name}\" DROP NOT NULL"));
+            changes.push(change(format!("Make {name} nullable"), vec![drop]));
+        }
+
+        if live.is_primary_key != column.is_primary_key {
+            let description = if column.is_primary_key {
+                format!("Make {name} the primary key")
+            } else {
+                format!("Drop primary key {name}")
+            };
+            changes.push(change(description, vec![]).rewriting().contract());
+        } else if column.is_unique && !live.is_unique {
+            changes.push(add_unique(table_name, name, deferrable).contract());
+        } else if live.is_unique && !column.is_unique {
+            let statements = unique_constraint(constraints, name)
+                .map(|constraint| alter(format!("DROP CONSTRAINT \"{constraint}\"")))
+                .into_iter()
+                .collect();
+            let dropped = change(format!("Drop unique constraint on {name}"), statements);
+            changes.push(if dropped.statements.is_empty() {
+                dropped.rewriting().contract()
+            } else {
+                dropped
+            });
+        }
+
+        if deferral_differs(live, column) {
+            changes.extend(alter_deferral(table_name, live, column, constraints));
+        }
+    }
+
+    for live in current {
+        if expected.iter().any(|c| c.name == live.name) {
+            continue;
+        }
+        let name = &live.name;
+        // New versions don't write the column, so it can't stay required
+        if expand && !live.nullable && !live.has_default && !live.is_primary_key {
+            let drop = alter(format!("ALTER COLUMN \"{name}\" DROP NOT NULL"));
+            changes.push(change(format!("Make {name} nullable"), vec![drop]));
+        }
+        let drop = alter(format!("DROP COLUMN \"{name}\""));
+        changes.push(change(format!("Drop column {name}"), vec![drop]).contract());
+    }
+
+    if !expand {
+        for change in &mut changes {
+            change.contract = false;
+        }
+    }
+    changes
+}
+
+/// `ADD CONSTRAINT ... UNIQUE`, which builds its index while blocking writes
+fn add_unique(table_name: &str, column: &str, deferrable: &[(&str, bool)]) -> PlannedChange {
+    let deferral = match deferrable.iter().find(|(name, _)| *name == column) {
+        Some((_, true)) => " DEFERRABLE INITIALLY DEFERRED",
+        Some((_, false)) => " DEFERRABLE",
+        None => "",
+    };
+    let sql = format!(
+        "ALTER TABLE \"{table_name}\" ADD CONSTRAINT \"{table_name}_{column}_key\" \
+         UNIQUE (\"{column}\"){deferral}"
+    );
+    PlannedChange::new(
+        table_name,
+        format!("Add unique constraint on {column}"),
+        vec![sql],
+    )
+    .blocking()
+}
+
+/// The unique and foreign key constraints on `column` made deferrable, or not, as declared
+///
+/// Only foreign keys can be altered, so a unique constraint is built again.
+fn alter_deferral(
+    table_name: &str,
+    live: &ColumnInfo,
+    column: &ColumnInfo,
+    constraints: &[ConstraintInfo],
+) -> Option<PlannedChange> {
+    let name = &column.name;
+    let deferral = match column.deferral {
+        Some(true) => "DEFERRABLE INITIALLY DEFERRED",
+        Some(false) => "DEFERRABLE INITIALLY IMMEDIATE",
+        None => "NOT DEFERRABLE",
+    };
+    let alter = |clause: String| format!("ALTER TABLE \"{table_name}\" {clause}");
+    let mut statements = Vec::new();
+    let mut rebuilds_unique = false;
+    if live.is_unique && column.is_unique && !column.is_primary_key {
+        if let Some(constraint) = unique_constraint(constraints, name) {
+            statements.push(alter(format!(
+                "DROP CONSTRAINT \"{constraint}\", \
+                 ADD CONSTRAINT \"{constraint}\" UNIQUE (\"{name}\") {deferral}"
+            )));
+            rebuilds_unique = true;
+        }
+    }
+    for foreign_key in constraints
+        .iter()
+        .filter(|c| c.kind == ConstraintKind::ForeignKey && c.columns == [name.as_str()])
+    {
+        let constraint = &foreign_key.name;
+        statements.push(alter(format!(
+            "ALTER CONSTRAINT \"{constraint}\" {deferral}"
+        )));
+    }
+    if statements.is_empty() {
+        return None;
+    }
+    let changed = PlannedChange::new(
+        table_name,
+        format!("Make constraints on {name} {}", deferral.to_lowercase()),
+        statements,
+    );
+    Some(if rebuilds_unique {
+        changed.blocking()
+    } else {
+        changed
+    })
+}
+
+/// Name of the unique constraint on just `column`
+fn unique_constraint<'a>(constraints: &'a [ConstraintInfo], column: &str) -> Option<&'a str> {
+    constraints
+        .iter()
+        .find(|c| c.kind == ConstraintKind::Unique && c.columns == [column])
+        .map(|c| c.name.as_str())
+}
+
+/// Value filling a new `NOT NULL` column in existing rows, as the table copy does
+fn zero_value(sql_type: &str) -> Option<&'static str> {
+    if sql_type.ends_with("[]") {
+        return Some("'{}'");
+    }
+    match sql_type {
+        "TEXT" => Some("''"),
+        "SMALLINT" | "INTEGER" | "BIGINT" | "REAL" | "DOUBLE PRECISION" => Some("0"),
+        "BOOLEAN" => Some("false"),
+        _ => None,
+    }
+}
+
+/// Whether PostgreSQL changes `from` to `to` without rewriting the table
+fn binary_coercible(from: &str, to: &str) -> bool {
+    let (from_base, from_mods) = split_modifiers(from);
+    let (to_base, to_mods) = split_modifiers(to);
+    match (from_base, to_base) {
+        ("CHARACTER VARYING", "TEXT") | ("TEXT", "CHARACTER VARYING") => to_mods.is_empty(),
+        ("CHARACTER VARYING", "CHARACTER VARYING") => match (&from_mods[..], &to_mods[..]) {
+            (_, []) => true,
+            ([from_len], [to_len]) => to_len >= from_len,
+            _ => false,
+        },
+        // More digits before the point, same scale
+        ("NUMERIC", "NUMERIC") => match (&from_mods[..], &to_mods[..]) {
+            (_, []) => true,
+            ([from_precision], [to_precision]) => to_precision >= from_precision,
+            ([from_precision, from_scale], [to_precision, to_scale]) => {
+                to_precision >= from_precision && to_scale == from_scale
+            }
+            _ => false,
+        },
+        _ => false,
+    }
+}
+
+/// `NUMERIC(20,8)` as `("NUMERIC", [20, 8])`
+fn split_modifiers(sql_type: &str) -> (&str, Vec<u32>) {
+    match sql_type.split_once('(') {
+        Some((base, rest)) => {
+            let modifiers = rest
+                .trim_end_matches(')')
+                .split(',')
+                .filter_map(|m| m.trim().parse().ok())
+                .collect();
+            (base.trim(), modifiers)
+        }
+        None => (sql_type, Vec::new()),
+    }
+}
+
+/// Run the statements of `changes` in order
+pub(crate) async fn apply_changes(
+    db: &Database,
+    table_name: &str,
+    changes: &[PlannedChange],
+) -> Result<Vec<String>> {
+    let mut applied = Vec::new();
+    for change in changes {
+        for sql in &change.statements {
+            debug!(table = %table_name, sql = %sql, "Altering table in place");
+            db.execute(sql, &[]).await.map_err(|e| {
+                Error::migration(
+                    format!("Failed to {}: {}", change.description.to_lowercase(), e),
+                    Some(table_name.to_string()),
+                    Some("alter_in_place".to_string()),
+                )
+            })?;
+            applied.push(sql.clone());
+        }
+    }
+    Ok(applied)
+}
+
+impl Migrations {
+    /// Changes `Migrations::init_with_config` would make with `config`, without making them
+    #[instrument(name = "orso.migrate.plan", skip_all, fields(migrations = migrations.len()))]
+    pub async fn plan(
+        db: &Database,
+        migrations: &[Box<dyn MigrationTrait>],
+        config: &MigrationConfig,
+    ) -> Result<Vec<PlannedChange>> {
+        let mut changes = Vec::new();
+        for migration in migrations {
+            changes.extend(migration.plan(db, config).await?);
+        }
+        Ok(changes)
+    }
+
+    /// Make the changes [`MigrationStrategy::ExpandContract`] deferred, once no instance of
+    /// the previous version is left
+    ///
+    /// Changes that can be made in place are, even those blocking writes; a table with any
+    /// other change is copied.
+    pub async fn contract(
+        db: &Database,
+        migrations: &[Box<dyn MigrationTrait>],
+    ) -> Result<Vec<crate::MigrationResult>> {
+        Self::contract_with_config(db, migrations, &MigrationConfig::default()).await
+    }
+
+    #[instrument(name = "orso.migrate.contract", skip_all, fields(migrations = migrations.len()))]
+    pub async fn contract_with_config(
+        db: &Database,
+        migrations: &[Box<dyn MigrationTrait>],
+        config: &MigrationConfig,
+    ) -> Result<Vec<crate::MigrationResult>> {
+        Self::init_with_config(db, migrations, &config.clone().contracting()).await
+    }
+
+    /// Statements creating a trigger that copies writes to `from` of `table_name` into `to`
+    /// and the other way around, while old and new versions write different columns
+    pub fn dual_write_sql(table_name: &str, from: &str, to: &str) -> Vec<String> {
+        let name = format!("{table_name}_{from}_{to}_dual_write");
+        vec![
+            format!(
+                "CREATE OR REPLACE FUNCTION \"{name}_fn\"() RETURNS trigger AS $$\n\
+                 BEGIN\n    \
+                 IF TG_OP = 'INSERT' THEN\n        \
+                 IF NEW.\"{to}\" IS NULL THEN\n            \
+                 NEW.\"{to}\" := NEW.\"{from}\";\n        \
+                 ELSIF NEW.\"{from}\" IS NULL THEN\n            \
+                 NEW.\"{from}\" := NEW.\"{to}\";\n        \
+                 END IF;\n    \
+                 ELSIF NEW.\"{from}\" IS DISTINCT FROM OLD.\"{from}\" THEN\n        \
+                 NEW.\"{to}\" := NEW.\"{from}\";\n    \
+                 ELSIF NEW.\"{to}\" IS DISTINCT FROM OLD.\"{to}\" THEN\n        \
+                 NEW.\"{from}\" := NEW.\"{to}\";\n    \
+                 END IF;\n    \
+                 RETURN NEW;\n\
+                 END;\n\
+                 $$ LANGUAGE plpgsql"
+            ),
+            format!("DROP TRIGGER IF EXISTS \"{name}_trigger\" ON \"{table_name}\""),
+            format!(
+                "CREATE TRIGGER \"{name}_trigger\" BEFORE INSERT OR UPDATE ON \"{table_name}\" \
+                 FOR EACH ROW EXECUTE FUNCTION \"{name}_fn\"()"
+            ),
+        ]
+    }
+
+    /// Keep `to` of `table_name` in step with `from`, see [`Migrations::dual_write_sql`]
+    pub async fn install_dual_write(
+        db: &Database,
+        table_name: &str,
+        from: &str,
+        to: &str,
+    ) -> Result<()> {
+        for sql in Self::dual_write_sql(table_name, from, to) {
+            db.execute(&sql, &[]).await.map_err(|e| {
+                Error::migration(
+                    format!("Failed to install dual-write trigger: {}", e),
+                    Some(table_name.to_string()),
+                    Some("install_dual_write".to_string()),
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Drop the trigger of [`Migrations::install_dual_write`], before contracting
+    pub async fn remove_dual_write(
+        db: &Database,
+        table_name: &str,
+        from: &str,
+        to: &str,
+    ) -> Result<()> {
+        let name = format!("{table_name}_{from}_{to}_dual_write");
+        for sql in [
+            format!("DROP TRIGGER IF EXISTS \"{name}_trigger\" ON \"{table_name}\""),
+            format!("DROP FUNCTION IF EXISTS \"{name}_fn\"()"),
+        ] {
+            db.execute(&sql, &[]).await.map_err(|e| {
+                Error::migration(
+                    format!("Failed to remove dual-write trigger: {}", e),
+                    Some(table_name.to_string()),
+                    Some("remove_dual_write".to_string()),
+                )
+            })?;
+        }
+        Ok(())
+    }
+
+    /// Copy `from` into `to` for rows of `table_name` where `to` is NULL, `batch_size` rows
+    /// per statement so no lock is held for long; returns the rows updated
+    #[instrument(name = "orso.migrate.backfill", skip_all, fields(table = %table_name))]
+    pub async fn backfill(
+        db: &Database,
+        table_name: &str,
+        from: &str,
+        to: &str,
+        batch_size: u32,
+    ) -> Result<u64> {
+        let sql = format!(
+            "UPDATE \"{table_name}\" SET \"{to}\" = \"{from}\" WHERE ctid = ANY(ARRAY(\
+             SELECT ctid FROM \"{table_name}\" \
+             WHERE \"{to}\" IS NULL AND \"{from}\" IS NOT NULL LIMIT {}))",
+            batch_size.max(1)
+        );
+        let mut total = 0;
+        loop {
+            let updated = db.execute(&sql, &[]).await.map_err(|e| {
+                Error::migration(
+                    format!("Failed to backfill {to}: {}", e),
+                    Some(table_name.to_string()),
+                    Some("backfill".to_string()),
+                )
+            })?;
+            if updated == 0 {
+                return Ok(total);
+            }
+            total += updated;
+            debug!(table = %table_name, rows = total, "Backfilled");
+        }
+    }
+}
@@ -3,7 +3,7 @@ mod tests {
     use crate::{
         self as orso, self as orso_postgres, migration, orso_column, orso_table, Database,
         DatabaseConfig, Filter, FilterOperator, FloatingCodec, IntegerCodec, Migrations, Operator,
-        Orso, OrsoDateTime, Pagination, Sort, SortOrder, Utils, Value,
+        Orso, OrsoDateTime, Pagination, Sort, SortOrder, TestDb, Utils, Value,
     };
     use serde::{Deserialize, Serialize};
 
@@ -30,45 +30,6 @@ mod tests {
         DatabaseConfig::new(connection_string).with_pool_size(10)
     }
 
-    /// Clean up test table to ensure isolated test runs
-    async fn cleanup_test_table(
-        db: &Database,
-        table_name: &str,
-    ) -> Result<(), Box<dyn std::error::Error>> {
-        // Drop table if it exists to ensure clean test state
-        let drop_sql = format!("DROP TABLE IF EXISTS \"{}\" CASCADE", table_name);
-        let _ = db.execute(&drop_sql, &[]).await; // Ignore errors if table doesn't exist
-
-        // Also drop any custom types that might have been created
-        let drop_type_sql = format!("DROP TYPE IF EXISTS \"{}\" CASCADE", table_name);
-        let _ = db.execute(&drop_type_sql, &[]).await; // Ignore errors if type doesn't exist
-
-        // Drop any temporary migration tables
-        let drop_temp_sql = format!("DROP TABLE IF EXISTS \"{}_temp_%\" CASCADE", table_name);
-        let _ = db.execute(&drop_temp_sql, &[]).await;
-
-        // Clear any PostgreSQL type cache issues by dropping schema and recreating
-        // This is aggressive but ensures clean test isolation
-        let schema_reset_sql = "SELECT pg_catalog.pg_type.typname FROM pg_catalog.pg_type
-                               WHERE pg_catalog.pg_type.typname LIKE '%test%'
-                               AND pg_catalog.pg_type.typtype = 'c'";
-        if let Ok(rows) = db.query(&schema_reset_sql, &[]).await {
-            for row in rows {
-                let type_name: String = row.get(0);
-                let drop_composite_sql = format!("DROP TYPE IF EXISTS \"{}\" CASCADE", type_name);
-                let _ = db.execute(&drop_composite_sql, &[]).await;
-            }
-        }
-
-        // Force PostgreSQL to clear its internal caches
-        let _ = db
-            .execute("SELECT pg_catalog.pg_stat_clear_snapshot()", &[])
-            .await;
-        let _ = db.execute("DISCARD ALL", &[]).await;
-
-        Ok(())
-    }
-
     #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
     #[orso_table("test_compressed_001")]
     struct TestCompressed {
@@ -173,15 +134,7 @@ mod tests {
     #[tokio::test]
     async fn test_compressed_field_integration() -> Result<(), Box<dyn std::error::Error>> {
         // Create PostgreSQL test database
-        let config = get_test_db_config();
-        let db = Database::init(config).await?;
-
-        // Clean up any existing test data
-        cleanup_test_table(&db, "test_compressed_001").await?;
-
-        // Create table
-        use orso::{migration, Migrations};
-        Migrations::init(&db, &[migration!(TestCompressed)]).await?;
+        let db = TestDb::new(get_test_db_config(), &[migration!(TestCompressed)]).await?;
 
         // Create test data
         let test_data = TestCompressed {
@@ -211,15 +164,7 @@ mod tests {
     #[tokio::test]
     async fn test_compressed_field_filtering() -> Result<(), Box<dyn std::error::Error>> {
         // Create PostgreSQL test database
-        let config = get_test_db_config();
-        let db = Database::init(config).await?;
-
-        // Clean up any existing test data
-        cleanup_test_table(&db, "test_compressed_001").await?;
-
-        // Create table
-        use orso::{migration, Migrations};
-        Migrations::init(&db, &[migration!(TestCompressed)]).await?;
+        let db = TestDb::new(get_test_db_config(), &[migration!(TestCompressed)]).await?;
 
         // Create test data
         let test_data1 = TestCompressed {
@@ -264,15 +209,7 @@ mod tests {
     #[tokio::test]
     async fn test_compressed_field_update() -> Result<(), Box<dyn std::error::Error>> {
         // Create PostgreSQL test database
-        let config = get_test_db_config();
-        let db = Database::init(config).await?;
-
-        // Clean up any existing test data
-        cleanup_test_table(&db, "test_compressed_001").await?;
-
-        // Create table
-        use orso::{migration, Migrations};
-        Migrations::init(&db, &[migration!(TestCompressed)]).await?;
+        let db = TestDb::new(get_test_db_config(), &[migration!(TestCompressed)]).await?;
 
         // Create test data
         let test_data = TestCompressed {
@@ -317,11 +254,7 @@ mod tests {
     async fn test_compressed_field_delete() -> Result<(), Box<dyn std::error::Error>> {
         // Create PostgreSQL test database
         let config = get_test_db_config();
-        let db = Database::init(config).await?;
-
-        // Create table
-        use orso::{migration, Migrations};
-        Migrations::init(&db, &[migration!(TestCompressed)]).await?;
+        let db = TestDb::new(config, &[migration!(TestCompressed)]).await?;
 
         // Create test data
         let test_data = TestCompressed {
@@ -353,11 +286,7 @@ mod tests {
     async fn test_multiple_compressed_fields_same_type() -> Result<(), Box<dyn std::error::Error>> {
         // Create PostgreSQL test database
         let config = get_test_db_config();
-        let db = Database::init(config).await?;
-
-        // Create table
-        use orso::{migration, Migrations};
-        Migrations::init(&db, &[migration!(TestUserWithMultipleCompressedFields)]).await?;
+        let db = TestDb::new(config, &[migration!(TestUserWithMultipleCompressedFields)]).await?;
 
         // Create test data with multiple compressed fields of the same type
         let test_data = TestUserWithMultipleCompressedFields {
@@ -397,15 +326,7 @@ mod tests {
     #[tokio::test]
     async fn test_basic_crud_operations() -> Result<(), Box<dyn std::error::Error>> {
         // Create PostgreSQL test database
-        let config = get_test_db_config();
-        let db = Database::init(config).await?;
-
-        // Clean up any existing test data
-        cleanup_test_table(&db, "test_users_002").await?;
-
-        // Create table
-        use orso::{migration, Migrations};
-        Migrations::init(&db, &[migration!(TestUser)]).await?;
+        let db = TestDb::new(get_test_db_config(), &[migration!(TestUser)]).await?;
 
         // Create test user
         let user = TestUser {
@@ -465,15 +386,7 @@ mod tests {
     #[tokio::test]
     async fn test_filtering_and_querying() -> Result<(), Box<dyn std::error::Error>> {
         // Create PostgreSQL test database
-        let config = get_test_db_config();
-        let db = Database::init(config).await?;
-
-        // Clean up any existing test data
-        cleanup_test_table(&db, "test_users_002").await?;
-
-        // Create table
-        use orso::{migration, Migrations};
-        Migrations::init(&db, &[migration!(TestUser)]).await?;
+        let db = TestDb::new(get_test_db_config(), &[migration!(TestUser)]).await?;
 
         // Create test users
         let users = vec![
@@ -548,11 +461,7 @@ mod tests {
     async fn test_unique_constraints() -> Result<(), Box<dyn std::error::Error>> {
         // Create PostgreSQL test database
         let config = get_test_db_config();
-        let db = Database::init(config).await?;
-
-        // Create table
-        use orso::{migration, Migrations};
-        Migrations::init(&db, &[migration!(TestUser)]).await?;
+        let db = TestDb::new(config, &[migration!(TestUser)]).await?;
 
         // Create first user
         let user1 = TestUser {
@@ -586,15 +495,7 @@ mod tests {
     #[tokio::test]
     async fn test_batch_operations() -> Result<(), Box<dyn std::error::Error>> {
         // Create PostgreSQL test database
-        let config = get_test_db_config();
-        let db = Database::init(config).await?;
-
-        // Clean up any existing test data
-        cleanup_test_table(&db, "test_users_002").await?;
-
-        // Create table
-        use orso::{migration, Migrations};
-        Migrations::init(&db, &[migration!(TestUser)]).await?;
+        let db = TestDb::new(get_test_db_config(), &[migration!(TestUser)]).await?;
 
         // Create multiple users
         let users = vec![
@@ -638,8 +539,13 @@ mod tests {
             .map(|id| id.as_str())
             .collect();
 
-        let deleted_count = TestUser::batch_delete(&user_ids, &db).await?;
-        assert_eq!(deleted_count, 3);
+        let deleted = TestUser::batch_delete(&user_ids, &db).await?;
+        assert_eq!(deleted.rows_affected, 3);
+        let mut deleted_ids = deleted.returned_ids;
+        deleted_ids.sort();
+        let mut user_ids = user_ids;
+        user_ids.sort();
+        assert_eq!(deleted_ids, user_ids);
 
         // Verify all users were deleted
         let remaining_users = TestUser::find_all(&db).await?;
@@ -651,7 +557,7 @@ mod tests {
     #[tokio::test]
     async fn test_migration_no_change_detection() -> Result<(), Box<dyn std::error::Error>> {
         use crate as orso;
-        use crate::{migration, Database, Migrations, Orso};
+        use crate::{migration, Migrations, Orso};
         use serde::{Deserialize, Serialize};
         #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
         #[orso_table("migration_test_006")]
@@ -663,7 +569,7 @@ mod tests {
         }
         // Create PostgreSQL test database
         let config = get_test_db_config();
-        let db = Database::init(config).await?;
+        let db = TestDb::new(config, &[]).await?;
 
         // Run initial migration
         let results1 = Migrations::init(&db, &[migration!(MigrationTest)]).await?;
@@ -689,7 +595,7 @@ mod tests {
     async fn test_migration_constraint_detection() -> Result<(), Box<dyn std::error::Error>> {
         // Create PostgreSQL test database
         let config = get_test_db_config();
-        let db = Database::init(config).await?;
+        let db = TestDb::new(config, &[]).await?;
 
         // First, create a table without unique constraints
         #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
@@ -763,11 +669,7 @@ mod tests {
     #[tokio::test]
     async fn test_migration_compression_detection() -> Result<(), Box<dyn std::error::Error>> {
         // Create PostgreSQL test database
-        let config = get_test_db_config();
-        let db = Database::init(config).await?;
-
-        // Clean up any existing test data and migration tables
-        cleanup_test_table(&db, "compression_migration_test").await?;
+        let db = TestDb::new(get_test_db_config(), &[]).await?;
 
         // First, create a table without compression
         #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
@@ -842,14 +744,7 @@ mod tests {
     #[tokio::test]
     async fn test_id_auto_generation() -> Result<(), Box<dyn std::error::Error>> {
         // Create PostgreSQL test database
-        let config = get_test_db_config();
-        let db = Database::init(config).await?;
-
-        // Clean up any existing test data
-        cleanup_test_table(&db, "id_generation_test_010").await?;
-
-        // Create table
-        Migrations::init(&db, &[migration!(IdGenerationTest)]).await?;
+        let db = TestDb::new(get_test_db_config(), &[migration!(IdGenerationTest)]).await?;
 
         // Create record with None ID (should auto-generate)
         let record = IdGenerationTest {
@@ -890,13 +785,10 @@ mod tests {
     async fn test_id_generation_debug() -> Result<(), Box<dyn std::error::Error>> {
         // Create PostgreSQL test database
         let config = get_test_db_config();
-        let db = Database::init(config).await?;
-
-        // Create table
-        Migrations::init(&db, &[migration!(IdGenerationDebugTest)]).await?;
+        let db = TestDb::new(config, &[migration!(IdGenerationDebugTest)]).await?;
 
         // Let's check if the table was created properly
-        let schema_sql = "SELECT table_name FROM information_schema.tables WHERE table_schema = 'public' AND table_name = 'id_generation_test'";
+        let schema_sql = "SELECT table_name FROM information_schema.tables WHERE table_schema = current_schema() AND table_name = 'id_generation_test'";
         let rows = db.query(&schema_sql, &[]).await?;
 
         if let Some(row) = rows.get(0) {
@@ -1076,10 +968,7 @@ mod tests {
 
         // Create PostgreSQL test database
         let config = get_test_db_config();
-        let db = Database::init(config).await?;
-
-        // Create table
-        Migrations::init(&db, &[migration!(CompressionTest)]).await?;
+        let db = TestDb::new(config, &[migration!(CompressionTest)]).await?;
 
         // Create test data
         let test_data = CompressionTest {
@@ -1205,7 +1094,7 @@ Test completed successfully!"
 
     #[tokio::test]
     async fn batch_compression_test() -> Result<(), Box<dyn std::error::Error>> {
-        use crate::{migration, Database, Migrations, Orso};
+        use crate::{migration, Orso};
         use serde::{Deserialize, Serialize};
 
         #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
@@ -1246,10 +1135,7 @@ Test completed successfully!"
         }
         // Create PostgreSQL test database
         let config = get_test_db_config();
-        let db = Database::init(config).await?;
-
-        // Create table
-        Migrations::init(&db, &[migration!(BatchCompressionTest)]).await?;
+        let db = TestDb::new(config, &[migration!(BatchCompressionTest)]).await?;
 
         // Create test data
         let test_data1 = BatchCompressionTest {
@@ -1452,10 +1338,7 @@ Test completed successfully!"
         // Test batch inserts
         println!("\n=== Testing Batch Inserts ===");
         let config2 = get_test_db_config();
-        let db2 = Database::init(config2).await?;
-
-        // Create table
-        Migrations::init(&db2, &[migration!(BatchCompressionTest)]).await?;
+        let db2 = TestDb::new(config2, &[migration!(BatchCompressionTest)]).await?;
 
         let batch_data = vec![test_data1.clone(), test_data2.clone(), test_data3.clone()];
 
@@ -1547,7 +1430,7 @@ Test completed successfully!"
     }
     #[tokio::test]
     async fn batch_operations_test() -> Result<(), Box<dyn std::error::Error>> {
-        use crate::{migration, Database, Migrations, Orso};
+        use crate::{migration, Orso};
         use serde::{Deserialize, Serialize};
 
         #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
@@ -1569,10 +1452,7 @@ Test completed successfully!"
         }
         // Create PostgreSQL test database
         let config = get_test_db_config();
-        let db = Database::init(config).await?;
-
-        // Create table
-        Migrations::init(&db, &[migration!(BatchOperationsTest)]).await?;
+        let db = TestDb::new(config, &[migration!(BatchOperationsTest)]).await?;
 
         println!("=== Testing Batch Insert ===");
 
@@ -1682,10 +1562,7 @@ Test completed successfully!"
         println!("\n=== Testing Batch Upsert ===");
 
         let config2 = get_test_db_config();
-        let db2 = Database::init(config2).await?;
-
-        // Create table
-        Migrations::init(&db2, &[migration!(BatchOperationsTest)]).await?;
+        let db2 = TestDb::new(config2, &[migration!(BatchOperationsTest)]).await?;
 
         // Create initial data for upsert
         let initial_data = vec![
@@ -1767,15 +1644,7 @@ Test completed successfully!"
     #[tokio::test]
     async fn debug_compression_check_vector_collect() -> Result<(), Box<dyn std::error::Error>> {
         // Create PostgreSQL test database
-        let config = get_test_db_config();
-        let db = Database::init(config).await?;
-
-        // Clean up any existing test data
-        cleanup_test_table(&db, "debug_compressed_005").await?;
-
-        // Create table
-        use orso::{migration, Migrations};
-        Migrations::init(&db, &[migration!(DebugCompressed)]).await?;
+        let db = TestDb::new(get_test_db_config(), &[migration!(DebugCompressed)]).await?;
 
         // Create test data
         let test_data = DebugCompressed {
@@ -1808,15 +1677,7 @@ Test completed successfully!"
     #[tokio::test]
     async fn debug_compression_check_vector_simple() -> Result<(), Box<dyn std::error::Error>> {
         // Create PostgreSQL test database
-        let config = get_test_db_config();
-        let db = Database::init(config).await?;
-
-        // Clean up any existing test data
-        cleanup_test_table(&db, "debug_compressed_005").await?;
-
-        // Create table
-        use orso::{migration, Migrations};
-        Migrations::init(&db, &[migration!(DebugCompressed)]).await?;
+        let db = TestDb::new(get_test_db_config(), &[migration!(DebugCompressed)]).await?;
 
         // Create test data
         let test_data = DebugCompressed {
@@ -1893,10 +1754,7 @@ Test completed successfully!"
     async fn test_collect_vs_vec_macro() -> Result<(), Box<dyn std::error::Error>> {
         // Create PostgreSQL test database
         let config = get_test_db_config();
-        let db = Database::init(config).await?;
-
-        // Create table
-        Migrations::init(&db, &[migration!(CollectVsVecTest)]).await?;
+        let db = TestDb::new(config, &[migration!(CollectVsVecTest)]).await?;
 
         // Create test data - one with collect, one with vec!
         let test_data = CollectVsVecTest {
@@ -1970,10 +1828,7 @@ Test completed successfully!"
     async fn test_allocator_specific_vec() -> Result<(), Box<dyn std::error::Error>> {
         // Create PostgreSQL test database
         let config = get_test_db_config();
-        let db = Database::init(config).await?;
-
-        // Create table
-        Migrations::init(&db, &[migration!(AllocatorTest)]).await?;
+        let db = TestDb::new(config, &[migration!(AllocatorTest)]).await?;
 
         // Create test data
         let test_data = AllocatorTest {
@@ -2059,7 +1914,7 @@ Test completed successfully!"
     #[tokio::test]
     async fn test_postgresql_arrays_simple() -> Result<(), Box<dyn std::error::Error>> {
         let config = get_test_db_config();
-        let db = Database::init(config).await?;
+        let db = TestDb::new(config, &[migration!(SimpleArrayTest)]).await?;
 
         // Simple test with just one array field
         #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
@@ -2071,16 +1926,6 @@ Test completed successfully!"
             name: String,
         }
 
-        // Clean up any existing table
-        let _ = db
-            .pool
-            .get()
-            .await?
-            .execute("DROP TABLE IF EXISTS simple_array_test", &[])
-            .await;
-
-        Migrations::init(&db, &[migration!(SimpleArrayTest)]).await?;
-
         let test_data = SimpleArrayTest {
             id: None,
             numbers: vec![1, 2, 3],
@@ -2103,7 +1948,7 @@ Test completed successfully!"
     #[tokio::test]
     async fn test_postgresql_native_arrays_basic() -> Result<(), Box<dyn std::error::Error>> {
         let config = get_test_db_config();
-        let db = Database::init(config).await?;
+        let db = TestDb::new(config, &[]).await?;
 
         // Clean up any existing table
         let _ = db
@@ -2221,17 +2066,7 @@ Test completed successfully!"
         println!("==================");
 
         let config = get_test_db_config();
-        let db = Database::init(config).await?;
-
-        // Clean up any existing table
-        let _ = db
-            .pool
-            .get()
-            .await?
-            .execute("DROP TABLE IF EXISTS test_arrays_vs_compressed", &[])
-            .await;
-
-        Migrations::init(&db, &[migration!(TestArraysVsCompressed)]).await?;
+        let db = TestDb::new(config, &[migration!(TestArraysVsCompressed)]).await?;
 
         // Verify SQL generation
         let migration_sql = TestArraysVsCompressed::migration_sql();
@@ -2322,17 +2157,7 @@ Test completed successfully!"
     #[tokio::test]
     async fn test_array_edge_cases() -> Result<(), Box<dyn std::error::Error>> {
         let config = get_test_db_config();
-        let db = Database::init(config).await?;
-
-        // Clean up any existing table
-        let _ = db
-            .pool
-            .get()
-            .await?
-            .execute("DROP TABLE IF EXISTS test_array_edge_cases", &[])
-            .await;
-
-        Migrations::init(&db, &[migration!(TestArrayEdgeCases)]).await?;
+        let db = TestDb::new(config, &[migration!(TestArrayEdgeCases)]).await?;
 
         // Test safe edge case values (PostgreSQL compatible)
         let test_data = TestArrayEdgeCases {
@@ -2386,17 +2211,7 @@ Test completed successfully!"
     #[tokio::test]
     async fn test_array_crud_operations() -> Result<(), Box<dyn std::error::Error>> {
         let config = get_test_db_config();
-        let db = Database::init(config).await?;
-
-        // Clean up any existing table
-        let _ = db
-            .pool
-            .get()
-            .await?
-            .execute("DROP TABLE IF EXISTS test_array_queries", &[])
-            .await;
-
-        Migrations::init(&db, &[migration!(TestArrayQueries)]).await?;
+        let db = TestDb::new(config, &[migration!(TestArrayQueries)]).await?;
 
         // Insert multiple records
         let records = vec![
@@ -2496,13 +2311,7 @@ Test completed successfully!"
 
     #[tokio::test]
     async fn test_all_numeric_array_types() -> Result<(), Box<dyn std::error::Error>> {
-        let config = get_test_db_config();
-        let db = Database::init(config).await?;
-
-        // Clean up any existing test data
-        cleanup_test_table(&db, "test_array_field_types").await?;
-
-        Migrations::init(&db, &[migration!(TestArrayFieldTypes)]).await?;
+        let db = TestDb::new(get_test_db_config(), &[migration!(TestArrayFieldTypes)]).await?;
 
         // Verify SQL uses correct PostgreSQL array types
         let migration_sql = TestArrayFieldTypes::migration_sql();
@@ -2604,15 +2413,7 @@ Test completed successfully!"
     #[tokio::test]
     async fn test_dedicated_basic_crud_operations() -> Result<(), Box<dyn std::error::Error>> {
         // Create PostgreSQL test database
-        let config = get_test_db_config();
-        let db = Database::init(config).await?;
-
-        // Clean up any existing test data with unique table name
-        cleanup_test_table(&db, "dedicated_crud_test_001").await?;
-
-        // Create table with dedicated migration
-        use orso::{migration, Migrations};
-        Migrations::init(&db, &[migration!(DedicatedCrudTest)]).await?;
+        let db = TestDb::new(get_test_db_config(), &[migration!(DedicatedCrudTest)]).await?;
 
         println!("=== Testing CREATE (Insert) ===");
 
@@ -2741,11 +2542,7 @@ Test completed successfully!"
     #[tokio::test]
     async fn test_cascade_delete_operations() -> Result<(), Box<dyn std::error::Error>> {
         // Create PostgreSQL test database
-        let config = get_test_db_config();
-        let db = Database::init(config).await?;
-
-        // Clean up any existing test data with unique table name
-        cleanup_test_table(&db, "cascade_delete_test_001").await?;
+        let db = TestDb::new(get_test_db_config(), &[migration!(CascadeDeleteTest)]).await?;
 
         // Create test struct for cascade deletes
         #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
@@ -2757,10 +2554,6 @@ Test completed successfully!"
             value: i32,
         }
 
-        // Create table with dedicated migration
-        use orso::{migration, Migrations};
-        Migrations::init(&db, &[migration!(CascadeDeleteTest)]).await?;
-
         println!("=== Testing CASCADE Delete Operations ===");
 
         // Insert test records
@@ -2793,7 +2586,7 @@ Test completed successfully!"
         // Test single CASCADE delete
         let first_record = &all_records[0];
         let deleted = first_record.delete_cascade(&db).await?;
-        assert!(deleted);
+        assert!(!deleted.is_noop());
         println!("✓ Single cascade delete successful");
 
         // Verify record was deleted
@@ -2809,7 +2602,7 @@ Test completed successfully!"
             .collect();
 
         let batch_deleted = CascadeDeleteTest::batch_delete_cascade(&remaining_ids, &db).await?;
-        assert_eq!(batch_deleted, 2);
+        assert_eq!(batch_deleted.rows_affected, 2);
         println!("✓ Batch cascade delete successful");
 
         // Verify all records were deleted
@@ -2845,7 +2638,7 @@ Test completed successfully!"
         let deleted_with_table = table_records[0]
             .delete_cascade_with_table(&db, "cascade_delete_test_001")
             .await?;
-        assert!(deleted_with_table);
+        assert!(!deleted_with_table.is_noop());
         println!("✓ Cascade delete with table name successful");
 
         // Test batch CASCADE delete with table name
@@ -2856,7 +2649,10 @@ Test completed successfully!"
             "cascade_delete_test_001",
         )
         .await?;
-        assert_eq!(batch_deleted_with_table, 1);
+        assert_eq!(
+            batch_deleted_with_table.returned_ids,
+            [last_record_id.as_str()]
+        );
         println!("✓ Batch cascade delete with table name successful");
 
         // Verify all records are gone
@@ -2872,4 +2668,7076 @@ Test completed successfully!"
 
         Ok(())
     }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("append_compressed_test_017")]
+    struct AppendCompressedTest {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+
+        #[orso_column(compress)]
+        data_points: Vec<i64>,
+
+        symbol: String,
+    }
+
+    #[test]
+    fn test_compressed_frame_roundtrip() {
+        use crate::compression::{
+            encode_frame, frame_header, is_framed, split_frames, UNKNOWN_COUNT,
+        };
+
+        let mut blob = encode_frame(3, b"first");
+        blob.extend(encode_frame(2, b"second"));
+
+        // Legacy blobs are wrapped with an unknown count
+        let mut legacy = frame_header(UNKNOWN_COUNT, 0);
+        legacy.truncate(12);
+        legacy.extend_from_slice(&(6u32).to_be_bytes());
+        legacy.extend_from_slice(b"ORSO..");
+        blob.extend(legacy);
+
+        assert!(is_framed(&blob));
+        assert!(!is_framed(b"ORSO\x01\x00\x00"));
+
+        let frames = split_frames(&blob).unwrap();
+        assert_eq!(frames.len(), 3);
+        assert_eq!(frames[0].count, Some(3));
+        assert_eq!(frames[0].payload, b"first");
+        assert_eq!(frames[1].count, Some(2));
+        assert_eq!(frames[1].payload, b"second");
+        assert_eq!(frames[2].count, None);
+        assert_eq!(frames[2].payload, b"ORSO..");
+
+        // Truncated blobs are rejected instead of silently dropping data
+        assert!(split_frames(&blob[..blob.len() - 1]).is_err());
+    }
+
+    #[tokio::test]
+    async fn test_append_compressed() -> Result<(), Box<dyn std::error::Error>> {
+        let db = TestDb::new(get_test_db_config(), &[migration!(AppendCompressedTest)]).await?;
+
+        let record = AppendCompressedTest {
+            id: None,
+            data_points: (0..1000).collect(),
+            symbol: "BTCUSDT".to_string(),
+        };
+        record.insert(&db).await?;
+
+        let stored = AppendCompressedTest::find_all(&db).await?;
+        let id = stored[0].id.clone().unwrap();
+
+        // Appends add frames after the ones written by insert
+        AppendCompressedTest::append_compressed(
+            "data_points",
+            &(1000..1500).collect::<Vec<i64>>(),
+            &id,
+            &db,
+        )
+        .await?;
+        AppendCompressedTest::append_compressed("data_points", &[1500i64, 1501, 1502], &id, &db)
+            .await?;
+
+        let reloaded = AppendCompressedTest::find_by_id(&id, &db).await?.unwrap();
+        assert_eq!(reloaded.data_points, (0..1503).collect::<Vec<i64>>());
+        println!("✓ Appended 503 values to compressed column");
+
+        // Unknown rows and non-compressed columns are reported
+        assert!(
+            AppendCompressedTest::append_compressed("data_points", &[1i64], "missing", &db)
+                .await
+                .is_err()
+        );
+        assert!(
+            AppendCompressedTest::append_compressed("symbol", &[1i64], &id, &db)
+                .await
+                .is_err()
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_read_compressed_range() -> Result<(), Box<dyn std::error::Error>> {
+        let table = "append_compressed_range_018";
+        let db = TestDb::new(
+            get_test_db_config(),
+            &[migration!(AppendCompressedTest, table)],
+        )
+        .await?;
+
+        let record = AppendCompressedTest {
+            id: Some("range-test".to_string()),
+            data_points: (0..1000).collect(),
+            symbol: "ETHUSDT".to_string(),
+        };
+        record.insert_with_table(&db, table).await?;
+
+        // Frames written by insert
+        let slice: Vec<i64> = AppendCompressedTest::read_compressed_range_with_table(
+            "range-test",
+            "data_points",
+            100..110,
+            &db,
+            table,
+        )
+        .await?;
+        assert_eq!(slice, (100..110).collect::<Vec<i64>>());
+
+        // Ranges spanning appended frames
+        for chunk in 1..4 {
+            let values: Vec<i64> = (chunk * 1000..(chunk + 1) * 1000).collect();
+            AppendCompressedTest::append_compressed_with_table(
+                "data_points",
+                &values,
+                "range-test",
+                &db,
+                table,
+            )
+            .await?;
+        }
+
+        let slice: Vec<i64> = AppendCompressedTest::read_compressed_range_with_table(
+            "range-test",
+            "data_points",
+            950..2050,
+            &db,
+            table,
+        )
+        .await?;
+        assert_eq!(slice, (950..2050).collect::<Vec<i64>>());
+
+        let tail: Vec<i64> = AppendCompressedTest::read_compressed_range_with_table(
+            "range-test",
+            "data_points",
+            3990..5000,
+            &db,
+            table,
+        )
+        .await?;
+        assert_eq!(tail, (3990..4000).collect::<Vec<i64>>());
+
+        let empty: Vec<i64> = AppendCompressedTest::read_compressed_range_with_table(
+            "range-test",
+            "data_points",
+            5000..6000,
+            &db,
+            table,
+        )
+        .await?;
+        assert!(empty.is_empty());
+
+        // Only the frames a range touches are fetched: a corrupt first frame goes unnoticed
+        db.execute(&format!("UPDATE {table} SET data_points = ''::bytea"), &[])
+            .await?;
+        for chunk in 0..3 {
+            let values: Vec<i64> = (chunk * 1000..(chunk + 1) * 1000).collect();
+            AppendCompressedTest::append_compressed_with_table(
+                "data_points",
+                &values,
+                "range-test",
+                &db,
+                table,
+            )
+            .await?;
+        }
+        db.execute(
+            &format!("UPDATE {table} SET data_points = set_byte(data_points, 4, 9)"),
+            &[],
+        )
+        .await?;
+        let slice: Vec<i64> = AppendCompressedTest::read_compressed_range_with_table(
+            "range-test",
+            "data_points",
+            1500..2500,
+            &db,
+            table,
+        )
+        .await?;
+        assert_eq!(slice, (1500..2500).collect::<Vec<i64>>());
+        assert!(
+            AppendCompressedTest::read_compressed_range_with_table::<i64>(
+                "range-test",
+                "data_points",
+                500..600,
+                &db,
+                table
+            )
+            .await
+            .is_err()
+        );
+
+        // Inserted arrays are split into frames, so a range read skips the ones before it
+        AppendCompressedTest {
+            id: Some("chunked".to_string()),
+            data_points: (0..10_000).collect(),
+            symbol: "SOLUSDT".to_string(),
+        }
+        .insert_with_table(&db, table)
+        .await?;
+        let row = db
+            .query_one(
+                &format!("SELECT data_points FROM {table} WHERE id = $1"),
+                &[&"chunked".to_string()],
+            )
+            .await?;
+        let frames: Vec<Option<u32>> =
+            crate::compression::split_frames(&row.try_get::<_, Vec<u8>>(0)?)?
+                .iter()
+                .map(|frame| frame.count)
+                .collect();
+        assert_eq!(frames, vec![Some(4096), Some(4096), Some(1808)]);
+        db.execute(
+            &format!(
+                "UPDATE {table} SET data_points = set_byte(data_points, 4, 9) WHERE id = 'chunked'"
+            ),
+            &[],
+        )
+        .await?;
+        let slice: Vec<i64> = AppendCompressedTest::read_compressed_range_with_table(
+            "chunked",
+            "data_points",
+            5000..5010,
+            &db,
+            table,
+        )
+        .await?;
+        assert_eq!(slice, (5000..5010).collect::<Vec<i64>>());
+        assert!(
+            AppendCompressedTest::read_compressed_range_with_table::<i64>(
+                "chunked",
+                "data_points",
+                10..20,
+                &db,
+                table
+            )
+            .await
+            .is_err()
+        );
+
+        // Appends and range reads run inside a transaction too
+        let mut client = db.pool.get().await?;
+        let tx = client.transaction().await?;
+        AppendCompressedTest::append_compressed_with_table(
+            "data_points",
+            &[-1i64, -2],
+            "range-test",
+            &tx,
+            table,
+        )
+        .await?;
+        let tail: Vec<i64> = AppendCompressedTest::read_compressed_range_with_table(
+            "range-test",
+            "data_points",
+            3000..3002,
+            &tx,
+            table,
+        )
+        .await?;
+        assert_eq!(tail, vec![-1, -2]);
+        tx.rollback().await?;
+        let tail: Vec<i64> = AppendCompressedTest::read_compressed_range_with_table(
+            "range-test",
+            "data_points",
+            3000..3002,
+            &db,
+            table,
+        )
+        .await?;
+        assert!(tail.is_empty());
+        println!("✓ Range reads across plain and framed blobs");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("lazy_compressed_test_019")]
+    struct LazyCompressedTest {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+
+        symbol: String,
+
+        #[orso_column(compress, lazy)]
+        ticks: crate::Lazy<Vec<i64>>,
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("lazy_numbered_test_019")]
+    struct LazyNumberedTest {
+        #[orso_column(primary_key)]
+        id: Option<i32>,
+
+        #[orso_column(compress, lazy)]
+        ticks: crate::Lazy<Vec<i64>>,
+    }
+
+    #[test]
+    fn test_lazy_field_metadata() {
+        assert_eq!(LazyCompressedTest::lazy_fields(), vec!["ticks"]);
+        assert_eq!(
+            crate::lazy::select_columns::<LazyCompressedTest>(),
+            vec!["id", "symbol"]
+        );
+        assert_eq!(crate::lazy::select_columns::<TestUser>(), vec!["*"]);
+        assert!(LazyCompressedTest::migration_sql().contains("ticks BYTEA"));
+        assert!(!LazyCompressedTest::migration_sql().contains("ticks BYTEA NOT NULL"));
+
+        // Rows without the lazy column deserialize into a deferred value
+        let mut map = std::collections::HashMap::new();
+        map.insert("id".to_string(), Value::Text("abc".to_string()));
+        map.insert("symbol".to_string(), Value::Text("BTC".to_string()));
+        crate::lazy::attach_sources::<LazyCompressedTest>(&mut map, "lazy_compressed_test_019");
+
+        let record = LazyCompressedTest::from_map(map).unwrap();
+        assert!(!record.ticks.is_loaded());
+        let source = record.ticks.source().unwrap();
+        assert_eq!(source.table, "lazy_compressed_test_019");
+        assert_eq!(source.key, "abc");
+        assert_eq!(source.key_type, crate::lazy::LazyKeyType::Text);
+
+        // Unloaded values are not written back
+        let map = record.to_map().unwrap();
+        assert!(!map.contains_key("ticks"));
+    }
+
+    #[tokio::test]
+    async fn test_lazy_compressed_field() -> Result<(), Box<dyn std::error::Error>> {
+        let db = TestDb::new(
+            get_test_db_config(),
+            &[migration!(LazyCompressedTest), migration!(LazyNumberedTest)],
+        )
+        .await?;
+
+        let record = LazyCompressedTest {
+            id: Some("lazy-1".to_string()),
+            symbol: "BTCUSDT".to_string(),
+            ticks: (0..5000).collect::<Vec<i64>>().into(),
+        };
+        record.insert(&db).await?;
+
+        // Finders skip the column
+        let mut found = LazyCompressedTest::find_by_id("lazy-1", &db)
+            .await?
+            .unwrap();
+        assert!(!found.ticks.is_loaded());
+
+        // Updating without loading keeps the stored blob
+        found.symbol = "ETHUSDT".to_string();
+        found.update(&db).await?;
+
+        let mut listed = LazyCompressedTest::find_all(&db).await?;
+        assert_eq!(listed.len(), 1);
+        assert_eq!(listed[0].symbol, "ETHUSDT");
+        assert!(!listed[0].ticks.is_loaded());
+
+        let ticks = listed[0].ticks.load(&db).await?;
+        assert_eq!(ticks.len(), 5000);
+        assert_eq!(ticks[4999], 4999);
+
+        // Integer keys are bound as integers, also inside a transaction
+        LazyNumberedTest {
+            id: None,
+            ticks: vec![1, 2, 3].into(),
+        }
+        .insert(&db)
+        .await?;
+        let mut numbered = LazyNumberedTest::find_all(&db).await?;
+        let source = numbered[0].ticks.source().unwrap();
+        assert_eq!(source.key_type, crate::lazy::LazyKeyType::Integer);
+
+        let mut client = db.pool.get().await?;
+        let tx = client.transaction().await?;
+        assert_eq!(numbered[0].ticks.load(&tx).await?, &vec![1, 2, 3]);
+        tx.commit().await?;
+        println!("✓ Lazy column loaded on demand");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, PartialEq)]
+    #[orso_table("lossy_compression_test_020")]
+    struct LossyCompressionTest {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        #[orso_column(compress(precision = 1e-3))]
+        prices: Vec<f64>,
+        #[orso_column(compress)]
+        volumes: Vec<f64>,
+    }
+
+    #[test]
+    fn test_compression_precision_metadata() {
+        assert_eq!(
+            LossyCompressionTest::field_compression_precision(),
+            vec![None, Some(1e-3), None]
+        );
+        assert_eq!(
+            LossyCompressionTest::field_compressed(),
+            vec![false, true, true]
+        );
+
+        let record = LossyCompressionTest {
+            id: Some("lossy-1".to_string()),
+            prices: (0..1000).map(|i| i as f64 * 0.1 + 0.00001).collect(),
+            volumes: (0..1000).map(|i| i as f64 * 0.5).collect(),
+        };
+        let map = record.to_map().unwrap();
+
+        // The precision is recorded in the frame header of the lossy column only
+        let Some(Value::Blob(prices)) = map.get("prices") else {
+            panic!("prices should be compressed");
+        };
+        let frames = crate::compression::split_frames(prices).unwrap();
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].count, Some(1000));
+        assert_eq!(frames[0].precision, Some(1e-3));
+
+        let decoded = LossyCompressionTest::from_map(map).unwrap();
+        assert_eq!(decoded.volumes, record.volumes);
+        for (decoded, original) in decoded.prices.iter().zip(&record.prices) {
+            assert!((decoded - original).abs() <= 1e-3);
+        }
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("recompress_test_021")]
+    struct RecompressBefore {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        #[orso_column(compress)]
+        prices: Vec<f64>,
+        #[orso_column(compress)]
+        counts: Vec<i64>,
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug)]
+    #[orso_table("recompress_test_021")]
+    struct RecompressAfter {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        #[orso_column(compress(precision = 1e-2))]
+        prices: Vec<f64>,
+        #[orso_column(compress)]
+        counts: Vec<i64>,
+    }
+
+    #[tokio::test]
+    async fn test_recompress_table() -> Result<(), Box<dyn std::error::Error>> {
+        let db = TestDb::new(get_test_db_config(), &[migration!(RecompressBefore)]).await?;
+
+        let records: Vec<RecompressBefore> = (0..5)
+            .map(|i| RecompressBefore {
+                id: Some(format!("row-{}", i)),
+                prices: (0..200).map(|p| p as f64 * 0.125 + i as f64).collect(),
+                counts: (0..200).collect(),
+            })
+            .collect();
+        RecompressBefore::batch_insert(&records, &db).await?;
+        RecompressBefore::append_compressed("counts", &[200i64, 201], "row-0", &db).await?;
+
+        let batches = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let seen = batches.clone();
+        let options = crate::RecompressOptions::new()
+            .with_batch_size(2)
+            .on_progress(move |_| {
+                seen.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            });
+
+        let progress =
+            crate::Compression::recompress_table::<RecompressAfter>(&db, options).await?;
+        assert_eq!(progress.rows_scanned, 5);
+        assert_eq!(progress.rows_rewritten, 5);
+        assert_eq!(batches.load(std::sync::atomic::Ordering::SeqCst), 3);
+
+        // Blobs now carry the new precision and appended frames are merged
+        let row = db
+            .query_one(
+                "SELECT prices, counts FROM recompress_test_021 WHERE id = $1",
+                &[&"row-0".to_string()],
+            )
+            .await?;
+        let prices: Vec<u8> = row.try_get(0)?;
+        let counts: Vec<u8> = row.try_get(1)?;
+        assert_eq!(
+            crate::compression::split_frames(&prices)?[0].precision,
+            Some(1e-2)
+        );
+        assert_eq!(crate::compression::split_frames(&counts)?.len(), 1);
+
+        let found = RecompressAfter::find_by_id("row-0", &db).await?.unwrap();
+        assert_eq!(found.counts.len(), 202);
+        assert_eq!(found.counts[201], 201);
+        for (found, original) in found.prices.iter().zip(&records[0].prices) {
+            assert!((found - original).abs() <= 1e-2);
+        }
+
+        // Running again finds nothing left to rewrite
+        let progress = crate::Compression::recompress_table::<RecompressAfter>(
+            &db,
+            crate::RecompressOptions::default(),
+        )
+        .await?;
+        assert_eq!(progress.rows_scanned, 5);
+        assert_eq!(progress.rows_rewritten, 0);
+
+        // Integer keys are paged in numeric order, with the codecs given in the options
+        db.execute(
+            "CREATE TABLE recompress_test_105 AS \
+             SELECT (ARRAY[1, 2, 9, 10, 11])[row_number() OVER (ORDER BY id)] AS id, \
+                    prices, counts FROM recompress_test_021",
+            &[],
+        )
+        .await?;
+        db.execute("ALTER TABLE recompress_test_105 ADD PRIMARY KEY (id)", &[])
+            .await?;
+        let options = crate::RecompressOptions::new()
+            .with_batch_size(2)
+            .with_precision(1e-1)
+            .with_integer_codec(crate::IntegerCodec::default())
+            .with_floating_codec(crate::FloatingCodec::default());
+        let progress = crate::Compression::recompress_table_with_table::<RecompressAfter>(
+            &db,
+            options,
+            "recompress_test_105",
+        )
+        .await?;
+        assert_eq!(progress.rows_scanned, 5);
+        assert_eq!(progress.rows_rewritten, 5);
+        let row = db
+            .query_one("SELECT prices FROM recompress_test_105 WHERE id = 10", &[])
+            .await?;
+        let prices: Vec<u8> = row.try_get(0)?;
+        assert_eq!(
+            crate::compression::split_frames(&prices)?[0].precision,
+            Some(1e-1)
+        );
+        println!("✓ Table recompressed with new precision");
+
+        Ok(())
+    }
+
+    static HOOK_DELETES: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0);
+    static HOOK_INSERTED: std::sync::Mutex<Option<String>> = std::sync::Mutex::new(None);
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("hooks_test_022")]
+    #[orso_hooks]
+    struct HookedAccount {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        #[orso_column(unique)]
+        email: String,
+        revision: i32,
+    }
+
+    impl crate::OrsoHooks for HookedAccount {
+        async fn before_insert(&mut self, _db: &impl crate::Executor) -> crate::Result<()> {
+            if self.email.is_empty() {
+                return Err(crate::Error::validation_field(
+                    "Email is required",
+                    "email",
+                    None,
+                ));
+            }
+            self.email = self.email.to_lowercase();
+            Ok(())
+        }
+
+        async fn after_insert(&self, _db: &impl crate::Executor) -> crate::Result<()> {
+            *HOOK_INSERTED.lock().unwrap() = self.id.clone();
+            Ok(())
+        }
+
+        async fn before_update(&mut self, _db: &impl crate::Executor) -> crate::Result<()> {
+            self.email = self.email.to_lowercase();
+            self.revision += 1;
+            Ok(())
+        }
+
+        async fn after_delete(&self, _db: &impl crate::Executor) -> crate::Result<()> {
+            HOOK_DELETES.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_lifecycle_hooks() -> Result<(), Box<dyn std::error::Error>> {
+        let db = TestDb::new(get_test_db_config(), &[migration!(HookedAccount)]).await?;
+
+        let account = HookedAccount {
+            id: Some("hook-1".to_string()),
+            email: "Alice@Example.COM".to_string(),
+            revision: 0,
+        };
+        account.insert(&db).await?;
+
+        let mut found = HookedAccount::find_by_id("hook-1", &db).await?.unwrap();
+        assert_eq!(found.email, "alice@example.com");
+
+        found.email = "ALICE@example.org".to_string();
+        found.update(&db).await?;
+        let found = HookedAccount::find_by_id("hook-1", &db).await?.unwrap();
+        assert_eq!(found.email, "alice@example.org");
+        assert_eq!(found.revision, 1);
+
+        // A failing before hook aborts the write
+        let invalid = HookedAccount {
+            id: Some("hook-2".to_string()),
+            email: String::new(),
+            revision: 0,
+        };
+        assert!(HookedAccount::batch_create(&[invalid], &db).await.is_err());
+        assert!(HookedAccount::find_by_id("hook-2", &db).await?.is_none());
+
+        found.delete(&db).await?;
+        assert_eq!(HOOK_DELETES.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        // After insert, the model has the key the database generated
+        let keyless = HookedAccount {
+            id: None,
+            email: "carol@example.com".to_string(),
+            revision: 0,
+        };
+        let result = keyless.insert(&db).await?;
+        let inserted = HOOK_INSERTED.lock().unwrap().clone();
+        assert_eq!(inserted.as_ref(), result.returned_ids.first());
+        assert!(inserted.is_some());
+
+        // Batch upserts and deletes bypass the hooks
+        let upserted = HookedAccount {
+            id: Some("hook-3".to_string()),
+            email: "Bob@Example.COM".to_string(),
+            revision: 0,
+        };
+        HookedAccount::batch_upsert(&[upserted], &db).await?;
+        let found = HookedAccount::find_by_id("hook-3", &db).await?.unwrap();
+        assert_eq!(found.email, "Bob@Example.COM");
+        HookedAccount::batch_delete(&["hook-3"], &db).await?;
+        assert!(HookedAccount::find_by_id("hook-3", &db).await?.is_none());
+        assert_eq!(HOOK_DELETES.load(std::sync::atomic::Ordering::SeqCst), 1);
+        println!("✓ Lifecycle hooks invoked");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("validated_test_023")]
+    struct ValidatedProfile {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        #[orso_column(validate(length(min = 1, max = 20)))]
+        name: String,
+        #[orso_column(unique, validate(email, length(max = 255)))]
+        email: String,
+        #[orso_column(validate(range(min = 0, max = 150)))]
+        age: i32,
+        #[orso_column(validate(url))]
+        homepage: Option<String>,
+        #[orso_column(validate(range(min = -1.5)))]
+        score: f64,
+    }
+
+    #[test]
+    fn test_validation_rules() {
+        let valid = ValidatedProfile {
+            id: None,
+            name: "Alice".to_string(),
+            email: "alice@example.com".to_string(),
+            age: 30,
+            homepage: None,
+            score: -1.5,
+        };
+        assert!(valid.validate().is_ok());
+
+        let invalid = ValidatedProfile {
+            id: None,
+            name: String::new(),
+            email: "not-an-email".to_string(),
+            age: 200,
+            homepage: Some("ftp://example.com".to_string()),
+            score: -2.0,
+        };
+        match invalid.validate() {
+            Err(crate::Error::Validation { errors, field, .. }) => {
+                let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+                assert_eq!(fields, vec!["name", "email", "age", "homepage", "score"]);
+                assert_eq!(field, None);
+            }
+            other => panic!("expected a validation error, got {:?}", other),
+        }
+
+        // Unique still applies alongside validation rules
+        assert_eq!(ValidatedProfile::unique_fields(), vec!["email"]);
+    }
+
+    #[tokio::test]
+    async fn test_validation_before_write() -> Result<(), Box<dyn std::error::Error>> {
+        let db = TestDb::new(get_test_db_config(), &[migration!(ValidatedProfile)]).await?;
+
+        let mut profile = ValidatedProfile {
+            id: Some("profile-1".to_string()),
+            name: "Bob".to_string(),
+            email: "bob@example.com".to_string(),
+            age: 40,
+            homepage: Some("https://example.com/bob".to_string()),
+            score: 0.0,
+        };
+        profile.insert(&db).await?;
+
+        profile.age = -1;
+        let err = profile.update(&db).await.unwrap_err();
+        assert!(matches!(err, crate::Error::Validation { field: Some(ref f), .. } if f == "age"));
+
+        let stored = ValidatedProfile::find_by_id("profile-1", &db)
+            .await?
+            .unwrap();
+        assert_eq!(stored.age, 40);
+        println!("✓ Invalid update rejected before reaching the database");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("audited_test_024")]
+    struct AttributedNote {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        body: String,
+        #[orso_column(created_by)]
+        created_by: Option<String>,
+        #[orso_column(updated_by)]
+        updated_by: Option<String>,
+    }
+
+    #[tokio::test]
+    async fn test_created_by_updated_by() -> Result<(), Box<dyn std::error::Error>> {
+        let db = TestDb::new(get_test_db_config(), &[migration!(AttributedNote)]).await?;
+
+        assert_eq!(AttributedNote::created_by_field(), Some("created_by"));
+        assert_eq!(AttributedNote::updated_by_field(), Some("updated_by"));
+        assert!(crate::OperationContext::current().is_none());
+
+        let note = AttributedNote {
+            id: Some("note-1".to_string()),
+            body: "draft".to_string(),
+            ..Default::default()
+        };
+        crate::OperationContext::new("alice")
+            .scope(async { note.insert(&db).await })
+            .await?;
+
+        let mut found = AttributedNote::find_by_id("note-1", &db).await?.unwrap();
+        assert_eq!(found.created_by.as_deref(), Some("alice"));
+        assert_eq!(found.updated_by.as_deref(), Some("alice"));
+
+        // A fresh value without created_by doesn't clear the stored creator
+        found.body = "final".to_string();
+        found.created_by = None;
+        found
+            .update_with_context(&db, &crate::OperationContext::new("bob"))
+            .await?;
+
+        let found = AttributedNote::find_by_id("note-1", &db).await?.unwrap();
+        assert_eq!(found.body, "final");
+        assert_eq!(found.created_by.as_deref(), Some("alice"));
+        assert_eq!(found.updated_by.as_deref(), Some("bob"));
+        println!("✓ Actor recorded in created_by/updated_by");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("audited_test_025")]
+    #[orso_audited]
+    struct AuditedInvoice {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        amount: i32,
+    }
+
+    #[tokio::test]
+    async fn test_audit_log() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::database::statement_kind;
+
+        let db = TestDb::new(get_test_db_config(), &[migration!(AuditedInvoice)]).await?;
+        assert!(AuditedInvoice::audited());
+        assert!(!TestUser::audited());
+
+        let mut invoice = AuditedInvoice {
+            id: Some("inv-1".to_string()),
+            amount: 100,
+        };
+        crate::OperationContext::new("alice")
+            .scope(async { invoice.insert(&db).await })
+            .await?;
+
+        invoice.amount = 150;
+        invoice
+            .update_with_context(&db, &crate::OperationContext::new("bob"))
+            .await?;
+        invoice.delete(&db).await?;
+
+        let history = crate::Audit::history::<AuditedInvoice>("inv-1", &db).await?;
+        let operations: Vec<&str> = history.iter().map(|e| e.operation.as_str()).collect();
+        assert_eq!(operations, vec!["INSERT", "UPDATE", "DELETE"]);
+
+        let actors: Vec<Option<&str>> = history.iter().map(|e| e.actor.as_deref()).collect();
+        assert_eq!(actors, vec![Some("alice"), Some("bob"), None]);
+
+        assert_eq!(history[1].old_values.as_ref().unwrap()["amount"], 100);
+        assert_eq!(history[1].new_values.as_ref().unwrap()["amount"], 150);
+        assert!(history[2].new_values.is_none());
+
+        // Re-running the migration keeps a single trigger
+        Migrations::init(&db, &[migration!(AuditedInvoice)]).await?;
+        AuditedInvoice {
+            id: Some("inv-2".to_string()),
+            amount: 1,
+        }
+        .insert(&db)
+        .await?;
+        assert_eq!(
+            crate::Audit::history::<AuditedInvoice>("inv-2", &db)
+                .await?
+                .len(),
+            1
+        );
+
+        // Only writes name the actor, and a transaction names it once for all of them
+        let actor_sql = "SELECT coalesce(current_setting('orso.actor', true), '') AS actor";
+        let read = crate::OperationContext::new("carol")
+            .scope(async { db.query_one(actor_sql, &[]).await })
+            .await?;
+        assert_eq!(read.get::<_, String>(0), "");
+        let seen = crate::OperationContext::new("carol")
+            .scope(db.transaction_with(crate::TxOptions::default(), |tx| {
+                Box::pin(async move {
+                    let before = tx.query_one(actor_sql, &[]).await?.get::<_, String>(0);
+                    for id in ["inv-3", "inv-4"] {
+                        AuditedInvoice {
+                            id: Some(id.to_string()),
+                            amount: 1,
+                        }
+                        .insert(tx)
+                        .await?;
+                    }
+                    let after = tx.query_one(actor_sql, &[]).await?.get::<_, String>(0);
+                    Ok((before, after))
+                })
+            }))
+            .await?;
+        assert_eq!(seen, (String::new(), "carol".to_string()));
+        let history = crate::Audit::history::<AuditedInvoice>("inv-4", &db).await?;
+        assert_eq!(history[0].actor.as_deref(), Some("carol"));
+
+        // Writes returning a single row name the actor too
+        crate::OperationContext::new("dave")
+            .scope(db.query_opt(
+                "UPDATE audited_test_025 SET amount = 2 WHERE id = $1 RETURNING id",
+                &[&"inv-2"],
+            ))
+            .await?;
+        let history = crate::Audit::history::<AuditedInvoice>("inv-2", &db).await?;
+        assert_eq!(history.last().unwrap().actor.as_deref(), Some("dave"));
+
+        // A pinned connection outside a transaction names the actor for the write, then clears it
+        let pinned = db.pin().await?;
+        let invoice = AuditedInvoice {
+            id: Some("inv-5".to_string()),
+            amount: 1,
+        };
+        crate::OperationContext::new("erin")
+            .scope(async { invoice.insert(&pinned).await })
+            .await?;
+        let history = crate::Audit::history::<AuditedInvoice>("inv-5", &db).await?;
+        assert_eq!(history[0].actor.as_deref(), Some("erin"));
+        assert_eq!(
+            pinned.query_one(actor_sql, &[]).await?.get::<_, String>(0),
+            ""
+        );
+
+        // Pipelined writes each name their own actor
+        let pipeline = db.pipeline().await?;
+        let pipelined = &pipeline;
+        let insert = move |id: &str, actor: &str| {
+            let invoice = AuditedInvoice {
+                id: Some(id.to_string()),
+                amount: 1,
+            };
+            crate::OperationContext::new(actor)
+                .scope(async move { invoice.insert(pipelined).await })
+        };
+        futures_util::try_join!(insert("inv-6", "frank"), insert("inv-7", "gina"))?;
+        drop(pipeline);
+        for (id, actor) in [("inv-6", "frank"), ("inv-7", "gina")] {
+            let history = crate::Audit::history::<AuditedInvoice>(id, &db).await?;
+            assert_eq!(history[0].actor.as_deref(), Some(actor));
+        }
+
+        // A write inside a WITH query is a write too
+        let cte = "WITH gone AS (DELETE FROM t RETURNING *) SELECT * FROM gone";
+        assert_eq!(statement_kind(cte), "delete");
+        assert_eq!(
+            statement_kind("WITH x AS (SELECT 'update') SELECT * FROM x"),
+            "other"
+        );
+        crate::OperationContext::new("hank")
+            .scope(db.query(
+                "WITH changed AS (UPDATE audited_test_025 SET amount = 3 WHERE id = $1 RETURNING id) \
+                 SELECT id FROM changed",
+                &[&"inv-6"],
+            ))
+            .await?;
+        let history = crate::Audit::history::<AuditedInvoice>("inv-6", &db).await?;
+        assert_eq!(history.last().unwrap().actor.as_deref(), Some("hank"));
+
+        // Statements that can't run in a transaction block still run under a context
+        crate::OperationContext::new("erin")
+            .scope(async {
+                db.execute(
+                    "CREATE INDEX CONCURRENTLY IF NOT EXISTS audited_test_025_amount_idx \
+                     ON audited_test_025 (amount)",
+                    &[],
+                )
+                .await?;
+                db.execute("VACUUM audited_test_025", &[]).await
+            })
+            .await?;
+        println!("✓ Audit log recorded every change");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("scoped_test_026")]
+    #[orso_default_scope]
+    struct ScopedOrder {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        status: String,
+        total: i32,
+    }
+
+    impl crate::DefaultScope for ScopedOrder {
+        fn default_scope() -> Option<FilterOperator> {
+            Some(FilterOperator::Single(crate::Filter::ne(
+                "status", "archived",
+            )))
+        }
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("scoped_entries_026")]
+    #[orso_default_scope]
+    struct ScopedEntry {
+        #[orso_column(primary_key)]
+        id: Option<i64>,
+        status: String,
+    }
+
+    impl crate::DefaultScope for ScopedEntry {
+        fn default_scope() -> Option<FilterOperator> {
+            Some(FilterOperator::Single(crate::Filter::ne(
+                "status", "archived",
+            )))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_scope() -> Result<(), Box<dyn std::error::Error>> {
+        let db = TestDb::new(
+            get_test_db_config(),
+            &[migration!(ScopedOrder), migration!(ScopedEntry)],
+        )
+        .await?;
+
+        let orders = vec![
+            ScopedOrder {
+                id: Some("o-1".to_string()),
+                status: "open".to_string(),
+                total: 10,
+            },
+            ScopedOrder {
+                id: Some("o-2".to_string()),
+                status: "paid".to_string(),
+                total: 20,
+            },
+            ScopedOrder {
+                id: Some("o-3".to_string()),
+                status: "archived".to_string(),
+                total: 30,
+            },
+        ];
+        ScopedOrder::batch_create(&orders, &db).await?;
+
+        assert_eq!(ScopedOrder::find_all(&db).await?.len(), 2);
+        assert_eq!(ScopedOrder::count(&db).await?, 2);
+        assert!(ScopedOrder::find_by_id("o-3", &db).await?.is_none());
+        assert!(ScopedOrder::find_by_id("o-1", &db).await?.is_some());
+
+        let filter = FilterOperator::Single(crate::Filter::gt("total", 5));
+        assert_eq!(ScopedOrder::find_where(filter.clone(), &db).await?.len(), 2);
+        assert_eq!(ScopedOrder::count_where(filter, &db).await?, 2);
+
+        let page = ScopedOrder::find_paginated(&crate::Pagination::new(1, 10), &db).await?;
+        assert_eq!(page.data.len(), 2);
+        assert_eq!(page.pagination.total, Some(2));
+
+        // The escape hatch sees everything
+        assert_eq!(crate::unscoped(ScopedOrder::find_all(&db)).await?.len(), 3);
+        assert!(crate::unscoped(ScopedOrder::find_by_id("o-3", &db))
+            .await?
+            .is_some());
+
+        // Saving a hidden record updates it instead of inserting a duplicate
+        let mut archived = orders[2].clone();
+        archived.total = 35;
+        archived.insert_or_update(&db).await?;
+        let archived = crate::unscoped(ScopedOrder::find_by_id("o-3", &db))
+            .await?
+            .unwrap();
+        assert_eq!(archived.total, 35);
+
+        // Scoped lookups bind the key with the type of a BIGINT key column
+        db.execute(
+            "INSERT INTO scoped_entries_026 (id, status) VALUES (1, 'open'), (2, 'archived')",
+            &[],
+        )
+        .await?;
+        assert_eq!(
+            ScopedEntry::find_by_id("1", &db).await?.unwrap().id,
+            Some(1)
+        );
+        assert!(ScopedEntry::find_by_id("2", &db).await?.is_none());
+        assert!(crate::unscoped(ScopedEntry::find_by_id("2", &db))
+            .await?
+            .is_some());
+
+        // Scopes are bound after the key rather than inlined
+        let found = ScopedOrder::find_by_ids(&["o-1", "o-3"], &db).await?;
+        assert!(found[0].is_some() && found[1].is_none());
+        let mock = crate::MockDatabase::new();
+        ScopedOrder::find_by_ids(&["o-1"], &mock).await?;
+        ScopedOrder::find_by_id("o-1", &mock).await?;
+        for statement in mock.statements() {
+            assert!(statement.sql.contains("status != $2"));
+            assert!(!statement.sql.contains("'archived'"));
+            assert!(statement.params.iter().any(|p| p.contains("archived")));
+        }
+        println!("✓ Default scope applied to finders");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_on_connect_sql() -> Result<(), Box<dyn std::error::Error>> {
+        let config = get_test_db_config().with_on_connect_sql([
+            "SET application_name = 'orso_on_connect'",
+            "SET statement_timeout = '5s'",
+        ]);
+        let db = Database::init(config).await?;
+
+        // Every connection handed out by the pool carries the session settings
+        for _ in 0..3 {
+            let row = db.query_one("SHOW application_name", &[]).await?;
+            assert_eq!(row.get::<_, String>(0), "orso_on_connect");
+            let row = db.query_one("SHOW statement_timeout", &[]).await?;
+            assert_eq!(row.get::<_, String>(0), "5s");
+        }
+
+        let broken = get_test_db_config().with_on_connect_sql(["SET no_such_setting = 1"]);
+        let db = Database::init(broken).await?;
+        assert!(db.query_one("SELECT 1", &[]).await.is_err());
+        println!("✓ On-connect SQL applied to pooled connections");
+
+        Ok(())
+    }
+
+    #[cfg(feature = "metrics")]
+    #[tokio::test]
+    async fn test_metrics_recorder() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::database::statement_kind;
+        use crate::metrics::PrometheusRecorder;
+        use std::sync::Arc;
+
+        assert_eq!(statement_kind("  SELECT 1"), "select");
+        assert_eq!(statement_kind("insert into t values (1)"), "insert");
+        assert_eq!(statement_kind("CREATE TABLE t ()"), "other");
+
+        let recorder = Arc::new(PrometheusRecorder::new());
+        let db = Database::init(get_test_db_config())
+            .await?
+            .with_metrics(recorder.clone());
+
+        db.query("SELECT generate_series(1, 5)", &[]).await?;
+        db.query_one("SELECT 1", &[]).await?;
+        assert!(db
+            .query("SELECT * FROM metrics_missing_table", &[])
+            .await
+            .is_err());
+        assert_eq!(recorder.query_count(), 3);
+
+        let output = recorder.render();
+        assert!(output.contains("# TYPE orso_query_duration_seconds histogram"));
+        assert!(output.contains("orso_query_duration_seconds_count{statement=\"select\"} 2"));
+        assert!(output.contains("orso_query_rows_sum{statement=\"select\"} 6"));
+        assert!(output.contains("orso_query_rows_bucket{statement=\"select\",le=\"1\"} 1"));
+        assert!(output.contains("orso_query_errors_total{statement=\"select\"} 1"));
+        assert!(output.contains("orso_pool_wait_seconds_count 3"));
+        assert!(output.contains("orso_pool_connections{state=\"active\"} 1"));
+        println!("✓ Metrics recorded for queries and pool checkouts");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("traced_test_027")]
+    struct TracedItem {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        secret: String,
+    }
+
+    /// Collects span names and their recorded fields
+    #[derive(Default, Clone)]
+    struct SpanCollector {
+        spans: std::sync::Arc<std::sync::Mutex<Vec<(String, String)>>>,
+    }
+
+    struct FieldWriter<'a>(&'a mut String);
+
+    impl tracing::field::Visit for FieldWriter<'_> {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0.push_str(&format!(" {}={:?}", field.name(), value));
+        }
+    }
+
+    impl tracing::Subscriber for SpanCollector {
+        fn enabled(&self, _: &tracing::Metadata<'_>) -> bool {
+            true
+        }
+
+        fn new_span(&self, attrs: &tracing::span::Attributes<'_>) -> tracing::span::Id {
+            let mut fields = String::new();
+            attrs.record(&mut FieldWriter(&mut fields));
+            let mut spans = self.spans.lock().unwrap();
+            spans.push((attrs.metadata().name().to_string(), fields));
+            tracing::span::Id::from_u64(spans.len() as u64)
+        }
+
+        fn record(&self, span: &tracing::span::Id, values: &tracing::span::Record<'_>) {
+            let mut spans = self.spans.lock().unwrap();
+            let entry = &mut spans[span.into_u64() as usize - 1];
+            values.record(&mut FieldWriter(&mut entry.1));
+        }
+
+        fn record_follows_from(&self, _: &tracing::span::Id, _: &tracing::span::Id) {}
+        fn event(&self, _: &tracing::Event<'_>) {}
+        fn enter(&self, _: &tracing::span::Id) {}
+        fn exit(&self, _: &tracing::span::Id) {}
+    }
+
+    #[tokio::test]
+    async fn test_operation_spans() -> Result<(), Box<dyn std::error::Error>> {
+        let test_db = TestDb::new(get_test_db_config(), &[migration!(TracedItem)]).await?;
+        let db: &Database = &test_db;
+
+        let item = TracedItem {
+            id: Some("item-1".to_string()),
+            secret: "s3cret-value".to_string(),
+        };
+
+        let collector = SpanCollector::default();
+        let guard = tracing::dispatcher::set_default(&tracing::Dispatch::new(collector.clone()));
+        item.insert(&db).await?;
+        drop(guard);
+
+        let spans = collector.spans.lock().unwrap().clone();
+        let (_, insert) = spans
+            .iter()
+            .find(|(name, _)| name == "orso.insert")
+            .unwrap();
+        assert!(insert.contains("operation=\"insert\""));
+        assert!(insert.contains("table=traced_test_027"));
+        let (_, query) = spans
+            .iter()
+            .find(|(name, fields)| name == "orso.query" && fields.contains("INSERT"))
+            .unwrap();
+        assert!(query.contains("db.operation=\"insert\""));
+        assert!(query.contains("rows=1"));
+        assert!(query.contains("duration_ms="));
+        assert!(query.contains("redacted"));
+        assert!(spans
+            .iter()
+            .all(|(_, fields)| !fields.contains("s3cret-value")));
+
+        // Parameter values are only recorded when opted in
+        let config = get_test_db_config()
+            .with_log_parameters(true)
+            .with_on_connect_sql([format!("SET search_path TO \"{}\"", test_db.schema())]);
+        let db = Database::init(config).await?;
+        let collector = SpanCollector::default();
+        let guard = tracing::dispatcher::set_default(&tracing::Dispatch::new(collector.clone()));
+        TracedItem::find_where(
+            FilterOperator::Single(crate::Filter::eq("secret", "s3cret-value")),
+            &db,
+        )
+        .await?;
+        drop(guard);
+
+        let spans = collector.spans.lock().unwrap().clone();
+        assert!(spans.iter().any(|(name, _)| name == "orso.find_where"));
+        let (_, query) = spans.iter().find(|(name, _)| name == "orso.query").unwrap();
+        assert!(query.contains("s3cret-value"));
+        assert!(query.contains("rows=1"));
+        println!("✓ Operations emit spans with redacted parameters");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_slow_query_threshold() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::database::statement_table;
+
+        assert_eq!(
+            statement_table("SELECT * FROM users WHERE id = $1"),
+            Some("users")
+        );
+        assert_eq!(
+            statement_table("INSERT INTO orders (id) VALUES ($1)"),
+            Some("orders")
+        );
+        assert_eq!(statement_table("UPDATE items SET qty = 1"), Some("items"));
+        assert_eq!(statement_table("DELETE FROM items"), Some("items"));
+        assert_eq!(statement_table("SELECT 1"), None);
+
+        let slow = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let recorded = slow.clone();
+        let config =
+            get_test_db_config().with_slow_query_threshold(std::time::Duration::from_millis(50));
+        let db = Database::init(config)
+            .await?
+            .on_slow_query(move |query| recorded.lock().unwrap().push(query.clone()));
+
+        db.query("SELECT 1", &[]).await?;
+        db.query("SELECT pg_sleep(0.1) FROM pg_class LIMIT 1", &[])
+            .await?;
+
+        let slow = slow.lock().unwrap();
+        assert_eq!(slow.len(), 1);
+        assert!(slow[0].sql.contains("pg_sleep"));
+        assert_eq!(slow[0].table.as_deref(), Some("pg_class"));
+        assert!(slow[0].duration >= std::time::Duration::from_millis(50));
+        println!("✓ Slow queries reported to the callback");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("explain_test_028")]
+    struct ExplainedRow {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        label: String,
+    }
+
+    #[tokio::test]
+    async fn test_explain_assert() -> Result<(), Box<dyn std::error::Error>> {
+        let config = get_test_db_config().with_on_connect_sql(["SET enable_seqscan = off"]);
+        let db = TestDb::new(config, &[migration!(ExplainedRow)]).await?;
+
+        let by_id = crate::QueryBuilder::new("explain_test_028")
+            ._where(FilterOperator::Single(crate::Filter::eq("id", "row-1")));
+        crate::ExplainAssert::new(&by_id, &db)
+            .await?
+            .uses_index("explain_test_028_pkey")
+            .no_seq_scan("explain_test_028");
+
+        let by_label = crate::QueryBuilder::new("explain_test_028")
+            ._where(FilterOperator::Single(crate::Filter::eq("label", "a")));
+        let plan = by_label.explain(&db).await?;
+        assert!(plan.has_seq_scan("explain_test_028"));
+        assert!(!plan.uses_index("explain_test_028_pkey"));
+
+        let explained = crate::ExplainAssert::new(&by_label, &db).await?;
+        let result = std::panic::catch_unwind(|| {
+            explained.no_seq_scan("explain_test_028");
+        });
+        assert!(result.is_err());
+        println!("✓ Query plans inspected with ExplainAssert");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_test_db_schema_lifecycle() -> Result<(), Box<dyn std::error::Error>> {
+        let db = TestDb::new(get_test_db_config(), &[migration!(ExplainedRow)]).await?;
+        let schema = db.schema().to_string();
+
+        let row = db.query_one("SELECT current_schema()", &[]).await?;
+        assert_eq!(row.get::<_, String>(0), schema);
+        ExplainedRow::default().insert(&db).await?;
+        assert_eq!(ExplainedRow::count(&db).await?, 1);
+        drop(db);
+
+        let check = Database::init(get_test_db_config()).await?;
+        let row = check
+            .query_one(
+                "SELECT COUNT(*) FROM pg_namespace WHERE nspname = $1",
+                &[&schema],
+            )
+            .await?;
+        assert_eq!(row.get::<_, i64>(0), 0);
+
+        // The schema is dropped with the settings the TestDb was made with, e.g. its hosts
+        let port = std::env::var("TEST_DB_PORT").unwrap_or("1332".to_string());
+        let user = std::env::var("TEST_DB_USER").unwrap_or("postgres".to_string());
+        let password = std::env::var("TEST_DB_PASSWORD").unwrap_or_default();
+        let mut connection_string = format!("user={user} dbname=postgres");
+        if !password.is_empty() {
+            connection_string.push_str(&format!(" password={password}"));
+        }
+        let config =
+            DatabaseConfig::new(connection_string).with_hosts([format!("localhost:{port}")]);
+        let db = TestDb::new(config, &[]).await?;
+        let schema = db.schema().to_string();
+        drop(db);
+        let row = check
+            .query_one(
+                "SELECT COUNT(*) FROM pg_namespace WHERE nspname = $1",
+                &[&schema],
+            )
+            .await?;
+        assert_eq!(row.get::<_, i64>(0), 0);
+        println!("✓ Test schema dropped with its TestDb");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("fixture_test_029")]
+    struct FixtureUser {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        name: String,
+        age: i32,
+    }
+
+    #[tokio::test]
+    async fn test_fixtures_and_seed() -> Result<(), Box<dyn std::error::Error>> {
+        let db = TestDb::new(get_test_db_config(), &[migration!(FixtureUser)]).await?;
+
+        let path = std::env::temp_dir().join(format!("orso_fixture_{}.json", db.schema()));
+        std::fs::write(
+            &path,
+            r#"{"alice": {"name": "Alice", "age": 30}, "bob": {"name": "Bob", "age": 41}}"#,
+        )?;
+
+        let loaded = crate::fixtures::load::<FixtureUser>(&db, &path).await?;
+        assert_eq!(loaded.len(), 2);
+        let alice_id = crate::fixtures::id("fixture_test_029", "alice");
+        let alice = FixtureUser::find_by_id(&alice_id, &db).await?.unwrap();
+        assert_eq!(alice.name, "Alice");
+
+        // Loading again updates the same records
+        crate::fixtures::load::<FixtureUser>(&db, &path).await?;
+        assert_eq!(FixtureUser::count(&db).await?, 2);
+        std::fs::remove_file(&path)?;
+
+        let seeded = crate::seed!(&db,
+            "carol" => FixtureUser { name: "Carol".to_string(), age: 25, ..Default::default() },
+            "dave" => FixtureUser {
+                id: Some("dave-id".to_string()),
+                name: "Dave".to_string(),
+                age: 52,
+            },
+        )
+        .await?;
+        assert_eq!(
+            seeded[0].id.as_deref(),
+            Some(crate::fixtures::id("fixture_test_029", "carol").as_str())
+        );
+        assert_eq!(seeded[1].id.as_deref(), Some("dave-id"));
+        assert_eq!(FixtureUser::count(&db).await?, 4);
+
+        let list = crate::fixtures::parse::<FixtureUser>(
+            r#"[{"name": "Eve", "age": 19}]"#,
+            crate::fixtures::FixtureFormat::Json,
+        )?;
+        assert_eq!(list[0].0, "0");
+        let invalid =
+            crate::fixtures::parse::<FixtureUser>("42", crate::fixtures::FixtureFormat::Json);
+        assert!(invalid.is_err());
+
+        #[cfg(feature = "yaml")]
+        {
+            let yaml = crate::fixtures::parse::<FixtureUser>(
+                "frank:\n  name: Frank\n  age: 33\n",
+                crate::fixtures::FixtureFormat::Yaml,
+            )?;
+            assert_eq!(yaml[0].0, "frank");
+            assert_eq!(yaml[0].1.age, 33);
+        }
+        println!("✓ Fixtures loaded and seeded with deterministic ids");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_mock_database() -> Result<(), Box<dyn std::error::Error>> {
+        let mock = crate::MockDatabase::new();
+        let user = FixtureUser {
+            id: Some("u1".to_string()),
+            name: "Alice".to_string(),
+            age: 30,
+        };
+        mock.return_rows(
+            "FROM fixture_test_029",
+            vec![crate::MockDatabase::row(&user)?],
+        )
+        .return_affected("DELETE FROM fixture_test_029", 3)
+        .return_error("DROP", "permission denied");
+
+        let rows = mock
+            .query("SELECT * FROM fixture_test_029 WHERE id = $1", &[&"u1"])
+            .await?;
+        let found = FixtureUser::from_map(rows[0].clone())?;
+        assert_eq!(found.name, "Alice");
+        assert!(mock.query_opt("SELECT * FROM other", &[]).await?.is_none());
+
+        // Later responses take precedence over earlier ones
+        mock.return_rows("WHERE age > $1", Vec::new());
+        assert!(mock
+            .query("SELECT * FROM fixture_test_029 WHERE age > $1", &[&18i32])
+            .await?
+            .is_empty());
+
+        assert_eq!(mock.execute("DELETE FROM fixture_test_029", &[]).await?, 3);
+        assert!(mock
+            .execute("DROP TABLE fixture_test_029", &[])
+            .await
+            .is_err());
+
+        let statements = mock.statements();
+        assert_eq!(statements.len(), 5);
+        assert_eq!(statements[0].params, vec!["\"u1\"".to_string()]);
+        assert_eq!(statements[2].params, vec!["18".to_string()]);
+        mock.clear_statements();
+        assert!(mock.statements().is_empty());
+        println!("✓ MockDatabase records statements and returns canned rows");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_executor() -> Result<(), Box<dyn std::error::Error>> {
+        let user = FixtureUser {
+            id: Some("u1".to_string()),
+            name: "Alice".to_string(),
+            age: 30,
+        };
+
+        // Model methods run against the mock like against a database
+        let mock = crate::MockDatabase::new();
+        let count_row =
+            std::collections::HashMap::from([("count".to_string(), crate::Value::Integer(7))]);
+        mock.return_rows(
+            "SELECT * FROM fixture_test_029",
+            vec![crate::MockDatabase::row(&user)?],
+        )
+        .return_rows("COUNT(*)", vec![count_row]);
+        let found = FixtureUser::find_all(&mock).await?;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].name, "Alice");
+        assert_eq!(FixtureUser::count(&mock).await?, 7);
+        assert_eq!(mock.statements().len(), 2);
+
+        // ...and inside a transaction
+        let db = TestDb::new(get_test_db_config(), &[migration!(FixtureUser)]).await?;
+        let mut client = db.pool.get().await?;
+
+        let tx = client.transaction().await?;
+        user.insert(&tx).await?;
+        assert_eq!(FixtureUser::count(&tx).await?, 1);
+        tx.rollback().await?;
+        assert_eq!(FixtureUser::count(&db).await?, 0);
+
+        let tx = client.transaction().await?;
+        user.insert(&tx).await?;
+        tx.commit().await?;
+        assert!(FixtureUser::find_by_id("u1", &db).await?.is_some());
+        println!("✓ Executor runs models on mocks and transactions");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_schema_describe() -> Result<(), Box<dyn std::error::Error>> {
+        let db = TestDb::new(
+            get_test_db_config(),
+            &[migration!(TestUser), migration!(FixtureUser)],
+        )
+        .await?;
+        db.execute(
+            "CREATE INDEX idx_users_name_age ON test_users_002 (name, age)",
+            &[],
+        )
+        .await?;
+        db.execute(
+            "ALTER TABLE test_users_002 ADD CONSTRAINT age_positive CHECK (age >= 0)",
+            &[],
+        )
+        .await?;
+
+        let table = crate::Schema::describe(&db, "test_users_002").await?;
+        let names: Vec<&str> = table.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(
+            names,
+            ["id", "name", "email", "age", "created_at", "updated_at"]
+        );
+        let id = table.column("id").unwrap();
+        assert!(id.is_primary_key && !id.nullable);
+        assert!(table.column("email").unwrap().is_unique);
+
+        let index = table.index("idx_users_name_age").unwrap();
+        assert_eq!(index.columns, ["name", "age"]);
+        assert!(!index.unique && !index.primary);
+        assert!(table
+            .indexes
+            .iter()
+            .any(|i| i.primary && i.columns == ["id"]));
+
+        let check = table.constraint("age_positive").unwrap();
+        assert_eq!(check.kind, crate::ConstraintKind::Check);
+        assert_eq!(check.columns, ["age"]);
+        assert!(check.definition.contains("age >= 0"));
+        assert!(table
+            .constraints
+            .iter()
+            .any(|c| c.kind == crate::ConstraintKind::Unique && c.columns == ["email"]));
+
+        let tables: Vec<String> = crate::Schema::tables(&db)
+            .await?
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        assert_eq!(tables, ["fixture_test_029", "test_users_002"]);
+        assert!(crate::Schema::describe(&db, "missing_table").await.is_err());
+        println!("✓ Schema describes columns, indexes and constraints");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_migrations_verify() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::Drift;
+
+        let db = TestDb::new(get_test_db_config(), &[migration!(TestUser)]).await?;
+        let models = [migration!(TestUser), migration!(FixtureUser)];
+
+        let drift = crate::Migrations::verify(&db, &models).await?;
+        assert_eq!(
+            drift,
+            [Drift::MissingTable {
+                table: "fixture_test_029".to_string()
+            }]
+        );
+
+        for sql in [
+            "ALTER TABLE test_users_002 ALTER COLUMN age TYPE BIGINT",
+            "ALTER TABLE test_users_002 ALTER COLUMN name DROP NOT NULL",
+            "ALTER TABLE test_users_002 ADD COLUMN nickname TEXT",
+            "CREATE INDEX idx_users_age ON test_users_002 (age)",
+        ] {
+            db.execute(sql, &[]).await?;
+        }
+
+        let drift = crate::Migrations::verify(&db, &[migration!(TestUser)]).await?;
+        let table = || "test_users_002".to_string();
+        assert_eq!(
+            drift,
+            [
+                Drift::NullabilityMismatch {
+                    table: table(),
+                    column: "name".to_string(),
+                    expected: false,
+                    actual: true,
+                },
+                Drift::TypeMismatch {
+                    table: table(),
+                    column: "age".to_string(),
+                    expected: "INTEGER".to_string(),
+                    actual: "BIGINT".to_string(),
+                },
+                Drift::ExtraColumn {
+                    table: table(),
+                    column: "nickname".to_string(),
+                },
+                Drift::ExtraIndex {
+                    table: table(),
+                    index: "idx_users_age".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            drift[1].to_string(),
+            "test_users_002.age: expected INTEGER, found BIGINT"
+        );
+
+        // Nothing was migrated
+        let table = crate::Schema::describe(&db, "test_users_002").await?;
+        assert!(table.column("nickname").is_some());
+        println!("✓ Migrations::verify reports drift without migrating");
+
+        Ok(())
+    }
+
+    #[cfg(feature = "codegen")]
+    #[tokio::test]
+    async fn test_codegen_from_schema() -> Result<(), Box<dyn std::error::Error>> {
+        let db = TestDb::new(get_test_db_config(), &[]).await?;
+        db.execute(
+            "CREATE TABLE companies (id TEXT PRIMARY KEY, name VARCHAR(100) NOT NULL UNIQUE)",
+            &[],
+        )
+        .await?;
+        db.execute(
+            "CREATE TABLE legacy_accounts (
+                id TEXT PRIMARY KEY DEFAULT gen_random_uuid(),
+                company_id TEXT REFERENCES companies(id),
+                balance BIGINT NOT NULL,
+                tags INTEGER[],
+                settings JSONB,
+                \"type\" TEXT NOT NULL,
+                created_at TIMESTAMP NOT NULL DEFAULT NOW()
+            )",
+            &[],
+        )
+        .await?;
+
+        let source = crate::codegen::generate(&db).await?;
+        let expected = r#"#[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+#[orso_table("legacy_accounts")]
+pub struct LegacyAccount {
+    #[orso_column(primary_key)]
+    pub id: Option<String>,
+
+    #[orso_column(ref = "companies")]
+    pub company_id: Option<String>,
+
+    pub balance: i64,
+
+    pub tags: Option<Vec<i32>>,
+
+    // TODO: no Orso mapping for jsonb
+    pub settings: Option<String>,
+
+    pub r#type: String,
+
+    #[orso_column(created_at)]
+    pub created_at: Option<OrsoDateTime>,
+}
+"#;
+        assert!(source.contains(expected), "{source}");
+        assert!(source.contains("pub struct Company {"));
+        assert!(source.contains("    #[orso_column(unique)]\n    pub name: String,"));
+        assert!(source.starts_with("use orso_postgres::"));
+        println!("✓ codegen writes models for existing tables");
+
+        Ok(())
+    }
+
+    /// Unit markers for generic models
+    #[derive(Clone, Debug, Default)]
+    struct Celsius;
+    #[derive(Clone, Debug, Default)]
+    struct Fahrenheit;
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("generic_test_030")]
+    struct Measurement<T, U = Celsius>
+    where
+        T: Clone + Default,
+    {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        value: T,
+        unit: std::marker::PhantomData<U>,
+    }
+
+    #[tokio::test]
+    async fn test_generic_model() -> Result<(), Box<dyn std::error::Error>> {
+        type Reading = Measurement<String>;
+        type FahrenheitReading = Measurement<String, Fahrenheit>;
+        assert_eq!(Reading::field_names(), ["id", "value"]);
+        assert!(!Reading::migration_sql().contains("unit"));
+
+        let db = TestDb::new(
+            get_test_db_config(),
+            &[
+                migration!(Reading),
+                migration!(FahrenheitReading, "generic_test_030_f"),
+            ],
+        )
+        .await?;
+        let reading = Reading {
+            id: Some("r1".to_string()),
+            value: "21.5".to_string(),
+            unit: std::marker::PhantomData,
+        };
+        reading.insert(&db).await?;
+        let found = Reading::find_by_id("r1", &db).await?.unwrap();
+        assert_eq!(found.value, "21.5");
+
+        let reading = FahrenheitReading {
+            id: Some("r2".to_string()),
+            value: "70.7".to_string(),
+            unit: std::marker::PhantomData,
+        };
+        reading.insert_with_table(&db, "generic_test_030_f").await?;
+        let found = FahrenheitReading::find_all_with_table(&db, "generic_test_030_f").await?;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].value, "70.7");
+        println!("✓ Generic models derive Orso");
+
+        Ok(())
+    }
+
+    #[derive(crate::OrsoEmbed, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+    struct Address {
+        street: String,
+        city: String,
+        zip: Option<String>,
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("flatten_test_031")]
+    struct Customer {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        name: String,
+        #[orso_column(flatten)]
+        address: Address,
+    }
+
+    #[tokio::test]
+    async fn test_flattened_embed() -> Result<(), Box<dyn std::error::Error>> {
+        assert_eq!(
+            Customer::field_names(),
+            [
+                "id",
+                "name",
+                "address_street",
+                "address_city",
+                "address_zip"
+            ]
+        );
+        assert_eq!(
+            Customer::field_nullable(),
+            [true, false, false, false, true]
+        );
+        assert!(Customer::migration_sql().contains("address_street TEXT NOT NULL"));
+
+        let db = TestDb::new(get_test_db_config(), &[migration!(Customer)]).await?;
+        let customer = Customer {
+            id: Some("c1".to_string()),
+            name: "Ada".to_string(),
+            address: Address {
+                street: "Unter den Linden 1".to_string(),
+                city: "Berlin".to_string(),
+                zip: None,
+            },
+        };
+        customer.insert(&db).await?;
+
+        let found = Customer::find_by_id("c1", &db).await?.unwrap();
+        assert_eq!(found.address, customer.address);
+
+        let filter = FilterOperator::Single(Filter::eq("address_city", "Berlin"));
+        let found = Customer::find_where(filter, &db).await?;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].name, "Ada");
+        println!("✓ Flattened structs map to prefixed columns");
+
+        Ok(())
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+    struct Preferences {
+        theme: String,
+        font_size: i32,
+        tags: Vec<String>,
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("jsonb_test_032")]
+    struct Profile {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        #[orso_column(jsonb)]
+        preferences: Preferences,
+        #[orso_column(jsonb)]
+        extra: Option<Preferences>,
+    }
+
+    #[tokio::test]
+    async fn test_jsonb_column() -> Result<(), Box<dyn std::error::Error>> {
+        assert!(Profile::migration_sql().contains("preferences JSONB NOT NULL"));
+        assert!(Profile::migration_sql().contains("extra JSONB"));
+
+        let db = TestDb::new(get_test_db_config(), &[migration!(Profile)]).await?;
+        for (id, theme, font_size) in [("p1", "dark", 14), ("p2", "light", 18)] {
+            let profile = Profile {
+                id: Some(id.to_string()),
+                preferences: Preferences {
+                    theme: theme.to_string(),
+                    font_size,
+                    tags: vec!["a".to_string(), "b".to_string()],
+                },
+                extra: None,
+            };
+            profile.insert(&db).await?;
+        }
+
+        let found = Profile::find_by_id("p1", &db).await?.unwrap();
+        assert_eq!(found.preferences.theme, "dark");
+        assert_eq!(found.preferences.tags, ["a", "b"]);
+        assert_eq!(found.extra, None);
+
+        let filter = FilterOperator::json_path("preferences->>'theme'", Operator::Eq, "light");
+        let found = Profile::find_where(filter, &db).await?;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id.as_deref(), Some("p2"));
+
+        let filter = FilterOperator::json_path("preferences->>'font_size'", Operator::Lt, 16);
+        let found = Profile::find_where(filter, &db).await?;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].id.as_deref(), Some("p1"));
+
+        let mut profile = found[0].clone();
+        profile.extra = Some(profile.preferences.clone());
+        profile.update(&db).await?;
+        let found = Profile::find_by_id("p1", &db).await?.unwrap();
+        assert_eq!(found.extra, Some(found.preferences.clone()));
+
+        // Numbers are compared as bigint or numeric, whatever the width of the value
+        db.execute(
+            "UPDATE jsonb_test_032 SET extra = '{\"font_size\": 3000000000}' WHERE id = 'p2'",
+            &[],
+        )
+        .await?;
+        let filter = FilterOperator::json_path("extra->>'font_size'", Operator::Gt, 16);
+        assert_eq!(Profile::count_where(filter, &db).await?, 1);
+        let filter = FilterOperator::json_path("extra->>'font_size'", Operator::Lt, 14.5);
+        assert_eq!(Profile::count_where(filter, &db).await?, 1);
+        let filter =
+            FilterOperator::json_path("extra->>'font_size'", Operator::Gt, 3_000_000_000i64);
+        assert_eq!(Profile::count_where(filter, &db).await?, 0);
+        println!("✓ JSONB columns round-trip and filter by path");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("m2m_users_033")]
+    struct Member {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        name: String,
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("m2m_roles_033")]
+    struct Role {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        name: String,
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("m2m_member_roles_033")]
+    #[orso_join_table(MemberRole, left = Member, right = Role)]
+    struct MemberRole {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        #[orso_column(ref = "m2m_users_033")]
+        member_id: String,
+        #[orso_column(ref = "m2m_roles_033")]
+        role_id: String,
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("m2m_badges_033")]
+    struct Badge {
+        #[orso_column(primary_key)]
+        id: Option<i32>,
+        name: String,
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("m2m_member_badges_033")]
+    #[orso_join_table(MemberBadge, left = Member, right = Badge)]
+    struct MemberBadge {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        #[orso_column(ref = "m2m_users_033")]
+        member_id: String,
+        #[orso_column(ref = "m2m_badges_033")]
+        badge_id: i32,
+    }
+
+    #[tokio::test]
+    async fn test_join_table() -> Result<(), Box<dyn std::error::Error>> {
+        let db = TestDb::new(
+            get_test_db_config(),
+            &[
+                migration!(Member),
+                migration!(Role),
+                migration!(MemberRole),
+                migration!(Badge),
+                migration!(MemberBadge),
+            ],
+        )
+        .await?;
+
+        let mut members = Vec::new();
+        for name in ["ada", "grace"] {
+            let member = Member {
+                id: Some(name.to_string()),
+                name: name.to_string(),
+            };
+            member.insert(&db).await?;
+            members.push(member);
+        }
+        let mut roles = Vec::new();
+        for name in ["admin", "editor", "viewer"] {
+            let role = Role {
+                id: Some(name.to_string()),
+                name: name.to_string(),
+            };
+            role.insert(&db).await?;
+            roles.push(role);
+        }
+        let (ada, grace) = (&members[0], &members[1]);
+
+        ada.add_role(&roles[0], &db).await?;
+        assert_eq!(ada.add_roles(&roles, &db).await?, 2);
+        grace.add_role(&roles[0], &db).await?;
+
+        let mut names: Vec<String> = ada.roles(&db).await?.into_iter().map(|r| r.name).collect();
+        names.sort();
+        assert_eq!(names, ["admin", "editor", "viewer"]);
+        assert_eq!(roles[0].members(&db).await?.len(), 2);
+        assert!(grace.roles(&db).await?.iter().all(|r| r.name == "admin"));
+
+        assert_eq!(ada.remove_roles(&roles[1..], &db).await?, 2);
+        roles[0].remove_member(grace, &db).await?;
+        assert_eq!(ada.roles(&db).await?.len(), 1);
+        assert!(grace.roles(&db).await?.is_empty());
+        assert_eq!(MemberRole::count(&db).await?, 1);
+
+        let unsaved = Role::default();
+        assert!(ada.add_role(&unsaved, &db).await.is_err());
+
+        // Each pair is linked once, which attaching relies on
+        let pair = MemberRole::indexes();
+        assert!(pair
+            .iter()
+            .any(|index| index.unique && index.columns == ["member_id", "role_id"]));
+
+        // Integer keys are bound as integers
+        for name in ["first", "helper"] {
+            Badge {
+                id: None,
+                name: name.to_string(),
+            }
+            .insert(&db)
+            .await?;
+        }
+        let badges = Badge::find_all(&db).await?;
+        assert_eq!(ada.add_badges(&badges, &db).await?, 2);
+        assert_eq!(ada.add_badges(&badges, &db).await?, 0);
+        assert_eq!(badges[0].members(&db).await?.len(), 1);
+        assert_eq!(ada.badges(&db).await?.len(), 2);
+        assert_eq!(ada.remove_badges(&badges[..1], &db).await?, 1);
+        assert_eq!(ada.badges(&db).await?.len(), 1);
+        println!("✓ Join tables link models both ways");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("tree_test_034")]
+    struct Category {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        #[orso_column(ref = "tree_test_034")]
+        parent_id: Option<String>,
+        name: String,
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("numbered_tree_test_034")]
+    struct NumberedCategory {
+        #[orso_column(primary_key)]
+        id: Option<i32>,
+        #[orso_column(ref = "numbered_tree_test_034")]
+        parent_id: Option<i32>,
+    }
+
+    #[tokio::test]
+    async fn test_tree_queries() -> Result<(), Box<dyn std::error::Error>> {
+        assert_eq!(Category::parent_field(), Some("parent_id"));
+        let db = TestDb::new(
+            get_test_db_config(),
+            &[migration!(Category), migration!(NumberedCategory)],
+        )
+        .await?;
+
+        // root -> (b, a) -> a1 -> a1x
+        for (id, parent) in [
+            ("root", None),
+            ("b", Some("root")),
+            ("a", Some("root")),
+            ("a1", Some("a")),
+            ("a1x", Some("a1")),
+        ] {
+            let category = Category {
+                id: Some(id.to_string()),
+                parent_id: parent.map(str::to_string),
+                name: id.to_string(),
+            };
+            category.insert(&db).await?;
+        }
+        let names = |categories: Vec<Category>| -> Vec<String> {
+            categories.into_iter().map(|c| c.name).collect()
+        };
+
+        let query = crate::TreeQuery::new().with_order(crate::Sort::asc("name"));
+        let found = Category::descendants_with("root", &query, &db).await?;
+        assert_eq!(names(found), ["a", "b", "a1", "a1x"]);
+
+        let query = query.with_max_depth(1);
+        let found = Category::descendants_with("root", &query, &db).await?;
+        assert_eq!(names(found), ["a", "b"]);
+
+        assert_eq!(
+            names(Category::ancestors("a1x", &db).await?),
+            ["a1", "a", "root"]
+        );
+        assert!(Category::descendants("a1x", &db).await?.is_empty());
+
+        // A cycle ends the walk instead of looping
+        db.execute(
+            "UPDATE tree_test_034 SET parent_id = 'a1x' WHERE id = 'root'",
+            &[],
+        )
+        .await?;
+        assert_eq!(Category::ancestors("a", &db).await?.len(), 3);
+        assert!(Member::descendants("ada", &db).await.is_err());
+
+        // Only the model's columns can order the walk
+        let query = crate::TreeQuery::new().with_order(crate::Sort::asc("name; DROP TABLE x"));
+        assert!(Category::descendants_with("root", &query, &db)
+            .await
+            .is_err());
+
+        // Integer keys are bound as integers
+        for (id, parent) in [(1, None), (2, Some(1)), (3, Some(2))] {
+            NumberedCategory {
+                id: Some(id),
+                parent_id: parent,
+            }
+            .insert(&db)
+            .await?;
+        }
+        assert_eq!(NumberedCategory::descendants("1", &db).await?.len(), 2);
+        assert_eq!(NumberedCategory::ancestors("3", &db).await?[0].id, Some(2));
+        println!("✓ Tree queries walk self-referential models");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("poly_posts_035")]
+    struct Post {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        title: String,
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("poly_photos_035")]
+    struct Photo {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        url: String,
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("poly_comments_035")]
+    struct Comment {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        #[orso_column(ref_poly)]
+        subject: crate::PolyRef,
+        body: String,
+    }
+
+    crate::orso_poly! {
+        #[derive(Debug)]
+        enum Commentable {
+            Post(Post),
+            Photo(Photo),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_polymorphic_reference() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::PolyRef;
+        assert_eq!(
+            Comment::field_names(),
+            ["id", "subject_type", "subject_id", "body"]
+        );
+
+        let db = TestDb::new(
+            get_test_db_config(),
+            &[migration!(Post), migration!(Photo), migration!(Comment)],
+        )
+        .await?;
+        let post = Post {
+            id: Some("p1".to_string()),
+            title: "Hello".to_string(),
+        };
+        post.insert(&db).await?;
+        let photo = Photo {
+            id: Some("ph1".to_string()),
+            url: "cat.png".to_string(),
+        };
+        photo.insert(&db).await?;
+
+        for (id, subject) in [("c1", PolyRef::to(&post)?), ("c2", PolyRef::to(&photo)?)] {
+            let comment = Comment {
+                id: Some(id.to_string()),
+                subject,
+                body: format!("comment {id}"),
+            };
+            comment.insert(&db).await?;
+        }
+
+        let comment = Comment::find_by_id("c1", &db).await?.unwrap();
+        assert!(comment.subject.is::<Post>());
+        assert_eq!(
+            comment.subject.load::<Photo>(&db).await?.map(|p| p.url),
+            None
+        );
+        match comment.subject.load_as::<Commentable>(&db).await? {
+            Some(Commentable::Post(found)) => assert_eq!(found.title, "Hello"),
+            other => panic!("expected the post, got {other:?}"),
+        }
+        let comment = Comment::find_by_id("c2", &db).await?.unwrap();
+        match comment.subject.load_as::<Commentable>(&db).await? {
+            Some(Commentable::Photo(found)) => assert_eq!(found.url, "cat.png"),
+            other => panic!("expected the photo, got {other:?}"),
+        }
+
+        let on_photo = Comment::find_where(PolyRef::filter("subject", &photo)?, &db).await?;
+        assert_eq!(on_photo.len(), 1);
+        assert_eq!(on_photo[0].body, "comment c2");
+
+        let unknown = PolyRef::new("videos", "v1");
+        assert!(unknown.load_as::<Commentable>(&db).await.is_err());
+        println!("✓ Polymorphic references load their target");
+
+        Ok(())
+    }
+
+    #[cfg(feature = "web")]
+    #[tokio::test]
+    async fn test_web_extractors() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::{FilterParams, SortParams};
+        use axum::extract::FromRequestParts;
+
+        let uri =
+            "/users?page=1&per_page=2&sort=-age&filter[age][gt]=25&filter[email][like]=%25x.io";
+        let request = axum::http::Request::builder().uri(uri).body(())?;
+        let (mut parts, _) = request.into_parts();
+        let pagination = Pagination::from_request_parts(&mut parts, &())
+            .await
+            .unwrap();
+        let sort = SortParams::<TestUser>::from_request_parts(&mut parts, &())
+            .await
+            .unwrap();
+        let filters = FilterParams::<TestUser>::from_request_parts(&mut parts, &())
+            .await
+            .unwrap();
+        assert_eq!((pagination.page, pagination.per_page), (1, 2));
+        assert_eq!(filters.filters.len(), 2);
+
+        let migrations = [migration!(TestUser, "web_test_036")];
+        let db = TestDb::new(get_test_db_config(), &migrations).await?;
+        for (name, age, email) in [
+            ("a", 20, "a@x.io"),
+            ("b", 30, "b@x.io"),
+            ("c", 40, "c@y.io"),
+            ("d", 50, "d@x.io"),
+        ] {
+            let user = TestUser {
+                id: None,
+                name: name.to_string(),
+                email: email.to_string(),
+                age,
+                created_at: None,
+                updated_at: None,
+            };
+            user.insert_with_table(&db, "web_test_036").await?;
+        }
+        let result = TestUser::list_where_with_table(
+            filters.into_filter(),
+            sort.into_sorts(),
+            Some(&pagination),
+            &db,
+            "web_test_036",
+        )
+        .await?;
+        let names: Vec<&str> = result.data.iter().map(|u| u.name.as_str()).collect();
+        assert_eq!(names, ["d", "b"]);
+
+        assert!(Pagination::from_query("page=0").is_err());
+        assert!(Pagination::from_query("per_page=1000").is_err());
+        assert!(Sort::from_query::<TestUser>("sort=age;drop").is_err());
+        assert!(Sort::from_query::<TestUser>("sort=password").is_err());
+        assert!(SortParams::<TestUser>::from_query("page=1")?.sort.is_none());
+        assert!(FilterParams::<TestUser>::from_query("filter[password]=x").is_err());
+
+        // Columns not holding the field's value can't be sorted or filtered by
+        assert!(Sort::from_query::<AppendCompressedTest>("sort=data_points").is_err());
+        assert!(FilterParams::<AppendCompressedTest>::from_query("filter[data_points]=x").is_err());
+        assert!(FilterParams::<AppendCompressedTest>::from_query("filter[symbol]=BTC").is_ok());
+        assert!(FilterParams::<TestUser>::from_query("filter[age][gt]=old").is_err());
+        println!("✓ Web extractors parse pagination, sort and filters");
+
+        Ok(())
+    }
+
+    #[cfg(feature = "openapi")]
+    #[test]
+    fn test_openapi_schema() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::{OrsoEmbed, PaginatedResult};
+        use utoipa::{PartialSchema, ToSchema};
+
+        #[derive(OrsoEmbed, Serialize, Deserialize, Clone, Debug, Default)]
+        struct Location {
+            lat: f64,
+            label: Option<String>,
+        }
+
+        #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+        #[orso_table("openapi_test_037")]
+        struct Venue {
+            #[orso_column(primary_key)]
+            id: Option<String>,
+            name: String,
+            capacity: Option<i64>,
+            #[orso_column(flatten)]
+            location: Location,
+        }
+
+        let user = serde_json::to_value(TestUser::schema())?;
+        assert_eq!(TestUser::name(), "TestUser");
+        assert_eq!(user["properties"]["age"]["format"], "int32");
+        assert_eq!(user["properties"]["id"]["readOnly"], true);
+        assert_eq!(user["properties"]["created_at"]["format"], "date-time");
+        assert!(user["required"]
+            .as_array()
+            .unwrap()
+            .contains(&"email".into()));
+
+        let venue = serde_json::to_value(Venue::schema())?;
+        let nullable = serde_json::json!(["integer", "null"]);
+        assert_eq!(venue["properties"]["capacity"]["type"], nullable);
+        assert_eq!(
+            venue["properties"]["location"]["properties"]["lat"]["type"],
+            "number"
+        );
+        assert_eq!(
+            venue["properties"]["location"]["required"],
+            serde_json::json!(["lat"])
+        );
+        assert!(venue["properties"].get("location_lat").is_none());
+
+        let page = serde_json::to_value(PaginatedResult::<Venue>::schema())?;
+        assert_eq!(
+            page["properties"]["data"]["items"]["$ref"],
+            "#/components/schemas/Venue"
+        );
+        assert_eq!(
+            page["properties"]["pagination"]["$ref"],
+            "#/components/schemas/Pagination"
+        );
+        println!("✓ OpenAPI schemas generated from columns");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("csv_test_038")]
+    struct Contact {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        name: String,
+        score: i32,
+        note: Option<String>,
+    }
+
+    #[tokio::test]
+    async fn test_csv_export_import() -> Result<(), Box<dyn std::error::Error>> {
+        let db = TestDb::new(get_test_db_config(), &[migration!(Contact)]).await?;
+        for (id, name, score, note) in [
+            ("c1", "O'Brien, Pat", 10, Some("said \"hi\"\non two lines")),
+            ("c2", "Lee", 20, None),
+            ("c3", "Kim $1", 30, Some("")),
+        ] {
+            let contact = Contact {
+                id: Some(id.to_string()),
+                name: name.to_string(),
+                score,
+                note: note.map(str::to_string),
+            };
+            contact.insert(&db).await?;
+        }
+
+        let mut csv = Vec::new();
+        let filter = FilterOperator::or(vec![
+            FilterOperator::Single(Filter::eq("name", "O'Brien, Pat")),
+            FilterOperator::Single(Filter::eq("name", "Kim $1")),
+        ]);
+        Contact::export_csv(filter, &mut csv, &db).await?;
+        let csv = String::from_utf8(csv)?;
+        assert!(csv.starts_with("id,name,score,note\n"));
+        assert!(csv.contains("\"O'Brien, Pat\""));
+        assert!(!csv.contains("Lee"));
+
+        let mut all = Vec::new();
+        Contact::export_csv(FilterOperator::Custom("TRUE".into()), &mut all, &db).await?;
+        Contact::delete_where(FilterOperator::Custom("TRUE".into()), &db).await?;
+        assert_eq!(Contact::import_csv(all.as_slice(), &db).await?, 3);
+        let c1 = Contact::find_by_id("c1", &db).await?.unwrap();
+        assert_eq!(c1.note.as_deref(), Some("said \"hi\"\non two lines"));
+        assert_eq!(Contact::find_by_id("c2", &db).await?.unwrap().note, None);
+
+        // Keys are generated when the primary key column is left out
+        let rows = "name,score\nNew,40\n";
+        assert_eq!(Contact::import_csv(rows.as_bytes(), &db).await?, 1);
+        assert_eq!(Contact::count(&db).await?, 4);
+
+        let rows = "name,score\nGood,1\nBad,high\n";
+        let err = Contact::import_csv(rows.as_bytes(), &db).await.unwrap_err();
+        assert!(err.to_string().contains("on line 3"), "{err}");
+        let err = Contact::import_csv("name,age\nX,1\n".as_bytes(), &db)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Unknown column"), "{err}");
+        assert_eq!(Contact::count(&db).await?, 4);
+        println!("✓ CSV export and import through COPY");
+
+        Ok(())
+    }
+
+    #[cfg(feature = "arrow")]
+    #[tokio::test]
+    async fn test_arrow_export() -> Result<(), Box<dyn std::error::Error>> {
+        use ::arrow::array::{Array, Float64Array, ListArray, StringArray};
+        use futures_util::TryStreamExt;
+        use parquet::arrow::arrow_reader::ParquetRecordBatchReaderBuilder;
+
+        #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+        #[orso_table("arrow_test_039")]
+        struct Series {
+            #[orso_column(primary_key)]
+            id: Option<String>,
+            symbol: String,
+            #[orso_column(compress)]
+            prices: Vec<f64>,
+            volumes: Vec<i64>,
+            note: Option<String>,
+        }
+
+        let db = TestDb::new(get_test_db_config(), &[migration!(Series)]).await?;
+        for (id, symbol) in [("s1", "AAA"), ("s2", "BBB"), ("s3", "CCC")] {
+            let series = Series {
+                id: Some(id.to_string()),
+                symbol: symbol.to_string(),
+                prices: vec![1.5, 2.25, 3.0],
+                volumes: vec![10, 20],
+                note: None,
+            };
+            series.insert(&db).await?;
+        }
+
+        let all = FilterOperator::Custom("TRUE".into());
+        let batches: Vec<_> = crate::arrow::record_batches::<Series>(all.clone(), 2, &db)
+            .try_collect()
+            .await?;
+        assert_eq!(
+            batches.iter().map(|b| b.num_rows()).collect::<Vec<_>>(),
+            [2, 1]
+        );
+        let symbols = batches[1].column_by_name("symbol").unwrap();
+        let symbols = symbols.as_any().downcast_ref::<StringArray>().unwrap();
+        assert_eq!(symbols.value(0), "CCC");
+
+        let mut file = Vec::new();
+        assert_eq!(
+            crate::arrow::write_parquet::<Series>(all, &mut file, &db).await?,
+            3
+        );
+        let reader = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(file))?.build()?;
+        let batch = reader.into_iter().next().unwrap()?;
+        assert_eq!(batch.num_rows(), 3);
+        let prices = batch.column_by_name("prices").unwrap();
+        let prices = prices
+            .as_any()
+            .downcast_ref::<ListArray>()
+            .unwrap()
+            .value(0);
+        let prices = prices.as_any().downcast_ref::<Float64Array>().unwrap();
+        assert_eq!(prices.values(), &[1.5, 2.25, 3.0]);
+        assert!(batch.column_by_name("note").unwrap().is_null(0));
+
+        // Compressed columns take their element type from the model, not from the rows
+        let mut file = Vec::new();
+        let none = FilterOperator::Custom("FALSE".into());
+        assert_eq!(
+            crate::arrow::write_parquet::<Series>(none, &mut file, &db).await?,
+            0
+        );
+        let reader = ParquetRecordBatchReaderBuilder::try_new(bytes::Bytes::from(file))?;
+        let prices = reader
+            .schema()
+            .field_with_name("prices")?
+            .data_type()
+            .clone();
+        let ::arrow::datatypes::DataType::List(item) = prices else {
+            panic!("prices should be a list, got {prices}");
+        };
+        assert_eq!(item.data_type(), &::arrow::datatypes::DataType::Float64);
+        println!("✓ Records exported as Arrow batches and Parquet");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("ndjson_test_040")]
+    struct Event {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        kind: String,
+    }
+
+    #[tokio::test]
+    async fn test_ndjson_export() -> Result<(), Box<dyn std::error::Error>> {
+        use futures_util::TryStreamExt;
+
+        let db = TestDb::new(get_test_db_config(), &[migration!(Event)]).await?;
+        let events: Vec<Event> = (0..1005)
+            .map(|i| Event {
+                id: Some(format!("e{i:04}")),
+                kind: if i % 2 == 0 { "click" } else { "view" }.to_string(),
+            })
+            .collect();
+        Event::batch_insert(&events, &db).await?;
+
+        let all = FilterOperator::Custom("TRUE".into());
+        let lines: Vec<String> = Event::export_ndjson(all.clone(), &db).try_collect().await?;
+        assert_eq!(lines.len(), 1005);
+        assert_eq!(lines[0], "{\"id\":\"e0000\",\"kind\":\"click\"}\n");
+
+        // Resume after the last line a client received
+        let last: Event = serde_json::from_str(&lines[999])?;
+        let rest: Vec<String> = Event::export_ndjson_after(all, last.id, &db)
+            .try_collect()
+            .await?;
+        assert_eq!(rest, lines[1000..]);
+
+        let clicks = FilterOperator::Single(Filter::eq("kind", "click"));
+        let lines: Vec<String> = Event::export_ndjson(clicks, &db).try_collect().await?;
+        assert_eq!(lines.len(), 503);
+        println!("✓ JSON Lines export pages through the table and resumes by key");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_session_identity_map() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::Session;
+
+        let table = "session_test_041";
+        let db = TestDb::new(
+            get_test_db_config(),
+            &[migration!(TestUser, "session_test_041")],
+        )
+        .await?;
+        let user = TestUser {
+            id: Some("u1".to_string()),
+            name: "Ada".to_string(),
+            email: "ada@example.com".to_string(),
+            age: 36,
+            created_at: None,
+            updated_at: None,
+        };
+        user.insert_with_table(&db, table).await?;
+
+        let session = Session::new(&*db);
+        let found = TestUser::find_by_id_with_table("u1", &session, table)
+            .await?
+            .unwrap();
+        assert_eq!(session.cached(), 1);
+
+        // Changes made outside the session are not seen while the row is cached
+        let mut renamed = found.clone();
+        renamed.name = "Grace".to_string();
+        renamed.update_with_table(&db, table).await?;
+        let cached = TestUser::find_by_id_with_table("u1", &session, table)
+            .await?
+            .unwrap();
+        assert_eq!(cached.name, "Ada");
+
+        // Writes through the session evict the table's rows
+        let mut older = cached.clone();
+        older.age = 37;
+        older.update_with_table(&session, table).await?;
+        assert_eq!(session.cached(), 0);
+        let fresh = TestUser::find_by_id_with_table("u1", &session, table)
+            .await?
+            .unwrap();
+        assert_eq!((fresh.name.as_str(), fresh.age), ("Ada", 37));
+
+        assert!(TestUser::find_by_id_with_table("missing", &session, table)
+            .await?
+            .is_none());
+        assert_eq!(session.cached(), 1);
+        println!("✓ Session answers repeated lookups from its identity map");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_query_cache() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::Cache;
+        use std::time::Duration;
+
+        let table = "cache_test_042";
+        let db = TestDb::new(
+            get_test_db_config(),
+            &[migration!(TestUser, "cache_test_042")],
+        )
+        .await?;
+        let user = |name: &str, age| TestUser {
+            id: None,
+            name: name.to_string(),
+            email: format!("{name}@example.com"),
+            age,
+            created_at: None,
+            updated_at: None,
+        };
+        user("ada", 36).insert_with_table(&db, table).await?;
+
+        let cache = Cache::new(10, Duration::from_secs(60));
+        let cached = cache.wrap(&*db);
+        assert_eq!(TestUser::count_with_table(&cached, table).await?, 1);
+
+        // Rows written elsewhere are not seen until the table is invalidated
+        user("bob", 40).insert_with_table(&db, table).await?;
+        assert_eq!(TestUser::count_with_table(&cached, table).await?, 1);
+        cache.invalidate_table(table);
+        assert_eq!(TestUser::count_with_table(&cached, table).await?, 2);
+
+        // Writes through the cache evict their table at once
+        user("cy", 50).insert_with_table(&cached, table).await?;
+        assert_eq!(TestUser::count_with_table(&cached, table).await?, 3);
+
+        // Change notifications evict entries for writes from any connection
+        Cache::install_with_table(&*db, table).await?;
+        let _listener = cache.listen(&get_test_db_config()).await?;
+        assert_eq!(cache.len(), 1);
+        user("dee", 60).insert_with_table(&db, table).await?;
+        for _ in 0..50 {
+            if cache.is_empty() {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(20)).await;
+        }
+        assert_eq!(TestUser::count_with_table(&cached, table).await?, 4);
+
+        // The listener is opened like pooled connections, running `on_connect_sql` first
+        let failing = get_test_db_config().with_on_connect_sql(["SELECT no_such_function_042()"]);
+        assert!(cache.listen(&failing).await.is_err());
+
+        // The least recently used entry makes room
+        let small = Cache::new(1, Duration::from_secs(60));
+        let cached = small.wrap(&*db);
+        TestUser::count_with_table(&cached, table).await?;
+        TestUser::find_all_with_table(&cached, table).await?;
+        assert_eq!(small.len(), 1);
+
+        // Results read while their table is invalidated aren't cached
+        struct Invalidating<'a> {
+            db: &'a Database,
+            cache: Cache,
+        }
+
+        impl crate::Executor for Invalidating<'_> {
+            async fn execute(
+                &self,
+                sql: &str,
+                params: &[&(dyn tokio_postgres::types::ToSql + Send + Sync)],
+            ) -> crate::Result<u64> {
+                crate::Executor::execute(self.db, sql, params).await
+            }
+
+            async fn query_rows(
+                &self,
+                sql: &str,
+                params: &[&(dyn tokio_postgres::types::ToSql + Send + Sync)],
+            ) -> crate::Result<Vec<crate::RowMap>> {
+                let rows = crate::Executor::query_rows(self.db, sql, params).await?;
+                self.cache.invalidate_table("cache_test_042");
+                Ok(rows)
+            }
+        }
+
+        let racing = Cache::new(10, Duration::from_secs(60));
+        let cached = racing.wrap(Invalidating {
+            db: &db,
+            cache: racing.clone(),
+        });
+        TestUser::count_with_table(&cached, table).await?;
+        assert!(racing.is_empty());
+        println!("✓ Query cache reads through and invalidates per table");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_pipeline() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::Executor;
+
+        let table = "pipeline_test_043";
+        let db = TestDb::new(
+            get_test_db_config(),
+            &[migration!(TestUser, "pipeline_test_043")],
+        )
+        .await?;
+        let mut ids = Vec::new();
+        for (name, age) in [("ada", 36), ("bob", 40), ("cy", 50)] {
+            let user = TestUser {
+                id: None,
+                name: name.to_string(),
+                email: format!("{name}@example.com"),
+                age,
+                created_at: None,
+                updated_at: None,
+            };
+            user.insert_with_table(&db, table).await?;
+            let found = TestUser::find_where_with_table(
+                FilterOperator::Single(Filter::eq("name", name)),
+                &db,
+                table,
+            )
+            .await?;
+            ids.push(found[0].id.clone().unwrap());
+        }
+
+        let pipeline = db.pipeline().await?;
+        let (first, count, adults) = futures_util::try_join!(
+            TestUser::find_by_id_with_table(&ids[0], &pipeline, table),
+            TestUser::count_with_table(&pipeline, table),
+            TestUser::count_where_with_table(
+                FilterOperator::Single(Filter::gt("age", 38)),
+                &pipeline,
+                table,
+            ),
+        )?;
+        assert_eq!(first.unwrap().name, "ada");
+        assert_eq!((count, adults), (3, 2));
+
+        let users = pipeline
+            .join_all(
+                ids.iter()
+                    .map(|id| TestUser::find_by_id_with_table(id, &pipeline, table)),
+            )
+            .await?;
+        let names: Vec<_> = users.into_iter().map(|user| user.unwrap().name).collect();
+        assert_eq!(names, ["ada", "bob", "cy"]);
+
+        // Every statement runs on the pipeline's connection
+        let pids = pipeline
+            .join_all((0..3).map(|_| pipeline.query_rows("SELECT pg_backend_pid() AS pid", &[])))
+            .await?;
+        assert!(pids
+            .windows(2)
+            .all(|pair| pair[0][0].get("pid") == pair[1][0].get("pid")));
+        println!("✓ Pipeline runs concurrent queries on one connection");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_find_by_ids_ordered() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::{Session, Uuid};
+
+        let table = "find_by_ids_test_044";
+        let db = TestDb::new(
+            get_test_db_config(),
+            &[
+                migration!(TestUser, "find_by_ids_test_044"),
+                migration!(Numbered),
+            ],
+        )
+        .await?;
+        let mut ids = Vec::new();
+        for (name, age) in [("ada", 36), ("bob", 40), ("cy", 50)] {
+            let user = TestUser {
+                id: None,
+                name: name.to_string(),
+                email: format!("{name}@example.com"),
+                age,
+                created_at: None,
+                updated_at: None,
+            };
+            user.insert_with_table(&db, table).await?;
+            let found = TestUser::find_where_with_table(
+                FilterOperator::Single(Filter::eq("name", name)),
+                &db,
+                table,
+            )
+            .await?;
+            ids.push(found[0].id.clone().unwrap());
+        }
+
+        let missing = Uuid::new_v4().to_string();
+        let wanted = [
+            ids[2].as_str(),
+            missing.as_str(),
+            ids[0].as_str(),
+            ids[2].as_str(),
+        ];
+        let users = TestUser::find_by_ids_with_table(&wanted, &db, table).await?;
+        let names: Vec<_> = users
+            .iter()
+            .map(|u| u.as_ref().map(|u| u.name.as_str()))
+            .collect();
+        assert_eq!(names, [Some("cy"), None, Some("ada"), Some("cy")]);
+        assert!(TestUser::find_by_ids_with_table::<&str>(&[], &db, table)
+            .await?
+            .is_empty());
+
+        // Records already in a session are answered from it
+        let session = Session::new(&*db);
+        TestUser::find_by_ids_with_table(&[ids[0].as_str()], &session, table).await?;
+        assert_eq!(session.cached(), 1);
+        let wanted = [ids[1].as_str(), ids[0].as_str()];
+        let users = TestUser::find_by_ids_with_table(&wanted, &session, table).await?;
+        assert_eq!(users[0].as_ref().unwrap().name, "bob");
+        assert_eq!(users[1].as_ref().unwrap().name, "ada");
+        assert_eq!(session.cached(), 2);
+
+        // Keys are bound with their own type
+        #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+        #[orso_table("numbered_test_105")]
+        struct Numbered {
+            #[orso_column(primary_key)]
+            id: Option<i32>,
+            label: String,
+        }
+        for (id, label) in [(1, "one"), (2, "two"), (10, "ten")] {
+            let numbered = Numbered {
+                id: Some(id),
+                label: label.to_string(),
+            };
+            numbered.insert(&*db).await?;
+        }
+        let found = Numbered::find_by_ids(&[10i32, 3, 1], &*db).await?;
+        let labels: Vec<_> = found
+            .iter()
+            .map(|n| n.as_ref().map(|n| n.label.as_str()))
+            .collect();
+        assert_eq!(labels, [Some("ten"), None, Some("one")]);
+        println!("✓ find_by_ids keeps the order of the given IDs");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_reload() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::{Error, Session};
+
+        let table = "reload_test_045";
+        let db = TestDb::new(
+            get_test_db_config(),
+            &[migration!(TestUser, "reload_test_045")],
+        )
+        .await?;
+        let user = TestUser {
+            id: None,
+            name: "ada".to_string(),
+            email: "ada@example.com".to_string(),
+            age: 36,
+            created_at: None,
+            updated_at: None,
+        };
+        user.insert_with_table(&db, table).await?;
+        let mut user = TestUser::find_all_with_table(&db, table).await?.remove(0);
+        let mut stale = user.clone();
+
+        user.age = 37;
+        user.update_with_table(&db, table).await?;
+        stale.reload_with_table(&db, table).await?;
+        assert_eq!(stale.age, 37);
+        assert!(stale.updated_at.is_some());
+
+        // Reloading through a session bypasses the cached row
+        let session = Session::new(&*db);
+        let id = user.id.clone().unwrap();
+        let mut cached = TestUser::find_by_id_with_table(&id, &session, table)
+            .await?
+            .unwrap();
+        db.execute(
+            &format!("UPDATE {table} SET age = 40 WHERE id = $1"),
+            &[&id],
+        )
+        .await?;
+        cached.reload_with_table(&session, table).await?;
+        assert_eq!(cached.age, 40);
+
+        user.delete_with_table(&db, table).await?;
+        let err = stale.reload_with_table(&db, table).await.unwrap_err();
+        assert!(matches!(err, Error::NotFound { .. }));
+        let mut unsaved = TestUser { id: None, ..stale };
+        assert!(unsaved.reload_with_table(&db, table).await.is_err());
+        println!("✓ reload replaces a record with its stored row");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_touch() -> Result<(), Box<dyn std::error::Error>> {
+        let table = "touch_test_046";
+        let db = TestDb::new(
+            get_test_db_config(),
+            &[migration!(TestUser, "touch_test_046")],
+        )
+        .await?;
+        for (name, age) in [("ada", 36), ("bob", 40), ("cy", 17)] {
+            let user = TestUser {
+                id: None,
+                name: name.to_string(),
+                email: format!("{name}@example.com"),
+                age,
+                created_at: None,
+                updated_at: None,
+            };
+            user.insert_with_table(&db, table).await?;
+        }
+        let adults = FilterOperator::Single(Filter::gt("age", 18));
+        let mut user = TestUser::find_where_with_table(adults.clone(), &db, table)
+            .await?
+            .remove(0);
+        let before = user.updated_at.unwrap();
+
+        // Local changes are neither written nor kept
+        user.name = "changed".to_string();
+        user.touch_with_table(&db, table).await?;
+        assert!(user.updated_at.unwrap() > before);
+        assert_ne!(user.name, "changed");
+
+        assert_eq!(
+            TestUser::touch_where_with_table(adults, &db, table).await?,
+            2
+        );
+        let minors = FilterOperator::Single(Filter::lt("age", 18));
+        let minor = TestUser::find_where_with_table(minors, &db, table)
+            .await?
+            .remove(0);
+        assert!(minor.updated_at.unwrap() < user.updated_at.unwrap());
+        println!("✓ touch only moves updated_at");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_delete_all_and_truncate() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::{ConfirmDeleteAll, ConfirmTruncate};
+
+        let table = "truncate_test_047";
+        let db = TestDb::new(
+            get_test_db_config(),
+            &[migration!(TestUser, "truncate_test_047")],
+        )
+        .await?;
+        let insert_users = || async {
+            for name in ["ada", "bob", "cy"] {
+                let user = TestUser {
+                    id: None,
+                    name: name.to_string(),
+                    email: format!("{name}@example.com"),
+                    age: 30,
+                    created_at: None,
+                    updated_at: None,
+                };
+                user.insert_with_table(&db, table).await?;
+            }
+            Ok::<_, crate::Error>(())
+        };
+
+        insert_users().await?;
+        assert_eq!(
+            TestUser::delete_all_with_table(ConfirmDeleteAll, &db, table).await?,
+            3
+        );
+        assert_eq!(TestUser::count_with_table(&db, table).await?, 0);
+
+        insert_users().await?;
+        let confirm = ConfirmTruncate {
+            cascade: true,
+            restart_identity: true,
+        };
+        TestUser::truncate_with_table(confirm, &db, table).await?;
+        assert_eq!(TestUser::count_with_table(&db, table).await?, 0);
+        TestUser::truncate_with_table(ConfirmTruncate::default(), &db, table).await?;
+        println!("✓ delete_all and truncate empty the table");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_delete_where_returning() -> Result<(), Box<dyn std::error::Error>> {
+        let table = "delete_returning_test_048";
+        let db = TestDb::new(
+            get_test_db_config(),
+            &[migration!(TestUser, "delete_returning_test_048")],
+        )
+        .await?;
+        for (name, age) in [("ada", 36), ("bob", 15), ("cy", 12)] {
+            let user = TestUser {
+                id: None,
+                name: name.to_string(),
+                email: format!("{name}@example.com"),
+                age,
+                created_at: None,
+                updated_at: None,
+            };
+            user.insert_with_table(&db, table).await?;
+        }
+        let minors = FilterOperator::Single(Filter::lt("age", 18));
+        let mut expected: Vec<String> = TestUser::find_where_with_table(minors.clone(), &db, table)
+            .await?
+            .into_iter()
+            .filter_map(|user| user.id)
+            .collect();
+
+        let mut deleted =
+            TestUser::delete_where_returning_with_table(minors.clone(), &db, table).await?;
+        expected.sort();
+        deleted.sort();
+        assert_eq!(deleted, expected);
+        assert_eq!(TestUser::count_with_table(&db, table).await?, 1);
+        assert!(
+            TestUser::delete_where_returning_with_table(minors, &db, table)
+                .await?
+                .is_empty()
+        );
+        println!("✓ delete_where_returning reports the deleted IDs");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("registry_events_049", connection = "analytics")]
+    struct RegistryEvent {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        name: String,
+    }
+
+    #[tokio::test]
+    async fn test_database_registry() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::DatabaseRegistry;
+
+        let primary = TestDb::new(
+            get_test_db_config(),
+            &[migration!(TestUser, "registry_users_049")],
+        )
+        .await?;
+        let analytics = TestDb::new(get_test_db_config(), &[migration!(RegistryEvent)]).await?;
+        let connect = |schema: &str| {
+            let search_path = format!("SET search_path TO \"{schema}\"");
+            Database::init(get_test_db_config().with_on_connect_sql([search_path]))
+        };
+        let registry = DatabaseRegistry::new()
+            .with_database("primary", connect(primary.schema()).await?)
+            .with_database("analytics", connect(analytics.schema()).await?);
+
+        assert_eq!(RegistryEvent::table_name(), "registry_events_049");
+        assert_eq!(RegistryEvent::connection(), Some("analytics"));
+        assert_eq!(TestUser::connection(), None);
+
+        let event = RegistryEvent {
+            id: None,
+            name: "signup".to_string(),
+        };
+        event.insert(registry.for_model::<RegistryEvent>()?).await?;
+        assert_eq!(RegistryEvent::count(&analytics).await?, 1);
+
+        let users = registry.for_model::<TestUser>()?;
+        assert_eq!(
+            TestUser::count_with_table(users, "registry_users_049").await?,
+            0
+        );
+
+        // Calls can name a database explicitly
+        assert_eq!(RegistryEvent::count(registry.get("analytics")?).await?, 1);
+        assert!(registry.get("legacy").is_err());
+        let registry = registry.with_default("analytics");
+        assert_eq!(RegistryEvent::count(registry.default_database()?).await?, 1);
+        println!("✓ DatabaseRegistry resolves models to their named database");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("shard_messages_050")]
+    #[orso_shard_key(user_id)]
+    struct ShardedMessage {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        user_id: String,
+        body: String,
+    }
+
+    #[tokio::test]
+    async fn test_shard_router() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::ShardRouter;
+
+        let first = TestDb::new(get_test_db_config(), &[migration!(ShardedMessage)]).await?;
+        let second = TestDb::new(get_test_db_config(), &[migration!(ShardedMessage)]).await?;
+        let connect = |schema: &str| {
+            let search_path = format!("SET search_path TO \"{schema}\"");
+            Database::init(get_test_db_config().with_on_connect_sql([search_path]))
+        };
+        let router = ShardRouter::new(vec![
+            connect(first.schema()).await?,
+            connect(second.schema()).await?,
+        ])
+        .with_router(|key, _| usize::from(!key.starts_with('a')));
+
+        for user_id in ["alice", "bob", "alice", "carol"] {
+            let message = ShardedMessage {
+                id: None,
+                user_id: user_id.to_string(),
+                body: format!("hello from {user_id}"),
+            };
+            message.insert(router.shard_for(&message)?).await?;
+        }
+        assert_eq!(ShardedMessage::count(&first).await?, 2);
+        assert_eq!(ShardedMessage::count(&second).await?, 2);
+
+        let by_alice = FilterOperator::Single(Filter::eq("user_id", "alice"));
+        let found = ShardedMessage::find_where(by_alice, router.shard_for_key("alice")).await?;
+        assert_eq!(found.len(), 2);
+
+        let everyone = FilterOperator::Custom("TRUE".to_string());
+        let all = ShardedMessage::find_where_all_shards(everyone, &router).await?;
+        assert_eq!(all.len(), 4);
+
+        // Without a custom router keys are spread by a stable hash
+        let hashed = ShardRouter::new(vec![
+            connect(first.schema()).await?,
+            connect(second.schema()).await?,
+        ]);
+        let on_first = (0..100)
+            .filter(|user| hashed.shard_index(&format!("user-{user}")) == 0)
+            .count();
+        assert!((30..=70).contains(&on_first));
+        assert!(hashed.shard_for(&TestUser::default()).is_err());
+        println!("✓ ShardRouter routes records by their shard key");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_failover_hosts() -> Result<(), Box<dyn std::error::Error>> {
+        let db = TestDb::new(
+            get_test_db_config(),
+            &[migration!(TestUser, "failover_test_051")],
+        )
+        .await?;
+        let port = std::env::var("TEST_DB_PORT").unwrap_or("1332".to_string());
+        let user = std::env::var("TEST_DB_USER").unwrap_or("postgres".to_string());
+        let password = std::env::var("TEST_DB_PASSWORD").unwrap_or_default();
+        let mut connection_string = format!("user={user} dbname=postgres");
+        if !password.is_empty() {
+            connection_string.push_str(&format!(" password={password}"));
+        }
+
+        // Nothing listens on the first host, so connections go to the second
+        let config = DatabaseConfig::new(connection_string)
+            .with_pool_size(2)
+            .with_hosts(["localhost:1".to_string(), format!("localhost:{port}")])
+            .with_read_write(true)
+            .with_connect_timeout(std::time::Duration::from_secs(2))
+            .with_on_connect_sql([format!("SET search_path TO \"{}\"", db.schema())]);
+        let failover = Database::init(config).await?;
+        assert_eq!(
+            TestUser::count_with_table(&failover, "failover_test_051").await?,
+            0
+        );
+
+        // A connection to a server that turned read-only is replaced and the write retried,
+        // and the rest of the pool, which went to the same server, is not reused either
+        let (first, second) = (failover.pool.get().await?, failover.pool.get().await?);
+        for client in [&first, &second] {
+            client
+                .batch_execute("SET default_transaction_read_only = on")
+                .await?;
+        }
+        drop(first);
+        let user = TestUser {
+            id: None,
+            name: "ada".to_string(),
+            email: "ada@example.com".to_string(),
+            age: 36,
+            created_at: None,
+            updated_at: None,
+        };
+        user.insert_with_table(&failover, "failover_test_051")
+            .await?;
+        assert_eq!(
+            TestUser::count_with_table(&*db, "failover_test_051").await?,
+            1
+        );
+        drop(second);
+        let (first, second) = (failover.pool.get().await?, failover.pool.get().await?);
+        for client in [&first, &second] {
+            let row = client
+                .query_one("SHOW default_transaction_read_only", &[])
+                .await?;
+            assert_eq!(row.get::<_, String>(0), "off");
+        }
+
+        // Single-row statements such as the lock upserts fail over the same way
+        for client in [&first, &second] {
+            client
+                .batch_execute("SET default_transaction_read_only = on")
+                .await?;
+        }
+        drop((first, second));
+        let renamed = failover
+            .query_opt(
+                "UPDATE failover_test_051 SET name = $1 WHERE email = $2 RETURNING name",
+                &[&"ada lovelace", &"ada@example.com"],
+            )
+            .await?;
+        assert_eq!(
+            renamed.map(|row| row.get::<_, String>(0)).as_deref(),
+            Some("ada lovelace")
+        );
+
+        use crate::database::split_host_port;
+        assert_eq!(split_host_port("db1:5433")?, ("db1", Some(5433)));
+        assert_eq!(split_host_port("db1")?, ("db1", None));
+        assert_eq!(split_host_port("[::1]:5433")?, ("::1", Some(5433)));
+        assert_eq!(split_host_port("[fe80::1]")?, ("fe80::1", None));
+        assert_eq!(split_host_port("fe80::1")?, ("fe80::1", None));
+        assert!(split_host_port("[::1]5433").is_err());
+        assert!(split_host_port("db1:port").is_err());
+
+        let conflicting = get_test_db_config().with_hosts([format!("localhost:{port}")]);
+        assert!(Database::init(conflicting).await.is_err());
+        println!("✓ Database fails over between hosts and drops read-only connections");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_credentials_provider() -> Result<(), Box<dyn std::error::Error>> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+        use std::time::Duration;
+
+        let fetched = Arc::new(AtomicUsize::new(0));
+        let counter = fetched.clone();
+        // Connections open through the configured connector, here one that doesn't encrypt
+        let config = get_test_db_config()
+            .with_pool_size(1)
+            .with_tls(tokio_postgres::NoTls)
+            .with_credentials(move || {
+                let counter = counter.clone();
+                async move {
+                    counter.fetch_add(1, Ordering::SeqCst);
+                    Ok(std::env::var("TEST_DB_PASSWORD").unwrap_or_default())
+                }
+            })
+            .with_max_connection_lifetime(Duration::from_millis(100));
+        assert_eq!(config.clone(), config);
+        let db = Database::init(config).await?;
+
+        db.query("SELECT 1", &[]).await?;
+        db.query("SELECT 1", &[]).await?;
+        assert_eq!(fetched.load(Ordering::SeqCst), 1);
+
+        // An expired connection is replaced, with freshly fetched credentials
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        db.query("SELECT 1", &[]).await?;
+        assert_eq!(fetched.load(Ordering::SeqCst), 2);
+
+        // A failing provider fails the statement with its own error
+        let failing = get_test_db_config()
+            .with_credentials(|| async { Err(crate::Error::connection("vault is sealed")) });
+        let db = Database::init(failing).await?;
+        let err = db.query("SELECT 1", &[]).await.unwrap_err();
+        assert!(err.to_string().contains("vault is sealed"), "{err}");
+        println!("✓ Credentials are fetched for every new connection");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_max_concurrent_queries() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::Error;
+        use std::time::Duration;
+
+        let config = get_test_db_config()
+            .with_max_concurrent_queries(1)
+            .with_queue_timeout(Duration::from_millis(50));
+        let db = Database::init(config).await?;
+
+        let (slow, shed) = futures_util::join!(db.query("SELECT pg_sleep(0.3)", &[]), async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            db.query("SELECT 1", &[]).await
+        });
+        slow?;
+        match shed {
+            Err(Error::Overloaded { waited, .. }) => assert!(waited >= Duration::from_millis(50)),
+            other => panic!("expected an overloaded error, got {other:?}"),
+        }
+        db.query("SELECT 1", &[]).await?;
+
+        // Without a queue timeout statements wait for their turn
+        let db = Database::init(get_test_db_config().with_max_concurrent_queries(1)).await?;
+        let (slow, queued) = futures_util::join!(db.query("SELECT pg_sleep(0.1)", &[]), async {
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            db.query("SELECT 1", &[]).await
+        });
+        slow?;
+        queued?;
+        println!("✓ max_concurrent_queries sheds statements past the queue timeout");
+
+        Ok(())
+    }
+
+    #[derive(crate::OrsoRow, Serialize, Deserialize, Debug, PartialEq)]
+    struct AgeGroupRow {
+        adult: bool,
+        users: i64,
+        oldest: Option<i32>,
+    }
+
+    #[tokio::test]
+    async fn test_orso_row() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::OrsoRow;
+
+        let table = "orso_row_test_052";
+        let db = TestDb::new(
+            get_test_db_config(),
+            &[migration!(TestUser, "orso_row_test_052")],
+        )
+        .await?;
+        for (name, age) in [("ada", 36), ("bob", 15), ("cy", 12)] {
+            let user = TestUser {
+                id: None,
+                name: name.to_string(),
+                email: format!("{name}@example.com"),
+                age,
+                created_at: None,
+                updated_at: None,
+            };
+            user.insert_with_table(&db, table).await?;
+        }
+
+        let sql = format!(
+            "SELECT age >= 18 AS adult, COUNT(*) AS users, MAX(age) AS oldest \
+             FROM {table} GROUP BY 1 ORDER BY 1"
+        );
+        let groups = AgeGroupRow::fetch_all(&sql, &[], &*db).await?;
+        assert_eq!(
+            groups,
+            vec![
+                AgeGroupRow {
+                    adult: false,
+                    users: 2,
+                    oldest: Some(15)
+                },
+                AgeGroupRow {
+                    adult: true,
+                    users: 1,
+                    oldest: Some(36)
+                },
+            ]
+        );
+        assert!(
+            AgeGroupRow::fetch_optional(&format!("{sql} LIMIT 0"), &[], &*db)
+                .await?
+                .is_none()
+        );
+
+        let grouped: Vec<AgeGroupRow> = crate::QueryBuilder::new(table)
+            .select(vec![
+                "age >= 18 AS adult",
+                "COUNT(*) AS users",
+                "MAX(age) AS oldest",
+            ])
+            .group_by(vec!["1"])
+            .order_by(Sort::new("1", SortOrder::Desc))
+            .execute_as(&*db)
+            .await?;
+        assert_eq!(grouped.len(), 2);
+        assert!(grouped[0].adult);
+        println!("✓ OrsoRow maps aggregate query results");
+
+        Ok(())
+    }
+
+    #[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+    struct Cents(i64);
+
+    impl crate::OrsoType for Cents {
+        fn sql_type() -> &'static str {
+            "INTEGER"
+        }
+
+        fn to_value(&self) -> Value {
+            Value::Integer(self.0)
+        }
+
+        fn from_value(value: Value) -> crate::Result<Self> {
+            match value {
+                Value::Integer(cents) => Ok(Cents(cents)),
+                other => Err(crate::Error::serialization(format!("not cents: {other:?}"))),
+            }
+        }
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("custom_type_invoices_053")]
+    struct CustomTypeInvoice {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        #[orso_column(custom)]
+        total: Cents,
+        #[orso_column(custom)]
+        discount: Option<Cents>,
+    }
+
+    #[derive(crate::OrsoRow, Serialize, Deserialize, Debug)]
+    struct InvoiceTotalRow {
+        #[orso_column(custom)]
+        total: Cents,
+    }
+
+    #[tokio::test]
+    async fn test_custom_orso_type() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::OrsoRow;
+
+        let db = TestDb::new(get_test_db_config(), &[migration!(CustomTypeInvoice)]).await?;
+        let column_type = db
+            .query_one(
+                "SELECT data_type FROM information_schema.columns \
+                 WHERE table_schema = current_schema() AND table_name = $1 AND column_name = $2",
+                &[&"custom_type_invoices_053", &"total"],
+            )
+            .await?;
+        assert_eq!(column_type.get::<_, String>(0), "integer");
+        let rerun = Migrations::init(&db, &[migration!(CustomTypeInvoice)]).await?;
+        assert!(matches!(
+            rerun[0].action,
+            crate::migrations::MigrationAction::SchemaMatched
+        ));
+
+        let invoice = CustomTypeInvoice {
+            id: None,
+            total: Cents(12_50),
+            discount: None,
+        };
+        invoice.insert(&*db).await?;
+        let found = CustomTypeInvoice::find_all(&*db).await?;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].total, Cents(12_50));
+        assert_eq!(found[0].discount, None);
+
+        let mut discounted = found[0].clone();
+        discounted.discount = Some(Cents(2_00));
+        discounted.update(&*db).await?;
+        let found = CustomTypeInvoice::find_all(&*db).await?;
+        assert_eq!(found[0].discount, Some(Cents(2_00)));
+
+        let row = InvoiceTotalRow::fetch_one(
+            "SELECT total - discount AS total FROM custom_type_invoices_053",
+            &[],
+            &*db,
+        )
+        .await?;
+        assert_eq!(row.total, Cents(10_50));
+        println!("✓ custom OrsoType fields round-trip through their own column type");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("encrypted_customers_054")]
+    struct EncryptedCustomer {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        name: String,
+        #[orso_column(encrypted)]
+        email: String,
+        #[orso_column(encrypted)]
+        api_token: Option<String>,
+    }
+
+    #[tokio::test]
+    async fn test_encrypted_columns() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::EncryptionKey;
+
+        let config = get_test_db_config().with_encryption_key(EncryptionKey::new(54, [7; 32]));
+        let db = TestDb::new(config, &[migration!(EncryptedCustomer)]).await?;
+        let rerun = Migrations::init(&db, &[migration!(EncryptedCustomer)]).await?;
+        assert!(matches!(
+            rerun[0].action,
+            crate::migrations::MigrationAction::SchemaMatched
+        ));
+
+        let customer = EncryptedCustomer {
+            id: None,
+            name: "ada".to_string(),
+            email: "ada@example.com".to_string(),
+            api_token: None,
+        };
+        customer.insert(&*db).await?;
+
+        let raw = db
+            .query_one("SELECT email, api_token FROM encrypted_customers_054", &[])
+            .await?;
+        let email: Vec<u8> = raw.get(0);
+        assert!(email.starts_with(b"ORSE"));
+        assert!(!String::from_utf8_lossy(&email).contains("ada@example.com"));
+        assert_eq!(raw.get::<_, Option<Vec<u8>>>(1), None);
+
+        let found = EncryptedCustomer::find_all(&*db).await?;
+        assert_eq!(found[0].email, "ada@example.com");
+        assert_eq!(found[0].api_token, None);
+
+        // After rotating, new writes use the new key and old rows still decrypt
+        let rotated = get_test_db_config()
+            .with_on_connect_sql([format!("SET search_path TO \"{}\"", db.schema())])
+            .with_encryption_key(EncryptionKey::new(54, [7; 32]))
+            .with_encryption_key(EncryptionKey::new(55, [8; 32]));
+        let rotated = Database::init(rotated).await?;
+        let mut updated = found[0].clone();
+        updated.api_token = Some("secret-token".to_string());
+        updated.update(&rotated).await?;
+        EncryptedCustomer {
+            id: None,
+            name: "bob".to_string(),
+            email: "bob@example.com".to_string(),
+            api_token: None,
+        }
+        .insert(&rotated)
+        .await?;
+
+        let token: Vec<u8> = rotated
+            .query_one(
+                "SELECT api_token FROM encrypted_customers_054 WHERE name = 'ada'",
+                &[],
+            )
+            .await?
+            .get(0);
+        assert_eq!(token[5..9], 55u32.to_be_bytes());
+        let mut emails: Vec<String> = EncryptedCustomer::find_all(&rotated)
+            .await?
+            .into_iter()
+            .map(|customer| customer.email)
+            .collect();
+        emails.sort();
+        assert_eq!(emails, ["ada@example.com", "bob@example.com"]);
+        let ada = EncryptedCustomer::find_by_id(found[0].id.as_deref().unwrap(), &rotated).await?;
+        assert_eq!(ada.unwrap().api_token.as_deref(), Some("secret-token"));
+        let mut plucked: Vec<String> = EncryptedCustomer::pluck("email", None, &rotated).await?;
+        plucked.sort();
+        assert_eq!(plucked, ["ada@example.com", "bob@example.com"]);
+
+        // Transactions started by the database encrypt and decrypt with its keys too
+        let carol = EncryptedCustomer {
+            id: None,
+            name: "carol".to_string(),
+            email: "carol@example.com".to_string(),
+            api_token: None,
+        };
+        let read_back = rotated
+            .transaction_with(crate::TxOptions::default(), |tx| {
+                Box::pin(async move {
+                    let id = carol.insert(tx).await?.id().map(str::to_string);
+                    EncryptedCustomer::find_by_id(id.as_deref().unwrap_or_default(), tx).await
+                })
+            })
+            .await?;
+        assert_eq!(read_back.unwrap().email, "carol@example.com");
+        let email: Vec<u8> = rotated
+            .query_one(
+                "SELECT email FROM encrypted_customers_054 WHERE name = 'carol'",
+                &[],
+            )
+            .await?
+            .get(0);
+        assert!(email.starts_with(b"ORSE"));
+
+        // Each database keeps its own keys: the first one never learned the new key
+        assert!(EncryptedCustomer::find_all(&*db).await.is_err());
+        println!("✓ encrypted columns are stored as ciphertext and read back with their key");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("masked_contacts_055")]
+    struct MaskedContact {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        #[orso_column(unique)]
+        name: String,
+        #[orso_column(masked)]
+        email: String,
+        #[orso_column(masked)]
+        phone: Option<String>,
+    }
+
+    #[tokio::test]
+    async fn test_masked_columns() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::ReadContext;
+
+        let db = TestDb::new(get_test_db_config(), &[migration!(MaskedContact)]).await?;
+        MaskedContact {
+            id: None,
+            name: "ada".to_string(),
+            email: "ada@example.com".to_string(),
+            phone: Some("+44 20 7946 0958".to_string()),
+        }
+        .insert(&*db)
+        .await?;
+
+        let mut contact = MaskedContact::find_all(&*db).await?.remove(0);
+        assert_eq!(contact.email, "***@example.com");
+        assert_eq!(contact.phone.as_deref(), Some("***0958"));
+
+        // Saving a redacted record keeps the real values
+        contact.name = "Ada".to_string();
+        contact.update(&*db).await?;
+        let id = contact.id.clone().unwrap();
+        let revealed = ReadContext::revealed()
+            .scope(MaskedContact::find_by_id(&id, &*db))
+            .await?
+            .unwrap();
+        assert_eq!(revealed.name, "Ada");
+        assert_eq!(revealed.email, "ada@example.com");
+        assert_eq!(revealed.phone.as_deref(), Some("+44 20 7946 0958"));
+
+        // Inside a revealed context masked columns are written
+        let mut changed = revealed.clone();
+        changed.email = "ada@lovelace.dev".to_string();
+        ReadContext::revealed().scope(changed.update(&*db)).await?;
+        let masked = MaskedContact::find_by_id(&id, &*db).await?.unwrap();
+        assert_eq!(masked.email, "***@lovelace.dev");
+
+        // Plucking a masked column redacts it too
+        let emails: Vec<String> = MaskedContact::pluck("email", None, &*db).await?;
+        assert_eq!(emails, ["***@lovelace.dev"]);
+        let emails: Vec<String> = ReadContext::revealed()
+            .scope(MaskedContact::pluck("email", None, &*db))
+            .await?;
+        assert_eq!(emails, ["ada@lovelace.dev"]);
+
+        // So does upserting a redacted record
+        MaskedContact::batch_upsert(&[masked], &*db).await?;
+        let revealed = ReadContext::revealed()
+            .scope(MaskedContact::find_by_id(&id, &*db))
+            .await?
+            .unwrap();
+        assert_eq!(revealed.email, "ada@lovelace.dev");
+        assert_eq!(revealed.phone.as_deref(), Some("+44 20 7946 0958"));
+        println!("✓ masked columns are redacted unless revealed");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("trigger_stamped_056")]
+    struct TriggerStamped {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        name: String,
+        #[orso_column(updated_at, trigger)]
+        updated_at: Option<OrsoDateTime>,
+    }
+
+    #[tokio::test]
+    async fn test_updated_at_trigger() -> Result<(), Box<dyn std::error::Error>> {
+        let db = TestDb::new(get_test_db_config(), &[migration!(TriggerStamped)]).await?;
+        assert!(TriggerStamped::updated_at_trigger());
+
+        let triggers = db
+            .query(
+                "SELECT tgname FROM pg_trigger WHERE tgname = $1",
+                &[&"trigger_stamped_056_updated_at_trigger"],
+            )
+            .await?;
+        assert_eq!(triggers.len(), 1);
+
+        TriggerStamped {
+            id: None,
+            name: "first".to_string(),
+            updated_at: None,
+        }
+        .insert(&*db)
+        .await?;
+
+        // Raw SQL can't backdate the column past the trigger
+        db.execute(
+            "UPDATE trigger_stamped_056 SET name = 'raw', updated_at = '2000-01-01'",
+            &[],
+        )
+        .await?;
+        let mut row = TriggerStamped::find_all(&*db).await?.remove(0);
+        assert_eq!(row.name, "raw");
+        let stamped = row.updated_at.unwrap();
+        let age = chrono::Utc::now() - *stamped.inner();
+        assert!(age < chrono::Duration::minutes(5));
+
+        // ORM updates leave the column to the trigger
+        row.name = "orm".to_string();
+        row.updated_at = None;
+        row.update(&*db).await?;
+        let row = TriggerStamped::find_all(&*db).await?.remove(0);
+        assert_eq!(row.name, "orm");
+        assert!(row.updated_at.unwrap().inner() >= stamped.inner());
+        println!("✓ updated_at maintained by a database trigger");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("tz_events_057")]
+    struct TzEvent {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        at: chrono::DateTime<chrono::Utc>,
+        local: chrono::DateTime<chrono::FixedOffset>,
+        seen: Option<chrono::DateTime<chrono::Local>>,
+        #[orso_column(created_at)]
+        created_at: Option<OrsoDateTime>,
+    }
+
+    #[tokio::test]
+    async fn test_timezone_round_trips() -> Result<(), Box<dyn std::error::Error>> {
+        use chrono::{DateTime, TimeZone, Utc};
+
+        // A session far from UTC must not shift stored instants
+        let config = get_test_db_config().with_timezone("Asia/Kolkata");
+        let db = TestDb::new(config, &[]).await?;
+        let zone: String = db.query_one("SHOW TIME ZONE", &[]).await?.get(0);
+        assert_eq!(zone, "Asia/Kolkata");
+
+        // A table from before TIMESTAMPTZ columns, holding UTC wall-clock times
+        db.execute(
+            "CREATE TABLE tz_events_057 (id TEXT PRIMARY KEY, \
+             at TIMESTAMP WITHOUT TIME ZONE NOT NULL, local TIMESTAMP WITHOUT TIME ZONE NOT NULL, \
+             seen TIMESTAMP WITHOUT TIME ZONE, created_at TIMESTAMP WITHOUT TIME ZONE)",
+            &[],
+        )
+        .await?;
+        db.execute(
+            "INSERT INTO tz_events_057 VALUES \
+             ('old', '2024-01-01 12:00:00', '2024-01-01 12:00:00', NULL, '2024-01-01 12:00:00')",
+            &[],
+        )
+        .await?;
+        Migrations::init(&db, &[migration!(TzEvent)]).await?;
+
+        let noon = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let old = TzEvent::find_by_id("old", &*db).await?.unwrap();
+        assert_eq!(old.at, noon);
+        assert_eq!(old.local, noon);
+        assert_eq!(old.created_at.unwrap().into_inner(), noon);
+
+        let local = DateTime::parse_from_rfc3339("2024-06-01T09:30:00-07:00")?;
+        let seen = chrono::Local::now();
+        TzEvent {
+            id: Some("new".to_string()),
+            at: noon,
+            local,
+            seen: Some(seen),
+            created_at: None,
+        }
+        .insert(&*db)
+        .await?;
+
+        let new = TzEvent::find_by_id("new", &*db).await?.unwrap();
+        assert_eq!(new.at, noon);
+        assert_eq!(new.local, local);
+        assert_eq!(
+            new.seen.unwrap().timestamp_micros(),
+            seen.timestamp_micros()
+        );
+        let age = Utc::now() - new.created_at.unwrap().into_inner();
+        assert!(
+            age.num_seconds().abs() < 300,
+            "NOW() default shifted by {age}"
+        );
+        println!("✓ timestamps round-trip as instants in any session time zone");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+    #[orso_table("timestamp_formats_058")]
+    struct Session058 {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        started_at: OrsoDateTime,
+        #[serde(with = "orso_postgres::timestamp_format::unix_millis")]
+        expires_at: Option<OrsoDateTime>,
+        #[serde(with = "orso_postgres::timestamp_format::rfc3339")]
+        seen_at: chrono::DateTime<chrono::Utc>,
+    }
+
+    #[tokio::test]
+    async fn test_datetime_precision_and_formats() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::Precision;
+        use chrono::{TimeZone, Utc};
+
+        let db = TestDb::new(get_test_db_config(), &[migration!(Session058)]).await?;
+
+        let precise = OrsoDateTime::new(Utc.timestamp_nanos(1_700_000_000_123_456_789));
+        assert_eq!(
+            precise.truncate(Precision::Millis).to_rfc3339(),
+            "2023-11-14T22:13:20.123+00:00"
+        );
+        assert_eq!(
+            precise.truncate(Precision::Seconds),
+            OrsoDateTime::new(Utc.timestamp_opt(1_700_000_000, 0).unwrap())
+        );
+
+        let session = Session058 {
+            id: Some("s1".to_string()),
+            started_at: OrsoDateTime::now(),
+            expires_at: Some(precise.truncate(Precision::Millis)),
+            seen_at: *precise.truncate(Precision::Micros),
+        };
+        let json = serde_json::to_value(&session)?;
+        assert_eq!(json["expires_at"], 1_700_000_000_123i64);
+        assert_eq!(json["seen_at"], "2023-11-14T22:13:20.123456Z");
+
+        // `now()` is already at PostgreSQL's resolution, so round trips compare equal
+        session.insert(&*db).await?;
+        assert_eq!(
+            Session058::find_by_id("s1", &*db).await?,
+            Some(session.clone())
+        );
+
+        // Timestamps selected as text are parsed by field type
+        let row = db
+            .query_one(
+                "SELECT id, started_at::text AS started_at, expires_at, seen_at::text AS seen_at \
+                 FROM timestamp_formats_058",
+                &[],
+            )
+            .await?;
+        let mut map = std::collections::HashMap::new();
+        for (i, column) in row.columns().iter().enumerate() {
+            map.insert(
+                column.name().to_string(),
+                Value::from_postgres_row(&row, i)?,
+            );
+        }
+        assert!(matches!(map["seen_at"], Value::Text(_)));
+        assert_eq!(Session058::from_map(map)?, session);
+        println!("✓ timestamps keep their precision and serde format");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("strict_readings_059")]
+    struct StrictReadings {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        #[orso_column(compress)]
+        readings: Vec<i64>,
+        count: i32,
+    }
+
+    #[tokio::test]
+    async fn test_strict_decoding() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::Error;
+
+        let config = get_test_db_config().with_strict_decoding(true);
+        let db = TestDb::new(config, &[migration!(StrictReadings)]).await?;
+        StrictReadings {
+            id: Some("r1".to_string()),
+            readings: vec![1, 2, 3],
+            count: 3,
+        }
+        .insert(&*db)
+        .await?;
+        assert_eq!(
+            StrictReadings::find_all(&*db).await?[0].readings,
+            vec![1, 2, 3]
+        );
+
+        // A blob with a valid header but a corrupt body
+        db.execute(
+            "UPDATE strict_readings_059 SET readings = '\\x4f52534f010000ffffffff'::bytea",
+            &[],
+        )
+        .await?;
+        match StrictReadings::find_all(&*db).await {
+            Err(Error::FieldDecode { table, column, .. }) => {
+                assert_eq!(table, "strict_readings_059");
+                assert_eq!(column, "readings");
+            }
+            other => panic!("expected a field decode error, got {other:?}"),
+        }
+        // Transactions started by the database decode as strictly
+        let in_tx = db
+            .transaction_with(crate::TxOptions::default(), |tx| {
+                Box::pin(async move { StrictReadings::find_all(tx).await })
+            })
+            .await;
+        assert!(matches!(in_tx, Err(Error::FieldDecode { .. })), "{in_tx:?}");
+
+        // Values serde rejects are reported with their column too
+        let mut map = std::collections::HashMap::new();
+        map.insert("id".to_string(), Value::Text("r2".to_string()));
+        map.insert("readings".to_string(), Value::BigIntArray(vec![1]));
+        map.insert("count".to_string(), Value::Text("many".to_string()));
+        let err = crate::decode::decode_record::<StrictReadings>(&*db, map.clone()).unwrap_err();
+        assert!(matches!(&err, Error::FieldDecode { column, .. } if column == "count"));
+        // Other databases and direct decoding are unaffected
+        let lenient = StrictReadings::from_map(map).unwrap_err();
+        assert!(
+            matches!(lenient, Error::Serialization { .. }),
+            "{lenient:?}"
+        );
+        assert!(err
+            .to_string()
+            .starts_with("Failed to decode strict_readings_059.count:"));
+        println!("✓ strict decoding names the column that failed");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("error_context_060")]
+    struct ContextItem {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        #[orso_column(unique)]
+        code: String,
+        qty: i32,
+    }
+
+    #[tokio::test]
+    async fn test_error_context() -> Result<(), Box<dyn std::error::Error>> {
+        let db = TestDb::new(get_test_db_config(), &[migration!(ContextItem)]).await?;
+
+        // A parameter of the wrong type
+        let sql = "INSERT INTO error_context_060 (code, qty) VALUES ($1, $2)";
+        let err = db
+            .execute(sql, &[&"secret-code", &"seven"])
+            .await
+            .unwrap_err();
+        let ctx = err.context().expect("statement context");
+        assert_eq!(ctx.operation, "insert");
+        assert_eq!(ctx.table.as_deref(), Some("error_context_060"));
+        assert_eq!(ctx.sql, sql);
+        assert_eq!(ctx.param_count, 2);
+        let message = err.to_string();
+        assert!(message.contains(sql));
+        assert!(!message.contains("secret-code") && !message.contains("seven"));
+
+        // A statement PostgreSQL rejects points at the failing clause
+        let sql = "SELECT code FROM error_context_060 WHERE qty = = 1";
+        let err = db.query(sql, &[]).await.unwrap_err();
+        let ctx = err.context().expect("statement context");
+        assert_eq!(ctx.position, Some(48));
+        assert_eq!(ctx.near(), Some("= 1"));
+        assert!(err.to_string().contains("at character 48"), "{err}");
+
+        // Errors from model operations carry their generated statement
+        let item = ContextItem {
+            id: None,
+            code: "A1".to_string(),
+            qty: 1,
+        };
+        item.insert(&*db).await?;
+        let err = item.insert(&*db).await.unwrap_err();
+        let ctx = err.context().expect("statement context");
+        assert_eq!(ctx.operation, "insert");
+        assert!(ctx.sql.contains("error_context_060"));
+        assert!(!ctx.to_string().contains("A1"));
+        println!("✓ database errors carry the failed statement without its values");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("paged_events_061")]
+    struct PagedEvent {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        kind: String,
+        seq: i32,
+    }
+
+    #[tokio::test]
+    async fn test_pagination_count_modes() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::CountMode;
+
+        let db = TestDb::new(get_test_db_config(), &[migration!(PagedEvent)]).await?;
+        for seq in 0..5 {
+            let kind = if seq % 2 == 0 { "even" } else { "odd" };
+            PagedEvent {
+                id: None,
+                kind: kind.to_string(),
+                seq,
+            }
+            .insert(&*db)
+            .await?;
+        }
+
+        // Without a count the next page is still known
+        let page = PagedEvent::find_paginated(&Pagination::new(1, 2).without_count(), &*db).await?;
+        assert_eq!(page.len(), 2);
+        assert_eq!(page.pagination.total, None);
+        assert!(page.pagination.has_next());
+        let last = PagedEvent::find_paginated(&Pagination::new(3, 2).without_count(), &*db).await?;
+        assert_eq!(last.len(), 1);
+        assert!(!last.pagination.has_next());
+
+        // Estimates come from the statistics once the table has been analyzed
+        db.execute("ANALYZE paged_events_061", &[]).await?;
+        let estimated = Pagination::new(1, 2).with_estimated_count();
+        let page = PagedEvent::find_paginated(&estimated, &*db).await?;
+        assert_eq!(page.pagination.count, CountMode::Estimated);
+        assert_eq!(page.pagination.total, Some(5));
+        assert_eq!(page.pagination.total_pages, Some(3));
+        let filter = FilterOperator::Single(Filter::eq("kind", "odd"));
+        let odd = PagedEvent::find_where_paginated(filter, &estimated, &*db).await?;
+        assert_eq!(odd.len(), 2);
+        assert!(odd.pagination.total.is_some());
+        assert!(!odd.pagination.has_next());
+
+        // Clients that don't know the new fields still deserialize
+        let legacy: Pagination = serde_json::from_str(
+            r#"{"page": 2, "per_page": 10, "total": null, "total_pages": null}"#,
+        )?;
+        assert_eq!(legacy.count, CountMode::Exact);
+        println!("✓ pagination without or with estimated counts");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("feed_items_062")]
+    struct FeedItem {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        channel: String,
+        score: i32,
+        seq: i32,
+    }
+
+    #[tokio::test]
+    async fn test_cursor_pagination_filtered_sorted() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::CursorPagination;
+
+        let db = TestDb::new(get_test_db_config(), &[migration!(FeedItem)]).await?;
+        let scores = [5, 5, 4, 3, 3, 1];
+        for (seq, score) in scores.iter().enumerate() {
+            for channel in ["news", "sports"] {
+                FeedItem {
+                    id: None,
+                    channel: channel.to_string(),
+                    score: *score,
+                    seq: seq as i32,
+                }
+                .insert(&*db)
+                .await?;
+            }
+        }
+
+        let filter = || FilterOperator::Single(Filter::eq("channel", "news"));
+        let sorts = || vec![Sort::desc("score")];
+        let mut page =
+            FeedItem::find_where_cursor(filter(), sorts(), &CursorPagination::new(2), &*db).await?;
+        let mut seen: Vec<i32> = page.data.iter().map(|item| item.seq).collect();
+        assert!(page.pagination.has_next);
+
+        // Rows inserted ahead of the cursor don't shift the following pages
+        FeedItem {
+            id: None,
+            channel: "news".to_string(),
+            score: 9,
+            seq: 99,
+        }
+        .insert(&*db)
+        .await?;
+
+        while page.pagination.has_next {
+            let next = CursorPagination::with_cursor(2, page.pagination.next_cursor.clone());
+            page = FeedItem::find_where_cursor(filter(), sorts(), &next, &*db).await?;
+            assert!(page.pagination.has_prev);
+            seen.extend(page.data.iter().map(|item| item.seq));
+        }
+        let mut expected = seen.clone();
+        expected.sort_by_key(|seq| std::cmp::Reverse(scores[*seq as usize]));
+        assert_eq!(seen.len(), scores.len());
+        assert_eq!(seen, expected);
+        assert!(seen[..2].iter().all(|seq| *seq < 2));
+        assert_eq!(page.pagination.next_cursor, None);
+
+        // A cursor only continues the sort order it was made for
+        let first =
+            FeedItem::find_where_cursor(filter(), sorts(), &CursorPagination::new(1), &*db).await?;
+        let resumed = CursorPagination::with_cursor(1, first.pagination.next_cursor);
+        let err = FeedItem::find_where_cursor(filter(), vec![Sort::asc("seq")], &resumed, &*db)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("different sort order"));
+
+        // Compressed columns store blobs, which a cursor can't seek by
+        let mock = crate::MockDatabase::new();
+        let err = AppendCompressedTest::find_where_cursor(
+            FilterOperator::Single(Filter::eq("symbol", "BTCUSDT")),
+            vec![Sort::asc("data_points")],
+            &CursorPagination::new(1),
+            &mock,
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("can't sort by"));
+        assert!(mock.statements().is_empty());
+        println!("✓ cursor pagination over filtered, sorted queries");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("ticks_063")]
+    struct Tick {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        seq: i32,
+        #[orso_column(created_at, seek)]
+        created_at: Option<OrsoDateTime>,
+    }
+
+    #[tokio::test]
+    async fn test_page_after() -> Result<(), Box<dyn std::error::Error>> {
+        let db = TestDb::new(get_test_db_config(), &[migration!(Tick)]).await?;
+        let drift = crate::Migrations::verify(&db, &[migration!(Tick)]).await?;
+        assert!(drift.is_empty(), "{drift:?}");
+        let rows = db
+            .query(
+                "SELECT indexdef FROM pg_indexes WHERE indexname = 'ticks_063_seek_idx'",
+                &[],
+            )
+            .await?;
+        let indexdef: String = rows[0].get(0);
+        assert!(indexdef.contains("(created_at, id)"));
+
+        // Several rows share a timestamp, so the id has to break ties
+        let base = OrsoDateTime::now();
+        for seq in 0..7 {
+            let offset = chrono::Duration::seconds(seq as i64 / 3);
+            Tick {
+                id: None,
+                seq,
+                created_at: Some(OrsoDateTime::new(*base.inner() + offset)),
+            }
+            .insert(&*db)
+            .await?;
+        }
+
+        let mut page = Tick::page_after(Value::Null, Value::Null, 2, &*db).await?;
+        let mut seen = Vec::new();
+        while let Some(last) = page.last().cloned() {
+            seen.extend(page.iter().map(|tick| tick.seq));
+            page = Tick::page_after(last.created_at, last.id, 2, &*db).await?;
+        }
+        seen.sort();
+        assert_eq!(seen, (0..7).collect::<Vec<_>>());
+        println!("✓ page_after walks (created_at, id) without skipping ties");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("ledger_entries_064")]
+    struct LedgerEntry {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        account: String,
+        amount: i32,
+        #[orso_column(created_at)]
+        created_at: Option<OrsoDateTime>,
+    }
+
+    #[tokio::test]
+    async fn test_first_last_and_find_one_where() -> Result<(), Box<dyn std::error::Error>> {
+        let db = TestDb::new(get_test_db_config(), &[migration!(LedgerEntry)]).await?;
+        assert!(LedgerEntry::first(None, &*db).await?.is_none());
+
+        let base = OrsoDateTime::now();
+        for (i, (account, amount)) in [("a", 30), ("b", 10), ("b", 20)].iter().enumerate() {
+            LedgerEntry {
+                id: None,
+                account: account.to_string(),
+                amount: *amount,
+                created_at: Some(OrsoDateTime::new(
+                    *base.inner() + chrono::Duration::seconds(i as i64),
+                )),
+            }
+            .insert(&*db)
+            .await?;
+        }
+
+        // created_at by default, any column otherwise
+        assert_eq!(LedgerEntry::first(None, &*db).await?.unwrap().amount, 30);
+        assert_eq!(LedgerEntry::last(None, &*db).await?.unwrap().amount, 20);
+        let by_amount = || Some(Sort::asc("amount"));
+        assert_eq!(
+            LedgerEntry::first(by_amount(), &*db).await?.unwrap().amount,
+            10
+        );
+        assert_eq!(
+            LedgerEntry::last(by_amount(), &*db).await?.unwrap().amount,
+            30
+        );
+
+        let account = |name: &str| FilterOperator::Single(Filter::eq("account", name));
+        let single = LedgerEntry::find_one_where(account("a"), &*db).await?;
+        assert_eq!(single.unwrap().amount, 30);
+        assert!(LedgerEntry::find_one_where(account("c"), &*db)
+            .await?
+            .is_none());
+        let err = LedgerEntry::find_one_where(account("b"), &*db)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("several match"));
+        println!("✓ first, last and find_one_where");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("subscribers_065")]
+    struct Subscriber {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        email: String,
+        active: bool,
+        #[orso_column(compress)]
+        history: Vec<i64>,
+    }
+
+    #[tokio::test]
+    async fn test_pluck() -> Result<(), Box<dyn std::error::Error>> {
+        let db = TestDb::new(get_test_db_config(), &[migration!(Subscriber)]).await?;
+        for (email, active) in [("a@x.io", true), ("b@x.io", false), ("c@x.io", true)] {
+            Subscriber {
+                id: None,
+                email: email.to_string(),
+                active,
+                history: vec![1, 2, 3],
+            }
+            .insert(&*db)
+            .await?;
+        }
+
+        let active = FilterOperator::Single(Filter::eq("active", true));
+        let mut emails: Vec<String> = Subscriber::pluck("email", Some(active), &*db).await?;
+        emails.sort();
+        assert_eq!(emails, ["a@x.io", "c@x.io"]);
+        let flags: Vec<bool> = Subscriber::pluck("active", None, &*db).await?;
+        assert_eq!(flags.len(), 3);
+
+        // Compressed columns are decompressed on their own
+        let histories: Vec<Vec<i64>> = Subscriber::pluck("history", None, &*db).await?;
+        assert!(histories.iter().all(|history| history == &[1, 2, 3]));
+
+        let err = Subscriber::pluck::<String>("nope", None, &*db)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Unknown column"));
+        println!("✓ pluck reads a single column");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("tickets_066")]
+    struct Ticket {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        status: String,
+        priority: i32,
+        assignee: Option<String>,
+    }
+
+    #[tokio::test]
+    async fn test_group_count() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::GroupByKey;
+
+        let db = TestDb::new(get_test_db_config(), &[migration!(Ticket)]).await?;
+        let tickets = [
+            ("open", 1, Some("ann")),
+            ("open", 2, None),
+            ("closed", 1, Some("ann")),
+        ];
+        for (status, priority, assignee) in tickets {
+            Ticket {
+                id: None,
+                status: status.to_string(),
+                priority,
+                assignee: assignee.map(str::to_string),
+            }
+            .insert(&*db)
+            .await?;
+        }
+
+        let by_status = Ticket::group_count("status", None, &*db).await?;
+        assert_eq!(by_status.len(), 2);
+        assert_eq!(by_status["open"], 2);
+        assert_eq!(by_status["closed"], 1);
+        let urgent = FilterOperator::Single(Filter::eq("priority", 1));
+        let by_status = Ticket::group_count("status", Some(urgent), &*db).await?;
+        assert_eq!(by_status["open"], 1);
+        // Non-text columns are keyed by their text form; NULLs are left out
+        assert_eq!(Ticket::group_count("priority", None, &*db).await?["1"], 2);
+        assert_eq!(Ticket::group_count("assignee", None, &*db).await?["ann"], 2);
+
+        let all = Ticket::find_all(&*db).await?;
+        let groups = all.group_by_key(|ticket| ticket.status.clone());
+        assert_eq!(groups["open"].len(), 2);
+        assert!(groups["closed"]
+            .iter()
+            .all(|ticket| ticket.status == "closed"));
+        println!("✓ group_count and group_by_key");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("scored_posts_067")]
+    struct ScoredPost {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        title: String,
+        #[orso_column(jsonb)]
+        metadata: serde_json::Value,
+    }
+
+    #[tokio::test]
+    async fn test_raw_filter() -> Result<(), Box<dyn std::error::Error>> {
+        let db = TestDb::new(get_test_db_config(), &[migration!(ScoredPost)]).await?;
+        for (title, score) in [("low", 3), ("mid", 10), ("high", 42)] {
+            ScoredPost {
+                id: None,
+                title: title.to_string(),
+                metadata: serde_json::json!({ "score": score }),
+            }
+            .insert(&*db)
+            .await?;
+        }
+
+        // Placeholders are numbered after those of the typed filters around them
+        let filter = FilterOperator::and(vec![
+            FilterOperator::Single(Filter::ne("title", "high")),
+            Filter::raw("(metadata->>'score')::int > $?", vec![Value::Integer(5)]),
+        ]);
+        let posts = ScoredPost::find_where(filter, &*db).await?;
+        assert_eq!(posts.len(), 1);
+        assert_eq!(posts[0].title, "mid");
+
+        // `$?` inside a quoted literal is left alone
+        let raw = Filter::raw(
+            "title <> '$?' AND (metadata->>'score')::int BETWEEN $? AND $?",
+            vec![Value::Integer(1), Value::Integer(20)],
+        );
+        assert_eq!(ScoredPost::count_where(raw, &*db).await?, 2);
+
+        let mismatched = Filter::raw("title = $? OR title = $?", vec![Value::from("low")]);
+        let err = ScoredPost::find_where(mismatched, &*db).await.unwrap_err();
+        assert!(err.to_string().contains("2 placeholders but 1 values"));
+        println!("✓ raw filter fragments with bound parameters");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("signups_068")]
+    struct Signup {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        #[orso_column(unique(case_insensitive))]
+        email: String,
+        name: String,
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("numbered_signups_068")]
+    struct NumberedSignup {
+        #[orso_column(primary_key)]
+        id: Option<i32>,
+        #[orso_column(unique(case_insensitive))]
+        email: String,
+    }
+
+    #[tokio::test]
+    async fn test_case_insensitive_unique() -> Result<(), Box<dyn std::error::Error>> {
+        let db = TestDb::new(
+            get_test_db_config(),
+            &[migration!(Signup), migration!(NumberedSignup)],
+        )
+        .await?;
+        assert!(Migrations::verify(&db, &[migration!(Signup)])
+            .await?
+            .is_empty());
+        let signup = |email: &str, name: &str| Signup {
+            id: None,
+            email: email.to_string(),
+            name: name.to_string(),
+        };
+
+        signup("Ann@Example.com", "Ann").insert(&*db).await?;
+        assert!(signup("ann@example.COM", "Impostor")
+            .insert(&*db)
+            .await
+            .is_err());
+
+        // Upserts find the existing row whatever the case
+        signup("ANN@example.com", "Ann B.").upsert(&*db).await?;
+        assert_eq!(Signup::find_all(&*db).await?[0].name, "Ann B.");
+        let batch = [signup("ann@EXAMPLE.com", "Ann C."), signup("bo@x.io", "Bo")];
+        Signup::batch_upsert(&batch, &*db).await?;
+        let signups = Signup::find_all(&*db).await?;
+        assert_eq!(signups.len(), 2);
+        let ann = signups.iter().find(|s| s.name.starts_with("Ann")).unwrap();
+        assert_eq!(ann.name, "Ann C.");
+        assert_eq!(ann.email.to_lowercase(), "ann@example.com");
+
+        // Migrating again keeps the index without reporting drift
+        Migrations::init(&db, &[migration!(Signup)]).await?;
+        assert!(Migrations::verify(&db, &[migration!(Signup)])
+            .await?
+            .is_empty());
+
+        // Integer keys are adopted too
+        NumberedSignup {
+            id: Some(7),
+            email: "Cy@example.com".to_string(),
+        }
+        .insert(&*db)
+        .await?;
+        NumberedSignup {
+            id: None,
+            email: "cy@EXAMPLE.com".to_string(),
+        }
+        .upsert(&*db)
+        .await?;
+        let numbered = NumberedSignup::find_all(&*db).await?;
+        assert_eq!(numbered.len(), 1);
+        assert_eq!(numbered[0].id, Some(7));
+        assert_eq!(numbered[0].email, "cy@EXAMPLE.com");
+
+        // A model carrying another key than the row found fails instead of updating it
+        let mut other = signup("ann@example.com", "Ann D.");
+        other.id = Some(crate::Uuid::new_v4().to_string());
+        assert!(matches!(
+            other.upsert(&*db).await,
+            Err(crate::Error::Validation { .. })
+        ));
+        let rejected = NumberedSignup {
+            id: Some(99),
+            email: "CY@example.com".to_string(),
+        }
+        .upsert(&*db)
+        .await;
+        assert!(matches!(rejected, Err(crate::Error::Validation { .. })));
+        let numbered = NumberedSignup::find_all(&*db).await?;
+        assert_eq!(numbered.len(), 1);
+        assert_eq!(numbered[0].id, Some(7));
+        assert_eq!(numbered[0].email, "cy@EXAMPLE.com");
+
+        // A model carrying the key of the row found updates it
+        let upserted = NumberedSignup {
+            id: Some(7),
+            email: "CY@example.com".to_string(),
+        }
+        .upsert(&*db)
+        .await?;
+        assert_eq!(upserted.rows_affected, 1);
+        assert_eq!(
+            NumberedSignup::find_all(&*db).await?[0].email,
+            "CY@example.com"
+        );
+
+        // Integer keys are bound with their column type by the other operations too
+        let mut cy = NumberedSignup::find_by_id("7", &*db).await?.unwrap();
+        cy.email = "cy@example.org".to_string();
+        cy.update(&*db).await?;
+        assert!(NumberedSignup::find_by_id("not-a-number", &*db)
+            .await
+            .is_err());
+        assert_eq!(
+            NumberedSignup::batch_delete(&["7", "8"], &*db)
+                .await?
+                .returned_ids,
+            vec!["7".to_string()]
+        );
+        println!("✓ case-insensitive unique columns");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("documented_069", comment = "Customers' shipping addresses")]
+    struct Documented {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        #[orso_column(comment = "ISO 3166-1 alpha-2", unique)]
+        country: String,
+        street: String,
+    }
+
+    #[tokio::test]
+    async fn test_table_and_column_comments() -> Result<(), Box<dyn std::error::Error>> {
+        let db = TestDb::new(get_test_db_config(), &[migration!(Documented)]).await?;
+        let row = db
+            .query_one(
+                "SELECT obj_description(to_regclass('documented_069'), 'pg_class'), \
+                 col_description(to_regclass('documented_069'), 2), \
+                 col_description(to_regclass('documented_069'), 3)",
+                &[],
+            )
+            .await?;
+        let comment = |i: usize| row.get::<_, Option<String>>(i);
+        assert_eq!(comment(0).as_deref(), Some("Customers' shipping addresses"));
+        assert_eq!(comment(1).as_deref(), Some("ISO 3166-1 alpha-2"));
+        assert_eq!(comment(2), None);
+
+        // Attributes after the comment still apply
+        assert_eq!(Documented::unique_fields(), ["country"]);
+        println!("✓ table and column comments");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("crm_accounts_070")]
+    struct CrmAccount {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        #[orso_column(unique)]
+        external_id: i32,
+        #[orso_column(unique)]
+        email: String,
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("crm_invoices_070")]
+    struct CrmInvoice {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        #[orso_column(ref = "crm_accounts_070(external_id)")]
+        account_external_id: i32,
+        #[orso_column(ref = "crm_accounts_070", ref_column = "email")]
+        billing_email: String,
+    }
+
+    #[tokio::test]
+    async fn test_reference_non_id_column() -> Result<(), Box<dyn std::error::Error>> {
+        let db = TestDb::new(
+            get_test_db_config(),
+            &[migration!(CrmAccount), migration!(CrmInvoice)],
+        )
+        .await?;
+        let invoices = crate::Schema::describe(&db, "crm_invoices_070").await?;
+        let mut references: Vec<&str> = invoices
+            .constraints
+            .iter()
+            .filter(|c| c.kind == crate::ConstraintKind::ForeignKey)
+            .map(|c| c.definition.as_str())
+            .collect();
+        references.sort();
+        assert_eq!(
+            references,
+            [
+                "FOREIGN KEY (account_external_id) REFERENCES crm_accounts_070(external_id)",
+                "FOREIGN KEY (billing_email) REFERENCES crm_accounts_070(email)",
+            ]
+        );
+        // The column takes the type of the referenced key, not TEXT
+        assert_eq!(
+            invoices.column("account_external_id").unwrap().sql_type,
+            "INTEGER"
+        );
+
+        CrmAccount {
+            id: None,
+            external_id: 42,
+            email: "billing@acme.io".to_string(),
+        }
+        .insert(&*db)
+        .await?;
+        let invoice = |external_id: i32, email: &str| CrmInvoice {
+            id: None,
+            account_external_id: external_id,
+            billing_email: email.to_string(),
+        };
+        invoice(42, "billing@acme.io").insert(&*db).await?;
+        assert!(invoice(7, "billing@acme.io").insert(&*db).await.is_err());
+        assert!(invoice(42, "nobody@acme.io").insert(&*db).await.is_err());
+        println!("✓ foreign keys referencing natural keys");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("seats_071")]
+    struct Seat {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        #[orso_column(unique, initially_deferred)]
+        position: i32,
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("seat_holds_071")]
+    struct SeatHold {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        #[orso_column(ref = "seats_071", deferrable)]
+        seat_id: String,
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("seats_071")]
+    struct ImmediateSeat {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        #[orso_column(unique)]
+        position: i32,
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("seat_holds_071")]
+    struct ImmediateSeatHold {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        #[orso_column(ref = "seats_071")]
+        seat_id: String,
+    }
+
+    #[tokio::test]
+    async fn test_deferrable_constraints() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::{MigrationConfig, MigrationStrategy};
+
+        let db = TestDb::new(
+            get_test_db_config(),
+            &[migration!(Seat), migration!(SeatHold)],
+        )
+        .await?;
+        let seat = |id: &str, position: i32| Seat {
+            id: Some(id.to_string()),
+            position,
+        };
+        seat("a", 1).insert(&*db).await?;
+        seat("b", 2).insert(&*db).await?;
+
+        // Swapping positions only has to hold at commit
+        let mut client = db.pool.get().await?;
+        let tx = client.transaction().await?;
+        seat("a", 2).update(&tx).await?;
+        seat("b", 1).update(&tx).await?;
+        tx.commit().await?;
+        assert_eq!(Seat::find_by_id("a", &*db).await?.unwrap().position, 2);
+
+        // A duplicate is still rejected, at commit
+        let tx = client.transaction().await?;
+        seat("c", 1).insert(&tx).await?;
+        assert!(tx.commit().await.is_err());
+
+        // The reference is deferrable but checked immediately until deferred
+        let hold = SeatHold {
+            id: None,
+            seat_id: "d".to_string(),
+        };
+        let tx = client.transaction().await?;
+        assert!(hold.insert(&tx).await.is_err());
+        tx.rollback().await?;
+
+        let tx = client.transaction().await?;
+        tx.batch_execute("SET CONSTRAINTS ALL DEFERRED").await?;
+        hold.insert(&tx).await?;
+        seat("d", 3).insert(&tx).await?;
+        tx.commit().await?;
+        assert_eq!(SeatHold::count(&*db).await?, 1);
+
+        // The deferrable position can't be an ON CONFLICT target
+        let err = Seat::batch_upsert(&[seat("a", 5)], &*db).await.unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("deferrable unique column position"));
+
+        // Migrations bring the constraints' deferral in line with the models
+        let models = [migration!(ImmediateSeat), migration!(ImmediateSeatHold)];
+        let drift = crate::Migrations::verify(&db, &models).await?;
+        let mismatch = |table: &str, column: &str, actual: bool| crate::Drift::DeferralMismatch {
+            table: table.to_string(),
+            column: column.to_string(),
+            expected: None,
+            actual: Some(actual),
+        };
+        assert_eq!(
+            drift,
+            [
+                mismatch("seats_071", "position", true),
+                mismatch("seat_holds_071", "seat_id", false)
+            ]
+        );
+        let in_place = MigrationConfig::default().with_strategy(MigrationStrategy::InPlace);
+        crate::Migrations::init_with_config(&db, &models, &in_place).await?;
+        assert!(crate::Migrations::verify(&db, &models).await?.is_empty());
+        let tx = client.transaction().await?;
+        assert!(seat("e", 3).insert(&tx).await.is_err());
+        tx.rollback().await?;
+        let tx = client.transaction().await?;
+        tx.batch_execute("SET CONSTRAINTS ALL DEFERRED").await?;
+        let orphan = SeatHold {
+            id: None,
+            seat_id: "f".to_string(),
+        };
+        assert!(orphan.insert(&tx).await.is_err());
+        println!("✓ deferrable unique and foreign key constraints");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("ledger_lines_072")]
+    struct LedgerLine {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        amount: i32,
+    }
+
+    #[tokio::test]
+    async fn test_transaction_with_options() -> Result<(), Box<dyn std::error::Error>> {
+        let db = TestDb::new(get_test_db_config(), &[migration!(LedgerLine)]).await?;
+        let line = LedgerLine {
+            id: None,
+            amount: 10,
+        };
+
+        let reporting = crate::TxOptions {
+            isolation: crate::IsolationLevel::Serializable,
+            read_only: true,
+            deferrable: true,
+        };
+        let isolation = db
+            .transaction_with(reporting, |tx| {
+                Box::pin(async move {
+                    let row = tx.query_one("SHOW transaction_isolation", &[]).await?;
+                    Ok(row.get::<_, String>(0))
+                })
+            })
+            .await?;
+        assert_eq!(isolation, "serializable");
+
+        let copy = line.clone();
+        let written = db
+            .transaction_with(reporting, |tx| {
+                Box::pin(async move { copy.insert(tx).await })
+            })
+            .await;
+        assert!(written.is_err());
+
+        // Errors roll back, success commits
+        let copy = line.clone();
+        let failed: crate::Result<()> = db
+            .transaction_with(crate::TxOptions::default(), |tx| {
+                Box::pin(async move {
+                    copy.insert(tx).await?;
+                    Err(crate::Error::validation("rejected"))
+                })
+            })
+            .await;
+        assert!(failed.is_err());
+        assert_eq!(LedgerLine::count(&*db).await?, 0);
+
+        db.transaction_with(crate::TxOptions::default(), |tx| {
+            Box::pin(async move { line.insert(tx).await })
+        })
+        .await?;
+        assert_eq!(LedgerLine::count(&*db).await?, 1);
+        println!("✓ transactions with isolation and access mode");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("on_call_073")]
+    struct OnCall {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        doctor: String,
+    }
+
+    #[tokio::test]
+    async fn test_serializable_retry() -> Result<(), Box<dyn std::error::Error>> {
+        use std::sync::atomic::{AtomicU32, Ordering};
+        use std::sync::Arc;
+
+        let db = TestDb::new(get_test_db_config(), &[migration!(OnCall)]).await?;
+
+        // Both transactions read the empty roster before either writes, so one of them is
+        // aborted; on its retry it sees the other doctor and leaves the roster alone
+        let barrier = Arc::new(tokio::sync::Barrier::new(2));
+        let attempts = Arc::new(AtomicU32::new(0));
+        let volunteer = |doctor: &'static str| {
+            let (barrier, attempts) = (barrier.clone(), attempts.clone());
+            db.serializable(move |tx| {
+                let (barrier, attempts) = (barrier.clone(), attempts.clone());
+                Box::pin(async move {
+                    let first = attempts.fetch_add(1, Ordering::SeqCst) < 2;
+                    let on_call = OnCall::count(tx).await?;
+                    if first {
+                        barrier.wait().await;
+                    }
+                    if on_call == 0 {
+                        let entry = OnCall {
+                            id: None,
+                            doctor: doctor.to_string(),
+                        };
+                        entry.insert(tx).await?;
+                    }
+                    Ok(on_call)
+                })
+            })
+        };
+        let (alice, bob) = tokio::join!(volunteer("alice"), volunteer("bob"));
+        let mut seen = [alice?, bob?];
+        seen.sort();
+        assert_eq!(seen, [0, 1]);
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+        assert_eq!(OnCall::count(&*db).await?, 1);
+
+        // Failures past the attempt limit are returned
+        let attempts = Arc::new(AtomicU32::new(0));
+        let counter = attempts.clone();
+        let policy = crate::RetryPolicy::default()
+            .with_max_attempts(3)
+            .with_backoff(std::time::Duration::ZERO, std::time::Duration::ZERO);
+        let result: crate::Result<()> = db
+            .serializable_with(policy, move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Box::pin(async {
+                    Err(crate::Error::postgres(
+                        "could not serialize",
+                        Some("40001".into()),
+                    ))
+                })
+            })
+            .await;
+        assert!(result.unwrap_err().is_serialization_failure());
+        assert_eq!(attempts.load(Ordering::SeqCst), 3);
+        println!("✓ serializable transactions retried on conflicts");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("email_jobs_074")]
+    struct EmailJob {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        recipient: String,
+        run_at: OrsoDateTime,
+        attempts: i32,
+    }
+
+    #[tokio::test]
+    async fn test_job_queue() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::Queue;
+        use std::time::Duration;
+
+        let db = TestDb::new(get_test_db_config(), &[migration!(EmailJob)]).await?;
+        let now = chrono::Utc::now();
+        let job = |id: &str, due_in: i64| EmailJob {
+            id: Some(id.to_string()),
+            recipient: format!("{id}@example.com"),
+            run_at: OrsoDateTime::from(now + chrono::Duration::seconds(due_in)),
+            attempts: 0,
+        };
+        for (id, due_in) in [("b", -10), ("a", -20), ("later", 3600)] {
+            job(id, due_in).insert(&*db).await?;
+        }
+
+        // Due jobs come earliest first and are hidden while claimed
+        let claimed = Queue::<EmailJob>::claim(5, &*db).await?;
+        let ids: Vec<_> = claimed.iter().filter_map(|j| j.id.as_deref()).collect();
+        assert_eq!(ids, ["a", "b"]);
+        assert!(claimed.iter().all(|j| j.attempts == 1));
+        assert!(Queue::<EmailJob>::claim(5, &*db).await?.is_empty());
+
+        // Concurrent workers skip each other's rows
+        job("c", -5).insert(&*db).await?;
+        job("d", -5).insert(&*db).await?;
+        let mut client = db.pool.get().await?;
+        let tx = client.transaction().await?;
+        let first = Queue::<EmailJob>::claim(1, &tx).await?;
+        let second = Queue::<EmailJob>::claim(5, &*db).await?;
+        tx.commit().await?;
+        assert_eq!(first.len(), 1);
+        assert_eq!(second.len(), 1);
+        assert_ne!(first[0].id, second[0].id);
+
+        // A job past its visibility timeout is due again
+        job("e", -5).insert(&*db).await?;
+        let claimed = Queue::<EmailJob>::claim_for(1, Duration::ZERO, &*db).await?;
+        let again = Queue::<EmailJob>::claim(1, &*db).await?;
+        assert_eq!(again[0].id, claimed[0].id);
+        assert_eq!(again[0].attempts, 2);
+
+        Queue::<EmailJob>::fail("e", Duration::ZERO, &*db).await?;
+        assert_eq!(Queue::<EmailJob>::claim(5, &*db).await?.len(), 1);
+        Queue::<EmailJob>::complete("e", &*db).await?;
+        assert!(EmailJob::find_by_id("e", &*db).await?.is_none());
+        assert!(Queue::<EmailJob>::complete("e", &*db).await.is_err());
+        println!("✓ job queue claims with SKIP LOCKED");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("shipments_075")]
+    struct Shipment {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        carrier: String,
+    }
+
+    #[tokio::test]
+    async fn test_outbox() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::{Outbox, OutboxEvent, OutboxExt};
+
+        let db = TestDb::new(get_test_db_config(), &[migration!(Shipment)]).await?;
+        Outbox::install(&db).await?;
+        let shipment = |id: &str| Shipment {
+            id: Some(id.to_string()),
+            carrier: "dhl".to_string(),
+        };
+
+        // Events of rolled back transactions are never published
+        let mut client = db.pool.get().await?;
+        let tx = client.transaction().await?;
+        shipment("s1").insert(&tx).await?;
+        tx.outbox(OutboxEvent::new("shipment.created", &shipment("s1"))?)
+            .await?;
+        tx.rollback().await?;
+
+        let tx = client.transaction().await?;
+        for id in ["s2", "s3"] {
+            shipment(id).insert(&tx).await?;
+            let event = OutboxEvent::new("shipment.created", &shipment(id))?.with_key(id);
+            tx.outbox(event).await?;
+        }
+        tx.commit().await?;
+
+        // A failing handler keeps the events for the next drain
+        let failed = Outbox::drain(&db, 10, |_| async {
+            Err(crate::Error::query("broker down"))
+        });
+        assert!(failed.await.is_err());
+
+        let published = std::sync::Mutex::new(Vec::new());
+        let count = Outbox::drain(&db, 1, |events| async {
+            published.lock().unwrap().extend(events);
+            Ok(())
+        })
+        .await?;
+        assert_eq!(count, 1);
+        assert_eq!(Outbox::drain(&db, 10, |_| async { Ok(()) }).await?, 1);
+        assert_eq!(Outbox::drain(&db, 10, |_| async { Ok(()) }).await?, 0);
+
+        let published = published.into_inner().unwrap();
+        assert_eq!(published[0].topic, "shipment.created");
+        assert_eq!(published[0].key.as_deref(), Some("s2"));
+        assert_eq!(published[0].payload["carrier"], "dhl");
+        println!("✓ outbox events published after commit");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_distributed_lock() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::Lock;
+        use std::time::Duration;
+
+        let db = TestDb::new(get_test_db_config(), &[]).await?;
+        Lock::install(&db).await?;
+        let minute = Duration::from_secs(60);
+
+        let mut held = Lock::acquire(&db, "daily-rollup", minute).await?.unwrap();
+        assert_eq!(held.token, 1);
+        assert!(Lock::acquire(&db, "daily-rollup", minute).await?.is_none());
+        assert!(Lock::acquire(&db, "hourly-rollup", minute).await?.is_some());
+        held.extend(&db, minute).await?;
+
+        // Released and expired locks can be taken again, with a higher token
+        held.release(&db).await?;
+        let mut stale = Lock::acquire(&db, "daily-rollup", Duration::ZERO)
+            .await?
+            .unwrap();
+        assert_eq!(stale.token, 2);
+        let current = Lock::acquire(&db, "daily-rollup", minute).await?.unwrap();
+        assert_eq!(current.token, 3);
+
+        // The stale holder can neither extend nor release the lock it lost
+        assert!(stale.extend(&db, minute).await.is_err());
+        stale.release(&db).await?;
+        assert!(Lock::acquire(&db, "daily-rollup", minute).await?.is_none());
+        println!("✓ table-backed locks with fencing tokens");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("tickets_076")]
+    struct RaffleTicket {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        #[orso_column(unique)]
+        number: i32,
+    }
+
+    #[tokio::test]
+    async fn test_bind_parameter_limit() -> Result<(), Box<dyn std::error::Error>> {
+        let db = TestDb::new(get_test_db_config(), &[migration!(RaffleTicket)]).await?;
+        let tickets: Vec<RaffleTicket> = (1..=3)
+            .map(|number| RaffleTicket {
+                id: Some(format!("t{number}")),
+                number,
+            })
+            .collect();
+        RaffleTicket::batch_create(&tickets, &*db).await?;
+
+        // IN lists past the limit are bound as one array
+        let numbers: Vec<Value> = (2..70_000).map(Value::Integer).collect();
+        let found = RaffleTicket::find_by_field_in("number", &numbers, &*db).await?;
+        assert_eq!(found.len(), 2);
+        let outside = FilterOperator::Single(Filter::not_in_values("number", numbers.clone()));
+        assert_eq!(RaffleTicket::count_where(outside.clone(), &*db).await?, 1);
+        let inlined = crate::FilterOperations::build_filter_operator_inline(&outside)?;
+        assert!(inlined.starts_with("number <> ALL(ARRAY[2, 3, 4"));
+
+        // Other statements that can't fit are rejected before reaching PostgreSQL
+        let filters = (0..70_000).map(|n| FilterOperator::Single(Filter::eq("number", n)));
+        let err = RaffleTicket::count_where(FilterOperator::Or(filters.collect()), &*db).await;
+        assert!(err.unwrap_err().to_string().contains("70000 parameters"));
+
+        let mut ids: Vec<String> = (0..70_000).map(|n| format!("x{n}")).collect();
+        ids.push("t1".to_string());
+        let ids: Vec<&str> = ids.iter().map(String::as_str).collect();
+        assert_eq!(
+            RaffleTicket::batch_delete(&ids, &*db).await?.returned_ids,
+            ["t1"]
+        );
+
+        // Batch writes past the limit are split into several statements
+        let ticket = |number: i32| RaffleTicket {
+            id: Some(format!("b{number}")),
+            number: number + 100,
+        };
+        let batch: Vec<RaffleTicket> = (0..40_000).map(ticket).collect();
+        let inserted = RaffleTicket::batch_create(&batch, &*db).await?;
+        assert_eq!(inserted.rows_affected, 40_000);
+        assert_eq!(inserted.returned_ids.len(), 40_000);
+        let replay: Vec<RaffleTicket> = (39_990..40_010).map(ticket).collect();
+        let inserted = RaffleTicket::batch_create_ignore_conflicts(&replay, &*db).await?;
+        assert_eq!(inserted.rows_affected, 10);
+
+        // A key repeated in one batch is written twice, the last one winning
+        let mut upserts: Vec<RaffleTicket> = (0..40_000).map(ticket).collect();
+        upserts.push(RaffleTicket {
+            id: Some("b7".to_string()),
+            number: 107,
+        });
+        let upserted = RaffleTicket::batch_upsert(&upserts, &*db).await?;
+        assert_eq!(upserted.rows_affected, 40_001);
+        assert_eq!(RaffleTicket::count(&*db).await?, 40_012);
+        println!("✓ statements stay within the bind parameter limit");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_in_filters_bind_arrays() -> Result<(), Box<dyn std::error::Error>> {
+        let db = TestDb::new(get_test_db_config(), &[migration!(RaffleTicket)]).await?;
+        let tickets: Vec<RaffleTicket> = (1..=4)
+            .map(|number| RaffleTicket {
+                id: Some(format!("t{number}")),
+                number,
+            })
+            .collect();
+        RaffleTicket::batch_create(&tickets, &*db).await?;
+
+        let ids = FilterOperator::Single(Filter::in_values("id", vec!["t1", "t3", "t9"]));
+        let numbers = FilterOperator::Single(Filter::not_in_values("number", vec![1, 2]));
+        let both = FilterOperator::And(vec![ids.clone(), numbers.clone()]);
+        let (sql, params) = crate::FilterOperations::build_filter_operator(&both)?;
+        assert_eq!(sql, "(id = ANY($1) AND number <> ALL($2::bigint[]))");
+        assert_eq!(params.len(), 2);
+
+        assert_eq!(RaffleTicket::count_where(ids, &*db).await?, 2);
+        assert_eq!(RaffleTicket::count_where(numbers, &*db).await?, 2);
+        assert_eq!(RaffleTicket::count_where(both, &*db).await?, 1);
+
+        // Integers mixed with reals are bound as reals, other mixes can't be bound
+        let numeric = vec![Value::Integer(1), Value::Real(2.0)];
+        let numeric = FilterOperator::Single(Filter::in_values("number", numeric));
+        let (sql, _) = crate::FilterOperations::build_filter_operator(&numeric)?;
+        assert_eq!(sql, "number = ANY($1::double precision[])");
+        assert_eq!(RaffleTicket::count_where(numeric, &*db).await?, 2);
+        let mixed = vec![Value::Integer(1), Value::Text("2".to_string())];
+        let mixed = Filter::in_values("number", mixed);
+        assert!(crate::FilterOperations::build_filter(&mixed).is_err());
+
+        let empty = FilterOperator::Single(Filter::in_values("number", Vec::<i64>::new()));
+        assert_eq!(RaffleTicket::count_where(empty, &*db).await?, 0);
+        println!("✓ IN filters bind one array");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("support_cases_077")]
+    struct SupportCase {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        status: String,
+        priority: i32,
+        reopened: i32,
+    }
+
+    #[tokio::test]
+    async fn test_query_builder_writes() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::QueryBuilder;
+
+        let db = TestDb::new(get_test_db_config(), &[migration!(SupportCase)]).await?;
+        let cases: Vec<SupportCase> = (1..=5)
+            .map(|priority| SupportCase {
+                id: Some(format!("c{priority}")),
+                status: "open".to_string(),
+                priority,
+                reopened: 0,
+            })
+            .collect();
+        SupportCase::batch_create(&cases, &*db).await?;
+
+        let low = FilterOperator::Single(Filter::lt("priority", 3));
+        let (sql, _) = QueryBuilder::update("support_cases_077")
+            .set("status", "closed")
+            .set_expression("reopened", "reopened + 1")
+            ._where(low.clone())
+            .returning(vec!["id"])
+            .build()?;
+        assert_eq!(
+            sql,
+            "UPDATE support_cases_077 SET status = $1, reopened = reopened + 1 \
+             WHERE priority < $2 RETURNING id"
+        );
+
+        let closed: Vec<SupportCase> = QueryBuilder::update("support_cases_077")
+            .set("status", "closed")
+            .set_expression("reopened", "reopened + 1")
+            ._where(low)
+            .execute_returning(&*db)
+            .await?;
+        assert_eq!(closed.len(), 2);
+        assert!(closed
+            .iter()
+            .all(|c| c.status == "closed" && c.reopened == 1));
+
+        // Sorting and limits pick the rows written
+        let escalated = QueryBuilder::update("support_cases_077")
+            .set("status", "escalated")
+            ._where(FilterOperator::Single(Filter::eq("status", "open")))
+            .order_by(Sort::desc("priority"))
+            .limit(1)
+            .execute_write(&*db)
+            .await?;
+        assert_eq!(escalated, 1);
+        let top = SupportCase::find_by_id("c5", &*db).await?.unwrap();
+        assert_eq!(top.status, "escalated");
+
+        let deleted: Vec<SupportCase> = QueryBuilder::delete("support_cases_077")
+            ._where(FilterOperator::Single(Filter::eq("status", "closed")))
+            .execute_returning(&*db)
+            .await?;
+        assert_eq!(deleted.len(), 2);
+        assert_eq!(SupportCase::count(&*db).await?, 3);
+
+        assert!(QueryBuilder::update("support_cases_077").build().is_err());
+        assert!(QueryBuilder::new("support_cases_077")
+            .execute_write(&*db)
+            .await
+            .is_err());
+        println!("✓ query builder updates and deletes");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("trades_078")]
+    struct ArchivedTrade {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        symbol: String,
+        day: i32,
+    }
+
+    #[tokio::test]
+    async fn test_archive_move_where() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::{Archive, ArchiveOptions};
+        use std::sync::atomic::{AtomicU64, Ordering};
+        use std::sync::Arc;
+
+        let db = TestDb::new(get_test_db_config(), &[migration!(ArchivedTrade)]).await?;
+        let trades: Vec<ArchivedTrade> = (1..=25)
+            .map(|day| ArchivedTrade {
+                id: Some(format!("t{day}")),
+                symbol: "BTC".to_string(),
+                day,
+            })
+            .collect();
+        ArchivedTrade::batch_create(&trades, &*db).await?;
+
+        let batches = Arc::new(AtomicU64::new(0));
+        let seen = batches.clone();
+        let options = ArchiveOptions::new()
+            .with_batch_size(4)
+            .on_progress(move |p| seen.store(p.batches, Ordering::SeqCst));
+        let old = FilterOperator::Single(Filter::le("day", 10));
+        let progress =
+            Archive::move_where_with::<ArchivedTrade>(old, "trades_078_archive", options, &db)
+                .await?;
+        assert_eq!(progress.rows_moved, 10);
+        assert_eq!(progress.batches, 3);
+        assert_eq!(batches.load(Ordering::SeqCst), 3);
+
+        assert_eq!(ArchivedTrade::count(&*db).await?, 15);
+        let mut archived = ArchivedTrade::find_all_with_table(&*db, "trades_078_archive").await?;
+        archived.sort_by_key(|t| t.day);
+        assert_eq!(archived.len(), 10);
+        assert_eq!(archived[0].id.as_deref(), Some("t1"));
+        assert_eq!(archived[9].day, 10);
+
+        // The archive table already exists on later runs
+        let next = FilterOperator::Single(Filter::eq("day", 11));
+        let progress =
+            Archive::move_where::<ArchivedTrade>(next, "trades_078_archive", &db).await?;
+        assert_eq!(progress.rows_moved, 1);
+        assert_eq!(
+            ArchivedTrade::count_with_table(&*db, "trades_078_archive").await?,
+            11
+        );
+        println!("✓ archive move_where");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("ticks_079")]
+    struct TradeTick {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        symbol: String,
+        price: f64,
+        size: i32,
+        traded_at: OrsoDateTime,
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("candles_080")]
+    struct Candle {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        symbol: String,
+        bucket: OrsoDateTime,
+        open: f64,
+        high: f64,
+        close: f64,
+        volume: i64,
+    }
+
+    #[tokio::test]
+    async fn test_rollup_refresh_incremental() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::{Aggregate, Rollup};
+        use chrono::{Duration as ChronoDuration, TimeZone, Utc};
+        use std::time::Duration;
+
+        let db = TestDb::new(
+            get_test_db_config(),
+            &[migration!(TradeTick), migration!(Candle)],
+        )
+        .await?;
+        let start = Utc.with_ymd_and_hms(2024, 1, 1, 12, 0, 0).unwrap();
+        let tick = |n: i64, price: f64, seconds: i64| TradeTick {
+            id: Some(format!("t{n}")),
+            symbol: "BTC".to_string(),
+            price,
+            size: 1,
+            traded_at: OrsoDateTime::new(start + ChronoDuration::seconds(seconds)),
+        };
+        let ticks = [tick(1, 10.0, 5), tick(2, 12.0, 20), tick(3, 11.0, 65)];
+        TradeTick::batch_create(&ticks, &*db).await?;
+
+        let candles = Rollup::<TradeTick, Candle>::new("traded_at")
+            .group_by("symbol", "symbol")
+            .bucket("bucket", Duration::from_secs(60))
+            .first("open", "price")
+            .aggregate("high", Aggregate::Max, "price")
+            .last("close", "price")
+            .aggregate("volume", Aggregate::Sum, "size");
+        candles.install(&*db).await?;
+        assert_eq!(candles.refresh_all(&*db).await?, 2);
+
+        let first = FilterOperator::Single(Filter::eq("bucket", OrsoDateTime::new(start)));
+        let candle = Candle::find_one(first.clone(), &*db).await?.unwrap();
+        assert_eq!((candle.open, candle.high, candle.close), (10.0, 12.0, 12.0));
+        assert_eq!(candle.volume, 2);
+
+        // A late tick in the first minute: `since` falls mid-bucket, the whole minute is redone
+        TradeTick::batch_create(&[tick(4, 15.0, 50)], &*db).await?;
+        let since = OrsoDateTime::new(start + ChronoDuration::seconds(50));
+        assert_eq!(candles.refresh_incremental(&*db, since).await?, 2);
+        let candle = Candle::find_one(first, &*db).await?.unwrap();
+        assert_eq!((candle.open, candle.high, candle.close), (10.0, 15.0, 15.0));
+        assert_eq!(candle.volume, 3);
+        assert_eq!(Candle::count(&*db).await?, 2);
+
+        // Without a bucket, the groups touched since then are recomputed from all their rows
+        let hourly = Rollup::<TradeTick, Candle>::new("traded_at")
+            .group_by("symbol", "symbol || '-1h'")
+            .group_by("bucket", "date_trunc('hour', traded_at)")
+            .first("open", "price")
+            .aggregate("high", Aggregate::Max, "price")
+            .last("close", "price")
+            .aggregate("volume", Aggregate::Count, "*");
+        assert_eq!(hourly.refresh_incremental(&*db, since).await?, 1);
+        let hour = FilterOperator::Single(Filter::eq("symbol", "BTC-1h"));
+        let candle = Candle::find_one(hour, &*db).await?.unwrap();
+        assert_eq!((candle.open, candle.close, candle.volume), (10.0, 11.0, 4));
+
+        assert!(Rollup::<TradeTick, Candle>::new("traded_at")
+            .group_by("missing", "symbol")
+            .aggregate("volume", Aggregate::Count, "*")
+            .refresh_sql(false)
+            .is_err());
+        println!("✓ rollup refresh");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("volume_bars_081")]
+    struct VolumeBar {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        #[orso_column(unique)]
+        minute: String,
+        volume: i32,
+        high: f64,
+        source: String,
+    }
+
+    #[tokio::test]
+    async fn test_batch_upsert_with_strategy() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::{Merge, UpsertStrategy};
+
+        let db = TestDb::new(get_test_db_config(), &[migration!(VolumeBar)]).await?;
+        let bar = |minute: &str, volume: i32, high: f64, source: &str| VolumeBar {
+            id: None,
+            minute: minute.to_string(),
+            volume,
+            high,
+            source: source.to_string(),
+        };
+        let strategy = UpsertStrategy::new()
+            .column("volume", Merge::Add)
+            .column("high", Merge::Max)
+            .column("source", Merge::KeepExisting);
+
+        let first = [bar("12:00", 5, 10.0, "a"), bar("12:01", 1, 3.0, "a")];
+        VolumeBar::batch_upsert_with(&first, &strategy, &*db).await?;
+        let replay = [bar("12:00", 2, 8.0, "b"), bar("12:02", 4, 1.0, "b")];
+        VolumeBar::batch_upsert_with(&replay, &strategy, &*db).await?;
+
+        let noon = FilterOperator::Single(Filter::eq("minute", "12:00"));
+        let merged = VolumeBar::find_one(noon.clone(), &*db).await?.unwrap();
+        assert_eq!((merged.volume, merged.high), (7, 10.0));
+        assert_eq!(merged.source, "a");
+        assert_eq!(VolumeBar::count(&*db).await?, 3);
+
+        // A NULL stored value is added to as 0
+        db.execute(
+            "ALTER TABLE volume_bars_081 ALTER COLUMN volume DROP NOT NULL",
+            &[],
+        )
+        .await?;
+        db.execute(
+            "UPDATE volume_bars_081 SET volume = NULL WHERE minute = '12:01'",
+            &[],
+        )
+        .await?;
+        VolumeBar::batch_upsert_with(&[bar("12:01", 3, 1.0, "b")], &strategy, &*db).await?;
+        let filter = FilterOperator::Single(Filter::eq("minute", "12:01"));
+        assert_eq!(VolumeBar::find_one(filter, &*db).await?.unwrap().volume, 3);
+
+        // Plain upserts still overwrite
+        VolumeBar::batch_upsert(&[bar("12:00", 1, 20.0, "c")], &*db).await?;
+        let overwritten = VolumeBar::find_one(noon, &*db).await?.unwrap();
+        assert_eq!((overwritten.volume, overwritten.high), (1, 20.0));
+        assert_eq!(overwritten.source, "c");
+        println!("✓ batch upsert merge strategies");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("ingest_events_082")]
+    struct IngestEvent {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        #[orso_column(unique)]
+        sequence: i32,
+        body: String,
+    }
+
+    #[tokio::test]
+    async fn test_batch_create_ignore_conflicts() -> Result<(), Box<dyn std::error::Error>> {
+        let db = TestDb::new(get_test_db_config(), &[migration!(IngestEvent)]).await?;
+        let event = |sequence: i32, body: &str| IngestEvent {
+            id: Some(format!("e{sequence}")),
+            sequence,
+            body: body.to_string(),
+        };
+
+        let first = [event(1, "a"), event(2, "b")];
+        let inserted = IngestEvent::batch_create_ignore_conflicts(&first, &*db).await?;
+        assert_eq!(inserted.rows_affected, 2);
+
+        // A replay overlapping the first delivery, by primary key and by unique column
+        let mut moved = event(3, "c");
+        moved.sequence = 2;
+        let replay = [event(2, "b"), moved, event(4, "d")];
+        let inserted = IngestEvent::batch_create_ignore_conflicts(&replay, &*db).await?;
+        assert_eq!(inserted.returned_ids, vec!["e4".to_string()]);
+        assert_eq!(IngestEvent::count(&*db).await?, 3);
+        assert!(IngestEvent::find_by_id("e3", &*db).await?.is_none());
+
+        assert!(IngestEvent::batch_create(&[event(1, "a")], &*db)
+            .await
+            .is_err());
+        println!("✓ batch create ignoring conflicts");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("write_results_083")]
+    struct WriteProbe {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        label: String,
+    }
+
+    #[tokio::test]
+    async fn test_write_results() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::WriteResultExt;
+
+        let db = TestDb::new(get_test_db_config(), &[migration!(WriteProbe)]).await?;
+        let probe = |label: &str| WriteProbe {
+            id: None,
+            label: label.to_string(),
+        };
+
+        // Generated keys come back from inserts
+        let inserted = probe("a").insert(&*db).await?;
+        assert_eq!(inserted.rows_affected, 1);
+        let id = inserted.id().unwrap().to_string();
+        let mut stored = WriteProbe::find_by_id(&id, &*db).await?.unwrap();
+
+        stored.label = "b".to_string();
+        let updated = stored.update(&*db).await?;
+        assert_eq!(updated.returned_ids, vec![id.clone()]);
+
+        let batch = WriteProbe::batch_create(&[probe("c"), probe("d")], &*db).await?;
+        assert_eq!(batch.rows_affected, 2);
+        assert_eq!(batch.returned_ids.len(), 2);
+
+        // Writes to a row deleted meanwhile match nothing
+        assert!(!stored.delete(&*db).await?.is_noop());
+        assert!(stored.update(&*db).await?.is_noop());
+        assert!(stored.delete(&*db).await?.is_noop());
+
+        let unit: crate::Result<()> = probe("e").insert(&*db).await.discard();
+        unit?;
+
+        // Keys of any type come back as text
+        db.execute(
+            "ALTER TABLE write_results_083 ALTER COLUMN id DROP DEFAULT, \
+             ALTER COLUMN id TYPE uuid USING id::uuid, \
+             ALTER COLUMN id SET DEFAULT gen_random_uuid()",
+            &[],
+        )
+        .await?;
+        let inserted = probe("f").insert(&*db).await?;
+        assert_eq!(inserted.id().unwrap().len(), 36);
+        let batch = WriteProbe::batch_create(&[probe("g"), probe("h")], &*db).await?;
+        assert_eq!(batch.returned_ids.len(), 2);
+        let gone = FilterOperator::Single(Filter::eq("label", "f"));
+        let deleted = WriteProbe::delete_where_returning(gone, &*db).await?;
+        assert_eq!(deleted, inserted.returned_ids);
+        println!("✓ write results");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("epoch_events_084")]
+    struct EpochEvent {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        name: String,
+        #[orso_column(created_at, as = "unix_ms")]
+        created_at: Option<i64>,
+        #[orso_column(updated_at, as = "unix_ms")]
+        updated_at: Option<i64>,
+    }
+
+    #[tokio::test]
+    async fn test_unix_ms_timestamps() -> Result<(), Box<dyn std::error::Error>> {
+        let db = TestDb::new(get_test_db_config(), &[migration!(EpochEvent)]).await?;
+        let before = chrono::Utc::now().timestamp_millis() - 1000;
+
+        let event = EpochEvent {
+            name: "opened".to_string(),
+            ..Default::default()
+        };
+        let id = event.insert(&*db).await?.id().unwrap().to_string();
+        let mut stored = EpochEvent::find_by_id(&id, &*db).await?.unwrap();
+        let created_at = stored.created_at.unwrap();
+        assert!(created_at >= before);
+        assert_eq!(
+            stored.get_created_at().unwrap().timestamp_millis(),
+            created_at
+        );
+
+        // Updates bump the millis like they would a timestamp
+        stored.updated_at = Some(0);
+        stored.name = "closed".to_string();
+        stored.update(&*db).await?;
+        let updated = EpochEvent::find_by_id(&id, &*db).await?.unwrap();
+        assert!(updated.updated_at.unwrap() >= created_at);
+        assert_eq!(updated.created_at, Some(created_at));
+        println!("✓ unix_ms timestamps");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("advised_orders_085")]
+    struct AdvisedOrder {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        customer: String,
+        status: String,
+        total: i32,
+    }
+
+    #[tokio::test]
+    async fn test_index_advisor() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::advisor::ObservedQuery;
+        use crate::migrations::MigrationAction;
+        use crate::{Advisor, QueryLog};
+        use std::sync::Arc;
+
+        let test_db = TestDb::new(get_test_db_config(), &[migration!(AdvisedOrder)]).await?;
+        let search_path = format!("SET search_path TO \"{}\"", test_db.schema());
+        let log = Arc::new(QueryLog::new());
+        let db = Database::init(get_test_db_config().with_on_connect_sql(vec![search_path]))
+            .await?
+            .with_query_log(log.clone());
+
+        let open_for = |customer: &str| {
+            FilterOperator::And(vec![
+                FilterOperator::Single(crate::Filter::eq("customer", customer)),
+                FilterOperator::Single(crate::Filter::eq("status", "open")),
+            ])
+        };
+        let by_total = vec![Sort::new("total", SortOrder::Desc)];
+        for customer in ["a", "b", "c"] {
+            AdvisedOrder::list_where(open_for(customer), Some(by_total.clone()), None, &db).await?;
+        }
+        AdvisedOrder::find_where(open_for("a"), &db).await?;
+        let large = FilterOperator::Single(crate::Filter::gt("total", 100));
+        AdvisedOrder::find_where(large, &db).await?;
+        AdvisedOrder::find_by_id("missing", &db).await?;
+        // Raw statements are recorded as well
+        db.execute(
+            "UPDATE advised_orders_085 SET status = 'closed' \
+             WHERE customer = $1 AND status = 'open'",
+            &[&"z"],
+        )
+        .await?;
+
+        // Counting the pages, the unsorted lookup and the update share a shape
+        let observed = log.observed();
+        let open = ObservedQuery::new("advised_orders_085")
+            .with_equality("customer")
+            .with_equality("status");
+        assert_eq!(observed[0], (open, 5));
+        let suggestions = Advisor::suggest_indexes::<AdvisedOrder>(&db, &observed).await?;
+        assert_eq!(suggestions.len(), 2);
+        // The unsorted lookups by customer and status fold into the sorted one
+        assert_eq!(suggestions[0].queries, 8);
+        assert_eq!(
+            suggestions[0].sql(),
+            "CREATE INDEX IF NOT EXISTS advised_orders_085_customer_status_total_idx \
+             ON advised_orders_085 (customer, status, total DESC)"
+        );
+        assert_eq!(
+            suggestions[1].columns,
+            vec![("total".to_string(), SortOrder::Asc)]
+        );
+
+        let results = Migrations::init(&db, &[Advisor::migration(suggestions)]).await?;
+        let MigrationAction::IndexesCreated { indexes } = &results[0].action else {
+            panic!("expected created indexes, got {}", results[0].action);
+        };
+        assert_eq!(indexes.len(), 2);
+        let table = crate::Schema::describe(&db, "advised_orders_085").await?;
+        assert!(table.index("advised_orders_085_total_idx").is_some());
+        assert!(Advisor::suggest_indexes::<AdvisedOrder>(&db, &observed)
+            .await?
+            .is_empty());
+
+        // Pipelined statements are recorded in the same log
+        log.clear();
+        let pipeline = db.pipeline().await?;
+        assert!(crate::Executor::query_log(&pipeline).is_some());
+        AdvisedOrder::find_where(open_for("b"), &pipeline).await?;
+        drop(pipeline);
+        let open = ObservedQuery::new("advised_orders_085")
+            .with_equality("customer")
+            .with_equality("status");
+        assert_eq!(log.observed(), vec![(open, 1)]);
+
+        let shape = ObservedQuery::from_sql(
+            "SELECT o.id FROM orders o WHERE o.customer = $1 \
+             AND (o.total BETWEEN $2 AND $3 OR o.status = $4) AND lower(o.email) = $5 \
+             ORDER BY o.created_at DESC LIMIT 5",
+        );
+        let expected = ObservedQuery::new("orders")
+            .with_equality("customer")
+            .with_sort("created_at", SortOrder::Desc);
+        assert_eq!(shape, Some(expected));
+        let shape = ObservedQuery::from_sql(
+            "DELETE FROM \"orders\" WHERE (total BETWEEN $1 AND $2) AND status IS NULL",
+        );
+        let expected = ObservedQuery::new("orders")
+            .with_equality("status")
+            .with_range("total");
+        assert_eq!(shape, Some(expected));
+        assert!(ObservedQuery::from_sql(
+            "SELECT * FROM orders JOIN customers ON customers.id = orders.customer"
+        )
+        .is_none());
+        let shape = ObservedQuery::from_sql("SELECT * FROM orders ORDER BY status, total DESC");
+        let expected = ObservedQuery::new("orders")
+            .with_sort("status", SortOrder::Asc)
+            .with_sort("total", SortOrder::Desc);
+        assert_eq!(shape, Some(expected));
+        assert!(ObservedQuery::from_sql(
+            "UPDATE orders SET total = c.total FROM carts c WHERE c.id = orders.id"
+        )
+        .is_none());
+        assert!(ObservedQuery::from_sql("INSERT INTO orders (id) VALUES ($1)").is_none());
+        println!("✓ index advisor");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("maintained_rows_086")]
+    struct MaintainedRow {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        #[orso_column(unique)]
+        payload: String,
+    }
+
+    #[tokio::test]
+    async fn test_maintenance() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::Maintenance;
+
+        let db = TestDb::new(get_test_db_config(), &[migration!(MaintainedRow)]).await?;
+        let rows: Vec<MaintainedRow> = (0..2000)
+            .map(|i| MaintainedRow {
+                id: None,
+                payload: format!("{i:0>200}"),
+            })
+            .collect();
+        MaintainedRow::batch_create(&rows, &*db).await?;
+        MaintainedRow::delete_where(
+            FilterOperator::Single(crate::Filter::gt("payload", format!("{:0>200}", 100))),
+            &*db,
+        )
+        .await?;
+
+        let analyzed = Maintenance::analyze::<MaintainedRow>(&db).await?;
+        assert_eq!(analyzed.statement, "ANALYZE maintained_rows_086");
+        assert!(analyzed.size_before > 0);
+
+        Maintenance::vacuum::<MaintainedRow>(&db, false).await?;
+        let full = Maintenance::vacuum::<MaintainedRow>(&db, true).await?;
+        assert!(full.reclaimed() > 0);
+        let reindexed = Maintenance::reindex::<MaintainedRow>(&db).await?;
+        assert_eq!(reindexed.table, "maintained_rows_086");
+        assert_eq!(MaintainedRow::count(&*db).await?, 101);
+
+        let missing = Maintenance::vacuum_with_table(&db, "no_such_table_086", true).await;
+        assert!(missing.is_err());
+        println!("✓ maintenance");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("sized_samples_087")]
+    struct SizedSample {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        kept: bool,
+        #[orso_column(compress)]
+        values: Vec<i64>,
+    }
+
+    #[tokio::test]
+    async fn test_table_stats() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::{Maintenance, Stats};
+
+        let db = TestDb::new(get_test_db_config(), &[migration!(SizedSample)]).await?;
+        let samples: Vec<SizedSample> = (0..400)
+            .map(|i| SizedSample {
+                id: None,
+                kept: i % 2 == 0,
+                values: (0..64).map(|v| v * i).collect(),
+            })
+            .collect();
+        SizedSample::batch_create(&samples, &*db).await?;
+        SizedSample::delete_where(
+            FilterOperator::Single(crate::Filter::eq("kept", false)),
+            &*db,
+        )
+        .await?;
+        Maintenance::analyze::<SizedSample>(&db).await?;
+
+        let sizes = Stats::table_sizes(&db).await?;
+        let size = sizes
+            .iter()
+            .find(|s| s.table == "sized_samples_087")
+            .unwrap();
+        assert!(size.heap_bytes > 0 && size.index_bytes > 0);
+        assert_eq!(
+            size.total_bytes,
+            size.heap_bytes + size.index_bytes + size.toast_bytes
+        );
+
+        let bloat = Stats::bloat::<SizedSample>(&db).await?;
+        assert_eq!(bloat.live_rows, 200);
+        assert!(bloat.last_analyze.is_some());
+        let churned = crate::TableBloat {
+            dead_rows: 600,
+            ..bloat.clone()
+        };
+        assert_eq!(churned.dead_ratio(), 0.75);
+
+        Maintenance::vacuum::<SizedSample>(&db, false).await?;
+        let bloat = Stats::bloat::<SizedSample>(&db).await?;
+        assert_eq!(bloat.dead_rows, 0);
+        assert!(bloat.last_vacuum.is_some());
+
+        assert!(Stats::bloat_with_table(&db, "no_such_table_087")
+            .await
+            .is_err());
+        println!("✓ table stats");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("loaded_ticks_088")]
+    struct LoadedTick {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        #[orso_column(unique)]
+        symbol: String,
+        price: f64,
+        note: Option<String>,
+        sizes: Vec<i64>,
+    }
+
+    #[tokio::test]
+    async fn test_loader() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::{LoadMethod, Loader, LoaderOptions};
+
+        let db = TestDb::new(get_test_db_config(), &[migration!(LoadedTick)]).await?;
+        let ticks = |from: i32, to: i32| {
+            (from..to).map(|i| LoadedTick {
+                id: None,
+                symbol: format!("SYM{i}"),
+                price: i as f64 / 4.0,
+                note: (i % 3 == 0).then(|| format!("say \"{i}\", then stop")),
+                sizes: vec![i as i64, -1],
+            })
+        };
+
+        let options = LoaderOptions::new()
+            .with_batch_size(100)
+            .with_concurrency(3);
+        let (loader, _failures) = Loader::<LoadedTick>::new(options.clone());
+        let written = loader
+            .run(futures_util::stream::iter(ticks(0, 250)), &db)
+            .await?;
+        assert_eq!(written, 250);
+        assert_eq!(loader.metrics().batches_written(), 3);
+
+        let (loader, _failures) =
+            Loader::<LoadedTick>::new(options.clone().with_method(LoadMethod::Copy));
+        let written = loader
+            .run(futures_util::stream::iter(ticks(250, 500)), &db)
+            .await?;
+        assert_eq!(written, 250);
+        assert_eq!(LoadedTick::count(&*db).await?, 500);
+
+        let copied = LoadedTick::find_one(
+            FilterOperator::Single(crate::Filter::eq("symbol", "SYM300")),
+            &*db,
+        )
+        .await?
+        .unwrap();
+        assert_eq!(copied.note.as_deref(), Some("say \"300\", then stop"));
+        assert_eq!(copied.price, 75.0);
+        assert_eq!(copied.sizes, vec![300, -1]);
+        let copied = LoadedTick::find_one(
+            FilterOperator::Single(crate::Filter::eq("symbol", "SYM301")),
+            &*db,
+        )
+        .await?
+        .unwrap();
+        assert_eq!(copied.note, None);
+
+        // The batch holding a duplicate symbol fails alone
+        let (loader, mut failures) = Loader::<LoadedTick>::new(options);
+        let written = loader
+            .run(futures_util::stream::iter(ticks(499, 700)), &db)
+            .await?;
+        assert_eq!(written, 101);
+        let failure = failures.recv().await.unwrap();
+        assert_eq!(failure.models.len(), 100);
+        assert_eq!(failure.models[0].symbol, "SYM499");
+        let metrics = loader.metrics();
+        assert_eq!(metrics.rows_received(), 201);
+        assert_eq!((metrics.batches_failed(), metrics.rows_failed()), (1, 100));
+        assert_eq!(LoadedTick::count(&*db).await?, 601);
+
+        // Without a receiver, the failure ends the run
+        drop(failures);
+        let result = loader
+            .run(futures_util::stream::iter(ticks(0, 10)), &db)
+            .await;
+        assert!(result.is_err());
+        println!("✓ loader");
+
+        Ok(())
+    }
+
+    #[cfg(feature = "cdc")]
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+    #[orso_table("captured_orders_089")]
+    struct CapturedOrder {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        status: String,
+        lines: Vec<i64>,
+        shipped: bool,
+        #[orso_column(jsonb)]
+        meta: Option<serde_json::Value>,
+    }
+
+    /// Needs a server running with `wal_level = logical`
+    #[cfg(feature = "cdc")]
+    #[tokio::test]
+    async fn test_change_feed() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::{Change, ChangeFeed};
+        use futures_util::TryStreamExt;
+
+        let config = get_test_db_config();
+        let db = TestDb::new(config.clone(), &[migration!(CapturedOrder)]).await?;
+        let feed = ChangeFeed::<CapturedOrder>::new("captured_orders_089")
+            .with_status_interval(std::time::Duration::from_millis(100));
+        feed.remove(&db).await?;
+        feed.create(&db).await?;
+        let start = feed.position(&db).await?.unwrap();
+
+        let mut order = CapturedOrder {
+            id: Some("o1".into()),
+            status: "open".into(),
+            lines: vec![3, 4],
+            shipped: false,
+            meta: Some(serde_json::json!({"channel": "web, \"eu\""})),
+        };
+        order.insert(&*db).await?;
+        order.status = "shipped".into();
+        order.shipped = true;
+        order.update(&*db).await?;
+        order.delete(&*db).await?;
+
+        let mut changes = Box::pin(feed.stream(&db, &config));
+        let wait = std::time::Duration::from_secs(5);
+        let inserted = tokio::time::timeout(wait, changes.try_next())
+            .await??
+            .unwrap();
+        let Change::Insert { after } = inserted.change else {
+            panic!("expected an insert, got {:?}", inserted.change);
+        };
+        assert_eq!(after.lines, vec![3, 4]);
+        assert_eq!(after.meta, order.meta);
+        assert!(inserted.lsn >= start);
+
+        let updated = tokio::time::timeout(wait, changes.try_next())
+            .await??
+            .unwrap();
+        let Change::Update { before, after } = updated.change else {
+            panic!("expected an update");
+        };
+        assert_eq!(before.unwrap().status, "open");
+        assert_eq!(after, order);
+        assert!(updated.xid != inserted.xid);
+
+        let deleted = tokio::time::timeout(wait, changes.try_next())
+            .await??
+            .unwrap();
+        assert_eq!(deleted.change, Change::Delete { before: order });
+        // Confirmed only once the stream is polled past it
+        assert!(feed.position(&db).await?.unwrap() < deleted.lsn);
+
+        CapturedOrder::truncate(crate::ConfirmTruncate::default(), &*db).await?;
+        let truncated = tokio::time::timeout(wait, changes.try_next())
+            .await??
+            .unwrap();
+        assert_eq!(truncated.change, Change::Truncate);
+        let confirmed = async {
+            while feed.position(&db).await?.unwrap() < deleted.lsn {
+                tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+            }
+            Ok::<_, crate::Error>(())
+        };
+        tokio::time::timeout(wait, confirmed).await??;
+
+        // A new stream sees the unconfirmed truncate again, once the first releases the slot
+        drop(changes);
+        let mut changes = Box::pin(feed.stream(&db, &config));
+        let again = tokio::time::timeout(wait, changes.try_next())
+            .await??
+            .unwrap();
+        assert_eq!(again.change, Change::Truncate);
+        drop(changes);
+
+        feed.remove(&db).await?;
+        assert_eq!(feed.position(&db).await?, None);
+        assert_eq!(
+            "16/B374D848".parse::<crate::Lsn>()?.to_string(),
+            "16/B374D848"
+        );
+        println!("✓ change feed");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_health_report() -> Result<(), Box<dyn std::error::Error>> {
+        let db = Database::init(get_test_db_config()).await?;
+        let report = db.health().await;
+        assert!(report.healthy, "{:?}", report.error);
+        assert!(report.latency > std::time::Duration::ZERO);
+        assert_eq!(report.pool.max_size, 10);
+        assert!(report.pool_utilization() < 1.0);
+        assert_eq!(report.replication_lag, None);
+        assert!(report.last_error.is_none());
+
+        assert!(db
+            .query("SELECT * FROM no_such_table_090", &[])
+            .await
+            .is_err());
+        let last = db.health().await.last_error.unwrap();
+        assert!(
+            last.message.contains("no_such_table_090"),
+            "{}",
+            last.message
+        );
+        assert_eq!(last.code.as_deref(), Some("42P01"));
+
+        let down = Database::init(
+            DatabaseConfig::new("postgresql://postgres@127.0.0.1:1/postgres")
+                .with_connect_timeout(std::time::Duration::from_millis(200)),
+        )
+        .await?;
+        let report = down.health().await;
+        assert!(!report.healthy);
+        assert!(report.error.is_some());
+        assert!(down.last_error().is_some());
+        println!("✓ health report");
+
+        Ok(())
+    }
+
+    #[derive(Default)]
+    struct RecordingHooks {
+        events: std::sync::Mutex<Vec<String>>,
+        errors: std::sync::Mutex<Vec<String>>,
+    }
+
+    impl crate::Hooks for RecordingHooks {
+        fn before_query(&self, sql: &str) -> crate::Result<()> {
+            if sql.contains("vetoed_091") {
+                return Err(crate::Error::validation("Vetoed by hook"));
+            }
+            self.events.lock().unwrap().push(format!("before {sql}"));
+            Ok(())
+        }
+
+        fn after_query(&self, sql: &str, _duration: std::time::Duration, rows: u64) {
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("after {sql} {rows}"));
+        }
+
+        fn on_error(&self, sql: &str, _duration: std::time::Duration, error: &crate::Error) {
+            let code = match error {
+                crate::Error::PostgreSql { code, .. } => code.clone().unwrap_or_default(),
+                _ => String::new(),
+            };
+            self.events
+                .lock()
+                .unwrap()
+                .push(format!("error {sql} {code}"));
+            self.errors.lock().unwrap().push(error.to_string());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_database_hooks() -> Result<(), Box<dyn std::error::Error>> {
+        let hooks = std::sync::Arc::new(RecordingHooks::default());
+        let db = Database::init(get_test_db_config())
+            .await?
+            .with_hooks(hooks.clone());
+
+        db.query("SELECT generate_series(1, 3)", &[]).await?;
+        assert!(db.execute("SELECT * FROM missing_091", &[]).await.is_err());
+        let vetoed = db.query_one("SELECT 'vetoed_091'", &[]).await;
+        assert!(matches!(vetoed, Err(crate::Error::Validation { .. })));
+        let pipeline = db.pipeline().await?;
+        crate::Executor::execute(&pipeline, "SELECT 1", &[]).await?;
+
+        let events = hooks.events.lock().unwrap().clone();
+        assert_eq!(
+            events,
+            vec![
+                "before SELECT generate_series(1, 3)",
+                "after SELECT generate_series(1, 3) 3",
+                "before SELECT * FROM missing_091",
+                "error SELECT * FROM missing_091 42P01",
+                "before SELECT 1",
+                "after SELECT 1 1",
+            ]
+        );
+        let errors = hooks.errors.lock().unwrap().clone();
+        assert!(
+            errors[0].contains("relation \"missing_091\" does not exist"),
+            "{errors:?}"
+        );
+
+        // Transactions report each of their statements
+        hooks.events.lock().unwrap().clear();
+        db.transaction_with(crate::TxOptions::default(), |tx| {
+            Box::pin(async move { crate::Executor::execute(tx, "SELECT 2", &[]).await })
+        })
+        .await?;
+        let vetoed = db
+            .transaction_with(crate::TxOptions::default(), |tx| {
+                Box::pin(
+                    async move { crate::Executor::execute(tx, "SELECT 'vetoed_091'", &[]).await },
+                )
+            })
+            .await;
+        assert!(matches!(vetoed, Err(crate::Error::Validation { .. })));
+        let events = hooks.events.lock().unwrap().clone();
+        assert_eq!(
+            events,
+            vec![
+                "before BEGIN",
+                "after BEGIN 0",
+                "before SELECT 2",
+                "after SELECT 2 1",
+                "after COMMIT 0",
+                "before BEGIN",
+                "after BEGIN 0",
+                "after ROLLBACK 0",
+            ]
+        );
+
+        // Work spanning several statements on one connection is reported once
+        db.execute("DROP TABLE IF EXISTS hooks_test_105", &[])
+            .await?;
+        db.execute("CREATE TABLE hooks_test_105 (id int PRIMARY KEY)", &[])
+            .await?;
+        hooks.events.lock().unwrap().clear();
+        crate::Maintenance::reindex_with_table(&db, "hooks_test_105").await?;
+        let events = hooks.events.lock().unwrap().clone();
+        assert_eq!(
+            &events[events.len() - 2..],
+            [
+                "before REINDEX TABLE hooks_test_105",
+                "after REINDEX TABLE hooks_test_105 0"
+            ]
+        );
+        db.execute("DROP TABLE hooks_test_105", &[]).await?;
+        println!("✓ database hooks");
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_circuit_breaker() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::{CircuitBreaker, CircuitState};
+
+        let cool_down = std::time::Duration::from_millis(300);
+        let breaker = std::sync::Arc::new(CircuitBreaker::new(2, cool_down));
+        let down = Database::init(
+            DatabaseConfig::new("postgresql://postgres@127.0.0.1:1/postgres")
+                .with_connect_timeout(std::time::Duration::from_millis(200)),
+        )
+        .await?
+        .with_circuit_breaker(breaker.clone());
+        let up = Database::init(get_test_db_config())
+            .await?
+            .with_circuit_breaker(breaker.clone());
+
+        // Errors from a reachable server don't count
+        assert!(up.query("SELECT * FROM missing_092", &[]).await.is_err());
+        assert!(down.query("SELECT 1", &[]).await.is_err());
+        assert_eq!(breaker.state(), CircuitState::Closed { failures: 1 });
+        assert!(down.query("SELECT 1", &[]).await.is_err());
+        assert_eq!(breaker.state(), CircuitState::Open);
+
+        match up.query("SELECT 1", &[]).await {
+            Err(crate::Error::CircuitOpen { retry_after, .. }) => assert!(retry_after <= cool_down),
+            other => panic!("expected the circuit to be open, got {other:?}"),
+        }
+
+        // A failed trial opens it again, a successful one closes it
+        tokio::time::sleep(cool_down).await;
+        assert!(matches!(
+            down.query("SELECT 1", &[]).await,
+            Err(crate::Error::Pool { .. })
+        ));
+        assert_eq!(breaker.state(), CircuitState::Open);
+        tokio::time::sleep(cool_down).await;
+        up.query("SELECT 1", &[]).await?;
+        assert_eq!(breaker.state(), CircuitState::Closed { failures: 0 });
+
+        // Transactions that can't get a connection count too
+        let began = down
+            .transaction_with(crate::TxOptions::default(), |_| Box::pin(async { Ok(()) }))
+            .await;
+        assert!(matches!(began, Err(crate::Error::Pool { .. })));
+        assert_eq!(breaker.state(), CircuitState::Closed { failures: 1 });
+        println!("✓ circuit breaker");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("ranked_traders_093")]
+    struct RankedTrader {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        name: String,
+        volume: f64,
+    }
+
+    #[tokio::test]
+    async fn test_named_queries() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::NamedQueries;
+
+        let test_db = TestDb::new(get_test_db_config(), &[migration!(RankedTrader)]).await?;
+        let traders: Vec<RankedTrader> = (1..=5)
+            .map(|i| RankedTrader {
+                id: None,
+                name: format!("trader {i}"),
+                volume: i as f64 * 100.0,
+            })
+            .collect();
+        RankedTrader::batch_create(&traders, &*test_db).await?;
+
+        let queries = NamedQueries::new()
+            .register::<RankedTrader>(
+                "top_traders",
+                "SELECT * FROM ranked_traders_093 WHERE volume > $1 ORDER BY volume DESC LIMIT $2",
+            )
+            .register::<RankedTrader>("names_only", "SELECT name FROM ranked_traders_093")
+            .register::<RankedTrader>("typo", "SELECT * FROM ranked_traders_093 WHERE volum > 1");
+        let Err(crate::Error::Validation { errors, .. }) = queries.validate(&test_db).await else {
+            panic!("expected invalid queries");
+        };
+        let mut failed: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+        failed.sort();
+        assert_eq!(failed, vec!["names_only", "typo"]);
+        assert!(errors.iter().any(|e| e.message.contains("id, volume")));
+
+        let queries = NamedQueries::new().register::<RankedTrader>(
+            "top_traders",
+            "SELECT * FROM ranked_traders_093 WHERE volume > $1 ORDER BY volume DESC LIMIT $2",
+        );
+        queries.validate(&test_db).await?;
+        let db = Database::init(
+            get_test_db_config()
+                .with_on_connect_sql(vec![format!("SET search_path TO \"{}\"", test_db.schema())]),
+        )
+        .await?
+        .with_named_queries(std::sync::Arc::new(queries));
+
+        let top = db
+            .named::<RankedTrader>("top_traders", &[&150.0f64, &2i64])
+            .await?;
+        let names: Vec<&str> = top.iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(names, vec!["trader 5", "trader 4"]);
+        assert!(db.named::<RankedTrader>("missing", &[]).await.is_err());
+        assert!(db.named::<SizedSample>("top_traders", &[]).await.is_err());
+        println!("✓ named queries");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("checked_players_094")]
+    struct CheckedPlayer {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        name: String,
+        score: i32,
+        active: bool,
+    }
+
+    #[tokio::test]
+    async fn test_checked_column_names() -> Result<(), Box<dyn std::error::Error>> {
+        let test_db = TestDb::new(get_test_db_config(), &[migration!(CheckedPlayer)]).await?;
+        let players: Vec<CheckedPlayer> = [("ada", 30, true), ("bob", 10, true), ("cy", 50, false)]
+            .into_iter()
+            .map(|(name, score, active)| CheckedPlayer {
+                id: None,
+                name: name.into(),
+                score,
+                active,
+            })
+            .collect();
+        CheckedPlayer::batch_create(&players, &*test_db).await?;
+
+        let col = CheckedPlayer::col();
+        assert_eq!(col.score.name(), "score");
+        assert_eq!(col.active.to_string(), "active");
+
+        let filter = FilterOperator::And(vec![
+            FilterOperator::Single(crate::filter!(col.score, Operator::Gt, 20)),
+            FilterOperator::Single(Filter::eq(col.active, true)),
+        ]);
+        let found = CheckedPlayer::find_where(filter, &*test_db).await?;
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].name, "ada");
+
+        let ranked = crate::QueryBuilder::new(CheckedPlayer::table_name())
+            .order_by(Sort::desc(col.score))
+            .execute::<CheckedPlayer>(&*test_db)
+            .await?;
+        let names: Vec<&str> = ranked.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["cy", "ada", "bob"]);
+        println!("✓ checked column names");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("typed_players_095")]
+    struct TypedPlayer {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        name: String,
+        age: i32,
+        nickname: Option<String>,
+        rating: f64,
+    }
+
+    /// Named like the filters of `TypedPlayer`, which have no name of their own
+    #[derive(Debug)]
+    struct TypedPlayerFilter;
+
+    #[tokio::test]
+    async fn test_typed_filters() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::Filterable;
+
+        let test_db = TestDb::new(get_test_db_config(), &[migration!(TypedPlayer)]).await?;
+        let players = [
+            ("Bob", 30, Some("bobby"), 1500.0),
+            ("Joe", 22, None, 1700.0),
+            ("Ann", 41, None, 1200.0),
+            ("Tom", 35, Some("tommy"), 1800.0),
+        ]
+        .map(|(name, age, nickname, rating)| TypedPlayer {
+            id: None,
+            name: name.into(),
+            age,
+            nickname: nickname.map(Into::into),
+            rating,
+        });
+        TypedPlayer::batch_create(&players, &*test_db).await?;
+
+        let names = |players: Vec<TypedPlayer>| {
+            let mut names: Vec<String> = players.into_iter().map(|p| p.name).collect();
+            names.sort();
+            names
+        };
+
+        let filter = TypedPlayer::filter()
+            .age()
+            .gt(25)
+            .and(TypedPlayer::filter().name().ilike("%O%"));
+        let found = TypedPlayer::find_where(filter.into(), &*test_db).await?;
+        assert_eq!(names(found), vec!["Bob", "Tom"]);
+
+        let filter = TypedPlayer::filter()
+            .nickname()
+            .is_null()
+            .or(TypedPlayer::filter().rating().between(1750.0, 2000.0));
+        let found = TypedPlayer::find_where(filter.into(), &*test_db).await?;
+        assert_eq!(names(found), vec!["Ann", "Joe", "Tom"]);
+
+        let filter = !TypedPlayer::filter().name().in_values(["Bob", "Ann"]);
+        let found = TypedPlayer::find_where(filter.into(), &*test_db).await?;
+        assert_eq!(names(found), vec!["Joe", "Tom"]);
+
+        let filter = TypedPlayer::filter().nickname().eq("tommy");
+        let found = TypedPlayer::find_where(filter.into(), &*test_db).await?;
+        assert_eq!(names(found), vec!["Tom"]);
+
+        // The generated type doesn't take the model's namespace
+        let _: <TypedPlayer as Filterable>::Filter = TypedPlayer::filter();
+        assert_eq!(format!("{:?}", TypedPlayerFilter), "TypedPlayerFilter");
+        println!("✓ typed filters");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("grouped_trades_096")]
+    struct GroupedTrade {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        category: String,
+        volume: f64,
+        quantity: i32,
+    }
+
+    #[tokio::test]
+    async fn test_grouped_aggregates() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::grouping::{avg, count, max, sum};
+
+        let test_db = TestDb::new(get_test_db_config(), &[migration!(GroupedTrade)]).await?;
+        let trades = [
+            ("fx", 100.0, 1),
+            ("fx", 250.0, 4),
+            ("fx", 50.0, 2),
+            ("rates", 1000.0, 10),
+            ("rates", 500.0, 3),
+            ("crypto", 75.0, 7),
+        ]
+        .map(|(category, volume, quantity)| GroupedTrade {
+            id: None,
+            category: category.into(),
+            volume,
+            quantity,
+        });
+        GroupedTrade::batch_create(&trades, &*test_db).await?;
+
+        let rows = crate::QueryBuilder::new(GroupedTrade::table_name())
+            .group_by(vec![GroupedTrade::col().category])
+            .aggregates([
+                count(),
+                sum("volume").alias("volume"),
+                avg("quantity"),
+                max(GroupedTrade::col().quantity),
+            ])
+            .having(count().gt(1))
+            .order_by(Sort::desc("volume"))
+            .execute_grouped(&*test_db)
+            .await?;
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].get::<String>("category")?, "rates");
+        assert_eq!(rows[0].get::<i64>("count")?, 2);
+        assert_eq!(rows[0].get::<f64>("volume")?, 1500.0);
+        assert_eq!(rows[0].get::<f64>("avg_quantity")?, 6.5);
+        assert_eq!(rows[0].get::<i32>("max_quantity")?, 10);
+        assert_eq!(
+            rows[1].keys().collect::<Vec<_>>(),
+            vec![("category", &Value::Text("fx".into()))]
+        );
+        assert!(rows[1].get::<i64>("missing").is_err());
+
+        let totals = crate::QueryBuilder::new(GroupedTrade::table_name())
+            .aggregates([count(), sum("quantity")])
+            .having(sum("quantity").ge(27.5))
+            .execute_grouped(&*test_db)
+            .await?;
+        assert!(totals.is_empty());
+        println!("✓ grouped aggregates");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("nullable_tickets_097")]
+    struct NullableTicket {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        title: String,
+        status: Option<String>,
+        priority: Option<i32>,
+    }
+
+    #[tokio::test]
+    async fn test_null_aware_filters() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::filters::FilterOperations;
+
+        let test_db = TestDb::new(get_test_db_config(), &[migration!(NullableTicket)]).await?;
+        let tickets = [
+            ("a", Some("open"), Some(1)),
+            ("b", Some("closed"), None),
+            ("c", None, Some(3)),
+            ("d", None, None),
+        ]
+        .map(|(title, status, priority)| NullableTicket {
+            id: None,
+            title: title.into(),
+            status: status.map(Into::into),
+            priority,
+        });
+        NullableTicket::batch_create(&tickets, &*test_db).await?;
+
+        let titles = |filter: Filter| {
+            let db = &test_db;
+            async move {
+                let mut titles: Vec<String> =
+                    NullableTicket::find_where(FilterOperator::Single(filter), &**db)
+                        .await?
+                        .into_iter()
+                        .map(|t| t.title)
+                        .collect();
+                titles.sort();
+                Ok::<_, crate::Error>(titles)
+            }
+        };
+
+        assert_eq!(titles(Filter::eq("status", Value::Null)).await?, ["c", "d"]);
+        assert_eq!(
+            titles(Filter::ne("status", None::<String>)).await?,
+            ["a", "b"]
+        );
+        assert_eq!(
+            titles(Filter::eq("priority", Value::Null)).await?,
+            ["b", "d"]
+        );
+        let open_or_unset = Filter::in_values("status", vec![Some("open".to_string()), None]);
+        assert_eq!(titles(open_or_unset).await?, ["a", "c", "d"]);
+        let not_closed_nor_unset =
+            Filter::not_in_values("status", vec![Some("closed".to_string()), None]);
+        assert_eq!(titles(not_closed_nor_unset).await?, ["a"]);
+        let only_null = Filter::in_values("priority", vec![Value::Null]);
+        assert_eq!(titles(only_null).await?, ["b", "d"]);
+        assert!(titles(Filter::gt("priority", Value::Null)).await.is_err());
+
+        let by_field = NullableTicket::find_by_field("status", Value::Null, &*test_db).await?;
+        assert_eq!(by_field.len(), 2);
+
+        let filter = FilterOperator::And(vec![
+            FilterOperator::Single(Filter::eq("status", Value::Null)),
+            FilterOperator::Single(Filter::in_values(
+                "priority",
+                vec![Value::Integer(3), Value::Null],
+            )),
+        ]);
+        let inline = FilterOperations::build_filter_operator_inline(&filter)?;
+        assert!(inline.starts_with("(status IS NULL AND (priority = ANY(ARRAY[3]"));
+        assert!(inline.ends_with(" OR priority IS NULL))"));
+        println!("✓ null-aware filters");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("flagged_accounts_098")]
+    struct FlaggedAccount {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        name: String,
+        active: bool,
+        verified: Option<bool>,
+    }
+
+    #[tokio::test]
+    async fn test_boolean_filters() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::Filterable;
+
+        let test_db = TestDb::new(get_test_db_config(), &[migration!(FlaggedAccount)]).await?;
+        let rows = test_db
+            .query(
+                "SELECT data_type FROM information_schema.columns \
+                 WHERE table_schema = current_schema() AND table_name = $1 \
+                 AND column_name IN ('active', 'verified')",
+                &[&"flagged_accounts_098"],
+            )
+            .await?;
+        assert!(rows.iter().all(|row| row.get::<_, String>(0) == "boolean"));
+
+        let accounts = [
+            ("a", true, Some(true)),
+            ("b", true, Some(false)),
+            ("c", false, None),
+            ("d", false, Some(true)),
+        ]
+        .map(|(name, active, verified)| FlaggedAccount {
+            id: None,
+            name: name.into(),
+            active,
+            verified,
+        });
+        FlaggedAccount::batch_create(&accounts, &*test_db).await?;
+        let stored = test_db
+            .query_one(
+                "SELECT verified FROM flagged_accounts_098 WHERE name = 'b'",
+                &[],
+            )
+            .await?;
+        assert_eq!(stored.get::<_, Option<bool>>(0), Some(false));
+
+        let names = |filter: FilterOperator| {
+            let db = &test_db;
+            async move {
+                let mut names: Vec<String> = FlaggedAccount::find_where(filter, &**db)
+                    .await?
+                    .into_iter()
+                    .map(|a| a.name)
+                    .collect();
+                names.sort();
+                Ok::<_, crate::Error>(names)
+            }
+        };
+        let single = FilterOperator::Single;
+
+        assert_eq!(
+            names(single(Filter::is_true("verified"))).await?,
+            ["a", "d"]
+        );
+        assert_eq!(names(single(Filter::is_false("verified"))).await?, ["b"]);
+        assert_eq!(names(single(Filter::is_unknown("verified"))).await?, ["c"]);
+        // Not true includes unknown, unlike `verified != true`
+        assert_eq!(
+            names(!single(Filter::is_true("verified"))).await?,
+            ["b", "c"]
+        );
+        assert_eq!(names(single(Filter::ne("verified", true))).await?, ["b"]);
+        assert_eq!(
+            names(single(Filter::eq("verified", None::<bool>))).await?,
+            ["c"]
+        );
+        assert_eq!(
+            names(single(crate::filter!("active", is_false))).await?,
+            ["c", "d"]
+        );
+
+        let typed = FlaggedAccount::filter()
+            .active()
+            .is_true()
+            .and(FlaggedAccount::filter().verified().is_false());
+        assert_eq!(names(typed.into()).await?, ["b"]);
+        let typed = FlaggedAccount::filter().verified().is_unknown();
+        assert_eq!(names(typed.into()).await?, ["c"]);
+
+        let found =
+            FlaggedAccount::find_where(single(Filter::is_unknown("verified")), &*test_db).await?;
+        assert_eq!(found[0].verified, None);
+        assert!(!found[0].active);
+        println!("✓ boolean filters");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("priced_orders_099")]
+    struct PricedOrder {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        #[orso_column(type = "decimal(20, 8)")]
+        price: f64,
+        #[orso_column(type = "VARCHAR(16)")]
+        symbol: String,
+    }
+
+    #[tokio::test]
+    async fn test_column_type_overrides() -> Result<(), Box<dyn std::error::Error>> {
+        use orso::migrations::MigrationAction;
+
+        let test_db = TestDb::new(get_test_db_config(), &[]).await?;
+        test_db
+            .execute(
+                "CREATE TABLE priced_orders_099 (id TEXT PRIMARY KEY DEFAULT gen_random_uuid(), \
+                 price DOUBLE PRECISION NOT NULL, symbol TEXT NOT NULL)",
+                &[],
+            )
+            .await?;
+        test_db
+            .execute(
+                "INSERT INTO priced_orders_099 (price, symbol) VALUES (1e15, 'BTC')",
+                &[],
+            )
+            .await?;
+
+        // 10^15 doesn't fit NUMERIC(20,8), which leaves 12 digits before the point
+        match Migrations::init(&test_db, &[migration!(PricedOrder)]).await {
+            Err(crate::Error::Schema { table, column, .. }) => {
+                assert_eq!(table.as_deref(), Some("priced_orders_099"));
+                assert_eq!(column.as_deref(), Some("price"));
+            }
+            other => panic!("Expected a schema error, got {other:?}"),
+        }
+
+        test_db
+            .execute("UPDATE priced_orders_099 SET price = 12.5", &[])
+            .await?;
+        let results = Migrations::init(&test_db, &[migration!(PricedOrder)]).await?;
+        assert!(matches!(
+            results[0].action,
+            MigrationAction::DataMigrated { .. }
+        ));
+        let results = Migrations::init(&test_db, &[migration!(PricedOrder)]).await?;
+        assert!(matches!(results[0].action, MigrationAction::SchemaMatched));
+        assert!(Migrations::verify(&test_db, &[migration!(PricedOrder)])
+            .await?
+            .is_empty());
+
+        let row = test_db
+            .query_one(
+                "SELECT format_type(atttypid, atttypmod) FROM pg_attribute \
+                 WHERE attrelid = 'priced_orders_099'::regclass AND attname = 'price'",
+                &[],
+            )
+            .await?;
+        assert_eq!(row.get::<_, String>(0), "numeric(20,8)");
+
+        #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+        #[orso_table("mistyped_orders_099")]
+        struct MistypedOrder {
+            #[orso_column(primary_key)]
+            id: Option<String>,
+            #[orso_column(type = "TEXT")]
+            price: f64,
+        }
+
+        let error = Migrations::init(&test_db, &[migration!(MistypedOrder)])
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            &error,
+            crate::Error::Schema { column: Some(column), .. } if column == "price"
+        ));
+        assert!(!crate::migrations::check_table_exists(&test_db, "mistyped_orders_099").await?);
+        println!("✓ column type overrides");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("indexed_members_100")]
+    #[orso_index(columns("last_name", "first_name"))]
+    #[orso_index(expr = "lower(email)", where = "NOT archived", unique)]
+    struct IndexedMember {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        first_name: String,
+        last_name: String,
+        email: String,
+        archived: bool,
+    }
+
+    #[tokio::test]
+    async fn test_declared_indexes() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::migrations::Drift;
+
+        let test_db = TestDb::new(get_test_db_config(), &[migration!(IndexedMember)]).await?;
+        let table = crate::Schema::describe(&test_db, "indexed_members_100").await?;
+        let names = table
+            .index("indexed_members_100_last_name_first_name_idx")
+            .expect("column index");
+        assert_eq!(names.columns, ["last_name", "first_name"]);
+        assert!(!names.unique);
+        let email = table
+            .index("indexed_members_100_lower_email_key")
+            .expect("expression index");
+        assert!(email.unique);
+        assert!(email.definition.contains("lower(email)"));
+        assert!(email.definition.contains("WHERE (NOT archived)"));
+
+        let member = |email: &str, archived: bool| IndexedMember {
+            id: None,
+            first_name: "Ada".into(),
+            last_name: "Lovelace".into(),
+            email: email.into(),
+            archived,
+        };
+        member("ada@example.com", true).insert(&*test_db).await?;
+        member("Ada@Example.com", false).insert(&*test_db).await?;
+        assert!(member("ADA@example.com", false)
+            .insert(&*test_db)
+            .await
+            .is_err());
+
+        let migrations = [migration!(IndexedMember)];
+        assert!(Migrations::verify(&test_db, &migrations).await?.is_empty());
+        test_db
+            .execute("DROP INDEX indexed_members_100_lower_email_key", &[])
+            .await?;
+        assert_eq!(
+            Migrations::verify(&test_db, &migrations).await?,
+            [Drift::MissingIndex {
+                table: "indexed_members_100".into(),
+                index: "indexed_members_100_lower_email_key".into(),
+            }]
+        );
+        Migrations::init(&test_db, &migrations).await?;
+        assert!(Migrations::verify(&test_db, &migrations).await?.is_empty());
+
+        // An index under the declared name but no longer unique or partial is rebuilt
+        test_db
+            .execute("DROP INDEX indexed_members_100_lower_email_key", &[])
+            .await?;
+        test_db
+            .execute(
+                "CREATE INDEX indexed_members_100_lower_email_key \
+                 ON indexed_members_100 (lower(email))",
+                &[],
+            )
+            .await?;
+        let drift = Migrations::verify(&test_db, &migrations).await?;
+        assert!(matches!(
+            drift.as_slice(),
+            [Drift::IndexDefinitionMismatch { index, actual, .. }]
+                if index == "indexed_members_100_lower_email_key" && !actual.contains("WHERE")
+        ));
+        Migrations::init(&test_db, &migrations).await?;
+        assert!(Migrations::verify(&test_db, &migrations).await?.is_empty());
+        let table = crate::Schema::describe(&test_db, "indexed_members_100").await?;
+        let email = table.index("indexed_members_100_lower_email_key").unwrap();
+        assert!(email.unique);
+        assert!(email.definition.contains("WHERE (NOT archived)"));
+        println!("✓ declared indexes");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("tagged_events_101")]
+    #[orso_index(columns("tags"), using = "gin")]
+    #[orso_index(columns("created_at"), using = "BRIN")]
+    struct TaggedEvent {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        name: String,
+        tags: Vec<i64>,
+        #[orso_column(created_at)]
+        created_at: Option<OrsoDateTime>,
+    }
+
+    #[tokio::test]
+    async fn test_index_methods() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::migrations::Drift;
+
+        let migrations = [migration!(TaggedEvent)];
+        let test_db = TestDb::new(get_test_db_config(), &migrations).await?;
+        let table = crate::Schema::describe(&test_db, "tagged_events_101").await?;
+        let tags = table
+            .index("tagged_events_101_tags_idx")
+            .expect("GIN index");
+        assert_eq!(tags.method, "gin");
+        assert!(tags.definition.contains("USING gin (tags)"));
+        let created = table
+            .index("tagged_events_101_created_at_idx")
+            .expect("BRIN index");
+        assert_eq!(created.method, "brin");
+        assert_eq!(
+            table.index("tagged_events_101_pkey").unwrap().method,
+            "btree"
+        );
+
+        test_db
+            .execute("DROP INDEX tagged_events_101_created_at_idx", &[])
+            .await?;
+        test_db
+            .execute(
+                "CREATE INDEX tagged_events_101_created_at_idx ON tagged_events_101 (created_at)",
+                &[],
+            )
+            .await?;
+        assert_eq!(
+            Migrations::verify(&test_db, &migrations).await?,
+            [Drift::IndexMethodMismatch {
+                table: "tagged_events_101".into(),
+                index: "tagged_events_101_created_at_idx".into(),
+                expected: "brin".into(),
+                actual: "btree".into(),
+            }]
+        );
+        Migrations::init(&test_db, &migrations).await?;
+        assert!(Migrations::verify(&test_db, &migrations).await?.is_empty());
+        let table = crate::Schema::describe(&test_db, "tagged_events_101").await?;
+        assert_eq!(
+            table
+                .index("tagged_events_101_created_at_idx")
+                .unwrap()
+                .method,
+            "brin"
+        );
+        println!("✓ index methods");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("vouchers_102")]
+    #[orso_index(columns("code"), unique, concurrently)]
+    #[non_transactional]
+    struct Voucher {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        code: String,
+    }
+
+    #[tokio::test]
+    async fn test_concurrent_indexes() -> Result<(), Box<dyn std::error::Error>> {
+        let migrations = [migration!(Voucher)];
+        let test_db = TestDb::new(get_test_db_config(), &migrations).await?;
+        let index_valid = || async {
+            let rows = test_db
+                .query(
+                    "SELECT ix.indisvalid FROM pg_index ix \
+                     JOIN pg_class i ON i.oid = ix.indexrelid \
+                     WHERE i.relname = 'vouchers_102_code_key'",
+                    &[],
+                )
+                .await?;
+            Ok::<_, crate::Error>(rows.first().map(|row| row.get::<_, bool>(0)))
+        };
+        assert_eq!(index_valid().await?, Some(true));
+
+        // A failed concurrent build is cleaned up instead of left invalid
+        test_db
+            .execute("DROP INDEX vouchers_102_code_key", &[])
+            .await?;
+        test_db
+            .execute("INSERT INTO vouchers_102 (code) VALUES ('A'), ('A')", &[])
+            .await?;
+        assert!(Migrations::init(&test_db, &migrations).await.is_err());
+        assert_eq!(index_valid().await?, None);
+
+        // An invalid index left by an earlier build is rebuilt
+        let build = "CREATE UNIQUE INDEX CONCURRENTLY vouchers_102_code_key ON vouchers_102 (code)";
+        assert!(test_db.execute(build, &[]).await.is_err());
+        assert_eq!(index_valid().await?, Some(false));
+        let dedupe = "DELETE FROM vouchers_102 WHERE ctid <> (SELECT min(ctid) FROM vouchers_102)";
+        test_db.execute(dedupe, &[]).await?;
+        Migrations::init(&test_db, &migrations).await?;
+        assert_eq!(index_valid().await?, Some(true));
+        assert!(Migrations::verify(&test_db, &migrations).await?.is_empty());
+        println!("✓ concurrent indexes");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("profiles_103")]
+    struct ProfileV1 {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        legacy_name: String,
+        #[orso_column(type = "VARCHAR(8)")]
+        code: Option<String>,
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("profiles_103")]
+    struct ProfileV2 {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        #[orso_column(type = "VARCHAR(32)")]
+        code: Option<String>,
+        full_name: String,
+        nickname: Option<String>,
+    }
+
+    #[tokio::test]
+    async fn test_migration_strategies() -> Result<(), Box<dyn std::error::Error>> {
+        use crate::{MigrationConfig, MigrationStrategy};
+        use orso::migrations::MigrationAction;
+
+        let test_db = TestDb::new(get_test_db_config(), &[migration!(ProfileV1)]).await?;
+        test_db
+            .execute(
+                "INSERT INTO profiles_103 (legacy_name, code) VALUES ('Ada', 'A1'), ('Bo', 'B2')",
+                &[],
+            )
+            .await?;
+        let v2 = [migration!(ProfileV2)];
+        let expand_contract =
+            MigrationConfig::default().with_strategy(MigrationStrategy::ExpandContract);
+
+        // Copying reports every change as a rewrite
+        let plan = Migrations::plan(&test_db, &v2, &MigrationConfig::default()).await?;
+        assert!(plan
+            .iter()
+            .all(|c| c.rewrites_table && c.statements.is_empty()));
+
+        let plan = Migrations::plan(&test_db, &v2, &expand_contract).await?;
+        let find = |description: &str| plan.iter().find(|c| c.description == description);
+        let widen = find("Change type of code from CHARACTER VARYING(8) to CHARACTER VARYING(32)");
+        assert!(widen.is_some_and(|c| c.is_safe() && !c.contract));
+        let required = find("Make full_name NOT NULL").expect("deferred NOT NULL");
+        assert!(required.contract && required.blocks_writes && !required.rewrites_table);
+        assert!(find("Make legacy_name nullable").is_some_and(|c| !c.contract));
+        assert!(find("Drop column legacy_name").is_some_and(|c| c.contract && c.is_safe()));
+        assert_eq!(
+            required.to_string(),
+            "profiles_103: Make full_name NOT NULL (blocks writes, contract)"
+        );
+
+        // Expanding leaves both versions working against the table
+        let results = Migrations::init_with_config(&test_db, &v2, &expand_contract).await?;
+        let MigrationAction::Expanded { pending } = &results[0].action else {
+            panic!("expected an expansion, got {}", results[0].action);
+        };
+        assert_eq!(
+            pending,
+            &["Make full_name NOT NULL", "Drop column legacy_name"]
+        );
+        assert!(results[0].backup_table.is_none());
+        test_db
+            .execute("INSERT INTO profiles_103 (code) VALUES ('C3')", &[])
+            .await?;
+        test_db
+            .execute("DELETE FROM profiles_103 WHERE code = 'C3'", &[])
+            .await?;
+
+        // The renamed column is kept in step with the old one and backfilled
+        let dual_write = Migrations::dual_write_sql("profiles_103", "legacy_name", "full_name");
+        assert!(dual_write[0].contains("NEW.\"full_name\" := NEW.\"legacy_name\""));
+        Migrations::install_dual_write(&test_db, "profiles_103", "legacy_name", "full_name")
+            .await?;
+        let filled =
+            Migrations::backfill(&test_db, "profiles_103", "legacy_name", "full_name", 1).await?;
+        assert_eq!(filled, 2);
+        test_db
+            .execute(
+                "INSERT INTO profiles_103 (legacy_name, code) VALUES ('Cy', 'C3')",
+                &[],
+            )
+            .await?;
+        test_db
+            .execute(
+                "UPDATE profiles_103 SET full_name = 'Ada L' WHERE code = 'A1'",
+                &[],
+            )
+            .await?;
+        let rows = test_db
+            .query(
+                "SELECT legacy_name, full_name FROM profiles_103 ORDER BY code",
+                &[],
+            )
+            .await?;
+        let names: Vec<(String, String)> = rows.iter().map(|r| (r.get(0), r.get(1))).collect();
+        assert_eq!(
+            names,
+            [("Ada L", "Ada L"), ("Bo", "Bo"), ("Cy", "Cy")].map(|(a, b)| (a.into(), b.into()))
+        );
+
+        Migrations::remove_dual_write(&test_db, "profiles_103", "legacy_name", "full_name").await?;
+        let results = Migrations::contract(&test_db, &v2).await?;
+        assert!(matches!(results[0].action, MigrationAction::AlteredInPlace));
+        assert!(Migrations::verify(&test_db, &v2).await?.is_empty());
+        let results = Migrations::init_with_config(&test_db, &v2, &expand_contract).await?;
+        assert!(matches!(results[0].action, MigrationAction::SchemaMatched));
+
+        // In place when every change is safe, copying otherwise
+        let in_place = MigrationConfig::default().with_strategy(MigrationStrategy::InPlace);
+        test_db
+            .execute("ALTER TABLE profiles_103 DROP COLUMN nickname", &[])
+            .await?;
+        let results = Migrations::init_with_config(&test_db, &v2, &in_place).await?;
+        assert!(matches!(results[0].action, MigrationAction::AlteredInPlace));
+        test_db
+            .execute(
+                "ALTER TABLE profiles_103 ALTER COLUMN full_name DROP NOT NULL",
+                &[],
+            )
+            .await?;
+        let plan = Migrations::plan(&test_db, &v2, &in_place).await?;
+        assert!(plan.iter().all(|c| c.rewrites_table));
+        let results = Migrations::init_with_config(&test_db, &v2, &in_place).await?;
+        assert!(matches!(
+            results[0].action,
+            MigrationAction::DataMigrated { .. }
+        ));
+        assert_eq!(results[0].rows_migrated, Some(3));
+        assert!(Migrations::verify(&test_db, &v2).await?.is_empty());
+        println!("✓ migration strategies");
+
+        Ok(())
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("ledgers_104")]
+    struct Ledger {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        amount: i32,
+    }
+
+    #[derive(Orso, Serialize, Deserialize, Clone, Debug, Default)]
+    #[orso_table("ledgers_104")]
+    #[non_transactional]
+    struct UnguardedLedger {
+        #[orso_column(primary_key)]
+        id: Option<String>,
+        amount: i32,
+    }
+
+    #[tokio::test]
+    async fn test_transactional_migrations() -> Result<(), Box<dyn std::error::Error>> {
+        let test_db = TestDb::new(get_test_db_config(), &[]).await?;
+        test_db
+            .execute(
+                "CREATE TABLE ledgers_104 (id TEXT PRIMARY KEY DEFAULT gen_random_uuid(), \
+                 amount TEXT NOT NULL)",
+                &[],
+            )
+            .await?;
+        test_db
+            .execute(
+                "INSERT INTO ledgers_104 (amount) VALUES ('12'), ('n/a')",
+                &[],
+            )
+            .await?;
+        let leftovers = || async {
+            let rows = test_db
+                .query(
+                    "SELECT table_name::text FROM information_schema.tables \
+                     WHERE table_schema = current_schema() AND table_name LIKE 'ledgers_104_%'",
+                    &[],
+                )
+                .await?;
+            Ok::<_, crate::Error>(rows.iter().map(|r| r.get(0)).collect::<Vec<String>>())
+        };
+        let amount_type = || async {
+            let row = test_db
+                .query_one(
+                    "SELECT data_type::text FROM information_schema.columns \
+                     WHERE table_schema = current_schema() AND table_name = 'ledgers_104' \
+                     AND column_name = 'amount'",
+                    &[],
+                )
+                .await?;
+            Ok::<_, crate::Error>(row.get::<_, String>(0))
+        };
+
+        // The failed copy is rolled back with everything else the migration did
+        assert!(Migrations::init(&test_db, &[migration!(Ledger)])
+            .await
+            .is_err());
+        assert!(leftovers().await?.is_empty());
+        assert_eq!(amount_type().await?, "text");
+
+        // Outside a transaction the copy is undone step by step
+        assert!(Migrations::init(&test_db, &[migration!(UnguardedLedger)])
+            .await
+            .is_err());
+        assert!(leftovers().await?.is_empty());
+        assert_eq!(amount_type().await?, "text");
+        let rows = test_db
+            .query("SELECT count(*) FROM ledgers_104", &[])
+            .await?;
+        assert_eq!(rows[0].get::<_, i64>(0), 2);
+
+        test_db
+            .execute("DELETE FROM ledgers_104 WHERE amount = 'n/a'", &[])
+            .await?;
+        let results = Migrations::init(&test_db, &[migration!(Ledger)]).await?;
+        assert_eq!(results[0].rows_migrated, Some(1));
+        assert_eq!(amount_type().await?, "integer");
+        println!("✓ transactional migrations");
+
+        Ok(())
+    }
 }
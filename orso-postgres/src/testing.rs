@@ -0,0 +1,146 @@
+//! Test database harness
+//!
+//! Enabled with the `testing` feature. [`TestDb`] creates a uniquely named schema, points every
+//! pooled connection at it through `search_path` and runs the given migrations, so tests don't
+//! share tables and need no manual cleanup. The schema is dropped when the `TestDb` is dropped:
+//!
+//! ```rust,ignore
+//! let db = TestDb::new(DatabaseConfig::new(url), &[migration!(User)]).await?;
+//! User::find_all(&db).await?;
+//! ```
+//!
+//! Dropping is best-effort and only logs a failure; [`TestDb::cleanup`] drops the schema
+//! through the pool and returns the error.
+
+use crate::{
+    Database, DatabaseConfig, Error, Executor, MigrationTrait, Migrations, Result, RowMap,
+};
+use std::ops::Deref;
+use tracing::{debug, warn};
+
+/// A database whose tables live in a throwaway schema
+#[derive(Debug)]
+pub struct TestDb {
+    db: Database,
+    schema: String,
+    /// Settings the schema is dropped with, so TLS, credentials and hosts apply to it too
+    config: DatabaseConfig,
+    dropped: bool,
+}
+
+impl TestDb {
+    /// Create a fresh schema and run `migrations` in it
+    pub async fn new(
+        config: DatabaseConfig,
+        migrations: &[Box<dyn MigrationTrait>],
+    ) -> Result<Self> {
+        let schema = format!("orso_test_{}", uuid::Uuid::new_v4().simple());
+
+        let bootstrap = Database::init(config.clone().with_pool_size(1)).await?;
+        bootstrap
+            .execute(&format!("CREATE SCHEMA \"{schema}\""), &[])
+            .await?;
+        debug!(schema = %schema, "Created test schema");
+
+        let mut on_connect_sql = config.on_connect_sql.clone();
+        on_connect_sql.push(format!("SET search_path TO \"{schema}\""));
+        let db = Database::init(config.clone().with_on_connect_sql(on_connect_sql)).await?;
+
+        let test_db = Self {
+            db,
+            schema,
+            config,
+            dropped: false,
+        };
+        Migrations::init(&test_db.db, migrations).await?;
+
+        Ok(test_db)
+    }
+
+    pub fn schema(&self) -> &str {
+        &self.schema
+    }
+
+    /// Drop the schema now instead of when the `TestDb` goes out of scope
+    pub async fn cleanup(mut self) -> Result<()> {
+        self.dropped = true;
+        self.db
+            .execute(
+                &format!("DROP SCHEMA IF EXISTS \"{}\" CASCADE", self.schema),
+                &[],
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+impl Deref for TestDb {
+    type Target = Database;
+
+    fn deref(&self) -> &Database {
+        &self.db
+    }
+}
+
+impl Executor for TestDb {
+    async fn execute(
+        &self,
+        sql: &str,
+        params: &[&(dyn tokio_postgres::types::ToSql + Send + Sync)],
+    ) -> Result<u64> {
+        self.db.execute(sql, params).await
+    }
+
+    async fn query_rows(
+        &self,
+        sql: &str,
+        params: &[&(dyn tokio_postgres::types::ToSql + Send + Sync)],
+    ) -> Result<Vec<RowMap>> {
+        Executor::query_rows(&self.db, sql, params).await
+    }
+
+    fn identity_map(&self) -> Option<&crate::session::IdentityMap> {
+        Executor::identity_map(&self.db)
+    }
+
+    fn query_log(&self) -> Option<&crate::advisor::QueryLog> {
+        self.db.query_log()
+    }
+
+    fn strict_decoding(&self) -> bool {
+        self.db.strict_decoding()
+    }
+
+    fn keyring(&self) -> Option<&std::sync::Arc<crate::encryption::Keyring>> {
+        self.db.keyring()
+    }
+}
+
+impl Drop for TestDb {
+    fn drop(&mut self) {
+        if self.dropped {
+            return;
+        }
+
+        // Drop can't await, and may run inside a runtime, so clean up on a separate thread
+        let sql = format!("DROP SCHEMA IF EXISTS \"{}\" CASCADE", self.schema);
+        let connector = crate::credentials::Connector::new(&self.config);
+        let pg_config = self.config.pg_config();
+        let result = std::thread::spawn(move || -> Result<()> {
+            let runtime = tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .map_err(|e| Error::connection(format!("Failed to start runtime: {e}")))?;
+            runtime.block_on(async {
+                let connection = connector.open(pg_config?).await?;
+                connection.client.batch_execute(&sql).await?;
+                Ok(())
+            })
+        })
+        .join();
+
+        if !matches!(result, Ok(Ok(()))) {
+            warn!(schema = %self.schema, "Failed to drop test schema");
+        }
+    }
+}
@@ -0,0 +1,215 @@
+//! TLS for database connections
+//!
+//! Connections are unencrypted unless the config carries a connector. Any
+//! [`MakeTls`] implementation works, such as `postgres-native-tls` or
+//! `tokio-postgres-rustls`:
+//!
+//! ```rust,ignore
+//! let connector = native_tls::TlsConnector::new()?;
+//! let config = DatabaseConfig::new("host=db.example.com user=app dbname=app sslmode=require")
+//!     .with_tls(postgres_native_tls::MakeTlsConnector::new(connector));
+//! ```
+//!
+//! Every connection of the pool is opened with it, as are the `LISTEN` connection of
+//! [`crate::Cache::listen`] and the replication connection of a change feed, which
+//! tokio-postgres can't open itself.
+
+use futures_util::future::BoxFuture;
+use futures_util::StreamExt;
+use std::any::TypeId;
+use std::sync::Arc;
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio::net::TcpStream;
+use tokio::sync::mpsc;
+use tokio::task::JoinHandle;
+use tokio_postgres::tls::{MakeTlsConnect, TlsConnect};
+use tokio_postgres::{AsyncMessage, Client, NoTls, Notification, Socket};
+use tracing::{debug, warn};
+
+/// An open connection, driven by a spawned task
+pub(crate) struct Connection {
+    pub(crate) client: Client,
+    pub(crate) handle: JoinHandle<()>,
+    /// Notifications of the channels the connection listens to
+    pub(crate) notifications: mpsc::UnboundedReceiver<Notification>,
+}
+
+/// A byte stream to a server, encrypted or not
+#[cfg(feature = "cdc")]
+pub(crate) trait Io: AsyncRead + AsyncWrite + Unpin + Send {}
+
+#[cfg(feature = "cdc")]
+impl<S: AsyncRead + AsyncWrite + Unpin + Send> Io for S {}
+
+type ConnectResult = std::result::Result<Connection, tokio_postgres::Error>;
+type ConnectFn = dyn Fn(tokio_postgres::Config) -> BoxFuture<'static, ConnectResult> + Send + Sync;
+#[cfg(feature = "cdc")]
+type WrapFn =
+    dyn Fn(String, TcpStream) -> BoxFuture<'static, std::io::Result<Box<dyn Io>>> + Send + Sync;
+
+/// A TLS connector for pooled connections as well as raw sockets
+///
+/// Implemented for every `MakeTlsConnect` that works on any stream, which includes
+/// `postgres-native-tls`, `tokio-postgres-rustls` and [`NoTls`].
+pub trait MakeTls: Clone + Send + Sync + 'static {
+    #[doc(hidden)]
+    fn into_tls(self) -> Tls;
+}
+
+impl<T> MakeTls for T
+where
+    T: MakeTlsConnect<Socket> + MakeTlsConnect<TcpStream> + Clone + Send + Sync + 'static,
+    <T as MakeTlsConnect<Socket>>::Stream: Send + 'static,
+    <T as MakeTlsConnect<Socket>>::TlsConnect: Send,
+    <<T as MakeTlsConnect<Socket>>::TlsConnect as TlsConnect<Socket>>::Future: Send,
+    <T as MakeTlsConnect<TcpStream>>::Stream: Send + 'static,
+    <T as MakeTlsConnect<TcpStream>>::TlsConnect: Send,
+    <<T as MakeTlsConnect<TcpStream>>::TlsConnect as TlsConnect<TcpStream>>::Future: Send,
+{
+    fn into_tls(self) -> Tls {
+        let tls = self.clone();
+        let connect: Arc<ConnectFn> = Arc::new(move |pg_config| {
+            let tls = tls.clone();
+            Box::pin(async move {
+                let (client, connection) = pg_config.connect(tls).await?;
+                Ok(spawn(client, connection))
+            })
+        });
+        #[cfg(feature = "cdc")]
+        let wrap: Arc<WrapFn> = Arc::new(move |host, stream| {
+            let mut tls = self.clone();
+            Box::pin(async move {
+                let connector = MakeTlsConnect::<TcpStream>::make_tls_connect(&mut tls, &host)
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.into()))?;
+                let stream = connector
+                    .connect(stream)
+                    .await
+                    .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e.into()))?;
+                Ok(Box::new(stream) as Box<dyn Io>)
+            })
+        });
+        Tls {
+            connect,
+            #[cfg(feature = "cdc")]
+            wrap,
+            encrypts: TypeId::of::<T>() != TypeId::of::<NoTls>(),
+        }
+    }
+}
+
+/// Drive `connection`, handing its notifications to the returned receiver
+fn spawn<S, T>(client: Client, mut connection: tokio_postgres::Connection<S, T>) -> Connection
+where
+    S: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    T: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+{
+    let (sender, notifications) = mpsc::unbounded_channel();
+    let handle = tokio::spawn(async move {
+        let mut messages = futures_util::stream::poll_fn(move |cx| connection.poll_message(cx));
+        while let Some(message) = messages.next().await {
+            match message {
+                // Nobody listens on pooled connections, which drop the receiver
+                Ok(AsyncMessage::Notification(notification)) => {
+                    let _ = sender.send(notification);
+                }
+                Ok(AsyncMessage::Notice(notice)) => {
+                    debug!(notice = %notice.message(), "Database notice");
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    warn!(error = %e, "Database connection closed with an error");
+                    break;
+                }
+            }
+        }
+    });
+    Connection {
+        client,
+        handle,
+        notifications,
+    }
+}
+
+/// Shared handle to a TLS connector, compared by identity
+#[derive(Clone)]
+pub struct Tls {
+    connect: Arc<ConnectFn>,
+    /// Starts TLS on a raw socket, for replication connections
+    #[cfg(feature = "cdc")]
+    wrap: Arc<WrapFn>,
+    /// Whether the connector can encrypt at all, unlike [`NoTls`]
+    encrypts: bool,
+}
+
+impl Tls {
+    pub fn new(tls: impl MakeTls) -> Self {
+        tls.into_tls()
+    }
+
+    /// Connect without TLS
+    pub fn none() -> Self {
+        Self::new(NoTls)
+    }
+
+    pub(crate) async fn connect(&self, pg_config: tokio_postgres::Config) -> ConnectResult {
+        (self.connect)(pg_config).await
+    }
+
+    /// Ask the server behind `stream` for TLS as `mode` says and start it if it agrees
+    #[cfg(feature = "cdc")]
+    pub(crate) async fn negotiate(
+        &self,
+        host: &str,
+        mut stream: TcpStream,
+        mode: tokio_postgres::config::SslMode,
+    ) -> crate::Result<Box<dyn Io>> {
+        use crate::Error;
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        use tokio_postgres::config::SslMode;
+
+        let required = mode == SslMode::Require;
+        if mode == SslMode::Disable || (!self.encrypts && !required) {
+            return Ok(Box::new(stream));
+        }
+        if !self.encrypts {
+            return Err(Error::connection(
+                "sslmode=require needs a TLS connector, see DatabaseConfig::with_tls",
+            ));
+        }
+
+        let mut request = bytes::BytesMut::new();
+        postgres_protocol::message::frontend::ssl_request(&mut request);
+        stream.write_all(&request).await?;
+        if stream.read_u8().await? != b'S' {
+            if required {
+                return Err(Error::connection("Server does not support TLS"));
+            }
+            return Ok(Box::new(stream));
+        }
+        (self.wrap)(host.to_string(), stream)
+            .await
+            .map_err(|e| Error::connection_with_source("TLS handshake failed", Box::new(e)))
+    }
+}
+
+impl Default for Tls {
+    fn default() -> Self {
+        Self::none()
+    }
+}
+
+impl std::fmt::Debug for Tls {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tls")
+            .field("encrypts", &self.encrypts)
+            .finish_non_exhaustive()
+    }
+}
+
+impl PartialEq for Tls {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.connect, &other.connect)
+    }
+}
+
+impl Eq for Tls {}
@@ -1,4 +1,6 @@
-use crate::{Database, FilterOperator, OrsoDateTime, Result};
+use crate::{
+    Database, Executor, FilterOperator, OrsoDateTime, Result, UpsertStrategy, WriteResult,
+};
 use serde::{de::DeserializeOwned, Serialize};
 use std::collections::HashMap;
 
@@ -17,6 +19,47 @@ pub enum FieldType {
     NumericArray,  // DOUBLE PRECISION[]
     // Vector types for pgvector extension
     Vector(u32),   // vector(N) - for embeddings/ML vectors
+    // #[orso_column(custom)] fields, with the OrsoType's SQL type
+    Custom(&'static str),
+}
+
+/// A column type set with `#[orso_column(type = "...")]`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TypeOverride {
+    pub column: &'static str,
+    /// The type as written, e.g. `NUMERIC(20,8)`
+    pub sql_type: &'static str,
+    /// `std::any::type_name` of the field
+    pub rust_type: &'static str,
+}
+
+/// Lifecycle hooks for models deriving `Orso` with `#[orso_hooks]`
+///
+/// `before_*` hooks run before the statement is sent and can modify the record (e.g. hash a
+/// password); returning an error aborts the operation. `after_*` hooks run once the statement
+/// succeeded. Changes made by `before_insert`/`before_update` are written to the database but
+/// not reflected in the caller's value. `batch_upsert` can't tell an insert from an update
+/// before the statement runs and `batch_delete` only has keys, so both bypass the hooks.
+#[allow(async_fn_in_trait)]
+pub trait OrsoHooks {
+    async fn before_insert(&mut self, _db: &impl Executor) -> Result<()> {
+        Ok(())
+    }
+    async fn after_insert(&self, _db: &impl Executor) -> Result<()> {
+        Ok(())
+    }
+    async fn before_update(&mut self, _db: &impl Executor) -> Result<()> {
+        Ok(())
+    }
+    async fn after_update(&self, _db: &impl Executor) -> Result<()> {
+        Ok(())
+    }
+    async fn before_delete(&self, _db: &impl Executor) -> Result<()> {
+        Ok(())
+    }
+    async fn after_delete(&self, _db: &impl Executor) -> Result<()> {
+        Ok(())
+    }
 }
 
 #[allow(async_fn_in_trait)]
@@ -31,9 +74,88 @@ pub trait Orso: Serialize + DeserializeOwned + Send + Sync + Clone {
     fn updated_at_field() -> Option<&'static str> {
         None
     }
+    /// Whether a database trigger maintains `updated_at` (`#[orso_column(updated_at, trigger)]`)
+    fn updated_at_trigger() -> bool {
+        false
+    }
+    /// Timestamp columns stored as Unix milliseconds (`#[orso_column(created_at, as = "unix_ms")]`)
+    fn unix_ms_fields() -> Vec<&'static str> {
+        vec![]
+    }
+    /// Whether migrations index `(created_at, primary key)` (`#[orso_column(created_at, seek)]`)
+    fn seek_index() -> bool {
+        false
+    }
+    fn created_by_field() -> Option<&'static str> {
+        None
+    }
+    fn updated_by_field() -> Option<&'static str> {
+        None
+    }
     fn unique_fields() -> Vec<&'static str> {
         vec![]
     }
+    /// The `unique_fields` compared case-insensitively (`#[orso_column(unique(case_insensitive))]`)
+    fn case_insensitive_unique_fields() -> Vec<&'static str> {
+        vec![]
+    }
+    /// Unique and foreign key columns checked at commit (`#[orso_column(.., deferrable)]`),
+    /// with whether they start out deferred (`initially_deferred`)
+    fn deferrable_fields() -> Vec<(&'static str, bool)> {
+        vec![]
+    }
+    fn lazy_fields() -> Vec<&'static str> {
+        vec![]
+    }
+    /// Fields stored encrypted in `BYTEA` columns (`#[orso_column(encrypted)]`)
+    fn encrypted_fields() -> Vec<&'static str> {
+        vec![]
+    }
+    /// Fields read redacted unless a [`ReadContext`](crate::ReadContext) reveals them
+    /// (`#[orso_column(masked)]`)
+    fn masked_fields() -> Vec<&'static str> {
+        vec![]
+    }
+    /// Column referencing the model's own table, which makes it a tree, see [`crate::tree`]
+    fn parent_field() -> Option<&'static str> {
+        None
+    }
+    /// Filter added to every finder (`#[orso_default_scope]`), see [`crate::scope`]
+    fn default_scope() -> Option<FilterOperator> {
+        None
+    }
+    /// Name of the registered database the model lives in (`#[orso_table(.., connection = ..)]`)
+    fn connection() -> Option<&'static str> {
+        None
+    }
+    /// Field a [`ShardRouter`](crate::ShardRouter) routes records by (`#[orso_shard_key(field)]`)
+    fn shard_key_field() -> Option<&'static str> {
+        None
+    }
+    /// Comment on the table (`#[orso_table(.., comment = "...")]`)
+    fn table_comment() -> Option<&'static str> {
+        None
+    }
+    /// Comments on columns (`#[orso_column(comment = "...")]`)
+    fn column_comments() -> Vec<(&'static str, &'static str)> {
+        vec![]
+    }
+    /// Columns whose type is set with `#[orso_column(type = "...")]`, checked by migrations
+    fn type_overrides() -> Vec<TypeOverride> {
+        vec![]
+    }
+    /// Indexes declared with `#[orso_index(...)]`, created by migrations
+    fn indexes() -> Vec<crate::IndexDef> {
+        vec![]
+    }
+    /// Whether migrations run in a transaction, unless marked `#[non_transactional]`
+    fn transactional() -> bool {
+        true
+    }
+    /// Whether changes are recorded in a `<table>_audit` table (`#[orso_audited]`)
+    fn audited() -> bool {
+        false
+    }
     fn has_auto_id() -> bool {
         true
     }
@@ -45,6 +167,14 @@ pub trait Orso: Serialize + DeserializeOwned + Send + Sync + Clone {
     fn field_types() -> Vec<FieldType>;
     fn field_nullable() -> Vec<bool>;
     fn field_compressed() -> Vec<bool>;
+    /// Lossy precision for compressed float fields, `None` for lossless compression
+    fn field_compression_precision() -> Vec<Option<f64>> {
+        vec![]
+    }
+    /// Element type of compressed `Vec` fields, `None` for other fields
+    fn field_compressed_elements() -> Vec<Option<FieldType>> {
+        vec![]
+    }
     fn columns() -> Vec<&'static str>;
 
     fn get_primary_key(&self) -> Option<String>;
@@ -55,187 +185,429 @@ pub trait Orso: Serialize + DeserializeOwned + Send + Sync + Clone {
 
     fn migration_sql() -> String;
 
+    /// Check the `#[orso_column(validate(...))]` rules, run before every insert and update
+    fn validate(&self) -> Result<()> {
+        Ok(())
+    }
+
     fn to_map(&self) -> Result<HashMap<String, crate::Value>>;
     fn from_map(map: HashMap<String, crate::Value>) -> Result<Self>;
+    /// Decrypt, decode `custom` and mask the columns present in `map`, as `from_map` does
+    /// before handing them to serde
+    fn decode_columns(_map: &mut HashMap<String, crate::Value>) -> Result<()> {
+        Ok(())
+    }
 
-    async fn insert(&self, db: &Database) -> Result<()> {
+    // Lifecycle hooks, run by CrudOperations around single writes, batch inserts and updates.
+    // Models opt in with #[orso_hooks] and implement OrsoHooks.
+    async fn before_insert(&mut self, _db: &impl Executor) -> Result<()> {
+        Ok(())
+    }
+    async fn after_insert(&self, _db: &impl Executor) -> Result<()> {
+        Ok(())
+    }
+    async fn before_update(&mut self, _db: &impl Executor) -> Result<()> {
+        Ok(())
+    }
+    async fn after_update(&self, _db: &impl Executor) -> Result<()> {
+        Ok(())
+    }
+    async fn before_delete(&self, _db: &impl Executor) -> Result<()> {
+        Ok(())
+    }
+    async fn after_delete(&self, _db: &impl Executor) -> Result<()> {
+        Ok(())
+    }
+
+    async fn insert(&self, db: &impl Executor) -> Result<WriteResult> {
         crate::operations::CrudOperations::insert(self, db).await
     }
-    async fn insert_with_table(&self, db: &Database, table_name: &str) -> Result<()> {
+    async fn insert_with_table(&self, db: &impl Executor, table_name: &str) -> Result<WriteResult> {
         crate::operations::CrudOperations::insert_with_table(self, db, table_name).await
     }
+    /// Insert with `ctx` as the actor for `created_by`/`updated_by` columns
+    async fn insert_with_context(
+        &self,
+        db: &impl Executor,
+        ctx: &crate::OperationContext,
+    ) -> Result<WriteResult> {
+        ctx.clone().scope(self.insert(db)).await
+    }
 
-    async fn find_by_id(id: &str, db: &Database) -> Result<Option<Self>> {
+    async fn find_by_id(id: &str, db: &impl Executor) -> Result<Option<Self>> {
         crate::operations::CrudOperations::find_by_id::<Self>(id, db).await
     }
 
     async fn find_by_id_with_table(
         id: &str,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<Option<Self>> {
         crate::operations::CrudOperations::find_by_id_with_table::<Self>(id, db, table_name).await
     }
 
-    async fn find_all(db: &Database) -> Result<Vec<Self>> {
+    async fn find_all(db: &impl Executor) -> Result<Vec<Self>> {
         crate::operations::CrudOperations::find_all::<Self>(db).await
     }
 
-    async fn find_all_with_table(db: &Database, table_name: &str) -> Result<Vec<Self>> {
+    async fn find_all_with_table(db: &impl Executor, table_name: &str) -> Result<Vec<Self>> {
         crate::operations::CrudOperations::find_all_with_table::<Self>(db, table_name).await
     }
 
-    async fn find_where(filter: FilterOperator, db: &Database) -> Result<Vec<Self>> {
+    async fn find_where(filter: FilterOperator, db: &impl Executor) -> Result<Vec<Self>> {
         crate::operations::CrudOperations::find_where::<Self>(filter, db).await
     }
 
     async fn find_where_with_table(
         filter: FilterOperator,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<Vec<Self>> {
         crate::operations::CrudOperations::find_where_with_table::<Self>(filter, db, table_name)
             .await
     }
 
-    async fn update(&self, db: &Database) -> Result<()> {
+    /// Records below `id` in a self-referential model, nearest first
+    async fn descendants(id: &str, db: &impl Executor) -> Result<Vec<Self>> {
+        crate::tree::descendants::<Self>(id, &crate::TreeQuery::new(), db).await
+    }
+
+    async fn descendants_with(
+        id: &str,
+        query: &crate::TreeQuery,
+        db: &impl Executor,
+    ) -> Result<Vec<Self>> {
+        crate::tree::descendants::<Self>(id, query, db).await
+    }
+
+    /// Records above `id` in a self-referential model, parent first
+    async fn ancestors(id: &str, db: &impl Executor) -> Result<Vec<Self>> {
+        crate::tree::ancestors::<Self>(id, &crate::TreeQuery::new(), db).await
+    }
+
+    async fn ancestors_with(
+        id: &str,
+        query: &crate::TreeQuery,
+        db: &impl Executor,
+    ) -> Result<Vec<Self>> {
+        crate::tree::ancestors::<Self>(id, query, db).await
+    }
+
+    /// Records matching `filter` on every shard of `router`, queried concurrently
+    async fn find_where_all_shards(
+        filter: FilterOperator,
+        router: &crate::ShardRouter,
+    ) -> Result<Vec<Self>> {
+        router.find_where_all_shards::<Self>(filter).await
+    }
+
+    /// Stream the records matching `filter` to `writer` as CSV, see [`crate::exchange`]
+    async fn export_csv(
+        filter: FilterOperator,
+        writer: impl std::io::Write,
+        db: &Database,
+    ) -> Result<()> {
+        crate::exchange::export_csv::<Self>(filter, writer, db).await
+    }
+
+    /// Stream the records matching `filter` as JSON lines in primary key order
+    fn export_ndjson<'a>(
+        filter: FilterOperator,
+        db: &'a impl Executor,
+    ) -> impl futures_util::Stream<Item = Result<String>> + 'a
+    where
+        Self: 'a,
+    {
+        crate::exchange::export_ndjson::<Self>(filter, None, db)
+    }
+
+    /// Resume a JSON lines export after the primary key of the last record received
+    fn export_ndjson_after<'a>(
+        filter: FilterOperator,
+        after: Option<String>,
+        db: &'a impl Executor,
+    ) -> impl futures_util::Stream<Item = Result<String>> + 'a
+    where
+        Self: 'a,
+    {
+        crate::exchange::export_ndjson::<Self>(filter, after, db)
+    }
+
+    /// Insert the records of a CSV file through `COPY`, returning how many were imported
+    async fn import_csv(reader: impl std::io::Read, db: &Database) -> Result<u64> {
+        crate::exchange::import_csv::<Self>(reader, db).await
+    }
+
+    /// Replace `self` with the stored row, picking up defaults, triggers and concurrent writes
+    async fn reload(&mut self, db: &impl Executor) -> Result<()> {
+        crate::operations::CrudOperations::reload(self, db).await
+    }
+
+    async fn reload_with_table(&mut self, db: &impl Executor, table_name: &str) -> Result<()> {
+        crate::operations::CrudOperations::reload_with_table(self, db, table_name).await
+    }
+
+    async fn update(&self, db: &impl Executor) -> Result<WriteResult> {
         crate::operations::CrudOperations::update(self, db).await
     }
 
-    async fn update_with_table(&self, db: &Database, table_name: &str) -> Result<()> {
+    async fn update_with_table(&self, db: &impl Executor, table_name: &str) -> Result<WriteResult> {
         crate::operations::CrudOperations::update_with_table(self, db, table_name).await
     }
 
-    async fn delete(&self, db: &Database) -> Result<bool> {
+    /// Update with `ctx` as the actor for the `updated_by` column
+    async fn update_with_context(
+        &self,
+        db: &impl Executor,
+        ctx: &crate::OperationContext,
+    ) -> Result<WriteResult> {
+        ctx.clone().scope(self.update(db)).await
+    }
+
+    /// Bump `updated_at` without rewriting the rest of the row
+    async fn touch(&mut self, db: &impl Executor) -> Result<()> {
+        crate::operations::CrudOperations::touch(self, db).await
+    }
+
+    async fn touch_with_table(&mut self, db: &impl Executor, table_name: &str) -> Result<()> {
+        crate::operations::CrudOperations::touch_with_table(self, db, table_name).await
+    }
+
+    /// Bump `updated_at` on every record matching `filter`, returning how many were touched
+    async fn touch_where(filter: FilterOperator, db: &impl Executor) -> Result<u64> {
+        crate::operations::CrudOperations::touch_where::<Self>(filter, db).await
+    }
+
+    async fn touch_where_with_table(
+        filter: FilterOperator,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<u64> {
+        crate::operations::CrudOperations::touch_where_with_table::<Self>(filter, db, table_name)
+            .await
+    }
+
+    async fn delete(&self, db: &impl Executor) -> Result<WriteResult> {
         crate::operations::CrudOperations::delete(self, db).await
     }
 
-    async fn delete_with_table(&self, db: &Database, table_name: &str) -> Result<bool> {
+    async fn delete_with_table(&self, db: &impl Executor, table_name: &str) -> Result<WriteResult> {
         crate::operations::CrudOperations::delete_with_table(self, db, table_name).await
     }
 
-    async fn delete_cascade(&self, db: &Database) -> Result<bool> {
+    async fn delete_cascade(&self, db: &impl Executor) -> Result<WriteResult> {
         crate::operations::CrudOperations::delete_cascade(self, db).await
     }
 
-    async fn delete_cascade_with_table(&self, db: &Database, table_name: &str) -> Result<bool> {
+    async fn delete_cascade_with_table(
+        &self,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<WriteResult> {
         crate::operations::CrudOperations::delete_cascade_with_table(self, db, table_name).await
     }
 
-    async fn count(db: &Database) -> Result<u64> {
+    /// Delete every record without running hooks, returning how many were deleted
+    async fn delete_all(
+        confirm: crate::operations::ConfirmDeleteAll,
+        db: &impl Executor,
+    ) -> Result<u64> {
+        crate::operations::CrudOperations::delete_all::<Self>(confirm, db).await
+    }
+
+    async fn delete_all_with_table(
+        confirm: crate::operations::ConfirmDeleteAll,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<u64> {
+        crate::operations::CrudOperations::delete_all_with_table::<Self>(confirm, db, table_name)
+            .await
+    }
+
+    /// Empty the table with `TRUNCATE`
+    async fn truncate(
+        confirm: crate::operations::ConfirmTruncate,
+        db: &impl Executor,
+    ) -> Result<()> {
+        crate::operations::CrudOperations::truncate::<Self>(confirm, db).await
+    }
+
+    async fn truncate_with_table(
+        confirm: crate::operations::ConfirmTruncate,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<()> {
+        crate::operations::CrudOperations::truncate_with_table::<Self>(confirm, db, table_name)
+            .await
+    }
+
+    async fn count(db: &impl Executor) -> Result<u64> {
         crate::operations::CrudOperations::count::<Self>(db).await
     }
 
-    async fn count_with_table(db: &Database, table_name: &str) -> Result<u64> {
+    async fn count_with_table(db: &impl Executor, table_name: &str) -> Result<u64> {
         crate::operations::CrudOperations::count_with_table::<Self>(db, table_name).await
     }
 
     // Advanced CRUD operations
-    async fn insert_or_update(&self, db: &Database) -> Result<()> {
+    async fn insert_or_update(&self, db: &impl Executor) -> Result<WriteResult> {
         crate::operations::CrudOperations::insert_or_update(self, db).await
     }
 
-    async fn insert_or_update_with_table(&self, db: &Database, table_name: &str) -> Result<()> {
+    async fn insert_or_update_with_table(
+        &self,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<WriteResult> {
         crate::operations::CrudOperations::insert_or_update_with_table(self, db, table_name).await
     }
 
-    async fn upsert(&self, db: &Database) -> Result<()> {
+    async fn upsert(&self, db: &impl Executor) -> Result<WriteResult> {
         crate::operations::CrudOperations::upsert(self, db).await
     }
 
-    async fn upsert_with_table(&self, db: &Database, table_name: &str) -> Result<()> {
+    async fn upsert_with_table(&self, db: &impl Executor, table_name: &str) -> Result<WriteResult> {
         crate::operations::CrudOperations::upsert_with_table(self, db, table_name).await
     }
 
     // Batch operations (Turso-optimized with execute_batch)
-    async fn batch_create(models: &[Self], db: &Database) -> Result<()> {
+    async fn batch_create(models: &[Self], db: &impl Executor) -> Result<WriteResult> {
         crate::operations::CrudOperations::batch_create(models, db).await
     }
 
     async fn batch_insert_with_table(
         models: &[Self],
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
-    ) -> Result<()> {
+    ) -> Result<WriteResult> {
         crate::operations::CrudOperations::batch_insert_with_table(models, db, table_name).await
     }
 
-    async fn batch_update(models: &[Self], db: &Database) -> Result<()> {
+    async fn batch_create_ignore_conflicts(
+        models: &[Self],
+        db: &impl Executor,
+    ) -> Result<WriteResult> {
+        crate::operations::CrudOperations::batch_create_ignore_conflicts(models, db).await
+    }
+
+    async fn batch_insert_ignore_conflicts_with_table(
+        models: &[Self],
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<WriteResult> {
+        crate::operations::CrudOperations::batch_insert_ignore_conflicts_with_table(
+            models, db, table_name,
+        )
+        .await
+    }
+
+    async fn batch_update(models: &[Self], db: &impl Executor) -> Result<WriteResult> {
         crate::operations::CrudOperations::batch_update(models, db).await
     }
 
     async fn batch_update_with_table(
         models: &[Self],
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
-    ) -> Result<()> {
+    ) -> Result<WriteResult> {
         crate::operations::CrudOperations::batch_update_with_table(models, db, table_name).await
     }
 
-    async fn batch_delete(ids: &[&str], db: &Database) -> Result<u64> {
+    async fn batch_delete(ids: &[&str], db: &impl Executor) -> Result<WriteResult> {
         crate::operations::CrudOperations::batch_delete::<Self>(ids, db).await
     }
 
-    async fn batch_delete_with_table(ids: &[&str], db: &Database, table_name: &str) -> Result<u64> {
+    async fn batch_delete_with_table(
+        ids: &[&str],
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<WriteResult> {
         crate::operations::CrudOperations::batch_delete_with_table::<Self>(ids, db, table_name)
             .await
     }
 
-    async fn batch_delete_cascade(ids: &[&str], db: &Database) -> Result<u64> {
+    async fn batch_delete_cascade(ids: &[&str], db: &impl Executor) -> Result<WriteResult> {
         crate::operations::CrudOperations::batch_delete_cascade::<Self>(ids, db).await
     }
 
-    async fn batch_delete_cascade_with_table(ids: &[&str], db: &Database, table_name: &str) -> Result<u64> {
+    async fn batch_delete_cascade_with_table(
+        ids: &[&str],
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<WriteResult> {
         crate::operations::CrudOperations::batch_delete_cascade_with_table::<Self>(ids, db, table_name)
             .await
     }
 
-    async fn batch_upsert(models: &[Self], db: &Database) -> Result<()> {
+    async fn batch_upsert(models: &[Self], db: &impl Executor) -> Result<WriteResult> {
         crate::operations::CrudOperations::batch_upsert(models, db).await
     }
 
     async fn batch_upsert_with_table(
         models: &[Self],
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
-    ) -> Result<()> {
+    ) -> Result<WriteResult> {
         crate::operations::CrudOperations::batch_upsert_with_table(models, db, table_name).await
     }
 
+    async fn batch_upsert_with(
+        models: &[Self],
+        strategy: &UpsertStrategy,
+        db: &impl Executor,
+    ) -> Result<WriteResult> {
+        crate::operations::CrudOperations::batch_upsert_with(models, strategy, db).await
+    }
+
+    async fn batch_upsert_with_strategy_with_table(
+        models: &[Self],
+        strategy: &UpsertStrategy,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<WriteResult> {
+        crate::operations::CrudOperations::batch_upsert_with_strategy_with_table(
+            models, strategy, db, table_name,
+        )
+        .await
+    }
+
     // Find operations
-    async fn find_one(filter: FilterOperator, db: &Database) -> Result<Option<Self>> {
+    async fn find_one(filter: FilterOperator, db: &impl Executor) -> Result<Option<Self>> {
         crate::operations::CrudOperations::find_one::<Self>(filter, db).await
     }
 
     async fn find_one_with_table(
         filter: FilterOperator,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<Option<Self>> {
         crate::operations::CrudOperations::find_one_with_table::<Self>(filter, db, table_name).await
     }
 
-    async fn find_latest<T>(db: &Database) -> Result<Option<T>>
+    async fn find_latest<T>(db: &impl Executor) -> Result<Option<T>>
     where
         T: crate::Orso,
     {
         Self::find_latest_with_table(db, T::table_name()).await
     }
 
-    async fn find_latest_with_table<T>(db: &Database, table_name: &str) -> Result<Option<T>>
+    async fn find_latest_with_table<T>(db: &impl Executor, table_name: &str) -> Result<Option<T>>
     where
         T: crate::Orso,
     {
         crate::operations::CrudOperations::find_latest_with_table::<T>(db, table_name).await
     }
 
-    async fn find_latest_filter(filter: FilterOperator, db: &Database) -> Result<Option<Self>> {
+    async fn find_latest_filter(
+        filter: FilterOperator,
+        db: &impl Executor,
+    ) -> Result<Option<Self>> {
         crate::operations::CrudOperations::find_latest_filter::<Self>(filter, db).await
     }
 
     async fn find_latest_filter_with_table(
         filter: FilterOperator,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<Option<Self>> {
         crate::operations::CrudOperations::find_latest_filter_with_table::<Self>(
@@ -244,13 +616,90 @@ pub trait Orso: Serialize + DeserializeOwned + Send + Sync + Clone {
         .await
     }
 
-    async fn find_first_filter(filter: FilterOperator, db: &Database) -> Result<Option<Self>> {
+    async fn first(sort: Option<crate::Sort>, db: &impl Executor) -> Result<Option<Self>> {
+        crate::operations::CrudOperations::first::<Self>(sort, db).await
+    }
+
+    async fn first_with_table(
+        sort: Option<crate::Sort>,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<Option<Self>> {
+        crate::operations::CrudOperations::first_with_table::<Self>(sort, db, table_name).await
+    }
+
+    async fn last(sort: Option<crate::Sort>, db: &impl Executor) -> Result<Option<Self>> {
+        crate::operations::CrudOperations::last::<Self>(sort, db).await
+    }
+
+    async fn last_with_table(
+        sort: Option<crate::Sort>,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<Option<Self>> {
+        crate::operations::CrudOperations::last_with_table::<Self>(sort, db, table_name).await
+    }
+
+    async fn find_one_where(filter: FilterOperator, db: &impl Executor) -> Result<Option<Self>> {
+        crate::operations::CrudOperations::find_one_where::<Self>(filter, db).await
+    }
+
+    async fn find_one_where_with_table(
+        filter: FilterOperator,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<Option<Self>> {
+        crate::operations::CrudOperations::find_one_where_with_table::<Self>(filter, db, table_name)
+            .await
+    }
+
+    async fn pluck<V: DeserializeOwned>(
+        column: &str,
+        filter: Option<FilterOperator>,
+        db: &impl Executor,
+    ) -> Result<Vec<V>> {
+        crate::operations::CrudOperations::pluck::<Self, V>(column, filter, db).await
+    }
+
+    async fn pluck_with_table<V: DeserializeOwned>(
+        column: &str,
+        filter: Option<FilterOperator>,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<Vec<V>> {
+        crate::operations::CrudOperations::pluck_with_table::<Self, V>(
+            column, filter, db, table_name,
+        )
+        .await
+    }
+
+    async fn group_count(
+        column: &str,
+        filter: Option<FilterOperator>,
+        db: &impl Executor,
+    ) -> Result<HashMap<String, i64>> {
+        crate::operations::CrudOperations::group_count::<Self>(column, filter, db).await
+    }
+
+    async fn group_count_with_table(
+        column: &str,
+        filter: Option<FilterOperator>,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<HashMap<String, i64>> {
+        crate::operations::CrudOperations::group_count_with_table::<Self>(
+            column, filter, db, table_name,
+        )
+        .await
+    }
+
+    async fn find_first_filter(filter: FilterOperator, db: &impl Executor) -> Result<Option<Self>> {
         crate::operations::CrudOperations::find_first_filter::<Self>(filter, db).await
     }
 
     async fn find_first_filter_with_table(
         filter: FilterOperator,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<Option<Self>> {
         crate::operations::CrudOperations::find_first_filter_with_table::<Self>(
@@ -259,35 +708,39 @@ pub trait Orso: Serialize + DeserializeOwned + Send + Sync + Clone {
         .await
     }
 
-    async fn exists(db: &Database) -> Result<bool> {
+    async fn exists(db: &impl Executor) -> Result<bool> {
         crate::operations::CrudOperations::exists::<Self>(db).await
     }
 
-    async fn exists_with_table(db: &Database, table_name: &str) -> Result<bool> {
+    async fn exists_with_table(db: &impl Executor, table_name: &str) -> Result<bool> {
         crate::operations::CrudOperations::exists_with_table::<Self>(db, table_name).await
     }
 
-    async fn exists_filter(filter: FilterOperator, db: &Database) -> Result<bool> {
+    async fn exists_filter(filter: FilterOperator, db: &impl Executor) -> Result<bool> {
         crate::operations::CrudOperations::exists_filter::<Self>(filter, db).await
     }
 
     async fn exists_filter_with_table(
         filter: FilterOperator,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<bool> {
         crate::operations::CrudOperations::exists_filter_with_table::<Self>(filter, db, table_name)
             .await
     }
 
-    async fn find_by_field(field: &str, value: crate::Value, db: &Database) -> Result<Vec<Self>> {
+    async fn find_by_field(
+        field: &str,
+        value: crate::Value,
+        db: &impl Executor,
+    ) -> Result<Vec<Self>> {
         crate::operations::CrudOperations::find_by_field::<Self>(field, value, db).await
     }
 
     async fn find_by_field_with_table(
         field: &str,
         value: crate::Value,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<Vec<Self>> {
         crate::operations::CrudOperations::find_by_field_with_table::<Self>(
@@ -299,7 +752,7 @@ pub trait Orso: Serialize + DeserializeOwned + Send + Sync + Clone {
     async fn find_latest_by_field(
         field: &str,
         value: crate::Value,
-        db: &Database,
+        db: &impl Executor,
     ) -> Result<Option<Self>> {
         crate::operations::CrudOperations::find_latest_by_field::<Self>(field, value, db).await
     }
@@ -307,7 +760,7 @@ pub trait Orso: Serialize + DeserializeOwned + Send + Sync + Clone {
     async fn find_latest_by_field_with_table(
         field: &str,
         value: crate::Value,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<Option<Self>> {
         crate::operations::CrudOperations::find_latest_by_field_with_table::<Self>(
@@ -319,7 +772,7 @@ pub trait Orso: Serialize + DeserializeOwned + Send + Sync + Clone {
     async fn find_first_by_field(
         field: &str,
         value: crate::Value,
-        db: &Database,
+        db: &impl Executor,
     ) -> Result<Option<Self>> {
         crate::operations::CrudOperations::find_first_by_field::<Self>(field, value, db).await
     }
@@ -327,7 +780,7 @@ pub trait Orso: Serialize + DeserializeOwned + Send + Sync + Clone {
     async fn find_first_by_field_with_table(
         field: &str,
         value: crate::Value,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<Option<Self>> {
         crate::operations::CrudOperations::find_first_by_field_with_table::<Self>(
@@ -336,22 +789,30 @@ pub trait Orso: Serialize + DeserializeOwned + Send + Sync + Clone {
         .await
     }
 
-    async fn find_by_ids(ids: &[&str], db: &Database) -> Result<Vec<Self>> {
-        crate::operations::CrudOperations::find_by_ids::<Self>(ids, db).await
+    /// Records with the given IDs in the same order, `None` where no record matched
+    async fn find_by_ids<K>(ids: &[K], db: &impl Executor) -> Result<Vec<Option<Self>>>
+    where
+        K: tokio_postgres::types::ToSql + ToString + Sync,
+    {
+        crate::operations::CrudOperations::find_by_ids::<Self, K>(ids, db).await
     }
 
-    async fn find_by_ids_with_table(
-        ids: &[&str],
-        db: &Database,
+    async fn find_by_ids_with_table<K>(
+        ids: &[K],
+        db: &impl Executor,
         table_name: &str,
-    ) -> Result<Vec<Self>> {
-        crate::operations::CrudOperations::find_by_ids_with_table::<Self>(ids, db, table_name).await
+    ) -> Result<Vec<Option<Self>>>
+    where
+        K: tokio_postgres::types::ToSql + ToString + Sync,
+    {
+        crate::operations::CrudOperations::find_by_ids_with_table::<Self, K>(ids, db, table_name)
+            .await
     }
 
     async fn find_by_field_in(
         field: &str,
         values: &[crate::Value],
-        db: &Database,
+        db: &impl Executor,
     ) -> Result<Vec<Self>> {
         crate::operations::CrudOperations::find_by_field_in::<Self>(field, values, db).await
     }
@@ -359,7 +820,7 @@ pub trait Orso: Serialize + DeserializeOwned + Send + Sync + Clone {
     async fn find_by_field_in_with_table(
         field: &str,
         values: &[crate::Value],
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<Vec<Self>> {
         crate::operations::CrudOperations::find_by_field_in_with_table::<Self>(
@@ -370,14 +831,14 @@ pub trait Orso: Serialize + DeserializeOwned + Send + Sync + Clone {
 
     async fn find_paginated(
         pagination: &crate::Pagination,
-        db: &Database,
+        db: &impl Executor,
     ) -> Result<crate::PaginatedResult<Self>> {
         crate::operations::CrudOperations::find_paginated::<Self>(pagination, db).await
     }
 
     async fn find_paginated_with_table(
         pagination: &crate::Pagination,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<crate::PaginatedResult<Self>> {
         crate::operations::CrudOperations::find_paginated_with_table::<Self>(
@@ -389,7 +850,7 @@ pub trait Orso: Serialize + DeserializeOwned + Send + Sync + Clone {
     async fn find_where_paginated(
         filter: FilterOperator,
         pagination: &crate::Pagination,
-        db: &Database,
+        db: &impl Executor,
     ) -> Result<crate::PaginatedResult<Self>> {
         crate::operations::CrudOperations::find_where_paginated::<Self>(filter, pagination, db)
             .await
@@ -398,7 +859,7 @@ pub trait Orso: Serialize + DeserializeOwned + Send + Sync + Clone {
     async fn find_where_paginated_with_table(
         filter: FilterOperator,
         pagination: &crate::Pagination,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<crate::PaginatedResult<Self>> {
         crate::operations::CrudOperations::find_where_paginated_with_table::<Self>(
@@ -407,11 +868,43 @@ pub trait Orso: Serialize + DeserializeOwned + Send + Sync + Clone {
         .await
     }
 
+    async fn page_after(
+        timestamp: impl Into<crate::Value> + Send,
+        id: impl Into<crate::Value> + Send,
+        limit: u32,
+        db: &impl Executor,
+    ) -> Result<Vec<Self>> {
+        crate::operations::CrudOperations::page_after::<Self>(timestamp, id, limit, db).await
+    }
+
+    async fn find_where_cursor(
+        filter: FilterOperator,
+        sorts: Vec<crate::Sort>,
+        pagination: &crate::CursorPagination,
+        db: &impl Executor,
+    ) -> Result<crate::CursorPaginatedResult<Self>> {
+        crate::operations::CrudOperations::find_where_cursor::<Self>(filter, sorts, pagination, db)
+            .await
+    }
+
+    async fn find_where_cursor_with_table(
+        filter: FilterOperator,
+        sorts: Vec<crate::Sort>,
+        pagination: &crate::CursorPagination,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<crate::CursorPaginatedResult<Self>> {
+        crate::operations::CrudOperations::find_where_cursor_with_table::<Self>(
+            filter, sorts, pagination, db, table_name,
+        )
+        .await
+    }
+
     // Search operations
     async fn search(
         search_filter: &crate::SearchFilter,
         pagination: Option<&crate::Pagination>,
-        db: &Database,
+        db: &impl Executor,
     ) -> Result<crate::PaginatedResult<Self>> {
         crate::operations::CrudOperations::search::<Self>(search_filter, pagination, db).await
     }
@@ -419,7 +912,7 @@ pub trait Orso: Serialize + DeserializeOwned + Send + Sync + Clone {
     async fn search_with_table(
         search_filter: &crate::SearchFilter,
         pagination: Option<&crate::Pagination>,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<crate::PaginatedResult<Self>> {
         crate::operations::CrudOperations::search_with_table::<Self>(
@@ -432,13 +925,13 @@ pub trait Orso: Serialize + DeserializeOwned + Send + Sync + Clone {
     }
 
     // Count operations
-    async fn count_where(filter: FilterOperator, db: &Database) -> Result<u64> {
+    async fn count_where(filter: FilterOperator, db: &impl Executor) -> Result<u64> {
         crate::operations::CrudOperations::count_where::<Self>(filter, db).await
     }
 
     async fn count_where_with_table(
         filter: FilterOperator,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<u64> {
         crate::operations::CrudOperations::count_where_with_table::<Self>(filter, db, table_name)
@@ -446,24 +939,43 @@ pub trait Orso: Serialize + DeserializeOwned + Send + Sync + Clone {
     }
 
     // Delete operations
-    async fn delete_where(filter: FilterOperator, db: &Database) -> Result<u64> {
+    async fn delete_where(filter: FilterOperator, db: &impl Executor) -> Result<u64> {
         crate::operations::CrudOperations::delete_where::<Self>(filter, db).await
     }
 
     async fn delete_where_with_table(
         filter: FilterOperator,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<u64> {
         crate::operations::CrudOperations::delete_where_with_table::<Self>(filter, db, table_name)
             .await
     }
 
+    /// Delete the records matching `filter`, returning the primary keys of the deleted rows
+    async fn delete_where_returning(
+        filter: FilterOperator,
+        db: &impl Executor,
+    ) -> Result<Vec<String>> {
+        crate::operations::CrudOperations::delete_where_returning::<Self>(filter, db).await
+    }
+
+    async fn delete_where_returning_with_table(
+        filter: FilterOperator,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<Vec<String>> {
+        crate::operations::CrudOperations::delete_where_returning_with_table::<Self>(
+            filter, db, table_name,
+        )
+        .await
+    }
+
     // List operations with sorting
     async fn list(
         sort: Option<Vec<crate::Sort>>,
         pagination: Option<&crate::Pagination>,
-        db: &Database,
+        db: &impl Executor,
     ) -> Result<crate::PaginatedResult<Self>> {
         crate::operations::CrudOperations::list::<Self>(sort, pagination, db).await
     }
@@ -471,7 +983,7 @@ pub trait Orso: Serialize + DeserializeOwned + Send + Sync + Clone {
     async fn list_with_table(
         sort: Option<Vec<crate::Sort>>,
         pagination: Option<&crate::Pagination>,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<crate::PaginatedResult<Self>> {
         crate::operations::CrudOperations::list_with_table::<Self>(sort, pagination, db, table_name)
@@ -482,7 +994,7 @@ pub trait Orso: Serialize + DeserializeOwned + Send + Sync + Clone {
         filter: FilterOperator,
         sort: Option<Vec<crate::Sort>>,
         pagination: Option<&crate::Pagination>,
-        db: &Database,
+        db: &impl Executor,
     ) -> Result<crate::PaginatedResult<Self>> {
         crate::operations::CrudOperations::list_where::<Self>(filter, sort, pagination, db).await
     }
@@ -491,7 +1003,7 @@ pub trait Orso: Serialize + DeserializeOwned + Send + Sync + Clone {
         filter: FilterOperator,
         sort: Option<Vec<crate::Sort>>,
         pagination: Option<&crate::Pagination>,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<crate::PaginatedResult<Self>> {
         crate::operations::CrudOperations::list_where_with_table::<Self>(
@@ -501,18 +1013,21 @@ pub trait Orso: Serialize + DeserializeOwned + Send + Sync + Clone {
     }
 
     // Custom query operations
-    async fn query(builder: crate::QueryBuilder, db: &Database) -> Result<Vec<Self>> {
+    async fn query(builder: crate::QueryBuilder, db: &impl Executor) -> Result<Vec<Self>> {
         crate::operations::CrudOperations::query::<Self>(builder, db).await
     }
 
-    async fn query_with_table(builder: crate::QueryBuilder, db: &Database) -> Result<Vec<Self>> {
+    async fn query_with_table(
+        builder: crate::QueryBuilder,
+        db: &impl Executor,
+    ) -> Result<Vec<Self>> {
         crate::operations::CrudOperations::query_with_table::<Self>(builder, db).await
     }
 
     async fn query_paginated(
         builder: crate::QueryBuilder,
         pagination: &crate::Pagination,
-        db: &Database,
+        db: &impl Executor,
     ) -> Result<crate::PaginatedResult<Self>> {
         crate::operations::CrudOperations::query_paginated::<Self>(builder, pagination, db).await
     }
@@ -520,7 +1035,7 @@ pub trait Orso: Serialize + DeserializeOwned + Send + Sync + Clone {
     async fn query_paginated_with_table(
         builder: crate::QueryBuilder,
         pagination: &crate::Pagination,
-        db: &Database,
+        db: &impl Executor,
     ) -> Result<crate::PaginatedResult<Self>> {
         crate::operations::CrudOperations::query_paginated_with_table::<Self>(
             builder, pagination, db,
@@ -533,7 +1048,7 @@ pub trait Orso: Serialize + DeserializeOwned + Send + Sync + Clone {
         function: crate::Aggregate,
         column: &str,
         filter: Option<FilterOperator>,
-        db: &Database,
+        db: &impl Executor,
     ) -> Result<Option<f64>> {
         crate::operations::CrudOperations::aggregate::<Self>(function, column, filter, db).await
     }
@@ -542,7 +1057,7 @@ pub trait Orso: Serialize + DeserializeOwned + Send + Sync + Clone {
         function: crate::Aggregate,
         column: &str,
         filter: Option<FilterOperator>,
-        db: &Database,
+        db: &impl Executor,
         table_name: &str,
     ) -> Result<Option<f64>> {
         crate::operations::CrudOperations::aggregate_with_table::<Self>(
@@ -551,8 +1066,66 @@ pub trait Orso: Serialize + DeserializeOwned + Send + Sync + Clone {
         .await
     }
 
+    // Compressed column operations
+    async fn append_compressed<V>(
+        column: &str,
+        values: &[V],
+        id: &str,
+        db: &impl Executor,
+    ) -> Result<()>
+    where
+        V: crate::compression::CompressedElement,
+    {
+        crate::compression::Compression::append_compressed::<Self, V>(column, values, id, db).await
+    }
+
+    async fn append_compressed_with_table<V>(
+        column: &str,
+        values: &[V],
+        id: &str,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<()>
+    where
+        V: crate::compression::CompressedElement,
+    {
+        crate::compression::Compression::append_compressed_with_table::<Self, V>(
+            column, values, id, db, table_name,
+        )
+        .await
+    }
+
+    async fn read_compressed_range<V>(
+        id: &str,
+        column: &str,
+        range: std::ops::Range<usize>,
+        db: &impl Executor,
+    ) -> Result<Vec<V>>
+    where
+        V: crate::compression::CompressedElement,
+    {
+        crate::compression::Compression::read_compressed_range::<Self, V>(id, column, range, db)
+            .await
+    }
+
+    async fn read_compressed_range_with_table<V>(
+        id: &str,
+        column: &str,
+        range: std::ops::Range<usize>,
+        db: &impl Executor,
+        table_name: &str,
+    ) -> Result<Vec<V>>
+    where
+        V: crate::compression::CompressedElement,
+    {
+        crate::compression::Compression::read_compressed_range_with_table::<Self, V>(
+            id, column, range, db, table_name,
+        )
+        .await
+    }
+
     // Legacy batch operations (for compatibility)
-    async fn batch_insert(records: &[Self], db: &Database) -> Result<u64> {
+    async fn batch_insert(records: &[Self], db: &impl Executor) -> Result<u64> {
         Self::batch_create(records, db).await?;
         Ok(records.len() as u64)
     }
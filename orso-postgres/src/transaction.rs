@@ -0,0 +1,241 @@
+//! Transactions with explicit options
+//!
+//! [`Database::transaction_with`] runs a closure in a transaction started with the given
+//! [`TxOptions`], commits when it returns `Ok` and rolls back when it returns an error:
+//!
+//! ```rust,ignore
+//! let report = db
+//!     .transaction_with(
+//!         TxOptions {
+//!             isolation: IsolationLevel::Serializable,
+//!             read_only: true,
+//!             deferrable: true,
+//!         },
+//!         |tx| Box::pin(async move {
+//!             let orders = Order::count(tx).await?;
+//!             let refunds = Refund::count(tx).await?;
+//!             Ok((orders, refunds))
+//!         }),
+//!     )
+//!     .await?;
+//! ```
+//!
+//! The closure's future may borrow the transaction but nothing else, so move owned data in.
+//! It gets a [`Transaction`], which encrypts, decrypts and decodes columns with the settings of
+//! the database and derefs to the underlying `deadpool_postgres::Transaction`.
+//!
+//! A serializable, read-only and deferrable transaction waits for a snapshot that can't
+//! conflict with concurrent writes, so long reports never fail with a serialization error.
+//!
+//! [`Database::serializable`] runs read-write serializable transactions, retrying the closure
+//! with backoff when PostgreSQL aborts it with a serialization failure. The closure can run
+//! several times, so it must not have effects outside the transaction:
+//!
+//! ```rust,ignore
+//! db.serializable(|tx| Box::pin(async move {
+//!     let booked = Booking::count_where(slot_filter(), tx).await?;
+//!     if booked >= CAPACITY {
+//!         return Err(Error::validation("Slot is full"));
+//!     }
+//!     Booking::new(slot).insert(tx).await
+//! }))
+//! .await?;
+//! ```
+
+use crate::database::{with_statement_context, writes_rows};
+use crate::executor::sync_params;
+use crate::{Database, Executor, Result, RowMap};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+use tokio_postgres::types::ToSql;
+use tracing::Instrument;
+
+/// Isolation level a transaction runs at
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum IsolationLevel {
+    /// PostgreSQL runs it as `ReadCommitted`
+    ReadUncommitted,
+    #[default]
+    ReadCommitted,
+    RepeatableRead,
+    Serializable,
+}
+
+impl From<IsolationLevel> for tokio_postgres::IsolationLevel {
+    fn from(level: IsolationLevel) -> Self {
+        match level {
+            IsolationLevel::ReadUncommitted => Self::ReadUncommitted,
+            IsolationLevel::ReadCommitted => Self::ReadCommitted,
+            IsolationLevel::RepeatableRead => Self::RepeatableRead,
+            IsolationLevel::Serializable => Self::Serializable,
+        }
+    }
+}
+
+/// How [`Database::transaction_with`](crate::Database::transaction_with) starts a transaction
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct TxOptions {
+    pub isolation: IsolationLevel,
+    /// Reject writes with `READ ONLY`
+    pub read_only: bool,
+    /// Wait for a safe snapshot; only applies to serializable read-only transactions
+    pub deferrable: bool,
+}
+
+/// How often and how patiently [`Database::serializable_with`](crate::Database::serializable_with)
+/// retries
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Attempts in total, including the first
+    pub max_attempts: u32,
+    /// Wait before the first retry, doubled after each one
+    pub initial_backoff: Duration,
+    pub max_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 5,
+            initial_backoff: Duration::from_millis(10),
+            max_backoff: Duration::from_secs(1),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn with_max_attempts(mut self, attempts: u32) -> Self {
+        self.max_attempts = attempts;
+        self
+    }
+
+    pub fn with_backoff(mut self, initial: Duration, max: Duration) -> Self {
+        self.initial_backoff = initial;
+        self.max_backoff = max;
+        self
+    }
+
+    /// Wait before retry number `retry` (from 1), with jitter so contending transactions
+    /// don't retry in lockstep
+    pub(crate) fn backoff(&self, retry: u32) -> Duration {
+        let base = self
+            .initial_backoff
+            .saturating_mul(1 << retry.saturating_sub(1).min(16))
+            .min(self.max_backoff);
+        let half = base / 2;
+        let jitter = uuid::Uuid::new_v4().as_u128() % (half.as_nanos() + 1);
+        half + Duration::from_nanos(jitter as u64)
+    }
+}
+
+/// A transaction started by [`Database::transaction_with`], running statements with the
+/// settings of the database it came from
+///
+/// Its statements, commit and rollback go through the hooks, circuit breaker, metrics and
+/// query log of the database like any other.
+pub struct Transaction<'a> {
+    db: &'a Database,
+    tx: deadpool_postgres::Transaction<'a>,
+    /// Whether the actor was exposed to audit triggers, which it is before the first write
+    actor_set: AtomicBool,
+}
+
+impl<'a> Transaction<'a> {
+    pub(crate) fn new(db: &'a Database, tx: deadpool_postgres::Transaction<'a>) -> Self {
+        Self {
+            db,
+            tx,
+            actor_set: AtomicBool::new(false),
+        }
+    }
+
+    /// Expose the current actor to audit triggers if `sql` is the first write
+    async fn set_actor(&self, sql: &str) -> Result<()> {
+        if !writes_rows(sql) || self.actor_set.swap(true, Ordering::Relaxed) {
+            return Ok(());
+        }
+        crate::executor::set_actor(&self.tx).await
+    }
+
+    /// Commit or roll back, reported to the database like a statement
+    pub(crate) async fn end(self, commit: bool) -> Result<()> {
+        let sql = if commit { "COMMIT" } else { "ROLLBACK" };
+        let span = self.db.statement_span(sql, &[]);
+        let started = Instant::now();
+        let result = if commit {
+            self.tx.commit().instrument(span.clone()).await
+        } else {
+            self.tx.rollback().instrument(span.clone()).await
+        };
+        self.db
+            .observe(&span, sql, started, result.as_ref().map(|_| 0));
+        with_statement_context(result, sql, 0)
+    }
+}
+
+impl<'a> std::ops::Deref for Transaction<'a> {
+    type Target = deadpool_postgres::Transaction<'a>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.tx
+    }
+}
+
+impl std::fmt::Debug for Transaction<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Transaction").finish_non_exhaustive()
+    }
+}
+
+impl Executor for Transaction<'_> {
+    async fn execute(&self, sql: &str, params: &[&(dyn ToSql + Send + Sync)]) -> Result<u64> {
+        self.db.before_query(sql)?;
+        self.set_actor(sql).await?;
+        if let Some(log) = self.db.query_log() {
+            log.record_sql(sql);
+        }
+        let span = self.db.statement_span(sql, params);
+        let started = Instant::now();
+        let result = tokio_postgres::Transaction::execute(&self.tx, sql, &sync_params(params))
+            .instrument(span.clone())
+            .await;
+        self.db
+            .observe(&span, sql, started, result.as_ref().copied());
+        with_statement_context(result, sql, params.len())
+    }
+
+    async fn query_rows(
+        &self,
+        sql: &str,
+        params: &[&(dyn ToSql + Send + Sync)],
+    ) -> Result<Vec<RowMap>> {
+        self.db.before_query(sql)?;
+        self.set_actor(sql).await?;
+        if let Some(log) = self.db.query_log() {
+            log.record_sql(sql);
+        }
+        let span = self.db.statement_span(sql, params);
+        let started = Instant::now();
+        let result = tokio_postgres::Transaction::query(&self.tx, sql, &sync_params(params))
+            .instrument(span.clone())
+            .await;
+        let rows = result.as_ref().map(|rows| rows.len() as u64);
+        self.db.observe(&span, sql, started, rows);
+        with_statement_context(result, sql, params.len())?
+            .iter()
+            .map(crate::operations::CrudOperations::row_to_map)
+            .collect()
+    }
+
+    fn query_log(&self) -> Option<&crate::advisor::QueryLog> {
+        self.db.query_log()
+    }
+
+    fn strict_decoding(&self) -> bool {
+        self.db.strict_decoding()
+    }
+
+    fn keyring(&self) -> Option<&std::sync::Arc<crate::encryption::Keyring>> {
+        self.db.keyring()
+    }
+}
@@ -0,0 +1,130 @@
+//! Recursive queries over self-referential models
+//!
+//! A model whose column references its own table (`#[orso_column(ref = "categories")]` on
+//! `parent_id` of `categories`) is a tree. [`Orso::descendants`] and [`Orso::ancestors`] walk it
+//! with `WITH RECURSIVE`, nearest records first:
+//!
+//! ```rust,ignore
+//! let subtree = Category::descendants(&root_id, &db).await?;
+//! let breadcrumbs = Category::ancestors(&leaf_id, &db).await?; // parent, grandparent, ...
+//!
+//! let query = TreeQuery::new().with_max_depth(2).with_order(Sort::asc("name"));
+//! let children_and_grandchildren = Category::descendants_with(&root_id, &query, &db).await?;
+//! ```
+//!
+//! Cycles in the data are cut off rather than followed forever.
+
+use crate::{Error, Executor, Orso, Result, Sort};
+use tracing::instrument;
+
+/// Depth limit and ordering of a tree query
+#[derive(Debug, Clone, Default)]
+pub struct TreeQuery {
+    /// Levels to walk, `1` for direct children or the parent only
+    pub max_depth: Option<u32>,
+    /// Ordering of records at the same depth
+    pub order_by: Vec<Sort>,
+}
+
+impl TreeQuery {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_depth(mut self, max_depth: u32) -> Self {
+        self.max_depth = Some(max_depth);
+        self
+    }
+
+    pub fn with_order(mut self, sort: Sort) -> Self {
+        self.order_by.push(sort);
+        self
+    }
+}
+
+enum Direction {
+    Down,
+    Up,
+}
+
+/// Records below `id`, ordered by depth
+#[instrument(name = "orso.descendants", skip_all, fields(table = T::table_name()))]
+pub async fn descendants<T: Orso>(
+    id: &str,
+    query: &TreeQuery,
+    db: &impl Executor,
+) -> Result<Vec<T>> {
+    walk::<T>(id, query, Direction::Down, db).await
+}
+
+/// Records above `id`, parent first
+#[instrument(name = "orso.ancestors", skip_all, fields(table = T::table_name()))]
+pub async fn ancestors<T: Orso>(id: &str, query: &TreeQuery, db: &impl Executor) -> Result<Vec<T>> {
+    walk::<T>(id, query, Direction::Up, db).await
+}
+
+async fn walk<T: Orso>(
+    id: &str,
+    query: &TreeQuery,
+    direction: Direction,
+    db: &impl Executor,
+) -> Result<Vec<T>> {
+    let table = T::table_name();
+    let parent = T::parent_field().ok_or_else(|| {
+        Error::operation(
+            format!("{table} has no column referencing its own table"),
+            "tree",
+            Some(table.to_string()),
+        )
+    })?;
+    let pk = T::primary_key_field();
+
+    // The anchor is the starting record itself; it is left out of the result
+    let join = match direction {
+        Direction::Down => format!("t.{parent} = tree.{pk}"),
+        Direction::Up => format!("t.{pk} = tree.{parent}"),
+    };
+    let depth_limit = query
+        .max_depth
+        .map(|depth| format!(" AND tree.__depth < {depth}"))
+        .unwrap_or_default();
+
+    let columns = T::columns();
+    let selected = crate::lazy::select_columns::<T>();
+    let selected = if selected == ["*"] {
+        &columns
+    } else {
+        &selected
+    };
+    let mut order = vec!["__depth".to_string()];
+    for sort in &query.order_by {
+        if !columns.contains(&sort.column.as_str()) {
+            return Err(Error::validation_field(
+                format!("{table} has no column to order by"),
+                sort.column.clone(),
+                None,
+            ));
+        }
+        order.push(format!("{} {}", sort.column, sort.order));
+    }
+
+    let sql = format!(
+        "WITH RECURSIVE tree AS (\
+         SELECT t.*, 0 AS __depth, ARRAY[t.{pk}::text] AS __path FROM {table} t WHERE t.{pk} = $1 \
+         UNION ALL \
+         SELECT t.*, tree.__depth + 1, tree.__path || t.{pk}::text FROM {table} t \
+         JOIN tree ON {join} WHERE NOT t.{pk}::text = ANY(tree.__path){depth_limit}\
+         ) SELECT {} FROM tree WHERE __depth > 0 ORDER BY {}",
+        selected.join(", "),
+        order.join(", "),
+    );
+
+    let key = crate::operations::key_param::<T>(id)?;
+    let rows = db.query_rows(&sql, &[key.as_ref()]).await?;
+    rows.into_iter()
+        .map(|mut map| {
+            crate::lazy::attach_sources::<T>(&mut map, table);
+            crate::decode::decode_record(db, map)
+        })
+        .collect()
+}
@@ -0,0 +1,323 @@
+//! Filters checked against the field types of a model
+//!
+//! `#[derive(Orso)]` implements [`Filterable`], whose `Model::filter()` has one method per
+//! column returning a [`TypedColumn`]. Comparisons only accept values of the field's type,
+//! `like` and `ilike` only exist on text fields, and the resulting [`TypedFilter`]s only
+//! combine with filters on the same model:
+//!
+//! ```rust,ignore
+//! use orso_postgres::Filterable;
+//!
+//! let filter = User::filter()
+//!     .age()
+//!     .gt(25)
+//!     .and(User::filter().name().ilike("%o%"));
+//! let users = User::find_where(filter.into(), &db).await?;
+//!
+//! // User::filter().age().gt("25") -> error[E0277]: the trait bound `i32: From<&str>` ...
+//! ```
+
+use crate::{Column, Filter, FilterOperator, OrsoDateTime, Value};
+use chrono::{DateTime, Utc};
+use std::fmt;
+use std::marker::PhantomData;
+
+/// Models with typed filters, implemented by `#[derive(Orso)]`
+///
+/// The filter type has no name of its own, only `<Model as Filterable>::Filter`, so it
+/// can't clash with items of the model's module.
+pub trait Filterable {
+    /// One method per column, returning its [`TypedColumn`]
+    type Filter;
+
+    /// Filters on this model whose values are checked against the field types
+    fn filter() -> Self::Filter;
+}
+
+/// A field type typed filters can compare
+pub trait FieldValue {
+    /// Type of the values the field is compared with, the inner type for `Option` fields
+    type Operand;
+
+    fn operand_value(operand: Self::Operand) -> Value;
+}
+
+macro_rules! impl_field_value {
+    ($variant:ident as $cast:ty: $($ty:ty),*) => {
+        $(impl FieldValue for $ty {
+            type Operand = Self;
+
+            fn operand_value(operand: Self) -> Value {
+                Value::$variant(operand as $cast)
+            }
+        })*
+    };
+}
+
+impl_field_value!(Integer as i64: i8, i16, i32, i64, u8, u16, u32);
+impl_field_value!(Real as f64: f32, f64);
+
+macro_rules! impl_field_value_from {
+    ($($ty:ty),*) => {
+        $(impl FieldValue for $ty {
+            type Operand = Self;
+
+            fn operand_value(operand: Self) -> Value {
+                Value::from(operand)
+            }
+        })*
+    };
+}
+
+impl_field_value_from!(
+    bool,
+    String,
+    Vec<u8>,
+    Vec<f32>,
+    DateTime<Utc>,
+    OrsoDateTime,
+    serde_json::Value
+);
+
+/// Compared like the inner type; `is_null` matches `None`
+impl<T: FieldValue> FieldValue for Option<T> {
+    type Operand = T::Operand;
+
+    fn operand_value(operand: T::Operand) -> Value {
+        T::operand_value(operand)
+    }
+}
+
+/// Field types `like` and `ilike` apply to
+#[diagnostic::on_unimplemented(message = "`{Self}` is not a text field")]
+pub trait TextField {}
+
+impl TextField for String {}
+
+impl<T: TextField> TextField for Option<T> {}
+
+/// A column of model `M` holding values of type `V`, returned by `Model::filter()`
+pub struct TypedColumn<M, V> {
+    column: Column,
+    _types: PhantomData<fn() -> (M, V)>,
+}
+
+impl<M, V> TypedColumn<M, V> {
+    pub const fn new(column: Column) -> Self {
+        Self {
+            column,
+            _types: PhantomData,
+        }
+    }
+
+    pub const fn column(&self) -> Column {
+        self.column
+    }
+
+    pub fn is_null(&self) -> TypedFilter<M> {
+        TypedFilter::single(Filter::is_null(self.column))
+    }
+
+    pub fn is_not_null(&self) -> TypedFilter<M> {
+        TypedFilter::single(Filter::is_not_null(self.column))
+    }
+}
+
+impl<M, V: FieldValue> TypedColumn<M, V> {
+    pub fn eq(&self, value: impl Into<V::Operand>) -> TypedFilter<M> {
+        TypedFilter::single(Filter::eq(self.column, Self::value(value)))
+    }
+
+    pub fn ne(&self, value: impl Into<V::Operand>) -> TypedFilter<M> {
+        TypedFilter::single(Filter::ne(self.column, Self::value(value)))
+    }
+
+    pub fn lt(&self, value: impl Into<V::Operand>) -> TypedFilter<M> {
+        TypedFilter::single(Filter::lt(self.column, Self::value(value)))
+    }
+
+    pub fn le(&self, value: impl Into<V::Operand>) -> TypedFilter<M> {
+        TypedFilter::single(Filter::le(self.column, Self::value(value)))
+    }
+
+    pub fn gt(&self, value: impl Into<V::Operand>) -> TypedFilter<M> {
+        TypedFilter::single(Filter::gt(self.column, Self::value(value)))
+    }
+
+    pub fn ge(&self, value: impl Into<V::Operand>) -> TypedFilter<M> {
+        TypedFilter::single(Filter::ge(self.column, Self::value(value)))
+    }
+
+    pub fn in_values(
+        &self,
+        values: impl IntoIterator<Item = impl Into<V::Operand>>,
+    ) -> TypedFilter<M> {
+        TypedFilter::single(Filter::in_values(self.column, Self::values(values)))
+    }
+
+    pub fn not_in_values(
+        &self,
+        values: impl IntoIterator<Item = impl Into<V::Operand>>,
+    ) -> TypedFilter<M> {
+        TypedFilter::single(Filter::not_in_values(self.column, Self::values(values)))
+    }
+
+    pub fn between(
+        &self,
+        min: impl Into<V::Operand>,
+        max: impl Into<V::Operand>,
+    ) -> TypedFilter<M> {
+        TypedFilter::single(Filter::between(
+            self.column,
+            Self::value(min),
+            Self::value(max),
+        ))
+    }
+
+    pub fn not_between(
+        &self,
+        min: impl Into<V::Operand>,
+        max: impl Into<V::Operand>,
+    ) -> TypedFilter<M> {
+        TypedFilter::single(Filter::not_between(
+            self.column,
+            Self::value(min),
+            Self::value(max),
+        ))
+    }
+
+    fn values(values: impl IntoIterator<Item = impl Into<V::Operand>>) -> Vec<Value> {
+        values.into_iter().map(Self::value).collect()
+    }
+
+    fn value(value: impl Into<V::Operand>) -> Value {
+        V::operand_value(value.into())
+    }
+}
+
+impl<M, V: TextField> TypedColumn<M, V> {
+    pub fn like(&self, pattern: impl Into<String>) -> TypedFilter<M> {
+        TypedFilter::single(Filter::like(self.column, pattern))
+    }
+
+    pub fn not_like(&self, pattern: impl Into<String>) -> TypedFilter<M> {
+        TypedFilter::single(Filter::not_like(self.column, pattern))
+    }
+
+    /// Case-insensitive `LIKE`
+    pub fn ilike(&self, pattern: impl Into<String>) -> TypedFilter<M> {
+        TypedFilter {
+            operator: Filter::raw(
+                format!("{} ILIKE $?", self.column),
+                vec![Value::Text(pattern.into())],
+            ),
+            _model: PhantomData,
+        }
+    }
+}
+
+impl<M> TypedColumn<M, bool> {
+    pub fn is_true(&self) -> TypedFilter<M> {
+        TypedFilter::single(Filter::is_true(self.column))
+    }
+
+    pub fn is_false(&self) -> TypedFilter<M> {
+        TypedFilter::single(Filter::is_false(self.column))
+    }
+}
+
+impl<M> TypedColumn<M, Option<bool>> {
+    /// Matches `Some(true)`
+    pub fn is_true(&self) -> TypedFilter<M> {
+        TypedFilter::single(Filter::is_true(self.column))
+    }
+
+    /// Matches `Some(false)`
+    pub fn is_false(&self) -> TypedFilter<M> {
+        TypedFilter::single(Filter::is_false(self.column))
+    }
+
+    /// Matches `None`
+    pub fn is_unknown(&self) -> TypedFilter<M> {
+        TypedFilter::single(Filter::is_unknown(self.column))
+    }
+}
+
+impl<M, V> Clone for TypedColumn<M, V> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<M, V> Copy for TypedColumn<M, V> {}
+
+impl<M, V> fmt::Debug for TypedColumn<M, V> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("TypedColumn").field(&self.column).finish()
+    }
+}
+
+/// A filter on model `M`, usable wherever a [`FilterOperator`] is through `.into()`
+pub struct TypedFilter<M> {
+    operator: FilterOperator,
+    _model: PhantomData<fn() -> M>,
+}
+
+impl<M> TypedFilter<M> {
+    fn single(filter: Filter) -> Self {
+        Self {
+            operator: FilterOperator::Single(filter),
+            _model: PhantomData,
+        }
+    }
+
+    pub fn and(self, other: TypedFilter<M>) -> Self {
+        Self {
+            operator: self.operator.and_with(other.operator),
+            _model: PhantomData,
+        }
+    }
+
+    pub fn or(self, other: TypedFilter<M>) -> Self {
+        Self {
+            operator: self.operator.or_with(other.operator),
+            _model: PhantomData,
+        }
+    }
+
+    pub fn into_operator(self) -> FilterOperator {
+        self.operator
+    }
+}
+
+impl<M> std::ops::Not for TypedFilter<M> {
+    type Output = Self;
+
+    fn not(self) -> Self::Output {
+        Self {
+            operator: !self.operator,
+            _model: PhantomData,
+        }
+    }
+}
+
+impl<M> From<TypedFilter<M>> for FilterOperator {
+    fn from(filter: TypedFilter<M>) -> Self {
+        filter.operator
+    }
+}
+
+impl<M> Clone for TypedFilter<M> {
+    fn clone(&self) -> Self {
+        Self {
+            operator: self.operator.clone(),
+            _model: PhantomData,
+        }
+    }
+}
+
+impl<M> fmt::Debug for TypedFilter<M> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("TypedFilter").field(&self.operator).finish()
+    }
+}
@@ -16,6 +16,7 @@ pub enum Value {
     NumericArray(Vec<f64>), // DOUBLE PRECISION[] - for f64, f32
     // Vector types for pgvector extension
     Vector(Vec<f32>),       // vector(N) - for embeddings/ML vectors
+    Json(serde_json::Value), // JSONB
 }
 
 impl From<i64> for Value {
@@ -151,7 +152,7 @@ impl From<serde_json::Value> for Value {
     }
 }
 
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize, Default)]
 pub enum SortOrder {
     #[default]
     Asc,
@@ -188,6 +189,118 @@ impl std::fmt::Display for Aggregate {
     }
 }
 
+/// Outcome of a write: the rows it affected and the primary keys of the rows written
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct WriteResult {
+    pub rows_affected: u64,
+    pub returned_ids: Vec<String>,
+}
+
+impl WriteResult {
+    /// A write of the row with primary key `id`, if it matched
+    pub(crate) fn for_id(rows_affected: u64, id: String) -> Self {
+        Self {
+            rows_affected,
+            returned_ids: if rows_affected > 0 { vec![id] } else { vec![] },
+        }
+    }
+
+    /// Whether no row matched, e.g. an update of a record deleted meanwhile
+    pub fn is_noop(&self) -> bool {
+        self.rows_affected == 0
+    }
+
+    /// The primary key of the first row written
+    pub fn id(&self) -> Option<&str> {
+        self.returned_ids.first().map(String::as_str)
+    }
+
+    pub(crate) fn add(&mut self, other: WriteResult) {
+        self.rows_affected += other.rows_affected;
+        self.returned_ids.extend(other.returned_ids);
+    }
+}
+
+/// Compatibility with the write methods that returned `()`
+pub trait WriteResultExt {
+    /// Drop the [`WriteResult`], keeping the error
+    fn discard(self) -> crate::Result<()>;
+}
+
+impl WriteResultExt for crate::Result<WriteResult> {
+    fn discard(self) -> crate::Result<()> {
+        self.map(|_| ())
+    }
+}
+
+/// How an upsert merges a column of a row that already exists
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, Default)]
+pub enum Merge {
+    /// Take the incoming value
+    #[default]
+    Overwrite,
+    /// Add the incoming value to the stored one, taking a NULL stored value as 0
+    Add,
+    /// Keep the larger of both values
+    Max,
+    /// Keep the smaller of both values
+    Min,
+    /// Leave the stored value alone
+    KeepExisting,
+}
+
+impl Merge {
+    /// The `SET` assignment for `column` of `table`, or `None` to leave it unchanged
+    pub fn assignment(&self, table: &str, column: &str) -> Option<String> {
+        match self {
+            Merge::Overwrite => Some(format!("{column} = EXCLUDED.{column}")),
+            Merge::Add => Some(format!(
+                "{column} = COALESCE({table}.{column}, 0) + EXCLUDED.{column}"
+            )),
+            Merge::Max => Some(format!(
+                "{column} = GREATEST({table}.{column}, EXCLUDED.{column})"
+            )),
+            Merge::Min => Some(format!(
+                "{column} = LEAST({table}.{column}, EXCLUDED.{column})"
+            )),
+            Merge::KeepExisting => None,
+        }
+    }
+}
+
+/// Per-column [`Merge`] rules for `batch_upsert_with`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct UpsertStrategy {
+    default: Merge,
+    columns: Vec<(String, Merge)>,
+}
+
+impl UpsertStrategy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Merge rule for columns without their own, `Overwrite` unless set
+    pub fn with_default(mut self, merge: Merge) -> Self {
+        self.default = merge;
+        self
+    }
+
+    pub fn column(mut self, column: impl Into<String>, merge: Merge) -> Self {
+        let column = column.into();
+        self.columns.retain(|(c, _)| *c != column);
+        self.columns.push((column, merge));
+        self
+    }
+
+    pub fn merge_for(&self, column: &str) -> Merge {
+        self.columns
+            .iter()
+            .find(|(c, _)| c == column)
+            .map_or(self.default, |(_, merge)| *merge)
+    }
+}
+
 #[derive(Debug, Clone, Copy, Serialize, Deserialize)]
 pub enum JoinType {
     Inner,
@@ -223,6 +336,26 @@ pub enum Operator {
     IsNotNull,
     Between,
     NotBetween,
+    /// Boolean is true; false and null don't match
+    IsTrue,
+    /// Boolean is false; true and null don't match
+    IsFalse,
+    /// Boolean is null
+    IsUnknown,
+}
+
+impl Operator {
+    /// Whether the operator takes no value, like `IS NULL`
+    pub fn is_unary(&self) -> bool {
+        matches!(
+            self,
+            Operator::IsNull
+                | Operator::IsNotNull
+                | Operator::IsTrue
+                | Operator::IsFalse
+                | Operator::IsUnknown
+        )
+    }
 }
 
 impl std::fmt::Display for Operator {
@@ -242,6 +375,9 @@ impl std::fmt::Display for Operator {
             Operator::IsNotNull => write!(f, "IS NOT NULL"),
             Operator::Between => write!(f, "BETWEEN"),
             Operator::NotBetween => write!(f, "NOT BETWEEN"),
+            Operator::IsTrue => write!(f, "IS TRUE"),
+            Operator::IsFalse => write!(f, "IS FALSE"),
+            Operator::IsUnknown => write!(f, "IS UNKNOWN"),
         }
     }
 }
@@ -249,7 +385,7 @@ impl std::fmt::Display for Operator {
 impl Value {
     pub fn to_postgres_param(&self) -> Box<dyn tokio_postgres::types::ToSql + Send + Sync> {
         match self {
-            Value::Null => Box::new(Option::<String>::None),
+            Value::Null => Box::new(SqlNull),
             Value::Integer(i) => {
                 // Check if the value fits in i32 range for PostgreSQL INTEGER columns
                 if *i >= i32::MIN as i64 && *i <= i32::MAX as i64 {
@@ -262,10 +398,7 @@ impl Value {
             }
             Value::Real(f) => Box::new(*f),
             Value::Text(s) => Box::new(s.clone()),
-            Value::DateTime(dt) => {
-                // Convert OrsoDateTime directly to SystemTime for PostgreSQL
-                Box::new(std::time::SystemTime::from(*dt.inner()))
-            }
+            Value::DateTime(dt) => Box::new(*dt),
             Value::Blob(b) => Box::new(b.clone()),
             Value::Boolean(b) => Box::new(*b),
             // Array types - pass directly to PostgreSQL
@@ -274,6 +407,42 @@ impl Value {
             Value::NumericArray(arr) => Box::new(arr.clone()),
             // Vector types - pass directly to PostgreSQL (pgvector handles Vec<f32>)
             Value::Vector(v) => Box::new(v.clone()),
+            Value::Json(v) => Box::new(v.clone()),
+        }
+    }
+
+    /// The value as a SQL literal, for statements that take no parameters such as `COPY`
+    pub(crate) fn to_sql_literal(&self) -> String {
+        fn quote(text: &str) -> String {
+            format!("E'{}'", text.replace('\\', "\\\\").replace('\'', "''"))
+        }
+        fn array<T: ToString>(items: &[T], cast: &str) -> String {
+            let items: Vec<String> = items.iter().map(T::to_string).collect();
+            format!("ARRAY[{}]::{cast}[]", items.join(", "))
+        }
+
+        match self {
+            Value::Null => "NULL".to_string(),
+            Value::Integer(i) => i.to_string(),
+            Value::Real(f) if f.is_finite() => format!("{f:?}::double precision"),
+            Value::Real(f) if f.is_nan() => "'NaN'::double precision".to_string(),
+            Value::Real(f) if *f > 0.0 => "'Infinity'::double precision".to_string(),
+            Value::Real(_) => "'-Infinity'::double precision".to_string(),
+            Value::Text(s) => quote(s),
+            Value::Blob(b) => {
+                let hex: String = b.iter().map(|byte| format!("{byte:02x}")).collect();
+                format!("E'\\\\x{hex}'::bytea")
+            }
+            Value::Boolean(b) => b.to_string().to_uppercase(),
+            Value::DateTime(dt) => format!("{}::timestamptz", quote(&dt.inner().to_rfc3339())),
+            Value::IntegerArray(arr) => array(arr, "integer"),
+            Value::BigIntArray(arr) => array(arr, "bigint"),
+            Value::NumericArray(arr) => array(arr, "double precision"),
+            Value::Vector(v) => {
+                let items: Vec<String> = v.iter().map(f32::to_string).collect();
+                format!("'[{}]'::vector", items.join(","))
+            }
+            Value::Json(v) => format!("{}::jsonb", quote(&v.to_string())),
         }
     }
 
@@ -307,14 +476,8 @@ impl Value {
                 Ok(val.map(Value::Boolean).unwrap_or(Value::Null))
             }
             "timestamp" | "timestamptz" => {
-                // Handle PostgreSQL timestamps using SystemTime, convert to DateTime
-                let val: Option<std::time::SystemTime> = row.try_get(idx)?;
-                Ok(val
-                    .map(|st| {
-                        let datetime = chrono::DateTime::<chrono::Utc>::from(st);
-                        Value::DateTime(OrsoDateTime::new(datetime))
-                    })
-                    .unwrap_or(Value::Null))
+                let val: Option<OrsoDateTime> = row.try_get(idx)?;
+                Ok(val.map(Value::DateTime).unwrap_or(Value::Null))
             }
             "_int8" | "int8[]" => {
                 // PostgreSQL BIGINT array
@@ -336,6 +499,10 @@ impl Value {
                 let val: Option<Vec<f32>> = row.try_get(idx)?;
                 Ok(val.map(Value::Vector).unwrap_or(Value::Null))
             }
+            "jsonb" | "json" => {
+                let val: Option<serde_json::Value> = row.try_get(idx)?;
+                Ok(val.map(Value::Json).unwrap_or(Value::Null))
+            }
             _ => {
                 // Try as string for unknown types
                 let val: Option<String> = row.try_get(idx)?;
@@ -349,13 +516,34 @@ impl Value {
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
 pub struct OrsoDateTime(pub chrono::DateTime<Utc>);
 
+/// Resolution to truncate an [`OrsoDateTime`] to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Precision {
+    Seconds,
+    Millis,
+    /// PostgreSQL's own resolution
+    Micros,
+}
+
 impl OrsoDateTime {
     pub fn new(dt: chrono::DateTime<Utc>) -> Self {
         Self(dt)
     }
 
+    /// The current time in microseconds, so it compares equal once stored and read back
     pub fn now() -> Self {
-        Self(Utc::now())
+        Self(Utc::now()).truncate(Precision::Micros)
+    }
+
+    /// Drop the digits below `precision`
+    pub fn truncate(self, precision: Precision) -> Self {
+        use chrono::DurationRound;
+        let step = match precision {
+            Precision::Seconds => chrono::TimeDelta::seconds(1),
+            Precision::Millis => chrono::TimeDelta::milliseconds(1),
+            Precision::Micros => chrono::TimeDelta::microseconds(1),
+        };
+        Self(self.0.duration_trunc(step).unwrap_or(self.0))
     }
 
     pub fn inner(&self) -> &chrono::DateTime<Utc> {
@@ -438,9 +626,12 @@ impl tokio_postgres::types::ToSql for OrsoDateTime {
         _ty: &tokio_postgres::types::Type,
         out: &mut tokio_postgres::types::private::BytesMut,
     ) -> Result<tokio_postgres::types::IsNull, Box<dyn std::error::Error + Sync + Send>> {
-        // Convert DateTime<Utc> to SystemTime for PostgreSQL
-        let system_time = std::time::SystemTime::from(self.0);
-        system_time.to_sql(_ty, out)
+        // Bound natively; naive TIMESTAMP columns hold UTC wall-clock times
+        if *_ty == tokio_postgres::types::Type::TIMESTAMP {
+            self.0.naive_utc().to_sql(_ty, out)
+        } else {
+            self.0.to_sql(_ty, out)
+        }
     }
 
     fn accepts(ty: &tokio_postgres::types::Type) -> bool {
@@ -453,14 +644,37 @@ impl tokio_postgres::types::ToSql for OrsoDateTime {
     tokio_postgres::types::to_sql_checked!();
 }
 
+/// NULL parameter that binds to a column of any type, JSONB included
+#[derive(Debug)]
+struct SqlNull;
+
+impl tokio_postgres::types::ToSql for SqlNull {
+    fn to_sql(
+        &self,
+        _ty: &tokio_postgres::types::Type,
+        _out: &mut tokio_postgres::types::private::BytesMut,
+    ) -> Result<tokio_postgres::types::IsNull, Box<dyn std::error::Error + Sync + Send>> {
+        Ok(tokio_postgres::types::IsNull::Yes)
+    }
+
+    fn accepts(_ty: &tokio_postgres::types::Type) -> bool {
+        true
+    }
+
+    tokio_postgres::types::to_sql_checked!();
+}
+
 impl<'a> tokio_postgres::types::FromSql<'a> for OrsoDateTime {
     fn from_sql(
         ty: &tokio_postgres::types::Type,
         raw: &'a [u8],
     ) -> Result<Self, Box<dyn std::error::Error + Sync + Send>> {
-        let system_time = std::time::SystemTime::from_sql(ty, raw)?;
-        let datetime = chrono::DateTime::<chrono::Utc>::from(system_time);
-        Ok(OrsoDateTime(datetime))
+        if *ty == tokio_postgres::types::Type::TIMESTAMP {
+            let naive = chrono::NaiveDateTime::from_sql(ty, raw)?;
+            Ok(OrsoDateTime(naive.and_utc()))
+        } else {
+            Ok(OrsoDateTime(chrono::DateTime::<Utc>::from_sql(ty, raw)?))
+        }
     }
 
     fn accepts(ty: &tokio_postgres::types::Type) -> bool {
@@ -500,6 +714,152 @@ where
     }
 }
 
+/// Timestamp types the [`timestamp_format`] serde modules accept
+pub trait TimestampField: Sized {
+    fn to_timestamp(&self) -> Option<OrsoDateTime>;
+    fn from_timestamp(timestamp: Option<OrsoDateTime>) -> Option<Self>;
+}
+
+impl TimestampField for OrsoDateTime {
+    fn to_timestamp(&self) -> Option<OrsoDateTime> {
+        Some(*self)
+    }
+
+    fn from_timestamp(timestamp: Option<OrsoDateTime>) -> Option<Self> {
+        timestamp
+    }
+}
+
+impl TimestampField for chrono::DateTime<Utc> {
+    fn to_timestamp(&self) -> Option<OrsoDateTime> {
+        Some(OrsoDateTime(*self))
+    }
+
+    fn from_timestamp(timestamp: Option<OrsoDateTime>) -> Option<Self> {
+        timestamp.map(OrsoDateTime::into_inner)
+    }
+}
+
+/// Unix milliseconds, for `#[orso_column(created_at, as = "unix_ms")]` columns
+impl TimestampField for i64 {
+    fn to_timestamp(&self) -> Option<OrsoDateTime> {
+        DateTime::from_timestamp_millis(*self).map(OrsoDateTime)
+    }
+
+    fn from_timestamp(timestamp: Option<OrsoDateTime>) -> Option<Self> {
+        timestamp.map(|t| t.into_inner().timestamp_millis())
+    }
+}
+
+impl<T: TimestampField> TimestampField for Option<T> {
+    fn to_timestamp(&self) -> Option<OrsoDateTime> {
+        self.as_ref().and_then(T::to_timestamp)
+    }
+
+    fn from_timestamp(timestamp: Option<OrsoDateTime>) -> Option<Self> {
+        Some(timestamp.and_then(|t| T::from_timestamp(Some(t))))
+    }
+}
+
+/// The current time in Unix milliseconds, the default of `as = "unix_ms"` timestamp columns
+pub const UNIX_MS_NOW: &str = "(extract(epoch FROM NOW()) * 1000)::bigint";
+
+/// SQL for the current time as stored in the timestamp `column` of `T`
+pub(crate) fn now_sql<T: crate::Orso>(column: &str) -> &'static str {
+    if T::unix_ms_fields().contains(&column) {
+        UNIX_MS_NOW
+    } else {
+        "NOW()"
+    }
+}
+
+/// Serde formats for timestamp fields
+///
+/// ```rust,ignore
+/// #[serde(with = "orso_postgres::timestamp_format::unix_millis")]
+/// expires_at: Option<OrsoDateTime>,
+/// ```
+///
+/// Both read either form, so rows and payloads written in the other format still load; a
+/// model's numeric timestamps are stored as Unix milliseconds.
+pub mod timestamp_format {
+    use super::TimestampField;
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    fn deserialize_any<'de, D, T>(deserializer: D) -> Result<T, D::Error>
+    where
+        D: Deserializer<'de>,
+        T: TimestampField,
+    {
+        let timestamp = match serde_json::Value::deserialize(deserializer)? {
+            serde_json::Value::Null => None,
+            serde_json::Value::Number(n) => {
+                let millis = n
+                    .as_i64()
+                    .ok_or_else(|| D::Error::custom("Invalid Unix timestamp"))?;
+                let dt = chrono::DateTime::from_timestamp_millis(millis)
+                    .ok_or_else(|| D::Error::custom("Unix timestamp out of range"))?;
+                Some(super::OrsoDateTime(dt))
+            }
+            serde_json::Value::String(s) => Some(
+                crate::Utils::parse_timestamp(&s)
+                    .map_err(|e| D::Error::custom(format!("Invalid timestamp format: {}", e)))?,
+            ),
+            other => {
+                return Err(D::Error::custom(format!(
+                    "Expected a timestamp, got {other}"
+                )))
+            }
+        };
+        T::from_timestamp(timestamp).ok_or_else(|| D::Error::custom("Missing timestamp"))
+    }
+
+    /// RFC 3339 in UTC with a `Z` suffix and only as many fractional digits as needed
+    pub mod rfc3339 {
+        use super::*;
+
+        pub fn serialize<S: Serializer, T: TimestampField>(
+            value: &T,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match value.to_timestamp() {
+                Some(t) => serializer.serialize_str(
+                    &t.inner()
+                        .to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true),
+                ),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>, T: TimestampField>(
+            deserializer: D,
+        ) -> Result<T, D::Error> {
+            deserialize_any(deserializer)
+        }
+    }
+
+    /// Milliseconds since the Unix epoch
+    pub mod unix_millis {
+        use super::*;
+
+        pub fn serialize<S: Serializer, T: TimestampField>(
+            value: &T,
+            serializer: S,
+        ) -> Result<S::Ok, S::Error> {
+            match value.to_timestamp() {
+                Some(t) => serializer.serialize_i64(t.inner().timestamp_millis()),
+                None => serializer.serialize_none(),
+            }
+        }
+
+        pub fn deserialize<'de, D: Deserializer<'de>, T: TimestampField>(
+            deserializer: D,
+        ) -> Result<T, D::Error> {
+            deserialize_any(deserializer)
+        }
+    }
+}
+
 // Additional trait implementations for OrsoDateTime compatibility
 impl From<OrsoDateTime> for std::time::SystemTime {
     fn from(dt: OrsoDateTime) -> Self {
@@ -53,6 +53,13 @@ impl Utils {
             return Ok(OrsoDateTime::new(dt.with_timezone(&Utc)));
         }
 
+        // Timestamps without an offset, e.g. TIMESTAMP columns rendered by `row_to_json`, are UTC
+        for format in ["%Y-%m-%dT%H:%M:%S%.f", "%Y-%m-%d %H:%M:%S%.f"] {
+            if let Ok(naive) = chrono::NaiveDateTime::parse_from_str(timestamp, format) {
+                return Ok(OrsoDateTime::new(naive.and_utc()));
+            }
+        }
+
         // If all formats fail, return error for the original RFC3339 attempt
         DateTime::parse_from_rfc3339(timestamp)
             .map(|dt| OrsoDateTime::new(dt.with_timezone(&Utc)))
@@ -98,11 +105,12 @@ impl Utils {
             crate::Value::Text(s) => Box::new(s.clone()),
             crate::Value::Blob(b) => Box::new(b.clone()),
             crate::Value::Boolean(b) => Box::new(*b),
-            crate::Value::DateTime(dt) => Box::new(std::time::SystemTime::from(*dt.inner())),
+            crate::Value::DateTime(dt) => Box::new(*dt),
             crate::Value::IntegerArray(arr) => Box::new(arr.clone()),
             crate::Value::BigIntArray(arr) => Box::new(arr.clone()),
             crate::Value::NumericArray(arr) => Box::new(arr.clone()),
             crate::Value::Vector(v) => Box::new(v.clone()),
+            crate::Value::Json(v) => Box::new(v.clone()),
         }
     }
 
@@ -0,0 +1,194 @@
+//! Field validation
+//!
+//! `#[orso_column(validate(...))]` generates a `validate()` method that calls the checks
+//! below for every annotated field. Failures are collected rather than returned one by one,
+//! so a single [`Error::Validation`](crate::Error::Validation) lists every failing field.
+//!
+//! Supported rules:
+//!
+//! - `length(min = 1, max = 255)` - character count of strings, element count of vectors
+//! - `range(min = 0, max = 100)` - numeric bounds, inclusive
+//! - `email` - a plausible email address
+//! - `url` - an `http://` or `https://` URL
+//!
+//! `None` values of `Option` fields are skipped by every rule.
+
+use crate::{Error, FieldError, Result};
+
+/// Values with a length that `length(...)` can check
+pub trait ValidateLength {
+    fn validation_length(&self) -> Option<usize>;
+}
+
+impl ValidateLength for String {
+    fn validation_length(&self) -> Option<usize> {
+        Some(self.chars().count())
+    }
+}
+
+impl ValidateLength for str {
+    fn validation_length(&self) -> Option<usize> {
+        Some(self.chars().count())
+    }
+}
+
+impl<T> ValidateLength for Vec<T> {
+    fn validation_length(&self) -> Option<usize> {
+        Some(self.len())
+    }
+}
+
+impl<T: ValidateLength> ValidateLength for Option<T> {
+    fn validation_length(&self) -> Option<usize> {
+        self.as_ref().and_then(|v| v.validation_length())
+    }
+}
+
+/// Numeric values that `range(...)` can check
+pub trait ValidateNumber {
+    fn validation_number(&self) -> Option<f64>;
+}
+
+macro_rules! impl_validate_number {
+    ($($ty:ty),*) => {
+        $(impl ValidateNumber for $ty {
+            fn validation_number(&self) -> Option<f64> {
+                Some(*self as f64)
+            }
+        })*
+    };
+}
+
+impl_validate_number!(i8, i16, i32, i64, u8, u16, u32, u64, f32, f64);
+
+impl<T: ValidateNumber> ValidateNumber for Option<T> {
+    fn validation_number(&self) -> Option<f64> {
+        self.as_ref().and_then(|v| v.validation_number())
+    }
+}
+
+/// Text values that format rules such as `email` can check
+pub trait ValidateText {
+    fn validation_text(&self) -> Option<&str>;
+}
+
+impl ValidateText for String {
+    fn validation_text(&self) -> Option<&str> {
+        Some(self)
+    }
+}
+
+impl ValidateText for str {
+    fn validation_text(&self) -> Option<&str> {
+        Some(self)
+    }
+}
+
+impl<T: ValidateText> ValidateText for Option<T> {
+    fn validation_text(&self) -> Option<&str> {
+        self.as_ref().and_then(|v| v.validation_text())
+    }
+}
+
+/// Check the length of a string or vector
+pub fn length<V: ValidateLength + ?Sized>(
+    field: &str,
+    value: &V,
+    min: Option<usize>,
+    max: Option<usize>,
+    errors: &mut Vec<FieldError>,
+) {
+    let Some(len) = value.validation_length() else {
+        return;
+    };
+
+    if let Some(min) = min.filter(|&min| len < min) {
+        errors.push(FieldError::new(
+            field,
+            format!("length must be at least {}", min),
+        ));
+    }
+    if let Some(max) = max.filter(|&max| len > max) {
+        errors.push(FieldError::new(
+            field,
+            format!("length must be at most {}", max),
+        ));
+    }
+}
+
+/// Check that a number lies within inclusive bounds
+pub fn range<V: ValidateNumber + ?Sized>(
+    field: &str,
+    value: &V,
+    min: Option<f64>,
+    max: Option<f64>,
+    errors: &mut Vec<FieldError>,
+) {
+    let Some(number) = value.validation_number() else {
+        return;
+    };
+
+    if let Some(min) = min.filter(|&min| number < min) {
+        errors.push(FieldError::new(field, format!("must be at least {}", min)));
+    }
+    if let Some(max) = max.filter(|&max| number > max) {
+        errors.push(FieldError::new(field, format!("must be at most {}", max)));
+    }
+}
+
+/// Check that a value looks like an email address
+pub fn email<V: ValidateText + ?Sized>(field: &str, value: &V, errors: &mut Vec<FieldError>) {
+    let Some(text) = value.validation_text() else {
+        return;
+    };
+
+    if !is_email(text) {
+        errors.push(FieldError::new(field, "must be a valid email address"));
+    }
+}
+
+/// Check that a value is an http(s) URL
+pub fn url<V: ValidateText + ?Sized>(field: &str, value: &V, errors: &mut Vec<FieldError>) {
+    let Some(text) = value.validation_text() else {
+        return;
+    };
+
+    if !is_url(text) {
+        errors.push(FieldError::new(field, "must be a valid URL"));
+    }
+}
+
+/// Turn collected failures into a result
+pub fn finish(errors: Vec<FieldError>) -> Result<()> {
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::validation_errors(errors))
+    }
+}
+
+fn is_email(text: &str) -> bool {
+    let Some((local, domain)) = text.split_once('@') else {
+        return false;
+    };
+
+    !local.is_empty()
+        && !domain.contains('@')
+        && !text.chars().any(char::is_whitespace)
+        && domain.contains('.')
+        && domain.split('.').all(|part| !part.is_empty())
+}
+
+fn is_url(text: &str) -> bool {
+    let rest = text
+        .strip_prefix("https://")
+        .or_else(|| text.strip_prefix("http://"));
+
+    match rest {
+        Some(rest) => {
+            let host = rest.split(['/', '?', '#']).next().unwrap_or("");
+            !host.is_empty() && !text.chars().any(char::is_whitespace)
+        }
+        None => false,
+    }
+}
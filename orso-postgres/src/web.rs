@@ -0,0 +1,267 @@
+//! Query string extractors for web handlers
+//!
+//! Enabled with the `web` feature. [`Pagination`], [`SortParams`] and [`FilterParams`] implement
+//! axum's `FromRequestParts`, so list endpoints can take them straight from the request:
+//!
+//! ```rust,ignore
+//! // GET /users?page=2&per_page=50&sort=-created_at&filter[age][gt]=25
+//! async fn list_users(
+//!     State(db): State<Database>,
+//!     pagination: Pagination,
+//!     sort: SortParams<User>,
+//!     filters: FilterParams<User>,
+//! ) -> Result<Json<PaginatedResult<User>>, AppError> {
+//!     let sort = sort.into_sorts();
+//!     Ok(Json(User::list_where(filters.into_filter(), sort, Some(&pagination), &db).await?))
+//! }
+//! ```
+//!
+//! Other frameworks can call the `from_query` parsers with the raw query string. Sort and filter
+//! columns are checked before they reach SQL: both must name a column of the model that isn't
+//! compressed, encrypted or masked. Malformed parameters are rejected with `400 Bad Request`.
+
+use crate::{
+    Error, FieldType, Filter, FilterOperator, Operator, Orso, Pagination, Result, Sort, SortOrder,
+    Utils, Value,
+};
+use axum::extract::FromRequestParts;
+use axum::http::{request::Parts, StatusCode};
+use std::marker::PhantomData;
+
+/// Largest `per_page` a client may request
+pub const MAX_PER_PAGE: u32 = 100;
+
+fn query_pairs(query: &str) -> Result<Vec<(String, String)>> {
+    serde_urlencoded::from_str(query)
+        .map_err(|e| Error::validation(format!("Invalid query string: {e}")))
+}
+
+fn parse_number(name: &str, value: &str) -> Result<u32> {
+    value.parse().map_err(|_| {
+        Error::validation_field("Expected a positive number", name, Some(value.into()))
+    })
+}
+
+fn rejection(error: Error) -> (StatusCode, String) {
+    (StatusCode::BAD_REQUEST, error.to_string())
+}
+
+/// Whether `column` is a column of `T` holding the field's value as is
+fn is_plain_column<T: Orso>(column: &str) -> bool {
+    T::columns().contains(&column) && !crate::query::opaque_columns::<T>().contains(&column)
+}
+
+impl Pagination {
+    /// Read `page` and `per_page`, defaulting to the first page of 20
+    pub fn from_query(query: &str) -> Result<Self> {
+        let mut pagination = Self::default();
+        for (key, value) in query_pairs(query)? {
+            match key.as_str() {
+                "page" => pagination.page = parse_number("page", &value)?,
+                "per_page" => pagination.per_page = parse_number("per_page", &value)?,
+                _ => {}
+            }
+        }
+
+        if pagination.page == 0 {
+            return Err(Error::validation_field(
+                "Pages start at 1",
+                "page",
+                Some("0".into()),
+            ));
+        }
+        if pagination.per_page == 0 || pagination.per_page > MAX_PER_PAGE {
+            return Err(Error::validation_field(
+                format!("Must be between 1 and {MAX_PER_PAGE}"),
+                "per_page",
+                Some(pagination.per_page.to_string()),
+            ));
+        }
+        Ok(pagination)
+    }
+}
+
+impl Sort {
+    /// Read `sort=column` or `sort=-column` for descending order, on a plain column of `T`
+    pub fn from_query<T: Orso>(query: &str) -> Result<Self> {
+        let value = query_pairs(query)?
+            .into_iter()
+            .find(|(key, _)| key == "sort")
+            .map(|(_, value)| value)
+            .ok_or_else(|| Error::validation("Missing sort parameter"))?;
+
+        let (column, order) = match value.strip_prefix('-') {
+            Some(column) => (column, SortOrder::Desc),
+            None => (value.strip_prefix('+').unwrap_or(&value), SortOrder::Asc),
+        };
+        let plain = !column.is_empty()
+            && column
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '_')
+            && !column.starts_with(|c: char| c.is_ascii_digit())
+            && is_plain_column::<T>(column);
+        if !plain {
+            return Err(Error::validation_field(
+                "Invalid sort column",
+                "sort",
+                Some(value),
+            ));
+        }
+        Ok(Sort::new(column, order))
+    }
+}
+
+/// The sort of a list of `T` read from an optional `sort` parameter, see [`Sort::from_query`]
+#[derive(Debug, Clone)]
+pub struct SortParams<T> {
+    pub sort: Option<Sort>,
+    model: PhantomData<fn() -> T>,
+}
+
+impl<T: Orso> SortParams<T> {
+    pub fn from_query(query: &str) -> Result<Self> {
+        let requested = query_pairs(query)?.iter().any(|(key, _)| key == "sort");
+        Ok(Self {
+            sort: requested
+                .then(|| Sort::from_query::<T>(query))
+                .transpose()?,
+            model: PhantomData,
+        })
+    }
+
+    /// The sort as `list_where` and friends take it
+    pub fn into_sorts(self) -> Option<Vec<Sort>> {
+        self.sort.map(|sort| vec![sort])
+    }
+}
+
+/// Filters on the columns of `T` read from `filter[column][op]=value` parameters
+///
+/// Operators are `eq` (also `filter[column]=value`), `ne`, `lt`, `lte`, `gt`, `gte`, `like`,
+/// `in` with comma separated values, and `null` with `true` or `false`. Values are converted to
+/// the column's type.
+#[derive(Debug, Clone)]
+pub struct FilterParams<T> {
+    pub filters: Vec<Filter>,
+    model: PhantomData<fn() -> T>,
+}
+
+impl<T: Orso> FilterParams<T> {
+    pub fn from_query(query: &str) -> Result<Self> {
+        let names = T::field_names();
+        let types = T::field_types();
+
+        let mut filters = Vec::new();
+        for (key, value) in query_pairs(query)? {
+            let Some(rest) = key.strip_prefix("filter[") else {
+                continue;
+            };
+            let (column, op) = match rest.split_once("][") {
+                Some((column, op)) => (column, op.strip_suffix(']')),
+                None => (rest.strip_suffix(']').unwrap_or(rest), Some("eq")),
+            };
+            let invalid =
+                || Error::validation_field("Invalid filter", key.clone(), Some(value.clone()));
+            let op = op.ok_or_else(invalid)?;
+
+            let position = names
+                .iter()
+                .position(|name| *name == column)
+                .ok_or_else(|| Error::validation_field("Unknown filter column", column, None))?;
+            if !is_plain_column::<T>(column) {
+                return Err(Error::validation_field(
+                    "Compressed, encrypted and masked columns can't be filtered by",
+                    column,
+                    None,
+                ));
+            }
+            let field_type = &types[position];
+            let parse = |raw: &str| parse_value(field_type, raw).ok_or_else(invalid);
+
+            let filter = match op {
+                "eq" => Filter::new_simple(column, Operator::Eq, parse(&value)?),
+                "ne" => Filter::new_simple(column, Operator::Ne, parse(&value)?),
+                "lt" => Filter::new_simple(column, Operator::Lt, parse(&value)?),
+                "lte" => Filter::new_simple(column, Operator::Le, parse(&value)?),
+                "gt" => Filter::new_simple(column, Operator::Gt, parse(&value)?),
+                "gte" => Filter::new_simple(column, Operator::Ge, parse(&value)?),
+                "like" => Filter::like(column, value.clone()),
+                "in" => Filter::in_values(
+                    column,
+                    value.split(',').map(parse).collect::<Result<Vec<_>>>()?,
+                ),
+                "null" => match value.as_str() {
+                    "true" => Filter::is_null(column),
+                    "false" => Filter::is_not_null(column),
+                    _ => return Err(invalid()),
+                },
+                _ => return Err(invalid()),
+            };
+            filters.push(filter);
+        }
+
+        Ok(Self {
+            filters,
+            model: PhantomData,
+        })
+    }
+
+    /// All filters combined with AND, matching every row when there are none
+    pub fn into_filter(self) -> FilterOperator {
+        if self.filters.is_empty() {
+            return FilterOperator::Custom("TRUE".to_string());
+        }
+        FilterOperator::and(
+            self.filters
+                .into_iter()
+                .map(FilterOperator::Single)
+                .collect(),
+        )
+    }
+}
+
+fn parse_value(field_type: &FieldType, raw: &str) -> Option<Value> {
+    match field_type {
+        FieldType::Integer | FieldType::BigInt => raw.parse().ok().map(Value::Integer),
+        FieldType::Numeric => raw.parse().ok().map(Value::Real),
+        FieldType::Boolean => raw.parse().ok().map(Value::Boolean),
+        FieldType::Timestamp => Utils::parse_timestamp(raw).ok().map(Value::DateTime),
+        _ => Some(Value::Text(raw.to_string())),
+    }
+}
+
+#[axum::async_trait]
+impl<S: Send + Sync> FromRequestParts<S> for Pagination {
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> std::result::Result<Self, Self::Rejection> {
+        Self::from_query(parts.uri.query().unwrap_or_default()).map_err(rejection)
+    }
+}
+
+#[axum::async_trait]
+impl<S: Send + Sync, T: Orso> FromRequestParts<S> for SortParams<T> {
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> std::result::Result<Self, Self::Rejection> {
+        Self::from_query(parts.uri.query().unwrap_or_default()).map_err(rejection)
+    }
+}
+
+#[axum::async_trait]
+impl<S: Send + Sync, T: Orso> FromRequestParts<S> for FilterParams<T> {
+    type Rejection = (StatusCode, String);
+
+    async fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> std::result::Result<Self, Self::Rejection> {
+        Self::from_query(parts.uri.query().unwrap_or_default()).map_err(rejection)
+    }
+}